@@ -35,7 +35,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .with_recursive(true)
         .with_auto_reload(true);
 
-    let mut watcher = PluginWatcher::new(watch_config)?;
+    let watcher = PluginWatcher::new(watch_config)?;
 
     // Set up change handler
     watcher.on_change(|event| {
@@ -0,0 +1,776 @@
+//! Authenticated management operations for admin surfaces.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use fusabi_host::Value;
+
+use crate::api::RuntimeApi;
+use crate::audit::{AuditOutcome, AuditRecord, AuditSink, TracingAuditSink};
+use crate::bulk::{BulkOp, BulkPolicy, BulkProgress, BulkReport};
+use crate::cancel::CancellationToken;
+use crate::descriptor::PluginDescriptor;
+use crate::error::{Error, Result};
+use crate::host_info::HostInfo;
+use crate::plugin::PluginInfo;
+use crate::rate_limit::{RateLimit, RateLimiter};
+use crate::runtime::PluginRuntime;
+
+/// Audit principal and rate limit key of callers that did not authenticate.
+const ANONYMOUS: &str = "anonymous";
+
+/// Access level of an admin principal.
+///
+/// Roles are ordered: each role may perform the operations of the roles
+/// before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Role {
+    /// List plugins and read their information.
+    ReadOnly,
+    /// Start, stop, reload and call plugins.
+    Operate,
+    /// Load and unload plugins.
+    Install,
+}
+
+/// An authenticated caller of the admin API.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Principal {
+    /// Caller identity, e.g. a token name or certificate subject.
+    pub id: String,
+    /// Granted role.
+    pub role: Role,
+}
+
+impl Principal {
+    /// Create a principal.
+    pub fn new(id: impl Into<String>, role: Role) -> Self {
+        Self {
+            id: id.into(),
+            role,
+        }
+    }
+}
+
+/// Admin operation, used for authorization.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AdminOperation {
+    /// List plugins.
+    List,
+    /// Get plugin information.
+    Info,
+    /// Start a plugin.
+    Start,
+    /// Stop a plugin.
+    Stop,
+    /// Reload a plugin.
+    Reload,
+    /// Call a plugin function.
+    Call,
+    /// Load a plugin.
+    Load,
+    /// Unload a plugin.
+    Unload,
+}
+
+impl AdminOperation {
+    /// Get the minimum role allowed to perform the operation.
+    pub fn required_role(&self) -> Role {
+        match self {
+            Self::List | Self::Info => Role::ReadOnly,
+            Self::Start | Self::Stop | Self::Reload | Self::Call => Role::Operate,
+            Self::Load | Self::Unload => Role::Install,
+        }
+    }
+
+    /// Check if the operation changes runtime state.
+    ///
+    /// Mutations are rate-limited per principal by [`AdminApi::authorize`]
+    /// and audited.
+    pub fn is_mutation(&self) -> bool {
+        !matches!(self, Self::List | Self::Info)
+    }
+
+    /// Get the operation name.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::List => "list",
+            Self::Info => "info",
+            Self::Start => "start",
+            Self::Stop => "stop",
+            Self::Reload => "reload",
+            Self::Call => "call",
+            Self::Load => "load",
+            Self::Unload => "unload",
+        }
+    }
+}
+
+/// Credentials presented by an admin request.
+#[derive(Debug, Clone, Default)]
+pub struct Credentials {
+    /// Bearer token from the `Authorization` header.
+    pub bearer_token: Option<String>,
+    /// Subject of a verified TLS client certificate.
+    pub client_cert_subject: Option<String>,
+}
+
+impl Credentials {
+    /// Credentials carrying a bearer token.
+    pub fn bearer(token: impl Into<String>) -> Self {
+        Self {
+            bearer_token: Some(token.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Credentials carrying a verified client certificate subject.
+    pub fn client_cert(subject: impl Into<String>) -> Self {
+        Self {
+            client_cert_subject: Some(subject.into()),
+            ..Self::default()
+        }
+    }
+}
+
+/// Identifies the principal behind admin credentials.
+pub trait Authenticator: Send + Sync {
+    /// Authenticate credentials, failing with [`Error::Unauthenticated`].
+    fn authenticate(&self, credentials: &Credentials) -> Result<Principal>;
+}
+
+/// Authenticates fixed bearer tokens.
+#[derive(Debug, Clone, Default)]
+pub struct StaticTokenAuthenticator {
+    tokens: HashMap<String, Principal>,
+}
+
+impl StaticTokenAuthenticator {
+    /// Create an authenticator without tokens.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accept a token for a principal.
+    pub fn with_token(mut self, token: impl Into<String>, principal: Principal) -> Self {
+        self.tokens.insert(token.into(), principal);
+        self
+    }
+}
+
+impl Authenticator for StaticTokenAuthenticator {
+    fn authenticate(&self, credentials: &Credentials) -> Result<Principal> {
+        let token = credentials
+            .bearer_token
+            .as_deref()
+            .ok_or_else(|| Error::unauthenticated("missing bearer token"))?;
+
+        // Compare every token in constant time to avoid leaking prefixes
+        self.tokens
+            .iter()
+            .fold(None, |found, (candidate, principal)| {
+                if constant_time_eq(candidate.as_bytes(), token.as_bytes()) {
+                    Some(principal.clone())
+                } else {
+                    found
+                }
+            })
+            .ok_or_else(|| Error::unauthenticated("invalid bearer token"))
+    }
+}
+
+/// Authenticates TLS client certificates verified by the transport.
+#[derive(Debug, Clone, Default)]
+pub struct MtlsAuthenticator {
+    subjects: HashMap<String, Role>,
+}
+
+impl MtlsAuthenticator {
+    /// Create an authenticator without trusted subjects.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Grant a role to a certificate subject.
+    pub fn with_subject(mut self, subject: impl Into<String>, role: Role) -> Self {
+        self.subjects.insert(subject.into(), role);
+        self
+    }
+}
+
+impl Authenticator for MtlsAuthenticator {
+    fn authenticate(&self, credentials: &Credentials) -> Result<Principal> {
+        let subject = credentials
+            .client_cert_subject
+            .as_deref()
+            .ok_or_else(|| Error::unauthenticated("missing client certificate"))?;
+
+        self.subjects
+            .get(subject)
+            .map(|role| Principal::new(subject, *role))
+            .ok_or_else(|| Error::unauthenticated(format!("untrusted certificate: {}", subject)))
+    }
+}
+
+type TokenValidator = Box<dyn Fn(&str) -> Result<Principal> + Send + Sync>;
+
+/// Authenticates bearer JWTs through a host-provided validation callback.
+///
+/// The runtime does not verify signatures itself; the callback decodes and
+/// validates the token with the host's JWT library and maps its claims to a
+/// principal.
+pub struct JwtAuthenticator {
+    validate: TokenValidator,
+}
+
+impl JwtAuthenticator {
+    /// Create an authenticator from a validation callback.
+    pub fn new<F>(validate: F) -> Self
+    where
+        F: Fn(&str) -> Result<Principal> + Send + Sync + 'static,
+    {
+        Self {
+            validate: Box::new(validate),
+        }
+    }
+}
+
+impl Authenticator for JwtAuthenticator {
+    fn authenticate(&self, credentials: &Credentials) -> Result<Principal> {
+        let token = credentials
+            .bearer_token
+            .as_deref()
+            .ok_or_else(|| Error::unauthenticated("missing bearer token"))?;
+        (self.validate)(token)
+    }
+}
+
+impl std::fmt::Debug for JwtAuthenticator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JwtAuthenticator").finish_non_exhaustive()
+    }
+}
+
+/// Tries several authenticators in order, e.g. mTLS then tokens.
+#[derive(Default)]
+pub struct ChainAuthenticator {
+    authenticators: Vec<Arc<dyn Authenticator>>,
+}
+
+impl ChainAuthenticator {
+    /// Create an empty chain.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append an authenticator.
+    pub fn with(mut self, authenticator: Arc<dyn Authenticator>) -> Self {
+        self.authenticators.push(authenticator);
+        self
+    }
+}
+
+impl Authenticator for ChainAuthenticator {
+    fn authenticate(&self, credentials: &Credentials) -> Result<Principal> {
+        let mut last_error = Error::unauthenticated("no authenticator configured");
+        for authenticator in &self.authenticators {
+            match authenticator.authenticate(credentials) {
+                Ok(principal) => return Ok(principal),
+                Err(e) => last_error = e,
+            }
+        }
+        Err(last_error)
+    }
+}
+
+/// Management operations on a runtime, authenticated and scoped by role.
+///
+/// Admin transports (HTTP, gRPC, CLI) extract [`Credentials`] from requests
+/// and delegate here, so every surface enforces the same rules.
+///
+/// Mutating operations are rate-limited per principal and every attempt,
+/// allowed or not, is written to the audit sink. Failed authentications
+/// share one bucket of the same limit.
+pub struct AdminApi {
+    runtime: Arc<PluginRuntime>,
+    authenticator: Arc<dyn Authenticator>,
+    rate_limiter: Option<RateLimiter>,
+    failed_auth: Option<RateLimiter>,
+    audit: Arc<dyn AuditSink>,
+}
+
+impl AdminApi {
+    /// Create an admin API for a runtime.
+    ///
+    /// Audit records go to `tracing` and mutations are not rate-limited
+    /// until configured.
+    pub fn new(runtime: Arc<PluginRuntime>, authenticator: Arc<dyn Authenticator>) -> Self {
+        Self {
+            runtime,
+            authenticator,
+            rate_limiter: None,
+            failed_auth: None,
+            audit: Arc::new(TracingAuditSink),
+        }
+    }
+
+    /// Limit the rate of mutating operations per principal.
+    ///
+    /// Failed authentications are limited to the same rate across all
+    /// callers: once they use up the limit, every request fails with
+    /// [`Error::RateLimited`] until the bucket refills, so credentials cannot
+    /// be guessed faster than the limit allows.
+    pub fn with_rate_limit(mut self, limit: RateLimit) -> Self {
+        self.rate_limiter = Some(RateLimiter::new(limit));
+        self.failed_auth = Some(RateLimiter::new(limit));
+        self
+    }
+
+    /// Write audit records to a sink.
+    pub fn with_audit_sink(mut self, sink: Arc<dyn AuditSink>) -> Self {
+        self.audit = sink;
+        self
+    }
+
+    /// Get the runtime.
+    pub fn runtime(&self) -> &Arc<PluginRuntime> {
+        &self.runtime
+    }
+
+    /// Authenticate credentials and check they allow an operation.
+    ///
+    /// Mutating operations take a token from the principal's rate limit,
+    /// failing with [`Error::RateLimited`] when it is used up.
+    pub fn authorize(
+        &self,
+        credentials: &Credentials,
+        operation: AdminOperation,
+    ) -> Result<Principal> {
+        let principal = self.authenticate(credentials)?;
+        if principal.role < operation.required_role() {
+            tracing::warn!(
+                "Admin principal {} denied {} operation",
+                principal.id,
+                operation.name()
+            );
+            return Err(Error::permission_denied(&principal.id, operation.name()));
+        }
+        if let (true, Some(limiter)) = (operation.is_mutation(), &self.rate_limiter) {
+            if let Err(retry_after) = limiter.try_acquire(&principal.id) {
+                tracing::warn!(
+                    "Admin principal {} rate limited on {} operation",
+                    principal.id,
+                    operation.name()
+                );
+                return Err(Error::rate_limited(&principal.id, retry_after));
+            }
+        }
+        Ok(principal)
+    }
+
+    /// Authenticate credentials, charging failures to the shared bucket.
+    fn authenticate(&self, credentials: &Credentials) -> Result<Principal> {
+        let Some(ref limiter) = self.failed_auth else {
+            return self.authenticator.authenticate(credentials);
+        };
+        if let Err(retry_after) = limiter.check(ANONYMOUS) {
+            tracing::warn!("Admin authentication rate limited after repeated failures");
+            return Err(Error::rate_limited(ANONYMOUS, retry_after));
+        }
+        self.authenticator.authenticate(credentials).map_err(|e| {
+            let _ = limiter.try_acquire(ANONYMOUS);
+            e
+        })
+    }
+
+    /// List loaded plugins.
+    pub fn list(&self, credentials: &Credentials) -> Result<Vec<PluginInfo>> {
+        self.authorize(credentials, AdminOperation::List)?;
+        Ok(self.runtime.plugins().iter().map(|p| p.info()).collect())
+    }
+
+    /// Get information about a plugin.
+    pub fn info(&self, credentials: &Credentials, name: &str) -> Result<PluginInfo> {
+        self.authorize(credentials, AdminOperation::Info)?;
+        self.runtime
+            .get(name)
+            .map(|p| p.info())
+            .ok_or_else(|| Error::plugin_not_found(name))
+    }
+
+    /// Get the host API version and build information.
+    pub fn host_info(&self, credentials: &Credentials) -> Result<HostInfo> {
+        self.authorize(credentials, AdminOperation::Info)?;
+        Ok(self.runtime.host_info())
+    }
+
+    /// Describe a plugin's exports, hooks, routes, configuration schema,
+    /// state and usage.
+    pub fn describe(&self, credentials: &Credentials, name: &str) -> Result<PluginDescriptor> {
+        self.authorize(credentials, AdminOperation::Info)?;
+        self.runtime
+            .get(name)
+            .map(|p| p.describe())
+            .ok_or_else(|| Error::plugin_not_found(name))
+    }
+
+    /// Start a plugin.
+    pub fn start(&self, credentials: &Credentials, name: &str) -> Result<()> {
+        self.mutate(credentials, AdminOperation::Start, name, |_| {
+            self.runtime.start(name)
+        })
+    }
+
+    /// Stop a plugin.
+    pub fn stop(&self, credentials: &Credentials, name: &str) -> Result<()> {
+        self.mutate(credentials, AdminOperation::Stop, name, |_| {
+            self.runtime.stop(name)
+        })
+    }
+
+    /// Reload a plugin.
+    pub fn reload(&self, credentials: &Credentials, name: &str) -> Result<()> {
+        self.mutate(credentials, AdminOperation::Reload, name, |_| {
+            self.runtime.reload(name)
+        })
+    }
+
+    /// Call a plugin function as the authenticated principal.
+    pub fn call(
+        &self,
+        credentials: &Credentials,
+        plugin: &str,
+        function: &str,
+        args: &[Value],
+    ) -> Result<Value> {
+        let target = format!("{}.{}", plugin, function);
+        self.mutate(credentials, AdminOperation::Call, &target, |principal| {
+            self.runtime.call_as(&principal.id, plugin, function, args)
+        })
+    }
+
+    /// Load a plugin from a manifest, source or bytecode file.
+    pub fn load(&self, credentials: &Credentials, path: &Path) -> Result<PluginInfo> {
+        let target = path.display().to_string();
+        self.mutate(credentials, AdminOperation::Load, &target, |_| {
+            RuntimeApi::load(self.runtime.as_ref(), path).map(|p| p.info())
+        })
+    }
+
+    /// Unload a plugin.
+    pub fn unload(&self, credentials: &Credentials, name: &str) -> Result<()> {
+        self.mutate(credentials, AdminOperation::Unload, name, |_| {
+            self.runtime.unload(name)
+        })
+    }
+
+    /// Apply many operations as the authenticated principal, reporting
+    /// progress after each one.
+    ///
+    /// Like [`PluginRuntime::bulk_with`], but every operation is authorized
+    /// against the principal's role, rate-limited and audited as if it had
+    /// been requested on its own. Fails without running anything if the
+    /// credentials do not authenticate.
+    pub fn bulk_with<F>(
+        &self,
+        credentials: &Credentials,
+        ops: impl IntoIterator<Item = BulkOp>,
+        policy: BulkPolicy,
+        on_progress: F,
+    ) -> Result<BulkReport>
+    where
+        F: FnMut(&BulkProgress<'_>),
+    {
+        self.bulk_cancellable(
+            credentials,
+            ops,
+            policy,
+            &CancellationToken::new(),
+            on_progress,
+        )
+    }
+
+    /// Apply many operations as the authenticated principal until `token`
+    /// is cancelled.
+    pub fn bulk_cancellable<F>(
+        &self,
+        credentials: &Credentials,
+        ops: impl IntoIterator<Item = BulkOp>,
+        policy: BulkPolicy,
+        token: &CancellationToken,
+        on_progress: F,
+    ) -> Result<BulkReport>
+    where
+        F: FnMut(&BulkProgress<'_>),
+    {
+        self.authenticate(credentials)?;
+        Ok(crate::bulk::run(
+            ops,
+            policy,
+            token,
+            on_progress,
+            |op| match op {
+                BulkOp::Load(path) => self.load(credentials, path).map(|_| ()),
+                BulkOp::Unload(name) => self.unload(credentials, name),
+                BulkOp::Start(name) => self.start(credentials, name),
+                BulkOp::Stop(name) => self.stop(credentials, name),
+                BulkOp::Reload(name) => self.reload(credentials, name),
+            },
+        ))
+    }
+
+    /// Authorize, rate-limit, run and audit a mutating operation.
+    fn mutate<T>(
+        &self,
+        credentials: &Credentials,
+        operation: AdminOperation,
+        target: &str,
+        f: impl FnOnce(&Principal) -> Result<T>,
+    ) -> Result<T> {
+        let audit = |principal: &str, outcome: AuditOutcome| {
+            self.audit.record(&AuditRecord::new(
+                principal,
+                operation.name(),
+                Some(target.to_string()),
+                outcome,
+            ));
+        };
+
+        let principal = match self.authorize(credentials, operation) {
+            Ok(principal) => principal,
+            Err(e) => {
+                match e.root() {
+                    Error::PermissionDenied { principal, .. } => {
+                        audit(principal, AuditOutcome::Denied(e.to_string()))
+                    }
+                    Error::RateLimited { key, .. } => audit(key, AuditOutcome::RateLimited),
+                    _ => audit(ANONYMOUS, AuditOutcome::Denied(e.to_string())),
+                }
+                return Err(e);
+            }
+        };
+
+        let result = f(&principal);
+        match &result {
+            Ok(_) => audit(&principal.id, AuditOutcome::Succeeded),
+            Err(e) => audit(&principal.id, AuditOutcome::Failed(e.to_string())),
+        }
+        result
+    }
+}
+
+impl std::fmt::Debug for AdminApi {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AdminApi")
+            .field("runtime", &self.runtime)
+            .finish_non_exhaustive()
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn admin() -> AdminApi {
+        let tokens = StaticTokenAuthenticator::new()
+            .with_token("viewer-token", Principal::new("viewer", Role::ReadOnly))
+            .with_token("ops-token", Principal::new("ops", Role::Operate));
+        let mtls = MtlsAuthenticator::new().with_subject("CN=deployer", Role::Install);
+        let auth = ChainAuthenticator::new()
+            .with(Arc::new(mtls))
+            .with(Arc::new(tokens));
+
+        AdminApi::new(
+            Arc::new(PluginRuntime::default_config().unwrap()),
+            Arc::new(auth),
+        )
+    }
+
+    #[test]
+    fn test_authentication() {
+        let admin = admin();
+
+        assert!(admin.list(&Credentials::bearer("viewer-token")).is_ok());
+        assert!(matches!(
+            admin.list(&Credentials::bearer("wrong")),
+            Err(Error::Unauthenticated(_))
+        ));
+        assert!(matches!(
+            admin.list(&Credentials::default()),
+            Err(Error::Unauthenticated(_))
+        ));
+        assert_eq!(
+            admin
+                .host_info(&Credentials::bearer("viewer-token"))
+                .unwrap(),
+            admin.runtime().host_info()
+        );
+        assert!(admin.host_info(&Credentials::default()).is_err());
+
+        let jwt = JwtAuthenticator::new(|token| match token.strip_prefix("jwt:") {
+            Some(sub) => Ok(Principal::new(sub, Role::Operate)),
+            None => Err(Error::unauthenticated("invalid signature")),
+        });
+        assert_eq!(
+            jwt.authenticate(&Credentials::bearer("jwt:alice"))
+                .unwrap()
+                .id,
+            "alice"
+        );
+        assert!(jwt.authenticate(&Credentials::bearer("forged")).is_err());
+    }
+
+    #[test]
+    fn test_role_scoping() {
+        let admin = admin();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("managed.fsx");
+        std::fs::write(&path, "let x = 1").unwrap();
+
+        let viewer = Credentials::bearer("viewer-token");
+        let ops = Credentials::bearer("ops-token");
+        let deployer = Credentials::client_cert("CN=deployer");
+
+        assert!(matches!(
+            admin.load(&ops, &path),
+            Err(Error::PermissionDenied { .. })
+        ));
+        assert_eq!(admin.load(&deployer, &path).unwrap().name, "managed");
+
+        assert!(matches!(
+            admin.reload(&viewer, "managed"),
+            Err(Error::PermissionDenied { .. })
+        ));
+        admin.reload(&ops, "managed").unwrap();
+        assert_eq!(admin.info(&viewer, "managed").unwrap().reload_count, 1);
+
+        assert!(admin.unload(&ops, "managed").is_err());
+        admin.unload(&deployer, "managed").unwrap();
+    }
+
+    #[test]
+    fn test_rate_limit_and_audit() {
+        let log = Arc::new(crate::audit::MemoryAuditLog::new(16));
+        let admin = admin()
+            .with_rate_limit(RateLimit::per_minute(2).unwrap())
+            .with_audit_sink(log.clone());
+
+        let ops = Credentials::bearer("ops-token");
+        let viewer = Credentials::bearer("viewer-token");
+
+        // Reads are neither limited nor audited
+        for _ in 0..5 {
+            admin.list(&viewer).unwrap();
+        }
+        assert!(log.is_empty());
+
+        assert!(admin.stop(&viewer, "missing").is_err());
+        assert!(admin.stop(&ops, "missing").is_err());
+        assert!(admin.start(&ops, "missing").is_err());
+        assert!(matches!(
+            admin.reload(&ops, "missing"),
+            Err(Error::RateLimited { ref key, .. }) if key == "ops"
+        ));
+
+        let outcomes: Vec<String> = log
+            .records()
+            .iter()
+            .map(|r| format!("{} {} {}", r.principal, r.operation, r.outcome.name()))
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                "viewer stop denied",
+                "ops stop failed",
+                "ops start failed",
+                "ops reload rate_limited",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_failed_authentication_limited() {
+        let log = Arc::new(crate::audit::MemoryAuditLog::new(16));
+        let admin = admin()
+            .with_rate_limit(RateLimit::per_minute(2).unwrap())
+            .with_audit_sink(log.clone());
+        let forged = Credentials::bearer("forged");
+
+        for _ in 0..2 {
+            assert!(matches!(
+                admin.list(&forged),
+                Err(Error::Unauthenticated(_))
+            ));
+        }
+        // Guessing stops for everyone until the bucket refills
+        assert!(matches!(
+            admin.list(&forged),
+            Err(Error::RateLimited { ref key, .. }) if key == "anonymous"
+        ));
+        assert!(matches!(
+            admin.reload(&Credentials::bearer("ops-token"), "missing"),
+            Err(Error::RateLimited { .. })
+        ));
+        assert_eq!(
+            log.records()[0].outcome.name(),
+            AuditOutcome::RateLimited.name()
+        );
+    }
+
+    #[test]
+    fn test_bulk_through_admin() {
+        use crate::bulk::BulkOutcome;
+
+        let log = Arc::new(crate::audit::MemoryAuditLog::new(16));
+        let admin = admin()
+            .with_rate_limit(RateLimit::per_minute(2).unwrap())
+            .with_audit_sink(log.clone());
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("managed.fsx");
+        std::fs::write(&path, "let x = 1").unwrap();
+
+        let ops = [
+            BulkOp::load(&path),
+            BulkOp::reload("managed"),
+            BulkOp::stop("managed"),
+        ];
+        let ops_role = Credentials::bearer("ops-token");
+        let report = admin
+            .bulk_with(&ops_role, ops.clone(), BulkPolicy::ContinueOnError, |_| {})
+            .unwrap();
+        assert!(matches!(
+            report.results[0].1,
+            BulkOutcome::Failed(Error::PermissionDenied { .. })
+        ));
+        assert_eq!(report.failed(), 3);
+
+        let deployer = Credentials::client_cert("CN=deployer");
+        let report = admin
+            .bulk_with(&deployer, ops, BulkPolicy::ContinueOnError, |_| {})
+            .unwrap();
+        assert_eq!(report.succeeded(), 2);
+        assert!(matches!(
+            report.results[2].1,
+            BulkOutcome::Failed(Error::RateLimited { .. })
+        ));
+        assert_eq!(log.len(), 6);
+
+        assert!(matches!(
+            admin.bulk_with(
+                &Credentials::bearer("forged"),
+                [],
+                BulkPolicy::default(),
+                |_| {}
+            ),
+            Err(Error::Unauthenticated(_))
+        ));
+    }
+}
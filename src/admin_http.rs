@@ -0,0 +1,649 @@
+//! HTTP transport for the admin API.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use fusabi_host::Value;
+use serde_json::json;
+
+use crate::admin::{AdminApi, AdminOperation, Credentials};
+use crate::error::{Error, Result};
+use crate::marshal;
+use crate::namespace;
+
+/// Largest request body accepted, in bytes.
+const MAX_BODY: usize = 1024 * 1024;
+
+/// Longest request or header line accepted, in bytes.
+const MAX_LINE: usize = 8 * 1024;
+
+/// Most headers accepted in one request.
+const MAX_HEADERS: usize = 64;
+
+/// Most connections handled at once; further ones are answered with 503.
+const MAX_CONNECTIONS: usize = 64;
+
+/// Path segments that may follow a plugin name.
+const PLUGIN_ACTIONS: &[&str] = &["schema", "start", "stop", "reload", "call"];
+
+/// Serves an [`AdminApi`] over HTTP/1.1.
+///
+/// | Method | Path | Operation |
+/// |--------|------|-----------|
+/// | `GET` | `/plugins` | List plugins |
+/// | `GET` | `/plugins/{name}` | Describe a plugin |
+/// | `GET` | `/plugins/{name}/schema` | Exports, hooks, routes and configuration schema |
+/// | `POST` | `/plugins/{name}/start` | Start a plugin |
+/// | `POST` | `/plugins/{name}/stop` | Stop a plugin |
+/// | `POST` | `/plugins/{name}/reload` | Reload a plugin |
+/// | `POST` | `/plugins/{name}/call/{function}` | Call an export with a JSON array of arguments |
+/// | `GET` | `/host` | Host API version and build information |
+/// | `GET` | `/metrics` | Prometheus scrape endpoint |
+///
+/// Namespaced names such as `acme/http-logger` are written as two segments,
+/// `/plugins/acme/http-logger/start`, or as one with the `/` encoded as
+/// `%2F`; path segments are percent-decoded.
+///
+/// Credentials are read from the `Authorization: Bearer` header, so the
+/// admin API's authentication, rate limits and auditing apply unchanged.
+/// Responses are JSON; errors carry the message and its stable key.
+///
+/// Since requests are read before they are authenticated, the server
+/// handles at most 64 connections at once and rejects request lines or
+/// headers longer than 8 KiB, more than 64 headers and bodies over 1 MiB.
+pub struct AdminHttpServer {
+    api: Arc<AdminApi>,
+    listener: TcpListener,
+}
+
+impl AdminHttpServer {
+    /// Bind a server to an address, e.g. `"127.0.0.1:9090"`.
+    pub fn bind(api: Arc<AdminApi>, addr: impl ToSocketAddrs) -> Result<Self> {
+        Ok(Self {
+            api,
+            listener: TcpListener::bind(addr)?,
+        })
+    }
+
+    /// Get the bound address.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Serve requests on a background thread until the handle is shut down.
+    pub fn spawn(self) -> Result<AdminHttpHandle> {
+        let addr = self.local_addr()?;
+        let stopping = Arc::new(AtomicBool::new(false));
+        let stop = stopping.clone();
+        let thread = std::thread::Builder::new()
+            .name("fusabi-admin-http".to_string())
+            .spawn(move || self.serve_until(&stop))?;
+        Ok(AdminHttpHandle {
+            addr,
+            stopping,
+            thread: Some(thread),
+        })
+    }
+
+    /// Serve requests on the current thread.
+    pub fn serve(self) {
+        self.serve_until(&AtomicBool::new(false));
+    }
+
+    fn serve_until(&self, stopping: &AtomicBool) {
+        let active = Arc::new(AtomicUsize::new(0));
+        for stream in self.listener.incoming() {
+            if stopping.load(Ordering::SeqCst) {
+                break;
+            }
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    tracing::warn!("Admin HTTP accept failed: {}", e);
+                    continue;
+                }
+            };
+            if active.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                active.fetch_sub(1, Ordering::SeqCst);
+                tracing::warn!("Admin HTTP connection limit reached, rejecting connection");
+                reject_busy(stream);
+                continue;
+            }
+            let api = self.api.clone();
+            let active = active.clone();
+            let spawned = std::thread::Builder::new()
+                .name("fusabi-admin-http-conn".to_string())
+                .spawn(move || {
+                    if let Err(e) = handle_connection(&api, stream) {
+                        tracing::debug!("Admin HTTP connection failed: {}", e);
+                    }
+                    active.fetch_sub(1, Ordering::SeqCst);
+                });
+            if let Err(e) = spawned {
+                tracing::warn!("Admin HTTP cannot handle connection: {}", e);
+                active.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for AdminHttpServer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AdminHttpServer")
+            .field("addr", &self.listener.local_addr().ok())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Handle of a server started with [`AdminHttpServer::spawn`].
+///
+/// Dropping the handle shuts the server down.
+#[derive(Debug)]
+pub struct AdminHttpHandle {
+    addr: SocketAddr,
+    stopping: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl AdminHttpHandle {
+    /// Get the address the server listens on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Stop accepting connections and wait for the server thread.
+    ///
+    /// Requests already being handled run to completion.
+    pub fn shutdown(mut self) {
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            self.stopping.store(true, Ordering::SeqCst);
+            // Wake the blocking accept
+            let _ = TcpStream::connect(self.addr);
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for AdminHttpHandle {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    credentials: Credentials,
+    body: Vec<u8>,
+}
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    headers: Vec<(&'static str, String)>,
+    body: String,
+}
+
+impl Response {
+    fn json(status: u16, body: serde_json::Value) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            headers: Vec::new(),
+            body: body.to_string(),
+        }
+    }
+
+    fn error(error: &Error) -> Self {
+        let status = match error.root() {
+            Error::PluginNotFound(_) | Error::FunctionNotFound(_) => 404,
+            Error::InvalidArguments(_) | Error::InvalidState { .. } => 400,
+            Error::Unauthenticated(_) => 401,
+            Error::PermissionDenied { .. } | Error::CallDenied { .. } => 403,
+            Error::RateLimited { .. } => 429,
+            _ => 500,
+        };
+        let mut response = Self::json(
+            status,
+            json!({ "error": error.to_string(), "code": error.message_key() }),
+        );
+        if let Error::RateLimited { retry_after, .. } = error.root() {
+            response
+                .headers
+                .push(("Retry-After", retry_after.as_secs_f64().ceil().to_string()));
+        }
+        response
+    }
+
+    fn not_found() -> Self {
+        Self::json(
+            404,
+            json!({ "error": "no such endpoint", "code": "not_found" }),
+        )
+    }
+}
+
+/// Answer a connection over the limit without reading its request.
+fn reject_busy(mut stream: TcpStream) {
+    let response = Response::json(
+        503,
+        json!({ "error": "too many connections", "code": "busy" }),
+    );
+    let _ = stream.set_write_timeout(Some(Duration::from_secs(1)));
+    let _ = write_response(&mut stream, &response);
+}
+
+fn handle_connection(api: &AdminApi, mut stream: TcpStream) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+    stream.set_write_timeout(Some(Duration::from_secs(10)))?;
+    let response = match read_request(&mut stream)? {
+        Some(request) => route(api, &request),
+        None => Response::json(
+            400,
+            json!({ "error": "malformed request", "code": "bad_request" }),
+        ),
+    };
+    write_response(&mut stream, &response)
+}
+
+fn read_request(stream: &mut TcpStream) -> std::io::Result<Option<Request>> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    if !read_line_limited(&mut reader, &mut line)? {
+        return Ok(None);
+    }
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Ok(None);
+    };
+    let method = method.to_string();
+    // Query strings are not used by any endpoint
+    let path = target.split('?').next().unwrap_or(target).to_string();
+
+    let mut credentials = Credentials::default();
+    let mut content_length = 0;
+    for headers in 0.. {
+        line.clear();
+        if !read_line_limited(&mut reader, &mut line)? {
+            return Ok(None);
+        }
+        if line.is_empty() {
+            break;
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if headers == MAX_HEADERS {
+            return Ok(None);
+        }
+        let Some((name, value)) = header.split_once(':') else {
+            return Ok(None);
+        };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("content-length") {
+            match value.parse::<usize>() {
+                Ok(length) if length <= MAX_BODY => content_length = length,
+                _ => return Ok(None),
+            }
+        } else if name.eq_ignore_ascii_case("authorization") {
+            if let Some(token) = value.strip_prefix("Bearer ") {
+                credentials.bearer_token = Some(token.trim().to_string());
+            }
+        }
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+    Ok(Some(Request {
+        method,
+        path,
+        credentials,
+        body,
+    }))
+}
+
+/// Read a line of at most [`MAX_LINE`] bytes, returning false if it is
+/// longer.
+fn read_line_limited(reader: &mut impl BufRead, line: &mut String) -> std::io::Result<bool> {
+    let read = reader.take(MAX_LINE as u64 + 1).read_line(line)?;
+    Ok(read <= MAX_LINE)
+}
+
+fn write_response(stream: &mut TcpStream, response: &Response) -> std::io::Result<()> {
+    let reason = match response.status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        429 => "Too Many Requests",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    };
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        reason,
+        response.content_type,
+        response.body.len()
+    );
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(response.body.as_bytes())?;
+    stream.flush()
+}
+
+fn route(api: &AdminApi, request: &Request) -> Response {
+    let credentials = &request.credentials;
+    let Some(segments) = path_segments(&request.path) else {
+        return Response::error(&Error::invalid_arguments(
+            "request path is not valid percent-encoded UTF-8",
+        ));
+    };
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+    let ok = || Response::json(200, json!({ "ok": true }));
+
+    let result = match (request.method.as_str(), segments.as_slice()) {
+        ("GET", ["plugins"]) => api.authorize(credentials, AdminOperation::List).map(|_| {
+            let mut plugins = Vec::new();
+            api.runtime().for_each_plugin(|p| {
+                plugins
+                    .push(json!({ "name": p.name(), "version": p.version(), "state": p.state() }))
+            });
+            Response::json(200, json!(plugins))
+        }),
+        ("GET", ["plugins", name]) => api
+            .describe(credentials, name)
+            .and_then(|descriptor| to_json(&descriptor))
+            .map(|body| Response::json(200, body)),
+        ("GET", ["plugins", name, "schema"]) => {
+            api.describe(credentials, name).and_then(|descriptor| {
+                Ok(Response::json(
+                    200,
+                    json!({
+                        "name": descriptor.name,
+                        "version": descriptor.version,
+                        "exports": to_json(&descriptor.exports)?,
+                        "hooks": descriptor.hooks,
+                        "routes": descriptor.routes,
+                        "config": to_json(&descriptor.config)?,
+                    }),
+                ))
+            })
+        }
+        ("POST", ["plugins", name, "start"]) => api.start(credentials, name).map(|()| ok()),
+        ("POST", ["plugins", name, "stop"]) => api.stop(credentials, name).map(|()| ok()),
+        ("POST", ["plugins", name, "reload"]) => api.reload(credentials, name).map(|()| ok()),
+        ("POST", ["plugins", name, "call", function]) => parse_args(&request.body)
+            .and_then(|args| api.call(credentials, name, function, &args))
+            .and_then(|value| to_json(&value))
+            .map(|result| Response::json(200, json!({ "result": result }))),
+        ("GET", ["host"]) => api
+            .host_info(credentials)
+            .and_then(|info| to_json(&info))
+            .map(|body| Response::json(200, body)),
+        ("GET", ["metrics"]) => metrics(api, credentials),
+        _ => return Response::not_found(),
+    };
+
+    result.unwrap_or_else(|e| Response::error(&e))
+}
+
+/// Split a request path into percent-decoded segments, joining a namespaced
+/// plugin name written as two segments into one.
+///
+/// A segment after the namespace that names an action, as in
+/// `/plugins/acme/stop`, is read as the action.
+fn path_segments(path: &str) -> Option<Vec<String>> {
+    let mut segments = path
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .map(percent_decode)
+        .collect::<Option<Vec<_>>>()?;
+    if segments.len() >= 3
+        && segments[0] == "plugins"
+        && !segments[1].contains(namespace::SEPARATOR)
+        && !PLUGIN_ACTIONS.contains(&segments[2].as_str())
+    {
+        let local = segments.remove(2);
+        segments[1] = format!("{}{}{}", segments[1], namespace::SEPARATOR, local);
+    }
+    Some(segments)
+}
+
+fn percent_decode(segment: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(segment.len());
+    let mut rest = segment.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex = tail
+                .get(..2)
+                .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))?;
+            bytes.push(u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+fn to_json(value: &impl serde::Serialize) -> Result<serde_json::Value> {
+    serde_json::to_value(value).map_err(|e| Error::Registry(format!("failed to encode: {}", e)))
+}
+
+fn parse_args(body: &[u8]) -> Result<Vec<Value>> {
+    if body.iter().all(u8::is_ascii_whitespace) {
+        return Ok(Vec::new());
+    }
+    match serde_json::from_slice(body) {
+        Ok(serde_json::Value::Array(args)) => {
+            let mut converter = marshal::JsonConverter::default();
+            args.into_iter().map(|arg| converter.convert(arg)).collect()
+        }
+        _ => Err(Error::invalid_arguments(
+            "request body must be a JSON array of arguments",
+        )),
+    }
+}
+
+#[cfg(feature = "metrics-prometheus")]
+fn metrics(api: &AdminApi, credentials: &Credentials) -> Result<Response> {
+    use prometheus::Encoder;
+
+    api.authorize(credentials, AdminOperation::List)?;
+    let Some(metrics) = api.runtime().metrics() else {
+        return Ok(Response::not_found());
+    };
+    let encoder = prometheus::TextEncoder::new();
+    let mut body = Vec::new();
+    encoder
+        .encode(&metrics.registry().gather(), &mut body)
+        .map_err(|e| Error::Registry(format!("failed to encode metrics: {}", e)))?;
+    Ok(Response {
+        status: 200,
+        content_type: "text/plain; version=0.0.4",
+        headers: Vec::new(),
+        body: String::from_utf8_lossy(&body).into_owned(),
+    })
+}
+
+#[cfg(not(feature = "metrics-prometheus"))]
+fn metrics(api: &AdminApi, credentials: &Credentials) -> Result<Response> {
+    api.authorize(credentials, AdminOperation::List)?;
+    Ok(Response::not_found())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::admin::{Principal, Role, StaticTokenAuthenticator};
+    use crate::runtime::{PluginRuntime, RuntimeConfig};
+
+    fn send(addr: SocketAddr, request: &str) -> (u16, String) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let status = response[9..12].parse().unwrap();
+        let body = response
+            .split_once("\r\n\r\n")
+            .map(|(_, body)| body.to_string())
+            .unwrap_or_default();
+        (status, body)
+    }
+
+    #[test]
+    fn test_admin_http() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("echo.fsx");
+        std::fs::write(&path, "let x = 1").unwrap();
+        let runtime = Arc::new(PluginRuntime::new(RuntimeConfig::new()).unwrap());
+        runtime.load_source(&path).unwrap();
+
+        let authenticator = StaticTokenAuthenticator::new()
+            .with_token("ops", Principal::new("ops", Role::Operate))
+            .with_token("viewer", Principal::new("viewer", Role::ReadOnly));
+        let api = Arc::new(AdminApi::new(runtime, Arc::new(authenticator)));
+        let server = AdminHttpServer::bind(api, "127.0.0.1:0")
+            .unwrap()
+            .spawn()
+            .unwrap();
+        let addr = server.local_addr();
+
+        let (status, body) = send(
+            addr,
+            "GET /plugins HTTP/1.1\r\nAuthorization: Bearer viewer\r\n\r\n",
+        );
+        assert_eq!(status, 200);
+        assert!(body.contains("\"name\":\"echo\""));
+
+        let (status, body) = send(addr, "GET /plugins/echo HTTP/1.1\r\n\r\n");
+        assert_eq!(status, 401);
+        assert!(body.contains("\"code\":\"unauthenticated\""));
+
+        let (status, body) = send(
+            addr,
+            "GET /plugins/echo/schema HTTP/1.1\r\nAuthorization: Bearer viewer\r\n\r\n",
+        );
+        assert_eq!(status, 200);
+        let schema: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(schema["name"], "echo");
+        assert!(schema["exports"].is_array());
+        assert!(schema["routes"].as_object().unwrap().is_empty());
+
+        let (status, body) = send(
+            addr,
+            "GET /host HTTP/1.1\r\nAuthorization: Bearer viewer\r\n\r\n",
+        );
+        assert_eq!(status, 200);
+        let host: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(host["runtime_version"], crate::VERSION);
+
+        let call = "POST /plugins/echo/call/main HTTP/1.1\r\nAuthorization: Bearer {}\r\n\
+                    Content-Length: 2\r\n\r\n[]";
+        assert_eq!(send(addr, &call.replace("{}", "viewer")).0, 403);
+        let (status, body) = send(addr, &call.replace("{}", "ops"));
+        assert_eq!(status, 200);
+        assert!(body.contains("\"result\""));
+
+        assert_eq!(
+            send(
+                addr,
+                "GET /plugins/missing HTTP/1.1\r\nAuthorization: Bearer ops\r\n\r\n"
+            )
+            .0,
+            404
+        );
+        assert_eq!(send(addr, "DELETE /plugins HTTP/1.1\r\n\r\n").0, 404);
+
+        // Oversized requests are rejected before authentication; both stop
+        // right where the server gives up reading
+        let long_line = format!("GET /{}", "a".repeat(MAX_LINE - 4));
+        assert_eq!(send(addr, &long_line).0, 400);
+        let many_headers = format!(
+            "GET /plugins HTTP/1.1\r\n{}",
+            "X-Filler: 1\r\n".repeat(MAX_HEADERS + 1)
+        );
+        assert_eq!(send(addr, &many_headers).0, 400);
+        server.shutdown();
+    }
+
+    #[test]
+    fn test_namespaced_plugin_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("logger.fsx"), "let x = 1").unwrap();
+        let manifest = dir.path().join("logger.toml");
+        std::fs::write(
+            &manifest,
+            "name = \"acme/http-logger\"\nversion = \"1.0.0\"\nsource = \"logger.fsx\"\n\
+             api-version = { major = 0, minor = 21, patch = 0 }\n",
+        )
+        .unwrap();
+        let runtime = Arc::new(PluginRuntime::new(RuntimeConfig::new()).unwrap());
+        runtime.load_manifest(&manifest).unwrap();
+
+        let authenticator =
+            StaticTokenAuthenticator::new().with_token("ops", Principal::new("ops", Role::Operate));
+        let api = Arc::new(AdminApi::new(runtime, Arc::new(authenticator)));
+        let server = AdminHttpServer::bind(api, "127.0.0.1:0")
+            .unwrap()
+            .spawn()
+            .unwrap();
+        let addr = server.local_addr();
+        let request = |method: &str, path: &str| {
+            send(
+                addr,
+                &format!(
+                    "{} {} HTTP/1.1\r\nAuthorization: Bearer ops\r\n\r\n",
+                    method, path
+                ),
+            )
+        };
+
+        for path in ["/plugins/acme/http-logger", "/plugins/acme%2Fhttp-logger"] {
+            let (status, body) = request("GET", path);
+            assert_eq!(status, 200, "{}", path);
+            assert!(body.contains("\"name\":\"acme/http-logger\""));
+        }
+        assert_eq!(request("GET", "/plugins/acme/http-logger/schema").0, 200);
+        assert_eq!(request("POST", "/plugins/acme/http-logger/stop").0, 200);
+        // Found, but already stopped
+        assert_eq!(request("POST", "/plugins/acme%2fhttp-logger/stop").0, 400);
+
+        // An action after the namespace is read as the action
+        assert_eq!(request("POST", "/plugins/acme/stop").0, 404);
+        assert_eq!(request("GET", "/plugins/acme/other").0, 404);
+        assert_eq!(request("GET", "/plugins/acme%2").0, 400);
+        assert_eq!(request("GET", "/plugins/%FF").0, 400);
+        server.shutdown();
+    }
+
+    #[test]
+    fn test_error_status_sees_plugin_context() {
+        let error = Error::rate_limited("echo", Duration::from_millis(1500)).in_plugin(
+            "echo",
+            crate::PluginOperation::Call,
+            None,
+        );
+        let response = Response::error(&error);
+        assert_eq!(response.status, 429);
+        assert_eq!(response.headers, vec![("Retry-After", "2".to_string())]);
+
+        let denied = Error::call_denied("ops", "echo", "main");
+        assert_eq!(Response::error(&denied).status, 403);
+    }
+}
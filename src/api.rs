@@ -0,0 +1,99 @@
+//! Object-safe runtime interface for embedding frameworks.
+
+use std::path::Path;
+
+use fusabi_host::Value;
+
+use crate::error::{Error, Result};
+use crate::plugin::PluginHandle;
+use crate::registry::RegistryStats;
+use crate::runtime::PluginRuntime;
+
+/// Core plugin runtime operations behind a trait object.
+///
+/// Frameworks can accept `Arc<dyn RuntimeApi>` instead of a concrete
+/// [`PluginRuntime`], and tests can substitute a lightweight implementation
+/// that never constructs engines.
+pub trait RuntimeApi: Send + Sync {
+    /// Load and register a plugin from a manifest (`.toml`), source (`.fsx`)
+    /// or bytecode (`.fzb`) file.
+    fn load(&self, path: &Path) -> Result<PluginHandle>;
+
+    /// Unload a plugin by name.
+    fn unload(&self, name: &str) -> Result<()>;
+
+    /// Get a plugin by name.
+    fn get(&self, name: &str) -> Option<PluginHandle>;
+
+    /// Get the names of all loaded plugins.
+    fn plugin_names(&self) -> Vec<String>;
+
+    /// Call a function on a plugin.
+    fn call(&self, plugin: &str, function: &str, args: &[Value]) -> Result<Value>;
+
+    /// Get plugin statistics.
+    fn stats(&self) -> RegistryStats;
+}
+
+impl RuntimeApi for PluginRuntime {
+    fn load(&self, path: &Path) -> Result<PluginHandle> {
+        match path.extension().and_then(|e| e.to_str()) {
+            #[cfg(feature = "serde")]
+            Some("toml") => self.load_manifest(path),
+            Some("fsx") => self.load_source(path),
+            Some("fzb") => self.load_bytecode(path),
+            _ => Err(Error::invalid_arguments(format!(
+                "unsupported plugin file: {}",
+                path.display()
+            ))),
+        }
+    }
+
+    fn unload(&self, name: &str) -> Result<()> {
+        PluginRuntime::unload(self, name)
+    }
+
+    fn get(&self, name: &str) -> Option<PluginHandle> {
+        PluginRuntime::get(self, name)
+    }
+
+    fn plugin_names(&self) -> Vec<String> {
+        self.registry().names()
+    }
+
+    fn call(&self, plugin: &str, function: &str, args: &[Value]) -> Result<Value> {
+        PluginRuntime::call(self, plugin, function, args)
+    }
+
+    fn stats(&self) -> RegistryStats {
+        PluginRuntime::stats(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_runtime_as_trait_object() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("embedded.fsx");
+        std::fs::write(&path, "let x = 1").unwrap();
+
+        let runtime: Arc<dyn RuntimeApi> = Arc::new(PluginRuntime::default_config().unwrap());
+        runtime.load(&path).unwrap();
+
+        assert_eq!(runtime.plugin_names(), vec!["embedded".to_string()]);
+        assert!(runtime.get("embedded").is_some());
+        assert_eq!(runtime.stats().total, 1);
+
+        runtime.unload("embedded").unwrap();
+        assert!(runtime.get("embedded").is_none());
+
+        assert!(matches!(
+            runtime.load(&dir.path().join("plugin.zip")),
+            Err(Error::InvalidArguments(_))
+        ));
+    }
+}
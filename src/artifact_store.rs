@@ -0,0 +1,624 @@
+//! Content-addressed store for plugin artifacts.
+
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use parking_lot::{Mutex, MutexGuard};
+
+use crate::crypto::sha256_hex;
+use crate::error::{Error, Result};
+use crate::namespace;
+
+/// Artifacts of plugin versions, each stored once by content hash.
+///
+/// Blobs live under `blobs/sha256/<first two hex digits>/<digest>` and
+/// every plugin version references them from
+/// `refs/<plugin>/<version>/<artifact>`, so identical bytecode kept for
+/// several plugins or versions takes the disk space of one copy. The order
+/// versions were installed in is kept in `history/<plugin>`.
+///
+/// Writes and garbage collection are serialized, within the process and
+/// across processes sharing the directory, through a `lock` file holding
+/// the owner's process ID. A lock file older than a minute is taken to be
+/// left behind by a crashed process and is removed.
+#[derive(Debug)]
+pub struct ArtifactStore {
+    root: PathBuf,
+    // Serializes writes with garbage collection so a blob is never collected
+    // between being written and being referenced.
+    lock: Mutex<()>,
+}
+
+/// How long to wait for another process to release the store.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+/// Age after which a lock file is considered abandoned.
+const STALE_LOCK: Duration = Duration::from_secs(60);
+
+/// Exclusive access to a store, released on drop.
+struct StoreLock<'a> {
+    path: PathBuf,
+    _guard: MutexGuard<'a, ()>,
+}
+
+impl Drop for StoreLock<'_> {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// What [`ArtifactStore::gc`] removed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GcReport {
+    /// Blobs deleted because nothing referenced them.
+    pub removed: usize,
+    /// Bytes freed.
+    pub freed_bytes: u64,
+}
+
+/// A plugin version recorded by [`ArtifactStore::record_install`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstalledVersion {
+    /// Plugin version.
+    pub version: String,
+    /// Whether the version has served calls successfully.
+    pub known_good: bool,
+}
+
+/// Store usage.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArtifactStats {
+    /// Distinct blobs stored.
+    pub blobs: usize,
+    /// Size of all blobs in bytes.
+    pub bytes: u64,
+    /// References from plugin versions to blobs.
+    pub refs: usize,
+}
+
+impl ArtifactStore {
+    /// Open a store, creating its directory if needed.
+    pub fn open(root: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
+        std::fs::create_dir_all(root.join("blobs").join("sha256"))?;
+        std::fs::create_dir_all(root.join("refs"))?;
+        Ok(Self {
+            root,
+            lock: Mutex::new(()),
+        })
+    }
+
+    /// Get the store directory.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Compute the digest of some content.
+    pub fn digest(content: &[u8]) -> String {
+        sha256_hex(content)
+    }
+
+    /// Store a blob, returning its digest. An existing blob is rewritten only
+    /// if it no longer matches its digest.
+    pub fn put(&self, content: &[u8]) -> Result<String> {
+        let _lock = self.lock()?;
+        self.write_blob(content)
+    }
+
+    /// Read a blob, checking it still matches its digest.
+    pub fn get(&self, digest: &str) -> Result<Vec<u8>> {
+        let content = std::fs::read(self.blob_path(digest)?)?;
+        if Self::digest(&content) != digest {
+            return Err(Error::Registry(format!("artifact {} is corrupt", digest)));
+        }
+        Ok(content)
+    }
+
+    /// Check if a blob is stored.
+    pub fn contains(&self, digest: &str) -> bool {
+        self.blob_path(digest).is_ok_and(|path| path.is_file())
+    }
+
+    /// Store an artifact of a plugin version, returning its digest.
+    pub fn put_artifact(
+        &self,
+        plugin: &str,
+        version: &str,
+        artifact: &str,
+        content: &[u8],
+    ) -> Result<String> {
+        let ref_path = self.ref_path(plugin, version, artifact)?;
+        let _lock = self.lock()?;
+        let digest = self.write_blob(content)?;
+        if let Some(parent) = ref_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        write_atomic(&ref_path, digest.as_bytes())?;
+        Ok(digest)
+    }
+
+    /// Get the digest an artifact of a plugin version refers to.
+    pub fn resolve(&self, plugin: &str, version: &str, artifact: &str) -> Option<String> {
+        let path = self.ref_path(plugin, version, artifact).ok()?;
+        let digest = std::fs::read_to_string(path).ok()?;
+        Some(digest.trim().to_string())
+    }
+
+    /// Read an artifact of a plugin version.
+    pub fn get_artifact(&self, plugin: &str, version: &str, artifact: &str) -> Result<Vec<u8>> {
+        let digest = self.resolve(plugin, version, artifact).ok_or_else(|| {
+            Error::Registry(format!(
+                "no artifact {} for {} {}",
+                artifact, plugin, version
+            ))
+        })?;
+        self.get(&digest)
+    }
+
+    /// List the stored versions of a plugin.
+    pub fn versions(&self, plugin: &str) -> Result<Vec<String>> {
+        let dir = self.root.join("refs").join(plugin_component(plugin)?);
+        let mut versions = list_dir(&dir)?;
+        versions.sort();
+        Ok(versions)
+    }
+
+    /// Drop the references of a plugin version. Its blobs are deleted by
+    /// the next [`gc`](Self::gc) unless another version shares them.
+    pub fn remove_version(&self, plugin: &str, version: &str) -> Result<()> {
+        let dir = self
+            .root
+            .join("refs")
+            .join(plugin_component(plugin)?)
+            .join(component(version)?);
+        let _lock = self.lock()?;
+        match std::fs::remove_dir_all(&dir) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+        // Leave no empty plugin directory behind
+        if let Some(parent) = dir.parent() {
+            let _ = std::fs::remove_dir(parent);
+        }
+        Ok(())
+    }
+
+    /// Record that a plugin version was installed, making it the newest
+    /// entry of the plugin's history.
+    pub fn record_install(&self, plugin: &str, version: &str) -> Result<()> {
+        component(version)?;
+        let _lock = self.lock()?;
+        let mut history = self.read_history(plugin)?;
+        let known_good = history
+            .iter()
+            .position(|v| v.version == version)
+            .is_some_and(|i| history.remove(i).known_good);
+        history.push(InstalledVersion {
+            version: version.to_string(),
+            known_good,
+        });
+        self.write_history(plugin, &history)
+    }
+
+    /// Mark a plugin version as having served calls successfully.
+    pub fn mark_known_good(&self, plugin: &str, version: &str) -> Result<()> {
+        let _lock = self.lock()?;
+        let mut history = self.read_history(plugin)?;
+        match history.iter_mut().find(|v| v.version == version) {
+            Some(entry) if entry.known_good => return Ok(()),
+            Some(entry) => entry.known_good = true,
+            None => history.push(InstalledVersion {
+                version: version.to_string(),
+                known_good: true,
+            }),
+        }
+        self.write_history(plugin, &history)
+    }
+
+    /// Get the installed versions of a plugin whose artifacts are still
+    /// stored, oldest first.
+    pub fn installed(&self, plugin: &str) -> Result<Vec<InstalledVersion>> {
+        let stored = self.versions(plugin)?;
+        let _lock = self.lock()?;
+        let mut history = self.read_history(plugin)?;
+        history.retain(|v| stored.contains(&v.version));
+        Ok(history)
+    }
+
+    /// Drop all but the `keep` most recently installed versions of a plugin,
+    /// returning the removed versions. Versions never recorded as installed
+    /// count as the oldest.
+    pub fn retain_versions(&self, plugin: &str, keep: usize) -> Result<Vec<String>> {
+        let history = self.installed(plugin)?;
+        let mut removed: Vec<String> = self
+            .versions(plugin)?
+            .into_iter()
+            .filter(|version| !history.iter().any(|v| &v.version == version))
+            .collect();
+        let excess = (removed.len() + history.len()).saturating_sub(keep);
+        removed.truncate(excess);
+        removed.extend(
+            history
+                .into_iter()
+                .take(excess - removed.len())
+                .map(|v| v.version),
+        );
+
+        for version in &removed {
+            self.remove_version(plugin, version)?;
+        }
+        Ok(removed)
+    }
+
+    /// Delete blobs no plugin version refers to.
+    pub fn gc(&self) -> Result<GcReport> {
+        let _lock = self.lock()?;
+        let referenced = self.referenced()?;
+        let mut report = GcReport::default();
+
+        for (digest, path) in self.blobs()? {
+            if referenced.contains(&digest) {
+                continue;
+            }
+            let size = std::fs::metadata(&path).map_or(0, |m| m.len());
+            std::fs::remove_file(&path)?;
+            report.removed += 1;
+            report.freed_bytes += size;
+        }
+
+        if report.removed > 0 {
+            tracing::debug!(
+                "Artifact store gc removed {} blobs ({} bytes)",
+                report.removed,
+                report.freed_bytes
+            );
+        }
+        Ok(report)
+    }
+
+    /// Get store usage.
+    pub fn stats(&self) -> Result<ArtifactStats> {
+        let _lock = self.lock()?;
+        let blobs = self.blobs()?;
+        Ok(ArtifactStats {
+            blobs: blobs.len(),
+            bytes: blobs
+                .iter()
+                .map(|(_, path)| std::fs::metadata(path).map_or(0, |m| m.len()))
+                .sum(),
+            refs: self.refs()?.len(),
+        })
+    }
+
+    /// Take the store lock, waiting for other processes to release it.
+    fn lock(&self) -> Result<StoreLock<'_>> {
+        let guard = self.lock.lock();
+        let path = self.root.join("lock");
+        let deadline = SystemTime::now() + LOCK_TIMEOUT;
+        loop {
+            match std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(mut file) => {
+                    std::io::Write::write_all(
+                        &mut file,
+                        std::process::id().to_string().as_bytes(),
+                    )?;
+                    return Ok(StoreLock {
+                        path,
+                        _guard: guard,
+                    });
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(e.into()),
+            }
+
+            let stale = std::fs::metadata(&path)
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|modified| modified.elapsed().ok())
+                .is_some_and(|age| age > STALE_LOCK);
+            if stale {
+                tracing::warn!("Removing abandoned artifact store lock {}", path.display());
+                let _ = std::fs::remove_file(&path);
+                continue;
+            }
+            if SystemTime::now() > deadline {
+                return Err(Error::Registry(format!(
+                    "artifact store {} is locked by another process",
+                    self.root.display()
+                )));
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    fn write_blob(&self, content: &[u8]) -> Result<String> {
+        let digest = Self::digest(content);
+        let path = self.blob_path(&digest)?;
+        match std::fs::read(&path) {
+            Ok(existing) if Self::digest(&existing) == digest => return Ok(digest),
+            Ok(_) => tracing::warn!("Rewriting corrupt artifact {}", digest),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        write_atomic(&path, content)?;
+        Ok(digest)
+    }
+
+    fn history_path(&self, plugin: &str) -> Result<PathBuf> {
+        Ok(self.root.join("history").join(plugin_component(plugin)?))
+    }
+
+    fn read_history(&self, plugin: &str) -> Result<Vec<InstalledVersion>> {
+        let content = match std::fs::read_to_string(self.history_path(plugin)?) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(content
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                let version = fields.next()?.to_string();
+                Some(InstalledVersion {
+                    version,
+                    known_good: fields.next() == Some("known-good"),
+                })
+            })
+            .collect())
+    }
+
+    fn write_history(&self, plugin: &str, history: &[InstalledVersion]) -> Result<()> {
+        let path = self.history_path(plugin)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content: String = history
+            .iter()
+            .map(|v| {
+                if v.known_good {
+                    format!("{} known-good\n", v.version)
+                } else {
+                    format!("{}\n", v.version)
+                }
+            })
+            .collect();
+        write_atomic(&path, content.as_bytes())
+    }
+
+    fn blob_path(&self, digest: &str) -> Result<PathBuf> {
+        if digest.len() != 64 || !digest.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(Error::invalid_arguments(format!(
+                "invalid artifact digest: {}",
+                digest
+            )));
+        }
+        Ok(self
+            .root
+            .join("blobs")
+            .join("sha256")
+            .join(&digest[..2])
+            .join(digest))
+    }
+
+    fn ref_path(&self, plugin: &str, version: &str, artifact: &str) -> Result<PathBuf> {
+        Ok(self
+            .root
+            .join("refs")
+            .join(plugin_component(plugin)?)
+            .join(component(version)?)
+            .join(component(artifact)?))
+    }
+
+    fn blobs(&self) -> Result<Vec<(String, PathBuf)>> {
+        let dir = self.root.join("blobs").join("sha256");
+        let mut blobs = Vec::new();
+        for prefix in list_dir(&dir)? {
+            for digest in list_dir(&dir.join(&prefix))? {
+                // Skip temporary files of interrupted writes
+                if digest.len() == 64 {
+                    blobs.push((digest.clone(), dir.join(&prefix).join(digest)));
+                }
+            }
+        }
+        Ok(blobs)
+    }
+
+    fn refs(&self) -> Result<Vec<PathBuf>> {
+        let dir = self.root.join("refs");
+        let mut refs = Vec::new();
+        for plugin in list_dir(&dir)? {
+            for version in list_dir(&dir.join(&plugin))? {
+                let version_dir = dir.join(&plugin).join(&version);
+                for artifact in list_dir(&version_dir)? {
+                    if !artifact.ends_with(".tmp") {
+                        refs.push(version_dir.join(artifact));
+                    }
+                }
+            }
+        }
+        Ok(refs)
+    }
+
+    fn referenced(&self) -> Result<std::collections::HashSet<String>> {
+        self.refs()?
+            .into_iter()
+            .map(|path| Ok(std::fs::read_to_string(path)?.trim().to_string()))
+            .collect()
+    }
+}
+
+/// Encode a plugin name as a single path component, keeping namespaced
+/// names such as `acme/http-logger` in one directory.
+fn plugin_component(plugin: &str) -> Result<String> {
+    namespace::validate(plugin).map_err(|_| {
+        Error::invalid_arguments(format!("invalid artifact store name: {:?}", plugin))
+    })?;
+    match namespace::split(plugin) {
+        (Some(namespace), local) => {
+            component(namespace)?;
+            component(local)?;
+            Ok(format!("{}%2F{}", namespace, local))
+        }
+        (None, local) => component(local).map(str::to_string),
+    }
+}
+
+/// Check a name is usable as a single path component.
+fn component(name: &str) -> Result<&str> {
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => Ok(name),
+        _ => Err(Error::invalid_arguments(format!(
+            "invalid artifact store name: {:?}",
+            name
+        ))),
+    }
+}
+
+fn list_dir(dir: &Path) -> Result<Vec<String>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut names = Vec::new();
+    for entry in entries {
+        if let Some(name) = entry?.file_name().to_str() {
+            names.push(name.to_string());
+        }
+    }
+    Ok(names)
+}
+
+fn write_atomic(path: &Path, content: &[u8]) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, content)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dedup_and_gc() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ArtifactStore::open(dir.path()).unwrap();
+
+        let a = store
+            .put_artifact("search", "1.0.0", "bytecode", b"same")
+            .unwrap();
+        let b = store
+            .put_artifact("search", "1.1.0", "bytecode", b"same")
+            .unwrap();
+        let c = store
+            .put_artifact("notes", "1.0.0", "bytecode", b"other")
+            .unwrap();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+
+        let stats = store.stats().unwrap();
+        assert_eq!(stats.blobs, 2);
+        assert_eq!(stats.refs, 3);
+        assert_eq!(store.versions("search").unwrap(), vec!["1.0.0", "1.1.0"]);
+
+        store.remove_version("search", "1.0.0").unwrap();
+        assert_eq!(store.gc().unwrap().removed, 0);
+        assert_eq!(
+            store.get_artifact("search", "1.1.0", "bytecode").unwrap(),
+            b"same"
+        );
+
+        store.remove_version("search", "1.1.0").unwrap();
+        let report = store.gc().unwrap();
+        assert_eq!(report.removed, 1);
+        assert_eq!(report.freed_bytes, 4);
+        assert!(!store.contains(&a));
+        assert!(store.contains(&c));
+        assert!(store.versions("search").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_install_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ArtifactStore::open(dir.path()).unwrap();
+        for version in ["1.0.0", "1.1.0", "0.9.0"] {
+            store
+                .put_artifact("search", version, "bytecode", version.as_bytes())
+                .unwrap();
+        }
+        store.record_install("search", "1.1.0").unwrap();
+        store.record_install("search", "1.0.0").unwrap();
+        store.mark_known_good("search", "1.1.0").unwrap();
+
+        let installed = store.installed("search").unwrap();
+        assert_eq!(installed.len(), 2);
+        assert_eq!(installed[0].version, "1.1.0");
+        assert!(installed[0].known_good);
+        assert!(!installed[1].known_good);
+
+        // The never-installed 0.9.0 goes first, then the oldest install
+        assert_eq!(
+            store.retain_versions("search", 1).unwrap(),
+            vec!["0.9.0", "1.1.0"]
+        );
+        assert_eq!(store.versions("search").unwrap(), vec!["1.0.0"]);
+        assert_eq!(store.installed("search").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_rejects_bad_names_and_corruption() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ArtifactStore::open(dir.path()).unwrap();
+        assert!(store
+            .put_artifact("../x", "1.0.0", "bytecode", b"x")
+            .is_err());
+        assert!(store
+            .put_artifact("acme/../x", "1.0.0", "bytecode", b"x")
+            .is_err());
+        store
+            .put_artifact("acme/search", "1.0.0", "bytecode", b"x")
+            .unwrap();
+        assert_eq!(store.versions("acme/search").unwrap(), vec!["1.0.0"]);
+        assert!(store.get("not-a-digest").is_err());
+
+        let digest = store.put(b"content").unwrap();
+        let path = store.blob_path(&digest).unwrap();
+        std::fs::write(path, b"tampered").unwrap();
+        assert!(store.get(&digest).is_err());
+
+        // Storing the content again repairs the blob
+        assert_eq!(store.put(b"content").unwrap(), digest);
+        assert_eq!(store.get(&digest).unwrap(), b"content");
+    }
+
+    #[test]
+    fn test_lock_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ArtifactStore::open(dir.path()).unwrap();
+        let lock = dir.path().join("lock");
+
+        store.put(b"content").unwrap();
+        assert!(!lock.exists());
+
+        // Another process holding the lock keeps writers out until it
+        // releases it
+        std::fs::write(&lock, b"1").unwrap();
+        let writer = std::thread::spawn({
+            let root = dir.path().to_path_buf();
+            move || ArtifactStore::open(root).unwrap().put(b"other")
+        });
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!writer.is_finished());
+        std::fs::remove_file(&lock).unwrap();
+        assert!(writer.join().unwrap().is_ok());
+        assert!(!lock.exists());
+    }
+}
@@ -0,0 +1,442 @@
+//! Audit records of security-relevant operations.
+
+use std::collections::{HashSet, VecDeque};
+#[cfg(feature = "serde")]
+use std::io::Write;
+#[cfg(feature = "serde")]
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use parking_lot::{Mutex, RwLock};
+
+use crate::sensitive::CallArg;
+
+/// Outcome of an audited operation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuditOutcome {
+    /// The operation was performed successfully.
+    Succeeded,
+    /// The operation was attempted but failed.
+    Failed(String),
+    /// The caller was not allowed to perform the operation.
+    Denied(String),
+    /// The caller exceeded its rate limit.
+    RateLimited,
+}
+
+impl AuditOutcome {
+    /// Get the outcome name.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Succeeded => "succeeded",
+            Self::Failed(_) => "failed",
+            Self::Denied(_) => "denied",
+            Self::RateLimited => "rate_limited",
+        }
+    }
+}
+
+/// A single audit log entry: who did what to which target, when.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditRecord {
+    /// When the operation happened.
+    pub at: SystemTime,
+    /// Who performed it.
+    pub principal: String,
+    /// What was done.
+    pub operation: String,
+    /// What it was done to, e.g. a plugin name or path.
+    pub target: Option<String>,
+    /// How it ended.
+    pub outcome: AuditOutcome,
+    /// Details of a sampled plugin call.
+    pub call: Option<CallDetails>,
+}
+
+/// Details recorded for a sampled plugin call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallDetails {
+    /// Called plugin.
+    pub plugin: String,
+    /// Called export.
+    pub export: String,
+    /// Arguments, rendered and truncated.
+    pub args: String,
+    /// Time spent in the call.
+    pub duration: Duration,
+}
+
+impl AuditRecord {
+    /// Create a record timestamped now.
+    pub fn new(
+        principal: impl Into<String>,
+        operation: impl Into<String>,
+        target: Option<String>,
+        outcome: AuditOutcome,
+    ) -> Self {
+        Self {
+            at: SystemTime::now(),
+            principal: principal.into(),
+            operation: operation.into(),
+            target,
+            outcome,
+            call: None,
+        }
+    }
+
+    /// Attach the details of a plugin call.
+    pub fn with_call(mut self, call: CallDetails) -> Self {
+        self.call = Some(call);
+        self
+    }
+}
+
+/// Which plugin calls are recorded in the audit log.
+///
+/// Calls are sampled at evenly spaced intervals, so a rate of `0.01` records
+/// every hundredth call. Calls to plugins added with
+/// [`with_plugin`](Self::with_plugin) are always recorded.
+///
+/// Sampled calls are kept in their own in-memory log, so they never evict
+/// capability audit records.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallSampling {
+    rate: f64,
+    plugins: HashSet<String>,
+    max_arg_chars: usize,
+    capacity: usize,
+}
+
+impl Default for CallSampling {
+    fn default() -> Self {
+        Self {
+            rate: 0.0,
+            plugins: HashSet::new(),
+            max_arg_chars: 200,
+            capacity: 1000,
+        }
+    }
+}
+
+impl CallSampling {
+    /// Record no calls until configured.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a fraction of all calls, between `0.0` and `1.0`.
+    pub fn with_rate(mut self, rate: f64) -> Self {
+        self.rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Record every call to a plugin.
+    pub fn with_plugin(mut self, plugin: impl Into<String>) -> Self {
+        self.plugins.insert(plugin.into());
+        self
+    }
+
+    /// Truncate rendered arguments to `chars` characters.
+    pub fn with_max_arg_chars(mut self, chars: usize) -> Self {
+        self.max_arg_chars = chars;
+        self
+    }
+
+    /// Keep at most `capacity` sampled calls in memory.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Get the number of sampled calls kept in memory.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Check if sampling records any calls.
+    pub fn is_enabled(&self) -> bool {
+        self.rate > 0.0 || !self.plugins.is_empty()
+    }
+
+    /// Check if the `n`th call, to `plugin`, is recorded.
+    pub(crate) fn samples(&self, plugin: &str, n: u64) -> bool {
+        if self.plugins.contains(plugin) {
+            return true;
+        }
+        // True whenever the running total of `rate` crosses an integer
+        (((n + 1) as f64) * self.rate).floor() > ((n as f64) * self.rate).floor()
+    }
+
+    /// Render call arguments for a record.
+    pub(crate) fn summarize(&self, args: &[fusabi_host::Value]) -> String {
+        self.truncate(args.iter().map(|arg| arg.to_string()))
+    }
+
+    /// Render call arguments for a record, redacting sensitive ones.
+    pub(crate) fn summarize_redacted(&self, args: &[CallArg]) -> String {
+        self.truncate(args.iter().map(CallArg::render))
+    }
+
+    fn truncate(&self, args: impl Iterator<Item = String>) -> String {
+        let rendered = args.collect::<Vec<_>>().join(", ");
+        match rendered.char_indices().nth(self.max_arg_chars) {
+            Some((end, _)) => format!("{}...", &rendered[..end]),
+            None => rendered,
+        }
+    }
+}
+
+/// Destination for audit records.
+pub trait AuditSink: Send + Sync {
+    /// Record an entry.
+    fn record(&self, record: &AuditRecord);
+}
+
+impl<T: AuditSink + ?Sized> AuditSink for Arc<T> {
+    fn record(&self, record: &AuditRecord) {
+        (**self).record(record)
+    }
+}
+
+/// Writes audit records as `tracing` events on the `fusabi::audit` target.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TracingAuditSink;
+
+impl AuditSink for TracingAuditSink {
+    fn record(&self, record: &AuditRecord) {
+        tracing::info!(
+            target: "fusabi::audit",
+            principal = %record.principal,
+            operation = %record.operation,
+            target_name = record.target.as_deref().unwrap_or("-"),
+            outcome = record.outcome.name(),
+            "audit"
+        );
+    }
+}
+
+/// Keeps the most recent audit records in memory.
+#[derive(Debug)]
+pub struct MemoryAuditLog {
+    capacity: usize,
+    records: Mutex<VecDeque<AuditRecord>>,
+}
+
+impl MemoryAuditLog {
+    /// Create a log holding at most `capacity` records.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            records: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Get the retained records, oldest first.
+    pub fn records(&self) -> Vec<AuditRecord> {
+        self.records.lock().iter().cloned().collect()
+    }
+
+    /// Get the number of retained records.
+    pub fn len(&self) -> usize {
+        self.records.lock().len()
+    }
+
+    /// Check if the log is empty.
+    pub fn is_empty(&self) -> bool {
+        self.records.lock().is_empty()
+    }
+}
+
+impl AuditSink for MemoryAuditLog {
+    fn record(&self, record: &AuditRecord) {
+        let mut records = self.records.lock();
+        if records.len() == self.capacity {
+            records.pop_front();
+        }
+        if self.capacity > 0 {
+            records.push_back(record.clone());
+        }
+    }
+}
+
+/// Appends audit records to a file, one JSON object per line.
+#[cfg(feature = "serde")]
+#[derive(Debug)]
+pub struct JsonlAuditSink {
+    file: Mutex<std::fs::File>,
+}
+
+#[cfg(feature = "serde")]
+impl JsonlAuditSink {
+    /// Open a file for appending, creating it if needed.
+    pub fn open(path: impl AsRef<Path>) -> crate::Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Render a record as a single JSON line.
+    pub fn to_json_line(record: &AuditRecord) -> String {
+        let at_ms = record
+            .at
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        let reason = match &record.outcome {
+            AuditOutcome::Failed(reason) | AuditOutcome::Denied(reason) => Some(reason),
+            _ => None,
+        };
+        let mut line = serde_json::json!({
+            "at_ms": at_ms,
+            "principal": record.principal,
+            "operation": record.operation,
+            "target": record.target,
+            "outcome": record.outcome.name(),
+            "reason": reason,
+        });
+        if let Some(ref call) = record.call {
+            line["plugin"] = call.plugin.clone().into();
+            line["export"] = call.export.clone().into();
+            line["args"] = call.args.clone().into();
+            line["duration_us"] = (call.duration.as_micros() as u64).into();
+        }
+        line.to_string()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl AuditSink for JsonlAuditSink {
+    fn record(&self, record: &AuditRecord) {
+        let line = Self::to_json_line(record);
+        let mut file = self.file.lock();
+        if let Err(e) = writeln!(file, "{}", line).and_then(|_| file.flush()) {
+            tracing::warn!("Failed to write audit record: {}", e);
+        }
+    }
+}
+
+/// In-memory logs that also forward records to extra sinks.
+///
+/// Sampled calls are retained apart from other records, so a busy plugin
+/// cannot push capability records out of memory.
+pub(crate) struct AuditFanout {
+    log: MemoryAuditLog,
+    calls: MemoryAuditLog,
+    sinks: RwLock<Vec<Arc<dyn AuditSink>>>,
+}
+
+impl AuditFanout {
+    /// Create a fanout retaining at most `capacity` records and
+    /// `call_capacity` sampled calls in memory.
+    pub(crate) fn new(capacity: usize, call_capacity: usize) -> Self {
+        Self {
+            log: MemoryAuditLog::new(capacity),
+            calls: MemoryAuditLog::new(call_capacity),
+            sinks: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Get the in-memory log.
+    pub(crate) fn log(&self) -> &MemoryAuditLog {
+        &self.log
+    }
+
+    /// Get the in-memory log of sampled calls.
+    pub(crate) fn calls(&self) -> &MemoryAuditLog {
+        &self.calls
+    }
+
+    /// Record a sampled call.
+    pub(crate) fn record_call(&self, record: &AuditRecord) {
+        self.calls.record(record);
+        self.forward(record);
+    }
+
+    /// Forward records to another sink as well.
+    pub(crate) fn add_sink(&self, sink: Arc<dyn AuditSink>) {
+        self.sinks.write().push(sink);
+    }
+
+    fn forward(&self, record: &AuditRecord) {
+        for sink in self.sinks.read().iter() {
+            sink.record(record);
+        }
+    }
+}
+
+impl AuditSink for AuditFanout {
+    fn record(&self, record: &AuditRecord) {
+        self.log.record(record);
+        self.forward(record);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_log_bounded() {
+        let log = MemoryAuditLog::new(2);
+        for op in ["load", "reload", "unload"] {
+            log.record(&AuditRecord::new(
+                "ops",
+                op,
+                Some("hello".to_string()),
+                AuditOutcome::Succeeded,
+            ));
+        }
+
+        let ops: Vec<String> = log.records().into_iter().map(|r| r.operation).collect();
+        assert_eq!(ops, vec!["reload", "unload"]);
+    }
+
+    #[test]
+    fn test_call_sampling() {
+        let sampling = CallSampling::new().with_rate(0.25).with_plugin("billing");
+        let sampled = (0..100).filter(|&n| sampling.samples("search", n)).count();
+        assert_eq!(sampled, 25);
+        assert!((0..4).all(|n| sampling.samples("billing", n)));
+        assert!(!CallSampling::new().is_enabled());
+
+        let sampling = CallSampling::new().with_max_arg_chars(3);
+        let summary = sampling.summarize(&[fusabi_host::Value::Int(12345)]);
+        assert_eq!(summary.chars().count(), 6);
+        assert!(summary.ends_with("..."));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_jsonl_sink() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let sink = JsonlAuditSink::open(&path).unwrap();
+        sink.record(&AuditRecord::new(
+            "notes",
+            "fs:read",
+            Some("/etc/passwd".to_string()),
+            AuditOutcome::Denied("outside scope".to_string()),
+        ));
+        sink.record(&AuditRecord::new(
+            "notes",
+            "fs:read",
+            Some("/data/notes.txt".to_string()),
+            AuditOutcome::Succeeded,
+        ));
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<serde_json::Value> = content
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["outcome"], "denied");
+        assert_eq!(lines[0]["reason"], "outside scope");
+        assert_eq!(lines[1]["target"], "/data/notes.txt");
+    }
+}
@@ -0,0 +1,144 @@
+//! Authorization of host callers to plugin exports.
+
+use std::fmt;
+
+/// Identity a plugin call is made on behalf of.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub enum Principal {
+    /// The host itself: calls through `PluginRuntime::call`, plugin handles
+    /// and scheduled jobs.
+    ///
+    /// No name maps to it, so a caller cannot pass itself off as the host.
+    #[default]
+    Host,
+    /// A host subsystem or user, such as a service name or an authenticated
+    /// user id.
+    Named(String),
+}
+
+impl Principal {
+    /// Create a named principal.
+    pub fn named(name: impl Into<String>) -> Self {
+        Self::Named(name.into())
+    }
+}
+
+impl fmt::Display for Principal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Host => f.write_str("<host>"),
+            Self::Named(name) => f.write_str(name),
+        }
+    }
+}
+
+/// Decides which principals may call which plugin exports.
+pub trait CallAuthorizer: Send + Sync + fmt::Debug {
+    /// Check if `principal` may call `export` of `plugin`.
+    fn allow(&self, principal: &Principal, plugin: &str, export: &str) -> bool;
+}
+
+/// Allow rule of an [`ExportAcl`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rule {
+    // `None` for the host
+    principal: Option<String>,
+    plugin: String,
+    export: String,
+}
+
+/// Authorizer allowing calls matching a list of rules and denying the rest.
+///
+/// Each part of a rule is either an exact name or `*`; a `*` principal
+/// matches the host too.
+///
+/// ```rust,ignore
+/// use fusabi_plugin_runtime::{CallAuthorizer, ExportAcl, Principal};
+///
+/// let acl = ExportAcl::new()
+///     .grant_host("*", "*")
+///     .grant("billing", "invoices", "*")
+///     .grant("*", "invoices", "summary");
+///
+/// assert!(acl.allow(&Principal::Host, "invoices", "void"));
+/// assert!(acl.allow(&Principal::named("billing"), "invoices", "void"));
+/// assert!(acl.allow(&Principal::named("reports"), "invoices", "summary"));
+/// assert!(!acl.allow(&Principal::named("reports"), "invoices", "void"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ExportAcl {
+    rules: Vec<Rule>,
+}
+
+impl ExportAcl {
+    /// Create an ACL denying every call.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow `principal` to call `export` of `plugin`.
+    pub fn grant(
+        mut self,
+        principal: impl Into<String>,
+        plugin: impl Into<String>,
+        export: impl Into<String>,
+    ) -> Self {
+        self.rules.push(Rule {
+            principal: Some(principal.into()),
+            plugin: plugin.into(),
+            export: export.into(),
+        });
+        self
+    }
+
+    /// Allow the host to call `export` of `plugin`.
+    pub fn grant_host(mut self, plugin: impl Into<String>, export: impl Into<String>) -> Self {
+        self.rules.push(Rule {
+            principal: None,
+            plugin: plugin.into(),
+            export: export.into(),
+        });
+        self
+    }
+}
+
+impl CallAuthorizer for ExportAcl {
+    fn allow(&self, principal: &Principal, plugin: &str, export: &str) -> bool {
+        let matches = |pattern: &str, value: &str| pattern == "*" || pattern == value;
+        self.rules.iter().any(|rule| {
+            let principal_matches = match (rule.principal.as_deref(), principal) {
+                (None, Principal::Host) => true,
+                (Some(pattern), Principal::Named(name)) => matches(pattern, name),
+                (Some(pattern), Principal::Host) => pattern == "*",
+                (None, Principal::Named(_)) => false,
+            };
+            principal_matches && matches(&rule.plugin, plugin) && matches(&rule.export, export)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_acl() {
+        let acl = ExportAcl::new()
+            .grant_host("*", "*")
+            .grant("alice", "notes", "read");
+        let alice = Principal::named("alice");
+
+        assert!(acl.allow(&Principal::Host, "anything", "main"));
+        assert!(acl.allow(&alice, "notes", "read"));
+        assert!(!acl.allow(&alice, "notes", "delete"));
+        assert!(!acl.allow(&Principal::named("bob"), "notes", "read"));
+        assert!(!ExportAcl::new().allow(&Principal::Host, "notes", "read"));
+
+        // Naming a principal after the host does not make it the host
+        assert!(!acl.allow(&Principal::named("host"), "notes", "delete"));
+        assert!(!acl.allow(&Principal::named("<host>"), "notes", "delete"));
+
+        let public = ExportAcl::new().grant("*", "notes", "read");
+        assert!(public.allow(&Principal::Host, "notes", "read"));
+    }
+}
@@ -0,0 +1,437 @@
+//! Execution budgets throttling plugins over sliding windows.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+
+/// What happens to calls made while a plugin is over budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThrottleAction {
+    /// Fail the call with [`Error::BudgetExceeded`](crate::Error::BudgetExceeded).
+    Reject,
+    /// Hold the call until the budget allows it, rejecting it if that takes
+    /// longer than `max_wait`.
+    Queue {
+        /// Longest a call is held.
+        max_wait: Duration,
+    },
+}
+
+/// Calls and call time a plugin may use over sliding windows.
+///
+/// Call time is the CPU time the calling thread spends in the plugin's
+/// exports, where the platform can measure it (Linux), and wall-clock time
+/// otherwise and for plugins running in a worker process. Calls still in
+/// flight count against the time budget at the average time of recent
+/// calls, so concurrent calls cannot all start on the last of the budget.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecutionBudget {
+    /// Calls allowed per window.
+    pub calls: Option<(u32, Duration)>,
+    /// Call time allowed per window.
+    pub time: Option<(Duration, Duration)>,
+    /// What happens to calls once the budget is used up.
+    pub action: ThrottleAction,
+}
+
+impl ExecutionBudget {
+    /// Create a budget without limits that rejects calls when exceeded.
+    pub fn new() -> Self {
+        Self {
+            calls: None,
+            time: None,
+            action: ThrottleAction::Reject,
+        }
+    }
+
+    /// Allow `calls` calls per `window`; zero denies every call.
+    pub fn with_calls(mut self, calls: u32, window: Duration) -> Self {
+        self.calls = Some((calls, window));
+        self
+    }
+
+    /// Allow `time` of call time per `window`.
+    pub fn with_time(mut self, time: Duration, window: Duration) -> Self {
+        self.time = Some((time, window));
+        self
+    }
+
+    /// Hold calls made while over budget for up to `max_wait`.
+    pub fn with_queueing(mut self, max_wait: Duration) -> Self {
+        self.action = ThrottleAction::Queue { max_wait };
+        self
+    }
+}
+
+impl Default for ExecutionBudget {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Use of a plugin's budget within its current windows.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BudgetUsage {
+    /// Calls made within the call window.
+    pub calls: u32,
+    /// Call time used within the time window.
+    pub time: Duration,
+    /// Whether calls are being throttled.
+    pub throttled: bool,
+}
+
+/// Outcome of asking for a call within a budget.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Admission {
+    /// The call may run.
+    Admitted,
+    /// The call may run, and ends a throttling episode.
+    Restored,
+    /// The call must wait or be rejected.
+    Exceeded {
+        /// Exceeded budget: `calls` or `time`.
+        budget: &'static str,
+        /// Time until the budget allows a call again.
+        retry_after: Duration,
+        /// Whether this starts a throttling episode.
+        newly: bool,
+    },
+}
+
+#[derive(Debug, Default)]
+struct Window {
+    calls: VecDeque<Instant>,
+    time: VecDeque<(Instant, Duration)>,
+    // Admission times of calls still running, for time budgets
+    in_flight: Vec<Instant>,
+    throttled: bool,
+}
+
+impl Window {
+    fn prune(&mut self, budget: &ExecutionBudget, now: Instant) {
+        if let Some((_, window)) = budget.calls {
+            while self
+                .calls
+                .front()
+                .is_some_and(|at| now.saturating_duration_since(*at) >= window)
+            {
+                self.calls.pop_front();
+            }
+        }
+        if let Some((_, window)) = budget.time {
+            while self
+                .time
+                .front()
+                .is_some_and(|(at, _)| now.saturating_duration_since(*at) >= window)
+            {
+                self.time.pop_front();
+            }
+        }
+    }
+
+    fn time_used(&self) -> Duration {
+        self.time.iter().map(|(_, spent)| *spent).sum()
+    }
+
+    /// Get the time reserved for each call in flight: the average of the
+    /// calls within the window.
+    fn reserved_per_call(&self) -> Duration {
+        match self.time.len() {
+            0 => Duration::ZERO,
+            n => self.time_used() / n as u32,
+        }
+    }
+
+    /// Get the exceeded budget and how long until it allows a call again.
+    fn exceeded(&self, budget: &ExecutionBudget, now: Instant) -> Option<(&'static str, Duration)> {
+        if let Some((max, window)) = budget.calls {
+            if max == 0 {
+                return Some(("calls", window));
+            }
+            if self.calls.len() >= max as usize {
+                // The oldest calls must leave the window until one slot frees
+                let oldest = self.calls[self.calls.len() - max as usize];
+                return Some(("calls", (oldest + window).saturating_duration_since(now)));
+            }
+        }
+        if let Some((max, window)) = budget.time {
+            let reserved = self.reserved_per_call();
+            let mut used = self.time_used() + reserved * self.in_flight.len() as u32;
+            if used >= max {
+                // Calls in flight free their reservation when they finish
+                let freed_at = self
+                    .time
+                    .iter()
+                    .find(|(_, spent)| {
+                        used = used.saturating_sub(*spent);
+                        used < max
+                    })
+                    .map(|(at, _)| *at + window)
+                    .unwrap_or(now + reserved);
+                return Some(("time", freed_at.saturating_duration_since(now)));
+            }
+        }
+        None
+    }
+}
+
+/// Tracks execution budgets and their use per plugin.
+///
+/// Plugins without a budget, set explicitly or through the default, are
+/// never throttled.
+#[derive(Debug, Default)]
+pub struct BudgetTracker {
+    default_budget: Option<ExecutionBudget>,
+    budgets: DashMap<String, ExecutionBudget>,
+    windows: DashMap<String, Window>,
+}
+
+impl BudgetTracker {
+    /// Create a tracker with an optional default budget.
+    pub fn new(default_budget: Option<ExecutionBudget>) -> Self {
+        Self {
+            default_budget,
+            ..Self::default()
+        }
+    }
+
+    /// Set the budget of a plugin.
+    pub fn set_budget(&self, plugin: impl Into<String>, budget: ExecutionBudget) {
+        self.budgets.insert(plugin.into(), budget);
+    }
+
+    /// Remove the explicit budget of a plugin, falling back to the default.
+    pub fn clear_budget(&self, plugin: &str) {
+        self.budgets.remove(plugin);
+        self.windows.remove(plugin);
+    }
+
+    /// Get the effective budget of a plugin.
+    pub fn budget(&self, plugin: &str) -> Option<ExecutionBudget> {
+        self.budgets
+            .get(plugin)
+            .map(|b| b.clone())
+            .or_else(|| self.default_budget.clone())
+    }
+
+    /// Get a plugin's use of its budget.
+    pub fn usage(&self, plugin: &str) -> BudgetUsage {
+        let Some(budget) = self.budget(plugin) else {
+            return BudgetUsage::default();
+        };
+        let Some(mut window) = self.windows.get_mut(plugin) else {
+            return BudgetUsage::default();
+        };
+        window.prune(&budget, Instant::now());
+        BudgetUsage {
+            calls: window.calls.len() as u32,
+            time: window.time_used(),
+            throttled: window.throttled,
+        }
+    }
+
+    /// Ask for a call at `now`, counting it if admitted.
+    pub(crate) fn admit_at(&self, plugin: &str, now: Instant) -> Admission {
+        let Some(budget) = self.budget(plugin) else {
+            return Admission::Admitted;
+        };
+        let mut window = self.windows.entry(plugin.to_string()).or_default();
+        window.prune(&budget, now);
+
+        if let Some((exceeded, retry_after)) = window.exceeded(&budget, now) {
+            let newly = !window.throttled;
+            window.throttled = true;
+            return Admission::Exceeded {
+                budget: exceeded,
+                retry_after,
+                newly,
+            };
+        }
+        if budget.calls.is_some() {
+            window.calls.push_back(now);
+        }
+        if budget.time.is_some() {
+            window.in_flight.push(now);
+        }
+        if std::mem::take(&mut window.throttled) {
+            Admission::Restored
+        } else {
+            Admission::Admitted
+        }
+    }
+
+    /// Start measuring a call admitted at `admitted_at`; its time is
+    /// charged when the returned charge is dropped.
+    ///
+    /// `cpu` measures the calling thread's CPU time, which is only
+    /// meaningful when the plugin runs on that thread.
+    pub(crate) fn start_call<'a>(
+        &'a self,
+        plugin: &'a str,
+        admitted_at: Instant,
+        cpu: bool,
+    ) -> BudgetCharge<'a> {
+        let cpu_start = if cpu { thread_cpu_time() } else { None };
+        BudgetCharge {
+            tracker: self,
+            plugin,
+            admitted_at,
+            cpu_start,
+        }
+    }
+
+    /// Charge call time to a plugin's budget, ending the call admitted at
+    /// `admitted_at`.
+    pub(crate) fn record_time(&self, plugin: &str, admitted_at: Instant, spent: Duration) {
+        if self.budget(plugin).is_some_and(|b| b.time.is_some()) {
+            let mut window = self.windows.entry(plugin.to_string()).or_default();
+            if let Some(i) = window.in_flight.iter().position(|at| *at == admitted_at) {
+                window.in_flight.swap_remove(i);
+            }
+            window.time.push_back((Instant::now(), spent));
+        }
+    }
+}
+
+/// A call in progress, charged to its plugin's budget when dropped.
+pub(crate) struct BudgetCharge<'a> {
+    tracker: &'a BudgetTracker,
+    plugin: &'a str,
+    admitted_at: Instant,
+    cpu_start: Option<Duration>,
+}
+
+impl Drop for BudgetCharge<'_> {
+    fn drop(&mut self) {
+        let spent = self
+            .cpu_start
+            .and_then(|start| Some(thread_cpu_time()?.saturating_sub(start)))
+            .unwrap_or_else(|| self.admitted_at.elapsed());
+        self.tracker
+            .record_time(self.plugin, self.admitted_at, spent);
+    }
+}
+
+/// Get the CPU time the current thread has used, if the platform reports it.
+#[cfg(target_os = "linux")]
+fn thread_cpu_time() -> Option<Duration> {
+    // The first field is the time spent on the CPU, in nanoseconds
+    let stat = std::fs::read_to_string("/proc/thread-self/schedstat").ok()?;
+    let nanos = stat.split_whitespace().next()?.parse().ok()?;
+    Some(Duration::from_nanos(nanos))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn thread_cpu_time() -> Option<Duration> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_call_budget() {
+        let tracker = BudgetTracker::new(None);
+        tracker.set_budget(
+            "feed",
+            ExecutionBudget::new().with_calls(2, Duration::from_secs(60)),
+        );
+        let now = Instant::now();
+
+        assert_eq!(tracker.admit_at("feed", now), Admission::Admitted);
+        assert_eq!(
+            tracker.admit_at("feed", now + Duration::from_secs(10)),
+            Admission::Admitted
+        );
+        assert_eq!(
+            tracker.admit_at("feed", now + Duration::from_secs(20)),
+            Admission::Exceeded {
+                budget: "calls",
+                retry_after: Duration::from_secs(40),
+                newly: true,
+            }
+        );
+        assert!(matches!(
+            tracker.admit_at("feed", now + Duration::from_secs(30)),
+            Admission::Exceeded { newly: false, .. }
+        ));
+        assert_eq!(
+            tracker.admit_at("feed", now + Duration::from_secs(60)),
+            Admission::Restored
+        );
+
+        // Plugins without a budget are never throttled
+        for _ in 0..10 {
+            assert_eq!(tracker.admit_at("other", now), Admission::Admitted);
+        }
+    }
+
+    #[test]
+    fn test_time_budget() {
+        let tracker = BudgetTracker::new(Some(
+            ExecutionBudget::new().with_time(Duration::from_millis(500), Duration::from_secs(1)),
+        ));
+        let admitted = Instant::now();
+        assert_eq!(tracker.admit_at("feed", admitted), Admission::Admitted);
+        tracker.record_time("feed", admitted, Duration::from_millis(600));
+        assert_eq!(tracker.usage("feed").time, Duration::from_millis(600));
+
+        match tracker.admit_at("feed", Instant::now()) {
+            Admission::Exceeded {
+                budget,
+                retry_after,
+                ..
+            } => {
+                assert_eq!(budget, "time");
+                assert!(retry_after <= Duration::from_secs(1));
+            }
+            other => panic!("unexpected admission: {:?}", other),
+        }
+        assert!(tracker.usage("feed").throttled);
+    }
+
+    #[test]
+    fn test_in_flight_calls_reserve_time() {
+        let tracker = BudgetTracker::new(Some(
+            ExecutionBudget::new().with_time(Duration::from_millis(500), Duration::from_secs(60)),
+        ));
+        let now = Instant::now();
+        assert_eq!(tracker.admit_at("feed", now), Admission::Admitted);
+        tracker.record_time("feed", now, Duration::from_millis(200));
+
+        // Each call in flight holds the 200ms average
+        let first = now + Duration::from_millis(1);
+        assert_eq!(tracker.admit_at("feed", first), Admission::Admitted);
+        let second = now + Duration::from_millis(2);
+        assert_eq!(tracker.admit_at("feed", second), Admission::Admitted);
+        assert!(matches!(
+            tracker.admit_at("feed", now + Duration::from_millis(3)),
+            Admission::Exceeded { budget: "time", .. }
+        ));
+
+        tracker.record_time("feed", first, Duration::from_millis(10));
+        assert_eq!(
+            tracker.admit_at("feed", now + Duration::from_millis(4)),
+            Admission::Restored
+        );
+    }
+
+    #[test]
+    fn test_zero_calls_deny_all() {
+        let tracker = BudgetTracker::new(None);
+        tracker.set_budget(
+            "feed",
+            ExecutionBudget::new().with_calls(0, Duration::from_secs(60)),
+        );
+        assert_eq!(
+            tracker.admit_at("feed", Instant::now()),
+            Admission::Exceeded {
+                budget: "calls",
+                retry_after: Duration::from_secs(60),
+                newly: true,
+            }
+        );
+    }
+}
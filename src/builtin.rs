@@ -0,0 +1,129 @@
+//! Plugins implemented natively in Rust by the host.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+use fusabi_host::Value;
+
+use crate::error::{Error, Result};
+use crate::manifest::{Manifest, ManifestBuilder};
+
+/// A native function exported by a builtin plugin.
+pub type BuiltinFn = Arc<dyn Fn(&[Value]) -> Result<Value> + Send + Sync>;
+
+/// Resolves every call of a builtin plugin by function name.
+pub(crate) type BuiltinDispatch = Arc<dyn Fn(&str, &[Value]) -> Result<Value> + Send + Sync>;
+
+/// A plugin implemented in Rust, registered with
+/// [`PluginRuntime::register_builtin`](crate::PluginRuntime::register_builtin).
+///
+/// Builtins share the registry, routing, lifecycle and metrics of scripted
+/// plugins. Functions named `init`, `cleanup` and `health` serve as the
+/// usual lifecycle hooks.
+#[derive(Clone)]
+pub struct BuiltinPlugin {
+    version: String,
+    description: Option<String>,
+    functions: BTreeMap<String, BuiltinFn>,
+    dispatch: Option<BuiltinDispatch>,
+}
+
+impl BuiltinPlugin {
+    /// Create a builtin plugin with no functions.
+    pub fn new(version: impl Into<String>) -> Self {
+        Self {
+            version: version.into(),
+            description: None,
+            functions: BTreeMap::new(),
+            dispatch: None,
+        }
+    }
+
+    /// Create a builtin plugin passing calls of functions it does not
+    /// export to `dispatch`, for exports added after it was attached.
+    #[cfg(any(test, feature = "testing"))]
+    pub(crate) fn dispatching(version: impl Into<String>, dispatch: BuiltinDispatch) -> Self {
+        Self {
+            dispatch: Some(dispatch),
+            ..Self::new(version)
+        }
+    }
+
+    /// Set the description.
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Export a function.
+    pub fn with_function<F>(mut self, name: impl Into<String>, function: F) -> Self
+    where
+        F: Fn(&[Value]) -> Result<Value> + Send + Sync + 'static,
+    {
+        self.functions.insert(name.into(), Arc::new(function));
+        self
+    }
+
+    /// Get the plugin version.
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Get the names of the exported functions, sorted.
+    pub fn functions(&self) -> impl Iterator<Item = &str> {
+        self.functions.keys().map(String::as_str)
+    }
+
+    /// Build the manifest describing the plugin.
+    pub(crate) fn manifest(&self, name: &str) -> Manifest {
+        let mut builder = ManifestBuilder::new(name, self.version.clone())
+            .exports(self.functions.keys().cloned());
+        if let Some(ref description) = self.description {
+            builder = builder.description(description.clone());
+        }
+        builder.build_unchecked()
+    }
+
+    /// Call an exported function.
+    pub(crate) fn call(&self, function: &str, args: &[Value]) -> Result<Value> {
+        match (self.functions.get(function), &self.dispatch) {
+            (Some(f), _) => f(args),
+            (None, Some(dispatch)) => dispatch(function, args),
+            (None, None) => Err(Error::FunctionNotFound(function.to_string())),
+        }
+    }
+}
+
+impl fmt::Debug for BuiltinPlugin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BuiltinPlugin")
+            .field("version", &self.version)
+            .field("functions", &self.functions.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_plugin() {
+        let plugin = BuiltinPlugin::new("1.0.0")
+            .with_description("JSON helpers")
+            .with_function("len", |args| match args {
+                [Value::String(s)] => Ok(Value::Int(s.len() as i64)),
+                _ => Err(Error::invalid_arguments("expected a string")),
+            });
+
+        let manifest = plugin.manifest("json-tools");
+        assert_eq!(manifest.name, "json-tools");
+        assert!(manifest.has_export("len"));
+        assert_eq!(manifest.description.as_deref(), Some("JSON helpers"));
+
+        let len = plugin.call("len", &[Value::String("abc".into())]).unwrap();
+        assert!(matches!(len, Value::Int(3)));
+        assert!(plugin.call("missing", &[]).is_err());
+    }
+}
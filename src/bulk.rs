@@ -0,0 +1,215 @@
+//! Bulk operations with progress reporting.
+
+use std::path::PathBuf;
+
+use crate::cancel::CancellationToken;
+use crate::error::{Error, Result};
+
+/// A single operation in a bulk request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BulkOp {
+    /// Load a plugin from a manifest, source or bytecode file.
+    Load(PathBuf),
+    /// Unload a plugin.
+    Unload(String),
+    /// Start a plugin.
+    Start(String),
+    /// Stop a plugin.
+    Stop(String),
+    /// Reload a plugin.
+    Reload(String),
+}
+
+impl BulkOp {
+    /// Load a plugin file.
+    pub fn load(path: impl Into<PathBuf>) -> Self {
+        Self::Load(path.into())
+    }
+
+    /// Unload a plugin.
+    pub fn unload(name: impl Into<String>) -> Self {
+        Self::Unload(name.into())
+    }
+
+    /// Start a plugin.
+    pub fn start(name: impl Into<String>) -> Self {
+        Self::Start(name.into())
+    }
+
+    /// Stop a plugin.
+    pub fn stop(name: impl Into<String>) -> Self {
+        Self::Stop(name.into())
+    }
+
+    /// Reload a plugin.
+    pub fn reload(name: impl Into<String>) -> Self {
+        Self::Reload(name.into())
+    }
+
+    /// Get the operation name.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Load(_) => "load",
+            Self::Unload(_) => "unload",
+            Self::Start(_) => "start",
+            Self::Stop(_) => "stop",
+            Self::Reload(_) => "reload",
+        }
+    }
+
+    /// Get the plugin name or path the operation applies to.
+    pub fn target(&self) -> String {
+        match self {
+            Self::Load(path) => path.display().to_string(),
+            Self::Unload(name) | Self::Start(name) | Self::Stop(name) | Self::Reload(name) => {
+                name.clone()
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for BulkOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.name(), self.target())
+    }
+}
+
+/// What to do with the remaining operations after one fails.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BulkPolicy {
+    /// Run every operation and report failures at the end.
+    #[default]
+    ContinueOnError,
+    /// Skip the remaining operations after the first failure.
+    StopOnError,
+}
+
+/// Result of a single bulk operation.
+#[derive(Debug)]
+pub enum BulkOutcome {
+    /// The operation succeeded.
+    Succeeded,
+    /// The operation failed.
+    Failed(Error),
+    /// The operation was not run because an earlier one failed.
+    Skipped,
+    /// The operation was not run because the request was cancelled.
+    Cancelled,
+}
+
+impl BulkOutcome {
+    /// Check if the operation succeeded.
+    pub fn is_success(&self) -> bool {
+        matches!(self, Self::Succeeded)
+    }
+}
+
+/// Progress of a bulk request, reported after each operation.
+#[derive(Debug)]
+pub struct BulkProgress<'a> {
+    /// Number of operations finished so far, including this one.
+    pub completed: usize,
+    /// Total number of operations.
+    pub total: usize,
+    /// The operation that just finished.
+    pub op: &'a BulkOp,
+    /// How it ended.
+    pub outcome: &'a BulkOutcome,
+}
+
+/// Results of a bulk request, in submission order.
+#[derive(Debug, Default)]
+pub struct BulkReport {
+    /// Each operation with its outcome.
+    pub results: Vec<(BulkOp, BulkOutcome)>,
+}
+
+impl BulkReport {
+    /// Check if every operation succeeded.
+    pub fn is_success(&self) -> bool {
+        self.results.iter().all(|(_, outcome)| outcome.is_success())
+    }
+
+    /// Count the succeeded operations.
+    pub fn succeeded(&self) -> usize {
+        self.count(|o| matches!(o, BulkOutcome::Succeeded))
+    }
+
+    /// Count the failed operations.
+    pub fn failed(&self) -> usize {
+        self.count(|o| matches!(o, BulkOutcome::Failed(_)))
+    }
+
+    /// Count the skipped operations.
+    pub fn skipped(&self) -> usize {
+        self.count(|o| matches!(o, BulkOutcome::Skipped))
+    }
+
+    /// Count the operations not run because the request was cancelled.
+    pub fn cancelled(&self) -> usize {
+        self.count(|o| matches!(o, BulkOutcome::Cancelled))
+    }
+
+    /// Check if the request was cancelled before every operation ran.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled() > 0
+    }
+
+    /// Get the failed operations with their errors.
+    pub fn failures(&self) -> impl Iterator<Item = (&BulkOp, &Error)> {
+        self.results
+            .iter()
+            .filter_map(|(op, outcome)| match outcome {
+                BulkOutcome::Failed(e) => Some((op, e)),
+                _ => None,
+            })
+    }
+
+    fn count(&self, f: impl Fn(&BulkOutcome) -> bool) -> usize {
+        self.results.iter().filter(|(_, o)| f(o)).count()
+    }
+}
+
+/// Apply operations in order with `apply` until `token` is cancelled.
+pub(crate) fn run<F>(
+    ops: impl IntoIterator<Item = BulkOp>,
+    policy: BulkPolicy,
+    token: &CancellationToken,
+    mut on_progress: F,
+    mut apply: impl FnMut(&BulkOp) -> Result<()>,
+) -> BulkReport
+where
+    F: FnMut(&BulkProgress<'_>),
+{
+    let ops: Vec<BulkOp> = ops.into_iter().collect();
+    let total = ops.len();
+    let mut report = BulkReport::default();
+    let mut failed = false;
+
+    for (i, op) in ops.into_iter().enumerate() {
+        let outcome = if token.is_cancelled() {
+            BulkOutcome::Cancelled
+        } else if failed && policy == BulkPolicy::StopOnError {
+            BulkOutcome::Skipped
+        } else {
+            match apply(&op) {
+                Ok(()) => BulkOutcome::Succeeded,
+                Err(e) => {
+                    tracing::warn!("Bulk operation {} failed: {}", op, e);
+                    failed = true;
+                    BulkOutcome::Failed(e)
+                }
+            }
+        };
+
+        on_progress(&BulkProgress {
+            completed: i + 1,
+            total,
+            op: &op,
+            outcome: &outcome,
+        });
+        report.results.push((op, outcome));
+    }
+
+    report
+}
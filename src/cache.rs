@@ -0,0 +1,235 @@
+//! Compiled bytecode cache keyed by source hash.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use dashmap::DashMap;
+use fusabi_host::CompileOptions;
+
+use crate::crypto::sha256_hex;
+use crate::error::Result;
+
+/// Cache statistics.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Lookups served from the cache.
+    pub hits: u64,
+    /// Lookups that required compilation.
+    pub misses: u64,
+    /// Entries currently held in memory.
+    pub entries: usize,
+}
+
+/// Cache of compiled bytecode keyed by a hash of the source and compile options.
+///
+/// Entries are always kept in memory; when a directory is configured they
+/// are also persisted as `<hash>.fzb` files so that they survive restarts.
+pub struct BytecodeCache {
+    dir: Option<PathBuf>,
+    entries: DashMap<String, Arc<Vec<u8>>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl BytecodeCache {
+    /// Create an in-memory cache.
+    pub fn in_memory() -> Self {
+        Self {
+            dir: None,
+            entries: DashMap::new(),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Create a cache persisted in a directory.
+    pub fn with_directory(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+
+        Ok(Self {
+            dir: Some(dir),
+            ..Self::in_memory()
+        })
+    }
+
+    /// Get the cache directory, if persisted.
+    pub fn directory(&self) -> Option<&Path> {
+        self.dir.as_deref()
+    }
+
+    /// Compute the cache key for a source under the given compile options.
+    ///
+    /// Keys include the engine version, so entries compiled by an older
+    /// engine are never reused after an upgrade.
+    pub fn key(source: &str, options: &CompileOptions) -> String {
+        let mut input = crate::VERSION.to_string();
+        if let Some(engine) = crate::host_info::engine_version() {
+            input.push_str(&engine);
+        }
+        input.push_str(&format!("{:?}", options));
+        input.push_str(source);
+        sha256_hex(input.as_bytes())
+    }
+
+    /// Look up bytecode by key.
+    pub fn get(&self, key: &str) -> Option<Vec<u8>> {
+        if let Some(entry) = self.entries.get(key) {
+            return Some(entry.as_ref().clone());
+        }
+
+        let path = self.entry_path(key)?;
+        let bytecode = std::fs::read(path).ok()?;
+        self.entries
+            .insert(key.to_string(), Arc::new(bytecode.clone()));
+        Some(bytecode)
+    }
+
+    /// Store bytecode under a key.
+    pub fn insert(&self, key: &str, bytecode: Vec<u8>) -> Result<()> {
+        if let Some(path) = self.entry_path(key) {
+            // Write through a temporary file so readers never see partial entries
+            let tmp = path.with_extension("fzb.tmp");
+            std::fs::write(&tmp, &bytecode)?;
+            std::fs::rename(&tmp, &path)?;
+        }
+
+        self.entries.insert(key.to_string(), Arc::new(bytecode));
+        Ok(())
+    }
+
+    /// Get cached bytecode for a source, compiling it on a miss.
+    pub fn get_or_compile<F>(
+        &self,
+        source: &str,
+        options: &CompileOptions,
+        compile: F,
+    ) -> Result<Vec<u8>>
+    where
+        F: FnOnce() -> Result<Vec<u8>>,
+    {
+        let key = Self::key(source, options);
+
+        if let Some(bytecode) = self.get(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(bytecode);
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let bytecode = compile()?;
+        if let Err(e) = self.insert(&key, bytecode.clone()) {
+            tracing::warn!("Failed to persist bytecode cache entry {}: {}", key, e);
+        }
+
+        Ok(bytecode)
+    }
+
+    /// Remove a single entry.
+    pub fn remove(&self, key: &str) {
+        self.entries.remove(key);
+        if let Some(path) = self.entry_path(key) {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
+    /// Remove all entries from memory and disk.
+    pub fn clear(&self) -> Result<()> {
+        let keys: Vec<String> = self.entries.iter().map(|e| e.key().clone()).collect();
+        for key in keys {
+            self.remove(&key);
+        }
+
+        if let Some(ref dir) = self.dir {
+            for entry in std::fs::read_dir(dir)? {
+                let path = entry?.path();
+                if path.extension().is_some_and(|e| e == "fzb") {
+                    std::fs::remove_file(path)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Get cache statistics.
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.entries.len(),
+        }
+    }
+
+    fn entry_path(&self, key: &str) -> Option<PathBuf> {
+        self.dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{}.fzb", key)))
+    }
+}
+
+impl std::fmt::Debug for BytecodeCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BytecodeCache")
+            .field("dir", &self.dir)
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_depends_on_source() {
+        let options = CompileOptions::default();
+        let a = BytecodeCache::key("let x = 1", &options);
+        let b = BytecodeCache::key("let x = 2", &options);
+
+        assert_eq!(a, BytecodeCache::key("let x = 1", &options));
+        assert_ne!(a, b);
+        assert_eq!(a.len(), 64);
+    }
+
+    #[test]
+    fn test_get_or_compile() {
+        let cache = BytecodeCache::in_memory();
+        let options = CompileOptions::default();
+        let mut compiles = 0;
+
+        for _ in 0..3 {
+            let bytecode = cache
+                .get_or_compile("source", &options, || {
+                    compiles += 1;
+                    Ok(vec![1, 2, 3])
+                })
+                .unwrap();
+            assert_eq!(bytecode, vec![1, 2, 3]);
+        }
+
+        assert_eq!(compiles, 1);
+        let stats = cache.stats();
+        assert_eq!(stats.hits, 2);
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.entries, 1);
+    }
+
+    #[test]
+    fn test_directory_cache_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let options = CompileOptions::default();
+        let key = BytecodeCache::key("source", &options);
+
+        BytecodeCache::with_directory(dir.path())
+            .unwrap()
+            .insert(&key, vec![9, 9])
+            .unwrap();
+
+        let reopened = BytecodeCache::with_directory(dir.path()).unwrap();
+        assert_eq!(reopened.get(&key), Some(vec![9, 9]));
+
+        reopened.clear().unwrap();
+        assert!(reopened.get(&key).is_none());
+    }
+}
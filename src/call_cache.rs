@@ -0,0 +1,217 @@
+//! Memoization of plugin call results.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use fusabi_host::Value;
+use parking_lot::Mutex;
+
+use crate::error::{Error, Result};
+use crate::signature::ExportSpec;
+
+/// Maximum number of cached results per plugin.
+const MAX_ENTRIES: usize = 1024;
+
+/// Export whose results may be cached.
+///
+/// ```toml
+/// [cache]
+/// exports = [
+///     { name = "format_date", ttl-secs = 300 },
+///     { name = "render", key-args = [0] },
+/// ]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CacheableExport {
+    /// Exported function name.
+    pub name: String,
+    /// Seconds a result stays valid; cached until reload when unset.
+    #[cfg_attr(feature = "serde", serde(rename = "ttl-secs", default))]
+    pub ttl_secs: Option<u64>,
+    /// Indices of the arguments forming the cache key; all when unset.
+    #[cfg_attr(feature = "serde", serde(rename = "key-args", default))]
+    pub key_args: Option<Vec<usize>>,
+}
+
+impl CacheableExport {
+    /// Cache results of an export until the plugin is reloaded.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ttl_secs: None,
+            key_args: None,
+        }
+    }
+
+    /// Expire results after `ttl`.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl_secs = Some(ttl.as_secs());
+        self
+    }
+
+    /// Derive the cache key from these argument positions only.
+    pub fn with_key_args(mut self, indices: impl IntoIterator<Item = usize>) -> Self {
+        self.key_args = Some(indices.into_iter().collect());
+        self
+    }
+
+    /// Get the time-to-live of cached results.
+    pub fn ttl(&self) -> Option<Duration> {
+        self.ttl_secs.map(Duration::from_secs)
+    }
+
+    /// Derive the cache key for a call.
+    pub fn key(&self, args: &[Value]) -> String {
+        match self.key_args {
+            Some(ref indices) => {
+                let selected: Vec<_> = indices.iter().map(|&i| args.get(i)).collect();
+                format!("{:?}", selected)
+            }
+            None => format!("{:?}", args),
+        }
+    }
+
+    /// Check the declaration against the plugin's exports.
+    pub fn validate(&self, exports: &[ExportSpec]) -> Result<()> {
+        if !exports.is_empty() && !exports.iter().any(|e| *e == self.name) {
+            return Err(Error::invalid_manifest(format!(
+                "cacheable function {} is not exported",
+                self.name
+            )));
+        }
+        if self.ttl_secs == Some(0) {
+            return Err(Error::invalid_manifest(format!(
+                "cacheable function {} has a zero TTL",
+                self.name
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// The `[cache]` table of a manifest.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CallCacheConfig {
+    /// Exports whose results may be cached.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub exports: Vec<CacheableExport>,
+}
+
+impl CallCacheConfig {
+    /// Check if no exports are cacheable.
+    pub fn is_empty(&self) -> bool {
+        self.exports.is_empty()
+    }
+
+    /// Find the cache declaration of an export.
+    pub fn export(&self, name: &str) -> Option<&CacheableExport> {
+        self.exports.iter().find(|e| e.name == name)
+    }
+}
+
+/// Call cache statistics.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CallCacheStats {
+    /// Calls answered from the cache.
+    pub hits: u64,
+    /// Cacheable calls that ran the plugin.
+    pub misses: u64,
+    /// Results currently cached.
+    pub entries: usize,
+}
+
+/// Cached result with its expiry, keyed by function and argument key.
+type Entries = HashMap<(String, String), (Value, Option<Instant>)>;
+
+/// Per-plugin store of cached call results.
+#[derive(Debug, Default)]
+pub(crate) struct CallCache {
+    entries: Mutex<Entries>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl CallCache {
+    /// Look up a cached result, dropping it if expired.
+    pub(crate) fn get(&self, function: &str, key: &str) -> Option<Value> {
+        let mut entries = self.entries.lock();
+        let id = (function.to_string(), key.to_string());
+        let hit = match entries.get(&id) {
+            Some((_, Some(expires))) if *expires <= Instant::now() => {
+                entries.remove(&id);
+                None
+            }
+            Some((value, _)) => Some(value.clone()),
+            None => None,
+        };
+
+        let counter = if hit.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        hit
+    }
+
+    /// Store a result.
+    pub(crate) fn insert(&self, function: &str, key: String, value: Value, ttl: Option<Duration>) {
+        let now = Instant::now();
+        let mut entries = self.entries.lock();
+        if entries.len() >= MAX_ENTRIES {
+            entries.retain(|_, (_, expires)| expires.map_or(true, |at| at > now));
+            if entries.len() >= MAX_ENTRIES {
+                entries.clear();
+            }
+        }
+        entries.insert(
+            (function.to_string(), key),
+            (value, ttl.map(|ttl| now + ttl)),
+        );
+    }
+
+    /// Drop every cached result.
+    pub(crate) fn clear(&self) {
+        self.entries.lock().clear();
+    }
+
+    /// Get cache statistics.
+    pub(crate) fn stats(&self) -> CallCacheStats {
+        CallCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.entries.lock().len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_keys_and_expiry() {
+        let export = CacheableExport::new("render").with_key_args([0]);
+        let a = export.key(&[Value::Int(1), Value::Int(2)]);
+        let b = export.key(&[Value::Int(1), Value::Int(3)]);
+        let c = export.key(&[Value::Int(2), Value::Int(2)]);
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+
+        let cache = CallCache::default();
+        assert!(cache.get("render", &a).is_none());
+        cache.insert("render", a.clone(), Value::Int(7), None);
+        assert!(matches!(cache.get("render", &b), Some(Value::Int(7))));
+
+        cache.insert("render", c.clone(), Value::Null, Some(Duration::ZERO));
+        assert!(cache.get("render", &c).is_none());
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 2, 1));
+    }
+}
@@ -0,0 +1,97 @@
+//! Cooperative cancellation of long-running operations.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::error::{Error, Result};
+
+/// Token shared between a long-running operation and whoever may abort it.
+///
+/// Clones share the same flag. Operations check the token between steps,
+/// so a step that is already running is allowed to finish.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Create a token that is not cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Check if cancellation was requested.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Fail with [`Error::Cancelled`] if cancellation was requested.
+    pub fn check(&self, operation: &str) -> Result<()> {
+        if self.is_cancelled() {
+            Err(Error::cancelled(operation))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Results gathered by an operation that may have been cancelled.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Partial<T> {
+    /// Results produced before the operation finished or was cancelled.
+    pub value: T,
+    /// Whether the operation stopped early because it was cancelled.
+    pub cancelled: bool,
+}
+
+impl<T> Partial<T> {
+    /// Wrap the results of an operation that ran to completion.
+    pub fn complete(value: T) -> Self {
+        Self {
+            value,
+            cancelled: false,
+        }
+    }
+
+    /// Wrap the results of an operation that was cancelled.
+    pub fn cancelled(value: T) -> Self {
+        Self {
+            value,
+            cancelled: true,
+        }
+    }
+
+    /// Check if the operation ran to completion.
+    pub fn is_complete(&self) -> bool {
+        !self.cancelled
+    }
+
+    /// Take the results, ignoring whether the operation was cancelled.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancellation_token() {
+        let token = CancellationToken::new();
+        let shared = token.clone();
+        assert!(token.check("discover").is_ok());
+
+        shared.cancel();
+        assert!(token.is_cancelled());
+        assert!(matches!(
+            token.check("discover"),
+            Err(Error::Cancelled(op)) if op == "discover"
+        ));
+    }
+}
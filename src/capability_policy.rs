@@ -0,0 +1,152 @@
+//! Policies deciding on capabilities that are not pre-granted.
+
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::manifest::CapabilitySpec;
+
+/// A capability requested by a plugin's manifest.
+#[derive(Debug, Clone, Copy)]
+pub struct CapabilityRequest<'a> {
+    /// Requesting plugin.
+    pub plugin: &'a str,
+    /// Version of the requesting plugin.
+    pub version: &'a str,
+    /// Requested capability, including its scopes.
+    pub capability: &'a CapabilitySpec,
+}
+
+/// Outcome of a [`CapabilityPolicy`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CapabilityDecision {
+    /// Grant the capability.
+    Grant,
+    /// Refuse the capability, failing the load.
+    Deny(String),
+}
+
+/// Decides on capabilities a manifest requests beyond the loader's engine
+/// configuration.
+///
+/// Capabilities already granted by `LoaderConfig::engine_config` are never
+/// passed to the policy.
+pub trait CapabilityPolicy: Send + Sync + fmt::Debug {
+    /// Decide whether to grant a requested capability.
+    fn decide(&self, request: &CapabilityRequest<'_>) -> CapabilityDecision;
+}
+
+/// Denies every capability that is not pre-granted.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DenyAll;
+
+impl CapabilityPolicy for DenyAll {
+    fn decide(&self, request: &CapabilityRequest<'_>) -> CapabilityDecision {
+        CapabilityDecision::Deny(format!("{} is not pre-granted", request.capability.name))
+    }
+}
+
+/// Grants capabilities on a list and denies the rest.
+#[derive(Debug, Clone, Default)]
+pub struct AllowList {
+    capabilities: HashSet<String>,
+}
+
+impl AllowList {
+    /// Create an empty allowlist.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow a capability by name.
+    pub fn allow(mut self, capability: impl Into<String>) -> Self {
+        self.capabilities.insert(capability.into());
+        self
+    }
+
+    /// Check if a capability is allowed.
+    pub fn contains(&self, capability: &str) -> bool {
+        self.capabilities.contains(capability)
+    }
+}
+
+impl CapabilityPolicy for AllowList {
+    fn decide(&self, request: &CapabilityRequest<'_>) -> CapabilityDecision {
+        if self.contains(&request.capability.name) {
+            CapabilityDecision::Grant
+        } else {
+            CapabilityDecision::Deny(format!("{} is not allowlisted", request.capability.name))
+        }
+    }
+}
+
+type Prompt = dyn Fn(&CapabilityRequest<'_>) -> CapabilityDecision + Send + Sync;
+
+/// Asks the embedding application, e.g. by showing a dialog.
+///
+/// The callback runs on the loading thread and blocks the load until it
+/// returns.
+pub struct PromptPolicy {
+    prompt: Box<Prompt>,
+}
+
+impl PromptPolicy {
+    /// Create a policy calling `prompt` for each request.
+    pub fn new<F>(prompt: F) -> Self
+    where
+        F: Fn(&CapabilityRequest<'_>) -> CapabilityDecision + Send + Sync + 'static,
+    {
+        Self {
+            prompt: Box::new(prompt),
+        }
+    }
+}
+
+impl CapabilityPolicy for PromptPolicy {
+    fn decide(&self, request: &CapabilityRequest<'_>) -> CapabilityDecision {
+        (self.prompt)(request)
+    }
+}
+
+impl fmt::Debug for PromptPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PromptPolicy").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policies() {
+        let net = CapabilitySpec::new("net:request").with_hosts(["api.example.com"]);
+        let request = CapabilityRequest {
+            plugin: "weather",
+            version: "1.0.0",
+            capability: &net,
+        };
+
+        assert!(matches!(
+            DenyAll.decide(&request),
+            CapabilityDecision::Deny(_)
+        ));
+
+        let allow = AllowList::new().allow("net:request");
+        assert_eq!(allow.decide(&request), CapabilityDecision::Grant);
+        assert!(!AllowList::new().allow("fs:read").contains("net:request"));
+
+        let prompt = PromptPolicy::new(|request| {
+            if request
+                .capability
+                .hosts
+                .iter()
+                .all(|h| h.ends_with(".example.com"))
+            {
+                CapabilityDecision::Grant
+            } else {
+                CapabilityDecision::Deny("user declined".into())
+            }
+        });
+        assert_eq!(prompt.decide(&request), CapabilityDecision::Grant);
+    }
+}
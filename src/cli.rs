@@ -0,0 +1,312 @@
+//! Plugin-provided command-line subcommands.
+
+use fusabi_host::Value;
+
+use crate::error::{Error, Result};
+use crate::signature::ExportSpec;
+
+/// Type of a command argument.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum CliArgKind {
+    /// Passed through as a string.
+    #[default]
+    String,
+    /// Parsed as an integer.
+    Int,
+    /// Parsed as a float.
+    Float,
+    /// A `--name` flag, `true` when present.
+    Bool,
+    /// Comma-separated strings.
+    List,
+}
+
+impl CliArgKind {
+    /// Get the type name.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::String => "string",
+            Self::Int => "int",
+            Self::Float => "float",
+            Self::Bool => "bool",
+            Self::List => "list",
+        }
+    }
+
+    /// Parse a raw argument into a value of this type.
+    pub fn parse(&self, raw: &str) -> Result<Value> {
+        let invalid =
+            || Error::invalid_arguments(format!("expected {}, got {:?}", self.name(), raw));
+        match self {
+            Self::String => Ok(Value::String(raw.to_string())),
+            Self::Int => raw.parse().map(Value::Int).map_err(|_| invalid()),
+            Self::Float => raw.parse().map(Value::Float).map_err(|_| invalid()),
+            Self::Bool => raw.parse().map(Value::Bool).map_err(|_| invalid()),
+            Self::List => Ok(Value::List(
+                raw.split(',')
+                    .filter(|s| !s.is_empty())
+                    .map(|s| Value::String(s.trim().to_string()))
+                    .collect(),
+            )),
+        }
+    }
+}
+
+/// Declared argument of a command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CliArg {
+    /// Argument name, used as `--name`.
+    pub name: String,
+    /// Argument type.
+    #[cfg_attr(feature = "serde", serde(rename = "type", default))]
+    pub kind: CliArgKind,
+    /// Whether the argument must be given.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub required: bool,
+    /// Value used when the argument is omitted.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub default: Option<String>,
+    /// Help text.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub help: Option<String>,
+}
+
+impl CliArg {
+    /// Create an optional argument.
+    pub fn new(name: impl Into<String>, kind: CliArgKind) -> Self {
+        Self {
+            name: name.into(),
+            kind,
+            required: false,
+            default: None,
+            help: None,
+        }
+    }
+
+    /// Mark the argument as required.
+    pub fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    /// Set the default value.
+    pub fn with_default(mut self, default: impl Into<String>) -> Self {
+        self.default = Some(default.into());
+        self
+    }
+}
+
+/// Subcommand declared in a plugin manifest.
+///
+/// ```toml
+/// [cli]
+/// commands = [{ name = "report", export = "run_report", args = [
+///     { name = "format", default = "text" },
+///     { name = "limit", type = "int" },
+/// ] }]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CliCommand {
+    /// Subcommand name.
+    pub name: String,
+    /// Exported function invoked by the command.
+    pub export: String,
+    /// Help text.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub description: Option<String>,
+    /// Declared arguments, passed to the export in this order.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub args: Vec<CliArg>,
+}
+
+impl CliCommand {
+    /// Create a command invoking an export.
+    pub fn new(name: impl Into<String>, export: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            export: export.into(),
+            description: None,
+            args: Vec::new(),
+        }
+    }
+
+    /// Declare an argument.
+    pub fn with_arg(mut self, arg: CliArg) -> Self {
+        self.args.push(arg);
+        self
+    }
+
+    /// Render a one-line usage string.
+    pub fn usage(&self) -> String {
+        let mut usage = self.name.clone();
+        for arg in &self.args {
+            let part = match arg.kind {
+                CliArgKind::Bool => format!("--{}", arg.name),
+                kind => format!("--{} <{}>", arg.name, kind.name()),
+            };
+            if arg.required {
+                usage.push_str(&format!(" {}", part));
+            } else {
+                usage.push_str(&format!(" [{}]", part));
+            }
+        }
+        usage
+    }
+
+    /// Parse raw command-line arguments into call arguments.
+    ///
+    /// Declared arguments are accepted as `--name value`, `--name=value` or
+    /// positionally in declaration order; bool flags take no value. Omitted
+    /// arguments use their default or `Value::Null`. A command without
+    /// declared arguments receives all raw arguments as one list of strings.
+    pub fn parse_args(&self, raw: &[String]) -> Result<Vec<Value>> {
+        if self.args.is_empty() {
+            return Ok(vec![Value::List(
+                raw.iter().cloned().map(Value::String).collect(),
+            )]);
+        }
+
+        let mut values: Vec<Option<Value>> = vec![None; self.args.len()];
+        let mut raw = raw.iter();
+
+        while let Some(token) = raw.next() {
+            if let Some(flag) = token.strip_prefix("--") {
+                let (name, inline) = match flag.split_once('=') {
+                    Some((name, value)) => (name, Some(value.to_string())),
+                    None => (flag, None),
+                };
+                let index = self
+                    .args
+                    .iter()
+                    .position(|a| a.name == name)
+                    .ok_or_else(|| {
+                        Error::invalid_arguments(format!("unknown option --{}", name))
+                    })?;
+                let arg = &self.args[index];
+                let value = match (inline, arg.kind) {
+                    (Some(value), _) => value,
+                    (None, CliArgKind::Bool) => "true".to_string(),
+                    (None, _) => raw.next().cloned().ok_or_else(|| {
+                        Error::invalid_arguments(format!("--{} requires a value", name))
+                    })?,
+                };
+                values[index] = Some(arg.kind.parse(&value)?);
+            } else {
+                let index = (0..self.args.len())
+                    .find(|&i| values[i].is_none() && self.args[i].kind != CliArgKind::Bool)
+                    .ok_or_else(|| {
+                        Error::invalid_arguments(format!("unexpected argument {:?}", token))
+                    })?;
+                values[index] = Some(self.args[index].kind.parse(token)?);
+            }
+        }
+
+        self.args
+            .iter()
+            .zip(values)
+            .map(|(arg, value)| match (value, &arg.default) {
+                (Some(value), _) => Ok(value),
+                (None, Some(default)) => arg.kind.parse(default),
+                (None, None) if arg.required => Err(Error::invalid_arguments(format!(
+                    "missing required argument --{}",
+                    arg.name
+                ))),
+                (None, None) if arg.kind == CliArgKind::Bool => Ok(Value::Bool(false)),
+                (None, None) => Ok(Value::Null),
+            })
+            .collect()
+    }
+
+    /// Check the command declaration against the plugin's exports.
+    pub fn validate(&self, exports: &[ExportSpec]) -> Result<()> {
+        if self.name.is_empty() || self.name.contains(char::is_whitespace) {
+            return Err(Error::invalid_manifest(format!(
+                "invalid command name: {:?}",
+                self.name
+            )));
+        }
+        if !exports.is_empty() && !exports.iter().any(|e| *e == self.export) {
+            return Err(Error::invalid_manifest(format!(
+                "command {} invokes unexported function {}",
+                self.name, self.export
+            )));
+        }
+        for (i, arg) in self.args.iter().enumerate() {
+            if self.args[..i].iter().any(|a| a.name == arg.name) {
+                return Err(Error::invalid_manifest(format!(
+                    "command {} declares argument {} twice",
+                    self.name, arg.name
+                )));
+            }
+            if let Some(default) = &arg.default {
+                arg.kind.parse(default)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The `[cli]` table of a manifest.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CliConfig {
+    /// Declared subcommands.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub commands: Vec<CliCommand>,
+}
+
+impl CliConfig {
+    /// Check if no commands are declared.
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Find a command by name.
+    pub fn command(&self, name: &str) -> Option<&CliCommand> {
+        self.commands.iter().find(|c| c.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(raw: &[&str]) -> Vec<String> {
+        raw.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let command = CliCommand::new("report", "run_report")
+            .with_arg(CliArg::new("path", CliArgKind::String).required())
+            .with_arg(CliArg::new("limit", CliArgKind::Int).with_default("10"))
+            .with_arg(CliArg::new("verbose", CliArgKind::Bool))
+            .with_arg(CliArg::new("tags", CliArgKind::List));
+
+        let values = command
+            .parse_args(&args(&["--verbose", "out.txt", "--tags=a,b"]))
+            .unwrap();
+        assert!(matches!(&values[0], Value::String(s) if s == "out.txt"));
+        assert!(matches!(values[1], Value::Int(10)));
+        assert!(matches!(values[2], Value::Bool(true)));
+        assert!(matches!(&values[3], Value::List(tags) if tags.len() == 2));
+
+        assert!(command.parse_args(&args(&["--limit", "5"])).is_err());
+        assert!(command
+            .parse_args(&args(&["x", "--limit", "many"]))
+            .is_err());
+        assert!(command.parse_args(&args(&["x", "--unknown"])).is_err());
+        assert_eq!(
+            command.usage(),
+            "report --path <string> [--limit <int>] [--verbose] [--tags <list>]"
+        );
+    }
+}
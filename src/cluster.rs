@@ -0,0 +1,512 @@
+//! Shared plugin installation state across host instances.
+
+use std::collections::HashMap;
+use std::fmt;
+#[cfg(feature = "serde")]
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+#[cfg(feature = "serde")]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use parking_lot::{Mutex, RwLock};
+
+use crate::error::{Error, Result};
+
+/// Age after which a state or lease lock is considered abandoned.
+#[cfg(feature = "serde")]
+const STALE_LOCK: Duration = Duration::from_secs(10);
+
+/// How long a write waits for the state lock of [`FileClusterBackend`].
+#[cfg(feature = "serde")]
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Attempts `publish` and `retract` make before giving up on a state that
+/// keeps changing under them.
+const MAX_CAS_ATTEMPTS: usize = 16;
+
+/// A plugin every host in the cluster should have loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DesiredPlugin {
+    /// Plugin name.
+    pub name: String,
+    /// Plugin version.
+    pub version: String,
+    /// Manifest path, reachable from every host.
+    pub manifest_path: PathBuf,
+}
+
+impl DesiredPlugin {
+    /// Create a desired plugin entry.
+    pub fn new(
+        name: impl Into<String>,
+        version: impl Into<String>,
+        manifest_path: impl Into<PathBuf>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            version: version.into(),
+            manifest_path: manifest_path.into(),
+        }
+    }
+}
+
+/// The cluster's desired plugin set at one revision.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClusterState {
+    /// Revision, incremented by every change.
+    pub revision: u64,
+    /// Plugins that should be loaded, sorted by name.
+    pub plugins: Vec<DesiredPlugin>,
+}
+
+/// Coordination backend storing the cluster's desired plugin set.
+///
+/// Implementations may use a shared file, a database or a coordination
+/// service; hosts only need to agree on the same backend. Changes are made
+/// with [`compare_and_swap`](Self::compare_and_swap), so concurrent writers
+/// never overwrite each other's changes.
+pub trait ClusterBackend: Send + Sync + fmt::Debug {
+    /// Get the desired plugin set and its revision.
+    fn state(&self) -> Result<ClusterState>;
+
+    /// Replace the desired set if it is still at `revision`, incrementing
+    /// the revision.
+    ///
+    /// Returns `false` without changing anything if another writer changed
+    /// the set first.
+    fn compare_and_swap(&self, revision: u64, plugins: Vec<DesiredPlugin>) -> Result<bool>;
+
+    /// Acquire or renew the lease `key` for `holder`.
+    ///
+    /// Returns `false` while another holder's lease has not expired. The
+    /// holder of a lease is the leader for whatever the key stands for.
+    fn acquire_lease(&self, key: &str, holder: &str, ttl: Duration) -> Result<bool>;
+
+    /// Get the plugins that should be loaded.
+    fn desired(&self) -> Result<Vec<DesiredPlugin>> {
+        Ok(self.state()?.plugins)
+    }
+
+    /// Add a plugin to the desired set, replacing one with the same name.
+    fn publish(&self, plugin: DesiredPlugin) -> Result<()> {
+        update(self, |plugins| {
+            plugins.retain(|p| p.name != plugin.name);
+            plugins.push(plugin.clone());
+            plugins.sort_by(|a, b| a.name.cmp(&b.name));
+            true
+        })
+    }
+
+    /// Remove a plugin from the desired set.
+    fn retract(&self, name: &str) -> Result<()> {
+        update(self, |plugins| {
+            let len = plugins.len();
+            plugins.retain(|p| p.name != name);
+            plugins.len() != len
+        })
+    }
+}
+
+/// Apply `change` to the desired set, retrying when another writer got in
+/// first. `change` returns whether it changed anything.
+fn update<B>(backend: &B, mut change: impl FnMut(&mut Vec<DesiredPlugin>) -> bool) -> Result<()>
+where
+    B: ClusterBackend + ?Sized,
+{
+    for _ in 0..MAX_CAS_ATTEMPTS {
+        let mut state = backend.state()?;
+        if !change(&mut state.plugins) {
+            return Ok(());
+        }
+        if backend.compare_and_swap(state.revision, state.plugins)? {
+            return Ok(());
+        }
+    }
+    Err(Error::Registry(format!(
+        "cluster state changed concurrently {} times in a row",
+        MAX_CAS_ATTEMPTS
+    )))
+}
+
+/// Backend held in memory; clones share the same state.
+///
+/// Suitable for tests and for several runtimes in one process.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryClusterBackend {
+    state: Arc<RwLock<ClusterState>>,
+    leases: Arc<Mutex<HashMap<String, (String, Instant)>>>,
+}
+
+impl MemoryClusterBackend {
+    /// Create an empty backend.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ClusterBackend for MemoryClusterBackend {
+    fn state(&self) -> Result<ClusterState> {
+        Ok(self.state.read().clone())
+    }
+
+    fn compare_and_swap(&self, revision: u64, plugins: Vec<DesiredPlugin>) -> Result<bool> {
+        let mut state = self.state.write();
+        if state.revision != revision {
+            return Ok(false);
+        }
+        *state = ClusterState {
+            revision: revision + 1,
+            plugins,
+        };
+        Ok(true)
+    }
+
+    fn acquire_lease(&self, key: &str, holder: &str, ttl: Duration) -> Result<bool> {
+        let now = Instant::now();
+        let mut leases = self.leases.lock();
+        if let Some((current, expires)) = leases.get(key) {
+            if current != holder && *expires > now {
+                return Ok(false);
+            }
+        }
+        leases.insert(key.to_string(), (holder.to_string(), now + ttl));
+        Ok(true)
+    }
+}
+
+/// Backend storing the desired set as JSON in a file on shared storage.
+///
+/// The file holds the state with its revision. Writes check the revision
+/// and replace the file atomically while holding a `<file>.lock` file, so
+/// concurrent writers on any host are serialized. Leases are kept in
+/// `<file>.<key>.lease` files next to it and are only read and replaced
+/// while holding a `<file>.<key>.lease.lock` file, so a lease has a single
+/// holder until it expires.
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone)]
+pub struct FileClusterBackend {
+    path: PathBuf,
+}
+
+#[cfg(feature = "serde")]
+impl FileClusterBackend {
+    /// Use the file at `path`, which need not exist yet.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Get the state file path.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn lease_path(&self, key: &str) -> PathBuf {
+        let key: String = key
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".{}.lease", key));
+        self.path.with_file_name(name)
+    }
+
+    fn read(&self) -> Result<ClusterState> {
+        let content = match std::fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(ClusterState::default())
+            }
+            Err(e) => return Err(e.into()),
+        };
+        serde_json::from_str(&content)
+            .map_err(|e| Error::Registry(format!("invalid cluster state: {}", e)))
+    }
+
+    fn write(&self, state: &ClusterState) -> Result<()> {
+        let json =
+            serde_json::to_string_pretty(state).map_err(|e| Error::Registry(e.to_string()))?;
+        let tmp = self
+            .path
+            .with_extension(format!("{}.tmp", std::process::id()));
+        std::fs::write(&tmp, json)?;
+        std::fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl ClusterBackend for FileClusterBackend {
+    fn state(&self) -> Result<ClusterState> {
+        self.read()
+    }
+
+    fn compare_and_swap(&self, revision: u64, plugins: Vec<DesiredPlugin>) -> Result<bool> {
+        let _lock = FileLock::acquire(&self.path)?;
+        if self.read()?.revision != revision {
+            return Ok(false);
+        }
+        self.write(&ClusterState {
+            revision: revision + 1,
+            plugins,
+        })?;
+        Ok(true)
+    }
+
+    fn acquire_lease(&self, key: &str, holder: &str, ttl: Duration) -> Result<bool> {
+        let path = self.lease_path(key);
+        // Another host is deciding on the lease right now
+        let Some(_lock) = FileLock::try_acquire(&path)? else {
+            return Ok(false);
+        };
+
+        let now = unix_millis(SystemTime::now());
+        let current: Option<FileLease> = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok());
+        if let Some(current) = current {
+            if current.holder != holder && current.expires_ms > now {
+                return Ok(false);
+            }
+        }
+
+        let lease = FileLease {
+            holder: holder.to_string(),
+            expires_ms: now + ttl.as_millis() as u64,
+        };
+        let json = serde_json::to_string(&lease).map_err(|e| Error::Registry(e.to_string()))?;
+        let tmp = path.with_extension(format!("{}.tmp", std::process::id()));
+        std::fs::write(&tmp, json)?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(true)
+    }
+}
+
+/// Exclusive right to replace a state or lease file, held while the file
+/// `<file>.lock` exists.
+#[cfg(feature = "serde")]
+struct FileLock(PathBuf);
+
+#[cfg(feature = "serde")]
+impl FileLock {
+    /// Take the lock on `file`, waiting up to [`LOCK_TIMEOUT`].
+    fn acquire(file: &Path) -> Result<Self> {
+        let deadline = Instant::now() + LOCK_TIMEOUT;
+        loop {
+            if let Some(lock) = Self::try_acquire(file)? {
+                return Ok(lock);
+            }
+            if Instant::now() >= deadline {
+                return Err(Error::Registry(format!(
+                    "timed out waiting for the lock on {}",
+                    file.display()
+                )));
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// Take the lock on `file`, or return `None` while another host holds
+    /// it.
+    ///
+    /// A lock left behind by a host that died while holding it is removed
+    /// once older than [`STALE_LOCK`].
+    fn try_acquire(file: &Path) -> Result<Option<Self>> {
+        let mut name = file.file_name().unwrap_or_default().to_os_string();
+        name.push(".lock");
+        let path = file.with_file_name(name);
+
+        for _ in 0..2 {
+            match std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(_) => return Ok(Some(Self(path))),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(e.into()),
+            }
+            let stale = std::fs::metadata(&path)
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|modified| modified.elapsed().ok())
+                .is_some_and(|age| age > STALE_LOCK);
+            if !stale {
+                return Ok(None);
+            }
+            let _ = std::fs::remove_file(&path);
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Lease record of [`FileClusterBackend`].
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct FileLease {
+    holder: String,
+    expires_ms: u64,
+}
+
+#[cfg(feature = "serde")]
+fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Changes made by `PluginRuntime::converge`.
+#[derive(Debug, Default)]
+pub struct ConvergeReport {
+    /// Plugins loaded because they were missing.
+    pub loaded: Vec<String>,
+    /// Plugins loaded again at the desired version.
+    pub upgraded: Vec<String>,
+    /// Cluster-managed plugins unloaded because they were retracted.
+    pub unloaded: Vec<String>,
+    /// Plugins that could not be brought to the desired state.
+    pub failed: Vec<(String, Error)>,
+}
+
+impl ConvergeReport {
+    /// Check if nothing had to change.
+    pub fn is_unchanged(&self) -> bool {
+        self.loaded.is_empty()
+            && self.upgraded.is_empty()
+            && self.unloaded.is_empty()
+            && self.failed.is_empty()
+    }
+
+    /// Check if every plugin reached the desired state.
+    pub fn is_converged(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_backend_shared() {
+        let backend = MemoryClusterBackend::new();
+        let other = backend.clone();
+        backend
+            .publish(DesiredPlugin::new("a", "1.0.0", "/plugins/a/plugin.toml"))
+            .unwrap();
+        assert_eq!(other.desired().unwrap()[0].name, "a");
+
+        other.retract("a").unwrap();
+        assert!(backend.desired().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_compare_and_swap() {
+        let backend = MemoryClusterBackend::new();
+        let stale = backend.state().unwrap();
+        assert_eq!(stale.revision, 0);
+        backend
+            .publish(DesiredPlugin::new("a", "1.0.0", "/plugins/a/plugin.toml"))
+            .unwrap();
+
+        // A writer still holding the old revision does not overwrite it
+        assert!(!backend
+            .compare_and_swap(stale.revision, Vec::new())
+            .unwrap());
+        let state = backend.state().unwrap();
+        assert_eq!(state.revision, 1);
+        assert_eq!(state.plugins.len(), 1);
+
+        // Retracting an absent plugin changes nothing
+        backend.retract("b").unwrap();
+        assert_eq!(backend.state().unwrap().revision, 1);
+    }
+
+    #[test]
+    fn test_memory_backend_leases() {
+        let backend = MemoryClusterBackend::new();
+        let ttl = Duration::from_secs(60);
+        assert!(backend.acquire_lease("job", "host-a", ttl).unwrap());
+        assert!(!backend.acquire_lease("job", "host-b", ttl).unwrap());
+        assert!(backend.acquire_lease("job", "host-a", ttl).unwrap());
+        assert!(backend.acquire_lease("other", "host-b", ttl).unwrap());
+
+        assert!(backend
+            .acquire_lease("short", "host-a", Duration::ZERO)
+            .unwrap());
+        assert!(backend.acquire_lease("short", "host-b", ttl).unwrap());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_file_backend() {
+        let dir = tempfile::tempdir().unwrap();
+        let backend = FileClusterBackend::new(dir.path().join("cluster.json"));
+        assert!(backend.desired().unwrap().is_empty());
+
+        backend
+            .publish(DesiredPlugin::new("a", "1.0.0", "/plugins/a/plugin.toml"))
+            .unwrap();
+        backend
+            .publish(DesiredPlugin::new("a", "1.1.0", "/plugins/a/plugin.toml"))
+            .unwrap();
+        backend
+            .publish(DesiredPlugin::new("b", "0.1.0", "/plugins/b/plugin.toml"))
+            .unwrap();
+
+        let other = FileClusterBackend::new(backend.path());
+        let desired = other.desired().unwrap();
+        assert_eq!(desired.len(), 2);
+        assert_eq!(desired[0].version, "1.1.0");
+
+        other.retract("a").unwrap();
+        assert_eq!(backend.desired().unwrap().len(), 1);
+        assert_eq!(backend.state().unwrap().revision, 4);
+
+        // Concurrent writers do not lose each other's changes
+        let writers: Vec<_> = (0..4)
+            .map(|i| {
+                let backend = backend.clone();
+                std::thread::spawn(move || {
+                    let name = format!("w{}", i);
+                    let path = format!("/plugins/{}/plugin.toml", name);
+                    backend
+                        .publish(DesiredPlugin::new(name, "1.0.0", path))
+                        .unwrap();
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        assert_eq!(other.desired().unwrap().len(), 5);
+
+        let ttl = Duration::from_secs(60);
+        assert!(backend.acquire_lease("job:b:sync", "host-a", ttl).unwrap());
+        assert!(!other.acquire_lease("job:b:sync", "host-b", ttl).unwrap());
+        assert!(backend.acquire_lease("job:b:sync", "host-a", ttl).unwrap());
+
+        // Expired leases are taken over, but not while another host holds
+        // the lease lock
+        assert!(backend
+            .acquire_lease("job:b:flush", "host-a", Duration::ZERO)
+            .unwrap());
+        let mut lock = backend.lease_path("job:b:flush").into_os_string();
+        lock.push(".lock");
+        std::fs::write(&lock, "").unwrap();
+        assert!(!other.acquire_lease("job:b:flush", "host-b", ttl).unwrap());
+        std::fs::remove_file(&lock).unwrap();
+        assert!(other.acquire_lease("job:b:flush", "host-b", ttl).unwrap());
+        assert!(!backend.acquire_lease("job:b:flush", "host-a", ttl).unwrap());
+    }
+}
@@ -0,0 +1,85 @@
+//! Bytecode compatibility with the engine.
+
+use std::fmt;
+use std::path::PathBuf;
+
+use fusabi_host::validate_bytecode;
+
+use crate::error::Result;
+
+/// Rewrites bytecode built for another engine version.
+///
+/// Consulted by the loader for bytecode-only plugins, i.e. when no `.fsx`
+/// next to the `.fzb` can be recompiled instead.
+pub trait BytecodeUpgrader: Send + Sync + fmt::Debug {
+    /// Return bytecode the running engine can execute.
+    fn upgrade(&self, plugin: &str, bytecode: &[u8]) -> Result<Vec<u8>>;
+}
+
+/// Bytecode-only plugin that would not load on another engine version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AtRiskPlugin {
+    /// Plugin name.
+    pub name: String,
+    /// Compiler version stamped into its bytecode, if readable.
+    pub compiler_version: Option<String>,
+    /// Path of the bytecode file.
+    pub entry_path: Option<PathBuf>,
+    /// Why the engine would reject it.
+    pub reason: String,
+}
+
+/// Plugins that will break on an engine upgrade.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompatReport {
+    /// Engine version checked against.
+    pub engine_version: String,
+    /// Plugins that cannot be recompiled from source and whose bytecode the
+    /// engine would reject, unless a [`BytecodeUpgrader`] handles them.
+    pub at_risk: Vec<AtRiskPlugin>,
+}
+
+impl CompatReport {
+    /// Check if every plugin survives the upgrade.
+    pub fn is_clean(&self) -> bool {
+        self.at_risk.is_empty()
+    }
+}
+
+/// Check that the running engine can execute bytecode, returning why not.
+pub(crate) fn check_bytecode(bytecode: &[u8]) -> std::result::Result<(), String> {
+    let metadata = validate_bytecode(bytecode).map_err(|e| e.to_string())?;
+    match crate::host_info::engine_version() {
+        Some(engine) => check_compiler_version(&metadata.compiler_version, &engine),
+        None => Ok(()),
+    }
+}
+
+/// Bytecode formats change with the compiler's major and minor version.
+pub(crate) fn check_compiler_version(
+    compiler: &str,
+    engine: &str,
+) -> std::result::Result<(), String> {
+    let format = |version: &str| version.split('.').take(2).collect::<Vec<_>>().join(".");
+    if format(compiler) == format(engine) {
+        Ok(())
+    } else {
+        Err(format!(
+            "compiled by {} but the engine is {}",
+            compiler, engine
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compiler_version_check() {
+        assert!(check_compiler_version("0.21.3", "0.21.0").is_ok());
+        let err = check_compiler_version("0.20.1", "0.21.0").unwrap_err();
+        assert!(err.contains("0.20.1"));
+        assert!(check_compiler_version("1.0.0", "2.0.0").is_err());
+    }
+}
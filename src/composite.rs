@@ -0,0 +1,248 @@
+//! Composite plugins: one name in front of several member plugins.
+
+use std::collections::BTreeMap;
+#[cfg(feature = "serde")]
+use std::path::{Path, PathBuf};
+
+use fusabi_host::Value;
+
+use crate::error::{Error, Result};
+use crate::health::HealthStatus;
+use crate::lifecycle::LifecycleState;
+use crate::manifest::Manifest;
+use crate::plugin::PluginHandle;
+#[cfg(feature = "serde")]
+use crate::signature::ExportSpec;
+
+/// A member of a composite plugin, from a `[[members]]` table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CompositeMember {
+    /// Path to the member's manifest, relative to the composite manifest.
+    pub manifest: String,
+    /// Prefix routing exports to the member; defaults to its name and a dot.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub prefix: Option<String>,
+}
+
+impl CompositeMember {
+    /// Create a member loaded from `manifest`.
+    pub fn new(manifest: impl Into<String>) -> Self {
+        Self {
+            manifest: manifest.into(),
+            prefix: None,
+        }
+    }
+
+    /// Route exports starting with `prefix` to the member.
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    /// Resolve the member's manifest path against the composite's.
+    #[cfg(feature = "serde")]
+    pub(crate) fn manifest_path(&self, composite_path: Option<&Path>) -> PathBuf {
+        composite_path
+            .and_then(Path::parent)
+            .unwrap_or(Path::new("."))
+            .join(&self.manifest)
+    }
+}
+
+/// The loaded members of a composite plugin, in manifest order.
+#[derive(Debug)]
+pub(crate) struct Composite {
+    members: Vec<(String, PluginHandle)>,
+}
+
+impl Composite {
+    /// Group loaded members, rejecting prefixes that are empty or shared.
+    #[cfg(feature = "serde")]
+    pub(crate) fn new(members: Vec<(String, PluginHandle)>) -> Result<Self> {
+        for (i, (prefix, _)) in members.iter().enumerate() {
+            if prefix.is_empty() {
+                return Err(Error::invalid_manifest(
+                    "composite member prefixes cannot be empty",
+                ));
+            }
+            if members[..i].iter().any(|(p, _)| p == prefix) {
+                return Err(Error::invalid_manifest(format!(
+                    "composite members share the prefix {}",
+                    prefix
+                )));
+            }
+        }
+        Ok(Self { members })
+    }
+
+    /// Get the exports of the composite: each member's, prefixed.
+    #[cfg(feature = "serde")]
+    pub(crate) fn exports(&self) -> Vec<ExportSpec> {
+        self.members
+            .iter()
+            .flat_map(|(prefix, member)| {
+                member
+                    .inner()
+                    .manifest()
+                    .exports
+                    .into_iter()
+                    .map(move |export| ExportSpec {
+                        name: format!("{}{}", prefix, export.name),
+                        signature: export.signature,
+                    })
+            })
+            .collect()
+    }
+
+    /// Get the member each export prefix routes to.
+    pub(crate) fn routes(&self) -> BTreeMap<String, String> {
+        self.members
+            .iter()
+            .map(|(prefix, member)| (prefix.clone(), member.name()))
+            .collect()
+    }
+
+    /// Get the member handles.
+    pub(crate) fn members(&self) -> Vec<PluginHandle> {
+        self.members.iter().map(|(_, m)| m.clone()).collect()
+    }
+
+    /// Forward a call to the member whose prefix is the longest match.
+    pub(crate) fn call(&self, function: &str, args: &[Value]) -> Result<Value> {
+        let (member, export) = self
+            .members
+            .iter()
+            .filter_map(|(prefix, member)| {
+                function
+                    .strip_prefix(prefix.as_str())
+                    .map(|export| (prefix.len(), member, export))
+            })
+            .max_by_key(|(len, _, _)| *len)
+            .map(|(_, member, export)| (member, export))
+            .ok_or_else(|| Error::FunctionNotFound(function.to_string()))?;
+        member.call(export, args)
+    }
+
+    /// Start every member that is not running, stopping them all again if
+    /// one fails.
+    pub(crate) fn start(&self) -> Result<()> {
+        for (_, member) in &self.members {
+            if member.state() == LifecycleState::Running {
+                continue;
+            }
+            if let Err(e) = member.inner().start() {
+                self.stop();
+                return Err(Error::init_failed(format!("{}: {}", member.name(), e)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Stop the running members in reverse order.
+    pub(crate) fn stop(&self) {
+        for (_, member) in self.members.iter().rev() {
+            if member.state() == LifecycleState::Running {
+                let _ = member.inner().stop();
+            }
+        }
+    }
+
+    /// Unload every member.
+    pub(crate) fn unload(&self) {
+        for (_, member) in self.members.iter().rev() {
+            let _ = member.inner().unload();
+        }
+    }
+
+    /// Check every member, unhealthy if any member is.
+    pub(crate) fn health(&self) -> HealthStatus {
+        let unhealthy: Vec<String> = self
+            .members
+            .iter()
+            .filter_map(|(_, member)| match member.health_check().status {
+                HealthStatus::Unhealthy(reason) => Some(format!("{}: {}", member.name(), reason)),
+                _ => None,
+            })
+            .collect();
+        if unhealthy.is_empty() {
+            HealthStatus::Healthy
+        } else {
+            HealthStatus::Unhealthy(unhealthy.join("; "))
+        }
+    }
+}
+
+/// Check the composite-specific fields of a manifest.
+pub(crate) fn validate(manifest: &Manifest) -> Result<()> {
+    if manifest.source.is_some() || manifest.bytecode.is_some() {
+        return Err(Error::invalid_manifest(
+            "composite manifests cannot specify 'source' or 'bytecode'",
+        ));
+    }
+    if !manifest.exports.is_empty() {
+        return Err(Error::invalid_manifest(
+            "composite manifests take their exports from their members",
+        ));
+    }
+    for (i, member) in manifest.members.iter().enumerate() {
+        if manifest.members[..i]
+            .iter()
+            .any(|m| m.manifest == member.manifest)
+        {
+            return Err(Error::invalid_manifest(format!(
+                "duplicate member: {}",
+                member.manifest
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+    use crate::builtin::BuiltinPlugin;
+    use crate::loader::{LoaderConfig, PluginLoader};
+
+    fn member(name: &str) -> PluginHandle {
+        let loader = PluginLoader::new(LoaderConfig::new().with_auto_start(false)).unwrap();
+        let builtin = BuiltinPlugin::new("1.0.0")
+            .with_function("query", |_| Ok(Value::Int(1)))
+            .with_function("health", |_| Ok(Value::Bool(true)));
+        loader.load_builtin(name, builtin).unwrap()
+    }
+
+    #[test]
+    fn test_composite_routing() {
+        let composite = Composite::new(vec![
+            ("index.".to_string(), member("index")),
+            ("index.fast.".to_string(), member("fast")),
+        ])
+        .unwrap();
+        assert!(composite.exports().iter().any(|e| e.name == "index.query"));
+        assert_eq!(composite.routes()["index.fast."], "fast");
+
+        composite.start().unwrap();
+        assert!(composite.call("index.query", &[]).is_ok());
+        assert!(composite.call("index.fast.query", &[]).is_ok());
+        assert!(matches!(
+            composite.call("other.query", &[]),
+            Err(Error::FunctionNotFound(_))
+        ));
+        assert_eq!(composite.health(), HealthStatus::Healthy);
+
+        composite.stop();
+        assert!(!composite.health().is_healthy());
+    }
+
+    #[test]
+    fn test_composite_rejects_shared_prefix() {
+        let members = vec![
+            ("a.".to_string(), member("a")),
+            ("a.".to_string(), member("b")),
+        ];
+        assert!(Composite::new(members).is_err());
+    }
+}
@@ -0,0 +1,133 @@
+//! Structured errors for invalid runtime, loader and watch configuration.
+
+use std::path::Path;
+
+use crate::error::{Error, Result};
+
+/// A configuration field that failed validation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigIssue {
+    /// Dotted path of the field, e.g. `loader.base_path` or
+    /// `plugin_patterns[1]`.
+    pub path: String,
+    /// What is wrong with it.
+    pub reason: String,
+}
+
+impl std::fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.reason)
+    }
+}
+
+/// Every problem found in a configuration, reported together so a
+/// configuration file can be fixed in one pass.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigError {
+    /// Offending fields, in the order they were checked.
+    pub issues: Vec<ConfigIssue>,
+}
+
+impl ConfigError {
+    /// Create an error without issues.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record an invalid field.
+    pub fn push(&mut self, path: impl Into<String>, reason: impl Into<String>) {
+        self.issues.push(ConfigIssue {
+            path: path.into(),
+            reason: reason.into(),
+        });
+    }
+
+    /// Record an invalid field if `valid` is false.
+    pub(crate) fn check(&mut self, valid: bool, path: &str, reason: impl Into<String>) {
+        if !valid {
+            self.push(path, reason);
+        }
+    }
+
+    /// Record the issues of a nested configuration under `prefix`.
+    pub(crate) fn nest(&mut self, prefix: &str, result: Result<()>) {
+        match result {
+            Ok(()) => {}
+            Err(Error::Config(nested)) => {
+                self.issues
+                    .extend(nested.issues.into_iter().map(|issue| ConfigIssue {
+                        path: format!("{}.{}", prefix, issue.path),
+                        reason: issue.reason,
+                    }))
+            }
+            Err(e) => self.push(prefix, e.to_string()),
+        }
+    }
+
+    /// Check if no issues were recorded.
+    pub fn is_empty(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Get the paths of the offending fields.
+    pub fn paths(&self) -> Vec<&str> {
+        self.issues
+            .iter()
+            .map(|issue| issue.path.as_str())
+            .collect()
+    }
+
+    /// Fail with [`Error::Config`] if any issue was recorded.
+    pub(crate) fn into_result(self) -> Result<()> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Config(self))
+        }
+    }
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, issue) in self.issues.iter().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            write!(f, "{}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+/// Record an issue unless `dir` is an existing directory.
+pub(crate) fn check_dir(errors: &mut ConfigError, path: &str, dir: &Path) {
+    if !dir.exists() {
+        errors.push(path, format!("{} does not exist", dir.display()));
+    } else if !dir.is_dir() {
+        errors.push(path, format!("{} is not a directory", dir.display()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_paths() {
+        let mut inner = ConfigError::new();
+        inner.push("proxy", "invalid proxy URL: proxy:3128");
+        inner.check(true, "request_timeout", "must be non-zero");
+
+        let mut outer = ConfigError::new();
+        outer.nest("network", inner.into_result());
+        outer.nest("loader", Ok(()));
+        outer.push("plugin_dirs[0]", "does not exist");
+
+        assert_eq!(outer.paths(), vec!["network.proxy", "plugin_dirs[0]"]);
+        assert_eq!(
+            Error::Config(outer).to_string(),
+            "invalid configuration: network.proxy: invalid proxy URL: proxy:3128; \
+             plugin_dirs[0]: does not exist"
+        );
+    }
+}
@@ -0,0 +1,367 @@
+//! Plugin-provided configuration schema for generated settings screens.
+
+use std::collections::HashMap;
+
+use crate::error::{Error, Result};
+
+/// Type of a configuration field.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ConfigFieldKind {
+    /// Free-form text.
+    #[default]
+    String,
+    /// Text that should be masked when displayed.
+    Secret,
+    /// Integer number.
+    Int,
+    /// Floating-point number.
+    Float,
+    /// Checkbox or toggle.
+    Bool,
+    /// One of the declared `options`.
+    Select,
+}
+
+impl ConfigFieldKind {
+    /// Get the type name.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::String => "string",
+            Self::Secret => "secret",
+            Self::Int => "int",
+            Self::Float => "float",
+            Self::Bool => "bool",
+            Self::Select => "select",
+        }
+    }
+}
+
+/// A single field of a plugin's configuration.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ConfigField {
+    /// Configuration key.
+    pub name: String,
+    /// Field type.
+    #[cfg_attr(feature = "serde", serde(rename = "type", default))]
+    pub kind: ConfigFieldKind,
+    /// Label shown next to the input.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub label: Option<String>,
+    /// Help text.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub description: Option<String>,
+    /// Value used when the field is left empty.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub default: Option<String>,
+    /// Whether a value must be given.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub required: bool,
+    /// Minimum number, or minimum length for text.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub min: Option<f64>,
+    /// Maximum number, or maximum length for text.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub max: Option<f64>,
+    /// Allowed values of a `select` field.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub options: Vec<String>,
+}
+
+impl ConfigField {
+    /// Create an optional field.
+    pub fn new(name: impl Into<String>, kind: ConfigFieldKind) -> Self {
+        Self {
+            name: name.into(),
+            kind,
+            label: None,
+            description: None,
+            default: None,
+            required: false,
+            min: None,
+            max: None,
+            options: Vec::new(),
+        }
+    }
+
+    /// Set the label.
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Set the help text.
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Set the default value.
+    pub fn with_default(mut self, default: impl Into<String>) -> Self {
+        self.default = Some(default.into());
+        self
+    }
+
+    /// Mark the field as required.
+    pub fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    /// Set the allowed range.
+    pub fn with_range(mut self, min: Option<f64>, max: Option<f64>) -> Self {
+        self.min = min;
+        self.max = max;
+        self
+    }
+
+    /// Set the allowed values of a `select` field.
+    pub fn with_options<I, S>(mut self, options: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.options = options.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Get the label, falling back to the field name.
+    pub fn display_label(&self) -> &str {
+        self.label.as_deref().unwrap_or(&self.name)
+    }
+
+    /// Check a value entered for this field.
+    pub fn check(&self, value: &str) -> Result<()> {
+        let invalid = |reason: String| {
+            Error::invalid_arguments(format!("config field {}: {}", self.name, reason))
+        };
+
+        let measure = match self.kind {
+            ConfigFieldKind::String | ConfigFieldKind::Secret => value.chars().count() as f64,
+            ConfigFieldKind::Int => value
+                .parse::<i64>()
+                .map_err(|_| invalid(format!("expected int, got {:?}", value)))?
+                as f64,
+            ConfigFieldKind::Float => value
+                .parse::<f64>()
+                .map_err(|_| invalid(format!("expected float, got {:?}", value)))?,
+            ConfigFieldKind::Bool => {
+                value
+                    .parse::<bool>()
+                    .map_err(|_| invalid(format!("expected bool, got {:?}", value)))?;
+                return Ok(());
+            }
+            ConfigFieldKind::Select => {
+                if !self.options.iter().any(|o| o == value) {
+                    return Err(invalid(format!(
+                        "{:?} is not one of {}",
+                        value,
+                        self.options.join(", ")
+                    )));
+                }
+                return Ok(());
+            }
+        };
+
+        if let Some(min) = self.min.filter(|&min| measure < min) {
+            return Err(invalid(format!("must be at least {}", min)));
+        }
+        if let Some(max) = self.max.filter(|&max| measure > max) {
+            return Err(invalid(format!("must be at most {}", max)));
+        }
+        Ok(())
+    }
+
+    /// Check the field declaration.
+    pub fn validate(&self) -> Result<()> {
+        if self.name.is_empty() || self.name.contains(char::is_whitespace) {
+            return Err(Error::invalid_manifest(format!(
+                "invalid config field name: {:?}",
+                self.name
+            )));
+        }
+        if self.kind == ConfigFieldKind::Select && self.options.is_empty() {
+            return Err(Error::invalid_manifest(format!(
+                "config field {} is a select without options",
+                self.name
+            )));
+        }
+        if let (Some(min), Some(max)) = (self.min, self.max) {
+            if min > max {
+                return Err(Error::invalid_manifest(format!(
+                    "config field {} has min greater than max",
+                    self.name
+                )));
+            }
+        }
+        if let Some(default) = &self.default {
+            self.check(default)
+                .map_err(|e| Error::invalid_manifest(format!("invalid default: {}", e)))?;
+        }
+        Ok(())
+    }
+}
+
+/// The `[config]` table of a manifest, describing the plugin's settings.
+///
+/// ```toml
+/// [[config.fields]]
+/// name = "interval"
+/// type = "int"
+/// label = "Poll interval (seconds)"
+/// default = "60"
+/// min = 1
+/// max = 3600
+///
+/// [[config.fields]]
+/// name = "mode"
+/// type = "select"
+/// options = ["fast", "safe"]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ConfigSchema {
+    /// Declared fields, in display order.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub fields: Vec<ConfigField>,
+}
+
+impl ConfigSchema {
+    /// Check if no fields are declared.
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Find a field by name.
+    pub fn field(&self, name: &str) -> Option<&ConfigField> {
+        self.fields.iter().find(|f| f.name == name)
+    }
+
+    /// Check the schema declaration.
+    pub fn validate(&self) -> Result<()> {
+        for (i, field) in self.fields.iter().enumerate() {
+            field.validate()?;
+            if self.fields[..i].iter().any(|f| f.name == field.name) {
+                return Err(Error::invalid_manifest(format!(
+                    "duplicate config field: {}",
+                    field.name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check submitted values against the schema.
+    ///
+    /// Unknown keys are rejected and required fields without a default
+    /// must be present.
+    pub fn check_values(&self, values: &HashMap<String, String>) -> Result<()> {
+        for key in values.keys() {
+            if self.field(key).is_none() {
+                return Err(Error::invalid_arguments(format!(
+                    "unknown config field: {}",
+                    key
+                )));
+            }
+        }
+        for field in &self.fields {
+            match values.get(&field.name) {
+                Some(value) => field.check(value)?,
+                None if field.required && field.default.is_none() => {
+                    return Err(Error::invalid_arguments(format!(
+                        "missing required config field: {}",
+                        field.name
+                    )));
+                }
+                None => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the default value of every field that has one.
+    pub fn defaults(&self) -> HashMap<String, String> {
+        self.fields
+            .iter()
+            .filter_map(|f| Some((f.name.clone(), f.default.clone()?)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_check_values() {
+        let schema = ConfigSchema {
+            fields: vec![
+                ConfigField::new("token", ConfigFieldKind::Secret)
+                    .required()
+                    .with_range(Some(8.0), None),
+                ConfigField::new("interval", ConfigFieldKind::Int)
+                    .with_label("Poll interval")
+                    .with_default("60")
+                    .with_range(Some(1.0), Some(3600.0)),
+                ConfigField::new("mode", ConfigFieldKind::Select).with_options(["fast", "safe"]),
+            ],
+        };
+        schema.validate().unwrap();
+        assert_eq!(
+            schema.field("interval").unwrap().display_label(),
+            "Poll interval"
+        );
+        assert_eq!(schema.field("mode").unwrap().display_label(), "mode");
+        assert_eq!(
+            schema.defaults().get("interval").map(String::as_str),
+            Some("60")
+        );
+
+        assert!(schema
+            .check_values(&values(&[("token", "secret-token"), ("mode", "safe")]))
+            .is_ok());
+        assert!(schema.check_values(&values(&[("mode", "safe")])).is_err());
+        assert!(schema.check_values(&values(&[("token", "short")])).is_err());
+        assert!(schema
+            .check_values(&values(&[("token", "secret-token"), ("interval", "0")]))
+            .is_err());
+        assert!(schema
+            .check_values(&values(&[("token", "secret-token"), ("mode", "slow")]))
+            .is_err());
+        assert!(schema
+            .check_values(&values(&[("token", "secret-token"), ("colour", "red")]))
+            .is_err());
+    }
+
+    #[test]
+    fn test_validate_declaration() {
+        let select = ConfigField::new("mode", ConfigFieldKind::Select);
+        assert!(select.validate().is_err());
+
+        let range = ConfigField::new("n", ConfigFieldKind::Int).with_range(Some(5.0), Some(1.0));
+        assert!(range.validate().is_err());
+
+        let default = ConfigField::new("n", ConfigFieldKind::Int).with_default("many");
+        assert!(default.validate().is_err());
+
+        let duplicate = ConfigSchema {
+            fields: vec![
+                ConfigField::new("n", ConfigFieldKind::Int),
+                ConfigField::new("n", ConfigFieldKind::Bool),
+            ],
+        };
+        assert!(duplicate.validate().is_err());
+    }
+}
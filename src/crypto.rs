@@ -0,0 +1,118 @@
+//! Hashing and signature verification behind a replaceable provider.
+
+use std::sync::{Arc, OnceLock};
+
+use sha2::{Digest, Sha256};
+
+use crate::error::{Error, Result};
+
+/// Hashing and signature primitives used by the runtime.
+///
+/// Every digest the runtime computes (bytecode cache keys, artifact store
+/// blobs, lockfile checksums) goes through the installed provider, so
+/// deployments bound to a FIPS module or an HSM can supply their own.
+pub trait CryptoProvider: Send + Sync {
+    /// Get the provider name, for diagnostics.
+    fn name(&self) -> &str;
+
+    /// Compute the SHA-256 digest of `data`.
+    fn sha256(&self, data: &[u8]) -> [u8; 32];
+
+    /// Verify `signature` over `message` with `public_key`.
+    ///
+    /// `algorithm` names the signature scheme, e.g. `"ed25519"`. Returns
+    /// [`Error::SignatureInvalid`] if the signature does not match or the
+    /// algorithm is not supported.
+    fn verify_signature(
+        &self,
+        algorithm: &str,
+        public_key: &[u8],
+        message: &[u8],
+        signature: &[u8],
+    ) -> Result<()>;
+}
+
+/// Provider backed by the `sha2` crate, used unless another is installed.
+///
+/// It verifies no signature algorithms; install a provider that does to
+/// check signatures.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultCryptoProvider;
+
+impl CryptoProvider for DefaultCryptoProvider {
+    fn name(&self) -> &str {
+        "sha2"
+    }
+
+    fn sha256(&self, data: &[u8]) -> [u8; 32] {
+        Sha256::digest(data).into()
+    }
+
+    fn verify_signature(
+        &self,
+        algorithm: &str,
+        _public_key: &[u8],
+        _message: &[u8],
+        _signature: &[u8],
+    ) -> Result<()> {
+        Err(Error::SignatureInvalid(format!(
+            "the {} crypto provider does not support {} signatures",
+            self.name(),
+            algorithm
+        )))
+    }
+}
+
+static PROVIDER: OnceLock<Arc<dyn CryptoProvider>> = OnceLock::new();
+
+/// Install the process-wide crypto provider.
+///
+/// Must be called before the runtime computes its first digest; fails with
+/// [`Error::InvalidState`] once a provider is in use.
+pub fn install_crypto_provider(provider: impl CryptoProvider + 'static) -> Result<()> {
+    let provider: Arc<dyn CryptoProvider> = Arc::new(provider);
+    PROVIDER.set(provider).map_err(|_| {
+        Error::invalid_state(
+            "no crypto provider in use",
+            format!("{} provider in use", crypto_provider().name()),
+        )
+    })
+}
+
+/// Get the process-wide crypto provider, installing the default one if none
+/// was installed.
+pub fn crypto_provider() -> &'static Arc<dyn CryptoProvider> {
+    PROVIDER.get_or_init(|| Arc::new(DefaultCryptoProvider))
+}
+
+/// Compute the hex-encoded SHA-256 digest of `data`.
+pub(crate) fn sha256_hex(data: &[u8]) -> String {
+    crypto_provider()
+        .sha256(data)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_provider() {
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert!(matches!(
+            DefaultCryptoProvider.verify_signature("ed25519", b"key", b"msg", b"sig"),
+            Err(Error::SignatureInvalid(_))
+        ));
+
+        // A provider is in use now, so it can no longer be replaced
+        assert!(matches!(
+            install_crypto_provider(DefaultCryptoProvider),
+            Err(Error::InvalidState { .. })
+        ));
+    }
+}
@@ -0,0 +1,128 @@
+//! Plugins loaded on behalf of other plugins.
+
+use crate::manifest::{CapabilitySpec, Manifest};
+#[cfg(feature = "serde")]
+use crate::plugin::PluginHandle;
+
+/// Capability a plugin must declare to load other plugins through
+/// [`PluginRuntime::load_delegated`](crate::PluginRuntime::load_delegated).
+pub(crate) const PLUGINS_LOAD: &str = "plugins:load";
+
+/// Check if a capability is enforced by the runtime rather than granted to
+/// the engine.
+pub(crate) fn is_runtime_capability(name: &str) -> bool {
+    name == PLUGINS_LOAD
+}
+
+/// Restrictions a plugin inherits from the plugin that loaded it.
+///
+/// The child keeps only the part of each declared capability that the
+/// parent also holds, so loading a plugin never grants more than the loader
+/// has. Restrictions are reapplied whenever the child's manifest is read
+/// again, and carry over to the plugins the child loads in turn.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Delegation {
+    parent: String,
+    ceiling: Vec<CapabilitySpec>,
+}
+
+impl Delegation {
+    /// Inherit the capabilities `parent` holds.
+    ///
+    /// Only declared capabilities the parent's engine was actually granted
+    /// count, so a parent denied a capability by the host cannot pass it on.
+    #[cfg(feature = "serde")]
+    pub(crate) fn new(parent: &PluginHandle) -> Self {
+        let granted = parent
+            .inner()
+            .engine_config()
+            .map(|config| config.capabilities);
+        let ceiling = parent
+            .inner()
+            .manifest()
+            .capabilities
+            .into_iter()
+            .filter(
+                |spec| match fusabi_host::Capability::from_name(&spec.name) {
+                    Some(cap) => granted.as_ref().is_some_and(|caps| caps.has(cap)),
+                    None => is_runtime_capability(&spec.name),
+                },
+            )
+            .collect();
+
+        Self {
+            parent: parent.name(),
+            ceiling,
+        }
+    }
+
+    /// Get the plugin that loaded the child.
+    pub fn parent(&self) -> &str {
+        &self.parent
+    }
+
+    /// Get the capabilities the child can hold at most.
+    pub fn ceiling(&self) -> &[CapabilitySpec] {
+        &self.ceiling
+    }
+
+    /// Narrow a manifest's capabilities to the ceiling, returning the names
+    /// of those dropped entirely.
+    pub(crate) fn restrict(&self, manifest: &mut Manifest) -> Vec<String> {
+        let mut withheld = Vec::new();
+        manifest.capabilities = std::mem::take(&mut manifest.capabilities)
+            .into_iter()
+            .filter_map(|spec| {
+                let narrowed = self
+                    .ceiling
+                    .iter()
+                    .find_map(|granted| spec.intersect(granted));
+                if narrowed.is_none() {
+                    withheld.push(spec.name);
+                }
+                narrowed
+            })
+            .collect();
+        withheld
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+    use crate::manifest::ManifestBuilder;
+    use crate::plugin::Plugin;
+    use fusabi_host::{Capabilities, Capability, EngineConfig};
+
+    #[test]
+    fn test_restrict_to_parent() {
+        let parent = Plugin::new(
+            ManifestBuilder::new("framework", "1.0.0")
+                .capability(PLUGINS_LOAD)
+                .capability("fs:read")
+                .build_unchecked(),
+        );
+        let parent = PluginHandle::new(parent);
+
+        // Nothing has been granted to an engine that was never initialized
+        let delegation = Delegation::new(&parent);
+        assert_eq!(delegation.ceiling(), [CapabilitySpec::new(PLUGINS_LOAD)]);
+
+        parent
+            .inner()
+            .initialize(
+                EngineConfig::default()
+                    .with_capabilities(Capabilities::none().with(Capability::FsRead)),
+            )
+            .unwrap();
+        let delegation = Delegation::new(&parent);
+        assert_eq!(delegation.parent(), "framework");
+
+        let mut child = ManifestBuilder::new("widget", "1.0.0")
+            .capability("fs:read")
+            .capability("net:request")
+            .build_unchecked();
+        assert_eq!(delegation.restrict(&mut child), vec!["net:request"]);
+        assert_eq!(child.capabilities, vec![CapabilitySpec::new("fs:read")]);
+    }
+}
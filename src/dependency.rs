@@ -0,0 +1,239 @@
+//! Dependency resolution between plugins.
+
+use std::collections::{BTreeMap, HashSet};
+
+use crate::error::{Error, Result};
+use crate::manifest::{Dependency, Manifest};
+
+/// Dependency graph over a set of plugins.
+#[derive(Debug, Clone, Default)]
+pub struct DependencyGraph {
+    // BTreeMap keeps resolution order deterministic for independent plugins.
+    nodes: BTreeMap<String, Node>,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    version: String,
+    priority: i32,
+    dependencies: Vec<Dependency>,
+}
+
+impl DependencyGraph {
+    /// Create an empty graph.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build a graph from plugin manifests.
+    pub fn from_manifests<'a, I>(manifests: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Manifest>,
+    {
+        let mut graph = Self::new();
+        for manifest in manifests {
+            graph.add(manifest);
+        }
+        graph
+    }
+
+    /// Add a plugin to the graph.
+    pub fn add(&mut self, manifest: &Manifest) {
+        self.nodes.insert(
+            manifest.name.clone(),
+            Node {
+                version: manifest.version.clone(),
+                priority: manifest.priority,
+                dependencies: manifest.dependencies.clone(),
+            },
+        );
+    }
+
+    /// Check if the graph contains a plugin.
+    pub fn contains(&self, name: &str) -> bool {
+        self.nodes.contains_key(name)
+    }
+
+    /// Get the number of plugins in the graph.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Check if the graph is empty.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Check the dependencies of a single plugin against the graph.
+    pub fn check(&self, name: &str) -> Result<()> {
+        let node = self
+            .nodes
+            .get(name)
+            .ok_or_else(|| Error::plugin_not_found(name))?;
+
+        for dep in &node.dependencies {
+            match self.nodes.get(&dep.name) {
+                Some(target) if dep.is_satisfied_by(&target.version) => {}
+                Some(_) => {
+                    return Err(Error::dependency_not_satisfied(&dep.name, &dep.version));
+                }
+                None if dep.optional => {}
+                None => {
+                    return Err(Error::dependency_not_satisfied(&dep.name, &dep.version));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check the dependencies of every plugin.
+    pub fn validate(&self) -> Result<()> {
+        for name in self.nodes.keys() {
+            self.check(name)?;
+        }
+        self.resolve().map(|_| ())
+    }
+
+    /// Get plugin names ordered so that dependencies come before dependents.
+    ///
+    /// Otherwise plugins are ordered by descending priority, then by name.
+    /// A dependency is placed before its dependents regardless of its own
+    /// priority. Missing dependencies are ignored here; use [`validate`](Self::validate)
+    /// to reject them. Returns [`Error::DependencyCycle`] if the graph is cyclic.
+    pub fn resolve(&self) -> Result<Vec<String>> {
+        let mut order = Vec::with_capacity(self.nodes.len());
+        let mut done = HashSet::new();
+        let mut visiting = Vec::new();
+
+        let mut roots: Vec<(&String, &Node)> = self.nodes.iter().collect();
+        // Stable, so equal priorities keep name order
+        roots.sort_by_key(|(_, node)| std::cmp::Reverse(node.priority));
+        for (name, _) in roots {
+            self.visit(name, &mut visiting, &mut done, &mut order)?;
+        }
+
+        Ok(order)
+    }
+
+    /// Get the names of plugins that directly depend on a plugin.
+    pub fn dependents(&self, name: &str) -> Vec<String> {
+        self.nodes
+            .iter()
+            .filter(|(_, node)| node.dependencies.iter().any(|d| d.name == name))
+            .map(|(dependent, _)| dependent.clone())
+            .collect()
+    }
+
+    fn visit(
+        &self,
+        name: &str,
+        visiting: &mut Vec<String>,
+        done: &mut HashSet<String>,
+        order: &mut Vec<String>,
+    ) -> Result<()> {
+        if done.contains(name) {
+            return Ok(());
+        }
+
+        if let Some(pos) = visiting.iter().position(|n| n == name) {
+            let mut cycle = visiting[pos..].to_vec();
+            cycle.push(name.to_string());
+            return Err(Error::DependencyCycle(cycle.join(" -> ")));
+        }
+
+        let Some(node) = self.nodes.get(name) else {
+            return Ok(());
+        };
+
+        visiting.push(name.to_string());
+        for dep in &node.dependencies {
+            self.visit(&dep.name, visiting, done, order)?;
+        }
+        visiting.pop();
+
+        done.insert(name.to_string());
+        order.push(name.to_string());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::ManifestBuilder;
+
+    fn manifest(name: &str, version: &str, deps: &[(&str, &str)]) -> Manifest {
+        let mut builder = ManifestBuilder::new(name, version).source("main.fsx");
+        for (dep, req) in deps {
+            builder = builder.dependency(Dependency::required(*dep, *req));
+        }
+        builder.build_unchecked()
+    }
+
+    #[test]
+    fn test_resolve_order() {
+        let manifests = [
+            manifest("app", "1.0.0", &[("http", "1.0"), ("log", "0.3")]),
+            manifest("http", "1.2.0", &[("log", "0.3")]),
+            manifest("log", "0.3.1", &[]),
+        ];
+        let graph = DependencyGraph::from_manifests(&manifests);
+
+        graph.validate().unwrap();
+        assert_eq!(graph.resolve().unwrap(), vec!["log", "http", "app"]);
+        assert_eq!(graph.dependents("log"), vec!["app", "http"]);
+    }
+
+    #[test]
+    fn test_resolve_by_priority() {
+        let mut ui = manifest("ui", "1.0.0", &[("log", "0.3")]);
+        ui.priority = 10;
+        let manifests = [
+            manifest("audit", "1.0.0", &[]),
+            manifest("log", "0.3.1", &[]),
+            ui,
+        ];
+        let graph = DependencyGraph::from_manifests(&manifests);
+
+        // `log` is pulled ahead of `audit` because `ui` needs it
+        assert_eq!(graph.resolve().unwrap(), vec!["log", "ui", "audit"]);
+    }
+
+    #[test]
+    fn test_missing_and_mismatched_dependencies() {
+        let graph = DependencyGraph::from_manifests(&[manifest("app", "1.0.0", &[("log", "1.0")])]);
+        assert!(matches!(
+            graph.check("app"),
+            Err(Error::DependencyNotSatisfied { .. })
+        ));
+
+        let graph = DependencyGraph::from_manifests(&[
+            manifest("app", "1.0.0", &[("log", "1.0")]),
+            manifest("log", "2.0.0", &[]),
+        ]);
+        assert!(graph.validate().is_err());
+
+        let mut optional = manifest("app", "1.0.0", &[]);
+        optional
+            .dependencies
+            .push(Dependency::optional("missing", "1.0"));
+        assert!(DependencyGraph::from_manifests(&[optional])
+            .validate()
+            .is_ok());
+    }
+
+    #[test]
+    fn test_cycle_detection() {
+        let graph = DependencyGraph::from_manifests(&[
+            manifest("a", "1.0.0", &[("b", "*")]),
+            manifest("b", "1.0.0", &[("c", "*")]),
+            manifest("c", "1.0.0", &[("a", "*")]),
+        ]);
+
+        match graph.resolve() {
+            Err(Error::DependencyCycle(cycle)) => assert_eq!(cycle, "a -> b -> c -> a"),
+            other => panic!("expected cycle, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,125 @@
+//! Structured description of a loaded plugin.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::config_schema::ConfigSchema;
+use crate::health::HEALTH_EXPORT;
+use crate::lifecycle::LifecycleState;
+use crate::manifest::{CapabilitySpec, Dependency, Isolation};
+use crate::origin::PluginOrigin;
+use crate::signature::ExportSpec;
+
+/// Exports the runtime calls at points of a plugin's lifecycle.
+pub(crate) const LIFECYCLE_HOOKS: [&str; 4] = ["init", "cleanup", HEALTH_EXPORT, "warmup"];
+
+/// Everything known about a loaded plugin, as returned by
+/// [`PluginHandle::describe`](crate::PluginHandle::describe).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PluginDescriptor {
+    /// Plugin ID.
+    pub id: u64,
+    /// Plugin name.
+    pub name: String,
+    /// Plugin version.
+    pub version: String,
+    /// Human-readable description.
+    pub description: Option<String>,
+    /// Plugin authors.
+    pub authors: Vec<String>,
+    /// Plugin license.
+    pub license: Option<String>,
+    /// Required Fusabi API version.
+    pub api_version: String,
+    /// Lifecycle state.
+    pub state: LifecycleState,
+    /// Exported functions with their signatures, if declared.
+    pub exports: Vec<ExportSpec>,
+    /// Lifecycle hooks among the exports, such as `init` and `health`.
+    pub hooks: Vec<String>,
+    /// Export prefixes of a composite plugin, mapped to the members they
+    /// route to.
+    pub routes: BTreeMap<String, String>,
+    /// Schema of the plugin's configuration.
+    pub config: ConfigSchema,
+    /// Requested capabilities.
+    pub capabilities: Vec<CapabilitySpec>,
+    /// Plugin dependencies.
+    pub dependencies: Vec<Dependency>,
+    /// Where the plugin's code runs.
+    pub isolation: Isolation,
+    /// Plugin tags.
+    pub tags: Vec<String>,
+    /// Custom metadata.
+    pub metadata: BTreeMap<String, String>,
+    /// Manifest path, if loaded from a manifest.
+    pub manifest_path: Option<PathBuf>,
+    /// Source or bytecode path.
+    pub entry_path: Option<PathBuf>,
+    /// Where the plugin was installed from.
+    pub origin: PluginOrigin,
+    /// Engine limits, once the plugin is initialized.
+    pub limits: Option<ResourceLimits>,
+    /// Usage statistics.
+    pub stats: PluginStats,
+}
+
+/// Engine limits applied to a plugin.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ResourceLimits {
+    /// Maximum memory in bytes.
+    pub max_memory: u64,
+    /// Maximum call depth.
+    pub max_call_depth: u64,
+    /// Maximum instructions per call.
+    pub max_instructions: u64,
+}
+
+impl From<&fusabi_host::Limits> for ResourceLimits {
+    fn from(limits: &fusabi_host::Limits) -> Self {
+        Self {
+            max_memory: widen(limits.max_memory),
+            max_call_depth: widen(limits.max_call_depth),
+            max_instructions: widen(limits.max_instructions),
+        }
+    }
+}
+
+impl ResourceLimits {
+    /// Set `limits` to these values, leaving any the engine cannot
+    /// represent alone.
+    #[cfg_attr(not(feature = "isolation"), allow(dead_code))]
+    pub(crate) fn apply(&self, limits: &mut fusabi_host::Limits) {
+        narrow(&mut limits.max_memory, self.max_memory);
+        narrow(&mut limits.max_call_depth, self.max_call_depth);
+        narrow(&mut limits.max_instructions, self.max_instructions);
+    }
+}
+
+fn narrow<T: TryFrom<u64>>(value: &mut T, n: u64) {
+    if let Ok(n) = T::try_from(n) {
+        *value = n;
+    }
+}
+
+fn widen<T: TryInto<u64>>(n: T) -> u64 {
+    n.try_into().unwrap_or(u64::MAX)
+}
+
+/// Usage statistics of a plugin.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PluginStats {
+    /// Calls that ran the plugin.
+    pub invocations: u64,
+    /// Calls per export, including cached ones.
+    pub export_calls: BTreeMap<String, u64>,
+    /// Calls answered from the call cache.
+    pub cache_hits: u64,
+    /// Number of reloads.
+    pub reloads: u64,
+    /// Seconds since the plugin was loaded.
+    pub uptime_secs: u64,
+}
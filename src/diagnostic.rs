@@ -0,0 +1,231 @@
+//! Source locations and compiler-style rendering of errors.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::error::Error;
+
+/// Where in a plugin's source an error occurred.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SourceSpan {
+    /// Source file, if known.
+    pub file: Option<PathBuf>,
+    /// 1-based line.
+    pub line: usize,
+    /// 1-based column.
+    pub column: usize,
+    /// The source line, if the source was available.
+    pub snippet: Option<String>,
+}
+
+impl SourceSpan {
+    /// Create a span at a line and column.
+    pub fn new(line: usize, column: usize) -> Self {
+        Self {
+            file: None,
+            line,
+            column,
+            snippet: None,
+        }
+    }
+
+    /// Set the source file.
+    pub fn with_file(mut self, file: impl Into<PathBuf>) -> Self {
+        self.file = Some(file.into());
+        self
+    }
+
+    /// Set the snippet to the span's line of `source`.
+    pub fn with_source(mut self, source: &str) -> Self {
+        self.snippet = self
+            .line
+            .checked_sub(1)
+            .and_then(|i| source.lines().nth(i))
+            .map(|line| line.to_string());
+        self
+    }
+
+    /// Find the position an engine or compiler message refers to, written
+    /// as `line 3, column 5` or `line 3`.
+    ///
+    /// The engine reports positions only in its messages, so the labels
+    /// must stand as words: `pipeline 3` or `line 3s` name no position.
+    pub(crate) fn locate(message: &str) -> Option<Self> {
+        let line = number_after(message, "line ")?;
+        let column = number_after(message, "column ")
+            .or_else(|| number_after(message, "col "))
+            .unwrap_or(1);
+        Some(Self::new(line, column))
+    }
+
+    /// Locate a message in a source file, reading the snippet from it.
+    pub(crate) fn locate_in(message: &str, file: &Path, source: Option<&str>) -> Option<Self> {
+        let span = Self::locate(message)?.with_file(file);
+        Some(match source {
+            Some(source) => span.with_source(source),
+            None => match std::fs::read_to_string(file) {
+                Ok(source) => span.with_source(&source),
+                Err(_) => span,
+            },
+        })
+    }
+}
+
+/// Parse the number following `label`, where both stand as whole words.
+fn number_after(message: &str, label: &str) -> Option<usize> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    message.match_indices(label).find_map(|(i, _)| {
+        if message[..i].chars().next_back().is_some_and(is_word) {
+            return None;
+        }
+        let rest = &message[i + label.len()..];
+        let end = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        if rest[end..].chars().next().is_some_and(is_word) {
+            return None;
+        }
+        rest[..end].parse().ok().filter(|&n| n > 0)
+    })
+}
+
+impl fmt::Display for SourceSpan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.file {
+            Some(ref file) => write!(f, "{}:{}:{}", file.display(), self.line, self.column),
+            None => write!(f, "line {}, column {}", self.line, self.column),
+        }
+    }
+}
+
+/// An error prepared for IDEs, CI logs and other tools.
+///
+/// `render` formats it the way compilers do:
+///
+/// ```text
+/// error[E3001]: compilation error: unexpected token `=`
+///  --> plugins/search/main.fsx:3:9
+///   |
+/// 3 | let x = = 1
+///   |         ^
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Diagnostic {
+    /// Stable error code, see [`Error::code`].
+    pub code: &'static str,
+    /// Error message, without the location.
+    pub message: String,
+    /// Where the error occurred, if known.
+    pub span: Option<SourceSpan>,
+    /// Suggested fixes.
+    pub help: Vec<String>,
+}
+
+impl Diagnostic {
+    /// Describe an error.
+    pub fn from_error(error: &Error) -> Self {
+        let span = error.span().cloned().or_else(|| match error.root() {
+            #[cfg(feature = "serde")]
+            Error::ManifestSyntax { line, column, .. } => Some(SourceSpan::new(*line, *column)),
+            _ => None,
+        });
+        Self {
+            code: error.code(),
+            message: error.root().to_string(),
+            span,
+            help: Vec::new(),
+        }
+    }
+
+    /// Add a suggested fix.
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help.push(help.into());
+        self
+    }
+
+    /// Format the diagnostic with its location, source line and help.
+    pub fn render(&self) -> String {
+        let mut out = format!("error[{}]: {}\n", self.code, self.message);
+        if let Some(ref span) = self.span {
+            let gutter = span.line.to_string().len();
+            let pad = " ".repeat(gutter);
+            out.push_str(&format!("{}--> {}\n", pad, span));
+            if let Some(ref snippet) = span.snippet {
+                out.push_str(&format!("{} |\n", pad));
+                out.push_str(&format!("{} | {}\n", span.line, snippet));
+                out.push_str(&format!(
+                    "{} | {}^\n",
+                    pad,
+                    " ".repeat(span.column.saturating_sub(1))
+                ));
+            }
+        }
+        for help in &self.help {
+            out.push_str(&format!("= help: {}\n", help));
+        }
+        out
+    }
+}
+
+impl From<&Error> for Diagnostic {
+    fn from(error: &Error) -> Self {
+        Self::from_error(error)
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locate() {
+        let span = SourceSpan::locate("unexpected token `=` at line 3, column 9").unwrap();
+        assert_eq!((span.line, span.column), (3, 9));
+        let span = SourceSpan::locate("unbound name on line 12").unwrap();
+        assert_eq!((span.line, span.column), (12, 1));
+        assert!(SourceSpan::locate("stack overflow").is_none());
+        assert!(SourceSpan::locate("pipeline stalled").is_none());
+        assert!(SourceSpan::locate("pipeline 3 failed").is_none());
+        assert!(SourceSpan::locate("deadline 5s exceeded").is_none());
+        let span = SourceSpan::locate("pipeline 2 failed at line 7, col 3").unwrap();
+        assert_eq!((span.line, span.column), (7, 3));
+
+        let span = SourceSpan::new(2, 5).with_source("let a = 1\nlet b = a +\n");
+        assert_eq!(span.snippet.as_deref(), Some("let b = a +"));
+        assert_eq!(span.to_string(), "line 2, column 5");
+    }
+
+    #[test]
+    fn test_render() {
+        let span = SourceSpan::new(3, 9)
+            .with_file("main.fsx")
+            .with_source("let a = 1\nlet b = 2\nlet x = = 1\n");
+        let error = Error::Compilation("unexpected token `=`".into()).at(span);
+        assert_eq!(
+            error.to_string(),
+            "main.fsx:3:9: compilation error: unexpected token `=`"
+        );
+
+        let diagnostic = Diagnostic::from_error(&error).with_help("remove the extra `=`");
+        assert_eq!(
+            diagnostic.render(),
+            "error[E3001]: compilation error: unexpected token `=`\n \
+             --> main.fsx:3:9\n  \
+             |\n\
+             3 | let x = = 1\n  \
+             |         ^\n\
+             = help: remove the extra `=`\n"
+        );
+
+        let diagnostic = Diagnostic::from(&Error::PluginUnloaded);
+        assert_eq!(diagnostic.render(), "error[E3008]: plugin was unloaded\n");
+    }
+}
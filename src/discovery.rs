@@ -0,0 +1,313 @@
+//! Finding plugin manifests in the configured plugin directories.
+
+#[cfg(feature = "serde")]
+use std::collections::HashSet;
+#[cfg(feature = "serde")]
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::error::Error;
+#[cfg(feature = "serde")]
+use crate::glob::wildcard_match;
+#[cfg(feature = "serde")]
+use crate::manifest::{ApiVersion, Manifest};
+use crate::plugin::PluginHandle;
+
+/// Metadata key recording the manifest name of a plugin renamed on discovery.
+#[cfg(feature = "serde")]
+pub(crate) const DISCOVERED_AS: &str = "discovered-as";
+
+/// What discovery does when two manifests declare the same plugin name.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CollisionStrategy {
+    /// Load none of the colliding manifests.
+    Error,
+    /// Load the manifest with the highest version.
+    PreferHigherVersion,
+    /// Load the manifest found first, in plugin directory order.
+    #[default]
+    PreferFirstDir,
+    /// Load every manifest, suffixing later ones with their directory name.
+    SuffixWithDir,
+}
+
+/// How a name collision was resolved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CollisionResolution {
+    /// None of the manifests were loaded.
+    Rejected,
+    /// Only the manifest at this path was loaded.
+    Chose(PathBuf),
+    /// Every manifest was loaded, later ones under the given names.
+    Renamed(Vec<(PathBuf, String)>),
+}
+
+/// Two or more manifests declaring the same plugin name.
+#[derive(Debug, Clone)]
+pub struct Collision {
+    /// Plugin name the manifests declare.
+    pub name: String,
+    /// Paths of the colliding manifests, in discovery order.
+    pub candidates: Vec<PathBuf>,
+    /// What discovery did about it.
+    pub resolution: CollisionResolution,
+}
+
+/// Outcome of scanning the plugin directories.
+#[derive(Debug, Default)]
+pub struct DiscoveryReport {
+    /// Plugins that were loaded.
+    pub loaded: Vec<PluginHandle>,
+    /// Name collisions and how they were resolved.
+    pub collisions: Vec<Collision>,
+    /// Manifests that could not be read or loaded.
+    pub failed: Vec<(PathBuf, Error)>,
+}
+
+impl DiscoveryReport {
+    /// Check if every manifest loaded without collisions.
+    pub fn is_clean(&self) -> bool {
+        self.collisions.is_empty() && self.failed.is_empty()
+    }
+}
+
+/// A manifest found during discovery.
+#[cfg(feature = "serde")]
+pub(crate) struct Candidate {
+    pub path: PathBuf,
+    pub manifest: Manifest,
+}
+
+/// Find the files matching `patterns` in each of `dirs`.
+///
+/// Directories are scanned in order and a file matched by several patterns
+/// is returned once.
+#[cfg(feature = "serde")]
+pub(crate) fn scan(dirs: &[PathBuf], patterns: &[String]) -> Vec<PathBuf> {
+    let mut seen = HashSet::new();
+    let mut found = Vec::new();
+
+    for dir in dirs {
+        if !dir.exists() {
+            tracing::warn!("Plugin directory does not exist: {}", dir.display());
+            continue;
+        }
+        for pattern in patterns {
+            let parts: Vec<&str> = pattern.split('/').filter(|p| !p.is_empty()).collect();
+            let mut matches = Vec::new();
+            expand(dir, &parts, &mut matches);
+            matches.sort();
+            for path in matches {
+                if seen.insert(path.clone()) {
+                    found.push(path);
+                }
+            }
+        }
+    }
+
+    found
+}
+
+#[cfg(feature = "serde")]
+fn expand(base: &Path, parts: &[&str], out: &mut Vec<PathBuf>) {
+    let Some((part, rest)) = parts.split_first() else {
+        if base.is_file() {
+            out.push(base.to_path_buf());
+        }
+        return;
+    };
+
+    if !part.contains(['*', '?']) {
+        expand(&base.join(part), rest, out);
+        return;
+    }
+
+    let Ok(entries) = std::fs::read_dir(base) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if wildcard_match(part, &entry.file_name().to_string_lossy()) {
+            expand(&entry.path(), rest, out);
+        }
+    }
+}
+
+/// Pick which candidates to load, grouping them by plugin name.
+///
+/// Candidates are returned in discovery order, renamed where the strategy
+/// requires it.
+#[cfg(feature = "serde")]
+pub(crate) fn resolve(
+    strategy: CollisionStrategy,
+    candidates: Vec<Candidate>,
+) -> (Vec<Candidate>, Vec<Collision>) {
+    let mut groups: Vec<Vec<Candidate>> = Vec::new();
+    for candidate in candidates {
+        match groups
+            .iter_mut()
+            .find(|g| g[0].manifest.name == candidate.manifest.name)
+        {
+            Some(group) => group.push(candidate),
+            None => groups.push(vec![candidate]),
+        }
+    }
+
+    let mut taken: HashSet<String> = groups.iter().map(|g| g[0].manifest.name.clone()).collect();
+    let mut chosen = Vec::new();
+    let mut collisions = Vec::new();
+
+    for mut group in groups {
+        if group.len() == 1 {
+            chosen.extend(group);
+            continue;
+        }
+
+        let name = group[0].manifest.name.clone();
+        let paths: Vec<PathBuf> = group.iter().map(|c| c.path.clone()).collect();
+        tracing::warn!(
+            "{} manifests declare plugin {}: {:?}",
+            paths.len(),
+            name,
+            paths
+        );
+
+        let resolution = match strategy {
+            CollisionStrategy::Error => CollisionResolution::Rejected,
+            CollisionStrategy::PreferFirstDir => {
+                let first = group.swap_remove(0);
+                let resolution = CollisionResolution::Chose(first.path.clone());
+                chosen.push(first);
+                resolution
+            }
+            CollisionStrategy::PreferHigherVersion => {
+                let mut best = 0;
+                for (i, candidate) in group.iter().enumerate().skip(1) {
+                    if version_key(&candidate.manifest) > version_key(&group[best].manifest) {
+                        best = i;
+                    }
+                }
+                let winner = group.swap_remove(best);
+                let resolution = CollisionResolution::Chose(winner.path.clone());
+                chosen.push(winner);
+                resolution
+            }
+            CollisionStrategy::SuffixWithDir => {
+                let mut renamed = Vec::new();
+                for (i, mut candidate) in group.into_iter().enumerate() {
+                    if i > 0 {
+                        let new_name = suffixed_name(&name, &candidate.path, &taken);
+                        taken.insert(new_name.clone());
+                        candidate
+                            .manifest
+                            .metadata
+                            .insert(DISCOVERED_AS.to_string(), name.clone());
+                        candidate.manifest.name = new_name.clone();
+                        renamed.push((candidate.path.clone(), new_name));
+                    }
+                    chosen.push(candidate);
+                }
+                CollisionResolution::Renamed(renamed)
+            }
+        };
+
+        collisions.push(Collision {
+            name,
+            candidates: paths,
+            resolution,
+        });
+    }
+
+    (chosen, collisions)
+}
+
+/// Keep the discovery name of a renamed plugin when its manifest is re-read.
+#[cfg(feature = "serde")]
+pub(crate) fn keep_discovered_name(current: &Manifest, reread: &mut Manifest) {
+    if current.metadata.get(DISCOVERED_AS) == Some(&reread.name) {
+        reread
+            .metadata
+            .insert(DISCOVERED_AS.to_string(), reread.name.clone());
+        reread.name = current.name.clone();
+    }
+}
+
+/// Versions that do not parse sort below every version that does.
+#[cfg(feature = "serde")]
+fn version_key(manifest: &Manifest) -> Option<ApiVersion> {
+    ApiVersion::parse(&manifest.version).ok()
+}
+
+#[cfg(feature = "serde")]
+fn suffixed_name(name: &str, path: &Path, taken: &HashSet<String>) -> String {
+    let dir = path
+        .parent()
+        .and_then(|p| p.file_name())
+        .map(|d| d.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "dup".to_string());
+    let base = format!("{}-{}", name, dir);
+
+    let mut candidate = base.clone();
+    let mut n = 2;
+    while taken.contains(&candidate) {
+        candidate = format!("{}-{}", base, n);
+        n += 1;
+    }
+    candidate
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_dedupes() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("plugin.toml"), "").unwrap();
+        std::fs::write(dir.path().join("other.toml"), "").unwrap();
+        std::fs::write(dir.path().join("main.fsx"), "").unwrap();
+
+        let found = scan(
+            &[dir.path().to_path_buf()],
+            &["*.toml".to_string(), "plugin.toml".to_string()],
+        );
+        assert_eq!(found.len(), 2);
+    }
+
+    #[test]
+    fn test_resolve_collisions() {
+        let candidates = || {
+            vec![
+                Candidate {
+                    path: PathBuf::from("a/p.toml"),
+                    manifest: Manifest::new("p", "1.0.0"),
+                },
+                Candidate {
+                    path: PathBuf::from("b/p.toml"),
+                    manifest: Manifest::new("p", "2.0.0"),
+                },
+            ]
+        };
+
+        let (chosen, collisions) = resolve(CollisionStrategy::Error, candidates());
+        assert!(chosen.is_empty());
+        assert_eq!(collisions[0].resolution, CollisionResolution::Rejected);
+
+        let (chosen, _) = resolve(CollisionStrategy::PreferFirstDir, candidates());
+        assert_eq!(chosen[0].manifest.version, "1.0.0");
+
+        let (chosen, _) = resolve(CollisionStrategy::PreferHigherVersion, candidates());
+        assert_eq!(chosen[0].manifest.version, "2.0.0");
+
+        let (chosen, collisions) = resolve(CollisionStrategy::SuffixWithDir, candidates());
+        assert_eq!(chosen.len(), 2);
+        assert_eq!(chosen[1].manifest.name, "p-b");
+        assert_eq!(
+            collisions[0].resolution,
+            CollisionResolution::Renamed(vec![(PathBuf::from("b/p.toml"), "p-b".to_string())])
+        );
+
+        let mut reread = Manifest::new("p", "2.0.1");
+        keep_discovered_name(&chosen[1].manifest, &mut reread);
+        assert_eq!(reread.name, "p-b");
+    }
+}
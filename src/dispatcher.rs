@@ -0,0 +1,503 @@
+//! Queued plugin calls executed by a bounded worker pool.
+
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::future::Future;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Waker};
+use std::thread::{JoinHandle, ThreadId};
+use std::time::{Duration, Instant};
+
+use fusabi_host::Value;
+use parking_lot::{Condvar, Mutex};
+
+use crate::config_error::ConfigError;
+use crate::error::{Error, Result};
+
+/// Sizing of the call dispatcher.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DispatcherConfig {
+    /// Number of worker threads executing calls.
+    pub workers: usize,
+    /// Calls a single plugin may have waiting before new ones are rejected.
+    pub queue_capacity: usize,
+}
+
+impl Default for DispatcherConfig {
+    fn default() -> Self {
+        Self {
+            workers: std::thread::available_parallelism().map_or(4, |n| n.get()),
+            queue_capacity: 64,
+        }
+    }
+}
+
+impl DispatcherConfig {
+    /// Create a dispatcher configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the number of worker threads.
+    pub fn with_workers(mut self, workers: usize) -> Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    /// Set the number of calls each plugin may have waiting.
+    pub fn with_queue_capacity(mut self, capacity: usize) -> Self {
+        self.queue_capacity = capacity;
+        self
+    }
+
+    /// Check the worker count and queue capacity.
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = ConfigError::new();
+        errors.check(self.workers > 0, "workers", "must be greater than zero");
+        errors.check(
+            self.queue_capacity > 0,
+            "queue_capacity",
+            "must be greater than zero",
+        );
+        errors.into_result()
+    }
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+thread_local! {
+    static ON_WORKER: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Check if the current thread is a dispatcher worker.
+pub(crate) fn on_worker() -> bool {
+    ON_WORKER.with(Cell::get)
+}
+
+#[derive(Default)]
+struct Queues {
+    pending: HashMap<String, VecDeque<Job>>,
+    // Plugins with pending calls, served round-robin
+    ready: VecDeque<String>,
+    closed: bool,
+    // Workers that have not exited yet
+    live: HashSet<ThreadId>,
+}
+
+struct Shared {
+    queues: Mutex<Queues>,
+    available: Condvar,
+    exited: Condvar,
+}
+
+/// Runs plugin calls on a fixed pool of workers.
+///
+/// Each plugin has its own bounded queue, and workers take calls from the
+/// queues in turn so one busy plugin cannot starve the others. A full queue
+/// rejects new calls with [`Error::QueueFull`] instead of blocking the caller.
+pub struct CallDispatcher {
+    config: DispatcherConfig,
+    shared: Arc<Shared>,
+    workers: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl CallDispatcher {
+    /// Start the worker pool.
+    ///
+    /// Fails with [`Error::Config`] if the configuration has no workers or
+    /// no queue capacity.
+    pub fn new(config: DispatcherConfig) -> Result<Self> {
+        config.validate()?;
+        let shared = Arc::new(Shared {
+            queues: Mutex::new(Queues::default()),
+            available: Condvar::new(),
+            exited: Condvar::new(),
+        });
+
+        let mut workers = Vec::with_capacity(config.workers);
+        for i in 0..config.workers {
+            let worker = shared.clone();
+            let handle = std::thread::Builder::new()
+                .name(format!("fusabi-call-{}", i))
+                .spawn(move || work(&worker))?;
+            shared.queues.lock().live.insert(handle.thread().id());
+            workers.push(handle);
+        }
+
+        Ok(Self {
+            config,
+            shared,
+            workers: Mutex::new(workers),
+        })
+    }
+
+    /// Get the dispatcher configuration.
+    pub fn config(&self) -> &DispatcherConfig {
+        &self.config
+    }
+
+    /// Queue `call` for `plugin`, returning a receipt for its result.
+    pub fn submit<F>(&self, plugin: &str, call: F) -> Result<CallReceipt>
+    where
+        F: FnOnce() -> Result<Value> + Send + 'static,
+    {
+        let (receipt, completer) = CallReceipt::pair();
+
+        let mut queues = self.shared.queues.lock();
+        if queues.closed {
+            return Err(Error::cancelled("call"));
+        }
+        let queue = queues.pending.entry(plugin.to_string()).or_default();
+        if queue.len() >= self.config.queue_capacity {
+            return Err(Error::queue_full(plugin, self.config.queue_capacity));
+        }
+        queue.push_back(Box::new(move || completer.complete(call())));
+        if queue.len() == 1 {
+            queues.ready.push_back(plugin.to_string());
+        }
+        drop(queues);
+
+        self.shared.available.notify_one();
+        Ok(receipt)
+    }
+
+    /// Get the number of calls waiting for `plugin`.
+    pub fn queued(&self, plugin: &str) -> usize {
+        self.shared
+            .queues
+            .lock()
+            .pending
+            .get(plugin)
+            .map_or(0, VecDeque::len)
+    }
+
+    /// Get the number of calls waiting across all plugins.
+    pub fn total_queued(&self) -> usize {
+        self.shared
+            .queues
+            .lock()
+            .pending
+            .values()
+            .map(VecDeque::len)
+            .sum()
+    }
+
+    /// Stop accepting calls, cancel the waiting ones and wait for running
+    /// calls to finish.
+    ///
+    /// Called from one of the dispatcher's own calls, it waits for the other
+    /// workers and lets the calling worker exit once its call returns.
+    pub fn shutdown(&self) {
+        self.shutdown_until(None);
+    }
+
+    /// Shut down like [`shutdown`](Self::shutdown), but stop waiting for
+    /// running calls at `deadline`, detaching their workers.
+    pub(crate) fn shutdown_until(&self, deadline: Option<Instant>) {
+        let abandoned = {
+            let mut queues = self.shared.queues.lock();
+            queues.closed = true;
+            queues.ready.clear();
+            std::mem::take(&mut queues.pending)
+        };
+        // Dropping the jobs cancels their receipts
+        drop(abandoned);
+        self.shared.available.notify_all();
+
+        // A worker cannot wait for itself; it exits after its call
+        let current = std::thread::current().id();
+        let workers: Vec<_> = self
+            .workers
+            .lock()
+            .drain(..)
+            .filter(|worker| worker.thread().id() != current)
+            .collect();
+
+        let busy = {
+            let mut queues = self.shared.queues.lock();
+            while queues.live.iter().any(|id| *id != current) {
+                match deadline {
+                    Some(deadline) => {
+                        if self
+                            .shared
+                            .exited
+                            .wait_until(&mut queues, deadline)
+                            .timed_out()
+                        {
+                            break;
+                        }
+                    }
+                    None => self.shared.exited.wait(&mut queues),
+                }
+            }
+            queues.live.clone()
+        };
+
+        for worker in workers {
+            if busy.contains(&worker.thread().id()) {
+                tracing::warn!("Abandoning busy call worker during shutdown");
+                continue;
+            }
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Drop for CallDispatcher {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+impl std::fmt::Debug for CallDispatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CallDispatcher")
+            .field("config", &self.config)
+            .field("queued", &self.total_queued())
+            .finish()
+    }
+}
+
+fn work(shared: &Shared) {
+    ON_WORKER.with(|on_worker| on_worker.set(true));
+    loop {
+        let job = {
+            let mut queues = shared.queues.lock();
+            loop {
+                if queues.closed {
+                    queues.live.remove(&std::thread::current().id());
+                    shared.exited.notify_all();
+                    return;
+                }
+                if let Some(plugin) = queues.ready.pop_front() {
+                    let Some(queue) = queues.pending.get_mut(&plugin) else {
+                        continue;
+                    };
+                    let Some(job) = queue.pop_front() else {
+                        continue;
+                    };
+                    if queue.is_empty() {
+                        queues.pending.remove(&plugin);
+                    } else {
+                        queues.ready.push_back(plugin);
+                    }
+                    break job;
+                }
+                shared.available.wait(&mut queues);
+            }
+        };
+
+        // A panicking call cancels its receipt without taking the worker down
+        let _ = catch_unwind(AssertUnwindSafe(job));
+    }
+}
+
+#[derive(Default)]
+struct Slot {
+    result: Option<Result<Value>>,
+    waker: Option<Waker>,
+}
+
+struct ReceiptState {
+    slot: Mutex<Slot>,
+    done: Condvar,
+}
+
+/// Result of a queued call, available once a worker has run it.
+///
+/// Wait for it with [`wait`](Self::wait), or `.await` it from async code.
+pub struct CallReceipt {
+    state: Arc<ReceiptState>,
+}
+
+/// Completes a receipt, cancelling it if dropped before completion.
+struct Completer {
+    state: Arc<ReceiptState>,
+    completed: bool,
+}
+
+impl CallReceipt {
+    fn pair() -> (Self, Completer) {
+        let state = Arc::new(ReceiptState {
+            slot: Mutex::new(Slot::default()),
+            done: Condvar::new(),
+        });
+        (
+            Self {
+                state: state.clone(),
+            },
+            Completer {
+                state,
+                completed: false,
+            },
+        )
+    }
+
+    /// Check if the call has finished.
+    pub fn is_done(&self) -> bool {
+        self.state.slot.lock().result.is_some()
+    }
+
+    /// Block until the call has finished.
+    pub fn wait(self) -> Result<Value> {
+        let mut slot = self.state.slot.lock();
+        loop {
+            if let Some(result) = slot.result.take() {
+                return result;
+            }
+            self.state.done.wait(&mut slot);
+        }
+    }
+
+    /// Block until the call has finished or `timeout` elapses, returning
+    /// the receipt back on timeout.
+    pub fn wait_timeout(self, timeout: Duration) -> std::result::Result<Result<Value>, Self> {
+        let deadline = Instant::now() + timeout;
+        {
+            let mut slot = self.state.slot.lock();
+            loop {
+                if let Some(result) = slot.result.take() {
+                    return Ok(result);
+                }
+                if self.state.done.wait_until(&mut slot, deadline).timed_out() {
+                    break;
+                }
+            }
+        }
+        Err(self)
+    }
+}
+
+impl Future for CallReceipt {
+    type Output = Result<Value>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut slot = self.state.slot.lock();
+        match slot.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                slot.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for CallReceipt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CallReceipt")
+            .field("done", &self.is_done())
+            .finish()
+    }
+}
+
+impl Completer {
+    fn complete(mut self, result: Result<Value>) {
+        self.fill(result);
+    }
+
+    fn fill(&mut self, result: Result<Value>) {
+        self.completed = true;
+        let waker = {
+            let mut slot = self.state.slot.lock();
+            slot.result = Some(result);
+            slot.waker.take()
+        };
+        self.state.done.notify_all();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl Drop for Completer {
+    fn drop(&mut self) {
+        if !self.completed {
+            self.fill(Err(Error::cancelled("call")));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dispatch_and_wait() {
+        let dispatcher = CallDispatcher::new(DispatcherConfig::new().with_workers(2)).unwrap();
+        let receipt = dispatcher.submit("p", || Ok(Value::Int(7))).unwrap();
+        assert!(matches!(receipt.wait(), Ok(Value::Int(7))));
+
+        let receipt = dispatcher
+            .submit("p", || panic!("plugin call panicked"))
+            .unwrap();
+        assert!(matches!(receipt.wait(), Err(Error::Cancelled(_))));
+    }
+
+    #[test]
+    fn test_queue_backpressure() {
+        let dispatcher = CallDispatcher::new(
+            DispatcherConfig::new()
+                .with_workers(1)
+                .with_queue_capacity(1),
+        )
+        .unwrap();
+
+        // Occupy the only worker until released
+        let (release, blocked) = std::sync::mpsc::channel::<()>();
+        let running = dispatcher
+            .submit("p", move || {
+                let _ = blocked.recv();
+                Ok(Value::Null)
+            })
+            .unwrap();
+        while dispatcher.queued("p") > 0 {
+            std::thread::yield_now();
+        }
+
+        let queued = dispatcher.submit("p", || Ok(Value::Int(1))).unwrap();
+        assert!(matches!(
+            dispatcher.submit("p", || Ok(Value::Null)),
+            Err(Error::QueueFull { capacity: 1, .. })
+        ));
+        // Other plugins have their own queue
+        let other = dispatcher.submit("q", || Ok(Value::Int(2))).unwrap();
+        let queued = queued.wait_timeout(Duration::from_millis(10)).unwrap_err();
+
+        release.send(()).unwrap();
+        assert!(running.wait().is_ok());
+        assert!(matches!(queued.wait(), Ok(Value::Int(1))));
+        assert!(matches!(other.wait(), Ok(Value::Int(2))));
+    }
+
+    #[test]
+    fn test_shutdown_from_worker() {
+        let dispatcher =
+            Arc::new(CallDispatcher::new(DispatcherConfig::new().with_workers(2)).unwrap());
+        let inner = dispatcher.clone();
+        let receipt = dispatcher
+            .submit("p", move || {
+                inner.shutdown();
+                Ok(Value::Int(1))
+            })
+            .unwrap();
+        assert!(matches!(
+            receipt.wait_timeout(Duration::from_secs(5)),
+            Ok(Ok(Value::Int(1)))
+        ));
+        assert!(matches!(
+            dispatcher.submit("p", || Ok(Value::Null)),
+            Err(Error::Cancelled(_))
+        ));
+    }
+
+    #[test]
+    fn test_config_validate() {
+        match CallDispatcher::new(DispatcherConfig::new().with_queue_capacity(0)) {
+            Err(Error::Config(e)) => assert_eq!(e.paths(), vec!["queue_capacity"]),
+            other => panic!("expected a config error, got {:?}", other),
+        }
+    }
+}
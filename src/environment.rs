@@ -0,0 +1,65 @@
+//! Environment variables plugins may read.
+
+use std::collections::BTreeSet;
+
+/// Capability a plugin must declare to read environment variables.
+pub(crate) const ENV_READ: &str = "sys:env";
+
+/// The `[env]` table of a manifest.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EnvConfig {
+    /// Environment variables the plugin wants to read.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub allow: Vec<String>,
+}
+
+impl EnvConfig {
+    /// Check if the plugin wants no environment variables.
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty()
+    }
+}
+
+/// Host policy on the environment variables plugins may read.
+///
+/// A plugin reads a variable only if it declares `sys:env`, lists the
+/// variable under `[env] allow` and the policy allows it. The default policy
+/// allows none.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnvPolicy {
+    allow: BTreeSet<String>,
+}
+
+impl EnvPolicy {
+    /// Create a policy allowing no variables.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow plugins to read a variable.
+    pub fn allow(mut self, name: impl Into<String>) -> Self {
+        self.allow.insert(name.into());
+        self
+    }
+
+    /// Check if plugins may read a variable.
+    pub fn allows(&self, name: &str) -> bool {
+        self.allow.contains(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_policy_defaults_to_none() {
+        assert!(!EnvPolicy::default().allows("LANG"));
+
+        let policy = EnvPolicy::new().allow("LANG").allow("TZ");
+        assert!(policy.allows("TZ"));
+        assert!(!policy.allows("HOME"));
+    }
+}
@@ -1,7 +1,15 @@
 //! Error types for plugin runtime operations.
 
+use std::fmt;
+use std::path::PathBuf;
+
 use thiserror::Error;
 
+use crate::config_error::ConfigError;
+use crate::diagnostic::SourceSpan;
+use crate::features::Feature;
+use crate::policy::PolicyViolation;
+
 /// Result type alias using [`enum@Error`].
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -16,6 +24,16 @@ pub enum Error {
     #[error("plugin already loaded: {0}")]
     PluginAlreadyLoaded(String),
 
+    /// A plugin name without a namespace matches plugins in several
+    /// namespaces.
+    #[error("plugin name {name} is ambiguous between {}", candidates.join(", "))]
+    AmbiguousPluginName {
+        /// Name looked up.
+        name: String,
+        /// Namespaced names it matches, sorted.
+        candidates: Vec<String>,
+    },
+
     /// Invalid manifest.
     #[error("invalid manifest: {0}")]
     InvalidManifest(String),
@@ -33,6 +51,16 @@ pub enum Error {
         provided: String,
     },
 
+    /// Bytecode was built for another engine version and has no source to
+    /// recompile from.
+    #[error("incompatible bytecode {path}: {reason}")]
+    IncompatibleBytecode {
+        /// Path of the bytecode file.
+        path: String,
+        /// Why the engine cannot run it.
+        reason: String,
+    },
+
     /// Missing required capability.
     #[error("missing required capability: {0}")]
     MissingCapability(String),
@@ -41,6 +69,69 @@ pub enum Error {
     #[error("capability not declared in manifest: {0}")]
     UndeclaredCapability(String),
 
+    /// Access outside the scope of a declared capability.
+    #[error("capability {capability} does not allow access to {target}")]
+    CapabilityDenied {
+        /// Capability name.
+        capability: String,
+        /// Denied path or host.
+        target: String,
+    },
+
+    /// Capability refused by the loader's capability policy.
+    #[error("capability {capability} not granted: {reason}")]
+    CapabilityNotGranted {
+        /// Capability name.
+        capability: String,
+        /// Reason given by the policy.
+        reason: String,
+    },
+
+    /// Call to a plugin export refused by the call authorizer.
+    #[error("{principal} may not call {plugin}.{export}")]
+    CallDenied {
+        /// Calling principal.
+        principal: String,
+        /// Plugin name.
+        plugin: String,
+        /// Export name.
+        export: String,
+    },
+
+    /// A host function required by a plugin is not registered.
+    #[error("plugin {plugin} requires host function {function}, which is not registered")]
+    MissingHostFunction {
+        /// Plugin requiring the function.
+        plugin: String,
+        /// Missing host function.
+        function: String,
+    },
+
+    /// A declared export is not defined by the plugin's code.
+    #[error("plugin {plugin} declares export {export}, which its code does not define")]
+    MissingExport {
+        /// Plugin name.
+        plugin: String,
+        /// Declared export.
+        export: String,
+    },
+
+    /// A plugin breaks rules of the loader's policy file.
+    #[error(
+        "plugin {plugin} violates the load policy: {}",
+        join_violations(violations)
+    )]
+    PolicyViolation {
+        /// Plugin name.
+        plugin: String,
+        /// Rules broken, in the order they were checked.
+        violations: Vec<PolicyViolation>,
+    },
+
+    /// A signature could not be verified.
+    #[error("signature verification failed: {0}")]
+    SignatureInvalid(String),
+
     /// Dependency not satisfied.
     #[error("dependency not satisfied: {name} requires {version}")]
     DependencyNotSatisfied {
@@ -50,6 +141,10 @@ pub enum Error {
         version: String,
     },
 
+    /// Plugin dependencies form a cycle.
+    #[error("dependency cycle: {0}")]
+    DependencyCycle(String),
+
     /// Plugin initialization failed.
     #[error("plugin initialization failed: {0}")]
     InitializationFailed(String),
@@ -71,6 +166,14 @@ pub enum Error {
     #[error("function not found: {0}")]
     FunctionNotFound(String),
 
+    /// No plugin provides a command-line subcommand.
+    #[error("command not found: {0}")]
+    CommandNotFound(String),
+
+    /// Invalid arguments for a plugin call.
+    #[error("invalid arguments: {0}")]
+    InvalidArguments(String),
+
     /// Compilation error.
     #[error("compilation error: {0}")]
     Compilation(String),
@@ -88,11 +191,28 @@ pub enum Error {
     #[error("manifest parse error: {0}")]
     ManifestParse(String),
 
+    /// Manifest error at a known position.
+    #[cfg(feature = "serde")]
+    #[error("manifest error at line {line}, column {column}: {reason}")]
+    ManifestSyntax {
+        /// 1-based line.
+        line: usize,
+        /// 1-based column.
+        column: usize,
+        /// What is wrong.
+        reason: String,
+    },
+
     /// Watch error.
     #[cfg(feature = "watch")]
     #[error("watch error: {0}")]
     Watch(String),
 
+    /// Plugin worker process crashed, hung or could not be started.
+    #[cfg(feature = "isolation")]
+    #[error("plugin process error: {0}")]
+    Isolation(String),
+
     /// Plugin was unloaded.
     #[error("plugin was unloaded")]
     PluginUnloaded,
@@ -101,9 +221,155 @@ pub enum Error {
     #[error("plugin reload failed: {0}")]
     ReloadFailed(String),
 
+    /// Rolling a plugin back to a previous version failed.
+    #[error("plugin rollback failed: {0}")]
+    RollbackFailed(String),
+
     /// Registry error.
     #[error("registry error: {0}")]
     Registry(String),
+
+    /// Runtime, loader or watch configuration is invalid.
+    #[error("invalid configuration: {0}")]
+    Config(ConfigError),
+
+    /// Plugin exceeded a storage or asset quota.
+    #[error("quota exceeded for plugin {plugin}: {resource} limit is {limit}")]
+    QuotaExceeded {
+        /// Plugin name.
+        plugin: String,
+        /// Exceeded resource.
+        resource: String,
+        /// Configured limit.
+        limit: usize,
+    },
+
+    /// Admin credentials were missing or invalid.
+    #[error("unauthenticated: {0}")]
+    Unauthenticated(String),
+
+    /// Admin principal lacks the role for an operation.
+    #[error("permission denied: {principal} may not {operation}")]
+    PermissionDenied {
+        /// Principal identity.
+        principal: String,
+        /// Denied operation.
+        operation: String,
+    },
+
+    /// Caller or plugin exceeded its rate limit.
+    #[error("rate limited: {key}, retry in {retry_after:?}")]
+    RateLimited {
+        /// Rate-limited key, e.g. a principal identity or a plugin name.
+        key: String,
+        /// Time until the next attempt may succeed.
+        retry_after: std::time::Duration,
+    },
+
+    /// A plugin used up its execution budget.
+    #[error("plugin {plugin} exceeded its {budget} budget, retry in {retry_after:?}")]
+    BudgetExceeded {
+        /// Throttled plugin.
+        plugin: String,
+        /// Exceeded budget: `calls` or `time`.
+        budget: String,
+        /// Time until the budget allows a call again.
+        retry_after: std::time::Duration,
+    },
+
+    /// A plugin's call queue is full.
+    #[error("call queue full for plugin {plugin}: {capacity} calls pending")]
+    QueueFull {
+        /// Plugin whose queue is full.
+        plugin: String,
+        /// Number of calls the queue holds.
+        capacity: usize,
+    },
+
+    /// Network request or configuration error.
+    #[error("network error: {0}")]
+    Network(String),
+
+    /// Network access attempted while offline mode is enabled.
+    #[error("offline mode: {0} requires network access")]
+    OfflineMode(String),
+
+    /// Operation was cancelled through a cancellation token.
+    #[error("cancelled: {0}")]
+    Cancelled(String),
+
+    /// Functionality was requested that the crate was built without.
+    #[error("the `{feature}` feature is not enabled (needed for {what})")]
+    FeatureDisabled {
+        /// Cargo feature name.
+        feature: String,
+        /// What needed the feature.
+        what: String,
+    },
+
+    /// An error at a known position in a plugin's source.
+    #[error("{span}: {source}")]
+    Located {
+        /// Where the error occurred.
+        span: SourceSpan,
+        /// The error itself.
+        source: Box<Error>,
+    },
+
+    /// An error raised while the runtime worked on a plugin.
+    #[error(
+        "{op} failed for plugin {name}{}: {source}",
+        file.as_ref().map(|f| format!(" ({})", f.display())).unwrap_or_default()
+    )]
+    InPlugin {
+        /// Plugin name.
+        name: String,
+        /// Operation that failed.
+        op: PluginOperation,
+        /// Manifest, source or bytecode file the operation worked on.
+        file: Option<PathBuf>,
+        /// The error itself.
+        source: Box<Error>,
+    },
+}
+
+/// Operation the runtime was running on a plugin when an error occurred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum PluginOperation {
+    /// Reading, validating and registering the plugin.
+    Load,
+    /// Compiling the plugin's source.
+    Compile,
+    /// Calling one of the plugin's functions.
+    Call,
+    /// Reloading the plugin from disk.
+    Reload,
+    /// Starting the plugin.
+    Start,
+    /// Stopping the plugin.
+    Stop,
+}
+
+impl PluginOperation {
+    /// Get the operation's name.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Load => "load",
+            Self::Compile => "compile",
+            Self::Call => "call",
+            Self::Reload => "reload",
+            Self::Start => "start",
+            Self::Stop => "stop",
+        }
+    }
+}
+
+impl fmt::Display for PluginOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
 }
 
 impl Error {
@@ -112,11 +378,29 @@ impl Error {
         Self::PluginNotFound(name.into())
     }
 
+    /// Create an ambiguous plugin name error.
+    pub fn ambiguous_plugin_name(name: impl Into<String>, candidates: Vec<String>) -> Self {
+        Self::AmbiguousPluginName {
+            name: name.into(),
+            candidates,
+        }
+    }
+
     /// Create an invalid manifest error.
     pub fn invalid_manifest(msg: impl Into<String>) -> Self {
         Self::InvalidManifest(msg.into())
     }
 
+    /// Create a manifest error at a line and column.
+    #[cfg(feature = "serde")]
+    pub fn manifest_syntax(line: usize, column: usize, reason: impl Into<String>) -> Self {
+        Self::ManifestSyntax {
+            line,
+            column,
+            reason: reason.into(),
+        }
+    }
+
     /// Create a missing manifest field error.
     pub fn missing_field(field: impl Into<String>) -> Self {
         Self::MissingManifestField(field.into())
@@ -130,11 +414,67 @@ impl Error {
         }
     }
 
+    /// Create an incompatible bytecode error.
+    pub fn incompatible_bytecode(path: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self::IncompatibleBytecode {
+            path: path.into(),
+            reason: reason.into(),
+        }
+    }
+
     /// Create a missing capability error.
     pub fn missing_capability(cap: impl Into<String>) -> Self {
         Self::MissingCapability(cap.into())
     }
 
+    /// Create a capability denied error.
+    pub fn capability_denied(capability: impl Into<String>, target: impl Into<String>) -> Self {
+        Self::CapabilityDenied {
+            capability: capability.into(),
+            target: target.into(),
+        }
+    }
+
+    /// Create a capability not granted error.
+    pub fn capability_not_granted(
+        capability: impl Into<String>,
+        reason: impl Into<String>,
+    ) -> Self {
+        Self::CapabilityNotGranted {
+            capability: capability.into(),
+            reason: reason.into(),
+        }
+    }
+
+    /// Create a call denied error.
+    pub fn call_denied(
+        principal: impl Into<String>,
+        plugin: impl Into<String>,
+        export: impl Into<String>,
+    ) -> Self {
+        Self::CallDenied {
+            principal: principal.into(),
+            plugin: plugin.into(),
+            export: export.into(),
+        }
+    }
+
+    /// Create a missing host function error.
+    pub fn missing_host_function(plugin: impl Into<String>, function: impl Into<String>) -> Self {
+        Self::MissingHostFunction {
+            plugin: plugin.into(),
+            function: function.into(),
+        }
+    }
+
+    /// Create a missing export error.
+    pub fn missing_export(plugin: impl Into<String>, export: impl Into<String>) -> Self {
+        Self::MissingExport {
+            plugin: plugin.into(),
+            export: export.into(),
+        }
+    }
+
     /// Create a dependency not satisfied error.
     pub fn dependency_not_satisfied(name: impl Into<String>, version: impl Into<String>) -> Self {
         Self::DependencyNotSatisfied {
@@ -153,6 +493,11 @@ impl Error {
         Self::ExecutionFailed(msg.into())
     }
 
+    /// Create an invalid arguments error.
+    pub fn invalid_arguments(msg: impl Into<String>) -> Self {
+        Self::InvalidArguments(msg.into())
+    }
+
     /// Create an invalid state error.
     pub fn invalid_state(expected: impl Into<String>, actual: impl Into<String>) -> Self {
         Self::InvalidState {
@@ -161,23 +506,512 @@ impl Error {
         }
     }
 
+    /// Create a quota exceeded error.
+    pub fn quota_exceeded(
+        plugin: impl Into<String>,
+        resource: impl Into<String>,
+        limit: usize,
+    ) -> Self {
+        Self::QuotaExceeded {
+            plugin: plugin.into(),
+            resource: resource.into(),
+            limit,
+        }
+    }
+
+    /// Create an unauthenticated error.
+    pub fn unauthenticated(msg: impl Into<String>) -> Self {
+        Self::Unauthenticated(msg.into())
+    }
+
+    /// Create a permission denied error.
+    pub fn permission_denied(principal: impl Into<String>, operation: impl Into<String>) -> Self {
+        Self::PermissionDenied {
+            principal: principal.into(),
+            operation: operation.into(),
+        }
+    }
+
+    /// Create a rate limited error.
+    pub fn rate_limited(key: impl Into<String>, retry_after: std::time::Duration) -> Self {
+        Self::RateLimited {
+            key: key.into(),
+            retry_after,
+        }
+    }
+
+    /// Create a budget exceeded error.
+    pub fn budget_exceeded(
+        plugin: impl Into<String>,
+        budget: impl Into<String>,
+        retry_after: std::time::Duration,
+    ) -> Self {
+        Self::BudgetExceeded {
+            plugin: plugin.into(),
+            budget: budget.into(),
+            retry_after,
+        }
+    }
+
+    /// Create a queue full error.
+    pub fn queue_full(plugin: impl Into<String>, capacity: usize) -> Self {
+        Self::QueueFull {
+            plugin: plugin.into(),
+            capacity,
+        }
+    }
+
+    /// Create a network error.
+    pub fn network(msg: impl Into<String>) -> Self {
+        Self::Network(msg.into())
+    }
+
+    /// Create an offline mode error.
+    pub fn offline_mode(subsystem: impl Into<String>) -> Self {
+        Self::OfflineMode(subsystem.into())
+    }
+
+    /// Create a policy violation error.
+    pub fn policy_violation(plugin: impl Into<String>, violations: Vec<PolicyViolation>) -> Self {
+        Self::PolicyViolation {
+            plugin: plugin.into(),
+            violations,
+        }
+    }
+
+    /// Create a feature disabled error.
+    pub fn feature_disabled(feature: Feature, what: impl Into<String>) -> Self {
+        Self::FeatureDisabled {
+            feature: feature.name().to_string(),
+            what: what.into(),
+        }
+    }
+
+    /// Attach the position in the plugin's source the error refers to.
+    pub fn at(self, span: SourceSpan) -> Self {
+        Self::Located {
+            span,
+            source: Box::new(self),
+        }
+    }
+
+    /// Record the plugin, operation and file the error relates to.
+    ///
+    /// Errors that already carry a plugin context, such as
+    /// [`Error::PluginNotFound`] or [`Error::CallDenied`], and errors about
+    /// the caller rather than the plugin, such as [`Error::Cancelled`], are
+    /// returned unchanged. A compilation error during a load is recorded as
+    /// a [`PluginOperation::Compile`].
+    pub fn in_plugin(
+        self,
+        name: impl Into<String>,
+        op: PluginOperation,
+        file: Option<PathBuf>,
+    ) -> Self {
+        if self.has_plugin_context() {
+            return self;
+        }
+        let op = match (op, self.root()) {
+            (PluginOperation::Load, Self::Compilation(_)) => PluginOperation::Compile,
+            (op, _) => op,
+        };
+        Self::InPlugin {
+            name: name.into(),
+            op,
+            file,
+            source: Box::new(self),
+        }
+    }
+
+    fn has_plugin_context(&self) -> bool {
+        matches!(
+            self.root(),
+            Self::PluginNotFound(_)
+                | Self::PluginAlreadyLoaded(_)
+                | Self::AmbiguousPluginName { .. }
+                | Self::CallDenied { .. }
+                | Self::MissingHostFunction { .. }
+                | Self::MissingExport { .. }
+                | Self::PolicyViolation { .. }
+                | Self::QuotaExceeded { .. }
+                | Self::BudgetExceeded { .. }
+                | Self::QueueFull { .. }
+                | Self::Config(_)
+                | Self::OfflineMode(_)
+                | Self::Cancelled(_)
+        ) || matches!(self, Self::InPlugin { .. })
+    }
+
+    /// Get the name of the plugin the error relates to, if recorded with
+    /// [`in_plugin`](Self::in_plugin).
+    pub fn plugin(&self) -> Option<&str> {
+        match self {
+            Self::InPlugin { name, .. } => Some(name),
+            _ => None,
+        }
+    }
+
+    /// Get the operation that failed, if recorded with
+    /// [`in_plugin`](Self::in_plugin).
+    pub fn operation(&self) -> Option<PluginOperation> {
+        match self {
+            Self::InPlugin { op, .. } => Some(*op),
+            _ => None,
+        }
+    }
+
+    /// Get the file the error relates to, if recorded with
+    /// [`in_plugin`](Self::in_plugin).
+    pub fn file(&self) -> Option<&std::path::Path> {
+        match self {
+            Self::InPlugin { file, .. } => file.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Get the position in the plugin's source the error refers to, if
+    /// known.
+    pub fn span(&self) -> Option<&SourceSpan> {
+        match self {
+            Self::Located { span, .. } => Some(span),
+            Self::InPlugin { source, .. } => source.span(),
+            _ => None,
+        }
+    }
+
+    /// Get the error without the position or plugin context attached to
+    /// it.
+    pub fn root(&self) -> &Error {
+        match self {
+            Self::Located { source, .. } | Self::InPlugin { source, .. } => source.root(),
+            other => other,
+        }
+    }
+
+    /// Stable machine-readable code of the kind of error, e.g. `E3001`
+    /// for compilation errors.
+    ///
+    /// Codes are grouped by thousands: `E1xxx` loading and manifests,
+    /// `E2xxx` capabilities and access control, `E3xxx` compilation and
+    /// execution, `E4xxx` reloading and the registry, `E5xxx` quotas and
+    /// throttling, `E6xxx` the host environment. A code is never reused
+    /// for another kind of error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::PluginNotFound(_) => "E1001",
+            Self::PluginAlreadyLoaded(_) => "E1002",
+            Self::AmbiguousPluginName { .. } => "E1003",
+            Self::InvalidManifest(_) => "E1004",
+            Self::MissingManifestField(_) => "E1005",
+            Self::ApiVersionMismatch { .. } => "E1006",
+            Self::IncompatibleBytecode { .. } => "E1007",
+            #[cfg(feature = "serde")]
+            Self::ManifestParse(_) => "E1008",
+            #[cfg(feature = "serde")]
+            Self::ManifestSyntax { .. } => "E1009",
+            Self::DependencyNotSatisfied { .. } => "E1010",
+            Self::DependencyCycle(_) => "E1011",
+            Self::MissingHostFunction { .. } => "E1012",
+            Self::MissingExport { .. } => "E1013",
+            Self::PolicyViolation { .. } => "E1014",
+            Self::MissingCapability(_) => "E2001",
+            Self::UndeclaredCapability(_) => "E2002",
+            Self::CapabilityDenied { .. } => "E2003",
+            Self::CapabilityNotGranted { .. } => "E2004",
+            Self::CallDenied { .. } => "E2005",
+            Self::SignatureInvalid(_) => "E2006",
+            Self::Unauthenticated(_) => "E2007",
+            Self::PermissionDenied { .. } => "E2008",
+            Self::Compilation(_) => "E3001",
+            Self::InitializationFailed(_) => "E3002",
+            Self::ExecutionFailed(_) => "E3003",
+            Self::FunctionNotFound(_) => "E3004",
+            Self::CommandNotFound(_) => "E3005",
+            Self::InvalidArguments(_) => "E3006",
+            Self::InvalidState { .. } => "E3007",
+            Self::PluginUnloaded => "E3008",
+            Self::Cancelled(_) => "E3009",
+            Self::ReloadFailed(_) => "E4001",
+            Self::RollbackFailed(_) => "E4002",
+            Self::Registry(_) => "E4003",
+            #[cfg(feature = "watch")]
+            Self::Watch(_) => "E4004",
+            #[cfg(feature = "isolation")]
+            Self::Isolation(_) => "E4005",
+            Self::QuotaExceeded { .. } => "E5001",
+            Self::RateLimited { .. } => "E5002",
+            Self::BudgetExceeded { .. } => "E5003",
+            Self::QueueFull { .. } => "E5004",
+            Self::Io(_) => "E6001",
+            Self::Host(_) => "E6002",
+            Self::Config(_) => "E6003",
+            Self::Network(_) => "E6004",
+            Self::OfflineMode(_) => "E6005",
+            Self::FeatureDisabled { .. } => "E6006",
+            Self::Located { source, .. } | Self::InPlugin { source, .. } => source.code(),
+        }
+    }
+
+    /// Stable key identifying the kind of error, used to look up
+    /// user-facing message templates.
+    pub fn message_key(&self) -> &'static str {
+        match self {
+            Self::PluginNotFound(_) => "plugin_not_found",
+            Self::PluginAlreadyLoaded(_) => "plugin_already_loaded",
+            Self::AmbiguousPluginName { .. } => "ambiguous_plugin_name",
+            Self::InvalidManifest(_) => "invalid_manifest",
+            Self::MissingManifestField(_) => "missing_manifest_field",
+            Self::ApiVersionMismatch { .. } => "api_version_mismatch",
+            Self::IncompatibleBytecode { .. } => "incompatible_bytecode",
+            Self::MissingCapability(_) => "missing_capability",
+            Self::UndeclaredCapability(_) => "undeclared_capability",
+            Self::CapabilityDenied { .. } => "capability_denied",
+            Self::CapabilityNotGranted { .. } => "capability_not_granted",
+            Self::CallDenied { .. } => "call_denied",
+            Self::MissingHostFunction { .. } => "missing_host_function",
+            Self::MissingExport { .. } => "missing_export",
+            Self::PolicyViolation { .. } => "policy_violation",
+            Self::SignatureInvalid(_) => "signature_invalid",
+            Self::DependencyNotSatisfied { .. } => "dependency_not_satisfied",
+            Self::DependencyCycle(_) => "dependency_cycle",
+            Self::InitializationFailed(_) => "initialization_failed",
+            Self::ExecutionFailed(_) => "execution_failed",
+            Self::InvalidState { .. } => "invalid_state",
+            Self::FunctionNotFound(_) => "function_not_found",
+            Self::CommandNotFound(_) => "command_not_found",
+            Self::InvalidArguments(_) => "invalid_arguments",
+            Self::Compilation(_) => "compilation",
+            Self::Io(_) => "io",
+            Self::Host(_) => "host",
+            #[cfg(feature = "serde")]
+            Self::ManifestParse(_) => "manifest_parse",
+            #[cfg(feature = "serde")]
+            Self::ManifestSyntax { .. } => "manifest_syntax",
+            #[cfg(feature = "watch")]
+            Self::Watch(_) => "watch",
+            #[cfg(feature = "isolation")]
+            Self::Isolation(_) => "isolation",
+            Self::PluginUnloaded => "plugin_unloaded",
+            Self::ReloadFailed(_) => "reload_failed",
+            Self::RollbackFailed(_) => "rollback_failed",
+            Self::Registry(_) => "registry",
+            Self::Config(_) => "invalid_config",
+            Self::QuotaExceeded { .. } => "quota_exceeded",
+            Self::Unauthenticated(_) => "unauthenticated",
+            Self::PermissionDenied { .. } => "permission_denied",
+            Self::RateLimited { .. } => "rate_limited",
+            Self::BudgetExceeded { .. } => "budget_exceeded",
+            Self::QueueFull { .. } => "queue_full",
+            Self::Network(_) => "network",
+            Self::OfflineMode(_) => "offline_mode",
+            Self::Cancelled(_) => "cancelled",
+            Self::FeatureDisabled { .. } => "feature_disabled",
+            Self::Located { source, .. } | Self::InPlugin { source, .. } => source.message_key(),
+        }
+    }
+
+    /// Named parameters substituted into message templates.
+    pub fn message_params(&self) -> Vec<(&'static str, String)> {
+        match self {
+            Self::PluginNotFound(plugin) | Self::PluginAlreadyLoaded(plugin) => {
+                vec![("plugin", plugin.clone())]
+            }
+            Self::AmbiguousPluginName { name, candidates } => vec![
+                ("plugin", name.clone()),
+                ("candidates", candidates.join(", ")),
+            ],
+            Self::MissingManifestField(field) => vec![("field", field.clone())],
+            Self::ApiVersionMismatch { required, provided } => vec![
+                ("required", required.clone()),
+                ("provided", provided.clone()),
+            ],
+            Self::IncompatibleBytecode { path, reason } => {
+                vec![("path", path.clone()), ("reason", reason.clone())]
+            }
+            Self::MissingCapability(capability) | Self::UndeclaredCapability(capability) => {
+                vec![("capability", capability.clone())]
+            }
+            Self::CapabilityDenied { capability, target } => vec![
+                ("capability", capability.clone()),
+                ("target", target.clone()),
+            ],
+            Self::CapabilityNotGranted { capability, reason } => vec![
+                ("capability", capability.clone()),
+                ("reason", reason.clone()),
+            ],
+            Self::CallDenied {
+                principal,
+                plugin,
+                export,
+            } => vec![
+                ("principal", principal.clone()),
+                ("plugin", plugin.clone()),
+                ("function", export.clone()),
+            ],
+            Self::MissingHostFunction { plugin, function } => {
+                vec![("plugin", plugin.clone()), ("function", function.clone())]
+            }
+            Self::MissingExport { plugin, export } => {
+                vec![("plugin", plugin.clone()), ("export", export.clone())]
+            }
+            Self::PolicyViolation { plugin, violations } => vec![
+                ("plugin", plugin.clone()),
+                ("reason", join_violations(violations)),
+            ],
+            Self::SignatureInvalid(reason) => vec![("reason", reason.clone())],
+            Self::DependencyNotSatisfied { name, version } => {
+                vec![("dependency", name.clone()), ("version", version.clone())]
+            }
+            Self::DependencyCycle(cycle) => vec![("cycle", cycle.clone())],
+            Self::InvalidState { expected, actual } => {
+                vec![("expected", expected.clone()), ("actual", actual.clone())]
+            }
+            Self::FunctionNotFound(function) => vec![("function", function.clone())],
+            Self::CommandNotFound(command) => vec![("command", command.clone())],
+            Self::QuotaExceeded {
+                plugin,
+                resource,
+                limit,
+            } => vec![
+                ("plugin", plugin.clone()),
+                ("resource", resource.clone()),
+                ("limit", limit.to_string()),
+            ],
+            Self::PermissionDenied {
+                principal,
+                operation,
+            } => vec![
+                ("principal", principal.clone()),
+                ("operation", operation.clone()),
+            ],
+            Self::RateLimited { key, retry_after } => vec![
+                ("key", key.clone()),
+                ("retry_after", retry_after.as_secs_f64().ceil().to_string()),
+            ],
+            Self::BudgetExceeded {
+                plugin,
+                budget,
+                retry_after,
+            } => vec![
+                ("plugin", plugin.clone()),
+                ("budget", budget.clone()),
+                ("retry_after", retry_after.as_secs_f64().ceil().to_string()),
+            ],
+            Self::QueueFull { plugin, capacity } => vec![
+                ("plugin", plugin.clone()),
+                ("capacity", capacity.to_string()),
+            ],
+            Self::OfflineMode(subsystem) => vec![("subsystem", subsystem.clone())],
+            Self::Cancelled(operation) => vec![("operation", operation.clone())],
+            Self::FeatureDisabled { feature, what } => {
+                vec![("feature", feature.clone()), ("what", what.clone())]
+            }
+            Self::Located { source, .. } => source.message_params(),
+            Self::InPlugin { name, source, .. } => {
+                let mut params = source.message_params();
+                if !params.iter().any(|(param, _)| *param == "plugin") {
+                    params.push(("plugin", name.clone()));
+                }
+                params
+            }
+            Self::PluginUnloaded => Vec::new(),
+            Self::InvalidManifest(reason)
+            | Self::InitializationFailed(reason)
+            | Self::ExecutionFailed(reason)
+            | Self::InvalidArguments(reason)
+            | Self::Compilation(reason)
+            | Self::ReloadFailed(reason)
+            | Self::RollbackFailed(reason)
+            | Self::Registry(reason)
+            | Self::Unauthenticated(reason)
+            | Self::Network(reason) => vec![("reason", reason.clone())],
+            #[cfg(feature = "serde")]
+            Self::ManifestParse(reason) => vec![("reason", reason.clone())],
+            #[cfg(feature = "serde")]
+            Self::ManifestSyntax {
+                line,
+                column,
+                reason,
+            } => vec![
+                ("line", line.to_string()),
+                ("column", column.to_string()),
+                ("reason", reason.clone()),
+            ],
+            #[cfg(feature = "watch")]
+            Self::Watch(reason) => vec![("reason", reason.clone())],
+            #[cfg(feature = "isolation")]
+            Self::Isolation(reason) => vec![("reason", reason.clone())],
+            Self::Config(e) => vec![("reason", e.to_string())],
+            Self::Io(e) => vec![("reason", e.to_string())],
+            Self::Host(e) => vec![("reason", e.to_string())],
+        }
+    }
+
+    /// Create a cancelled error.
+    pub fn cancelled(operation: impl Into<String>) -> Self {
+        Self::Cancelled(operation.into())
+    }
+
     /// Returns true if this error is recoverable.
     pub fn is_recoverable(&self) -> bool {
         matches!(
-            self,
-            Self::PluginNotFound(_) | Self::FunctionNotFound(_) | Self::InvalidState { .. }
+            self.root(),
+            Self::PluginNotFound(_)
+                | Self::AmbiguousPluginName { .. }
+                | Self::FunctionNotFound(_)
+                | Self::CommandNotFound(_)
+                | Self::InvalidState { .. }
+                | Self::RateLimited { .. }
+                | Self::BudgetExceeded { .. }
+                | Self::QueueFull { .. }
+                | Self::Cancelled(_)
         )
     }
 
+    /// Returns true if the same call may succeed when tried again.
+    ///
+    /// Worker failures, throttling, full queues and network errors are
+    /// transient; missing plugins or functions, invalid arguments and
+    /// cancellation are not. Engine failures are not either: the call may
+    /// have run partway, and a supervised plugin is restarting after one.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self.root(),
+            Self::Isolation(_)
+                | Self::RateLimited { .. }
+                | Self::BudgetExceeded { .. }
+                | Self::QueueFull { .. }
+                | Self::Network(_)
+        )
+    }
+
+    /// Get how long to wait before retrying, for throttling errors.
+    pub fn retry_after(&self) -> Option<std::time::Duration> {
+        match self.root() {
+            Self::RateLimited { retry_after, .. } | Self::BudgetExceeded { retry_after, .. } => {
+                Some(*retry_after)
+            }
+            _ => None,
+        }
+    }
+
     /// Returns true if this error should trigger a reload.
     pub fn should_reload(&self) -> bool {
         matches!(
-            self,
+            self.root(),
             Self::Compilation(_) | Self::ExecutionFailed(_) | Self::ReloadFailed(_)
         )
     }
 }
 
+fn join_violations(violations: &[PolicyViolation]) -> String {
+    violations
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -200,4 +1034,70 @@ mod tests {
         assert!(Error::Compilation("test".into()).should_reload());
         assert!(!Error::plugin_not_found("test").should_reload());
     }
+
+    #[test]
+    fn test_error_codes() {
+        assert_eq!(Error::plugin_not_found("test").code(), "E1001");
+        assert_eq!(Error::Compilation("test".into()).code(), "E3001");
+
+        let located = Error::Compilation("test".into()).at(SourceSpan::new(2, 4));
+        assert_eq!(located.code(), "E3001");
+        assert_eq!(located.message_key(), "compilation");
+        assert_eq!(located.span(), Some(&SourceSpan::new(2, 4)));
+        assert!(located.should_reload());
+        assert!(matches!(located.root(), Error::Compilation(_)));
+    }
+
+    #[test]
+    fn test_in_plugin() {
+        let err = Error::execution_failed("division by zero").in_plugin(
+            "search",
+            PluginOperation::Call,
+            Some(PathBuf::from("plugins/search/main.fsx")),
+        );
+        assert_eq!(
+            err.to_string(),
+            "call failed for plugin search (plugins/search/main.fsx): \
+             plugin execution failed: division by zero"
+        );
+        assert_eq!(err.plugin(), Some("search"));
+        assert_eq!(err.operation(), Some(PluginOperation::Call));
+        assert_eq!(err.code(), "E3003");
+        assert!(err.should_reload());
+
+        // Wrapping again keeps the innermost context
+        let err = err.in_plugin("other", PluginOperation::Reload, None);
+        assert_eq!(err.plugin(), Some("search"));
+
+        let err = Error::Compilation("unexpected token".into()).in_plugin(
+            "search",
+            PluginOperation::Load,
+            None,
+        );
+        assert_eq!(err.operation(), Some(PluginOperation::Compile));
+        assert_eq!(
+            err.to_string(),
+            "compile failed for plugin search: compilation error: unexpected token"
+        );
+
+        let err =
+            Error::plugin_not_found("search").in_plugin("search", PluginOperation::Call, None);
+        assert!(matches!(err, Error::PluginNotFound(_)));
+    }
+
+    #[test]
+    fn test_message_params() {
+        let err = Error::capability_denied("fs:read", "/etc");
+        assert_eq!(err.message_key(), "capability_denied");
+        assert_eq!(
+            err.message_params(),
+            vec![
+                ("capability", "fs:read".to_string()),
+                ("target", "/etc".to_string())
+            ]
+        );
+
+        let err = err.in_plugin("notes", PluginOperation::Call, None);
+        assert_eq!(err.message_params().last().unwrap().1, "notes");
+    }
 }
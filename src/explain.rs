@@ -0,0 +1,187 @@
+//! Explaining why a plugin fails to load.
+
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::error::Error;
+
+/// Step a plugin passes through while loading, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LoadGate {
+    /// Reading and parsing the manifest.
+    Parse,
+    /// Validating the manifest fields.
+    Validate,
+    /// Checking the plugin's API version against the host's.
+    ApiVersion,
+    /// Resolving and authorizing the requested capabilities.
+    Capabilities,
+    /// Checking the host functions the plugin requires are registered.
+    HostFunctions,
+    /// Checking required dependencies are loaded at a matching version.
+    Dependencies,
+    /// Compiling the source or reading the bytecode.
+    Compile,
+    /// Creating the plugin's engine and running its `init()`.
+    Init,
+}
+
+impl LoadGate {
+    /// Get the gate's name.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Parse => "parse",
+            Self::Validate => "validate",
+            Self::ApiVersion => "api-version",
+            Self::Capabilities => "capabilities",
+            Self::HostFunctions => "host-functions",
+            Self::Dependencies => "dependencies",
+            Self::Compile => "compile",
+            Self::Init => "init",
+        }
+    }
+}
+
+impl fmt::Display for LoadGate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The gate a plugin failed at.
+#[derive(Debug)]
+pub struct LoadFailure {
+    /// Failing gate.
+    pub gate: LoadGate,
+    /// Error the loader would return.
+    pub error: Error,
+    /// What the loader was looking at, e.g. the resolved entry path.
+    pub context: Vec<String>,
+    /// Changes likely to fix the failure.
+    pub suggestions: Vec<String>,
+}
+
+/// Outcome of [`PluginLoader::explain`](crate::PluginLoader::explain).
+#[derive(Debug)]
+pub struct LoadExplanation {
+    /// Manifest that was checked.
+    pub manifest_path: PathBuf,
+    /// Gates passed, in order.
+    pub passed: Vec<LoadGate>,
+    /// First failing gate, if any.
+    pub failure: Option<LoadFailure>,
+}
+
+impl LoadExplanation {
+    /// Check if the plugin would load.
+    pub fn is_loadable(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+impl fmt::Display for LoadExplanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.manifest_path.display())?;
+        for gate in &self.passed {
+            writeln!(f, "  ok    {}", gate)?;
+        }
+        if let Some(ref failure) = self.failure {
+            writeln!(f, "  FAIL  {}: {}", failure.gate, failure.error)?;
+            for line in &failure.context {
+                writeln!(f, "        {}", line)?;
+            }
+            for suggestion in &failure.suggestions {
+                writeln!(f, "  help: {}", suggestion)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Suggest fixes for an error raised at a gate.
+pub(crate) fn suggestions(gate: LoadGate, error: &Error) -> Vec<String> {
+    let suggestion = match (gate, error.root()) {
+        (LoadGate::Parse, Error::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+            "check the manifest path; relative paths are resolved against the loader's base path"
+                .to_string()
+        }
+        (LoadGate::Parse, Error::ManifestSyntax { line, column, .. }) => {
+            format!("fix the manifest at line {}, column {}", line, column)
+        }
+        (LoadGate::Parse, _) => {
+            "fix the manifest syntax; `Manifest::from_toml_strict` also reports unknown keys"
+                .to_string()
+        }
+        (_, Error::MissingManifestField(field)) => {
+            format!("add `{}` to the manifest", field)
+        }
+        (_, Error::ApiVersionMismatch { provided, .. }) => format!(
+            "build the plugin against API {} or run it on a host that provides its api-version",
+            provided
+        ),
+        (_, Error::CapabilityNotGranted { capability, .. }) => format!(
+            "remove `{}` from the manifest's capabilities or ask the host to allow it",
+            capability
+        ),
+        (LoadGate::Capabilities, Error::InvalidManifest(_)) => {
+            "use capability names the host knows, e.g. `fs:read` or `net:request`".to_string()
+        }
+        (_, Error::MissingHostFunction { function, .. }) => format!(
+            "register the host function `{}` before loading the plugin, or remove it from `host-functions`",
+            function
+        ),
+        (_, Error::MissingExport { export, .. }) => format!(
+            "define `{}` in the plugin's code, or remove it from `exports`",
+            export
+        ),
+        (_, Error::DependencyNotSatisfied { name, version }) => format!(
+            "load `{}` at a version matching `{}` first, or mark the dependency optional",
+            name, version
+        ),
+        (LoadGate::Compile, Error::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+            "check the `source`/`bytecode` path; it is resolved relative to the manifest"
+                .to_string()
+        }
+        (_, Error::Compilation(_)) => "fix the compile errors in the plugin source".to_string(),
+        (_, Error::IncompatibleBytecode { .. }) => {
+            "rebuild the bytecode with the current compiler, ship the .fsx next to it, or \
+             configure a `BytecodeUpgrader`"
+                .to_string()
+        }
+        (_, Error::QuotaExceeded { .. }) => {
+            "shrink the plugin or raise `LoaderConfig::with_max_asset_size`".to_string()
+        }
+        (_, Error::PolicyViolation { .. }) => {
+            "remove what the host's policy forbids from the plugin, sign it, or ask the host's \
+             operators to change the policy"
+                .to_string()
+        }
+        (_, Error::MissingCapability(capability)) => {
+            format!("declare `{}` in the manifest's capabilities", capability)
+        }
+        (LoadGate::Init, _) => {
+            "check the entry file is a valid plugin for its engine, that its `init()` succeeds, \
+             and see the host log"
+                .to_string()
+        }
+        (_, Error::InvalidManifest(_)) => "fix the manifest field named above".to_string(),
+        _ => return Vec::new(),
+    };
+    vec![suggestion]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suggestions() {
+        let missing = Error::Io(std::io::Error::from(std::io::ErrorKind::NotFound));
+        assert!(suggestions(LoadGate::Compile, &missing)[0].contains("relative to the manifest"));
+
+        let dep = Error::dependency_not_satisfied("auth", "^1.0");
+        assert!(suggestions(LoadGate::Dependencies, &dep)[0].contains("`auth`"));
+
+        assert!(suggestions(LoadGate::Dependencies, &Error::PluginUnloaded).is_empty());
+    }
+}
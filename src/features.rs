@@ -0,0 +1,136 @@
+//! Optional Cargo features and the functionality they enable.
+
+use std::fmt;
+
+use crate::config_error::ConfigError;
+use crate::error::{Error, Result};
+
+/// An optional Cargo feature of this crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Feature {
+    /// Manifests, discovery, lockfiles, clustering and rollback.
+    Serde,
+    /// File watching and hot reload.
+    Watch,
+    /// Async streams of file events.
+    Tokio,
+    /// Running plugins in worker processes.
+    Isolation,
+    /// WebAssembly plugins.
+    Wasm,
+    /// JSON Schema generation for manifests.
+    Schema,
+    /// Prometheus metrics.
+    MetricsPrometheus,
+    /// The admin HTTP server.
+    AdminHttp,
+    /// Test helpers such as the mock runtime.
+    Testing,
+}
+
+impl Feature {
+    /// Every optional feature.
+    pub const ALL: [Feature; 9] = [
+        Self::Serde,
+        Self::Watch,
+        Self::Tokio,
+        Self::Isolation,
+        Self::Wasm,
+        Self::Schema,
+        Self::MetricsPrometheus,
+        Self::AdminHttp,
+        Self::Testing,
+    ];
+
+    /// Get the Cargo feature name.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Serde => "serde",
+            Self::Watch => "watch",
+            Self::Tokio => "tokio",
+            Self::Isolation => "isolation",
+            Self::Wasm => "wasm",
+            Self::Schema => "schema",
+            Self::MetricsPrometheus => "metrics-prometheus",
+            Self::AdminHttp => "admin-http",
+            Self::Testing => "testing",
+        }
+    }
+
+    /// Check if the crate was built with the feature.
+    pub fn is_enabled(&self) -> bool {
+        match self {
+            Self::Serde => cfg!(feature = "serde"),
+            Self::Watch => cfg!(feature = "watch"),
+            Self::Tokio => cfg!(feature = "tokio"),
+            Self::Isolation => cfg!(feature = "isolation"),
+            Self::Wasm => cfg!(feature = "wasm"),
+            Self::Schema => cfg!(feature = "schema"),
+            Self::MetricsPrometheus => cfg!(feature = "metrics-prometheus"),
+            Self::AdminHttp => cfg!(feature = "admin-http"),
+            Self::Testing => cfg!(feature = "testing"),
+        }
+    }
+
+    /// Fail with [`Error::FeatureDisabled`] if the crate was built without
+    /// the feature `what` needs.
+    pub fn require(&self, what: &str) -> Result<()> {
+        if self.is_enabled() {
+            Ok(())
+        } else {
+            Err(Error::feature_disabled(*self, what))
+        }
+    }
+
+    /// Record an issue if a configuration field that needs the feature is
+    /// set in a build without it.
+    pub(crate) fn check(&self, errors: &mut ConfigError, set: bool, path: &str) {
+        errors.check(
+            !set || self.is_enabled(),
+            path,
+            format!("requires the `{}` feature, which is not enabled", self),
+        );
+    }
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Get the optional features the crate was built with.
+pub fn enabled_features() -> Vec<Feature> {
+    Feature::ALL
+        .into_iter()
+        .filter(Feature::is_enabled)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enabled_features() {
+        let enabled = enabled_features();
+        assert_eq!(enabled.contains(&Feature::Serde), cfg!(feature = "serde"));
+        assert_eq!(enabled.contains(&Feature::Watch), cfg!(feature = "watch"));
+
+        let mut errors = ConfigError::new();
+        Feature::Wasm.check(&mut errors, false, "unset");
+        Feature::Wasm.check(&mut errors, true, "set");
+        match Feature::Wasm.require("wasm plugins") {
+            Ok(()) => assert!(errors.is_empty()),
+            Err(e) => {
+                assert_eq!(errors.paths(), vec!["set"]);
+                assert_eq!(
+                    e.to_string(),
+                    "the `wasm` feature is not enabled (needed for wasm plugins)"
+                );
+            }
+        }
+    }
+}
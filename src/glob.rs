@@ -0,0 +1,106 @@
+//! Wildcard patterns for plugin discovery and file watching.
+
+#[cfg(feature = "watch")]
+use std::path::{Component, Path};
+
+/// Match `name` against a pattern where `*` matches any run of characters
+/// and `?` matches one character.
+pub(crate) fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((sp, sn)) = star {
+            p = sp + 1;
+            n = sn + 1;
+            star = Some((sp, sn + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Match a path against a glob.
+///
+/// The glob is split on `/` and matched against any run of consecutive path
+/// components, with `**` standing for any number of components. A glob
+/// matching a directory matches everything below it, so `target` and
+/// `.git/` cover whole trees while `*.swp` covers single files. A trailing
+/// `/` restricts the glob to directories.
+///
+/// Callers pass the path relative to the directory the glob applies to, so
+/// components above it never match.
+#[cfg(feature = "watch")]
+pub(crate) fn glob_match(glob: &str, path: &Path) -> bool {
+    let dir_only = glob.ends_with('/');
+    let parts: Vec<&str> = glob.split('/').filter(|part| !part.is_empty()).collect();
+    if parts.is_empty() {
+        return false;
+    }
+    let components: Vec<String> = path
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect();
+
+    // A directory-only glob must end before the last component
+    let last = components.len() - usize::from(dir_only && !components.is_empty());
+    (0..components.len()).any(|start| {
+        (start + 1..=last).any(|end| match_components(&parts, &components[start..end]))
+    })
+}
+
+#[cfg(feature = "watch")]
+fn match_components(parts: &[&str], components: &[String]) -> bool {
+    match parts.split_first() {
+        None => components.is_empty(),
+        Some((&"**", rest)) => {
+            (0..=components.len()).any(|skip| match_components(rest, &components[skip..]))
+        }
+        Some((part, rest)) => components.split_first().is_some_and(|(first, remaining)| {
+            wildcard_match(part, first) && match_components(rest, remaining)
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("*.toml", "plugin.toml"));
+        assert!(wildcard_match("plugin.toml", "plugin.toml"));
+        assert!(wildcard_match("p?ugin.*", "plugin.toml"));
+        assert!(!wildcard_match("*.toml", "plugin.fsx"));
+        assert!(!wildcard_match("plugin", "plugin.toml"));
+    }
+
+    #[cfg(feature = "watch")]
+    #[test]
+    fn test_glob_match() {
+        let path = Path::new("/work/plugins/target/debug/main.fsx");
+        assert!(glob_match("target/", path));
+        assert!(glob_match("target", path));
+        assert!(glob_match("*.fsx", path));
+        assert!(glob_match("plugins/**/main.fsx", path));
+        assert!(glob_match("target/*/main.fsx", path));
+        assert!(!glob_match("main.fsx/", path));
+        assert!(!glob_match("plugins/main.fsx", path));
+        assert!(!glob_match(".git/", path));
+        assert!(glob_match("*~", Path::new("/work/main.fsx~")));
+        assert!(!glob_match("", path));
+    }
+}
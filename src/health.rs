@@ -0,0 +1,68 @@
+//! Plugin health checks through an optional `health` export.
+
+use std::time::Duration;
+
+use fusabi_host::Value;
+
+use crate::error::Result;
+
+/// Export called to check a plugin's health.
+pub(crate) const HEALTH_EXPORT: &str = "health";
+
+/// Outcome of a plugin health check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HealthStatus {
+    /// The check passed.
+    Healthy,
+    /// The check failed, or the plugin is not running.
+    Unhealthy(String),
+    /// The plugin is running but exports no `health` function.
+    Unchecked,
+}
+
+impl HealthStatus {
+    /// Interpret the result of a `health` call.
+    ///
+    /// A call that fails or returns `false` is unhealthy; any other value is
+    /// healthy.
+    pub(crate) fn from_result(result: Result<Value>) -> Self {
+        match result {
+            Ok(Value::Bool(false)) => Self::Unhealthy("health() returned false".to_string()),
+            Ok(_) => Self::Healthy,
+            Err(e) => Self::Unhealthy(e.to_string()),
+        }
+    }
+
+    /// Check if the plugin is not known to be unhealthy.
+    pub fn is_healthy(&self) -> bool {
+        !matches!(self, Self::Unhealthy(_))
+    }
+}
+
+/// Health of one plugin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PluginHealth {
+    /// Plugin name.
+    pub name: String,
+    /// Outcome of the check.
+    pub status: HealthStatus,
+    /// Time the check took.
+    pub duration: Duration,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+
+    #[test]
+    fn test_health_status_from_result() {
+        assert_eq!(
+            HealthStatus::from_result(Ok(Value::Null)),
+            HealthStatus::Healthy
+        );
+        assert!(!HealthStatus::from_result(Ok(Value::Bool(false))).is_healthy());
+        assert!(!HealthStatus::from_result(Err(Error::execution_failed("db down"))).is_healthy());
+        assert!(HealthStatus::Unchecked.is_healthy());
+    }
+}
@@ -0,0 +1,188 @@
+//! Host functions the embedding application makes available to plugins.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use fusabi_host::Value;
+use parking_lot::RwLock;
+
+use crate::error::Result;
+
+/// Implementation of a host function, called with the plugin's arguments.
+pub type HostFn = Arc<dyn Fn(&[Value]) -> Result<Value> + Send + Sync>;
+
+/// Implementation of a host function that acts on behalf of the calling
+/// plugin, called with the plugin's name and arguments.
+pub type ScopedHostFn = Arc<dyn Fn(&str, &[Value]) -> Result<Value> + Send + Sync>;
+
+/// Implementation a host function was registered with.
+#[derive(Clone)]
+enum Binding {
+    Shared(HostFn),
+    Scoped(ScopedHostFn),
+}
+
+/// Set of host functions currently registered by the embedding application.
+///
+/// Plugins declare the host functions they need in their manifest's
+/// `host-functions`. Loading fails while one of them is missing, and the
+/// runtime re-checks loaded plugins whenever the table changes.
+///
+/// Functions registered with an implementation are bound into the engines
+/// created afterwards: wasm plugins import them from the `host` module.
+/// Scoped implementations are bound to the plugin the engine belongs to.
+/// Functions registered by name only are provided to the engine by the
+/// application itself; the table just tracks that they are available.
+#[derive(Default)]
+pub struct HostFunctionTable {
+    functions: RwLock<BTreeMap<String, Option<Binding>>>,
+}
+
+impl HostFunctionTable {
+    /// Create an empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a host function the application binds itself, returning
+    /// whether it was new.
+    pub fn register(&self, name: impl Into<String>) -> bool {
+        self.functions.write().insert(name.into(), None).is_none()
+    }
+
+    /// Register a host function with its implementation, returning whether
+    /// it was new.
+    ///
+    /// Replaces an earlier registration of the same name; engines created
+    /// before keep the implementation they were bound to.
+    pub fn register_fn<F>(&self, name: impl Into<String>, function: F) -> bool
+    where
+        F: Fn(&[Value]) -> Result<Value> + Send + Sync + 'static,
+    {
+        self.functions
+            .write()
+            .insert(name.into(), Some(Binding::Shared(Arc::new(function))))
+            .is_none()
+    }
+
+    /// Register a host function whose implementation is told which plugin
+    /// calls it, returning whether it was new.
+    ///
+    /// Replaces an earlier registration like [`register_fn`](Self::register_fn).
+    pub fn register_scoped_fn<F>(&self, name: impl Into<String>, function: F) -> bool
+    where
+        F: Fn(&str, &[Value]) -> Result<Value> + Send + Sync + 'static,
+    {
+        self.functions
+            .write()
+            .insert(name.into(), Some(Binding::Scoped(Arc::new(function))))
+            .is_none()
+    }
+
+    /// Remove a host function, returning whether it was registered.
+    pub fn unregister(&self, name: &str) -> bool {
+        self.functions.write().remove(name).is_some()
+    }
+
+    /// Check if a host function is registered.
+    pub fn contains(&self, name: &str) -> bool {
+        self.functions.read().contains_key(name)
+    }
+
+    /// Get the implementation of a host function, if it was registered
+    /// with one shared by all plugins.
+    pub fn get(&self, name: &str) -> Option<HostFn> {
+        match self.functions.read().get(name) {
+            Some(Some(Binding::Shared(function))) => Some(function.clone()),
+            _ => None,
+        }
+    }
+
+    /// Get the registered host functions, sorted by name.
+    pub fn names(&self) -> Vec<String> {
+        self.functions.read().keys().cloned().collect()
+    }
+
+    /// Get the functions of `required` that are not registered.
+    pub fn missing(&self, required: &[String]) -> Vec<String> {
+        let functions = self.functions.read();
+        required
+            .iter()
+            .filter(|name| !functions.contains_key(name.as_str()))
+            .cloned()
+            .collect()
+    }
+
+    /// Get the implementations of the functions of `required` that have
+    /// one, to bind into a new engine of `plugin`.
+    pub(crate) fn bind(&self, plugin: &str, required: &[String]) -> BTreeMap<String, HostFn> {
+        let functions = self.functions.read();
+        required
+            .iter()
+            .filter_map(|name| {
+                let function: HostFn = match functions.get(name)?.clone()? {
+                    Binding::Shared(function) => function,
+                    Binding::Scoped(function) => {
+                        let plugin = plugin.to_string();
+                        Arc::new(move |args| function(&plugin, args))
+                    }
+                };
+                Some((name.clone(), function))
+            })
+            .collect()
+    }
+}
+
+impl std::fmt::Debug for HostFunctionTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HostFunctionTable")
+            .field("functions", &self.names())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_function_table() {
+        let table = HostFunctionTable::new();
+        assert!(table.register("ui.notify"));
+        assert!(!table.register("ui.notify"));
+        table.register("db.query");
+
+        let required = vec!["db.query".to_string(), "fs.watch".to_string()];
+        assert_eq!(table.missing(&required), vec!["fs.watch"]);
+
+        assert!(table.unregister("db.query"));
+        assert_eq!(table.missing(&required).len(), 2);
+        assert_eq!(table.names(), vec!["ui.notify"]);
+    }
+
+    #[test]
+    fn test_bind_implementations() {
+        let table = HostFunctionTable::new();
+        table.register("ui.notify");
+        assert!(table.register_fn("math.double", |args| match args {
+            [Value::Int(n)] => Ok(Value::Int(n * 2)),
+            _ => Err(crate::Error::invalid_arguments("expected an int")),
+        }));
+
+        let required = vec!["math.double".to_string(), "ui.notify".to_string()];
+        assert!(table.missing(&required).is_empty());
+        let bound = table.bind("p", &required);
+        assert_eq!(bound.keys().collect::<Vec<_>>(), vec!["math.double"]);
+        assert!(matches!(
+            bound["math.double"](&[Value::Int(21)]),
+            Ok(Value::Int(42))
+        ));
+        assert!(table.get("ui.notify").is_none());
+
+        // Scoped implementations learn which plugin calls them
+        table.register_scoped_fn("whoami", |plugin, _| Ok(Value::String(plugin.to_string())));
+        let bound = table.bind("p", &["whoami".to_string()]);
+        assert!(matches!(bound["whoami"](&[]), Ok(Value::String(ref s)) if s == "p"));
+        assert!(table.get("whoami").is_none());
+    }
+}
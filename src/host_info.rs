@@ -0,0 +1,138 @@
+//! Host API version and build information.
+
+use std::sync::OnceLock;
+
+use fusabi_host::{compile_source, validate_bytecode, CompileOptions, Value};
+
+use crate::features::enabled_features;
+use crate::host_functions::HostFunctionTable;
+use crate::manifest::ApiVersion;
+
+/// Name of the engine backend plugins run on.
+pub const ENGINE_BACKEND: &str = "fusabi-host";
+
+/// Host functions telling plugins about the host, registered by the runtime.
+///
+/// `host.info()` returns the [`HostInfo`] summary as a string, and
+/// `host.api_version()` the host API version as a `[major, minor, patch]`
+/// list, which wasm plugins import as three `i64` results.
+pub const HOST_INFO_FUNCTIONS: [&str; 2] = ["host.info", "host.api_version"];
+
+/// Host API version and build information.
+///
+/// Useful for diagnosing compatibility problems between the host and its
+/// plugins, e.g. in support bundles or admin surfaces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct HostInfo {
+    /// Plugin API version provided by the host.
+    pub api_version: String,
+    /// Version of this crate.
+    pub runtime_version: String,
+    /// Cargo features this crate was built with.
+    pub features: Vec<String>,
+    /// Engine backend name.
+    pub engine_backend: String,
+    /// Compiler version reported by the engine, if it could be determined.
+    pub engine_version: Option<String>,
+}
+
+impl HostInfo {
+    /// Collect host information for a host API version.
+    pub fn collect(api_version: &ApiVersion) -> Self {
+        Self {
+            api_version: api_version.to_string(),
+            runtime_version: crate::VERSION.to_string(),
+            features: enabled_features()
+                .iter()
+                .map(|f| f.name().to_string())
+                .collect(),
+            engine_backend: ENGINE_BACKEND.to_string(),
+            engine_version: engine_version(),
+        }
+    }
+
+    /// Check if the crate was built with a feature.
+    pub fn has_feature(&self, feature: &str) -> bool {
+        self.features.iter().any(|f| f == feature)
+    }
+}
+
+impl std::fmt::Display for HostInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "fusabi-plugin-runtime {} (api {}, {} {}, features: {})",
+            self.runtime_version,
+            self.api_version,
+            self.engine_backend,
+            self.engine_version.as_deref().unwrap_or("unknown"),
+            if self.features.is_empty() {
+                "none".to_string()
+            } else {
+                self.features.join(", ")
+            }
+        )
+    }
+}
+
+/// Register the [`HOST_INFO_FUNCTIONS`] for a host API version in `table`.
+pub(crate) fn register_host_functions(table: &HostFunctionTable, api_version: &ApiVersion) {
+    let version = api_version.clone();
+    table.register_fn("host.info", move |_| {
+        Ok(Value::String(HostInfo::collect(&version).to_string()))
+    });
+    let version = api_version.clone();
+    table.register_fn("host.api_version", move |_| {
+        Ok(Value::List(
+            [version.major, version.minor, version.patch]
+                .into_iter()
+                .map(|part| Value::Int(i64::from(part)))
+                .collect(),
+        ))
+    });
+}
+
+/// The engine exposes no version constant, so compile a trivial program and
+/// read the compiler version stamped into its bytecode.
+pub(crate) fn engine_version() -> Option<String> {
+    static VERSION: OnceLock<Option<String>> = OnceLock::new();
+    VERSION
+        .get_or_init(|| {
+            let compiled = compile_source("()", &CompileOptions::default()).ok()?;
+            validate_bytecode(&compiled.bytecode)
+                .ok()
+                .map(|metadata| metadata.compiler_version)
+        })
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect_host_info() {
+        let info = HostInfo::collect(&ApiVersion::new(0, 18, 0));
+
+        assert_eq!(info.api_version, "0.18.0");
+        assert_eq!(info.runtime_version, crate::VERSION);
+        assert_eq!(info.engine_backend, ENGINE_BACKEND);
+        assert_eq!(info.has_feature("serde"), cfg!(feature = "serde"));
+        assert!(info.to_string().contains("api 0.18.0"));
+    }
+
+    #[test]
+    fn test_host_functions() {
+        let table = HostFunctionTable::new();
+        register_host_functions(&table, &ApiVersion::new(0, 18, 2));
+
+        let version = table.get("host.api_version").unwrap()(&[]).unwrap();
+        assert!(matches!(
+            version,
+            Value::List(ref parts) if matches!(parts.as_slice(), [Value::Int(0), Value::Int(18), Value::Int(2)])
+        ));
+        let info = table.get("host.info").unwrap()(&[]).unwrap();
+        assert!(matches!(info, Value::String(ref s) if s.contains("api 0.18.2")));
+    }
+}
@@ -0,0 +1,256 @@
+//! Automatic plugin reload driven by the file watcher.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use parking_lot::RwLock;
+
+use crate::error::Result;
+use crate::lifecycle::{LifecycleHooks, Transition};
+use crate::loader::PluginLoader;
+use crate::plugin::PluginHandle;
+use crate::registry::PluginRegistry;
+use crate::tasks::TaskSupervisor;
+use crate::watcher::{PluginWatcher, WatchConfig, WatchEvent, WatchEventKind, WatcherStats};
+
+/// Reloads plugins when the files they were loaded from change.
+///
+/// Changed paths are mapped back to plugins through the manifest and entry
+/// paths recorded in [`PluginInfo`](crate::PluginInfo). Only changes under a
+/// configuration with `auto_reload` enabled trigger a reload.
+pub struct HotReloadManager {
+    watcher: PluginWatcher,
+    loader: Arc<PluginLoader>,
+    registry: Arc<PluginRegistry>,
+    hooks: Arc<RwLock<LifecycleHooks>>,
+}
+
+impl HotReloadManager {
+    /// Create a manager reloading plugins of a registry.
+    pub fn new(
+        config: WatchConfig,
+        loader: Arc<PluginLoader>,
+        registry: Arc<PluginRegistry>,
+    ) -> Result<Self> {
+        Self::with_hooks(
+            config,
+            loader,
+            registry,
+            Arc::new(RwLock::new(LifecycleHooks::new())),
+            Arc::new(TaskSupervisor::new()),
+        )
+    }
+
+    pub(crate) fn with_hooks(
+        config: WatchConfig,
+        loader: Arc<PluginLoader>,
+        registry: Arc<PluginRegistry>,
+        hooks: Arc<RwLock<LifecycleHooks>>,
+        tasks: Arc<TaskSupervisor>,
+    ) -> Result<Self> {
+        let watcher = PluginWatcher::new(config)?.with_tasks(tasks);
+
+        let (l, r, h) = (loader.clone(), registry.clone(), hooks.clone());
+        watcher
+            .subscribe()
+            .events([
+                WatchEventKind::Created,
+                WatchEventKind::Modified,
+                WatchEventKind::Renamed,
+            ])
+            .when_auto_reload()
+            .on_change(move |event| match &event {
+                WatchEvent::Renamed { from, to } => {
+                    // Plugins loaded from the old path or a directory above it
+                    // follow the rename; otherwise the destination may be an
+                    // entry file replaced by an editor's atomic save
+                    let moved: Vec<_> = r
+                        .relocate(from, to)
+                        .iter()
+                        .filter_map(|name| r.get(name))
+                        .collect();
+                    if moved.is_empty() {
+                        Self::reload_matching(&l, &r, &h, to);
+                    } else {
+                        Self::reload_plugins(&l, &h, moved, to);
+                    }
+                }
+                _ => {
+                    Self::reload_matching(&l, &r, &h, event.path());
+                }
+            });
+
+        Ok(Self {
+            watcher,
+            loader,
+            registry,
+            hooks,
+        })
+    }
+
+    /// Get the underlying watcher.
+    pub fn watcher(&self) -> &PluginWatcher {
+        &self.watcher
+    }
+
+    /// Get the underlying watcher mutably, e.g. to add watch groups.
+    pub fn watcher_mut(&mut self) -> &mut PluginWatcher {
+        &mut self.watcher
+    }
+
+    /// Watch a path.
+    pub fn watch(&self, path: impl AsRef<Path>) -> Result<()> {
+        self.watcher.watch(path)
+    }
+
+    /// Start watching.
+    pub fn start(&self) -> Result<()> {
+        self.watcher.start()
+    }
+
+    /// Stop watching.
+    pub fn stop(&self) {
+        self.watcher.stop();
+    }
+
+    /// Check if the manager is watching.
+    pub fn is_running(&self) -> bool {
+        self.watcher.is_running()
+    }
+
+    /// Get watcher statistics.
+    pub fn stats(&self) -> WatcherStats {
+        self.watcher.stats()
+    }
+
+    /// Reload every plugin loaded from a path.
+    ///
+    /// Plugins whose origin policy disables watching are skipped. Returns
+    /// the name and reload result of each affected plugin.
+    pub fn reload_path(&self, path: impl AsRef<Path>) -> Vec<(String, Result<()>)> {
+        Self::reload_matching(&self.loader, &self.registry, &self.hooks, path.as_ref())
+    }
+
+    fn reload_matching(
+        loader: &PluginLoader,
+        registry: &PluginRegistry,
+        hooks: &RwLock<LifecycleHooks>,
+        path: &Path,
+    ) -> Vec<(String, Result<()>)> {
+        Self::reload_plugins(loader, hooks, registry.find_by_path(path), path)
+    }
+
+    fn reload_plugins(
+        loader: &PluginLoader,
+        hooks: &RwLock<LifecycleHooks>,
+        plugins: Vec<PluginHandle>,
+        path: &Path,
+    ) -> Vec<(String, Result<()>)> {
+        plugins
+            .into_iter()
+            .filter(|plugin| loader.config().origin_policy(plugin.info().origin).watch)
+            .map(|plugin| {
+                let name = plugin.name();
+                let guards = hooks.read().guards(Transition::Reload);
+                let result = guards
+                    .check(&name)
+                    .and_then(|()| loader.reload_from_disk(&plugin));
+
+                match result {
+                    Ok(()) => {
+                        tracing::info!(
+                            "Reloaded plugin {} after change to {}",
+                            name,
+                            path.display()
+                        );
+                        hooks
+                            .read()
+                            .emit_reloaded(&name, plugin.info().reload_count);
+                    }
+                    Err(ref e) => {
+                        tracing::warn!("Failed to reload plugin {}: {}", name, e);
+                        hooks.read().emit_error(&name, &e.to_string());
+                    }
+                }
+
+                (name, result)
+            })
+            .collect()
+    }
+}
+
+impl std::fmt::Debug for HotReloadManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HotReloadManager")
+            .field("watcher", &self.watcher)
+            .field("plugins", &self.registry.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::loader::LoaderConfig;
+    use crate::registry::RegistryConfig;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_reload_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("watched.fsx");
+        std::fs::write(&path, "let x = 1").unwrap();
+
+        let loader = Arc::new(PluginLoader::new(LoaderConfig::new()).unwrap());
+        let registry = Arc::new(PluginRegistry::new(RegistryConfig::new()));
+        registry
+            .register(loader.load_source(&path).unwrap())
+            .unwrap();
+
+        let manager = HotReloadManager::new(WatchConfig::new(), loader, registry.clone()).unwrap();
+
+        let results = manager.reload_path(&path);
+        assert_eq!(results.len(), 1);
+        assert!(results[0].1.is_ok());
+        assert_eq!(registry.get("watched").unwrap().info().reload_count, 1);
+
+        assert!(manager.reload_path(dir.path().join("other.fsx")).is_empty());
+    }
+
+    #[test]
+    fn test_reload_after_rename() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let plugins = root.join("plugins");
+        std::fs::create_dir(&plugins).unwrap();
+        std::fs::write(plugins.join("watched.fsx"), "let x = 1").unwrap();
+
+        let loader = Arc::new(PluginLoader::new(LoaderConfig::new()).unwrap());
+        let registry = Arc::new(PluginRegistry::new(RegistryConfig::new()));
+        registry
+            .register(loader.load_source(plugins.join("watched.fsx")).unwrap())
+            .unwrap();
+
+        let manager = HotReloadManager::new(
+            WatchConfig::new().with_debounce(Duration::ZERO),
+            loader,
+            registry.clone(),
+        )
+        .unwrap();
+        manager.watch(&root).unwrap();
+        manager.start().unwrap();
+
+        // Moving the plugin's directory relocates and reloads it
+        let moved = root.join("moved");
+        std::fs::rename(&plugins, &moved).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while registry.get("watched").unwrap().info().reload_count == 0 && Instant::now() < deadline
+        {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+
+        let info = registry.get("watched").unwrap().info();
+        assert_eq!(info.reload_count, 1);
+        assert_eq!(info.entry_path, Some(moved.join("watched.fsx")));
+    }
+}
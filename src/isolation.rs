@@ -0,0 +1,656 @@
+//! Running plugins in a separate worker process.
+//!
+//! The host talks to the worker over its stdin and stdout, one JSON message
+//! per line. The host sends `init` once after spawning the worker, then
+//! `execute` for every call and lifecycle function, and `shutdown` when the
+//! plugin is unloaded. The worker answers each request with either
+//! `{"ok": <value>}` or `{"error": "<message>"}`.
+//!
+//! `init` carries the plugin's bytecode and the capabilities and limits of
+//! its engine configuration, so the worker's engine is set up exactly like
+//! an in-process one. Answers are converted back into values, records
+//! included.
+//!
+//! A worker that crashes or misses the call timeout is killed; the call
+//! fails and the next call starts a fresh worker. Shutdown kills the worker
+//! of a plugin whose cleanup misses the deadline the same way. If the plugin's `init()`
+//! had run, the fresh worker runs it again before taking the call.
+//!
+//! Workers start with an empty environment apart from the variables the
+//! plugin may read.
+//!
+//! Lines the worker writes to stderr are captured by the plugin's logger. A
+//! leading `[debug]`, `[info]`, `[warn]` or `[error]` tag sets their level.
+
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::Duration;
+
+use fusabi_host::{Capabilities, Engine, EngineConfig, Value};
+use parking_lot::Mutex;
+
+use crate::descriptor::ResourceLimits;
+use crate::error::{Error, Result};
+use crate::logger::{LogLevel, PluginLogger};
+use crate::marshal;
+use crate::probe::CAPABILITY_NAMES;
+use crate::regional::RegionalSettings;
+use crate::sensitive::zeroize_string;
+
+/// How to start plugin worker processes.
+#[derive(Debug, Clone)]
+pub struct IsolationConfig {
+    /// Worker executable, which must call [`run_worker`].
+    pub worker: PathBuf,
+    /// Arguments passed to the worker.
+    pub args: Vec<String>,
+    /// Time a worker may take to answer a request before it is killed.
+    pub call_timeout: Duration,
+}
+
+impl IsolationConfig {
+    /// Start workers by running `worker`.
+    pub fn new(worker: impl Into<PathBuf>) -> Self {
+        Self {
+            worker: worker.into(),
+            args: Vec::new(),
+            call_timeout: Duration::from_secs(30),
+        }
+    }
+
+    /// Set the worker arguments.
+    pub fn with_args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args = args.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Set the call timeout.
+    pub fn with_call_timeout(mut self, timeout: Duration) -> Self {
+        self.call_timeout = timeout;
+        self
+    }
+}
+
+/// Request sent to a worker.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum Request {
+    Init {
+        plugin: String,
+        capabilities: Vec<String>,
+        #[serde(default)]
+        regional: Option<RegionalSettings>,
+        #[serde(default)]
+        limits: Option<ResourceLimits>,
+        #[serde(default)]
+        bytecode: Option<Vec<u8>>,
+    },
+    Execute {
+        expr: String,
+    },
+    Shutdown,
+}
+
+/// Answer of a worker.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Response {
+    Ok(serde_json::Value),
+    Error(String),
+}
+
+/// A running worker process.
+struct Worker {
+    // Shared with the engine so it can be killed during a call
+    child: Arc<Mutex<Child>>,
+    stdin: ChildStdin,
+    responses: Receiver<String>,
+}
+
+impl Worker {
+    fn kill(self) {
+        kill_process(&self.child);
+    }
+}
+
+fn kill_process(child: &Mutex<Child>) {
+    let mut child = child.lock();
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Client side of a plugin running in a worker process.
+pub(crate) struct ProcessEngine {
+    config: IsolationConfig,
+    plugin: String,
+    capabilities: Vec<String>,
+    limits: ResourceLimits,
+    bytecode: Option<Vec<u8>>,
+    env: Vec<String>,
+    regional: RegionalSettings,
+    logger: Arc<PluginLogger>,
+    worker: Mutex<Option<Worker>>,
+    // Process of the current worker, reachable while a call holds `worker`
+    process: Mutex<Option<Arc<Mutex<Child>>>>,
+    started: AtomicBool,
+}
+
+impl ProcessEngine {
+    /// Start a worker for a plugin.
+    ///
+    /// The worker's engine gets the capabilities and limits of
+    /// `engine_config`. Workers only see the environment variables named in
+    /// `env`, plus `LANG` and `TZ` describing `regional`.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn start(
+        config: IsolationConfig,
+        plugin: String,
+        engine_config: &EngineConfig,
+        bytecode: Option<Vec<u8>>,
+        env: Vec<String>,
+        regional: RegionalSettings,
+        logger: Arc<PluginLogger>,
+    ) -> Result<Self> {
+        let capabilities = CAPABILITY_NAMES
+            .iter()
+            .filter(|name| {
+                fusabi_host::Capability::from_name(name)
+                    .is_some_and(|cap| engine_config.capabilities.has(cap))
+            })
+            .map(|name| name.to_string())
+            .collect();
+        let engine = Self {
+            config,
+            plugin,
+            capabilities,
+            limits: ResourceLimits::from(&engine_config.limits),
+            bytecode,
+            env,
+            regional,
+            logger,
+            worker: Mutex::new(None),
+            process: Mutex::new(None),
+            started: AtomicBool::new(false),
+        };
+        let worker = engine.spawn()?;
+        *engine.worker.lock() = Some(worker);
+        Ok(engine)
+    }
+
+    /// Record whether the plugin's `init()` has run, so a restarted worker
+    /// runs it again.
+    pub(crate) fn set_started(&self, started: bool) {
+        self.started.store(started, Ordering::SeqCst);
+    }
+
+    /// Kill the worker process, failing the call it is running.
+    ///
+    /// The next call starts a fresh worker.
+    pub(crate) fn kill(&self) {
+        if let Some(child) = self.process.lock().take() {
+            kill_process(&child);
+        }
+    }
+
+    /// Evaluate an expression in the worker, restarting it if needed.
+    ///
+    /// Calls are serialized per plugin.
+    pub(crate) fn execute(&self, expr: &str) -> Result<Value> {
+        let mut slot = self.worker.lock();
+        let mut worker = match slot.take() {
+            Some(worker) => worker,
+            None => self.respawn()?,
+        };
+
+        let mut request = Request::Execute {
+            expr: expr.to_string(),
+        };
+        let result = self.request(&mut worker, &request);
+        // The expression may carry sensitive arguments
+        if let Request::Execute { ref mut expr } = request {
+            zeroize_string(expr);
+        }
+        match result {
+            Ok(value) => {
+                *slot = Some(worker);
+                Ok(value)
+            }
+            Err(e @ Error::ExecutionFailed(_)) => {
+                *slot = Some(worker);
+                Err(e)
+            }
+            Err(e) => {
+                tracing::warn!("Plugin {} worker failed, killing it: {}", self.plugin, e);
+                worker.kill();
+                Err(e)
+            }
+        }
+    }
+
+    /// Start a worker in place of one that was killed, running the
+    /// plugin's `init()` if it had run in the old one.
+    fn respawn(&self) -> Result<Worker> {
+        let mut worker = self.spawn()?;
+        if self.started.load(Ordering::SeqCst) {
+            let init = Request::Execute {
+                expr: marshal::call_expression("init", &[])?,
+            };
+            if let Err(e) = self.request(&mut worker, &init) {
+                worker.kill();
+                return Err(Error::init_failed(format!(
+                    "init() failed in restarted worker: {}",
+                    e
+                )));
+            }
+        }
+        Ok(worker)
+    }
+
+    fn spawn(&self) -> Result<Worker> {
+        let env = self
+            .env
+            .iter()
+            .filter_map(|name| std::env::var_os(name).map(|value| (name, value)));
+        let mut child = Command::new(&self.config.worker)
+            .args(&self.config.args)
+            .env_clear()
+            .envs(env)
+            .envs(self.regional.env_vars())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| {
+                Error::Isolation(format!(
+                    "cannot start worker {}: {}",
+                    self.config.worker.display(),
+                    e
+                ))
+            })?;
+
+        let stdin = child.stdin.take().expect("worker stdin is piped");
+        let stdout = child.stdout.take().expect("worker stdout is piped");
+        let stderr = child.stderr.take().expect("worker stderr is piped");
+        let logger = self.logger.clone();
+        std::thread::Builder::new()
+            .name(format!("fusabi-worker-log-{}", self.plugin))
+            .spawn(move || {
+                for line in BufReader::new(stderr).lines() {
+                    let Ok(line) = line else { break };
+                    let (level, message) = LogLevel::parse_prefix(&line);
+                    logger.log(level, message);
+                }
+            })?;
+        let (tx, responses) = mpsc::channel();
+        std::thread::Builder::new()
+            .name(format!("fusabi-worker-{}", self.plugin))
+            .spawn(move || {
+                for line in BufReader::new(stdout).lines() {
+                    let Ok(line) = line else { break };
+                    if tx.send(line).is_err() {
+                        break;
+                    }
+                }
+            })?;
+
+        let child = Arc::new(Mutex::new(child));
+        *self.process.lock() = Some(child.clone());
+        let mut worker = Worker {
+            child,
+            stdin,
+            responses,
+        };
+        let init = Request::Init {
+            plugin: self.plugin.clone(),
+            capabilities: self.capabilities.clone(),
+            regional: Some(self.regional.clone()),
+            limits: Some(self.limits),
+            bytecode: self.bytecode.clone(),
+        };
+        if let Err(e) = self.request(&mut worker, &init) {
+            worker.kill();
+            return Err(Error::init_failed(e.to_string()));
+        }
+        Ok(worker)
+    }
+
+    /// Send a request and wait for its answer.
+    ///
+    /// Errors reported by the worker are `ExecutionFailed`; anything else
+    /// means the worker can no longer be used.
+    fn request(&self, worker: &mut Worker, request: &Request) -> Result<Value> {
+        let mut line =
+            serde_json::to_string(request).map_err(|e| Error::Isolation(e.to_string()))?;
+        let written = writeln!(worker.stdin, "{}", line).and_then(|()| worker.stdin.flush());
+        zeroize_string(&mut line);
+        written.map_err(|e| Error::Isolation(format!("plugin process is gone: {}", e)))?;
+
+        let answer = match worker.responses.recv_timeout(self.config.call_timeout) {
+            Ok(answer) => answer,
+            Err(RecvTimeoutError::Timeout) => {
+                return Err(Error::Isolation(format!(
+                    "plugin process did not answer within {:?}",
+                    self.config.call_timeout
+                )))
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                return Err(Error::Isolation("plugin process exited".to_string()))
+            }
+        };
+
+        match serde_json::from_str(&answer) {
+            Ok(Response::Ok(value)) => marshal::from_json(value),
+            Ok(Response::Error(message)) => Err(Error::execution_failed(message)),
+            Err(e) => Err(Error::Isolation(format!("invalid worker response: {}", e))),
+        }
+    }
+}
+
+impl Drop for ProcessEngine {
+    fn drop(&mut self) {
+        if let Some(mut worker) = self.worker.lock().take() {
+            let _ = writeln!(
+                worker.stdin,
+                "{}",
+                serde_json::to_string(&Request::Shutdown).unwrap_or_default()
+            );
+            worker.kill();
+        }
+    }
+}
+
+impl std::fmt::Debug for ProcessEngine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProcessEngine")
+            .field("plugin", &self.plugin)
+            .field("worker", &self.config.worker)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Serve plugin requests on stdin and stdout.
+///
+/// Call this from the `main` of the worker executable configured in
+/// [`IsolationConfig`]. Returns when the host sends `shutdown` or closes
+/// stdin.
+pub fn run_worker() -> Result<()> {
+    let stdin = std::io::stdin();
+    let stdout = std::io::stdout();
+    serve(stdin.lock(), stdout.lock())
+}
+
+fn serve(input: impl BufRead, mut output: impl Write) -> Result<()> {
+    let mut engine: Option<Engine> = None;
+
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(Request::Init {
+                plugin,
+                capabilities,
+                regional,
+                limits,
+                bytecode,
+            }) => {
+                let mut caps = Capabilities::none();
+                for name in &capabilities {
+                    if let Some(cap) = fusabi_host::Capability::from_name(name) {
+                        caps.grant(cap);
+                    }
+                }
+                let mut config = EngineConfig::default().with_capabilities(caps);
+                if let Some(limits) = limits {
+                    limits.apply(&mut config.limits);
+                }
+                let checked = match bytecode {
+                    Some(ref bytecode) => crate::compat::check_bytecode(bytecode),
+                    None => Ok(()),
+                };
+                match checked.and_then(|()| Engine::new(config).map_err(|e| e.to_string())) {
+                    Ok(e) => {
+                        engine = Some(e);
+                        let regional = regional.unwrap_or_else(RegionalSettings::host);
+                        tracing::debug!(
+                            "Worker initialized for plugin {} ({}, {})",
+                            plugin,
+                            regional.locale,
+                            regional.timezone
+                        );
+                        Response::Ok(serde_json::Value::Null)
+                    }
+                    Err(e) => Response::Error(e),
+                }
+            }
+            Ok(Request::Execute { expr }) => match engine {
+                Some(ref engine) => match engine.execute(&expr) {
+                    Ok(value) => serde_json::to_value(&value)
+                        .map(Response::Ok)
+                        .unwrap_or_else(|e| Response::Error(e.to_string())),
+                    Err(e) => Response::Error(e.to_string()),
+                },
+                None => Response::Error("worker not initialized".to_string()),
+            },
+            Ok(Request::Shutdown) => return Ok(()),
+            Err(e) => Response::Error(format!("invalid request: {}", e)),
+        };
+
+        let line = serde_json::to_string(&response).map_err(|e| Error::Isolation(e.to_string()))?;
+        writeln!(output, "{}", line)?;
+        output.flush()?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn logger() -> Arc<PluginLogger> {
+        Arc::new(PluginLogger::new("p", 10))
+    }
+
+    #[test]
+    fn test_serve_protocol() {
+        let input = concat!(
+            r#"{"op":"execute","expr":"main()"}"#,
+            "\n",
+            r#"{"op":"init","plugin":"p","capabilities":["fs:read"]}"#,
+            "\n",
+            r#"{"op":"execute","expr":"main()"}"#,
+            "\n",
+            r#"{"op":"shutdown"}"#,
+            "\n",
+            r#"{"op":"execute","expr":"never()"}"#,
+            "\n",
+        );
+        let mut output = Vec::new();
+        serve(input.as_bytes(), &mut output).unwrap();
+
+        let lines: Vec<&str> = std::str::from_utf8(&output).unwrap().lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                r#"{"error":"worker not initialized"}"#,
+                r#"{"ok":null}"#,
+                r#"{"ok":null}"#
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_worker_crash_and_timeout() {
+        let echo = IsolationConfig::new("/bin/sh")
+            .with_args(["-c", r#"while read line; do echo '{"ok":7}'; done"#]);
+        let engine = ProcessEngine::start(
+            echo,
+            "p".into(),
+            &EngineConfig::default(),
+            None,
+            Vec::new(),
+            RegionalSettings::host(),
+            logger(),
+        )
+        .unwrap();
+        assert!(matches!(engine.execute("main()"), Ok(Value::Int(7))));
+
+        // Answers init, then dies on the first call
+        let crashing = IsolationConfig::new("/bin/sh")
+            .with_args(["-c", r#"read line; echo '{"ok":null}'; read line; exit 1"#]);
+        let engine = ProcessEngine::start(
+            crashing,
+            "p".into(),
+            &EngineConfig::default(),
+            None,
+            Vec::new(),
+            RegionalSettings::host(),
+            logger(),
+        )
+        .unwrap();
+        assert!(matches!(engine.execute("main()"), Err(Error::Isolation(_))));
+        assert!(matches!(engine.execute("main()"), Err(Error::Isolation(_))));
+
+        let hanging = IsolationConfig::new("/bin/sh")
+            .with_args(["-c", r#"read line; echo '{"ok":null}'; sleep 5"#])
+            .with_call_timeout(Duration::from_millis(100));
+        let engine = ProcessEngine::start(
+            hanging,
+            "p".into(),
+            &EngineConfig::default(),
+            None,
+            Vec::new(),
+            RegionalSettings::host(),
+            logger(),
+        )
+        .unwrap();
+        assert!(matches!(engine.execute("main()"), Err(Error::Isolation(_))));
+    }
+
+    #[test]
+    fn test_serve_checks_bytecode() {
+        let input = concat!(
+            r#"{"op":"init","plugin":"p","capabilities":[],"bytecode":[0,1,2]}"#,
+            "\n",
+            r#"{"op":"execute","expr":"main()"}"#,
+            "\n",
+        );
+        let mut output = Vec::new();
+        serve(input.as_bytes(), &mut output).unwrap();
+
+        let lines: Vec<&str> = std::str::from_utf8(&output).unwrap().lines().collect();
+        assert!(lines[0].starts_with(r#"{"error":"#));
+        assert_eq!(lines[1], r#"{"error":"worker not initialized"}"#);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_restarted_worker_runs_init() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("requests");
+        let script = format!(
+            r#"while read line; do echo "$line" >> {}; case "$line" in *crash*) exit 1;; esac; echo '{{"ok":1}}'; done"#,
+            log.display()
+        );
+        let mut engine_config = EngineConfig::default()
+            .with_capabilities(Capabilities::none().with(fusabi_host::Capability::FsRead));
+        engine_config.limits.max_call_depth = 7;
+        let engine = ProcessEngine::start(
+            IsolationConfig::new("/bin/sh").with_args(["-c", script.as_str()]),
+            "p".into(),
+            &engine_config,
+            Some(vec![1, 2]),
+            Vec::new(),
+            RegionalSettings::host(),
+            logger(),
+        )
+        .unwrap();
+        engine.set_started(true);
+        assert!(matches!(
+            engine.execute("crash()"),
+            Err(Error::Isolation(_))
+        ));
+        assert!(matches!(engine.execute("main()"), Ok(Value::Int(1))));
+
+        let requests: Vec<serde_json::Value> = std::fs::read_to_string(&log)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let ops: Vec<&str> = requests
+            .iter()
+            .map(|r| match r["op"].as_str().unwrap() {
+                "init" => "init",
+                _ => r["expr"].as_str().unwrap(),
+            })
+            .collect();
+        assert_eq!(ops, vec!["init", "crash()", "init", "init()", "main()"]);
+        assert_eq!(requests[0]["capabilities"], serde_json::json!(["fs:read"]));
+        assert_eq!(requests[0]["limits"]["max_call_depth"], 7);
+        assert_eq!(requests[0]["bytecode"], serde_json::json!([1, 2]));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_worker_environment() {
+        let printenv = IsolationConfig::new("/bin/sh").with_args([
+            "-c",
+            r#"while read line; do echo "{\"ok\":\"$PATH|$HOME|$LANG|$TZ\"}"; done"#,
+        ]);
+        let engine = ProcessEngine::start(
+            printenv,
+            "p".into(),
+            &EngineConfig::default(),
+            None,
+            vec!["PATH".into()],
+            RegionalSettings::new("de-CH", "Europe/Zurich"),
+            logger(),
+        )
+        .unwrap();
+        let expected = format!(
+            "{}||de_CH.UTF-8|Europe/Zurich",
+            std::env::var("PATH").unwrap()
+        );
+        assert!(matches!(engine.execute("main()"), Ok(Value::String(s)) if s == expected));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_worker_stderr_captured() {
+        let chatty = IsolationConfig::new("/bin/sh").with_args([
+            "-c",
+            r#"while read line; do echo '[warn] low disk' >&2; echo '{"ok":null}'; done"#,
+        ]);
+        let logger = logger();
+        let engine = ProcessEngine::start(
+            chatty,
+            "p".into(),
+            &EngineConfig::default(),
+            None,
+            Vec::new(),
+            RegionalSettings::host(),
+            logger.clone(),
+        )
+        .unwrap();
+        engine.execute("main()").unwrap();
+
+        let deadline = std::time::Instant::now() + Duration::from_secs(2);
+        while logger.recent().is_empty() && std::time::Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let lines = logger.recent();
+        assert_eq!(lines[0].level, LogLevel::Warn);
+        assert_eq!(lines[0].message, "low disk");
+    }
+}
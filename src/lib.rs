@@ -10,6 +10,7 @@
 //! - **Hot Reload** - Automatically reload plugins when files change
 //! - **Lifecycle Management** - Initialize, run, and cleanup plugins
 //! - **Metrics Hooks** - Track plugin performance and usage
+//! - **Plugin Storage** - Per-plugin key-value state with transactions
 //!
 //! ## Quick Start
 //!
@@ -30,39 +31,217 @@
 //!
 //! - `serde` (default): Enable manifest parsing and serialization
 //! - `watch`: Enable filesystem watching for hot reload
+//! - `isolation`: Run plugins in separate worker processes
+//! - `wasm`: Run `.wasm` plugin entries through wasmtime
+//! - `schema`: Generate the manifest JSON Schema
 //! - `metrics-prometheus`: Prometheus metrics integration
+//! - `admin-http`: HTTP server for the admin API
+//! - `testing`: `MockRuntime` for testing applications without compiling plugins
 
 #![warn(missing_docs)]
 #![warn(rust_2018_idioms)]
 
+mod admin;
+mod api;
+mod artifact_store;
+mod audit;
+mod authorizer;
+mod budget;
+mod builtin;
+mod bulk;
+mod cache;
+mod call_cache;
+mod cancel;
+mod capability_policy;
+mod cli;
+mod cluster;
+mod compat;
+mod composite;
+mod config_error;
+mod config_schema;
+mod crypto;
+mod delegation;
+mod dependency;
+mod descriptor;
+mod diagnostic;
+mod discovery;
+mod dispatcher;
+mod environment;
 mod error;
+mod features;
+mod glob;
+mod health;
+mod host_functions;
+mod host_info;
 mod lifecycle;
+mod load_gen;
 mod loader;
+mod lockfile;
+mod logger;
 mod manifest;
+mod marshal;
+mod messages;
+mod namespace;
+mod network;
+mod origin;
 mod plugin;
+mod policy;
+mod pool;
+mod prewarm;
+mod probe;
+mod rate_limit;
+mod regional;
 mod registry;
+mod retry;
 mod runtime;
+mod schedule;
+mod sensitive;
+mod signature;
+mod startup;
+mod storage;
+mod supervisor;
+mod tasks;
+mod tenant;
+mod update;
+mod version;
+#[cfg(feature = "wasm")]
+mod wasm;
 
+#[cfg(feature = "serde")]
+mod explain;
+#[cfg(feature = "serde")]
+mod registry_index;
+#[cfg(feature = "serde")]
+mod validation;
+
+#[cfg(feature = "watch")]
+mod hot_reload;
 #[cfg(feature = "watch")]
 mod watcher;
 
+#[cfg(feature = "isolation")]
+mod isolation;
+
 #[cfg(feature = "metrics-prometheus")]
 mod metrics;
 
-pub use error::{Error, Result};
-pub use lifecycle::{LifecycleHooks, LifecycleState, PluginLifecycle};
+#[cfg(feature = "admin-http")]
+mod admin_http;
+
+#[cfg(any(test, feature = "testing"))]
+mod mock;
+
+pub use admin::{
+    AdminApi, AdminOperation, Authenticator, ChainAuthenticator, Credentials, JwtAuthenticator,
+    MtlsAuthenticator, Principal, Role, StaticTokenAuthenticator,
+};
+pub use api::RuntimeApi;
+pub use artifact_store::{ArtifactStats, ArtifactStore, GcReport, InstalledVersion};
+#[cfg(feature = "serde")]
+pub use audit::JsonlAuditSink;
+pub use audit::{
+    AuditOutcome, AuditRecord, AuditSink, CallDetails, CallSampling, MemoryAuditLog,
+    TracingAuditSink,
+};
+pub use authorizer::{CallAuthorizer, ExportAcl, Principal};
+pub use budget::{BudgetTracker, BudgetUsage, ExecutionBudget, ThrottleAction};
+pub use builtin::{BuiltinFn, BuiltinPlugin};
+pub use bulk::{BulkOp, BulkOutcome, BulkPolicy, BulkProgress, BulkReport};
+pub use cache::{BytecodeCache, CacheStats};
+pub use call_cache::{CacheableExport, CallCacheConfig, CallCacheStats};
+pub use cancel::{CancellationToken, Partial};
+pub use capability_policy::{
+    AllowList, CapabilityDecision, CapabilityPolicy, CapabilityRequest, DenyAll, PromptPolicy,
+};
+pub use cli::{CliArg, CliArgKind, CliCommand, CliConfig};
+#[cfg(feature = "serde")]
+pub use cluster::FileClusterBackend;
+pub use cluster::{
+    ClusterBackend, ClusterState, ConvergeReport, DesiredPlugin, MemoryClusterBackend,
+};
+pub use compat::{AtRiskPlugin, BytecodeUpgrader, CompatReport};
+pub use composite::CompositeMember;
+pub use config_error::{ConfigError, ConfigIssue};
+pub use config_schema::{ConfigField, ConfigFieldKind, ConfigSchema};
+pub use crypto::{crypto_provider, install_crypto_provider, CryptoProvider, DefaultCryptoProvider};
+pub use delegation::Delegation;
+pub use dependency::DependencyGraph;
+pub use descriptor::{PluginDescriptor, PluginStats, ResourceLimits};
+pub use diagnostic::{Diagnostic, SourceSpan};
+pub use discovery::{Collision, CollisionResolution, CollisionStrategy, DiscoveryReport};
+pub use dispatcher::{CallDispatcher, CallReceipt, DispatcherConfig};
+pub use environment::{EnvConfig, EnvPolicy};
+pub use error::{Error, PluginOperation, Result};
+pub use features::{enabled_features, Feature};
+pub use health::{HealthStatus, PluginHealth};
+pub use host_functions::{HostFn, HostFunctionTable, ScopedHostFn};
+pub use host_info::{HostInfo, HOST_INFO_FUNCTIONS};
+pub use lifecycle::{LifecycleHooks, LifecycleState, PluginLifecycle, Transition};
+pub use load_gen::{LoadGenerator, LoadPlan, LoadReport, PluginLoad, TracedCall};
 pub use loader::{LoaderConfig, PluginLoader};
-pub use manifest::{ApiVersion, Dependency, Manifest, ManifestBuilder};
+pub use lockfile::{Drift, LockedPlugin, Lockfile, VerifyReport};
+pub use logger::{LogLevel, LogLine, PluginLogger};
+pub use manifest::{
+    ApiVersion, CapabilitySpec, Dependency, Isolation, Manifest, ManifestBuilder, PluginFeature,
+};
+pub use messages::MessageCatalog;
+pub use network::{NetworkConfig, OfflineSwitch};
+pub use origin::{OriginPolicy, PluginOrigin, TrustLevel};
 pub use plugin::{Plugin, PluginHandle, PluginInfo};
-pub use registry::{PluginRegistry, RegistryConfig};
+pub use policy::{LimitCaps, PolicyFile, PolicyViolation, SignatureRule};
+pub use pool::{AutoscalePolicy, PoolSignals};
+pub use prewarm::CallProfile;
+pub use probe::EngineProbe;
+pub use rate_limit::{CallRateLimits, RateLimit, RateLimiter};
+pub use regional::RegionalSettings;
+pub use registry::{PluginRef, PluginRegistry, RegistryConfig, RegistryStats};
+pub use retry::{CallOptions, RetryPolicy};
 pub use runtime::{PluginRuntime, RuntimeConfig};
+pub use schedule::ScheduledJob;
+pub use sensitive::{CallArg, Sensitive};
+pub use signature::{ExportSignature, ExportSpec, Param, ValueType};
+pub use startup::{PluginLoadTime, StartupReport};
+pub use storage::{
+    PluginStorage, StorageQuota, StorageScope, StorageStats, Transaction, STORAGE_HOST_FUNCTIONS,
+};
+pub use supervisor::{RestartPolicy, Supervisor};
+pub use tasks::{
+    PluginShutdownOutcome, ShutdownPolicy, ShutdownReport, TaskHealth, TaskSupervisor, TaskWaker,
+};
+pub use tenant::{TenantActivation, TenantPolicy};
+pub use update::{Release, UpdateChecker, UpdateConfig, UpdateEvent, UpdatePolicy, UpdateSource};
+pub use version::VersionReq;
 
+#[cfg(feature = "serde")]
+pub use explain::{LoadExplanation, LoadFailure, LoadGate};
+#[cfg(feature = "serde")]
+pub use registry_index::{
+    IndexArtifact, IndexDocument, IndexEntry, IndexFetcher, IndexRelease, LocalFetcher,
+    RegistryIndex,
+};
+#[cfg(feature = "serde")]
+pub use validation::{ValidationCheck, ValidationProblem, ValidationReport};
+
+#[cfg(feature = "watch")]
+pub use hot_reload::HotReloadManager;
 #[cfg(feature = "watch")]
-pub use watcher::{PluginWatcher, WatchConfig, WatchEvent};
+pub use watcher::{
+    EventFilter, PluginWatcher, SubscriptionBuilder, WatchBackend, WatchConfig, WatchEvent,
+    WatchEventKind, WatcherStats,
+};
+
+#[cfg(feature = "isolation")]
+pub use isolation::{run_worker, IsolationConfig};
 
 #[cfg(feature = "metrics-prometheus")]
 pub use metrics::{MetricsConfig, PluginMetrics};
 
+#[cfg(feature = "admin-http")]
+pub use admin_http::{AdminHttpHandle, AdminHttpServer};
+
+#[cfg(any(test, feature = "testing"))]
+pub use mock::{MockCall, MockRuntime};
+
 // Re-export key types from fusabi-host for convenience
 pub use fusabi_host::{Capabilities, Capability, Error as HostError, Limits, Value};
 
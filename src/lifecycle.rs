@@ -1,9 +1,17 @@
 //! Plugin lifecycle management.
 
-use std::time::Instant;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+
+use crate::pool::PoolSignals;
 
 /// Plugin lifecycle state.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum LifecycleState {
     /// Plugin has been created but not initialized.
     Created,
@@ -169,6 +177,51 @@ pub enum LifecycleEvent {
         /// Error time.
         at: Instant,
     },
+    /// Host functions required by the plugin became available or unavailable.
+    HostFunctionsChanged {
+        /// Plugin name.
+        name: String,
+        /// Required host functions that are now missing.
+        missing: Vec<String>,
+        /// Change time.
+        at: Instant,
+    },
+    /// Plugin failed a health check.
+    Unhealthy {
+        /// Plugin name.
+        name: String,
+        /// Why the check failed.
+        reason: String,
+        /// Check time.
+        at: Instant,
+    },
+    /// Plugin used up its execution budget and calls are being throttled.
+    BudgetExceeded {
+        /// Plugin name.
+        name: String,
+        /// Exceeded budget: `calls` or `time`.
+        budget: String,
+        /// Time until the budget allows a call again.
+        retry_after: Duration,
+        /// Throttling start time.
+        at: Instant,
+    },
+    /// Plugin is within its execution budget again.
+    BudgetRestored {
+        /// Plugin name.
+        name: String,
+        /// Throttling end time.
+        at: Instant,
+    },
+    /// Engines were added to or removed from the plugin's engine pool.
+    PoolScaled {
+        /// Plugin name.
+        name: String,
+        /// Pool load after scaling.
+        signals: PoolSignals,
+        /// Scaling time.
+        at: Instant,
+    },
 }
 
 impl LifecycleEvent {
@@ -182,6 +235,11 @@ impl LifecycleEvent {
             Self::Reloaded { name, .. } => name,
             Self::Unloaded { name, .. } => name,
             Self::Error { name, .. } => name,
+            Self::HostFunctionsChanged { name, .. } => name,
+            Self::Unhealthy { name, .. } => name,
+            Self::BudgetExceeded { name, .. } => name,
+            Self::BudgetRestored { name, .. } => name,
+            Self::PoolScaled { name, .. } => name,
         }
     }
 
@@ -195,6 +253,11 @@ impl LifecycleEvent {
             Self::Reloaded { at, .. } => *at,
             Self::Unloaded { at, .. } => *at,
             Self::Error { at, .. } => *at,
+            Self::HostFunctionsChanged { at, .. } => *at,
+            Self::Unhealthy { at, .. } => *at,
+            Self::BudgetExceeded { at, .. } => *at,
+            Self::BudgetRestored { at, .. } => *at,
+            Self::PoolScaled { at, .. } => *at,
         }
     }
 
@@ -208,6 +271,33 @@ impl LifecycleEvent {
             Self::Reloaded { .. } => "reloaded",
             Self::Unloaded { .. } => "unloaded",
             Self::Error { .. } => "error",
+            Self::HostFunctionsChanged { .. } => "host_functions_changed",
+            Self::Unhealthy { .. } => "unhealthy",
+            Self::BudgetExceeded { .. } => "budget_exceeded",
+            Self::BudgetRestored { .. } => "budget_restored",
+            Self::PoolScaled { .. } => "pool_scaled",
+        }
+    }
+}
+
+/// Lifecycle transition that pre-transition hooks can veto.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Transition {
+    /// Starting a plugin.
+    Start,
+    /// Stopping a plugin.
+    Stop,
+    /// Reloading a plugin.
+    Reload,
+}
+
+impl Transition {
+    /// Get the transition name.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Start => "start",
+            Self::Stop => "stop",
+            Self::Reload => "reload",
         }
     }
 }
@@ -215,9 +305,45 @@ impl LifecycleEvent {
 /// Boxed lifecycle event handler.
 pub type LifecycleEventHandler = Box<dyn Fn(&LifecycleEvent) + Send + Sync>;
 
+/// Shared pre-transition hook; an `Err` vetoes the transition.
+pub type TransitionGuard = Arc<dyn Fn(&str) -> crate::Result<()> + Send + Sync>;
+
+/// The pre-transition hooks registered for one transition.
+///
+/// Taken out of [`LifecycleHooks`] with [`guards`](LifecycleHooks::guards)
+/// so they run after the lock guarding the hooks is released, and may
+/// register hooks themselves.
+#[derive(Clone)]
+pub struct TransitionGuards {
+    transition: Transition,
+    guards: Vec<TransitionGuard>,
+}
+
+impl TransitionGuards {
+    /// Run the hooks for a plugin.
+    ///
+    /// Hooks run in registration order and the first error is returned.
+    pub fn check(&self, name: &str) -> crate::Result<()> {
+        for guard in &self.guards {
+            if let Err(e) = guard(name) {
+                tracing::info!(
+                    "Hook vetoed {} of plugin {}: {}",
+                    self.transition.name(),
+                    name,
+                    e
+                );
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+}
+
 /// Hooks for lifecycle events.
 pub struct LifecycleHooks {
     handlers: Vec<LifecycleEventHandler>,
+    guards: Vec<(Transition, TransitionGuard)>,
+    subscribers: Mutex<Vec<Sender<LifecycleEvent>>>,
 }
 
 impl LifecycleHooks {
@@ -225,6 +351,8 @@ impl LifecycleHooks {
     pub fn new() -> Self {
         Self {
             handlers: Vec::new(),
+            guards: Vec::new(),
+            subscribers: Mutex::new(Vec::new()),
         }
     }
 
@@ -236,11 +364,71 @@ impl LifecycleHooks {
         self.handlers.push(Box::new(handler));
     }
 
+    /// Receive every event emitted from now on.
+    ///
+    /// Unlike handlers, which run inside the emitting call, events are queued
+    /// for the receiver to process on its own thread. Dropping the receiver
+    /// ends the subscription.
+    pub fn subscribe(&self) -> Receiver<LifecycleEvent> {
+        let (tx, rx) = mpsc::channel();
+        self.subscribers.lock().push(tx);
+        rx
+    }
+
+    /// Add a hook run before a plugin starts; an `Err` aborts the start.
+    pub fn before_start<F>(&mut self, guard: F)
+    where
+        F: Fn(&str) -> crate::Result<()> + Send + Sync + 'static,
+    {
+        self.guards.push((Transition::Start, Arc::new(guard)));
+    }
+
+    /// Add a hook run before a plugin stops; an `Err` aborts the stop.
+    pub fn before_stop<F>(&mut self, guard: F)
+    where
+        F: Fn(&str) -> crate::Result<()> + Send + Sync + 'static,
+    {
+        self.guards.push((Transition::Stop, Arc::new(guard)));
+    }
+
+    /// Add a hook run before a plugin reloads; an `Err` aborts the reload.
+    pub fn before_reload<F>(&mut self, guard: F)
+    where
+        F: Fn(&str) -> crate::Result<()> + Send + Sync + 'static,
+    {
+        self.guards.push((Transition::Reload, Arc::new(guard)));
+    }
+
+    /// Get the pre-transition hooks for a transition.
+    pub fn guards(&self, transition: Transition) -> TransitionGuards {
+        TransitionGuards {
+            transition,
+            guards: self
+                .guards
+                .iter()
+                .filter(|(t, _)| *t == transition)
+                .map(|(_, guard)| guard.clone())
+                .collect(),
+        }
+    }
+
+    /// Run the pre-transition hooks for a plugin.
+    ///
+    /// Hooks run in registration order and the first error is returned.
+    /// Callers holding a lock on the hooks run [`guards`](Self::guards)
+    /// after releasing it instead.
+    pub fn check(&self, transition: Transition, name: &str) -> crate::Result<()> {
+        self.guards(transition).check(name)
+    }
+
     /// Emit a lifecycle event.
     pub fn emit(&self, event: LifecycleEvent) {
         for handler in &self.handlers {
             handler(&event);
         }
+        self.subscribers
+            .lock()
+            .retain(|tx| tx.send(event.clone()).is_ok());
     }
 
     /// Emit a created event.
@@ -300,6 +488,51 @@ impl LifecycleHooks {
             at: Instant::now(),
         });
     }
+
+    /// Emit a host functions changed event.
+    pub fn emit_host_functions_changed(&self, name: &str, missing: Vec<String>) {
+        self.emit(LifecycleEvent::HostFunctionsChanged {
+            name: name.to_string(),
+            missing,
+            at: Instant::now(),
+        });
+    }
+
+    /// Emit an unhealthy event.
+    pub fn emit_unhealthy(&self, name: &str, reason: &str) {
+        self.emit(LifecycleEvent::Unhealthy {
+            name: name.to_string(),
+            reason: reason.to_string(),
+            at: Instant::now(),
+        });
+    }
+
+    /// Emit a budget exceeded event.
+    pub fn emit_budget_exceeded(&self, name: &str, budget: &str, retry_after: Duration) {
+        self.emit(LifecycleEvent::BudgetExceeded {
+            name: name.to_string(),
+            budget: budget.to_string(),
+            retry_after,
+            at: Instant::now(),
+        });
+    }
+
+    /// Emit a budget restored event.
+    pub fn emit_budget_restored(&self, name: &str) {
+        self.emit(LifecycleEvent::BudgetRestored {
+            name: name.to_string(),
+            at: Instant::now(),
+        });
+    }
+
+    /// Emit a pool scaled event.
+    pub fn emit_pool_scaled(&self, name: &str, signals: PoolSignals) {
+        self.emit(LifecycleEvent::PoolScaled {
+            name: name.to_string(),
+            signals,
+            at: Instant::now(),
+        });
+    }
 }
 
 impl Default for LifecycleHooks {
@@ -312,6 +545,8 @@ impl std::fmt::Debug for LifecycleHooks {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("LifecycleHooks")
             .field("handler_count", &self.handlers.len())
+            .field("guard_count", &self.guards.len())
+            .field("subscriber_count", &self.subscribers.lock().len())
             .finish()
     }
 }
@@ -357,6 +592,27 @@ mod tests {
         assert_eq!(counter.load(Ordering::Relaxed), 3);
     }
 
+    #[test]
+    fn test_transition_guards() {
+        let mut hooks = LifecycleHooks::new();
+        hooks.before_stop(|name| {
+            if name == "ledger" {
+                Err(crate::Error::invalid_state("idle", "open transaction"))
+            } else {
+                Ok(())
+            }
+        });
+
+        assert!(hooks.check(Transition::Stop, "ledger").is_err());
+        assert!(hooks.check(Transition::Stop, "other").is_ok());
+        assert!(hooks.check(Transition::Start, "ledger").is_ok());
+
+        // Guards taken out keep working after the hooks are dropped
+        let guards = hooks.guards(Transition::Stop);
+        drop(hooks);
+        assert!(guards.check("ledger").is_err());
+    }
+
     #[test]
     fn test_lifecycle_event_info() {
         let event = LifecycleEvent::Started {
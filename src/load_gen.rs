@@ -0,0 +1,454 @@
+//! Synthetic load for capacity planning.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use fusabi_host::Value;
+use parking_lot::Mutex;
+
+use crate::config_error::ConfigError;
+use crate::error::Result;
+use crate::prewarm::CallProfile;
+use crate::runtime::PluginRuntime;
+
+/// A call issued by the load generator.
+#[derive(Debug, Clone)]
+pub struct TracedCall {
+    /// Plugin name.
+    pub plugin: String,
+    /// Export to call.
+    pub export: String,
+    /// Call arguments.
+    pub args: Vec<Value>,
+    /// When the call was made, relative to the start of the recording.
+    pub offset: Duration,
+}
+
+impl TracedCall {
+    /// Create a call without arguments.
+    pub fn new(plugin: impl Into<String>, export: impl Into<String>) -> Self {
+        Self {
+            plugin: plugin.into(),
+            export: export.into(),
+            args: Vec::new(),
+            offset: Duration::ZERO,
+        }
+    }
+
+    /// Set the call arguments.
+    pub fn with_args(mut self, args: Vec<Value>) -> Self {
+        self.args = args;
+        self
+    }
+
+    /// Set when the call was made, relative to the start of the recording.
+    pub fn with_offset(mut self, offset: Duration) -> Self {
+        self.offset = offset;
+        self
+    }
+}
+
+/// Calls to issue, in the order they are picked.
+///
+/// The generator cycles through the plan until the run ends, so a call with
+/// weight 3 is issued three times as often as one with weight 1. Replayed
+/// traces are issued once instead, each call at its recorded offset.
+#[derive(Debug, Clone, Default)]
+pub struct LoadPlan {
+    calls: Vec<(TracedCall, u64)>,
+    replay: bool,
+}
+
+impl LoadPlan {
+    /// Create an empty plan.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replay a recorded sequence of calls with its original timing.
+    ///
+    /// Each call is issued once, at its [`offset`](TracedCall::offset) from
+    /// the start of the run; the generator's rate does not apply.
+    pub fn replay(trace: impl IntoIterator<Item = TracedCall>) -> Self {
+        let mut calls: Vec<(TracedCall, u64)> = trace.into_iter().map(|call| (call, 1)).collect();
+        calls.sort_by_key(|(call, _)| call.offset);
+        Self {
+            calls,
+            replay: true,
+        }
+    }
+
+    /// Reproduce the call mix of a recorded profile, e.g. from
+    /// `PluginRuntime::call_profile()`. Exports are called without arguments.
+    pub fn from_profile(profile: &CallProfile) -> Self {
+        let mut calls: Vec<(TracedCall, u64)> = profile
+            .plugins
+            .iter()
+            .flat_map(|(plugin, exports)| {
+                exports
+                    .iter()
+                    .filter(|(_, calls)| **calls > 0)
+                    .map(move |(export, calls)| (TracedCall::new(plugin, export), *calls))
+            })
+            .collect();
+        calls.sort_by(|a, b| (&a.0.plugin, &a.0.export).cmp(&(&b.0.plugin, &b.0.export)));
+        Self {
+            calls,
+            replay: false,
+        }
+    }
+
+    /// Add a call issued `weight` times per cycle of the plan.
+    ///
+    /// Added to a replayed trace, the call is issued once at its offset.
+    pub fn with_call(mut self, call: TracedCall, weight: u64) -> Self {
+        if weight > 0 {
+            self.calls
+                .push((call, if self.replay { 1 } else { weight }));
+            if self.replay {
+                self.calls.sort_by_key(|(call, _)| call.offset);
+            }
+        }
+        self
+    }
+
+    /// Check if the plan replays a trace.
+    pub fn is_replay(&self) -> bool {
+        self.replay
+    }
+
+    /// Check if the plan has no calls.
+    pub fn is_empty(&self) -> bool {
+        self.calls.is_empty()
+    }
+
+    /// Get the `n`th call issued.
+    fn pick(&self, n: u64) -> &TracedCall {
+        let cycle: u64 = self.calls.iter().map(|(_, weight)| weight).sum();
+        let mut slot = n % cycle;
+        for (call, weight) in &self.calls {
+            if slot < *weight {
+                return call;
+            }
+            slot -= weight;
+        }
+        unreachable!("slot is below the cycle length")
+    }
+}
+
+/// Issues the calls of a [`LoadPlan`] against a runtime at a target rate.
+#[derive(Debug, Clone)]
+pub struct LoadGenerator {
+    plan: LoadPlan,
+    rate: f64,
+    duration: Duration,
+    concurrency: usize,
+}
+
+impl LoadGenerator {
+    /// Run a plan at 100 calls per second for 10 seconds on 4 threads.
+    pub fn new(plan: LoadPlan) -> Self {
+        Self {
+            plan,
+            rate: 100.0,
+            duration: Duration::from_secs(10),
+            concurrency: 4,
+        }
+    }
+
+    /// Set the target rate in calls per second, a positive finite number.
+    pub fn with_rate(mut self, calls_per_sec: f64) -> Self {
+        self.rate = calls_per_sec;
+        self
+    }
+
+    /// Set how long to generate load.
+    ///
+    /// Replayed calls recorded later than this are not issued.
+    pub fn with_duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Set the number of threads issuing calls.
+    ///
+    /// The achieved rate falls short of the target when every thread is
+    /// busy waiting on a slow call.
+    pub fn with_concurrency(mut self, threads: usize) -> Self {
+        self.concurrency = threads.max(1);
+        self
+    }
+
+    /// Check the rate and duration.
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = ConfigError::new();
+        errors.check(
+            self.rate.is_finite() && self.rate > 0.0,
+            "rate",
+            "must be a positive finite number of calls per second",
+        );
+        errors.check(
+            !self.duration.is_zero(),
+            "duration",
+            "must be greater than zero",
+        );
+        errors.into_result()
+    }
+
+    /// Get the number of calls the run issues.
+    fn total(&self) -> u64 {
+        if self.plan.replay {
+            let recorded = self
+                .plan
+                .calls
+                .iter()
+                .filter(|(call, _)| call.offset <= self.duration)
+                .count();
+            return recorded as u64;
+        }
+        (self.rate * self.duration.as_secs_f64()).floor() as u64
+    }
+
+    /// Get when the `n`th call is due, relative to the start of the run.
+    fn due(&self, n: u64) -> Duration {
+        if self.plan.replay {
+            return self.plan.calls[n as usize].0.offset;
+        }
+        Duration::from_secs_f64(n as f64 / self.rate)
+    }
+
+    /// Generate load against a runtime and report what it sustained.
+    ///
+    /// Fails with [`Error::Config`](crate::Error::Config) if the rate or
+    /// duration are invalid.
+    pub fn run(&self, runtime: &PluginRuntime) -> Result<LoadReport> {
+        self.validate()?;
+        let samples: Mutex<HashMap<String, Samples>> = Mutex::new(HashMap::new());
+        let total = self.total();
+        let next = AtomicU64::new(0);
+        let started = Instant::now();
+
+        if !self.plan.is_empty() {
+            std::thread::scope(|scope| {
+                for _ in 0..self.concurrency {
+                    scope.spawn(|| loop {
+                        let n = next.fetch_add(1, Ordering::Relaxed);
+                        if n >= total {
+                            break;
+                        }
+                        let due = started + self.due(n);
+                        if let Some(wait) = due.checked_duration_since(Instant::now()) {
+                            std::thread::sleep(wait);
+                        }
+
+                        let call = self.plan.pick(n);
+                        let call_started = Instant::now();
+                        let result = runtime.call(&call.plugin, &call.export, &call.args);
+                        let latency = call_started.elapsed();
+
+                        let mut samples = samples.lock();
+                        let entry = samples.entry(call.plugin.clone()).or_default();
+                        entry.latencies.push(latency);
+                        if result.is_err() {
+                            entry.errors += 1;
+                        }
+                    });
+                }
+            });
+        }
+
+        let elapsed = started.elapsed();
+        let plugins = samples
+            .into_inner()
+            .into_iter()
+            .map(|(name, samples)| {
+                let storage_bytes = runtime.storage_stats(&name).bytes;
+                (name, samples.summarize(elapsed, storage_bytes))
+            })
+            .collect();
+
+        let target_rate = if self.plan.replay {
+            total as f64
+                / self
+                    .due(total.saturating_sub(1))
+                    .as_secs_f64()
+                    .max(f64::EPSILON)
+        } else {
+            self.rate
+        };
+        Ok(LoadReport {
+            target_rate,
+            elapsed,
+            plugins,
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+struct Samples {
+    latencies: Vec<Duration>,
+    errors: u64,
+}
+
+impl Samples {
+    fn summarize(mut self, elapsed: Duration, storage_bytes: usize) -> PluginLoad {
+        self.latencies.sort();
+        let percentile = |p: f64| -> Duration {
+            let rank = ((p * self.latencies.len() as f64).ceil() as usize).max(1);
+            self.latencies.get(rank - 1).copied().unwrap_or_default()
+        };
+        let busy: Duration = self.latencies.iter().sum();
+
+        PluginLoad {
+            calls: self.latencies.len() as u64,
+            errors: self.errors,
+            p50: percentile(0.50),
+            p90: percentile(0.90),
+            p99: percentile(0.99),
+            max: self.latencies.last().copied().unwrap_or_default(),
+            busy,
+            concurrency: busy.as_secs_f64() / elapsed.as_secs_f64().max(f64::EPSILON),
+            storage_bytes,
+        }
+    }
+}
+
+/// Load sustained by one plugin.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PluginLoad {
+    /// Calls issued.
+    pub calls: u64,
+    /// Calls that failed.
+    pub errors: u64,
+    /// Median latency.
+    pub p50: Duration,
+    /// 90th percentile latency.
+    pub p90: Duration,
+    /// 99th percentile latency.
+    pub p99: Duration,
+    /// Slowest call.
+    pub max: Duration,
+    /// Total time spent in calls.
+    pub busy: Duration,
+    /// Average number of calls in flight, i.e. the pool size the load needs.
+    pub concurrency: f64,
+    /// Storage used by the plugin after the run.
+    pub storage_bytes: usize,
+}
+
+/// Outcome of [`LoadGenerator::run`].
+#[derive(Debug, Clone, Default)]
+pub struct LoadReport {
+    /// Requested calls per second; for replays, the recorded rate.
+    pub target_rate: f64,
+    /// Wall-clock duration of the run.
+    pub elapsed: Duration,
+    /// Load sustained per plugin.
+    pub plugins: HashMap<String, PluginLoad>,
+}
+
+impl LoadReport {
+    /// Get the total number of calls issued.
+    pub fn calls(&self) -> u64 {
+        self.plugins.values().map(|p| p.calls).sum()
+    }
+
+    /// Get the total number of failed calls.
+    pub fn errors(&self) -> u64 {
+        self.plugins.values().map(|p| p.errors).sum()
+    }
+
+    /// Get the achieved calls per second.
+    pub fn throughput(&self) -> f64 {
+        self.calls() as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::RuntimeConfig;
+
+    #[test]
+    fn test_plan_weights() {
+        let plan = LoadPlan::new()
+            .with_call(TracedCall::new("a", "main"), 3)
+            .with_call(TracedCall::new("b", "main"), 1)
+            .with_call(TracedCall::new("c", "main"), 0);
+        let picked: Vec<&str> = (0..8).map(|n| plan.pick(n).plugin.as_str()).collect();
+        assert_eq!(picked, ["a", "a", "a", "b", "a", "a", "a", "b"]);
+
+        let mut profile = CallProfile::new();
+        profile.record("search", "query", 5);
+        profile.record("search", "index", 0);
+        let plan = LoadPlan::from_profile(&profile);
+        assert_eq!(plan.calls.len(), 1);
+        assert_eq!(plan.calls[0].1, 5);
+    }
+
+    #[test]
+    fn test_run_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("echo.fsx");
+        std::fs::write(&path, "let x = 1").unwrap();
+        let runtime = PluginRuntime::new(RuntimeConfig::new()).unwrap();
+        runtime.load_source(&path).unwrap();
+
+        let plan = LoadPlan::new()
+            .with_call(TracedCall::new("echo", "main"), 1)
+            .with_call(TracedCall::new("missing", "main"), 1);
+        let report = LoadGenerator::new(plan)
+            .with_rate(200.0)
+            .with_duration(Duration::from_millis(100))
+            .with_concurrency(2)
+            .run(&runtime)
+            .unwrap();
+
+        assert_eq!(report.calls(), 20);
+        assert_eq!(report.plugins["echo"].calls, 10);
+        assert_eq!(report.plugins["echo"].errors, 0);
+        assert_eq!(report.plugins["missing"].errors, 10);
+        assert!(report.plugins["echo"].p50 <= report.plugins["echo"].max);
+    }
+
+    #[test]
+    fn test_invalid_rate() {
+        for rate in [f64::INFINITY, f64::NAN, 0.0, -1.0] {
+            let generator = LoadGenerator::new(LoadPlan::new()).with_rate(rate);
+            assert!(matches!(generator.validate(), Err(crate::Error::Config(_))));
+        }
+        let runtime = PluginRuntime::new(RuntimeConfig::new()).unwrap();
+        assert!(LoadGenerator::new(LoadPlan::new())
+            .with_rate(f64::INFINITY)
+            .run(&runtime)
+            .is_err());
+    }
+
+    #[test]
+    fn test_replay_timing() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("echo.fsx"), "let x = 1").unwrap();
+        let runtime = PluginRuntime::new(RuntimeConfig::new()).unwrap();
+        runtime.load_source(dir.path().join("echo.fsx")).unwrap();
+
+        let trace = [0, 60, 20, 500]
+            .map(|ms| TracedCall::new("echo", "main").with_offset(Duration::from_millis(ms)));
+        let plan = LoadPlan::replay(trace);
+        assert!(plan.is_replay());
+        let offsets: Vec<u64> = (0..4)
+            .map(|n| plan.pick(n).offset.as_millis() as u64)
+            .collect();
+        assert_eq!(offsets, [0, 20, 60, 500]);
+
+        // Issued once each at the recorded times, regardless of the rate
+        let report = LoadGenerator::new(plan)
+            .with_rate(1000.0)
+            .with_duration(Duration::from_millis(100))
+            .run(&runtime)
+            .unwrap();
+        assert_eq!(report.calls(), 3);
+        assert!(report.elapsed >= Duration::from_millis(60));
+        assert!(report.elapsed < Duration::from_millis(500));
+    }
+}
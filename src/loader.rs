@@ -1,12 +1,50 @@
 //! Plugin loading and compilation.
 
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-
-use fusabi_host::{compile_file, compile_source, validate_bytecode, CompileOptions, EngineConfig};
-
-use crate::error::{Error, Result};
+use std::sync::Arc;
+use std::time::Instant;
+
+use parking_lot::RwLock;
+
+use fusabi_host::{
+    compile_file, compile_source, validate_bytecode, CompileOptions, CompileResult, EngineConfig,
+};
+
+use crate::artifact_store::ArtifactStore;
+use crate::builtin::BuiltinPlugin;
+use crate::cache::BytecodeCache;
+use crate::capability_policy::{CapabilityDecision, CapabilityPolicy, CapabilityRequest};
+use crate::compat::{check_bytecode, BytecodeUpgrader};
+#[cfg(feature = "serde")]
+use crate::composite::Composite;
+use crate::config_error::{check_dir, ConfigError};
+#[cfg(feature = "serde")]
+use crate::delegation::{self, Delegation};
+use crate::diagnostic::SourceSpan;
+use crate::discovery;
+use crate::environment::EnvPolicy;
+use crate::error::{Error, PluginOperation, Result};
+#[cfg(feature = "serde")]
+use crate::explain::{suggestions, LoadExplanation, LoadFailure, LoadGate};
+use crate::host_functions::HostFunctionTable;
+#[cfg(feature = "isolation")]
+use crate::isolation::IsolationConfig;
+use crate::logger::DEFAULT_LOG_CAPACITY;
 use crate::manifest::{ApiVersion, Manifest};
-use crate::plugin::{Plugin, PluginHandle};
+#[cfg(feature = "metrics-prometheus")]
+use crate::metrics::PluginMetrics;
+use crate::namespace;
+use crate::origin::{self, OriginPolicy, PluginOrigin};
+use crate::plugin::{Plugin, PluginHandle, PluginInfo};
+use crate::policy::{self, PolicyFile};
+use crate::probe::EngineProbe;
+#[cfg(feature = "serde")]
+use crate::validation::{ValidationCheck, ValidationReport};
+
+/// Previous versions of each plugin kept in the artifact store unless
+/// configured otherwise.
+const DEFAULT_RETAINED_VERSIONS: usize = 10;
 
 /// Configuration for the plugin loader.
 #[derive(Debug, Clone)]
@@ -23,6 +61,42 @@ pub struct LoaderConfig {
     pub auto_start: bool,
     /// Whether to validate manifests strictly.
     pub strict_validation: bool,
+    /// Maximum size of a plugin's source or bytecode in bytes.
+    pub max_asset_size: Option<usize>,
+    /// Cache for compiled source files.
+    pub bytecode_cache: Option<Arc<BytecodeCache>>,
+    /// Store keeping the bytecode of every loaded plugin version.
+    pub artifact_store: Option<Arc<ArtifactStore>>,
+    /// Number of previous versions of each plugin kept in the artifact
+    /// store; ten when unset.
+    pub retained_versions: Option<usize>,
+    /// Policy consulted for capabilities not granted by `engine_config`;
+    /// they are granted when unset.
+    pub capability_policy: Option<Arc<dyn CapabilityPolicy>>,
+    /// Hook rewriting bytecode built for another engine version when no
+    /// source is available.
+    pub bytecode_upgrader: Option<Arc<dyn BytecodeUpgrader>>,
+    /// Number of output lines kept per plugin.
+    pub log_capacity: usize,
+    /// Directories whose plugins have a known origin.
+    pub origin_dirs: Vec<(PathBuf, PluginOrigin)>,
+    /// Policies overriding the built-in defaults per origin.
+    pub origin_policies: HashMap<PluginOrigin, OriginPolicy>,
+    /// Host functions the application provides; plugins requiring others
+    /// are rejected. Requirements are not checked when unset.
+    pub host_functions: Option<Arc<HostFunctionTable>>,
+    /// Environment variables plugins may read; none by default.
+    pub env_policy: EnvPolicy,
+    /// Optional manifest features enabled per plugin name.
+    pub plugin_features: HashMap<String, Vec<String>>,
+    /// Rules every plugin must satisfy to load; none when unset.
+    pub policy: Option<Arc<PolicyFile>>,
+    /// Worker processes for plugins declaring `isolation = "process"`.
+    #[cfg(feature = "isolation")]
+    pub isolation: Option<Arc<IsolationConfig>>,
+    /// Metrics attached to loaded plugins, also recording compile times.
+    #[cfg(feature = "metrics-prometheus")]
+    pub metrics: Option<Arc<PluginMetrics>>,
 }
 
 impl Default for LoaderConfig {
@@ -34,6 +108,23 @@ impl Default for LoaderConfig {
             base_path: None,
             auto_start: true,
             strict_validation: true,
+            max_asset_size: None,
+            bytecode_cache: None,
+            artifact_store: None,
+            retained_versions: None,
+            capability_policy: None,
+            bytecode_upgrader: None,
+            log_capacity: DEFAULT_LOG_CAPACITY,
+            origin_dirs: Vec::new(),
+            origin_policies: HashMap::new(),
+            host_functions: None,
+            env_policy: EnvPolicy::default(),
+            plugin_features: HashMap::new(),
+            policy: None,
+            #[cfg(feature = "isolation")]
+            isolation: None,
+            #[cfg(feature = "metrics-prometheus")]
+            metrics: None,
         }
     }
 }
@@ -80,6 +171,181 @@ impl LoaderConfig {
         self
     }
 
+    /// Set the maximum source or bytecode size.
+    pub fn with_max_asset_size(mut self, bytes: usize) -> Self {
+        self.max_asset_size = Some(bytes);
+        self
+    }
+
+    /// Set the bytecode cache used when compiling sources.
+    pub fn with_bytecode_cache(mut self, cache: impl Into<Arc<BytecodeCache>>) -> Self {
+        self.bytecode_cache = Some(cache.into());
+        self
+    }
+
+    /// Keep the bytecode of loaded plugin versions in an artifact store.
+    ///
+    /// Unless set with [`with_retained_versions`](Self::with_retained_versions),
+    /// ten previous versions of each plugin are kept and older ones are
+    /// evicted, together with the blobs nothing else refers to.
+    pub fn with_artifact_store(mut self, store: impl Into<Arc<ArtifactStore>>) -> Self {
+        self.artifact_store = Some(store.into());
+        self
+    }
+
+    /// Keep only the `previous` most recent versions of each plugin in the
+    /// artifact store besides the newest one.
+    pub fn with_retained_versions(mut self, previous: usize) -> Self {
+        self.retained_versions = Some(previous);
+        self
+    }
+
+    /// Set the policy deciding on capabilities not granted by the engine
+    /// configuration.
+    pub fn with_capability_policy(mut self, policy: impl CapabilityPolicy + 'static) -> Self {
+        self.capability_policy = Some(Arc::new(policy));
+        self
+    }
+
+    /// Set the hook upgrading incompatible bytecode of plugins without
+    /// source.
+    pub fn with_bytecode_upgrader(mut self, upgrader: impl BytecodeUpgrader + 'static) -> Self {
+        self.bytecode_upgrader = Some(Arc::new(upgrader));
+        self
+    }
+
+    /// Keep the last `lines` lines of output of each plugin.
+    pub fn with_log_capacity(mut self, lines: usize) -> Self {
+        self.log_capacity = lines;
+        self
+    }
+
+    /// Treat plugins under `dir` as coming from `origin`.
+    pub fn with_origin_dir(mut self, dir: impl Into<PathBuf>, origin: PluginOrigin) -> Self {
+        self.origin_dirs.push((dir.into(), origin));
+        self
+    }
+
+    /// Override the defaults applied to plugins of `origin`.
+    pub fn with_origin_policy(mut self, origin: PluginOrigin, policy: OriginPolicy) -> Self {
+        self.origin_policies.insert(origin, policy);
+        self
+    }
+
+    /// Check plugin host function requirements against `table`.
+    pub fn with_host_functions(mut self, table: impl Into<Arc<HostFunctionTable>>) -> Self {
+        self.host_functions = Some(table.into());
+        self
+    }
+
+    /// Set the environment variables plugins may read.
+    pub fn with_env_policy(mut self, policy: EnvPolicy) -> Self {
+        self.env_policy = policy;
+        self
+    }
+
+    /// Enable optional features declared in a plugin's manifest, adding
+    /// their capabilities and exports when the plugin is loaded.
+    ///
+    /// Loading fails if the plugin does not declare one of the features.
+    pub fn with_plugin_features<I, S>(mut self, plugin: impl Into<String>, features: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.plugin_features
+            .entry(plugin.into())
+            .or_default()
+            .extend(features.into_iter().map(Into::into));
+        self
+    }
+
+    /// Enforce a policy on every plugin loaded.
+    ///
+    /// Plugins declaring forbidden capabilities, with banned names or
+    /// namespaces, or lacking a required signature fail to load with
+    /// [`Error::PolicyViolation`]; engine limits are lowered to the
+    /// policy's caps.
+    pub fn with_policy(mut self, policy: PolicyFile) -> Self {
+        self.policy = Some(Arc::new(policy));
+        self
+    }
+
+    /// Get the features enabled for a plugin.
+    pub fn plugin_features(&self, plugin: &str) -> &[String] {
+        self.plugin_features
+            .get(plugin)
+            .map_or(&[], |features| features.as_slice())
+    }
+
+    /// Get the host functions `manifest` requires that are not registered.
+    pub fn missing_host_functions(&self, manifest: &Manifest) -> Vec<String> {
+        self.host_functions
+            .as_ref()
+            .map(|table| table.missing(&manifest.host_functions))
+            .unwrap_or_default()
+    }
+
+    /// Get the origin of a plugin loaded from `path`.
+    pub fn origin_of(&self, path: &Path) -> PluginOrigin {
+        origin::origin_of(&self.origin_dirs, path).unwrap_or_default()
+    }
+
+    /// Get the policy applied to plugins of `origin`.
+    pub fn origin_policy(&self, origin: PluginOrigin) -> OriginPolicy {
+        self.origin_policies
+            .get(&origin)
+            .cloned()
+            .unwrap_or_else(|| OriginPolicy::for_origin(origin))
+    }
+
+    /// Validate the configuration, failing with [`Error::Config`] listing
+    /// every invalid field.
+    ///
+    /// Origin directories need not exist yet, since plugins may be
+    /// installed into them later.
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = ConfigError::new();
+        if let Some(ref base) = self.base_path {
+            check_dir(&mut errors, "base_path", base);
+        }
+        errors.check(
+            self.max_asset_size != Some(0),
+            "max_asset_size",
+            "must be greater than zero",
+        );
+        errors.check(
+            self.retained_versions.is_none() || self.artifact_store.is_some(),
+            "retained_versions",
+            "requires an artifact store",
+        );
+        for (i, (dir, _)) in self.origin_dirs.iter().enumerate() {
+            errors.check(
+                !dir.exists() || dir.is_dir(),
+                &format!("origin_dirs[{}]", i),
+                format!("{} is not a directory", dir.display()),
+            );
+        }
+        if let Some(ref policy) = self.policy {
+            errors.nest("policy", policy.validate());
+        }
+        errors.into_result()
+    }
+
+    /// Run plugins declaring process isolation in workers started by `config`.
+    #[cfg(feature = "isolation")]
+    pub fn with_isolation(mut self, config: IsolationConfig) -> Self {
+        self.isolation = Some(Arc::new(config));
+        self
+    }
+
+    /// Record compile times and plugin calls to `metrics`.
+    #[cfg(feature = "metrics-prometheus")]
+    pub fn with_metrics(mut self, metrics: impl Into<Arc<PluginMetrics>>) -> Self {
+        self.metrics = Some(metrics.into());
+        self
+    }
+
     /// Create a strict loader config.
     pub fn strict() -> Self {
         Self {
@@ -89,6 +355,23 @@ impl LoaderConfig {
             base_path: None,
             auto_start: false,
             strict_validation: true,
+            max_asset_size: None,
+            bytecode_cache: None,
+            artifact_store: None,
+            retained_versions: None,
+            capability_policy: None,
+            bytecode_upgrader: None,
+            log_capacity: DEFAULT_LOG_CAPACITY,
+            origin_dirs: Vec::new(),
+            origin_policies: HashMap::new(),
+            host_functions: None,
+            env_policy: EnvPolicy::default(),
+            plugin_features: HashMap::new(),
+            policy: None,
+            #[cfg(feature = "isolation")]
+            isolation: None,
+            #[cfg(feature = "metrics-prometheus")]
+            metrics: None,
         }
     }
 }
@@ -96,12 +379,21 @@ impl LoaderConfig {
 /// Plugin loader for loading plugins from manifests and source files.
 pub struct PluginLoader {
     config: LoaderConfig,
+    engine_config: RwLock<EngineConfig>,
+    probe: RwLock<EngineProbe>,
 }
 
 impl PluginLoader {
     /// Create a new plugin loader.
     pub fn new(config: LoaderConfig) -> Result<Self> {
-        Ok(Self { config })
+        config.validate()?;
+        let probe = RwLock::new(EngineProbe::run(&config.engine_config));
+        let engine_config = RwLock::new(config.engine_config.clone());
+        Ok(Self {
+            config,
+            engine_config,
+            probe,
+        })
     }
 
     /// Get the loader configuration.
@@ -109,6 +401,29 @@ impl PluginLoader {
         &self.config
     }
 
+    /// Get the base engine configuration for new engines.
+    pub fn engine_config(&self) -> EngineConfig {
+        self.engine_config.read().clone()
+    }
+
+    /// Replace the base engine configuration.
+    ///
+    /// Applies to engines created afterwards; running plugins keep their
+    /// engines until reloaded or restarted.
+    pub fn set_engine_config(&self, config: EngineConfig) {
+        *self.probe.write() = EngineProbe::run(&config);
+        *self.engine_config.write() = config;
+    }
+
+    /// Get what the engine build supports, probed when the loader was
+    /// created or the engine configuration last changed.
+    ///
+    /// Loading rejects plugins needing capabilities or backends the build
+    /// lacks before creating their engines.
+    pub fn probe_engine(&self) -> EngineProbe {
+        self.probe.read().clone()
+    }
+
     /// Load a plugin from a manifest file.
     #[cfg(feature = "serde")]
     pub fn load_from_manifest(&self, manifest_path: impl AsRef<Path>) -> Result<PluginHandle> {
@@ -118,60 +433,468 @@ impl PluginLoader {
         self.load_manifest(manifest, Some(manifest_path))
     }
 
+    /// Load a plugin on behalf of `parent`, narrowing its capabilities to
+    /// those `parent` holds.
+    ///
+    /// Returns the plugin and the names of the capabilities it declares but
+    /// was not given. Composite manifests are rejected since their members
+    /// are loaded from their own manifests.
+    #[cfg(feature = "serde")]
+    pub fn load_delegated(
+        &self,
+        manifest_path: impl AsRef<Path>,
+        parent: &PluginHandle,
+    ) -> Result<(PluginHandle, Vec<String>)> {
+        let manifest_path = self.resolve_path(manifest_path.as_ref());
+        let mut manifest = Manifest::from_file(&manifest_path)?;
+        if manifest.is_composite() {
+            return Err(Error::invalid_manifest(format!(
+                "composite plugin {} cannot be loaded by plugin {}",
+                manifest.name,
+                parent.name()
+            )));
+        }
+
+        // Enabled features are subject to the parent's capabilities too
+        self.enable_features(&mut manifest)?;
+        let delegation = Delegation::new(parent);
+        let withheld = delegation.restrict(&mut manifest);
+        let plugin = self.initialize_manifest(manifest, Some(manifest_path))?;
+        plugin.inner().set_delegation(delegation);
+
+        if self.config.auto_start {
+            plugin.inner().start()?;
+        }
+
+        Ok((plugin, withheld))
+    }
+
+    /// Walk a manifest through every load gate without registering the
+    /// plugin, reporting the first gate that fails.
+    ///
+    /// The manifest is always validated, even without strict validation.
+    /// Dependencies are skipped since the loader does not know which plugins
+    /// are loaded; [`PluginRuntime::explain`](crate::PluginRuntime::explain)
+    /// checks them too.
+    #[cfg(feature = "serde")]
+    pub fn explain(&self, manifest_path: impl AsRef<Path>) -> LoadExplanation {
+        self.explain_against(manifest_path.as_ref(), None)
+    }
+
+    /// Check a plugin without creating an engine or registering it,
+    /// reporting every problem found.
+    ///
+    /// Checks the manifest fields, capability names and API version, that
+    /// the entry file exists and compiles, and that the declared exports are
+    /// defined by the compiled code. The manifest is always validated, even
+    /// without strict validation.
+    #[cfg(feature = "serde")]
+    pub fn validate(&self, manifest_path: impl AsRef<Path>) -> ValidationReport {
+        let manifest_path = self.resolve_path(manifest_path.as_ref());
+        let mut report = ValidationReport::new(manifest_path.clone());
+
+        let manifest = match Manifest::from_file(&manifest_path) {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                report.check::<()>(ValidationCheck::Schema, Err(e));
+                return report;
+            }
+        };
+        report.plugin = Some(manifest.name.clone());
+
+        let unknown: Vec<Error> = manifest
+            .capabilities
+            .iter()
+            .filter(|spec| {
+                fusabi_host::Capability::from_name(&spec.name).is_none()
+                    && !delegation::is_runtime_capability(&spec.name)
+            })
+            .map(|spec| Error::invalid_manifest(format!("unknown capability: {}", spec)))
+            .collect();
+        // Field validation checks capability names last; leave those to the
+        // capabilities check
+        let fields = match manifest.validate() {
+            Err(e)
+                if unknown
+                    .first()
+                    .is_some_and(|u| u.to_string() == e.to_string()) =>
+            {
+                Ok(())
+            }
+            result => result,
+        };
+        report.check(ValidationCheck::Schema, fields);
+        report.check_all(ValidationCheck::Capabilities, unknown);
+
+        let api_version = if manifest.is_compatible_with_host(&self.config.host_api_version) {
+            Ok(())
+        } else {
+            Err(Error::api_version_mismatch(
+                manifest.api_version.to_string(),
+                self.config.host_api_version.to_string(),
+            ))
+        };
+        report.check(ValidationCheck::ApiVersion, api_version);
+
+        let Some(entry_path) = self.entry_path(&manifest, Some(&manifest_path)) else {
+            return report;
+        };
+        let entry = if entry_path.is_file() {
+            Ok(())
+        } else {
+            Err(Error::invalid_manifest(format!(
+                "entry file {} does not exist",
+                entry_path.display()
+            )))
+        };
+        if report.check(ValidationCheck::EntryFile, entry).is_none() {
+            return report;
+        }
+
+        let Some(bytecode) = report.check(
+            ValidationCheck::Compile,
+            self.load_entry(&manifest, &entry_path),
+        ) else {
+            return report;
+        };
+        if !manifest.uses_wasm() {
+            let missing = undefined_exports(&manifest, &bytecode)
+                .into_iter()
+                .map(|export| Error::missing_export(&manifest.name, export))
+                .collect();
+            report.check_all(ValidationCheck::Exports, missing);
+        }
+
+        report
+    }
+
+    /// Explain a load, checking dependencies against `loaded` if given.
+    #[cfg(feature = "serde")]
+    pub(crate) fn explain_against(
+        &self,
+        manifest_path: &Path,
+        loaded: Option<&[PluginInfo]>,
+    ) -> LoadExplanation {
+        let manifest_path = self.resolve_path(manifest_path);
+        let mut passed = Vec::new();
+        let failure = self
+            .run_gates(&manifest_path, loaded, &mut passed)
+            .err()
+            .map(|failure| *failure);
+        LoadExplanation {
+            manifest_path,
+            passed,
+            failure,
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    fn run_gates(
+        &self,
+        manifest_path: &Path,
+        loaded: Option<&[PluginInfo]>,
+        passed: &mut Vec<LoadGate>,
+    ) -> std::result::Result<(), Box<LoadFailure>> {
+        let fail = |gate: LoadGate, error: Error, context: Vec<String>| {
+            Box::new(LoadFailure {
+                gate,
+                suggestions: suggestions(gate, &error),
+                error,
+                context,
+            })
+        };
+
+        let mut manifest =
+            Manifest::from_file(manifest_path).map_err(|e| fail(LoadGate::Parse, e, Vec::new()))?;
+        passed.push(LoadGate::Parse);
+
+        manifest
+            .validate()
+            .and_then(|()| self.enable_features(&mut manifest))
+            .and_then(|()| self.check_policy(&manifest))
+            .map_err(|e| fail(LoadGate::Validate, e, Vec::new()))?;
+        passed.push(LoadGate::Validate);
+
+        if !manifest.is_compatible_with_host(&self.config.host_api_version) {
+            let error = Error::api_version_mismatch(
+                manifest.api_version.to_string(),
+                self.config.host_api_version.to_string(),
+            );
+            let context = vec![
+                format!("plugin api-version: {}", manifest.api_version),
+                format!("host api-version: {}", self.config.host_api_version),
+            ];
+            return Err(fail(LoadGate::ApiVersion, error, context));
+        }
+        passed.push(LoadGate::ApiVersion);
+
+        let origin = self.config.origin_of(manifest_path);
+        let engine_config = self.build_engine_config(&manifest, origin).map_err(|e| {
+            let requested: Vec<String> = manifest
+                .capabilities
+                .iter()
+                .map(|c| c.to_string())
+                .collect();
+            let context = vec![format!("requested: {}", requested.join(", "))];
+            fail(LoadGate::Capabilities, e, context)
+        })?;
+        passed.push(LoadGate::Capabilities);
+
+        let missing = self.config.missing_host_functions(&manifest);
+        if let Some(function) = missing.first() {
+            let error = Error::missing_host_function(&manifest.name, function);
+            let context = vec![format!("missing: {}", missing.join(", "))];
+            return Err(fail(LoadGate::HostFunctions, error, context));
+        }
+        passed.push(LoadGate::HostFunctions);
+
+        if let Some(loaded) = loaded {
+            for dep in manifest.dependencies.iter().filter(|d| !d.optional) {
+                let target = loaded.iter().find(|info| info.name == dep.name);
+                if target.is_some_and(|info| dep.is_satisfied_by(&info.version)) {
+                    continue;
+                }
+                let context = vec![match target {
+                    Some(info) => format!("{} {} is loaded", info.name, info.version),
+                    None => format!("{} is not loaded", dep.name),
+                }];
+                let error = Error::dependency_not_satisfied(&dep.name, &dep.version);
+                return Err(fail(LoadGate::Dependencies, error, context));
+            }
+            passed.push(LoadGate::Dependencies);
+        }
+
+        let entry_path = self.entry_path(&manifest, Some(manifest_path));
+        let entry_context = || {
+            entry_path
+                .iter()
+                .map(|p| format!("entry: {}", p.display()))
+                .collect::<Vec<_>>()
+        };
+        self.check_signature(&manifest, Some(manifest_path), entry_path.as_deref())
+            .map_err(|e| fail(LoadGate::Compile, e, entry_context()))?;
+        let bytecode = match entry_path {
+            Some(ref path) => Some(
+                self.load_entry(&manifest, path)
+                    .and_then(|bytecode| {
+                        self.verify_exports(&manifest, &bytecode)?;
+                        Ok(bytecode)
+                    })
+                    .map_err(|e| fail(LoadGate::Compile, e, entry_context()))?,
+            ),
+            None => None,
+        };
+        passed.push(LoadGate::Compile);
+
+        let plugin = self.new_plugin(manifest);
+        if let Some(bytecode) = bytecode {
+            plugin.set_bytecode(bytecode);
+        }
+        plugin
+            .initialize(engine_config)
+            .and_then(|()| plugin.start())
+            .map_err(|e| fail(LoadGate::Init, e, entry_context()))?;
+        // Run cleanup() so the trial engine releases what init() acquired
+        if let Err(e) = plugin.stop() {
+            tracing::debug!(
+                "Plugin {} cleanup after explain failed: {}",
+                plugin.name(),
+                e
+            );
+        }
+        passed.push(LoadGate::Init);
+
+        Ok(())
+    }
+
     /// Load a plugin from a manifest object.
     pub fn load_manifest(
         &self,
-        manifest: Manifest,
+        mut manifest: Manifest,
         manifest_path: Option<PathBuf>,
     ) -> Result<PluginHandle> {
-        // Validate manifest
-        if self.config.strict_validation {
-            manifest.validate()?;
+        self.enable_features(&mut manifest)?;
+        let plugin = self.initialize_manifest(manifest, manifest_path)?;
+
+        // Auto-start if configured
+        if self.config.auto_start {
+            plugin.inner().start()?;
         }
 
-        // Check API version compatibility
-        if !manifest.is_compatible_with_host(&self.config.host_api_version) {
-            return Err(Error::api_version_mismatch(
-                manifest.api_version.to_string(),
-                self.config.host_api_version.to_string(),
-            ));
+        Ok(plugin)
+    }
+
+    /// Load and initialize a plugin from a manifest object, without
+    /// starting it.
+    fn initialize_manifest(
+        &self,
+        manifest: Manifest,
+        manifest_path: Option<PathBuf>,
+    ) -> Result<PluginHandle> {
+        self.check_manifest(&manifest)?;
+        if manifest.is_composite() {
+            return self.initialize_composite(manifest, manifest_path, &mut Vec::new());
         }
 
         // Create plugin
-        let plugin = Plugin::new(manifest.clone());
+        let plugin = self.new_plugin(manifest.clone());
+        let origin = manifest_path
+            .as_deref()
+            .map_or(PluginOrigin::Direct, |path| self.config.origin_of(path));
+        plugin.set_origin(origin);
 
         // Resolve entry point path
-        let entry_path = manifest.entry_point().map(|p| {
-            if let Some(ref manifest_path) = manifest_path {
-                manifest_path.parent().unwrap_or(Path::new(".")).join(p)
-            } else {
-                self.resolve_path(Path::new(p))
-            }
-        });
+        let entry_path = self.entry_path(&manifest, manifest_path.as_deref());
+
+        plugin.set_paths(manifest_path.clone(), entry_path.clone());
+        self.check_signature(&manifest, manifest_path.as_deref(), entry_path.as_deref())?;
 
         // Load source or bytecode
         if let Some(ref entry_path) = entry_path {
-            if manifest.uses_source() {
-                self.compile_and_load(&plugin, entry_path)?;
-            } else {
-                self.load_bytecode(&plugin, entry_path)?;
-            }
+            let source = source_snapshot(entry_path);
+            let bytecode = self.load_entry(&manifest, entry_path)?;
+            self.verify_exports(&manifest, &bytecode)?;
+            self.store_artifact(&manifest, &bytecode);
+            plugin.set_bytecode(bytecode);
+            plugin.set_source(source);
         }
 
         // Build engine config with required capabilities
-        let engine_config = self.build_engine_config(&manifest)?;
+        let engine_config = self.build_engine_config(&manifest, origin)?;
 
         // Initialize plugin
         plugin.initialize(engine_config)?;
 
-        // Auto-start if configured
-        if self.config.auto_start {
-            plugin.start()?;
+        Ok(PluginHandle::new(plugin))
+    }
+
+    /// Load the members of a composite manifest and the plugin forwarding
+    /// to them.
+    ///
+    /// `visiting` holds the canonical paths of the composites being loaded
+    /// around this one, so a composite that includes itself, directly or
+    /// through another composite, fails instead of recursing forever. If
+    /// anything fails, the members loaded so far are unloaded again.
+    #[cfg(feature = "serde")]
+    fn initialize_composite(
+        &self,
+        mut manifest: Manifest,
+        manifest_path: Option<PathBuf>,
+        visiting: &mut Vec<PathBuf>,
+    ) -> Result<PluginHandle> {
+        self.check_signature(&manifest, manifest_path.as_deref(), None)?;
+        let canonical = manifest_path
+            .as_deref()
+            .map(|path| std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()));
+        if let Some(ref canonical) = canonical {
+            if visiting.contains(canonical) {
+                return Err(Error::invalid_manifest(format!(
+                    "composite {} includes itself through {}",
+                    manifest.name,
+                    canonical.display()
+                )));
+            }
+            visiting.push(canonical.clone());
+        }
+        let result = self.initialize_composite_members(&mut manifest, &manifest_path, visiting);
+        if canonical.is_some() {
+            visiting.pop();
+        }
+        let composite = result?;
+
+        let plugin = self.new_plugin(manifest.clone());
+        let origin = manifest_path
+            .as_deref()
+            .map_or(PluginOrigin::Direct, |path| self.config.origin_of(path));
+        plugin.set_origin(origin);
+        plugin.set_paths(manifest_path, None);
+        plugin.set_composite(composite);
+
+        let initialized = self
+            .build_engine_config(&manifest, origin)
+            .and_then(|engine_config| plugin.initialize(engine_config));
+        if let Err(e) = initialized {
+            // Unloading the composite unloads the members it holds
+            let _ = plugin.unload();
+            return Err(e);
         }
 
         Ok(PluginHandle::new(plugin))
     }
 
+    /// Load each member of a composite, unloading those already loaded if
+    /// one fails.
+    #[cfg(feature = "serde")]
+    fn initialize_composite_members(
+        &self,
+        manifest: &mut Manifest,
+        manifest_path: &Option<PathBuf>,
+        visiting: &mut Vec<PathBuf>,
+    ) -> Result<Composite> {
+        let mut members: Vec<(String, PluginHandle)> = Vec::with_capacity(manifest.members.len());
+        let unload = |members: &[(String, PluginHandle)]| {
+            for (_, member) in members.iter().rev() {
+                let _ = member.inner().unload();
+            }
+        };
+
+        for member in &manifest.members {
+            let member_path = self.resolve_path(&member.manifest_path(manifest_path.as_deref()));
+            let loaded = Manifest::from_file(&member_path).and_then(|mut member_manifest| {
+                self.enable_features(&mut member_manifest)?;
+                let name = member_manifest.name.clone();
+                let prefix = member
+                    .prefix
+                    .clone()
+                    .unwrap_or_else(|| format!("{}.", name));
+                let handle = if member_manifest.is_composite() {
+                    self.check_manifest(&member_manifest).and_then(|()| {
+                        self.initialize_composite(
+                            member_manifest,
+                            Some(member_path.clone()),
+                            visiting,
+                        )
+                    })
+                } else {
+                    self.initialize_manifest(member_manifest, Some(member_path.clone()))
+                }
+                .map_err(|e| e.in_plugin(name, PluginOperation::Load, Some(member_path)))?;
+                Ok((prefix, handle))
+            });
+            match loaded {
+                Ok(member) => members.push(member),
+                Err(e) => {
+                    unload(&members);
+                    return Err(e);
+                }
+            }
+        }
+
+        match Composite::new(members.clone()) {
+            Ok(composite) => {
+                manifest.exports = composite.exports();
+                Ok(composite)
+            }
+            Err(e) => {
+                unload(&members);
+                Err(e)
+            }
+        }
+    }
+
+    /// Member manifests can only be read with the `serde` feature.
+    #[cfg(not(feature = "serde"))]
+    fn initialize_composite(
+        &self,
+        _manifest: Manifest,
+        _manifest_path: Option<PathBuf>,
+        _visiting: &mut Vec<PathBuf>,
+    ) -> Result<PluginHandle> {
+        Err(Error::invalid_manifest(
+            "composite plugins require the `serde` feature",
+        ))
+    }
+
     /// Load a plugin from a source file directly.
     pub fn load_source(&self, source_path: impl AsRef<Path>) -> Result<PluginHandle> {
         let source_path = self.resolve_path(source_path.as_ref());
@@ -185,18 +908,52 @@ impl PluginLoader {
             .and_then(|s| s.to_str())
             .unwrap_or("unnamed")
             .to_string();
+        self.check_asset_size(&name, source.len())?;
 
         let manifest = Manifest::new(name, "0.0.0");
+        self.check_policy(&manifest)?;
 
         // Create plugin
-        let plugin = Plugin::new(manifest);
+        let plugin = self.new_plugin(manifest);
+        plugin.set_origin(self.config.origin_of(&source_path));
+        plugin.set_paths(None, Some(source_path));
 
         // Compile source
-        let compile_result = compile_source(&source, &self.config.compile_options)?;
-        plugin.set_bytecode(compile_result.bytecode);
+        let bytecode = self
+            .compile_cached(&plugin.name(), &source)
+            .map_err(|e| locate_compile_error(e, &source_path, Some(&source)))?;
+        plugin.set_bytecode(bytecode);
+        plugin.set_source(Some(source));
 
         // Initialize with default config
-        plugin.initialize(self.config.engine_config.clone())?;
+        plugin.initialize(self.capped_engine_config())?;
+
+        // Auto-start if configured
+        if self.config.auto_start {
+            plugin.start()?;
+        }
+
+        Ok(PluginHandle::new(plugin))
+    }
+
+    /// Load a plugin implemented natively in Rust.
+    pub fn load_builtin(
+        &self,
+        name: impl Into<String>,
+        builtin: BuiltinPlugin,
+    ) -> Result<PluginHandle> {
+        let manifest = builtin.manifest(&name.into());
+        if manifest.name.is_empty() {
+            return Err(Error::missing_field("name"));
+        }
+        // Builtins are compiled into the host, so only signatures are skipped
+        self.check_policy(&manifest)?;
+
+        let plugin = self.new_plugin(manifest);
+        plugin.set_origin(PluginOrigin::Builtin);
+        plugin.set_builtin(builtin);
+
+        plugin.initialize(self.capped_engine_config())?;
 
         // Auto-start if configured
         if self.config.auto_start {
@@ -210,27 +967,27 @@ impl PluginLoader {
     pub fn load_bytecode_file(&self, bytecode_path: impl AsRef<Path>) -> Result<PluginHandle> {
         let bytecode_path = self.resolve_path(bytecode_path.as_ref());
 
-        // Read bytecode
-        let bytecode = std::fs::read(&bytecode_path)?;
-
-        // Validate bytecode
-        let metadata = validate_bytecode(&bytecode)?;
-
-        // Create manifest from bytecode metadata
         let name = bytecode_path
             .file_stem()
             .and_then(|s| s.to_str())
             .unwrap_or("unnamed")
             .to_string();
 
+        // Read and validate bytecode
+        let bytecode = self.read_bytecode(&name, &bytecode_path)?;
+        let metadata = validate_bytecode(&bytecode)?;
+
         let manifest = Manifest::new(name, metadata.compiler_version.clone());
+        self.check_policy(&manifest)?;
 
         // Create plugin
-        let plugin = Plugin::new(manifest);
+        let plugin = self.new_plugin(manifest);
+        plugin.set_origin(self.config.origin_of(&bytecode_path));
+        plugin.set_paths(None, Some(bytecode_path));
         plugin.set_bytecode(bytecode);
 
         // Initialize with default config
-        plugin.initialize(self.config.engine_config.clone())?;
+        plugin.initialize(self.capped_engine_config())?;
 
         // Auto-start if configured
         if self.config.auto_start {
@@ -240,11 +997,203 @@ impl PluginLoader {
         Ok(PluginHandle::new(plugin))
     }
 
+    /// Load a plugin from source held in memory, such as generated code or
+    /// a download, without writing it to a file first.
+    ///
+    /// The plugin has no files to reload from, so
+    /// [`reload_from_disk`](Self::reload_from_disk) fails for it and hot
+    /// reload never picks it up.
+    pub fn load_source_str(&self, name: impl Into<String>, source: &str) -> Result<PluginHandle> {
+        let name = name.into();
+        Self::check_name(&name)?;
+        self.check_asset_size(&name, source.len())?;
+
+        let bytecode = self.compile_cached(&name, source)?;
+        let plugin = self.load_in_memory(Manifest::new(name, "0.0.0"), bytecode)?;
+        plugin.inner().set_source(Some(source.to_string()));
+        Ok(plugin)
+    }
+
+    /// Load a plugin from bytecode held in memory.
+    ///
+    /// Bytecode from an older compiler goes through the configured
+    /// [`BytecodeUpgrader`], as there is no source to recompile. Like
+    /// [`load_source_str`](Self::load_source_str), the plugin cannot be
+    /// reloaded from disk.
+    pub fn load_bytecode_bytes(
+        &self,
+        name: impl Into<String>,
+        bytecode: Vec<u8>,
+    ) -> Result<PluginHandle> {
+        let name = name.into();
+        Self::check_name(&name)?;
+        self.check_asset_size(&name, bytecode.len())?;
+
+        let bytecode =
+            self.accept_bytecode(&name, bytecode, &format!("<memory:{}>", name), None)?;
+        let metadata = validate_bytecode(&bytecode)?;
+        self.load_in_memory(Manifest::new(name, metadata.compiler_version), bytecode)
+    }
+
+    /// Check the name of a plugin that has no manifest or file to name it.
+    fn check_name(name: &str) -> Result<()> {
+        if name.is_empty() {
+            return Err(Error::missing_field("name"));
+        }
+        namespace::validate(name)
+    }
+
+    fn load_in_memory(&self, manifest: Manifest, bytecode: Vec<u8>) -> Result<PluginHandle> {
+        self.check_policy(&manifest)?;
+        let plugin = self.new_plugin(manifest);
+        plugin.set_bytecode(bytecode);
+        plugin.initialize(self.capped_engine_config())?;
+
+        if self.config.auto_start {
+            plugin.start()?;
+        }
+
+        Ok(PluginHandle::new(plugin))
+    }
+
     /// Reload a plugin.
     pub fn reload(&self, plugin: &PluginHandle) -> Result<()> {
         plugin.inner().reload()
     }
 
+    /// Reload a plugin from the files it was loaded from.
+    ///
+    /// The manifest is re-read, the entry point recompiled or re-validated,
+    /// and a fresh engine initialized before it is swapped in. Any failure
+    /// is reported as [`Error::ReloadFailed`] and the plugin keeps running
+    /// its previous version.
+    ///
+    /// A composite plugin reloads all of its members this way, swapping them
+    /// in together once every one is ready. Its own manifest is not re-read:
+    /// adding or removing members takes an unload and a fresh load.
+    pub fn reload_from_disk(&self, plugin: &PluginHandle) -> Result<()> {
+        let mut plugins = Vec::new();
+        let mut pending = vec![plugin.clone()];
+        while let Some(plugin) = pending.pop() {
+            let members = plugin.inner().members();
+            if members.is_empty() {
+                plugins.push(plugin);
+            } else {
+                pending.extend(members.into_iter().rev());
+            }
+        }
+
+        let mut swaps = Vec::with_capacity(plugins.len());
+        for plugin in plugins {
+            let info = plugin.info();
+            let prepared = self
+                .prepare_reload(&plugin, &info)
+                .and_then(|(manifest, entry_path, bytecode, source, engine_config)| {
+                    let swap = plugin
+                        .inner()
+                        .prepare_swap(manifest, Some(bytecode), engine_config)?
+                        .with_source(source);
+                    Ok((entry_path, swap))
+                })
+                .map_err(|e| match e.root() {
+                    Error::ReloadFailed(_) => e,
+                    _ => Error::ReloadFailed(format!("{}: {}", info.name, e)),
+                });
+            match prepared {
+                Ok((entry_path, swap)) => swaps.push((plugin, info, entry_path, swap)),
+                Err(e) => {
+                    for (_, _, _, swap) in swaps {
+                        swap.discard();
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        let mut swaps = swaps.into_iter();
+        while let Some((plugin, info, entry_path, swap)) = swaps.next() {
+            let retired = match plugin.inner().commit_swap(swap) {
+                Ok(retired) => retired,
+                Err(e) => {
+                    for (_, _, _, swap) in swaps {
+                        swap.discard();
+                    }
+                    return Err(e);
+                }
+            };
+            retired.retire();
+            // The new manifest passed the host function check
+            plugin.inner().set_missing_host_functions(Vec::new());
+            if let Some(origin) = info
+                .manifest_path
+                .as_deref()
+                .and_then(|path| origin::origin_of(&self.config.origin_dirs, path))
+            {
+                plugin.inner().set_origin(origin);
+            }
+            plugin
+                .inner()
+                .set_paths(info.manifest_path, Some(entry_path));
+        }
+
+        Ok(())
+    }
+
+    /// Read, validate and compile the new version of a plugin.
+    fn prepare_reload(&self, plugin: &PluginHandle, info: &PluginInfo) -> Result<PreparedReload> {
+        let current = plugin.inner().manifest();
+
+        let (manifest, entry_path, engine_config) = match info.manifest_path {
+            Some(ref manifest_path) => {
+                #[cfg(feature = "serde")]
+                let manifest = {
+                    let mut manifest = Manifest::from_file(manifest_path)?;
+                    discovery::keep_discovered_name(&current, &mut manifest);
+                    self.enable_features(&mut manifest)?;
+                    if let Some(delegation) = plugin.inner().delegation() {
+                        delegation.restrict(&mut manifest);
+                    }
+                    manifest
+                };
+                // Manifests can only be re-read with the `serde` feature
+                #[cfg(not(feature = "serde"))]
+                let manifest = current;
+                self.check_manifest(&manifest)?;
+                let entry_path = self.entry_path(&manifest, Some(manifest_path));
+                let origin = origin::origin_of(&self.config.origin_dirs, manifest_path)
+                    .unwrap_or(info.origin);
+                let engine_config = self.build_engine_config(&manifest, origin)?;
+                (manifest, entry_path, engine_config)
+            }
+            None => {
+                let engine_config = plugin
+                    .inner()
+                    .engine_config()
+                    .unwrap_or_else(|| self.capped_engine_config());
+                (current, info.entry_path.clone(), engine_config)
+            }
+        };
+
+        let entry_path = entry_path.ok_or_else(|| {
+            Error::ReloadFailed(format!("no source recorded for plugin {}", info.name))
+        })?;
+
+        let source = source_snapshot(&entry_path);
+        let bytecode = if let Some(ref manifest_path) = info.manifest_path {
+            self.check_signature(&manifest, Some(manifest_path), Some(&entry_path))?;
+            let bytecode = self.load_entry(&manifest, &entry_path)?;
+            self.verify_exports(&manifest, &bytecode)?;
+            self.store_artifact(&manifest, &bytecode);
+            bytecode
+        } else if entry_path.extension().is_some_and(|e| e == "fsx") {
+            self.compile_entry(&info.name, &entry_path)?
+        } else {
+            self.read_bytecode(&info.name, &entry_path)?
+        };
+
+        Ok((manifest, entry_path, bytecode, source, engine_config))
+    }
+
     // Helper methods
 
     fn resolve_path(&self, path: &Path) -> PathBuf {
@@ -257,41 +1206,349 @@ impl PluginLoader {
         }
     }
 
-    fn compile_and_load(&self, plugin: &Plugin, source_path: &Path) -> Result<()> {
-        let compile_result = compile_file(source_path, &self.config.compile_options)
-            .map_err(|e: fusabi_host::Error| Error::Compilation(e.to_string()))?;
+    /// Apply the features the configuration enables for the plugin.
+    fn enable_features(&self, manifest: &mut Manifest) -> Result<()> {
+        let features = self.config.plugin_features(&manifest.name).to_vec();
+        manifest.enable_features(&features)
+    }
 
-        plugin.set_bytecode(compile_result.bytecode);
+    /// Check a manifest against the name, namespace and capability rules
+    /// of the policy, if configured.
+    fn check_policy(&self, manifest: &Manifest) -> Result<()> {
+        match self.config.policy {
+            Some(ref policy) => policy::enforce(&manifest.name, policy.check_manifest(manifest)),
+            None => Ok(()),
+        }
+    }
 
-        // Log warnings
-        for warning in &compile_result.warnings {
-            tracing::warn!("Plugin {}: {}", plugin.name(), warning.message);
+    /// Check the signature the policy requires of the plugin's files, if
+    /// any.
+    fn check_signature(
+        &self,
+        manifest: &Manifest,
+        manifest_path: Option<&Path>,
+        entry_path: Option<&Path>,
+    ) -> Result<()> {
+        match self.config.policy {
+            Some(ref policy) => policy::enforce(
+                &manifest.name,
+                policy.check_signature(manifest, manifest_path, entry_path),
+            ),
+            None => Ok(()),
+        }
+    }
+
+    /// Get the base engine configuration with the policy's limit caps
+    /// applied.
+    fn capped_engine_config(&self) -> EngineConfig {
+        let mut config = self.engine_config();
+        if let Some(ref policy) = self.config.policy {
+            policy.cap_limits(&mut config.limits);
+        }
+        config
+    }
+
+    fn check_manifest(&self, manifest: &Manifest) -> Result<()> {
+        // Validate manifest
+        if self.config.strict_validation {
+            manifest.validate()?;
+        }
+        self.check_policy(manifest)?;
+
+        // Check API version compatibility
+        if !manifest.is_compatible_with_host(&self.config.host_api_version) {
+            return Err(Error::api_version_mismatch(
+                manifest.api_version.to_string(),
+                self.config.host_api_version.to_string(),
+            ));
+        }
+
+        if let Some(function) = self.config.missing_host_functions(manifest).first() {
+            return Err(Error::missing_host_function(&manifest.name, function));
         }
 
+        self.probe.read().check_manifest(manifest)?;
+
         Ok(())
     }
 
-    fn load_bytecode(&self, plugin: &Plugin, bytecode_path: &Path) -> Result<()> {
-        let bytecode = std::fs::read(bytecode_path)?;
+    fn entry_path(&self, manifest: &Manifest, manifest_path: Option<&Path>) -> Option<PathBuf> {
+        manifest.entry_point().map(|p| {
+            if let Some(manifest_path) = manifest_path {
+                manifest_path.parent().unwrap_or(Path::new(".")).join(p)
+            } else {
+                self.resolve_path(Path::new(p))
+            }
+        })
+    }
 
-        // Validate
-        validate_bytecode(&bytecode)?;
+    fn load_entry(&self, manifest: &Manifest, entry_path: &Path) -> Result<Vec<u8>> {
+        if manifest.uses_wasm() {
+            // Compiled by the wasm engine when the plugin is initialized
+            let module = std::fs::read(entry_path)?;
+            self.check_asset_size(&manifest.name, module.len())?;
+            Ok(module)
+        } else if manifest.uses_source() {
+            self.compile_entry(&manifest.name, entry_path)
+        } else {
+            self.read_bytecode(&manifest.name, entry_path)
+        }
+    }
 
-        plugin.set_bytecode(bytecode);
+    /// Check the exports `manifest` declares are defined by `bytecode`.
+    ///
+    /// Without strict validation, undefined exports are logged instead.
+    fn verify_exports(&self, manifest: &Manifest, bytecode: &[u8]) -> Result<()> {
+        if manifest.uses_wasm() {
+            return Ok(());
+        }
+        for export in undefined_exports(manifest, bytecode) {
+            if self.config.strict_validation {
+                return Err(Error::missing_export(&manifest.name, export));
+            }
+            tracing::warn!(
+                "Plugin {}: export {} is not defined by its code",
+                manifest.name,
+                export
+            );
+        }
         Ok(())
     }
 
-    fn build_engine_config(&self, manifest: &Manifest) -> Result<EngineConfig> {
+    fn new_plugin(&self, manifest: Manifest) -> Plugin {
+        let plugin = Plugin::new(manifest);
+        plugin.logger().set_capacity(self.config.log_capacity);
+        plugin.set_env_policy(self.config.env_policy.clone());
+        if let Some(ref table) = self.config.host_functions {
+            plugin.set_host_functions(table.clone());
+        }
+        #[cfg(feature = "isolation")]
+        if let Some(ref isolation) = self.config.isolation {
+            plugin.set_isolation(isolation.clone());
+        }
+        #[cfg(feature = "metrics-prometheus")]
+        if let Some(ref metrics) = self.config.metrics {
+            plugin.set_metrics(metrics.clone());
+        }
+        plugin
+    }
+
+    fn compile_entry(&self, name: &str, source_path: &Path) -> Result<Vec<u8>> {
+        if let Ok(metadata) = std::fs::metadata(source_path) {
+            self.check_asset_size(name, metadata.len() as usize)?;
+        }
+
+        let bytecode = if self.config.bytecode_cache.is_some() {
+            let source = std::fs::read_to_string(source_path)?;
+            self.compile_cached(name, &source)
+                .map_err(|e| locate_compile_error(e, source_path, Some(&source)))?
+        } else {
+            let started = Instant::now();
+            let compile_result = compile_file(source_path, &self.config.compile_options).map_err(
+                |e: fusabi_host::Error| {
+                    locate_compile_error(Error::Compilation(e.to_string()), source_path, None)
+                },
+            )?;
+            self.record_compile(name, started);
+            Self::log_warnings(name, &compile_result);
+            compile_result.bytecode
+        };
+        self.check_asset_size(name, bytecode.len())?;
+
+        Ok(bytecode)
+    }
+
+    /// Compile a source string, going through the bytecode cache if configured.
+    fn compile_cached(&self, name: &str, source: &str) -> Result<Vec<u8>> {
+        let compile = || {
+            let started = Instant::now();
+            let compile_result = compile_source(source, &self.config.compile_options)
+                .map_err(|e| Error::Compilation(e.to_string()))?;
+            self.record_compile(name, started);
+            Self::log_warnings(name, &compile_result);
+            Ok(compile_result.bytecode)
+        };
+
+        match self.config.bytecode_cache {
+            Some(ref cache) => cache.get_or_compile(source, &self.config.compile_options, compile),
+            None => compile(),
+        }
+    }
+
+    fn record_compile(&self, name: &str, started: Instant) {
+        #[cfg(feature = "metrics-prometheus")]
+        if let Some(ref metrics) = self.config.metrics {
+            metrics.record_compile(name, started.elapsed().as_secs_f64());
+        }
+        #[cfg(not(feature = "metrics-prometheus"))]
+        let _ = (name, started);
+    }
+
+    fn log_warnings(name: &str, compile_result: &CompileResult) {
+        for warning in &compile_result.warnings {
+            tracing::warn!("Plugin {}: {}", name, warning.message);
+        }
+    }
+
+    /// Read a bytecode file. Bytecode built for another engine version is
+    /// replaced by compiling the `.fsx` next to it, or else by the
+    /// configured upgrader.
+    fn read_bytecode(&self, name: &str, bytecode_path: &Path) -> Result<Vec<u8>> {
+        let bytecode = std::fs::read(bytecode_path)?;
+        self.check_asset_size(name, bytecode.len())?;
+
+        let source_path = bytecode_path.with_extension("fsx");
+        self.accept_bytecode(
+            name,
+            bytecode,
+            &bytecode_path.display().to_string(),
+            Some(source_path.as_path()).filter(|path| path.is_file()),
+        )
+    }
+
+    /// Check that bytecode from `location` runs on this engine, recompiling
+    /// it from `source_path` or upgrading it if not.
+    fn accept_bytecode(
+        &self,
+        name: &str,
+        bytecode: Vec<u8>,
+        location: &str,
+        source_path: Option<&Path>,
+    ) -> Result<Vec<u8>> {
+        let Err(reason) = check_bytecode(&bytecode) else {
+            return Ok(bytecode);
+        };
+
+        if let Some(source_path) = source_path {
+            tracing::info!(
+                "Plugin {}: {}, recompiling {}",
+                name,
+                reason,
+                source_path.display()
+            );
+            return self.compile_entry(name, source_path);
+        }
+
+        let incompatible = |reason| Error::incompatible_bytecode(location, reason);
+        let Some(ref upgrader) = self.config.bytecode_upgrader else {
+            return Err(incompatible(reason));
+        };
+
+        tracing::info!("Plugin {}: {}, upgrading bytecode", name, reason);
+        let upgraded = upgrader.upgrade(name, &bytecode)?;
+        check_bytecode(&upgraded)
+            .map_err(|reason| incompatible(format!("upgraded bytecode: {}", reason)))?;
+        self.check_asset_size(name, upgraded.len())?;
+        Ok(upgraded)
+    }
+
+    /// Keep a plugin version's bytecode and manifest in the artifact store,
+    /// if configured, and drop versions beyond the retention limit.
+    fn store_artifact(&self, manifest: &Manifest, bytecode: &[u8]) {
+        let Some(ref store) = self.config.artifact_store else {
+            return;
+        };
+        let (name, version) = (&manifest.name, &manifest.version);
+        let result = store
+            .put_artifact(name, version, "bytecode", bytecode)
+            .and_then(|_| {
+                #[cfg(feature = "serde")]
+                store.put_artifact(name, version, "manifest", manifest.to_toml()?.as_bytes())?;
+                store.record_install(name, version)
+            })
+            .and_then(|()| {
+                let previous = self
+                    .config
+                    .retained_versions
+                    .unwrap_or(DEFAULT_RETAINED_VERSIONS);
+                let removed = store.retain_versions(name, previous + 1)?;
+                if !removed.is_empty() {
+                    store.gc()?;
+                }
+                Ok(())
+            });
+        if let Err(e) = result {
+            tracing::warn!("Plugin {}: failed to store artifact: {}", name, e);
+        }
+    }
+
+    /// Read a stored plugin version back from the artifact store.
+    #[cfg(feature = "serde")]
+    pub(crate) fn stored_version(&self, name: &str, version: &str) -> Result<(Manifest, Vec<u8>)> {
+        let store = self
+            .config
+            .artifact_store
+            .as_ref()
+            .ok_or_else(|| Error::Registry("no artifact store configured".to_string()))?;
+        let manifest = store.get_artifact(name, version, "manifest")?;
+        let manifest = String::from_utf8(manifest)
+            .map_err(|e| Error::ManifestParse(e.to_string()))
+            .and_then(|toml| Manifest::from_toml(&toml))?;
+        let bytecode = store.get_artifact(name, version, "bytecode")?;
+        Ok((manifest, bytecode))
+    }
+
+    /// Check a manifest against the host and build its engine
+    /// configuration, as when loading it.
+    #[cfg(feature = "serde")]
+    pub(crate) fn prepare_manifest(
+        &self,
+        manifest: &Manifest,
+        origin: PluginOrigin,
+    ) -> Result<EngineConfig> {
+        self.check_manifest(manifest)?;
+        self.build_engine_config(manifest, origin)
+    }
+
+    fn check_asset_size(&self, plugin: &str, size: usize) -> Result<()> {
+        match self.config.max_asset_size {
+            Some(max) if size > max => Err(Error::quota_exceeded(plugin, "asset bytes", max)),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn build_engine_config(
+        &self,
+        manifest: &Manifest,
+        origin: PluginOrigin,
+    ) -> Result<EngineConfig> {
         // Start with base config
-        let mut config = self.config.engine_config.clone();
+        let mut config = self.capped_engine_config();
+        let policy = self
+            .config
+            .origin_policy(origin)
+            .effective_capability_policy(self.config.capability_policy.as_ref());
 
         // Add required capabilities
         let mut caps = config.capabilities.clone();
-        for cap_name in &manifest.capabilities {
-            let cap = fusabi_host::Capability::from_name(cap_name).ok_or_else(|| {
-                Error::invalid_manifest(format!("unknown capability: {}", cap_name))
-            })?;
-            caps.grant(cap);
+        for spec in &manifest.capabilities {
+            // Runtime capabilities go through the policy but not to the engine
+            let cap = match fusabi_host::Capability::from_name(&spec.name) {
+                Some(cap) => Some(cap),
+                None if delegation::is_runtime_capability(&spec.name) => None,
+                None => {
+                    return Err(Error::invalid_manifest(format!(
+                        "unknown capability: {}",
+                        spec
+                    )))
+                }
+            };
+            if cap.is_some_and(|cap| caps.has(cap)) {
+                continue;
+            }
+            if let Some(ref policy) = policy {
+                let request = CapabilityRequest {
+                    plugin: &manifest.name,
+                    version: &manifest.version,
+                    capability: spec,
+                };
+                if let CapabilityDecision::Deny(reason) = policy.decide(&request) {
+                    return Err(Error::capability_not_granted(&spec.name, reason));
+                }
+            }
+            if let Some(cap) = cap {
+                caps.grant(cap);
+            }
         }
         config.capabilities = caps;
 
@@ -299,6 +1556,48 @@ impl PluginLoader {
     }
 }
 
+/// New version of a plugin read by `prepare_reload`: its manifest, entry
+/// file, bytecode, source text and engine configuration.
+type PreparedReload = (Manifest, PathBuf, Vec<u8>, Option<String>, EngineConfig);
+
+/// Read a source entry before it is compiled, so error snippets quote the
+/// code that runs rather than a later edit of the file.
+fn source_snapshot(entry_path: &Path) -> Option<String> {
+    if entry_path.extension().is_some_and(|e| e == "fsx") {
+        std::fs::read_to_string(entry_path).ok()
+    } else {
+        None
+    }
+}
+
+/// Attach the position a compile error names in `source_path`, if any.
+fn locate_compile_error(error: Error, source_path: &Path, source: Option<&str>) -> Error {
+    let span = match error {
+        Error::Compilation(ref message) => SourceSpan::locate_in(message, source_path, source),
+        _ => None,
+    };
+    match span {
+        Some(span) => error.at(span),
+        None => error,
+    }
+}
+
+/// Get the exports `manifest` declares that `bytecode` does not define.
+///
+/// Bytecode without a function table defines nothing to check against.
+fn undefined_exports(manifest: &Manifest, bytecode: &[u8]) -> Vec<String> {
+    let functions = match validate_bytecode(bytecode) {
+        Ok(metadata) if !metadata.functions.is_empty() => metadata.functions,
+        _ => return Vec::new(),
+    };
+    manifest
+        .exports
+        .iter()
+        .filter(|export| !functions.contains(&export.name))
+        .map(|export| export.name.clone())
+        .collect()
+}
+
 impl std::fmt::Debug for PluginLoader {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("PluginLoader")
@@ -310,6 +1609,7 @@ impl std::fmt::Debug for PluginLoader {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::capability_policy::{AllowList, DenyAll};
     use crate::manifest::ManifestBuilder;
 
     #[test]
@@ -344,6 +1644,215 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_max_asset_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("big.fsx");
+        std::fs::write(&path, "x".repeat(128)).unwrap();
+
+        let loader = PluginLoader::new(LoaderConfig::new().with_max_asset_size(64)).unwrap();
+        let result = loader.load_source(&path);
+        assert!(matches!(result, Err(Error::QuotaExceeded { .. })));
+    }
+
+    #[test]
+    fn test_config_validation() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("file.txt");
+        std::fs::write(&file, "").unwrap();
+
+        assert!(LoaderConfig::new()
+            .with_base_path(dir.path())
+            .with_origin_dir(dir.path().join("later"), PluginOrigin::Remote)
+            .validate()
+            .is_ok());
+
+        let result = PluginLoader::new(
+            LoaderConfig::new()
+                .with_base_path(dir.path().join("missing"))
+                .with_max_asset_size(0)
+                .with_retained_versions(2)
+                .with_origin_dir(&file, PluginOrigin::User),
+        );
+        assert!(matches!(
+            result,
+            Err(Error::Config(e)) if e.paths() == [
+                "base_path",
+                "max_asset_size",
+                "retained_versions",
+                "origin_dirs[0]",
+            ]
+        ));
+    }
+
+    #[test]
+    fn test_bytecode_cache_reused() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cached.fsx");
+        std::fs::write(&path, "let x = 1").unwrap();
+
+        let cache = Arc::new(BytecodeCache::in_memory());
+        let loader =
+            PluginLoader::new(LoaderConfig::new().with_bytecode_cache(cache.clone())).unwrap();
+        loader.load_source(&path).unwrap();
+        loader.load_source(&path).unwrap();
+
+        let stats = cache.stats();
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.hits, 1);
+    }
+
+    #[test]
+    fn test_load_in_memory() {
+        let loader = PluginLoader::new(LoaderConfig::new()).unwrap();
+
+        let plugin = loader
+            .load_source_str("generated", "let greet = \"hello\"")
+            .unwrap();
+        assert_eq!(plugin.name(), "generated");
+        assert!(plugin.info().entry_path.is_none());
+        assert!(matches!(
+            loader.reload_from_disk(&plugin),
+            Err(Error::ReloadFailed(_))
+        ));
+
+        let bytecode = compile_source("let x = 1", &CompileOptions::default())
+            .unwrap()
+            .bytecode;
+        let plugin = loader
+            .load_bytecode_bytes("acme/compiled", bytecode)
+            .unwrap();
+        assert_eq!(plugin.name(), "acme/compiled");
+
+        assert!(loader.load_source_str("", "let x = 1").is_err());
+        assert!(loader.load_bytecode_bytes("bad", vec![0, 1, 2]).is_err());
+
+        let loader = PluginLoader::new(LoaderConfig::new().with_max_asset_size(4)).unwrap();
+        assert!(matches!(
+            loader.load_source_str("big", "let x = 1"),
+            Err(Error::QuotaExceeded { .. })
+        ));
+    }
+
+    #[test]
+    fn test_reload_from_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("live.fsx");
+        std::fs::write(&path, "let x = 1").unwrap();
+
+        let cache = Arc::new(BytecodeCache::in_memory());
+        let loader =
+            PluginLoader::new(LoaderConfig::new().with_bytecode_cache(cache.clone())).unwrap();
+        let plugin = loader.load_source(&path).unwrap();
+
+        std::fs::write(&path, "let x = 2").unwrap();
+        loader.reload_from_disk(&plugin).unwrap();
+
+        assert_eq!(cache.stats().misses, 2);
+        assert_eq!(plugin.info().reload_count, 1);
+        assert_eq!(plugin.info().entry_path, Some(path));
+
+        let detached = PluginHandle::new(Plugin::new(Manifest::new("detached", "1.0.0")));
+        assert!(matches!(
+            loader.reload_from_disk(&detached),
+            Err(Error::ReloadFailed(_))
+        ));
+    }
+
+    #[test]
+    fn test_failed_reload_keeps_old_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("live.fsx");
+        std::fs::write(&path, "let x = 1").unwrap();
+
+        let loader = PluginLoader::new(LoaderConfig::new()).unwrap();
+        let plugin = loader.load_source(&path).unwrap();
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            loader.reload_from_disk(&plugin),
+            Err(Error::ReloadFailed(_))
+        ));
+        assert_eq!(plugin.state(), crate::LifecycleState::Running);
+        assert_eq!(plugin.info().reload_count, 0);
+        assert!(plugin.call("main", &[]).is_ok());
+    }
+
+    #[test]
+    fn test_capability_policy() {
+        let manifest = ManifestBuilder::new("test", "1.0.0")
+            .capability("fs:read")
+            .capability("net:request")
+            .build_unchecked();
+        let base = EngineConfig::default().with_capabilities(
+            fusabi_host::Capabilities::none().with(fusabi_host::Capability::FsRead),
+        );
+
+        let loader = PluginLoader::new(
+            LoaderConfig::new()
+                .with_engine_config(base.clone())
+                .with_capability_policy(DenyAll),
+        )
+        .unwrap();
+        assert!(matches!(
+            loader.build_engine_config(&manifest, PluginOrigin::Direct),
+            Err(Error::CapabilityNotGranted { capability, .. }) if capability == "net:request"
+        ));
+
+        let loader = PluginLoader::new(
+            LoaderConfig::new()
+                .with_engine_config(base)
+                .with_capability_policy(AllowList::new().allow("net:request")),
+        )
+        .unwrap();
+        let config = loader
+            .build_engine_config(&manifest, PluginOrigin::Direct)
+            .unwrap();
+        assert!(config.capabilities.has(fusabi_host::Capability::NetRequest));
+    }
+
+    #[test]
+    fn test_origin_trust() {
+        let manifest = ManifestBuilder::new("test", "1.0.0")
+            .capability("net:request")
+            .build_unchecked();
+        let loader = PluginLoader::new(
+            LoaderConfig::new()
+                .with_origin_dir("/opt/app/plugins", PluginOrigin::Builtin)
+                .with_origin_dir("/home/me/.app/plugins", PluginOrigin::User),
+        )
+        .unwrap();
+
+        assert_eq!(
+            loader
+                .config()
+                .origin_of(Path::new("/opt/app/plugins/x/plugin.toml")),
+            PluginOrigin::Builtin
+        );
+        assert!(loader
+            .build_engine_config(&manifest, PluginOrigin::Direct)
+            .is_ok());
+        assert!(loader
+            .build_engine_config(&manifest, PluginOrigin::Builtin)
+            .is_ok());
+        assert!(matches!(
+            loader.build_engine_config(&manifest, PluginOrigin::User),
+            Err(Error::CapabilityNotGranted { .. })
+        ));
+
+        let loader = PluginLoader::new(
+            LoaderConfig::new().with_origin_policy(
+                PluginOrigin::User,
+                OriginPolicy::for_origin(PluginOrigin::User)
+                    .with_capability_policy(AllowList::new().allow("net:request")),
+            ),
+        )
+        .unwrap();
+        assert!(loader
+            .build_engine_config(&manifest, PluginOrigin::User)
+            .is_ok());
+    }
+
     #[test]
     fn test_api_version_check() {
         let loader = PluginLoader::new(
@@ -362,4 +1871,526 @@ mod tests {
         let result = loader.load_manifest(manifest, None);
         assert!(matches!(result, Err(Error::ApiVersionMismatch { .. })));
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_explain() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = dir.path().join("plugin.toml");
+        std::fs::write(
+            &manifest,
+            r#"
+name = "search"
+version = "1.0.0"
+api-version = { major = 0, minor = 21, patch = 0 }
+source = "main.fsx"
+"#,
+        )
+        .unwrap();
+
+        let loader = PluginLoader::new(LoaderConfig::new()).unwrap();
+        let explanation = loader.explain(&manifest);
+        let failure = explanation.failure.as_ref().unwrap();
+        assert_eq!(failure.gate, LoadGate::Compile);
+        assert_eq!(
+            explanation.passed,
+            vec![
+                LoadGate::Parse,
+                LoadGate::Validate,
+                LoadGate::ApiVersion,
+                LoadGate::Capabilities,
+                LoadGate::HostFunctions
+            ]
+        );
+        assert!(failure.context[0].ends_with("main.fsx"));
+        assert!(!failure.suggestions.is_empty());
+
+        std::fs::write(dir.path().join("main.fsx"), "let x = 1").unwrap();
+        assert!(loader.explain(&manifest).is_loadable());
+
+        let missing = loader.explain(dir.path().join("missing.toml"));
+        assert_eq!(missing.failure.unwrap().gate, LoadGate::Parse);
+    }
+
+    #[cfg(all(feature = "serde", feature = "wasm"))]
+    #[test]
+    fn test_explain_runs_init() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = dir.path().join("plugin.toml");
+        std::fs::write(
+            &manifest,
+            r#"
+name = "trap"
+version = "1.0.0"
+api-version = { major = 0, minor = 21, patch = 0 }
+bytecode = "plugin.wasm"
+exports = ["init"]
+"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("plugin.wasm"),
+            r#"(module (func (export "init") unreachable))"#,
+        )
+        .unwrap();
+
+        let loader = PluginLoader::new(LoaderConfig::new()).unwrap();
+        let explanation = loader.explain(&manifest);
+        let failure = explanation.failure.as_ref().unwrap();
+        assert_eq!(failure.gate, LoadGate::Init);
+        assert!(matches!(failure.error, Error::InitializationFailed(_)));
+        assert_eq!(explanation.passed.last(), Some(&LoadGate::Compile));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_validate() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = dir.path().join("plugin.toml");
+        std::fs::write(
+            &manifest,
+            r#"
+name = "search"
+version = "1.0.0"
+api-version = { major = 0, minor = 21, patch = 0 }
+source = "main.fsx"
+capabilities = ["fs:read", "gpu:compute"]
+exports = ["query", "index"]
+"#,
+        )
+        .unwrap();
+
+        let loader = PluginLoader::new(LoaderConfig::new()).unwrap();
+        let report = loader.validate(&manifest);
+        assert_eq!(report.plugin.as_deref(), Some("search"));
+        assert_eq!(report.problems.len(), 2);
+        assert_eq!(report.problems[0].check, ValidationCheck::Capabilities);
+        assert_eq!(report.problems[1].check, ValidationCheck::EntryFile);
+
+        std::fs::write(
+            dir.path().join("main.fsx"),
+            "let query text = text\nlet indx doc = doc\n",
+        )
+        .unwrap();
+        let report = loader.validate(&manifest);
+        assert!(report.passed.contains(&ValidationCheck::Compile));
+        assert!(matches!(
+            report.problems.last().map(|p| &p.error),
+            Some(Error::MissingExport { export, .. }) if export == "index"
+        ));
+
+        let missing = loader.validate(dir.path().join("missing.toml"));
+        assert_eq!(missing.problems[0].check, ValidationCheck::Schema);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_verify_exports() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("main.fsx"), "let query text = text\n").unwrap();
+        let manifest = dir.path().join("plugin.toml");
+        std::fs::write(
+            &manifest,
+            r#"
+name = "search"
+version = "1.0.0"
+api-version = { major = 0, minor = 21, patch = 0 }
+source = "main.fsx"
+exports = ["query", "indx"]
+"#,
+        )
+        .unwrap();
+
+        let strict = PluginLoader::new(LoaderConfig::new()).unwrap();
+        assert!(matches!(
+            strict.load_from_manifest(&manifest),
+            Err(Error::MissingExport { export, .. }) if export == "indx"
+        ));
+
+        let lenient = PluginLoader::new(LoaderConfig::new().with_strict_validation(false)).unwrap();
+        assert!(lenient.load_from_manifest(&manifest).is_ok());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_plugin_features() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("main.fsx"),
+            "let render feed = feed\nlet fetch url = url\n",
+        )
+        .unwrap();
+        let manifest = dir.path().join("plugin.toml");
+        std::fs::write(
+            &manifest,
+            r#"
+name = "feeds"
+version = "1.0.0"
+api-version = { major = 0, minor = 21, patch = 0 }
+source = "main.fsx"
+exports = ["render"]
+
+[features.network]
+description = "Fetch remote feeds"
+capabilities = ["net:request"]
+exports = ["fetch"]
+"#,
+        )
+        .unwrap();
+
+        let loader = PluginLoader::new(LoaderConfig::new()).unwrap();
+        let plugin = loader.load_from_manifest(&manifest).unwrap();
+        let loaded = plugin.inner().manifest();
+        assert!(!loaded.requires_capability("net:request"));
+        assert!(loaded.export("fetch").is_none());
+
+        let config = LoaderConfig::new().with_plugin_features("feeds", ["network"]);
+        assert_eq!(config.plugin_features("feeds"), ["network"]);
+        let loader = PluginLoader::new(config).unwrap();
+        let plugin = loader.load_from_manifest(&manifest).unwrap();
+        let loaded = plugin.inner().manifest();
+        assert!(loaded.requires_capability("net:request"));
+        assert!(loaded.export("fetch").is_some());
+
+        let loader =
+            PluginLoader::new(LoaderConfig::new().with_plugin_features("feeds", ["offline"]))
+                .unwrap();
+        assert!(matches!(
+            loader.load_from_manifest(&manifest),
+            Err(Error::InvalidManifest(_))
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_env_capability() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("main.fsx"), "let main () = 1").unwrap();
+        let manifest = dir.path().join("plugin.toml");
+        std::fs::write(
+            &manifest,
+            r#"
+name = "clock"
+version = "1.0.0"
+api-version = { major = 0, minor = 21, patch = 0 }
+source = "main.fsx"
+capabilities = ["sys:env"]
+
+[env]
+allow = ["PATH"]
+"#,
+        )
+        .unwrap();
+
+        let loader = PluginLoader::new(
+            LoaderConfig::new().with_env_policy(crate::environment::EnvPolicy::new().allow("PATH")),
+        )
+        .unwrap();
+        let plugin = loader.load_from_manifest(&manifest).unwrap();
+        let config = plugin.inner().engine_config().unwrap();
+        assert!(config.capabilities.has(fusabi_host::Capability::EnvRead));
+        assert_eq!(
+            plugin.inner().env_var("PATH").unwrap(),
+            std::env::var("PATH").ok()
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_load_policy() {
+        use crate::policy::{LimitCaps, SignatureRule};
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("main.fsx"), "let main () = 1\n").unwrap();
+        let manifest = dir.path().join("plugin.toml");
+        std::fs::write(
+            &manifest,
+            r#"
+name = "payments"
+version = "1.0.0"
+api-version = { major = 0, minor = 21, patch = 0 }
+source = "main.fsx"
+exports = ["main"]
+capabilities = ["net:request"]
+tags = ["billing"]
+"#,
+        )
+        .unwrap();
+
+        let mut engine_config = EngineConfig::default();
+        engine_config.limits.max_call_depth = 500;
+        let policy = PolicyFile::new().with_limits(LimitCaps {
+            max_call_depth: Some(64),
+            ..LimitCaps::default()
+        });
+        let loader = PluginLoader::new(
+            LoaderConfig::new()
+                .with_engine_config(engine_config)
+                .with_policy(policy.clone()),
+        )
+        .unwrap();
+        let plugin = loader.load_from_manifest(&manifest).unwrap();
+        assert_eq!(
+            plugin
+                .inner()
+                .engine_config()
+                .unwrap()
+                .limits
+                .max_call_depth,
+            64
+        );
+
+        let strict = policy
+            .forbid_capability("net:request")
+            .require_signature(SignatureRule::new(["billing"], "ed25519", ["00ff"]));
+        let loader = PluginLoader::new(LoaderConfig::new().with_policy(strict)).unwrap();
+        match loader.load_from_manifest(&manifest) {
+            Err(Error::PolicyViolation { plugin, violations }) => {
+                assert_eq!(plugin, "payments");
+                assert_eq!(violations[0].rule, "forbidden-capabilities");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let signed = PolicyFile::new().require_signature(SignatureRule::new(
+            ["billing"],
+            "ed25519",
+            ["00ff"],
+        ));
+        let loader = PluginLoader::new(LoaderConfig::new().with_policy(signed)).unwrap();
+        match loader.load_from_manifest(&manifest) {
+            Err(Error::PolicyViolation { violations, .. }) => {
+                assert_eq!(violations[0].rule, "signatures");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let banned = PolicyFile::new().ban_plugin("pay*");
+        let loader = PluginLoader::new(LoaderConfig::new().with_policy(banned)).unwrap();
+        assert!(matches!(
+            loader.load_source_str("payroll", "let main () = 1\n"),
+            Err(Error::PolicyViolation { .. })
+        ));
+        assert!(loader
+            .load_source_str("reports", "let main () = 1\n")
+            .is_ok());
+        assert!(matches!(
+            loader.load_builtin("payroll", crate::BuiltinPlugin::new("1.0.0")),
+            Err(Error::PolicyViolation { .. })
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_load_composite() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["index", "rank"] {
+            let member = dir.path().join(name);
+            std::fs::create_dir(&member).unwrap();
+            std::fs::write(member.join("main.fsx"), "let query text = text\n").unwrap();
+            std::fs::write(
+                member.join("plugin.toml"),
+                format!(
+                    "name = \"{}\"\nversion = \"1.0.0\"\n\
+                     api-version = {{ major = 0, minor = 21, patch = 0 }}\n\
+                     source = \"main.fsx\"\nexports = [\"query\"]\n",
+                    name
+                ),
+            )
+            .unwrap();
+        }
+        let manifest = dir.path().join("plugin.toml");
+        std::fs::write(
+            &manifest,
+            r#"
+name = "search-suite"
+version = "1.0.0"
+api-version = { major = 0, minor = 21, patch = 0 }
+
+[[members]]
+manifest = "index/plugin.toml"
+
+[[members]]
+manifest = "rank/plugin.toml"
+prefix = "ranking."
+"#,
+        )
+        .unwrap();
+
+        let loader = PluginLoader::new(LoaderConfig::new()).unwrap();
+        let suite = loader.load_from_manifest(&manifest).unwrap();
+        assert!(suite.has_export("index.query"));
+        assert!(suite.has_export("ranking.query"));
+        assert!(suite.call("ranking.query", &[]).is_ok());
+
+        let members = suite.inner().members();
+        assert_eq!(members.len(), 2);
+        assert!(members
+            .iter()
+            .all(|m| m.state() == crate::LifecycleState::Running));
+
+        // Reloading the composite reloads its members
+        loader.reload_from_disk(&suite).unwrap();
+        assert!(members.iter().all(|m| m.info().reload_count == 1));
+        assert!(suite.call("index.query", &[]).is_ok());
+
+        suite.inner().stop().unwrap();
+        assert!(members
+            .iter()
+            .all(|m| m.state() == crate::LifecycleState::Stopped));
+        suite.inner().unload().unwrap();
+        assert!(members
+            .iter()
+            .all(|m| m.state() == crate::LifecycleState::Unloaded));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_load_composite_failures() {
+        let dir = tempfile::tempdir().unwrap();
+        let header = "version = \"1.0.0\"\napi-version = { major = 0, minor = 21, patch = 0 }\n";
+        std::fs::write(dir.path().join("main.fsx"), "let query text = text\n").unwrap();
+        std::fs::write(
+            dir.path().join("broken.toml"),
+            format!(
+                "name = \"broken\"\n{}source = \"main.fsx\"\nexports = [\"missing\"]\n",
+                header
+            ),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("index.toml"),
+            format!(
+                "name = \"index\"\n{}source = \"main.fsx\"\nexports = [\"query\"]\n",
+                header
+            ),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("outer.toml"),
+            format!(
+                "name = \"outer\"\n{}[[members]]\nmanifest = \"inner.toml\"\n",
+                header
+            ),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("inner.toml"),
+            format!(
+                "name = \"inner\"\n{}[[members]]\nmanifest = \"index.toml\"\n\
+                 [[members]]\nmanifest = \"outer.toml\"\n",
+                header
+            ),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("suite.toml"),
+            format!(
+                "name = \"suite\"\n{}[[members]]\nmanifest = \"index.toml\"\n\
+                 [[members]]\nmanifest = \"broken.toml\"\n",
+                header
+            ),
+        )
+        .unwrap();
+        let loader = PluginLoader::new(LoaderConfig::new()).unwrap();
+
+        let error = loader
+            .load_from_manifest(dir.path().join("outer.toml"))
+            .unwrap_err();
+        assert!(matches!(error.root(), Error::InvalidManifest(_)));
+        assert!(error.to_string().contains("includes itself"));
+
+        // A failing member keeps its error, naming the member
+        let error = loader
+            .load_from_manifest(dir.path().join("suite.toml"))
+            .unwrap_err();
+        assert_eq!(error.plugin(), Some("broken"));
+        assert!(matches!(
+            error.root(),
+            Error::MissingExport { export, .. } if export == "missing"
+        ));
+    }
+
+    #[test]
+    fn test_probe_engine() {
+        let loader = PluginLoader::new(LoaderConfig::new()).unwrap();
+        let probe = loader.probe_engine();
+        assert!(probe.engine_error.is_none());
+        assert!(probe.capabilities.contains("fs:read"));
+
+        let mut engine_config = fusabi_host::EngineConfig::default();
+        engine_config.limits.max_call_depth = 9;
+        loader.set_engine_config(engine_config);
+        assert_eq!(loader.probe_engine().limits.max_call_depth, 9);
+
+        let manifest = ManifestBuilder::new("worker", "1.0.0")
+            .source("worker.fsx")
+            .isolation(crate::Isolation::Process)
+            .build_unchecked();
+        let result = loader.probe_engine().check_manifest(&manifest);
+        #[cfg(feature = "isolation")]
+        assert!(result.is_ok());
+        #[cfg(not(feature = "isolation"))]
+        {
+            assert!(matches!(
+                result,
+                Err(Error::FeatureDisabled { ref feature, .. }) if feature == "isolation"
+            ));
+            // Rejected before an engine is created for the missing source
+            assert!(matches!(
+                loader.load_manifest(manifest, None),
+                Err(Error::FeatureDisabled { ref feature, .. }) if feature == "isolation"
+            ));
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_artifact_store() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("main.fsx"), "let x = 1").unwrap();
+        for (file, version) in [("a.toml", "1.0.0"), ("b.toml", "1.1.0")] {
+            std::fs::write(
+                dir.path().join(file),
+                format!(
+                    "name = \"search\"\nversion = \"{}\"\n\
+                     api-version = {{ major = 0, minor = 21, patch = 0 }}\n\
+                     source = \"main.fsx\"\n",
+                    version
+                ),
+            )
+            .unwrap();
+        }
+
+        let store = Arc::new(ArtifactStore::open(dir.path().join("store")).unwrap());
+        let loader = PluginLoader::new(
+            LoaderConfig::new()
+                .with_artifact_store(store.clone())
+                .with_auto_start(false),
+        )
+        .unwrap();
+        loader
+            .load_from_manifest(dir.path().join("a.toml"))
+            .unwrap();
+        loader
+            .load_from_manifest(dir.path().join("b.toml"))
+            .unwrap();
+
+        assert_eq!(store.versions("search").unwrap(), vec!["1.0.0", "1.1.0"]);
+        // Both versions share the bytecode blob but not their manifests
+        assert_eq!(store.stats().unwrap().blobs, 3);
+
+        let loader = PluginLoader::new(
+            LoaderConfig::new()
+                .with_artifact_store(store.clone())
+                .with_retained_versions(0)
+                .with_auto_start(false),
+        )
+        .unwrap();
+        loader
+            .load_from_manifest(dir.path().join("a.toml"))
+            .unwrap();
+        assert_eq!(store.versions("search").unwrap(), vec!["1.0.0"]);
+        assert_eq!(store.stats().unwrap().blobs, 2);
+    }
 }
@@ -0,0 +1,411 @@
+//! Lockfile pinning the installed plugins and the hashes of their files.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::artifact_store::ArtifactStore;
+use crate::error::{Error, Result};
+use crate::plugin::PluginInfo;
+
+/// A plugin pinned by the lockfile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LockedPlugin {
+    /// Plugin name.
+    pub name: String,
+    /// Plugin version.
+    pub version: String,
+    /// Manifest path.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub manifest: Option<PathBuf>,
+    /// SHA-256 of the manifest.
+    #[cfg_attr(feature = "serde", serde(rename = "manifest-sha256", default))]
+    pub manifest_sha256: Option<String>,
+    /// Source or bytecode path.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub entry: Option<PathBuf>,
+    /// SHA-256 of the source or bytecode.
+    #[cfg_attr(feature = "serde", serde(rename = "entry-sha256", default))]
+    pub entry_sha256: Option<String>,
+}
+
+impl LockedPlugin {
+    /// Pin a loaded plugin, hashing its files as they are now.
+    pub fn capture(info: &PluginInfo) -> Result<Self> {
+        let hash = |path: &Option<PathBuf>| path.as_deref().map(file_sha256).transpose();
+        Ok(Self {
+            name: info.name.clone(),
+            version: info.version.clone(),
+            manifest_sha256: hash(&info.manifest_path)?,
+            manifest: info.manifest_path.clone(),
+            entry_sha256: hash(&info.entry_path)?,
+            entry: info.entry_path.clone(),
+        })
+    }
+
+    fn files(&self) -> impl Iterator<Item = (&Path, &str)> {
+        [
+            (self.manifest.as_deref(), self.manifest_sha256.as_deref()),
+            (self.entry.as_deref(), self.entry_sha256.as_deref()),
+        ]
+        .into_iter()
+        .filter_map(|(path, hash)| Some((path?, hash?)))
+    }
+
+    fn check_files(&self, drift: &mut Vec<Drift>) {
+        for (path, expected) in self.files() {
+            match file_sha256(path) {
+                Ok(actual) if actual == expected => {}
+                Ok(_) => drift.push(Drift::Modified {
+                    plugin: self.name.clone(),
+                    path: path.to_path_buf(),
+                }),
+                Err(_) => drift.push(Drift::MissingFile {
+                    plugin: self.name.clone(),
+                    path: path.to_path_buf(),
+                }),
+            }
+        }
+    }
+}
+
+/// Installed plugins with the versions and file hashes to expect.
+///
+/// ```toml
+/// [[plugin]]
+/// name = "search"
+/// version = "1.2.0"
+/// manifest = "/opt/plugins/search/plugin.toml"
+/// manifest-sha256 = "9f86d0..."
+/// entry = "/opt/plugins/search/main.fsx"
+/// entry-sha256 = "60303a..."
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Lockfile {
+    /// Pinned plugins, sorted by name.
+    #[cfg_attr(feature = "serde", serde(rename = "plugin", default))]
+    pub plugins: Vec<LockedPlugin>,
+}
+
+impl Lockfile {
+    /// Create an empty lockfile.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pin loaded plugins.
+    pub fn capture(plugins: &[PluginInfo]) -> Result<Self> {
+        let mut plugins = plugins
+            .iter()
+            .map(LockedPlugin::capture)
+            .collect::<Result<Vec<_>>>()?;
+        plugins.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(Self { plugins })
+    }
+
+    /// Get a pinned plugin.
+    pub fn get(&self, name: &str) -> Option<&LockedPlugin> {
+        self.plugins.iter().find(|p| p.name == name)
+    }
+
+    /// Load a lockfile.
+    ///
+    /// A lockfile that cannot be parsed is an [`Error::Config`] at
+    /// `lockfile`.
+    #[cfg(feature = "serde")]
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        toml::from_str(&content).map_err(|e| {
+            let mut errors = crate::config_error::ConfigError::new();
+            errors.push("lockfile", e.to_string());
+            Error::Config(errors)
+        })
+    }
+
+    /// Write the lockfile atomically.
+    #[cfg(feature = "serde")]
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = toml::to_string_pretty(self).map_err(|e| Error::Registry(e.to_string()))?;
+        let tmp = path.with_extension("lock.tmp");
+        std::fs::write(&tmp, content)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Compare the lockfile with what is installed, regardless of what is
+    /// loaded: the files on disk and, if given, the versions kept in the
+    /// artifact store.
+    pub fn verify_installed(&self, store: Option<&ArtifactStore>) -> VerifyReport {
+        let mut drift = Vec::new();
+        for locked in &self.plugins {
+            locked.check_files(&mut drift);
+            if let Some(store) = store {
+                if store
+                    .resolve(&locked.name, &locked.version, "bytecode")
+                    .is_none()
+                {
+                    drift.push(Drift::NotStored {
+                        plugin: locked.name.clone(),
+                        version: locked.version.clone(),
+                    });
+                }
+            }
+        }
+        VerifyReport { drift }
+    }
+
+    /// Compare the lockfile with the files on disk and the loaded plugins.
+    pub fn verify(&self, loaded: &[PluginInfo]) -> VerifyReport {
+        let mut drift = Vec::new();
+
+        for locked in &self.plugins {
+            locked.check_files(&mut drift);
+
+            match loaded.iter().find(|info| info.name == locked.name) {
+                None => drift.push(Drift::NotLoaded {
+                    plugin: locked.name.clone(),
+                }),
+                Some(info) if info.version != locked.version => drift.push(Drift::VersionSkew {
+                    plugin: locked.name.clone(),
+                    locked: locked.version.clone(),
+                    loaded: info.version.clone(),
+                }),
+                Some(_) => {}
+            }
+        }
+
+        let mut unlocked: Vec<&PluginInfo> = loaded
+            .iter()
+            .filter(|info| self.get(&info.name).is_none())
+            .collect();
+        unlocked.sort_by(|a, b| a.name.cmp(&b.name));
+        drift.extend(unlocked.into_iter().map(|info| Drift::Unlocked {
+            plugin: info.name.clone(),
+        }));
+
+        VerifyReport { drift }
+    }
+}
+
+/// Difference between the lockfile and the running deployment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Drift {
+    /// A pinned file no longer matches its hash.
+    Modified {
+        /// Plugin owning the file.
+        plugin: String,
+        /// Changed file.
+        path: PathBuf,
+    },
+    /// A pinned file cannot be read.
+    MissingFile {
+        /// Plugin owning the file.
+        plugin: String,
+        /// Missing file.
+        path: PathBuf,
+    },
+    /// A pinned plugin is not loaded.
+    NotLoaded {
+        /// Plugin name.
+        plugin: String,
+    },
+    /// A loaded plugin is not pinned.
+    Unlocked {
+        /// Plugin name.
+        plugin: String,
+    },
+    /// The artifact store holds no bytecode for a pinned version.
+    NotStored {
+        /// Plugin name.
+        plugin: String,
+        /// Pinned version.
+        version: String,
+    },
+    /// A loaded plugin's version differs from the pinned one.
+    VersionSkew {
+        /// Plugin name.
+        plugin: String,
+        /// Pinned version.
+        locked: String,
+        /// Loaded version.
+        loaded: String,
+    },
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Modified { plugin, path } => {
+                write!(f, "{}: {} was modified", plugin, path.display())
+            }
+            Self::MissingFile { plugin, path } => {
+                write!(f, "{}: {} is missing", plugin, path.display())
+            }
+            Self::NotLoaded { plugin } => write!(f, "{}: locked but not loaded", plugin),
+            Self::Unlocked { plugin } => write!(f, "{}: loaded but not locked", plugin),
+            Self::NotStored { plugin, version } => {
+                write!(f, "{}: {} is not in the artifact store", plugin, version)
+            }
+            Self::VersionSkew {
+                plugin,
+                locked,
+                loaded,
+            } => write!(
+                f,
+                "{}: locked at {} but {} is loaded",
+                plugin, locked, loaded
+            ),
+        }
+    }
+}
+
+/// Outcome of [`Lockfile::verify`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VerifyReport {
+    /// Differences found, empty when the deployment matches the lockfile.
+    pub drift: Vec<Drift>,
+}
+
+impl VerifyReport {
+    /// Check if the deployment matches the lockfile.
+    pub fn is_consistent(&self) -> bool {
+        self.drift.is_empty()
+    }
+
+    /// Fail with an [`Error::Registry`] listing the drift, if any.
+    pub fn into_result(self) -> Result<()> {
+        if self.is_consistent() {
+            return Ok(());
+        }
+        let drift: Vec<String> = self.drift.iter().map(Drift::to_string).collect();
+        Err(Error::Registry(format!(
+            "installed plugins drifted from the lockfile: {}",
+            drift.join("; ")
+        )))
+    }
+}
+
+fn file_sha256(path: &Path) -> Result<String> {
+    let content = std::fs::read(path)?;
+    Ok(crate::crypto::sha256_hex(&content))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::Manifest;
+    use crate::plugin::Plugin;
+
+    fn info(name: &str, version: &str, entry: &Path) -> PluginInfo {
+        let plugin = Plugin::new(Manifest::new(name, version));
+        plugin.set_paths(None, Some(entry.to_path_buf()));
+        plugin.info()
+    }
+
+    #[test]
+    fn test_verify_drift() {
+        let dir = tempfile::tempdir().unwrap();
+        let search = dir.path().join("search.fsx");
+        let notes = dir.path().join("notes.fsx");
+        std::fs::write(&search, "let x = 1").unwrap();
+        std::fs::write(&notes, "let y = 1").unwrap();
+
+        let loaded = vec![
+            info("search", "1.0.0", &search),
+            info("notes", "1.0.0", &notes),
+        ];
+        let lock = Lockfile::capture(&loaded).unwrap();
+        assert_eq!(lock.plugins[0].name, "notes");
+        assert!(lock.verify(&loaded).is_consistent());
+
+        std::fs::write(&search, "let x = 2").unwrap();
+        std::fs::remove_file(&notes).unwrap();
+        let loaded = vec![
+            info("search", "1.1.0", &search),
+            info("extra", "1.0.0", &search),
+        ];
+
+        let report = lock.verify(&loaded);
+        assert_eq!(
+            report.drift,
+            vec![
+                Drift::MissingFile {
+                    plugin: "notes".into(),
+                    path: notes,
+                },
+                Drift::NotLoaded {
+                    plugin: "notes".into(),
+                },
+                Drift::Modified {
+                    plugin: "search".into(),
+                    path: search,
+                },
+                Drift::VersionSkew {
+                    plugin: "search".into(),
+                    locked: "1.0.0".into(),
+                    loaded: "1.1.0".into(),
+                },
+                Drift::Unlocked {
+                    plugin: "extra".into(),
+                },
+            ]
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_lockfile_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let entry = dir.path().join("main.fsx");
+        std::fs::write(&entry, "()").unwrap();
+
+        let lock = Lockfile::capture(&[info("search", "1.0.0", &entry)]).unwrap();
+        let path = dir.path().join("fusabi.lock");
+        lock.save(&path).unwrap();
+        assert!(std::fs::read_to_string(&path)
+            .unwrap()
+            .contains("[[plugin]]"));
+        assert_eq!(Lockfile::load(&path).unwrap(), lock);
+
+        std::fs::write(&path, "[[plugin]]\nname = 1\n").unwrap();
+        match Lockfile::load(&path) {
+            Err(Error::Config(e)) => assert_eq!(e.paths(), vec!["lockfile"]),
+            other => panic!("expected a config error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_verify_installed() {
+        let dir = tempfile::tempdir().unwrap();
+        let entry = dir.path().join("main.fsx");
+        std::fs::write(&entry, "let x = 1").unwrap();
+        let store = ArtifactStore::open(dir.path().join("store")).unwrap();
+        store
+            .put_artifact("search", "1.0.0", "bytecode", b"bytecode")
+            .unwrap();
+
+        let lock = Lockfile::capture(&[
+            info("search", "1.0.0", &entry),
+            info("notes", "1.0.0", &entry),
+        ])
+        .unwrap();
+        // Nothing is loaded, and that is not drift here
+        assert!(lock.verify_installed(None).is_consistent());
+
+        let report = lock.verify_installed(Some(&store));
+        assert_eq!(
+            report.drift,
+            vec![Drift::NotStored {
+                plugin: "notes".into(),
+                version: "1.0.0".into(),
+            }]
+        );
+        match report.into_result() {
+            Err(Error::Registry(reason)) => {
+                assert!(reason.ends_with("notes: 1.0.0 is not in the artifact store"))
+            }
+            other => panic!("expected a registry error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,156 @@
+//! Capturing the log output of plugins.
+//!
+//! Only process-isolated workers have their output captured automatically;
+//! in-process engines write to the host's own stdout, so their buffer holds
+//! just the lines host functions record with [`PluginLogger::log`].
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::SystemTime;
+
+use parking_lot::Mutex;
+
+/// Number of lines kept per plugin unless configured otherwise.
+pub(crate) const DEFAULT_LOG_CAPACITY: usize = 100;
+
+/// Severity of a captured log line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    /// Diagnostic detail.
+    Debug,
+    /// Regular output, including `print`.
+    Info,
+    /// Something unexpected the plugin recovered from.
+    Warn,
+    /// A failure reported by the plugin.
+    Error,
+}
+
+impl LogLevel {
+    /// Split a leading `[level]` tag off a line, defaulting to `Info`.
+    pub fn parse_prefix(line: &str) -> (Self, &str) {
+        let tags = [
+            ("[debug]", Self::Debug),
+            ("[info]", Self::Info),
+            ("[warn]", Self::Warn),
+            ("[error]", Self::Error),
+        ];
+        for (tag, level) in tags {
+            if let Some(rest) = line.strip_prefix(tag) {
+                return (level, rest.trim_start());
+            }
+        }
+        (Self::Info, line)
+    }
+}
+
+/// A line of plugin output.
+#[derive(Debug, Clone)]
+pub struct LogLine {
+    /// When the line was captured.
+    pub timestamp: SystemTime,
+    /// Severity of the line.
+    pub level: LogLevel,
+    /// The line without its trailing newline.
+    pub message: String,
+}
+
+/// Buffer of the most recent output of one plugin.
+///
+/// Every line is also forwarded to `tracing` with a `plugin` field.
+#[derive(Debug)]
+pub struct PluginLogger {
+    plugin: String,
+    capacity: AtomicUsize,
+    lines: Mutex<VecDeque<LogLine>>,
+}
+
+impl PluginLogger {
+    /// Create a logger keeping the last `capacity` lines of `plugin`.
+    pub fn new(plugin: impl Into<String>, capacity: usize) -> Self {
+        Self {
+            plugin: plugin.into(),
+            capacity: AtomicUsize::new(capacity),
+            lines: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Get the name of the plugin whose output is captured.
+    pub fn plugin(&self) -> &str {
+        &self.plugin
+    }
+
+    /// Change the number of lines kept, dropping the oldest if needed.
+    pub fn set_capacity(&self, capacity: usize) {
+        self.capacity.store(capacity, Ordering::Relaxed);
+        let mut lines = self.lines.lock();
+        while lines.len() > capacity {
+            lines.pop_front();
+        }
+    }
+
+    /// Record a line of plugin output.
+    pub fn log(&self, level: LogLevel, message: impl Into<String>) {
+        let message = message.into();
+        match level {
+            LogLevel::Debug => tracing::debug!(plugin = %self.plugin, "{}", message),
+            LogLevel::Info => tracing::info!(plugin = %self.plugin, "{}", message),
+            LogLevel::Warn => tracing::warn!(plugin = %self.plugin, "{}", message),
+            LogLevel::Error => tracing::error!(plugin = %self.plugin, "{}", message),
+        }
+
+        let capacity = self.capacity.load(Ordering::Relaxed);
+        if capacity == 0 {
+            return;
+        }
+        let mut lines = self.lines.lock();
+        if lines.len() >= capacity {
+            lines.pop_front();
+        }
+        lines.push_back(LogLine {
+            timestamp: SystemTime::now(),
+            level,
+            message,
+        });
+    }
+
+    /// Get the buffered lines, oldest first.
+    pub fn recent(&self) -> Vec<LogLine> {
+        self.lines.lock().iter().cloned().collect()
+    }
+
+    /// Drop every buffered line.
+    pub fn clear(&self) {
+        self.lines.lock().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_logger_keeps_last_lines() {
+        let logger = PluginLogger::new("demo", 2);
+        logger.log(LogLevel::Info, "one");
+        logger.log(LogLevel::Warn, "two");
+        logger.log(LogLevel::Error, "three");
+
+        let lines = logger.recent();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].message, "two");
+        assert_eq!(lines[1].level, LogLevel::Error);
+
+        logger.set_capacity(1);
+        assert_eq!(logger.recent()[0].message, "three");
+    }
+
+    #[test]
+    fn test_parse_prefix() {
+        assert_eq!(
+            LogLevel::parse_prefix("[warn] low disk"),
+            (LogLevel::Warn, "low disk")
+        );
+        assert_eq!(LogLevel::parse_prefix("hello"), (LogLevel::Info, "hello"));
+    }
+}
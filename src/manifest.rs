@@ -1,14 +1,25 @@
 //! Plugin manifest schema and validation.
 
-use std::collections::HashMap;
-#[cfg(feature = "serde")]
-use std::path::Path;
-
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Component, Path};
+
+use crate::call_cache::{CacheableExport, CallCacheConfig};
+use crate::cli::{CliCommand, CliConfig};
+use crate::composite::{self, CompositeMember};
+use crate::config_schema::{ConfigField, ConfigSchema};
+use crate::delegation;
+use crate::environment::{EnvConfig, ENV_READ};
 use crate::error::{Error, Result};
+use crate::namespace;
+use crate::rate_limit::CallRateLimits;
+use crate::schedule::ScheduledJob;
+use crate::signature::ExportSpec;
+use crate::version::VersionReq;
 
 /// API version specification.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ApiVersion {
     /// Major version.
     pub major: u32,
@@ -76,6 +87,7 @@ impl std::fmt::Display for ApiVersion {
 /// Plugin dependency specification.
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Dependency {
     /// Dependency name.
     pub name: String,
@@ -104,11 +116,328 @@ impl Dependency {
             optional: true,
         }
     }
+
+    /// Parse the version requirement.
+    pub fn requirement(&self) -> Result<VersionReq> {
+        VersionReq::parse(&self.version)
+    }
+
+    /// Check if a plugin version satisfies this dependency's requirement.
+    ///
+    /// See [`VersionReq`] for the accepted syntax. Unparseable requirements
+    /// or versions never match.
+    pub fn is_satisfied_by(&self, version: &str) -> bool {
+        let Ok(req) = self.requirement() else {
+            return false;
+        };
+        req.is_any() || ApiVersion::parse(version).is_ok_and(|v| req.matches(&v))
+    }
+}
+
+/// Capability declaration, optionally with a declared scope.
+///
+/// In a manifest this is either a bare name (`"fs:read"`) or a table such
+/// as `{ cap = "fs:read", paths = ["/data"] }` or
+/// `{ cap = "net:request", hosts = ["api.example.com", "*.example.org"] }`.
+/// Empty scopes allow everything the capability covers.
+///
+/// The engine can only be granted whole capabilities, so it cannot enforce
+/// scopes: plugins declaring one fail to initialize with
+/// [`Error::InvalidManifest`] rather than run with the unscoped capability.
+/// Scopes are kept for checking and intersecting declarations, e.g. with
+/// [`Plugin::check_path_access`](crate::Plugin::check_path_access).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapabilitySpec {
+    /// Capability name.
+    pub name: String,
+    /// Path prefixes a filesystem capability is limited to.
+    pub paths: Vec<String>,
+    /// Hosts a network capability is limited to.
+    pub hosts: Vec<String>,
+}
+
+impl CapabilitySpec {
+    /// Create an unscoped capability declaration.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            paths: Vec::new(),
+            hosts: Vec::new(),
+        }
+    }
+
+    /// Limit the capability to paths under these prefixes.
+    pub fn with_paths<I, S>(mut self, paths: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.paths.extend(paths.into_iter().map(Into::into));
+        self
+    }
+
+    /// Limit the capability to these hosts.
+    ///
+    /// A leading `*.` matches any subdomain.
+    pub fn with_hosts<I, S>(mut self, hosts: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.hosts.extend(hosts.into_iter().map(Into::into));
+        self
+    }
+
+    /// Check if the declaration carries a scope.
+    pub fn is_scoped(&self) -> bool {
+        !self.paths.is_empty() || !self.hosts.is_empty()
+    }
+
+    /// Check if a path is inside the declared scope.
+    ///
+    /// Paths with `..` components are rejected when a scope is set.
+    pub fn allows_path(&self, path: &Path) -> bool {
+        if self.paths.is_empty() {
+            return true;
+        }
+        if path.components().any(|c| c == Component::ParentDir) {
+            return false;
+        }
+        self.paths.iter().any(|prefix| path.starts_with(prefix))
+    }
+
+    /// Check if a host is inside the declared scope.
+    pub fn allows_host(&self, host: &str) -> bool {
+        if self.hosts.is_empty() {
+            return true;
+        }
+        let host = host.to_ascii_lowercase();
+        self.hosts.iter().any(|allowed| {
+            let allowed = allowed.to_ascii_lowercase();
+            match allowed.strip_prefix("*.") {
+                Some(domain) => host
+                    .strip_suffix(domain)
+                    .is_some_and(|sub| sub.ends_with('.') && sub.len() > 1),
+                None => host == allowed,
+            }
+        })
+    }
+
+    /// Get the part of this declaration that `other` also covers, or `None`
+    /// if they share nothing.
+    ///
+    /// An unscoped declaration takes the other's scope; two scopes keep the
+    /// entries of each that fall inside the other.
+    pub fn intersect(&self, other: &CapabilitySpec) -> Option<CapabilitySpec> {
+        if self.name != other.name {
+            return None;
+        }
+        let paths = intersect_scopes(&self.paths, &other.paths, |spec, path| {
+            spec.allows_path(Path::new(path))
+        });
+        let hosts = intersect_scopes(&self.hosts, &other.hosts, CapabilitySpec::allows_host);
+        let (paths, hosts) = (paths?, hosts?);
+        Some(CapabilitySpec {
+            name: self.name.clone(),
+            paths,
+            hosts,
+        })
+    }
+}
+
+/// Intersect two scope lists, `None` meaning the scopes are disjoint.
+fn intersect_scopes(
+    a: &[String],
+    b: &[String],
+    allows: impl Fn(&CapabilitySpec, &str) -> bool,
+) -> Option<Vec<String>> {
+    if a.is_empty() || b.is_empty() {
+        return Some(if a.is_empty() { b } else { a }.to_vec());
+    }
+    let scope = |entries: &[String]| CapabilitySpec {
+        name: String::new(),
+        paths: entries.to_vec(),
+        hosts: entries.to_vec(),
+    };
+    let (scope_a, scope_b) = (scope(a), scope(b));
+    let mut kept: Vec<String> = a
+        .iter()
+        .filter(|entry| allows(&scope_b, entry))
+        .cloned()
+        .collect();
+    for entry in b {
+        if allows(&scope_a, entry) && !kept.contains(entry) {
+            kept.push(entry.clone());
+        }
+    }
+    (!kept.is_empty()).then_some(kept)
+}
+
+impl From<&str> for CapabilitySpec {
+    fn from(name: &str) -> Self {
+        Self::new(name)
+    }
+}
+
+impl From<String> for CapabilitySpec {
+    fn from(name: String) -> Self {
+        Self::new(name)
+    }
+}
+
+impl PartialEq<str> for CapabilitySpec {
+    fn eq(&self, other: &str) -> bool {
+        self.name == other
+    }
+}
+
+impl PartialEq<&str> for CapabilitySpec {
+    fn eq(&self, other: &&str) -> bool {
+        self.name == *other
+    }
+}
+
+impl std::fmt::Display for CapabilitySpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(untagged)]
+enum CapabilitySpecRepr {
+    Name(String),
+    Scoped(ScopedCapability),
+}
+
+// Unknown keys are rejected so a misspelled scope never reads as no scope
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+struct ScopedCapability {
+    cap: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    paths: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    hosts: Vec<String>,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for CapabilitySpec {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        let repr = if self.is_scoped() {
+            CapabilitySpecRepr::Scoped(ScopedCapability {
+                cap: self.name.clone(),
+                paths: self.paths.clone(),
+                hosts: self.hosts.clone(),
+            })
+        } else {
+            CapabilitySpecRepr::Name(self.name.clone())
+        };
+        repr.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for CapabilitySpec {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        // Visited by hand so errors in a table name the offending key
+        struct SpecVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for SpecVisitor {
+            type Value = CapabilitySpec;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str("a capability name or a { cap, paths, hosts } table")
+            }
+
+            fn visit_str<E: serde::de::Error>(
+                self,
+                name: &str,
+            ) -> std::result::Result<Self::Value, E> {
+                Ok(CapabilitySpec::new(name))
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                map: A,
+            ) -> std::result::Result<Self::Value, A::Error> {
+                let scoped = ScopedCapability::deserialize(
+                    serde::de::value::MapAccessDeserializer::new(map),
+                )?;
+                if scoped.paths.is_empty() && scoped.hosts.is_empty() {
+                    return Err(serde::de::Error::custom(format!(
+                        "capability {} is a table but declares neither paths nor hosts",
+                        scoped.cap
+                    )));
+                }
+                Ok(CapabilitySpec {
+                    name: scoped.cap,
+                    paths: scoped.paths,
+                    hosts: scoped.hosts,
+                })
+            }
+        }
+
+        deserializer.deserialize_any(SpecVisitor)
+    }
+}
+
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for CapabilitySpec {
+    fn schema_name() -> String {
+        "CapabilitySpec".to_string()
+    }
+
+    fn json_schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        CapabilitySpecRepr::json_schema(gen)
+    }
+}
+
+/// Where a plugin's code runs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Isolation {
+    /// In an engine inside the host process.
+    #[default]
+    InProcess,
+    /// In a separate worker process (requires the `isolation` feature).
+    Process,
+}
+
+/// Optional capabilities and exports a host can enable for a plugin.
+///
+/// Declared under `[features.<name>]` and activated with
+/// [`LoaderConfig::with_plugin_features`](crate::LoaderConfig::with_plugin_features).
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PluginFeature {
+    /// What the feature adds, for hosts deciding whether to enable it.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub description: Option<String>,
+    /// Capabilities the plugin only requests with the feature enabled.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub capabilities: Vec<CapabilitySpec>,
+    /// Exports the plugin only provides with the feature enabled.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub exports: Vec<ExportSpec>,
 }
 
 /// Plugin manifest defining metadata and requirements.
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Manifest {
     /// Plugin name (unique identifier).
     pub name: String,
@@ -134,12 +463,20 @@ pub struct Manifest {
 
     /// Required capabilities.
     #[cfg_attr(feature = "serde", serde(default))]
-    pub capabilities: Vec<String>,
+    pub capabilities: Vec<CapabilitySpec>,
 
     /// Plugin dependencies.
     #[cfg_attr(feature = "serde", serde(default))]
     pub dependencies: Vec<Dependency>,
 
+    /// Host functions the plugin needs the embedding application to provide.
+    #[cfg_attr(feature = "serde", serde(default, rename = "host-functions"))]
+    pub host_functions: Vec<String>,
+
+    /// Ordering among plugins; higher priorities start first and stop last.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub priority: i32,
+
     /// Entry point source file (.fsx).
     #[cfg_attr(feature = "serde", serde(default))]
     pub source: Option<String>,
@@ -148,9 +485,51 @@ pub struct Manifest {
     #[cfg_attr(feature = "serde", serde(default))]
     pub bytecode: Option<String>,
 
-    /// Exported functions.
+    /// Exported functions, optionally with typed signatures.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub exports: Vec<ExportSpec>,
+
+    /// Exports whose results the runtime may cache.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub cache: CallCacheConfig,
+
+    /// Calls per second the runtime lets into the plugin and its exports.
+    #[cfg_attr(feature = "serde", serde(default, rename = "rate-limit"))]
+    pub rate_limit: CallRateLimits,
+
+    /// Command-line subcommands provided by the plugin.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub cli: CliConfig,
+
+    /// Schema of the plugin's configuration, for generated settings screens.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub config: ConfigSchema,
+
+    /// Exports the runtime calls periodically.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub schedule: Vec<ScheduledJob>,
+
+    /// Where the plugin's code runs.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub isolation: Isolation,
+
+    /// Whether calls keep no state in the engine, letting the runtime serve
+    /// them from a pool of engines.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub stateless: bool,
+
+    /// Environment variables the plugin wants to read.
     #[cfg_attr(feature = "serde", serde(default))]
-    pub exports: Vec<String>,
+    pub env: EnvConfig,
+
+    /// Member plugins, making this a composite plugin.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub members: Vec<CompositeMember>,
+
+    /// Optional capability sets and exports, off unless the host enables
+    /// them.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub features: BTreeMap<String, PluginFeature>,
 
     /// Plugin tags for categorization.
     #[cfg_attr(feature = "serde", serde(default))]
@@ -173,9 +552,21 @@ impl Manifest {
             api_version: ApiVersion::default(),
             capabilities: Vec::new(),
             dependencies: Vec::new(),
+            host_functions: Vec::new(),
+            priority: 0,
             source: None,
             bytecode: None,
             exports: Vec::new(),
+            cache: CallCacheConfig::default(),
+            rate_limit: CallRateLimits::default(),
+            cli: CliConfig::default(),
+            config: ConfigSchema::default(),
+            schedule: Vec::new(),
+            isolation: Isolation::default(),
+            stateless: false,
+            env: EnvConfig::default(),
+            members: Vec::new(),
+            features: BTreeMap::new(),
             tags: Vec::new(),
             metadata: HashMap::new(),
         }
@@ -200,6 +591,63 @@ impl Manifest {
         serde_json::from_str(content).map_err(|e| Error::ManifestParse(e.to_string()))
     }
 
+    /// Parse manifest from TOML, rejecting unknown keys.
+    ///
+    /// Errors carry the line and column of the offending key or value.
+    #[cfg(feature = "serde")]
+    pub fn from_toml_strict(content: &str) -> Result<Self> {
+        let syntax_error = |e: toml::de::Error| {
+            let (line, column) = e
+                .span()
+                .map(|span| line_column(content, span.start))
+                .unwrap_or((1, 1));
+            Error::manifest_syntax(line, column, e.message())
+        };
+        let value: toml::Value = toml::from_str(content).map_err(syntax_error)?;
+        let manifest: Self = toml::from_str(content).map_err(syntax_error)?;
+        let input =
+            serde_json::to_value(&value).map_err(|e| Error::ManifestParse(e.to_string()))?;
+        manifest.check_unknown_keys(content, &input)?;
+        Ok(manifest)
+    }
+
+    /// Parse manifest from JSON, rejecting unknown keys.
+    ///
+    /// Errors carry the line and column of the offending key or value.
+    #[cfg(feature = "serde")]
+    pub fn from_json_strict(content: &str) -> Result<Self> {
+        let syntax_error =
+            |e: serde_json::Error| Error::manifest_syntax(e.line(), e.column(), e.to_string());
+        let input: serde_json::Value = serde_json::from_str(content).map_err(syntax_error)?;
+        let manifest: Self = serde_json::from_str(content).map_err(syntax_error)?;
+        manifest.check_unknown_keys(content, &input)?;
+        Ok(manifest)
+    }
+
+    /// Report the first key of `input` that parsing this manifest ignored.
+    #[cfg(feature = "serde")]
+    fn check_unknown_keys(&self, content: &str, input: &serde_json::Value) -> Result<()> {
+        let parsed = serde_json::to_value(self).map_err(|e| Error::ManifestParse(e.to_string()))?;
+        match unknown_key(input, &parsed, "") {
+            Some(path) => {
+                let key = path.rsplit('.').next().unwrap_or(&path);
+                let (line, column) = find_key(content, key).unwrap_or((1, 1));
+                Err(Error::manifest_syntax(
+                    line,
+                    column,
+                    format!("unknown field `{}`", path),
+                ))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Generate a JSON Schema describing the manifest format.
+    #[cfg(feature = "schema")]
+    pub fn json_schema() -> serde_json::Value {
+        serde_json::to_value(schemars::schema_for!(Manifest)).unwrap_or_default()
+    }
+
     /// Serialize to TOML string.
     #[cfg(feature = "serde")]
     pub fn to_toml(&self) -> Result<String> {
@@ -218,41 +666,191 @@ impl Manifest {
         if self.name.is_empty() {
             return Err(Error::missing_field("name"));
         }
+        namespace::validate(&self.name)?;
 
         if self.version.is_empty() {
             return Err(Error::missing_field("version"));
         }
 
-        // Must have either source or bytecode
-        if self.source.is_none() && self.bytecode.is_none() {
+        if self.is_composite() {
+            composite::validate(self)?;
+        } else if self.source.is_none() && self.bytecode.is_none() {
+            // Must have either source or bytecode
             return Err(Error::invalid_manifest(
                 "manifest must specify either 'source' or 'bytecode'",
             ));
         }
 
-        // Validate capability names
-        for cap in &self.capabilities {
-            if fusabi_host::Capability::from_name(cap).is_none() {
+        if self.source.as_deref().is_some_and(|p| p.ends_with(".wasm")) {
+            return Err(Error::invalid_manifest(
+                "wasm modules must be declared as 'bytecode'",
+            ));
+        }
+        if self.uses_wasm() && self.isolation == Isolation::Process {
+            return Err(Error::invalid_manifest(
+                "wasm plugins cannot use process isolation",
+            ));
+        }
+
+        for dep in &self.dependencies {
+            dep.requirement()?;
+        }
+
+        if !self.env.is_empty() && self.capability(ENV_READ).is_none() {
+            return Err(Error::invalid_manifest(format!(
+                "[env] allow requires the {} capability",
+                ENV_READ
+            )));
+        }
+
+        for (i, export) in self.exports.iter().enumerate() {
+            if self.exports[..i].iter().any(|e| e.name == export.name) {
+                return Err(Error::invalid_manifest(format!(
+                    "duplicate export: {}",
+                    export.name
+                )));
+            }
+        }
+
+        for (name, feature) in &self.features {
+            if name.is_empty() {
+                return Err(Error::invalid_manifest("feature names must not be empty"));
+            }
+            for (i, export) in feature.exports.iter().enumerate() {
+                if self.export(&export.name).is_some()
+                    || feature.exports[..i].iter().any(|e| e.name == export.name)
+                {
+                    return Err(Error::invalid_manifest(format!(
+                        "duplicate export in feature {}: {}",
+                        name, export.name
+                    )));
+                }
+            }
+        }
+
+        for (i, export) in self.cache.exports.iter().enumerate() {
+            export.validate(&self.exports)?;
+            if self.cache.exports[..i]
+                .iter()
+                .any(|e| e.name == export.name)
+            {
+                return Err(Error::invalid_manifest(format!(
+                    "duplicate cacheable function: {}",
+                    export.name
+                )));
+            }
+        }
+
+        for (i, command) in self.cli.commands.iter().enumerate() {
+            command.validate(&self.exports)?;
+            if self.cli.commands[..i]
+                .iter()
+                .any(|c| c.name == command.name)
+            {
+                return Err(Error::invalid_manifest(format!(
+                    "duplicate command: {}",
+                    command.name
+                )));
+            }
+        }
+
+        self.rate_limit.validate(&self.exports)?;
+        self.config.validate()?;
+
+        for (i, job) in self.schedule.iter().enumerate() {
+            job.validate(&self.exports)?;
+            if self.schedule[..i].iter().any(|j| j.export == job.export) {
+                return Err(Error::invalid_manifest(format!(
+                    "duplicate scheduled function: {}",
+                    job.export
+                )));
+            }
+        }
+
+        // Validate capability names and scopes
+        let optional = self.features.values().flat_map(|f| &f.capabilities);
+        for cap in self.capabilities.iter().chain(optional) {
+            if fusabi_host::Capability::from_name(&cap.name).is_none()
+                && !delegation::is_runtime_capability(&cap.name)
+            {
                 return Err(Error::invalid_manifest(format!(
                     "unknown capability: {}",
                     cap
                 )));
             }
+            if !cap.paths.is_empty() && !cap.name.starts_with("fs:") {
+                return Err(Error::invalid_manifest(format!(
+                    "capability {} does not accept a path scope",
+                    cap
+                )));
+            }
+            if !cap.hosts.is_empty() && !cap.name.starts_with("net:") {
+                return Err(Error::invalid_manifest(format!(
+                    "capability {} does not accept a host scope",
+                    cap
+                )));
+            }
         }
 
         Ok(())
     }
 
+    /// Add the capabilities and exports of the named features.
+    ///
+    /// Fails if a feature is not declared. Enabling a feature twice has no
+    /// further effect.
+    pub fn enable_features<S: AsRef<str>>(&mut self, names: &[S]) -> Result<()> {
+        for name in names {
+            let name = name.as_ref();
+            let feature = self.features.get(name).cloned().ok_or_else(|| {
+                Error::invalid_manifest(format!("plugin {} has no feature {:?}", self.name, name))
+            })?;
+            for cap in feature.capabilities {
+                if !self.capabilities.contains(&cap) {
+                    self.capabilities.push(cap);
+                }
+            }
+            for export in feature.exports {
+                if self.export(&export.name).is_none() {
+                    self.exports.push(export);
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Check if this manifest requires a capability.
     pub fn requires_capability(&self, cap: &str) -> bool {
         self.capabilities.iter().any(|c| c == cap)
     }
 
+    /// Get the declaration of a capability.
+    pub fn capability(&self, cap: &str) -> Option<&CapabilitySpec> {
+        self.capabilities.iter().find(|c| c == &cap)
+    }
+
     /// Check if this manifest is compatible with a host API version.
     pub fn is_compatible_with_host(&self, host_version: &ApiVersion) -> bool {
         host_version.is_compatible_with(&self.api_version)
     }
 
+    /// Check if the manifest exports a function.
+    pub fn has_export(&self, name: &str) -> bool {
+        self.exports.iter().any(|e| e == name)
+    }
+
+    /// Get the names of the exported functions, in declaration order.
+    ///
+    /// Matches what `exports` held before it carried signatures.
+    pub fn export_names(&self) -> Vec<&str> {
+        self.exports.iter().map(|e| e.name.as_str()).collect()
+    }
+
+    /// Get the declaration of an exported function.
+    pub fn export(&self, name: &str) -> Option<&ExportSpec> {
+        self.exports.iter().find(|e| e == &name)
+    }
+
     /// Get the entry point path (source or bytecode).
     pub fn entry_point(&self) -> Option<&str> {
         self.source.as_deref().or(self.bytecode.as_deref())
@@ -262,6 +860,102 @@ impl Manifest {
     pub fn uses_source(&self) -> bool {
         self.source.is_some()
     }
+
+    /// Get the namespace of a name like `acme/http-logger`.
+    pub fn namespace(&self) -> Option<&str> {
+        namespace::split(&self.name).0
+    }
+
+    /// Get the name without its namespace.
+    pub fn local_name(&self) -> &str {
+        namespace::split(&self.name).1
+    }
+
+    /// Check if the manifest composes member plugins.
+    pub fn is_composite(&self) -> bool {
+        !self.members.is_empty()
+    }
+
+    /// Check if the entry point is a WebAssembly module.
+    pub fn uses_wasm(&self) -> bool {
+        self.source.is_none()
+            && self
+                .bytecode
+                .as_deref()
+                .is_some_and(|path| path.ends_with(".wasm"))
+    }
+}
+
+/// Find a key of `input` missing from its parsed form, as a dotted path.
+///
+/// Keys set to an empty array are skipped since serialization may omit them.
+/// An object that parsed into another shape, such as a table read back as a
+/// bare string, has all its keys ignored, so the first one is reported.
+#[cfg(feature = "serde")]
+fn unknown_key(
+    input: &serde_json::Value,
+    parsed: &serde_json::Value,
+    path: &str,
+) -> Option<String> {
+    use serde_json::Value;
+
+    let join = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", path, key)
+        }
+    };
+    match (input, parsed) {
+        (Value::Object(input), Value::Object(parsed)) => {
+            input.iter().find_map(|(key, value)| match parsed.get(key) {
+                Some(parsed) => unknown_key(value, parsed, &join(key)),
+                None if value.as_array().is_some_and(Vec::is_empty) => None,
+                None => Some(join(key)),
+            })
+        }
+        (Value::Array(input), Value::Array(parsed)) => input
+            .iter()
+            .zip(parsed)
+            .enumerate()
+            .find_map(|(i, (input, parsed))| unknown_key(input, parsed, &join(&i.to_string()))),
+        (Value::Object(input), _) => input
+            .iter()
+            .find(|(_, value)| !value.as_array().is_some_and(Vec::is_empty))
+            .map(|(key, _)| join(key)),
+        _ => None,
+    }
+}
+
+/// Convert a byte offset to a 1-based line and column.
+#[cfg(feature = "serde")]
+fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().map_or(0, |l| l.chars().count()) + 1;
+    (line, column)
+}
+
+/// Locate the first line defining `key`, bare or quoted.
+#[cfg(feature = "serde")]
+fn find_key(content: &str, key: &str) -> Option<(usize, usize)> {
+    let candidates = [
+        key.to_string(),
+        format!("\"{}\"", key),
+        format!("'{}'", key),
+    ];
+    content.lines().enumerate().find_map(|(i, line)| {
+        candidates.iter().find_map(|candidate| {
+            line.match_indices(candidate.as_str()).find_map(|(at, _)| {
+                let prev = line[..at].chars().next_back();
+                let rest = line[at + candidate.len()..].trim_start();
+                let boundary =
+                    prev.map_or(true, |c| !(c.is_alphanumeric() || c == '-' || c == '_'));
+                (boundary && (rest.starts_with('=') || rest.starts_with(':')))
+                    .then(|| (i + 1, line[..at].chars().count() + 1))
+            })
+        })
+    })
 }
 
 /// Builder for creating manifests.
@@ -301,8 +995,8 @@ impl ManifestBuilder {
         self
     }
 
-    /// Add a capability requirement.
-    pub fn capability(mut self, cap: impl Into<String>) -> Self {
+    /// Add a capability requirement, either a name or a scoped [`CapabilitySpec`].
+    pub fn capability(mut self, cap: impl Into<CapabilitySpec>) -> Self {
         self.manifest.capabilities.push(cap.into());
         self
     }
@@ -311,7 +1005,7 @@ impl ManifestBuilder {
     pub fn capabilities<I, S>(mut self, caps: I) -> Self
     where
         I: IntoIterator<Item = S>,
-        S: Into<String>,
+        S: Into<CapabilitySpec>,
     {
         self.manifest
             .capabilities
@@ -338,7 +1032,7 @@ impl ManifestBuilder {
     }
 
     /// Add an export.
-    pub fn export(mut self, name: impl Into<String>) -> Self {
+    pub fn export(mut self, name: impl Into<ExportSpec>) -> Self {
         self.manifest.exports.push(name.into());
         self
     }
@@ -347,7 +1041,7 @@ impl ManifestBuilder {
     pub fn exports<I, S>(mut self, exports: I) -> Self
     where
         I: IntoIterator<Item = S>,
-        S: Into<String>,
+        S: Into<ExportSpec>,
     {
         self.manifest
             .exports
@@ -355,6 +1049,87 @@ impl ManifestBuilder {
         self
     }
 
+    /// Allow the runtime to cache an export's results.
+    pub fn cacheable(mut self, export: CacheableExport) -> Self {
+        self.manifest.cache.exports.push(export);
+        self
+    }
+
+    /// Limit calls into the plugin to `calls` per second.
+    pub fn max_calls_per_second(mut self, calls: u32) -> Self {
+        self.manifest.rate_limit.max_calls_per_second = Some(calls);
+        self
+    }
+
+    /// Limit calls into an export to `calls` per second.
+    pub fn function_rate_limit(mut self, function: impl Into<String>, calls: u32) -> Self {
+        self.manifest
+            .rate_limit
+            .functions
+            .insert(function.into(), calls);
+        self
+    }
+
+    /// Run an export periodically.
+    pub fn scheduled(mut self, job: ScheduledJob) -> Self {
+        self.manifest.schedule.push(job);
+        self
+    }
+
+    /// Set where the plugin's code runs.
+    pub fn isolation(mut self, isolation: Isolation) -> Self {
+        self.manifest.isolation = isolation;
+        self
+    }
+
+    /// Mark calls as keeping no state in the engine.
+    pub fn stateless(mut self, stateless: bool) -> Self {
+        self.manifest.stateless = stateless;
+        self
+    }
+
+    /// Declare an optional feature.
+    pub fn feature(mut self, name: impl Into<String>, feature: PluginFeature) -> Self {
+        self.manifest.features.insert(name.into(), feature);
+        self
+    }
+
+    /// Set the start priority.
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.manifest.priority = priority;
+        self
+    }
+
+    /// Require a host function.
+    pub fn host_function(mut self, name: impl Into<String>) -> Self {
+        self.manifest.host_functions.push(name.into());
+        self
+    }
+
+    /// Add an environment variable the plugin wants to read.
+    pub fn env_var(mut self, name: impl Into<String>) -> Self {
+        self.manifest.env.allow.push(name.into());
+        self
+    }
+
+    /// Add a member plugin, making this a composite plugin.
+    pub fn member(mut self, member: CompositeMember) -> Self {
+        self.manifest.members.push(member);
+        self
+    }
+
+    /// Add a command-line subcommand.
+    pub fn command(mut self, command: CliCommand) -> Self {
+        self.manifest.cli.commands.push(command);
+        self
+    }
+
+    /// Add a configuration field.
+    pub fn config_field(mut self, field: ConfigField) -> Self {
+        self.manifest.config.fields.push(field);
+        self
+    }
+
     /// Add a tag.
     pub fn tag(mut self, tag: impl Into<String>) -> Self {
         self.manifest.tags.push(tag.into());
@@ -419,6 +1194,25 @@ mod tests {
         assert!(!v4.is_compatible_with(&v1));
     }
 
+    #[test]
+    fn test_dependency_requirement() {
+        let dep = Dependency::required("log", "1.2");
+        assert!(dep.is_satisfied_by("1.2.0"));
+        assert!(dep.is_satisfied_by("1.9.3"));
+        assert!(!dep.is_satisfied_by("1.1.9"));
+        assert!(!dep.is_satisfied_by("2.0.0"));
+
+        let dep = Dependency::required("log", "0.3");
+        assert!(dep.is_satisfied_by("0.3.7"));
+        assert!(!dep.is_satisfied_by("0.4.0"));
+
+        assert!(Dependency::required("log", "=1.0.1").is_satisfied_by("1.0.1"));
+        assert!(!Dependency::required("log", "=1.0.1").is_satisfied_by("1.0.2"));
+        assert!(Dependency::required("log", "*").is_satisfied_by("anything"));
+        assert!(Dependency::required("log", ">=0.3, <0.5").is_satisfied_by("0.4.1"));
+        assert!(!Dependency::required("log", "^1.x.2").is_satisfied_by("1.0.2"));
+    }
+
     #[test]
     fn test_manifest_builder() {
         let manifest = ManifestBuilder::new("test-plugin", "1.0.0")
@@ -460,6 +1254,46 @@ mod tests {
         assert!(manifest.validate().is_err());
     }
 
+    #[test]
+    fn test_wasm_entry() {
+        let mut manifest = Manifest::new("test", "1.0.0");
+        manifest.bytecode = Some("plugin.wasm".into());
+        assert!(manifest.uses_wasm());
+        assert!(manifest.validate().is_ok());
+
+        manifest.isolation = Isolation::Process;
+        assert!(manifest.validate().is_err());
+
+        let mut manifest = Manifest::new("test", "1.0.0");
+        manifest.source = Some("plugin.wasm".into());
+        assert!(!manifest.uses_wasm());
+        assert!(manifest.validate().is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_export_signatures_toml() {
+        let toml = r#"
+name = "typed"
+version = "1.0.0"
+api-version = { major = 0, minor = 21, patch = 0 }
+source = "main.fsx"
+exports = ["init", "process(input: string) -> record"]
+"#;
+
+        let manifest = Manifest::from_toml(toml).unwrap();
+        assert!(manifest.has_export("process"));
+        assert_eq!(manifest.export_names(), vec!["init", "process"]);
+        assert!(manifest.export("process").unwrap().signature.is_some());
+        assert!(manifest.export("init").unwrap().signature.is_none());
+
+        let reparsed = Manifest::from_toml(&toml::to_string(&manifest).unwrap()).unwrap();
+        assert_eq!(reparsed.exports, manifest.exports);
+
+        let bad = toml.replace("input: string", "input: blob");
+        assert!(Manifest::from_toml(&bad).is_err());
+    }
+
     #[cfg(feature = "serde")]
     #[test]
     fn test_manifest_toml() {
@@ -477,4 +1311,359 @@ exports = ["init", "run"]
         assert_eq!(manifest.name, "my-plugin");
         assert_eq!(manifest.capabilities.len(), 2);
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_scoped_capabilities_toml() {
+        let toml = r#"
+name = "scoped"
+version = "1.0.0"
+api-version = { major = 0, minor = 21, patch = 0 }
+capabilities = [
+    "time:read",
+    { cap = "fs:read", paths = ["/data"] },
+    { cap = "net:request", hosts = ["api.example.com"] },
+]
+source = "main.fsx"
+"#;
+
+        let manifest = Manifest::from_toml(toml).unwrap();
+        manifest.validate().unwrap();
+        assert!(manifest.requires_capability("fs:read"));
+        assert_eq!(manifest.capability("fs:read").unwrap().paths, vec!["/data"]);
+
+        let roundtrip = Manifest::from_toml(&manifest.to_toml().unwrap()).unwrap();
+        assert_eq!(roundtrip.capabilities, manifest.capabilities);
+
+        let mut invalid = manifest.clone();
+        invalid.capabilities = vec![CapabilitySpec::new("time:read").with_paths(["/tmp"])];
+        assert!(invalid.validate().is_err());
+
+        // A misspelled or missing scope is an error, never an unscoped grant
+        let with_capability = |entry: &str| {
+            Manifest::from_toml(&format!(
+                "name = \"scoped\"\nversion = \"1.0.0\"\nsource = \"main.fsx\"\n\
+                 api-version = {{ major = 0, minor = 21, patch = 0 }}\n\
+                 capabilities = [{}]\n",
+                entry
+            ))
+        };
+        assert!(with_capability(r#"{ cap = "fs:read", paths = ["/data"] }"#).is_ok());
+        let err = with_capability(r#"{ cap = "fs:read", path = ["/data"] }"#).unwrap_err();
+        assert!(err.to_string().contains("unknown field `path`"), "{}", err);
+        assert!(with_capability(r#"{ cap = "net:request", host = ["api.example.com"] }"#).is_err());
+        let err = with_capability(r#"{ cap = "fs:read" }"#).unwrap_err();
+        assert!(
+            err.to_string().contains("neither paths nor hosts"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_capability_scopes() {
+        let fs = CapabilitySpec::new("fs:read").with_paths(["/data"]);
+        assert!(fs.allows_path(Path::new("/data/plugins/a.txt")));
+        assert!(!fs.allows_path(Path::new("/etc/passwd")));
+        assert!(!fs.allows_path(Path::new("/data/../etc/passwd")));
+        assert!(CapabilitySpec::new("fs:read").allows_path(Path::new("/etc")));
+
+        let net =
+            CapabilitySpec::new("net:request").with_hosts(["api.example.com", "*.example.org"]);
+        assert!(net.allows_host("API.example.com"));
+        assert!(net.allows_host("cdn.example.org"));
+        assert!(!net.allows_host("example.org"));
+        assert!(!net.allows_host("evilexample.org"));
+        assert!(!net.allows_host("example.com"));
+    }
+
+    #[test]
+    fn test_capability_intersect() {
+        let unscoped = CapabilitySpec::new("fs:read");
+        let data = CapabilitySpec::new("fs:read").with_paths(["/data"]);
+        assert_eq!(unscoped.intersect(&data), Some(data.clone()));
+        assert_eq!(data.intersect(&unscoped), Some(data.clone()));
+        assert_eq!(
+            CapabilitySpec::new("fs:read")
+                .with_paths(["/data/cache", "/etc"])
+                .intersect(&data),
+            Some(CapabilitySpec::new("fs:read").with_paths(["/data/cache"]))
+        );
+        assert_eq!(
+            CapabilitySpec::new("fs:read")
+                .with_paths(["/etc"])
+                .intersect(&data),
+            None
+        );
+        assert_eq!(CapabilitySpec::new("fs:write").intersect(&data), None);
+
+        let org = CapabilitySpec::new("net:request").with_hosts(["*.example.org"]);
+        assert_eq!(
+            CapabilitySpec::new("net:request")
+                .with_hosts(["cdn.example.org", "example.com"])
+                .intersect(&org),
+            Some(CapabilitySpec::new("net:request").with_hosts(["cdn.example.org"]))
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_cli_commands_toml() {
+        let toml = r#"
+name = "reports"
+version = "1.0.0"
+api-version = { major = 0, minor = 21, patch = 0 }
+source = "main.fsx"
+exports = ["run_report"]
+
+[cli]
+commands = [
+    { name = "report", export = "run_report", args = [{ name = "limit", type = "int" }] },
+]
+"#;
+
+        let manifest = Manifest::from_toml(toml).unwrap();
+        manifest.validate().unwrap();
+        let command = manifest.cli.command("report").unwrap();
+        assert_eq!(command.args[0].kind, crate::cli::CliArgKind::Int);
+
+        let mut invalid = manifest.clone();
+        invalid.cli.commands[0].export = "missing".into();
+        assert!(invalid.validate().is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_config_schema_toml() {
+        let toml = r#"
+name = "poller"
+version = "1.0.0"
+api-version = { major = 0, minor = 21, patch = 0 }
+source = "main.fsx"
+
+[[config.fields]]
+name = "interval"
+type = "int"
+label = "Poll interval (seconds)"
+default = "60"
+min = 1
+max = 3600
+
+[[config.fields]]
+name = "mode"
+type = "select"
+options = ["fast", "safe"]
+"#;
+
+        let manifest = Manifest::from_toml(toml).unwrap();
+        manifest.validate().unwrap();
+        let interval = manifest.config.field("interval").unwrap();
+        assert_eq!(interval.kind, crate::config_schema::ConfigFieldKind::Int);
+        assert_eq!(interval.max, Some(3600.0));
+
+        let mut invalid = manifest.clone();
+        invalid.config.fields[0].default = Some("0".into());
+        assert!(invalid.validate().is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_schedule_toml() {
+        let toml = r#"
+name = "indexer"
+version = "1.0.0"
+api-version = { major = 0, minor = 21, patch = 0 }
+source = "main.fsx"
+exports = ["refresh", "compact"]
+
+[[schedule]]
+export = "refresh"
+every-secs = 60
+
+[[schedule]]
+export = "compact"
+every-secs = 3600
+singleton = true
+"#;
+
+        let manifest = Manifest::from_toml(toml).unwrap();
+        manifest.validate().unwrap();
+        assert_eq!(manifest.schedule.len(), 2);
+        assert!(!manifest.schedule[0].singleton);
+        assert!(manifest.schedule[1].singleton);
+
+        let mut invalid = manifest.clone();
+        invalid.schedule[1].export = "refresh".into();
+        assert!(invalid.validate().is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_rate_limit_toml() {
+        let toml = r#"
+name = "search"
+version = "1.0.0"
+api-version = { major = 0, minor = 21, patch = 0 }
+source = "main.fsx"
+exports = ["index", "query"]
+
+[rate-limit]
+max-calls-per-second = 50
+functions = { index = 5 }
+"#;
+
+        let manifest = Manifest::from_toml(toml).unwrap();
+        manifest.validate().unwrap();
+        assert_eq!(manifest.rate_limit.max_calls_per_second, Some(50));
+        assert_eq!(
+            manifest.rate_limit.function_limit("index"),
+            crate::rate_limit::RateLimit::per_second(5).ok()
+        );
+        assert_eq!(manifest.rate_limit.function_limit("query"), None);
+
+        let mut invalid = manifest.clone();
+        invalid.rate_limit.functions.insert("reindex".into(), 1);
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn test_enable_features() {
+        let network = PluginFeature {
+            description: Some("Remote feeds".into()),
+            capabilities: vec!["net:request".into()],
+            exports: vec!["fetch".into()],
+        };
+        let mut manifest = ManifestBuilder::new("feeds", "1.0.0")
+            .source("main.fsx")
+            .export("render")
+            .feature("network", network.clone())
+            .build_unchecked();
+        assert!(manifest.validate().is_ok());
+
+        manifest.enable_features(&["network", "network"]).unwrap();
+        assert_eq!(
+            manifest.capabilities,
+            vec![CapabilitySpec::new("net:request")]
+        );
+        assert_eq!(manifest.exports.len(), 2);
+        assert!(manifest.enable_features(&["offline"]).is_err());
+
+        // Optional exports must not shadow the plugin's own
+        let mut invalid = ManifestBuilder::new("feeds", "1.0.0")
+            .source("main.fsx")
+            .export("fetch")
+            .feature("network", network)
+            .build_unchecked();
+        assert!(invalid.validate().is_err());
+        invalid.features.clear();
+        invalid.features.insert(
+            "bad".into(),
+            PluginFeature {
+                capabilities: vec!["invalid:cap".into()],
+                ..Default::default()
+            },
+        );
+        assert!(invalid.validate().is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_isolation_toml() {
+        let base = r#"
+name = "parser"
+version = "1.0.0"
+api-version = { major = 0, minor = 21, patch = 0 }
+source = "main.fsx"
+"#;
+        let manifest = Manifest::from_toml(base).unwrap();
+        assert_eq!(manifest.isolation, Isolation::InProcess);
+        assert!(!manifest.stateless);
+
+        let manifest = Manifest::from_toml(&format!("{}stateless = true\n", base)).unwrap();
+        assert!(manifest.stateless);
+
+        let manifest = Manifest::from_toml(&format!("{}isolation = \"process\"\n", base)).unwrap();
+        assert_eq!(manifest.isolation, Isolation::Process);
+        assert!(manifest
+            .to_toml()
+            .unwrap()
+            .contains("isolation = \"process\""));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_env_toml() {
+        let base = r#"
+name = "clock"
+version = "1.0.0"
+api-version = { major = 0, minor = 21, patch = 0 }
+source = "main.fsx"
+"#;
+        let manifest = Manifest::from_toml(&format!(
+            "{}capabilities = [\"sys:env\"]\n[env]\nallow = [\"LANG\", \"TZ\"]\n",
+            base
+        ))
+        .unwrap();
+        assert_eq!(manifest.env.allow, vec!["LANG", "TZ"]);
+        manifest.validate().unwrap();
+
+        let manifest = Manifest::from_toml(&format!("{}[env]\nallow = [\"TZ\"]\n", base)).unwrap();
+        assert!(matches!(
+            manifest.validate(),
+            Err(Error::InvalidManifest(_))
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_strict_parse() {
+        let base = r#"name = "strict"
+version = "1.0.0"
+api-version = { major = 0, minor = 21, patch = 0 }
+source = "main.fsx"
+"#;
+        let manifest = Manifest::from_toml_strict(base).unwrap();
+        assert_eq!(manifest.name, "strict");
+        assert!(Manifest::from_json_strict(&manifest.to_json().unwrap()).is_ok());
+
+        let typo = format!("{}capabilites = [\"fs:read\"]\n", base);
+        assert!(Manifest::from_toml(&typo).is_ok());
+        match Manifest::from_toml_strict(&typo) {
+            Err(Error::ManifestSyntax {
+                line,
+                column,
+                reason,
+            }) => {
+                assert_eq!((line, column), (5, 1));
+                assert!(reason.contains("capabilites"));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let nested = base.replace("patch = 0", "patch = 0, pach = 1");
+        assert!(matches!(
+            Manifest::from_toml_strict(&nested),
+            Err(Error::ManifestSyntax { line: 3, .. })
+        ));
+
+        let wrong_type = base.replace("\"1.0.0\"", "1");
+        assert!(matches!(
+            Manifest::from_toml_strict(&wrong_type),
+            Err(Error::ManifestSyntax { line: 2, .. })
+        ));
+
+        // A table that parses back as a bare string ignored all of its keys
+        let input = serde_json::json!({ "capabilities": [{ "cap": "fs:read", "paths": [] }] });
+        let parsed = serde_json::json!({ "capabilities": ["fs:read"] });
+        assert_eq!(
+            unknown_key(&input, &parsed, "").as_deref(),
+            Some("capabilities.0.cap")
+        );
+    }
+
+    #[cfg(feature = "schema")]
+    #[test]
+    fn test_json_schema() {
+        assert!(Manifest::json_schema().is_object());
+    }
 }
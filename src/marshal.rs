@@ -0,0 +1,307 @@
+//! Argument marshalling for plugin calls.
+//!
+//! Calls are executed as Fusabi expressions, so every argument must be
+//! rendered as a literal the engine parses back into the same value. Values
+//! are never spliced in verbatim: strings are quoted and escaped, and
+//! anything that cannot be expressed as a literal is rejected.
+//!
+//! `fusabi_host::Engine` has no value-based call entry point: `execute` is
+//! the only way to run code in it, and the process worker only receives
+//! source as well. Rendering literals is therefore the only way to pass
+//! arguments; the function name is restricted to a dotted identifier so it
+//! cannot carry code of its own.
+
+use fusabi_host::Value;
+
+use crate::error::{Error, Result};
+#[cfg(feature = "serde")]
+use crate::sensitive::zeroize_json;
+use crate::sensitive::zeroize_string;
+
+/// Build a call expression for an exported function.
+pub(crate) fn call_expression(function: &str, args: &[Value]) -> Result<String> {
+    if !is_identifier(function) {
+        return Err(Error::FunctionNotFound(function.to_string()));
+    }
+
+    let mut args = args.iter().map(to_literal).collect::<Result<Vec<_>>>()?;
+    let expression = format!("{}({})", function, args.join(", "));
+    // Arguments may be sensitive; only the expression is handed on
+    args.iter_mut().for_each(zeroize_string);
+    Ok(expression)
+}
+
+/// Render a value as a Fusabi literal.
+pub(crate) fn to_literal(value: &Value) -> Result<String> {
+    let mut out = String::new();
+    match render_value(value, &mut out) {
+        Ok(()) => Ok(out),
+        Err(e) => {
+            // The value may be sensitive, so the partial rendering is wiped
+            zeroize_string(&mut out);
+            Err(e)
+        }
+    }
+}
+
+fn render_value(value: &Value, out: &mut String) -> Result<()> {
+    match value {
+        Value::Null => out.push_str("()"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Int(n) => out.push_str(&n.to_string()),
+        Value::Float(x) if x.is_finite() => {
+            // Debug formatting keeps the fraction, so 2.0 is not read back as an int
+            out.push_str(&format!("{:?}", x));
+        }
+        Value::Float(_) => {
+            return Err(Error::invalid_arguments(
+                "non-finite floats cannot be marshalled",
+            ))
+        }
+        Value::String(s) => push_string(s, out),
+        Value::List(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push_str("; ");
+                }
+                render_value(item, out)?;
+            }
+            out.push(']');
+        }
+        // Records are only built by the engine and serialize as JSON objects
+        #[cfg(feature = "serde")]
+        other => {
+            let mut json = serde_json::to_value(other)
+                .map_err(|e| Error::invalid_arguments(format!("cannot marshal argument: {}", e)))?;
+            let rendered = render_json(&json, out);
+            zeroize_json(&mut json);
+            rendered?;
+        }
+        #[cfg(not(feature = "serde"))]
+        _ => {
+            return Err(Error::invalid_arguments(
+                "records can only be marshalled with the `serde` feature",
+            ))
+        }
+    }
+    Ok(())
+}
+
+/// Convert JSON from outside the engine, such as a worker's answer or an
+/// admin request argument, into a value.
+///
+/// Objects become records: they are rendered as a record literal and
+/// evaluated by an engine without capabilities, since records can only be
+/// built by the engine.
+#[cfg(feature = "serde")]
+pub(crate) fn from_json(value: serde_json::Value) -> Result<Value> {
+    JsonConverter::default().convert(value)
+}
+
+/// Converts JSON values into values, sharing one engine across the records
+/// of a call.
+#[cfg(feature = "serde")]
+#[derive(Default)]
+pub(crate) struct JsonConverter {
+    // Created on the first record, as most values have none
+    engine: Option<fusabi_host::Engine>,
+}
+
+#[cfg(feature = "serde")]
+impl JsonConverter {
+    /// Convert a JSON value, see [`from_json`].
+    pub(crate) fn convert(&mut self, value: serde_json::Value) -> Result<Value> {
+        use serde_json::Value as Json;
+
+        Ok(match value {
+            Json::Null => Value::Null,
+            Json::Bool(b) => Value::Bool(b),
+            Json::Number(n) => match n.as_i64() {
+                Some(i) => Value::Int(i),
+                None => Value::Float(n.as_f64().unwrap_or_default()),
+            },
+            Json::String(s) => Value::String(s),
+            Json::Array(items) => Value::List(
+                items
+                    .into_iter()
+                    .map(|item| self.convert(item))
+                    .collect::<Result<_>>()?,
+            ),
+            Json::Object(_) => {
+                let mut literal = String::new();
+                render_json(&value, &mut literal)?;
+                self.engine()?.execute(&literal).map_err(|e| {
+                    Error::invalid_arguments(format!("cannot convert record: {}", e))
+                })?
+            }
+        })
+    }
+
+    fn engine(&mut self) -> Result<&fusabi_host::Engine> {
+        if self.engine.is_none() {
+            let config = fusabi_host::EngineConfig::default()
+                .with_capabilities(fusabi_host::Capabilities::none());
+            self.engine = Some(fusabi_host::Engine::new(config)?);
+        }
+        Ok(self.engine.as_ref().expect("engine was just created"))
+    }
+}
+
+#[cfg(feature = "serde")]
+fn render_json(value: &serde_json::Value, out: &mut String) -> Result<()> {
+    use serde_json::Value as Json;
+
+    match value {
+        Json::Null => out.push_str("()"),
+        Json::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Json::Number(n) => out.push_str(&n.to_string()),
+        Json::String(s) => push_string(s, out),
+        Json::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push_str("; ");
+                }
+                render_json(item, out)?;
+            }
+            out.push(']');
+        }
+        Json::Object(fields) => {
+            out.push('{');
+            for (i, (key, field)) in fields.iter().enumerate() {
+                if !is_identifier(key) {
+                    return Err(Error::invalid_arguments(format!(
+                        "record field {:?} is not a valid identifier",
+                        key
+                    )));
+                }
+                out.push_str(if i > 0 { "; " } else { " " });
+                out.push_str(key);
+                out.push_str(" = ");
+                render_json(field, out)?;
+            }
+            out.push_str(if fields.is_empty() { "}" } else { " }" });
+        }
+    }
+
+    Ok(())
+}
+
+/// Render a string as a quoted Fusabi literal.
+#[cfg(feature = "serde")]
+pub(crate) fn string_literal(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    push_string(s, &mut out);
+    out
+}
+
+fn push_string(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Whether `name` is an identifier, or a path of identifiers joined by `.`.
+fn is_identifier(name: &str) -> bool {
+    name.split('.').all(|segment| {
+        let mut chars = segment.chars();
+        match chars.next() {
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+            _ => return false,
+        }
+        chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identifier_validation() {
+        assert!(is_identifier("main"));
+        assert!(is_identifier("Module.run_2"));
+        assert!(!is_identifier(""));
+        assert!(!is_identifier("2fast"));
+        assert!(!is_identifier("f() ; evil"));
+        assert!(!is_identifier(".main"));
+        assert!(!is_identifier("main."));
+        assert!(!is_identifier("Module..run"));
+        assert!(!is_identifier("Module.2run"));
+
+        assert!(call_expression("main", &[]).unwrap() == "main()");
+        assert!(matches!(
+            call_expression("run(); cleanup", &[]),
+            Err(Error::FunctionNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_marshal_values() {
+        let args = [
+            Value::Null,
+            Value::Bool(true),
+            Value::Int(-3),
+            Value::Float(2.0),
+            Value::String("say \"hi\"\n".to_string()),
+            Value::List(vec![Value::Int(1), Value::Float(0.5)]),
+        ];
+        assert_eq!(
+            call_expression("main", &args).unwrap(),
+            r#"main((), true, -3, 2.0, "say \"hi\"\n", [1; 0.5])"#
+        );
+        assert!(matches!(
+            to_literal(&Value::Float(f64::NAN)),
+            Err(Error::InvalidArguments(_))
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_from_json_records_in_lists() {
+        let value =
+            from_json(serde_json::json!([{ "a": 1 }, 2, { "b": [{ "c": true }] }])).unwrap();
+        match value {
+            Value::List(items) => {
+                assert_eq!(items.len(), 3);
+                assert!(matches!(items[1], Value::Int(2)));
+            }
+            other => panic!("expected a list, got {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_render_escapes_strings() {
+        let mut out = String::new();
+        render_json(&serde_json::json!("say \"hi\"\n\\"), &mut out).unwrap();
+        assert_eq!(out, r#""say \"hi\"\n\\""#);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_render_collections() {
+        let mut out = String::new();
+        render_json(
+            &serde_json::json!({ "name": "x", "tags": [1, 2.5, true, null] }),
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(out, r#"{ name = "x"; tags = [1; 2.5; true; ()] }"#);
+
+        let mut out = String::new();
+        let result = render_json(&serde_json::json!({ "bad key": 1 }), &mut out);
+        assert!(matches!(result, Err(Error::InvalidArguments(_))));
+    }
+}
@@ -0,0 +1,448 @@
+//! Localized, user-facing error messages.
+
+use std::collections::HashMap;
+
+use crate::error::Error;
+
+/// Built-in English templates, keyed by [`Error::message_key`].
+const ENGLISH: &[(&str, &str)] = &[
+    ("plugin_not_found", "Plugin {plugin} is not installed."),
+    (
+        "plugin_already_loaded",
+        "Plugin {plugin} is already loaded.",
+    ),
+    (
+        "ambiguous_plugin_name",
+        "More than one plugin is called {plugin}: {candidates}. Use the full name.",
+    ),
+    (
+        "invalid_manifest",
+        "The manifest of plugin {plugin} is invalid: {reason}",
+    ),
+    (
+        "missing_manifest_field",
+        "The manifest of plugin {plugin} is missing the {field} field.",
+    ),
+    (
+        "api_version_mismatch",
+        "Plugin {plugin} needs API version {required}, but the host provides {provided}.",
+    ),
+    (
+        "incompatible_bytecode",
+        "The compiled plugin {path} does not work with this engine ({reason}). Recompile it from source.",
+    ),
+    (
+        "missing_capability",
+        "Plugin {plugin} needs permission {capability}.",
+    ),
+    (
+        "undeclared_capability",
+        "Plugin {plugin} used permission {capability} without declaring it.",
+    ),
+    (
+        "capability_denied",
+        "Permission {capability} does not allow plugin {plugin} to access {target}.",
+    ),
+    (
+        "capability_not_granted",
+        "Permission {capability} was not granted to plugin {plugin}: {reason}",
+    ),
+    (
+        "call_denied",
+        "{principal} is not allowed to use {function} of plugin {plugin}.",
+    ),
+    (
+        "missing_host_function",
+        "Plugin {plugin} needs the host function {function}, which the application does not provide.",
+    ),
+    (
+        "missing_export",
+        "Plugin {plugin} lists {export} as an export, but its code does not define it.",
+    ),
+    (
+        "policy_violation",
+        "Plugin {plugin} is not allowed by the host's policy: {reason}",
+    ),
+    (
+        "signature_invalid",
+        "The signature of plugin {plugin} could not be verified: {reason}",
+    ),
+    (
+        "dependency_not_satisfied",
+        "Plugin {plugin} needs {dependency} {version}.",
+    ),
+    ("dependency_cycle", "Plugins depend on each other: {cycle}"),
+    (
+        "initialization_failed",
+        "Plugin {plugin} failed to start: {reason}",
+    ),
+    ("execution_failed", "Plugin {plugin} failed: {reason}"),
+    (
+        "invalid_state",
+        "Plugin {plugin} is {actual}, but must be {expected} for this action.",
+    ),
+    (
+        "function_not_found",
+        "Plugin {plugin} has no function {function}.",
+    ),
+    (
+        "command_not_found",
+        "No plugin provides the command {command}.",
+    ),
+    ("invalid_arguments", "Invalid arguments: {reason}"),
+    (
+        "compilation",
+        "Plugin {plugin} could not be compiled: {reason}",
+    ),
+    ("io", "A file could not be read or written: {reason}"),
+    ("host", "The plugin engine reported an error: {reason}"),
+    (
+        "manifest_parse",
+        "The manifest of plugin {plugin} could not be read: {reason}",
+    ),
+    (
+        "manifest_syntax",
+        "The manifest of plugin {plugin} has an error at line {line}, column {column}: {reason}",
+    ),
+    ("watch", "Plugin files could not be watched: {reason}"),
+    (
+        "isolation",
+        "The process of plugin {plugin} failed: {reason}",
+    ),
+    ("plugin_unloaded", "Plugin {plugin} has been unloaded."),
+    (
+        "reload_failed",
+        "Plugin {plugin} could not be reloaded: {reason}",
+    ),
+    (
+        "rollback_failed",
+        "Plugin {plugin} could not be rolled back: {reason}",
+    ),
+    ("registry", "Plugin registry error: {reason}"),
+    (
+        "quota_exceeded",
+        "Plugin {plugin} exceeded its {resource} limit of {limit}.",
+    ),
+    ("unauthenticated", "Sign-in required: {reason}"),
+    (
+        "permission_denied",
+        "{principal} is not allowed to {operation} plugins.",
+    ),
+    (
+        "rate_limited",
+        "Too many requests. Try again in {retry_after} seconds.",
+    ),
+    (
+        "budget_exceeded",
+        "Plugin {plugin} has used up its {budget} budget. Try again in {retry_after} seconds.",
+    ),
+    (
+        "queue_full",
+        "Plugin {plugin} is busy with {capacity} pending calls. Try again later.",
+    ),
+    (
+        "invalid_config",
+        "The configuration is invalid: {reason}",
+    ),
+    ("network", "A network error occurred: {reason}"),
+    ("offline_mode", "{subsystem} is unavailable while offline."),
+    ("cancelled", "The {operation} operation was cancelled."),
+    (
+        "feature_disabled",
+        "{what} is unavailable because this build does not include the `{feature}` feature.",
+    ),
+];
+
+/// Built-in English templates for errors that name no plugin, keyed by
+/// [`Error::message_key`] with an `.unnamed` suffix.
+const ENGLISH_UNNAMED: &[(&str, &str)] = &[
+    (
+        "invalid_manifest.unnamed",
+        "The plugin manifest is invalid: {reason}",
+    ),
+    (
+        "missing_manifest_field.unnamed",
+        "The plugin manifest is missing the {field} field.",
+    ),
+    (
+        "api_version_mismatch.unnamed",
+        "This plugin needs API version {required}, but the host provides {provided}.",
+    ),
+    (
+        "missing_capability.unnamed",
+        "This plugin needs permission {capability}.",
+    ),
+    (
+        "undeclared_capability.unnamed",
+        "This plugin used permission {capability} without declaring it.",
+    ),
+    (
+        "capability_denied.unnamed",
+        "Permission {capability} does not allow access to {target}.",
+    ),
+    (
+        "capability_not_granted.unnamed",
+        "Permission {capability} was not granted: {reason}",
+    ),
+    (
+        "missing_host_function.unnamed",
+        "This plugin needs the host function {function}, which the application does not provide.",
+    ),
+    (
+        "missing_export.unnamed",
+        "This plugin lists {export} as an export, but its code does not define it.",
+    ),
+    (
+        "signature_invalid.unnamed",
+        "The plugin's signature could not be verified: {reason}",
+    ),
+    (
+        "dependency_not_satisfied.unnamed",
+        "This plugin needs {dependency} {version}.",
+    ),
+    (
+        "initialization_failed.unnamed",
+        "The plugin failed to start: {reason}",
+    ),
+    ("execution_failed.unnamed", "The plugin failed: {reason}"),
+    (
+        "invalid_state.unnamed",
+        "The plugin is {actual}, but must be {expected} for this action.",
+    ),
+    (
+        "function_not_found.unnamed",
+        "The plugin has no function {function}.",
+    ),
+    (
+        "compilation.unnamed",
+        "The plugin could not be compiled: {reason}",
+    ),
+    (
+        "manifest_parse.unnamed",
+        "The plugin manifest could not be read: {reason}",
+    ),
+    (
+        "manifest_syntax.unnamed",
+        "The plugin manifest has an error at line {line}, column {column}: {reason}",
+    ),
+    ("isolation.unnamed", "The plugin process failed: {reason}"),
+    ("plugin_unloaded.unnamed", "The plugin has been unloaded."),
+    (
+        "reload_failed.unnamed",
+        "The plugin could not be reloaded: {reason}",
+    ),
+    (
+        "rollback_failed.unnamed",
+        "The plugin could not be rolled back: {reason}",
+    ),
+];
+
+/// Catalog of message templates per locale.
+///
+/// Templates reference error parameters as `{name}`. Rendering looks up
+/// the exact locale, then its language (`de-AT` falls back to `de`), then
+/// the default locale, and finally the error's `Display` output.
+///
+/// Errors raised while working on a plugin carry its name as `{plugin}`.
+/// For errors that name no plugin, a template stored under the message key
+/// with an `.unnamed` suffix is preferred in each locale.
+#[derive(Debug, Clone)]
+pub struct MessageCatalog {
+    default_locale: String,
+    templates: HashMap<String, HashMap<String, String>>,
+}
+
+impl MessageCatalog {
+    /// Create a catalog with the built-in English messages.
+    pub fn new() -> Self {
+        let mut catalog = Self::empty();
+        for (key, template) in ENGLISH.iter().chain(ENGLISH_UNNAMED) {
+            catalog.insert("en", *key, *template);
+        }
+        catalog
+    }
+
+    /// Create a catalog without any messages.
+    pub fn empty() -> Self {
+        Self {
+            default_locale: "en".to_string(),
+            templates: HashMap::new(),
+        }
+    }
+
+    /// Set the locale used when no template exists for the requested one.
+    pub fn with_default_locale(mut self, locale: impl AsRef<str>) -> Self {
+        self.default_locale = normalize(locale.as_ref());
+        self
+    }
+
+    /// Add a template.
+    pub fn with_message(
+        mut self,
+        locale: impl AsRef<str>,
+        key: impl Into<String>,
+        template: impl Into<String>,
+    ) -> Self {
+        self.insert(locale, key, template);
+        self
+    }
+
+    /// Add or replace a template.
+    pub fn insert(
+        &mut self,
+        locale: impl AsRef<str>,
+        key: impl Into<String>,
+        template: impl Into<String>,
+    ) {
+        self.templates
+            .entry(normalize(locale.as_ref()))
+            .or_default()
+            .insert(key.into(), template.into());
+    }
+
+    /// Get the default locale.
+    pub fn default_locale(&self) -> &str {
+        &self.default_locale
+    }
+
+    /// List locales with at least one template, sorted.
+    pub fn locales(&self) -> Vec<String> {
+        let mut locales: Vec<_> = self.templates.keys().cloned().collect();
+        locales.sort();
+        locales
+    }
+
+    /// Find the template for a message key in a locale, with fallbacks.
+    pub fn template(&self, key: &str, locale: &str) -> Option<&str> {
+        self.find(&[key], locale)
+    }
+
+    /// Find the first of `keys` defined in a locale, with fallbacks.
+    fn find(&self, keys: &[&str], locale: &str) -> Option<&str> {
+        let locale = normalize(locale);
+        let language = locale.split('-').next().unwrap_or_default();
+
+        for candidate in [locale.as_str(), language, self.default_locale.as_str()] {
+            let Some(templates) = self.templates.get(candidate) else {
+                continue;
+            };
+            if let Some(template) = keys.iter().find_map(|key| templates.get(*key)) {
+                return Some(template);
+            }
+        }
+        None
+    }
+
+    /// Render a user-facing message for an error.
+    pub fn render(&self, error: &Error, locale: &str) -> String {
+        let key = error.message_key();
+        let params = error.message_params();
+        let template = if params.iter().any(|(name, _)| *name == "plugin") {
+            self.find(&[key], locale)
+        } else {
+            self.find(&[&format!("{}.unnamed", key), key], locale)
+        };
+        match template {
+            Some(template) => substitute(template, &params),
+            None => error.to_string(),
+        }
+    }
+}
+
+impl Default for MessageCatalog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Normalize a locale tag, e.g. `pt_BR` to `pt-br`.
+fn normalize(locale: &str) -> String {
+    locale.trim().replace('_', "-").to_ascii_lowercase()
+}
+
+/// Replace `{name}` placeholders; unknown placeholders are kept as is.
+fn substitute(template: &str, params: &[(&str, String)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let value = after.find('}').and_then(|end| {
+            let name = &after[..end];
+            params
+                .iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, v)| (v, end))
+        });
+        match value {
+            Some((value, end)) => {
+                out.push_str(value);
+                rest = &after[end + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::PluginOperation;
+
+    #[test]
+    fn test_render_with_fallbacks() {
+        let catalog = MessageCatalog::new().with_message(
+            "de",
+            "missing_capability",
+            "Dieses Plugin benötigt die Berechtigung {capability}.",
+        );
+        let err = Error::missing_capability("fs:write");
+
+        assert_eq!(
+            catalog.render(&err, "en"),
+            "This plugin needs permission fs:write."
+        );
+        assert_eq!(
+            catalog.render(
+                &Error::missing_capability("fs:write").in_plugin(
+                    "notes",
+                    PluginOperation::Load,
+                    None
+                ),
+                "en"
+            ),
+            "Plugin notes needs permission fs:write."
+        );
+        assert_eq!(
+            catalog.render(&err, "de_AT"),
+            "Dieses Plugin benötigt die Berechtigung fs:write."
+        );
+        assert_eq!(
+            catalog.render(&err, "fr"),
+            "This plugin needs permission fs:write."
+        );
+
+        let err = Error::quota_exceeded("notes", "storage", 1024);
+        assert_eq!(
+            catalog.render(&err, "en-US"),
+            "Plugin notes exceeded its storage limit of 1024."
+        );
+
+        let empty = MessageCatalog::empty();
+        assert_eq!(empty.render(&err, "en"), err.to_string());
+    }
+
+    #[test]
+    fn test_substitute() {
+        let params = [("a", "1".to_string())];
+        assert_eq!(substitute("{a}+{a}={b}", &params), "1+1={b}");
+        assert_eq!(substitute("{unterminated", &params), "{unterminated");
+    }
+}
@@ -1,13 +1,25 @@
 //! Prometheus metrics integration for plugin runtime.
 
-use prometheus::{Counter, Histogram, Registry};
+use std::sync::{Arc, Weak};
+
+use parking_lot::RwLock;
+use prometheus::core::{Collector, Desc};
+use prometheus::proto::MetricFamily;
+#[cfg(feature = "watch")]
+use prometheus::IntGauge;
+use prometheus::{CounterVec, HistogramOpts, HistogramVec, IntGaugeVec, Opts, Registry};
+
+use crate::registry::{PluginRegistry, RegistryStats};
+
+#[cfg(feature = "watch")]
+use crate::watcher::WatcherStats;
 
 /// Configuration for plugin metrics collection.
 #[derive(Debug, Clone)]
 pub struct MetricsConfig {
     /// Metric prefix for all plugin metrics.
     pub prefix: String,
-    /// Whether to collect detailed timing histograms.
+    /// Whether call durations are labeled by function as well as plugin.
     pub detailed_timing: bool,
 }
 
@@ -40,14 +52,46 @@ impl MetricsConfig {
 }
 
 /// Plugin metrics collector.
+///
+/// Counters and histograms are labeled by `plugin`; call durations are
+/// additionally labeled by `function`.
 pub struct PluginMetrics {
     config: MetricsConfig,
     registry: Registry,
-    plugins_loaded: Counter,
-    plugins_unloaded: Counter,
-    plugin_errors: Counter,
-    load_duration: Histogram,
-    call_duration: Histogram,
+    plugins_loaded: CounterVec,
+    plugins_unloaded: CounterVec,
+    plugin_errors: CounterVec,
+    load_duration: HistogramVec,
+    compile_duration: HistogramVec,
+    call_duration: HistogramVec,
+    plugins_by_state: IntGaugeVec,
+    tracked: Arc<RwLock<Option<Weak<PluginRegistry>>>>,
+    #[cfg(feature = "watch")]
+    watcher_events: IntGaugeVec,
+    #[cfg(feature = "watch")]
+    watcher_debounce_entries: IntGauge,
+}
+
+/// Publishes plugin counts by lifecycle state, read from the tracked
+/// registry whenever metrics are gathered, so the counts never lag behind
+/// plugins that were unloaded or changed state.
+struct StateCollector {
+    gauge: IntGaugeVec,
+    tracked: Arc<RwLock<Option<Weak<PluginRegistry>>>>,
+}
+
+impl Collector for StateCollector {
+    fn desc(&self) -> Vec<&Desc> {
+        self.gauge.desc()
+    }
+
+    fn collect(&self) -> Vec<MetricFamily> {
+        let registry = self.tracked.read().as_ref().and_then(Weak::upgrade);
+        if let Some(registry) = registry {
+            set_state_gauges(&self.gauge, &registry.stats());
+        }
+        self.gauge.collect()
+    }
 }
 
 impl PluginMetrics {
@@ -55,39 +99,86 @@ impl PluginMetrics {
     pub fn new(config: MetricsConfig) -> Self {
         let registry = Registry::new();
 
-        let plugins_loaded = Counter::new(
-            format!("{}_loaded_total", config.prefix),
-            "Total number of plugins loaded",
+        let plugins_loaded = CounterVec::new(
+            Opts::new(
+                format!("{}_loaded_total", config.prefix),
+                "Total number of plugins loaded",
+            ),
+            &["plugin"],
         )
         .unwrap();
 
-        let plugins_unloaded = Counter::new(
-            format!("{}_unloaded_total", config.prefix),
-            "Total number of plugins unloaded",
+        let plugins_unloaded = CounterVec::new(
+            Opts::new(
+                format!("{}_unloaded_total", config.prefix),
+                "Total number of plugins unloaded",
+            ),
+            &["plugin"],
         )
         .unwrap();
 
-        let plugin_errors = Counter::new(
-            format!("{}_errors_total", config.prefix),
-            "Total number of plugin errors",
+        let plugin_errors = CounterVec::new(
+            Opts::new(
+                format!("{}_errors_total", config.prefix),
+                "Total number of plugin errors",
+            ),
+            &["plugin"],
         )
         .unwrap();
 
-        let load_duration = Histogram::with_opts(
-            prometheus::HistogramOpts::new(
+        let load_duration = HistogramVec::new(
+            HistogramOpts::new(
                 format!("{}_load_duration_seconds", config.prefix),
                 "Plugin load duration in seconds",
             )
             .buckets(vec![0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0]),
+            &["plugin"],
+        )
+        .unwrap();
+
+        let compile_duration = HistogramVec::new(
+            HistogramOpts::new(
+                format!("{}_compile_duration_seconds", config.prefix),
+                "Plugin source compile duration in seconds",
+            )
+            .buckets(vec![0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0]),
+            &["plugin"],
         )
         .unwrap();
 
-        let call_duration = Histogram::with_opts(
-            prometheus::HistogramOpts::new(
+        let call_duration = HistogramVec::new(
+            HistogramOpts::new(
                 format!("{}_call_duration_seconds", config.prefix),
                 "Plugin call duration in seconds",
             )
             .buckets(vec![0.0001, 0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5]),
+            &["plugin", "function"],
+        )
+        .unwrap();
+
+        let plugins_by_state = IntGaugeVec::new(
+            Opts::new(
+                format!("{}_plugins", config.prefix),
+                "Registered plugins by lifecycle state",
+            ),
+            &["state"],
+        )
+        .unwrap();
+
+        #[cfg(feature = "watch")]
+        let watcher_events = IntGaugeVec::new(
+            Opts::new(
+                format!("{}_watcher_events", config.prefix),
+                "Watcher events by processing stage",
+            ),
+            &["stage"],
+        )
+        .unwrap();
+
+        #[cfg(feature = "watch")]
+        let watcher_debounce_entries = IntGauge::new(
+            format!("{}_watcher_debounce_entries", config.prefix),
+            "Paths currently tracked for watcher debouncing",
         )
         .unwrap();
 
@@ -95,7 +186,22 @@ impl PluginMetrics {
         registry.register(Box::new(plugins_unloaded.clone())).ok();
         registry.register(Box::new(plugin_errors.clone())).ok();
         registry.register(Box::new(load_duration.clone())).ok();
+        registry.register(Box::new(compile_duration.clone())).ok();
         registry.register(Box::new(call_duration.clone())).ok();
+        let tracked = Arc::new(RwLock::new(None));
+        registry
+            .register(Box::new(StateCollector {
+                gauge: plugins_by_state.clone(),
+                tracked: tracked.clone(),
+            }))
+            .ok();
+        #[cfg(feature = "watch")]
+        {
+            registry.register(Box::new(watcher_events.clone())).ok();
+            registry
+                .register(Box::new(watcher_debounce_entries.clone()))
+                .ok();
+        }
 
         Self {
             config,
@@ -104,7 +210,14 @@ impl PluginMetrics {
             plugins_unloaded,
             plugin_errors,
             load_duration,
+            compile_duration,
             call_duration,
+            plugins_by_state,
+            tracked,
+            #[cfg(feature = "watch")]
+            watcher_events,
+            #[cfg(feature = "watch")]
+            watcher_debounce_entries,
         }
     }
 
@@ -119,42 +232,122 @@ impl PluginMetrics {
     }
 
     /// Record a plugin load event.
-    pub fn record_load(&self, duration_secs: f64) {
-        self.plugins_loaded.inc();
-        self.load_duration.observe(duration_secs);
+    pub fn record_load(&self, plugin: &str, duration_secs: f64) {
+        self.plugins_loaded.with_label_values(&[plugin]).inc();
+        self.load_duration
+            .with_label_values(&[plugin])
+            .observe(duration_secs);
     }
 
     /// Record a plugin unload event.
-    pub fn record_unload(&self) {
-        self.plugins_unloaded.inc();
+    pub fn record_unload(&self, plugin: &str) {
+        self.plugins_unloaded.with_label_values(&[plugin]).inc();
     }
 
     /// Record a plugin error.
-    pub fn record_error(&self) {
-        self.plugin_errors.inc();
+    pub fn record_error(&self, plugin: &str) {
+        self.plugin_errors.with_label_values(&[plugin]).inc();
+    }
+
+    /// Record the compilation of a plugin's source.
+    pub fn record_compile(&self, plugin: &str, duration_secs: f64) {
+        self.compile_duration
+            .with_label_values(&[plugin])
+            .observe(duration_secs);
     }
 
     /// Record a plugin function call.
-    pub fn record_call(&self, duration_secs: f64) {
-        self.call_duration.observe(duration_secs);
+    ///
+    /// Without detailed timing the `function` label is left empty.
+    pub fn record_call(&self, plugin: &str, function: &str, duration_secs: f64) {
+        let function = if self.config.detailed_timing {
+            function
+        } else {
+            ""
+        };
+        self.call_duration
+            .with_label_values(&[plugin, function])
+            .observe(duration_secs);
+    }
+
+    /// Publish plugin counts by lifecycle state.
+    ///
+    /// Counts of a registry tracked with
+    /// [`track_registry`](Self::track_registry) replace these when metrics
+    /// are gathered.
+    pub fn record_registry_stats(&self, stats: &RegistryStats) {
+        set_state_gauges(&self.plugins_by_state, stats);
+    }
+
+    /// Publish the plugin counts by lifecycle state of `registry` each time
+    /// metrics are gathered.
+    ///
+    /// The runtime tracks its registry when created with metrics.
+    pub fn track_registry(&self, registry: &Arc<PluginRegistry>) {
+        *self.tracked.write() = Some(Arc::downgrade(registry));
+    }
+
+    /// Publish a snapshot of watcher counters.
+    #[cfg(feature = "watch")]
+    pub fn record_watcher_stats(&self, stats: &WatcherStats) {
+        for (stage, value) in [
+            ("received", stats.events_received),
+            ("filtered", stats.events_filtered),
+            ("debounced", stats.events_debounced),
+            ("delivered", stats.events_delivered),
+            ("reloads", stats.reloads_triggered),
+            ("dropped", stats.events_dropped),
+        ] {
+            self.watcher_events
+                .with_label_values(&[stage])
+                .set(value as i64);
+        }
+        self.watcher_debounce_entries
+            .set(stats.debounce_entries as i64);
     }
 
     /// Get the total number of plugins loaded.
     pub fn plugins_loaded_total(&self) -> u64 {
-        self.plugins_loaded.get() as u64
+        sum_counters(&self.plugins_loaded)
     }
 
     /// Get the total number of plugins unloaded.
     pub fn plugins_unloaded_total(&self) -> u64 {
-        self.plugins_unloaded.get() as u64
+        sum_counters(&self.plugins_unloaded)
     }
 
     /// Get the total number of plugin errors.
     pub fn plugin_errors_total(&self) -> u64 {
-        self.plugin_errors.get() as u64
+        sum_counters(&self.plugin_errors)
+    }
+
+    /// Get the number of errors recorded for one plugin.
+    pub fn plugin_errors(&self, plugin: &str) -> u64 {
+        self.plugin_errors.with_label_values(&[plugin]).get() as u64
     }
 }
 
+fn set_state_gauges(gauge: &IntGaugeVec, stats: &RegistryStats) {
+    for (state, value) in [
+        ("running", stats.running),
+        ("stopped", stats.stopped),
+        ("error", stats.error),
+        ("unloaded", stats.unloaded),
+    ] {
+        gauge.with_label_values(&[state]).set(value as i64);
+    }
+}
+
+/// Sum a counter across all label values.
+fn sum_counters(counter: &CounterVec) -> u64 {
+    counter
+        .collect()
+        .iter()
+        .flat_map(|family| family.get_metric())
+        .map(|metric| metric.get_counter().get_value())
+        .sum::<f64>() as u64
+}
+
 impl std::fmt::Debug for PluginMetrics {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("PluginMetrics")
@@ -184,14 +377,97 @@ mod tests {
     fn test_metrics_recording() {
         let metrics = PluginMetrics::new(MetricsConfig::default());
 
-        metrics.record_load(0.1);
-        metrics.record_load(0.2);
-        metrics.record_unload();
-        metrics.record_error();
-        metrics.record_call(0.01);
+        metrics.record_load("a", 0.1);
+        metrics.record_load("b", 0.2);
+        metrics.record_unload("a");
+        metrics.record_error("b");
+        metrics.record_call("b", "run", 0.01);
 
         assert_eq!(metrics.plugins_loaded_total(), 2);
         assert_eq!(metrics.plugins_unloaded_total(), 1);
         assert_eq!(metrics.plugin_errors_total(), 1);
+        assert_eq!(metrics.plugin_errors("a"), 0);
+        assert_eq!(metrics.plugin_errors("b"), 1);
+        assert_eq!(
+            metrics
+                .call_duration
+                .with_label_values(&["b", "run"])
+                .get_sample_count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_record_registry_stats() {
+        let metrics = PluginMetrics::new(MetricsConfig::default());
+        metrics.record_registry_stats(&RegistryStats {
+            total: 3,
+            running: 2,
+            error: 1,
+            ..Default::default()
+        });
+
+        let running = metrics.plugins_by_state.with_label_values(&["running"]);
+        assert_eq!(running.get(), 2);
+        assert_eq!(
+            metrics.plugins_by_state.with_label_values(&["error"]).get(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_state_gauge_tracks_registry() {
+        use crate::builtin::BuiltinPlugin;
+        use crate::manifest::Manifest;
+        use crate::plugin::{Plugin, PluginHandle};
+
+        fn gathered(metrics: &PluginMetrics, state: &str) -> i64 {
+            metrics
+                .registry()
+                .gather()
+                .iter()
+                .filter(|family| family.get_name() == "fusabi_plugin_plugins")
+                .flat_map(|family| family.get_metric())
+                .find(|metric| metric.get_label().iter().any(|l| l.get_value() == state))
+                .map_or(0, |metric| metric.get_gauge().get_value() as i64)
+        }
+
+        let metrics = PluginMetrics::new(MetricsConfig::default());
+        let registry = Arc::new(PluginRegistry::default_config());
+        metrics.track_registry(&registry);
+
+        let plugin = Plugin::new(Manifest::new("clock", "1.0.0"));
+        plugin.set_builtin(BuiltinPlugin::new("1.0.0"));
+        plugin
+            .initialize(fusabi_host::EngineConfig::default())
+            .unwrap();
+        plugin.start().unwrap();
+        registry.register(PluginHandle::new(plugin)).unwrap();
+        assert_eq!(gathered(&metrics, "running"), 1);
+
+        // Unloading outside the runtime does not leave the count behind
+        registry.unregister("clock").unwrap();
+        assert_eq!(gathered(&metrics, "running"), 0);
+    }
+
+    #[cfg(feature = "watch")]
+    #[test]
+    fn test_record_watcher_stats() {
+        let metrics = PluginMetrics::new(MetricsConfig::default());
+        metrics.record_watcher_stats(&WatcherStats {
+            events_received: 5,
+            events_delivered: 3,
+            debounce_entries: 2,
+            ..Default::default()
+        });
+
+        assert_eq!(
+            metrics
+                .watcher_events
+                .with_label_values(&["received"])
+                .get(),
+            5
+        );
+        assert_eq!(metrics.watcher_debounce_entries.get(), 2);
     }
 }
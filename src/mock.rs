@@ -0,0 +1,298 @@
+//! In-memory runtime for testing host applications.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use fusabi_host::{EngineConfig, Value};
+use parking_lot::{Mutex, RwLock};
+
+use crate::api::RuntimeApi;
+use crate::builtin::BuiltinPlugin;
+use crate::error::{Error, Result};
+use crate::lifecycle::LifecycleState;
+use crate::manifest::Manifest;
+use crate::origin::PluginOrigin;
+use crate::plugin::{Plugin, PluginHandle};
+use crate::registry::RegistryStats;
+
+type MockFunction = Arc<dyn Fn(&[Value]) -> Result<Value> + Send + Sync>;
+type MockFunctions = Arc<RwLock<HashMap<String, MockFunction>>>;
+
+/// A call recorded by [`MockRuntime`].
+#[derive(Debug, Clone)]
+pub struct MockCall {
+    /// Plugin name.
+    pub plugin: String,
+    /// Function name.
+    pub function: String,
+    /// Call arguments.
+    pub args: Vec<Value>,
+}
+
+struct MockPlugin {
+    handle: PluginHandle,
+    functions: MockFunctions,
+    loaded: bool,
+}
+
+/// [`RuntimeApi`] implementation whose plugins are Rust closures.
+///
+/// Plugins are registered at test time and start out loaded. Loading a path
+/// re-activates the plugin named by its file stem, so code that loads plugins
+/// from disk can be tested without compiling fixtures.
+///
+/// Each plugin is served by a builtin backend, so calls made through its
+/// [`PluginHandle`] run the closures too and are recorded like calls made
+/// through the runtime. Functions added later are visible through handles
+/// already handed out.
+///
+/// ```rust,ignore
+/// let runtime = MockRuntime::new()
+///     .with_function("greeter", "greet", |args| Ok(args[0].clone()));
+/// let runtime: Arc<dyn RuntimeApi> = Arc::new(runtime);
+/// ```
+#[derive(Default)]
+pub struct MockRuntime {
+    plugins: RwLock<HashMap<String, MockPlugin>>,
+    calls: Arc<Mutex<Vec<MockCall>>>,
+}
+
+impl MockRuntime {
+    /// Create an empty mock runtime.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a function, creating its plugin if needed.
+    pub fn with_function<F>(self, plugin: &str, function: &str, f: F) -> Self
+    where
+        F: Fn(&[Value]) -> Result<Value> + Send + Sync + 'static,
+    {
+        self.add_function(plugin, function, f);
+        self
+    }
+
+    /// Register a function, creating its plugin if needed.
+    pub fn add_function<F>(&self, plugin: &str, function: &str, f: F)
+    where
+        F: Fn(&[Value]) -> Result<Value> + Send + Sync + 'static,
+    {
+        let mut plugins = self.plugins.write();
+        let entry = plugins
+            .entry(plugin.to_string())
+            .or_insert_with(|| self.mock_plugin(plugin));
+
+        entry
+            .functions
+            .write()
+            .insert(function.to_string(), Arc::new(f));
+        entry.handle.inner().add_export(function);
+    }
+
+    /// Create a running plugin whose backend calls its registered closures.
+    fn mock_plugin(&self, name: &str) -> MockPlugin {
+        let functions = MockFunctions::default();
+        let dispatch = {
+            let plugin = name.to_string();
+            let functions = functions.clone();
+            let calls = self.calls.clone();
+            move |function: &str, args: &[Value]| {
+                let f = functions
+                    .read()
+                    .get(function)
+                    .cloned()
+                    .ok_or_else(|| Error::FunctionNotFound(function.to_string()))?;
+
+                calls.lock().push(MockCall {
+                    plugin: plugin.clone(),
+                    function: function.to_string(),
+                    args: args.to_vec(),
+                });
+
+                // Run outside the locks so closures may call back into the runtime
+                f(args)
+            }
+        };
+
+        let plugin = Plugin::new(Manifest::new(name, "0.0.0"));
+        plugin.set_origin(PluginOrigin::Builtin);
+        plugin.set_builtin(BuiltinPlugin::dispatching("0.0.0", Arc::new(dispatch)));
+        plugin
+            .initialize(EngineConfig::default())
+            .and_then(|()| plugin.start())
+            .expect("builtin plugins without init() always start");
+
+        MockPlugin {
+            handle: PluginHandle::new(plugin),
+            functions,
+            loaded: true,
+        }
+    }
+
+    /// Get every recorded call in order.
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.calls.lock().clone()
+    }
+
+    /// Count the recorded calls of a function.
+    pub fn call_count(&self, plugin: &str, function: &str) -> usize {
+        self.calls
+            .lock()
+            .iter()
+            .filter(|c| c.plugin == plugin && c.function == function)
+            .count()
+    }
+
+    /// Forget recorded calls.
+    pub fn clear_calls(&self) {
+        self.calls.lock().clear();
+    }
+}
+
+impl RuntimeApi for MockRuntime {
+    fn load(&self, path: &Path) -> Result<PluginHandle> {
+        let name = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .ok_or_else(|| Error::invalid_arguments(format!("invalid path: {}", path.display())))?;
+
+        let mut plugins = self.plugins.write();
+        let plugin = plugins
+            .get_mut(name)
+            .ok_or_else(|| Error::plugin_not_found(name))?;
+        plugin.loaded = true;
+        plugin.handle.inner().set_state(LifecycleState::Running);
+        Ok(plugin.handle.clone())
+    }
+
+    fn unload(&self, name: &str) -> Result<()> {
+        let mut plugins = self.plugins.write();
+        match plugins.get_mut(name) {
+            Some(plugin) if plugin.loaded => {
+                plugin.loaded = false;
+                plugin.handle.inner().set_state(LifecycleState::Unloaded);
+                Ok(())
+            }
+            _ => Err(Error::plugin_not_found(name)),
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<PluginHandle> {
+        self.plugins
+            .read()
+            .get(name)
+            .filter(|p| p.loaded)
+            .map(|p| p.handle.clone())
+    }
+
+    fn plugin_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .plugins
+            .read()
+            .iter()
+            .filter(|(_, p)| p.loaded)
+            .map(|(name, _)| name.clone())
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn call(&self, plugin: &str, function: &str, args: &[Value]) -> Result<Value> {
+        let handle = self
+            .get(plugin)
+            .ok_or_else(|| Error::plugin_not_found(plugin))?;
+        handle.call(function, args)
+    }
+
+    fn stats(&self) -> RegistryStats {
+        let plugins = self.plugins.read();
+        let loaded = plugins.values().filter(|p| p.loaded).count();
+
+        RegistryStats {
+            total: loaded,
+            running: loaded,
+            unloaded: plugins.len() - loaded,
+            ..RegistryStats::default()
+        }
+    }
+}
+
+impl std::fmt::Debug for MockRuntime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MockRuntime")
+            .field("plugins", &self.plugin_names())
+            .field("calls", &self.calls.lock().len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_calls() {
+        let runtime = MockRuntime::new()
+            .with_function("greeter", "greet", |args| match args {
+                [Value::String(name)] => Ok(Value::String(format!("hello {}", name))),
+                _ => Err(Error::invalid_arguments("expected a name")),
+            })
+            .with_function("greeter", "fail", |_| Err(Error::execution_failed("boom")));
+        let api: &dyn RuntimeApi = &runtime;
+
+        let result = api
+            .call("greeter", "greet", &[Value::String("fusabi".into())])
+            .unwrap();
+        assert!(matches!(result, Value::String(ref s) if s == "hello fusabi"));
+        assert!(api.call("greeter", "fail", &[]).is_err());
+        assert!(matches!(
+            api.call("greeter", "missing", &[]),
+            Err(Error::FunctionNotFound(_))
+        ));
+
+        assert_eq!(runtime.call_count("greeter", "greet"), 1);
+        assert_eq!(runtime.calls().len(), 2);
+        assert!(api.get("greeter").unwrap().has_export("greet"));
+    }
+
+    #[test]
+    fn test_mock_handles() {
+        let runtime = MockRuntime::new().with_function("greeter", "greet", |_| Ok(Value::Int(1)));
+        let handle = runtime.get("greeter").unwrap();
+
+        // Handles call the closures and record the calls
+        assert!(matches!(handle.call("greet", &[]), Ok(Value::Int(1))));
+        assert_eq!(runtime.call_count("greeter", "greet"), 1);
+
+        // Functions added later reach handles already handed out
+        runtime.add_function("greeter", "wave", |_| Ok(Value::Int(2)));
+        assert!(handle.has_export("wave"));
+        assert!(matches!(handle.call("wave", &[]), Ok(Value::Int(2))));
+        assert!(matches!(
+            runtime.call("greeter", "wave", &[]),
+            Ok(Value::Int(2))
+        ));
+        assert_eq!(runtime.call_count("greeter", "wave"), 2);
+
+        runtime.unload("greeter").unwrap();
+        assert!(handle.call("greet", &[]).is_err());
+    }
+
+    #[test]
+    fn test_mock_load_unload() {
+        let runtime = MockRuntime::new().with_function("greeter", "greet", |_| Ok(Value::Null));
+
+        runtime.unload("greeter").unwrap();
+        assert!(runtime.get("greeter").is_none());
+        assert_eq!(runtime.stats().unloaded, 1);
+        assert!(runtime.call("greeter", "greet", &[]).is_err());
+
+        runtime.load(Path::new("plugins/greeter.fsx")).unwrap();
+        assert_eq!(runtime.plugin_names(), vec!["greeter".to_string()]);
+        assert!(matches!(
+            runtime.load(Path::new("plugins/other.fsx")),
+            Err(Error::PluginNotFound(_))
+        ));
+    }
+}
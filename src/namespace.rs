@@ -0,0 +1,48 @@
+//! Namespaced plugin names such as `acme/http-logger`.
+
+use crate::error::{Error, Result};
+
+/// Separator between a plugin's namespace and its local name.
+pub(crate) const SEPARATOR: char = '/';
+
+/// Split a plugin name into its namespace, if any, and its local name.
+pub(crate) fn split(name: &str) -> (Option<&str>, &str) {
+    match name.split_once(SEPARATOR) {
+        Some((namespace, local)) => (Some(namespace), local),
+        None => (None, name),
+    }
+}
+
+/// Check a name is flat or a single `namespace/name` pair.
+pub(crate) fn validate(name: &str) -> Result<()> {
+    let (namespace, local) = split(name);
+    let valid = match namespace {
+        Some(namespace) => !namespace.is_empty() && !local.is_empty() && !local.contains(SEPARATOR),
+        None => true,
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::invalid_manifest(format!(
+            "invalid plugin name {:?}: expected `name` or `namespace/name`",
+            name
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_and_validate() {
+        assert_eq!(split("acme/http-logger"), (Some("acme"), "http-logger"));
+        assert_eq!(split("http-logger"), (None, "http-logger"));
+
+        assert!(validate("acme/http-logger").is_ok());
+        assert!(validate("http-logger").is_ok());
+        for name in ["/http-logger", "acme/", "acme/http/logger"] {
+            assert!(validate(name).is_err(), "{}", name);
+        }
+    }
+}
@@ -0,0 +1,307 @@
+//! Network settings shared by network-touching subsystems.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::config_error::ConfigError;
+use crate::error::{Error, Result};
+
+/// Shared switch that disables network access for air-gapped deployments.
+///
+/// Clones share the same state, so flipping the switch on a runtime affects
+/// every subsystem created from it. Subsystems call [`ensure_online`] before
+/// touching the network.
+///
+/// [`ensure_online`]: OfflineSwitch::ensure_online
+#[derive(Debug, Clone, Default)]
+pub struct OfflineSwitch {
+    offline: Arc<AtomicBool>,
+}
+
+impl OfflineSwitch {
+    /// Create a switch in the given state.
+    pub fn new(offline: bool) -> Self {
+        Self {
+            offline: Arc::new(AtomicBool::new(offline)),
+        }
+    }
+
+    /// Check if network access is disabled.
+    pub fn is_offline(&self) -> bool {
+        self.offline.load(Ordering::SeqCst)
+    }
+
+    /// Enable or disable offline mode.
+    pub fn set_offline(&self, offline: bool) {
+        self.offline.store(offline, Ordering::SeqCst);
+    }
+
+    /// Fail with [`Error::OfflineMode`] if network access is disabled.
+    pub fn ensure_online(&self, subsystem: &str) -> Result<()> {
+        if self.is_offline() {
+            return Err(Error::offline_mode(subsystem));
+        }
+        Ok(())
+    }
+}
+
+/// HTTP(S) settings shared by every network-touching subsystem.
+///
+/// The runtime does no networking itself; hosts read this configuration when
+/// building the clients behind update sources, index clients and webhooks so
+/// proxies and private CAs are configured in one place.
+#[derive(Debug, Clone)]
+pub struct NetworkConfig {
+    /// Proxy URL for all requests, e.g. `http://proxy.corp:3128`.
+    pub proxy: Option<String>,
+    /// Hosts that bypass the proxy. A leading `.` matches subdomains.
+    pub no_proxy: Vec<String>,
+    /// PEM bundle of additional trusted CA certificates.
+    pub ca_bundle: Option<PathBuf>,
+    /// Timeout for establishing a connection.
+    pub connect_timeout: Duration,
+    /// Timeout for a whole request.
+    pub request_timeout: Duration,
+    /// Number of retries after a failed request.
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for each further attempt.
+    pub retry_backoff: Duration,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            proxy: None,
+            no_proxy: Vec::new(),
+            ca_bundle: None,
+            connect_timeout: Duration::from_secs(10),
+            request_timeout: Duration::from_secs(30),
+            max_retries: 3,
+            retry_backoff: Duration::from_millis(500),
+        }
+    }
+}
+
+impl NetworkConfig {
+    /// Create a new network configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a configuration from the `HTTPS_PROXY`, `HTTP_PROXY` and
+    /// `NO_PROXY` environment variables.
+    pub fn from_env() -> Self {
+        let var = |name: &str| {
+            std::env::var(name)
+                .or_else(|_| std::env::var(name.to_lowercase()))
+                .ok()
+                .filter(|v| !v.is_empty())
+        };
+
+        Self {
+            proxy: var("HTTPS_PROXY").or_else(|| var("HTTP_PROXY")),
+            no_proxy: var("NO_PROXY")
+                .map(|hosts| {
+                    hosts
+                        .split(',')
+                        .map(|h| h.trim().to_string())
+                        .filter(|h| !h.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            ..Self::default()
+        }
+    }
+
+    /// Set the proxy URL.
+    pub fn with_proxy(mut self, proxy: impl Into<String>) -> Self {
+        self.proxy = Some(proxy.into());
+        self
+    }
+
+    /// Add a host that bypasses the proxy.
+    pub fn with_no_proxy(mut self, host: impl Into<String>) -> Self {
+        self.no_proxy.push(host.into());
+        self
+    }
+
+    /// Set the CA bundle path.
+    pub fn with_ca_bundle(mut self, path: impl Into<PathBuf>) -> Self {
+        self.ca_bundle = Some(path.into());
+        self
+    }
+
+    /// Set the connect timeout.
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Set the request timeout.
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// Set the retry count and initial backoff.
+    pub fn with_retries(mut self, max_retries: u32, backoff: Duration) -> Self {
+        self.max_retries = max_retries;
+        self.retry_backoff = backoff;
+        self
+    }
+
+    /// Get the proxy to use for a host, if any.
+    pub fn proxy_for(&self, host: &str) -> Option<&str> {
+        let host = host.to_ascii_lowercase();
+        let bypass = self.no_proxy.iter().any(|entry| {
+            let entry = entry.to_ascii_lowercase();
+            if entry == "*" {
+                true
+            } else if let Some(suffix) = entry.strip_prefix('.') {
+                host == suffix || host.ends_with(&entry)
+            } else {
+                host == entry
+            }
+        });
+
+        if bypass {
+            None
+        } else {
+            self.proxy.as_deref()
+        }
+    }
+
+    /// Validate the configuration, failing with [`Error::Config`] listing
+    /// every invalid field.
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = ConfigError::new();
+        if let Some(ref proxy) = self.proxy {
+            let scheme = proxy.split("://").next().unwrap_or_default();
+            errors.check(
+                proxy.contains("://") && matches!(scheme, "http" | "https" | "socks5"),
+                "proxy",
+                format!("invalid proxy URL: {}", proxy),
+            );
+        }
+
+        if let Some(ref bundle) = self.ca_bundle {
+            errors.check(
+                bundle.is_file(),
+                "ca_bundle",
+                format!("CA bundle not found: {}", bundle.display()),
+            );
+        }
+
+        errors.check(
+            !self.connect_timeout.is_zero(),
+            "connect_timeout",
+            "must be non-zero",
+        );
+        errors.check(
+            !self.request_timeout.is_zero(),
+            "request_timeout",
+            "must be non-zero",
+        );
+
+        errors.into_result()
+    }
+
+    /// Run a network operation, retrying [`Error::Network`] failures with
+    /// exponential backoff.
+    pub fn retry<T, F>(&self, mut op: F) -> Result<T>
+    where
+        F: FnMut() -> Result<T>,
+    {
+        let mut delay = self.retry_backoff;
+        let mut attempt = 0;
+
+        loop {
+            match op() {
+                Err(Error::Network(msg)) if attempt < self.max_retries => {
+                    attempt += 1;
+                    tracing::debug!(
+                        "Network request failed ({}), retry {}/{}",
+                        msg,
+                        attempt,
+                        self.max_retries
+                    );
+                    std::thread::sleep(delay);
+                    delay = delay.saturating_mul(2);
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offline_switch_shared() {
+        let switch = OfflineSwitch::default();
+        let clone = switch.clone();
+        assert!(switch.ensure_online("update checks").is_ok());
+
+        clone.set_offline(true);
+        assert!(switch.is_offline());
+        assert!(matches!(
+            switch.ensure_online("update checks"),
+            Err(Error::OfflineMode(ref s)) if s == "update checks"
+        ));
+    }
+
+    #[test]
+    fn test_proxy_for() {
+        let config = NetworkConfig::new()
+            .with_proxy("http://proxy.corp:3128")
+            .with_no_proxy("localhost")
+            .with_no_proxy(".internal");
+
+        assert_eq!(
+            config.proxy_for("plugins.example.com"),
+            Some("http://proxy.corp:3128")
+        );
+        assert_eq!(config.proxy_for("LOCALHOST"), None);
+        assert_eq!(config.proxy_for("index.internal"), None);
+        assert_eq!(config.proxy_for("internal"), None);
+        assert!(config.validate().is_ok());
+
+        assert!(matches!(
+            NetworkConfig::new()
+                .with_proxy("proxy:3128")
+                .with_connect_timeout(Duration::ZERO)
+                .validate(),
+            Err(Error::Config(e)) if e.paths() == ["proxy", "connect_timeout"]
+        ));
+        assert!(NetworkConfig::new()
+            .with_ca_bundle("/nonexistent/ca.pem")
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn test_retry() {
+        let config = NetworkConfig::new().with_retries(2, Duration::ZERO);
+
+        let mut attempts = 0;
+        let result: Result<()> = config.retry(|| {
+            attempts += 1;
+            Err(Error::network("connection reset"))
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 3);
+
+        // Non-network errors are not retried
+        let mut attempts = 0;
+        let result: Result<()> = config.retry(|| {
+            attempts += 1;
+            Err(Error::offline_mode("fetch"))
+        });
+        assert!(matches!(result, Err(Error::OfflineMode(_))));
+        assert_eq!(attempts, 1);
+    }
+}
@@ -0,0 +1,247 @@
+//! Where plugins come from and how much they are trusted.
+
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use crate::capability_policy::{CapabilityPolicy, DenyAll};
+use crate::update::UpdatePolicy;
+
+/// Where a plugin was installed from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum PluginOrigin {
+    /// Loaded by the host application from a path outside the origin
+    /// directories.
+    #[default]
+    Direct,
+    /// Bundled with the application.
+    Builtin,
+    /// Installed by the user into a plugin directory.
+    User,
+    /// Downloaded from a remote index by the update checker.
+    Remote,
+}
+
+/// How far the capabilities a plugin requests are trusted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TrustLevel {
+    /// Requested capabilities are denied unless the origin's capability
+    /// policy grants them.
+    Untrusted,
+    /// Requested capabilities go through the capability policy, if any.
+    #[default]
+    Standard,
+    /// Requested capabilities skip the origin's policy but still go through
+    /// the loader's capability policy, if any.
+    Trusted,
+}
+
+/// Defaults applied to every plugin of an origin.
+#[derive(Debug, Clone)]
+pub struct OriginPolicy {
+    /// How far requested capabilities are trusted.
+    pub trust: TrustLevel,
+    /// Policy replacing the loader's capability policy for this origin.
+    pub capability_policy: Option<Arc<dyn CapabilityPolicy>>,
+    /// Whether hot reload watches plugins of this origin.
+    pub watch: bool,
+    /// Whether the update checker tracks plugins of this origin.
+    pub updates: bool,
+    /// Update policy replacing the checker's default for this origin.
+    pub update_policy: Option<UpdatePolicy>,
+}
+
+impl OriginPolicy {
+    /// Create a policy with standard trust, watching and updates enabled.
+    pub fn new() -> Self {
+        Self {
+            trust: TrustLevel::Standard,
+            capability_policy: None,
+            watch: true,
+            updates: true,
+            update_policy: None,
+        }
+    }
+
+    /// Get the built-in defaults for an origin.
+    ///
+    /// Bundled plugins are trusted and change only with the application.
+    /// User-installed and downloaded plugins are untrusted and never
+    /// upgraded without asking.
+    pub fn for_origin(origin: PluginOrigin) -> Self {
+        match origin {
+            PluginOrigin::Direct => Self::new(),
+            PluginOrigin::Builtin => Self::new()
+                .with_trust(TrustLevel::Trusted)
+                .with_watch(false)
+                .with_updates(false),
+            PluginOrigin::User => Self::new()
+                .with_trust(TrustLevel::Untrusted)
+                .with_update_policy(UpdatePolicy::Notify),
+            PluginOrigin::Remote => Self::new()
+                .with_trust(TrustLevel::Untrusted)
+                .with_watch(false)
+                .with_update_policy(UpdatePolicy::Notify),
+        }
+    }
+
+    /// Set the trust level.
+    pub fn with_trust(mut self, trust: TrustLevel) -> Self {
+        self.trust = trust;
+        self
+    }
+
+    /// Set the capability policy for plugins of this origin.
+    pub fn with_capability_policy(mut self, policy: impl CapabilityPolicy + 'static) -> Self {
+        self.capability_policy = Some(Arc::new(policy));
+        self
+    }
+
+    /// Set whether hot reload watches plugins of this origin.
+    pub fn with_watch(mut self, watch: bool) -> Self {
+        self.watch = watch;
+        self
+    }
+
+    /// Set whether the update checker tracks plugins of this origin.
+    pub fn with_updates(mut self, updates: bool) -> Self {
+        self.updates = updates;
+        self
+    }
+
+    /// Set the update policy for plugins of this origin.
+    pub fn with_update_policy(mut self, policy: UpdatePolicy) -> Self {
+        self.update_policy = Some(policy);
+        self
+    }
+
+    /// Get the capability policy to apply, given the loader's own policy.
+    pub(crate) fn effective_capability_policy(
+        &self,
+        fallback: Option<&Arc<dyn CapabilityPolicy>>,
+    ) -> Option<Arc<dyn CapabilityPolicy>> {
+        match self.trust {
+            TrustLevel::Trusted => fallback.cloned(),
+            TrustLevel::Standard => self.capability_policy.clone().or_else(|| fallback.cloned()),
+            TrustLevel::Untrusted => Some(
+                self.capability_policy
+                    .clone()
+                    .unwrap_or_else(|| Arc::new(DenyAll)),
+            ),
+        }
+    }
+}
+
+impl Default for OriginPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Find the origin of the deepest directory containing `path`.
+///
+/// Both sides are resolved first, so neither `..` nor a symlink makes a
+/// path outside a directory look like it is inside.
+pub(crate) fn origin_of(dirs: &[(PathBuf, PluginOrigin)], path: &Path) -> Option<PluginOrigin> {
+    let path = resolve(path);
+    dirs.iter()
+        .map(|(dir, origin)| (resolve(dir), origin))
+        .filter(|(dir, _)| path.starts_with(dir))
+        .max_by_key(|(dir, _)| dir.components().count())
+        .map(|(_, origin)| *origin)
+}
+
+/// Canonicalize a path, or normalize it lexically if it does not exist.
+fn resolve(path: &Path) -> PathBuf {
+    if let Ok(path) = std::fs::canonicalize(path) {
+        return path;
+    }
+    let mut resolved = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            other => resolved.push(other),
+        }
+    }
+    resolved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capability_policy::AllowList;
+
+    #[test]
+    fn test_origin_of() {
+        let dirs = vec![
+            (PathBuf::from("/opt/app/plugins"), PluginOrigin::Builtin),
+            (PathBuf::from("/opt/app/plugins/user"), PluginOrigin::User),
+        ];
+        assert_eq!(
+            origin_of(&dirs, Path::new("/opt/app/plugins/core/plugin.toml")),
+            Some(PluginOrigin::Builtin)
+        );
+        assert_eq!(
+            origin_of(&dirs, Path::new("/opt/app/plugins/user/x/plugin.toml")),
+            Some(PluginOrigin::User)
+        );
+        assert_eq!(origin_of(&dirs, Path::new("/tmp/plugin.toml")), None);
+        assert_eq!(
+            origin_of(
+                &dirs,
+                Path::new("/opt/app/plugins/../../../tmp/plugin.toml")
+            ),
+            None
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_origin_of_resolves_symlinks() {
+        let root = tempfile::tempdir().unwrap();
+        let builtin = root.path().join("builtin");
+        let outside = root.path().join("outside");
+        std::fs::create_dir(&builtin).unwrap();
+        std::fs::create_dir(&outside).unwrap();
+        std::fs::write(outside.join("plugin.toml"), "").unwrap();
+        std::os::unix::fs::symlink(&outside, builtin.join("link")).unwrap();
+
+        let dirs = vec![(builtin.clone(), PluginOrigin::Builtin)];
+        assert_eq!(origin_of(&dirs, &builtin.join("link/plugin.toml")), None);
+
+        // A directory given through a symlink still matches
+        let alias = root.path().join("alias");
+        std::os::unix::fs::symlink(&builtin, &alias).unwrap();
+        std::fs::write(builtin.join("plugin.toml"), "").unwrap();
+        let dirs = vec![(alias, PluginOrigin::Builtin)];
+        assert_eq!(
+            origin_of(&dirs, &builtin.join("plugin.toml")),
+            Some(PluginOrigin::Builtin)
+        );
+    }
+
+    #[test]
+    fn test_effective_capability_policy() {
+        let fallback: Arc<dyn CapabilityPolicy> = Arc::new(AllowList::new());
+
+        // Trust skips the origin's policy but not the loader's
+        let builtin = OriginPolicy::for_origin(PluginOrigin::Builtin);
+        assert!(builtin
+            .effective_capability_policy(Some(&fallback))
+            .is_some());
+        assert!(builtin.effective_capability_policy(None).is_none());
+
+        let direct = OriginPolicy::for_origin(PluginOrigin::Direct);
+        assert!(direct
+            .effective_capability_policy(Some(&fallback))
+            .is_some());
+        assert!(direct.effective_capability_policy(None).is_none());
+
+        let user = OriginPolicy::for_origin(PluginOrigin::User);
+        assert!(user.effective_capability_policy(None).is_some());
+    }
+}
@@ -1,17 +1,44 @@
 //! Plugin representation and execution.
 
-use std::path::PathBuf;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
-use std::time::Instant;
-
-use parking_lot::RwLock;
-
-use fusabi_host::{Engine, EngineConfig, Value};
-
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+
+use parking_lot::{Mutex, RwLock};
+
+use fusabi_host::{Capabilities, Engine, EngineConfig, Value};
+
+use crate::audit::{AuditOutcome, AuditRecord, AuditSink};
+use crate::authorizer::Principal;
+use crate::builtin::BuiltinPlugin;
+use crate::call_cache::{CallCache, CallCacheStats};
+use crate::composite::Composite;
+use crate::config_schema::ConfigSchema;
+use crate::delegation::{self, Delegation};
+use crate::descriptor::{PluginDescriptor, PluginStats, ResourceLimits, LIFECYCLE_HOOKS};
+use crate::diagnostic::SourceSpan;
+use crate::environment::{EnvPolicy, ENV_READ};
 use crate::error::{Error, Result};
+use crate::health::{HealthStatus, PluginHealth, HEALTH_EXPORT};
+use crate::host_functions::{HostFn, HostFunctionTable};
+#[cfg(feature = "isolation")]
+use crate::isolation::{IsolationConfig, ProcessEngine};
 use crate::lifecycle::LifecycleState;
-use crate::manifest::Manifest;
+use crate::logger::{LogLine, PluginLogger, DEFAULT_LOG_CAPACITY};
+use crate::manifest::{Isolation, Manifest};
+use crate::marshal;
+#[cfg(feature = "metrics-prometheus")]
+use crate::metrics::PluginMetrics;
+use crate::origin::PluginOrigin;
+use crate::pool::{AutoscalePolicy, EnginePool, PoolSignals, ScaleListener};
+use crate::probe::CAPABILITY_NAMES;
+use crate::rate_limit::CallRateLimiter;
+use crate::regional::RegionalSettings;
+use crate::sensitive::{zeroize_string, CallArg, ExposedArgs};
+#[cfg(feature = "wasm")]
+use crate::wasm::WasmEngine;
 
 static NEXT_PLUGIN_ID: AtomicU64 = AtomicU64::new(1);
 
@@ -30,6 +57,8 @@ pub struct PluginInfo {
     pub entry_path: Option<PathBuf>,
     /// When the plugin was loaded.
     pub loaded_at: Instant,
+    /// How long loading the plugin took, once it is registered.
+    pub load_duration: Option<Duration>,
     /// When the plugin was last reloaded.
     pub last_reload: Option<Instant>,
     /// Total reload count.
@@ -38,6 +67,8 @@ pub struct PluginInfo {
     pub invocation_count: u64,
     /// Current lifecycle state.
     pub state: LifecycleState,
+    /// Where the plugin was installed from.
+    pub origin: PluginOrigin,
 }
 
 impl PluginInfo {
@@ -50,10 +81,153 @@ impl PluginInfo {
             manifest_path: None,
             entry_path: None,
             loaded_at: Instant::now(),
+            load_duration: None,
             last_reload: None,
             reload_count: 0,
             invocation_count: 0,
             state: LifecycleState::Created,
+            origin: PluginOrigin::default(),
+        }
+    }
+}
+
+/// Arguments of a call passed through a [`CallGate`], for its records.
+#[derive(Clone, Copy)]
+pub(crate) enum GateArgs<'a> {
+    /// Arguments passed as plain values.
+    Plain(&'a [Value]),
+    /// Arguments some of which may be sensitive.
+    Sensitive(&'a [CallArg]),
+}
+
+/// Checks a runtime applies around every call to the plugins it manages.
+pub(crate) trait CallGate: Send + Sync {
+    /// Run `call`, a call of `function` on `plugin` with `args`, if the
+    /// runtime admits it.
+    fn guard(
+        &self,
+        plugin: &PluginHandle,
+        function: &str,
+        args: GateArgs<'_>,
+        call: &mut dyn FnMut() -> Result<Value>,
+    ) -> Result<Value>;
+}
+
+/// Where a plugin's code is executed.
+enum Backend {
+    InProcess(Engine),
+    Builtin(Arc<BuiltinPlugin>),
+    Composite(Arc<Composite>),
+    #[cfg(feature = "isolation")]
+    Process(Box<ProcessEngine>),
+    #[cfg(feature = "wasm")]
+    Wasm(WasmEngine),
+}
+
+impl Backend {
+    /// Call an exported function.
+    fn call(&self, function: &str, args: &[Value]) -> Result<Value> {
+        self.call_with(function, args, false)
+    }
+
+    /// Call an exported function, zeroing the call expression afterwards if
+    /// `sensitive` is set.
+    ///
+    /// Engines without a native call entry point run a call expression with
+    /// the arguments rendered as literals, see [`marshal`].
+    fn call_with(&self, function: &str, args: &[Value], sensitive: bool) -> Result<Value> {
+        let execute = |run: &dyn Fn(&str) -> Result<Value>| {
+            let mut expression = marshal::call_expression(function, args)?;
+            let result = run(&expression);
+            if sensitive {
+                zeroize_string(&mut expression);
+            }
+            result
+        };
+        match self {
+            Self::InProcess(engine) => execute(&|expression| {
+                engine
+                    .execute(expression)
+                    .map_err(|e| Error::execution_failed(e.to_string()))
+            }),
+            Self::Builtin(plugin) => plugin.call(function, args),
+            Self::Composite(composite) => composite.call(function, args),
+            #[cfg(feature = "isolation")]
+            Self::Process(engine) => execute(&|expression| engine.execute(expression)),
+            #[cfg(feature = "wasm")]
+            Self::Wasm(module) => module.call(function, args),
+        }
+    }
+
+    /// Run a lifecycle function, returning the error message on failure.
+    fn hook(&self, function: &str) -> std::result::Result<Value, String> {
+        let result = self.call(function, &[]).map_err(|e| match e {
+            Error::ExecutionFailed(message) => message,
+            other => other.to_string(),
+        });
+        #[cfg(feature = "isolation")]
+        if let Self::Process(engine) = self {
+            match function {
+                "init" if result.is_ok() => engine.set_started(true),
+                "cleanup" => engine.set_started(false),
+                _ => {}
+            }
+        }
+        result
+    }
+
+    /// Interrupt the call in progress, if the backend can be interrupted.
+    fn kill(&self) -> bool {
+        match self {
+            #[cfg(feature = "isolation")]
+            Self::Process(engine) => {
+                engine.kill();
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A replacement engine built by [`Plugin::prepare_swap`].
+pub(crate) struct EngineSwap {
+    manifest: Manifest,
+    bytecode: Option<Vec<u8>>,
+    source: Option<String>,
+    engine_config: EngineConfig,
+    engine: Backend,
+    was_running: bool,
+}
+
+impl EngineSwap {
+    /// Record the source text the new bytecode was compiled from.
+    pub(crate) fn with_source(mut self, source: Option<String>) -> Self {
+        self.source = source;
+        self
+    }
+
+    /// Drop the replacement, running its `cleanup()` if `init()` ran.
+    pub(crate) fn discard(self) {
+        if self.was_running && self.manifest.has_export("cleanup") {
+            let _ = self.engine.hook("cleanup");
+        }
+    }
+}
+
+/// An engine swapped out by [`Plugin::commit_swap`].
+pub(crate) struct RetiredEngine {
+    engine: Option<Arc<Backend>>,
+    manifest: Manifest,
+    was_running: bool,
+}
+
+impl RetiredEngine {
+    /// Run the old version's `cleanup()` if the plugin was running.
+    pub(crate) fn retire(self) {
+        if self.was_running && self.manifest.has_export("cleanup") {
+            if let Some(engine) = self.engine {
+                let _ = engine.hook("cleanup");
+            }
         }
     }
 }
@@ -62,13 +236,39 @@ impl PluginInfo {
 struct PluginInner {
     manifest: Manifest,
     info: PluginInfo,
-    engine: Option<Engine>,
+    engine: Option<Arc<Backend>>,
+    engine_config: Option<EngineConfig>,
     bytecode: Option<Vec<u8>>,
+    /// Source text the bytecode was compiled from, for error snippets.
+    source: Option<Arc<str>>,
+    missing_host_functions: Vec<String>,
 }
 
 /// A loaded Fusabi plugin.
+///
+/// Calls only hold the state lock long enough to check the state and take a
+/// reference to the engine, so independent calls run concurrently and a
+/// reload can swap engines while older calls finish on the previous one.
 pub struct Plugin {
     inner: RwLock<PluginInner>,
+    transition: Mutex<()>,
+    invocations: AtomicU64,
+    call_cache: CallCache,
+    export_calls: dashmap::DashMap<String, u64>,
+    audit: std::sync::OnceLock<Arc<dyn AuditSink>>,
+    gate: std::sync::OnceLock<Weak<dyn CallGate>>,
+    logger: Arc<PluginLogger>,
+    env_policy: std::sync::OnceLock<EnvPolicy>,
+    host_functions: std::sync::OnceLock<Arc<HostFunctionTable>>,
+    regional: RwLock<Option<RegionalSettings>>,
+    builtin: std::sync::OnceLock<Arc<BuiltinPlugin>>,
+    composite: std::sync::OnceLock<Arc<Composite>>,
+    delegation: std::sync::OnceLock<Delegation>,
+    pool: RwLock<Option<Arc<EnginePool<Arc<Backend>>>>>,
+    #[cfg(feature = "isolation")]
+    isolation: std::sync::OnceLock<Arc<IsolationConfig>>,
+    #[cfg(feature = "metrics-prometheus")]
+    metrics: std::sync::OnceLock<Arc<PluginMetrics>>,
 }
 
 impl Plugin {
@@ -76,15 +276,317 @@ impl Plugin {
     pub fn new(manifest: Manifest) -> Self {
         let id = NEXT_PLUGIN_ID.fetch_add(1, Ordering::Relaxed);
         let info = PluginInfo::new(id, &manifest);
+        let logger = Arc::new(PluginLogger::new(&manifest.name, DEFAULT_LOG_CAPACITY));
 
         Self {
             inner: RwLock::new(PluginInner {
                 manifest,
                 info,
                 engine: None,
+                engine_config: None,
                 bytecode: None,
+                source: None,
+                missing_host_functions: Vec::new(),
             }),
+            transition: Mutex::new(()),
+            invocations: AtomicU64::new(0),
+            call_cache: CallCache::default(),
+            export_calls: dashmap::DashMap::new(),
+            audit: std::sync::OnceLock::new(),
+            gate: std::sync::OnceLock::new(),
+            logger,
+            env_policy: std::sync::OnceLock::new(),
+            host_functions: std::sync::OnceLock::new(),
+            regional: RwLock::new(None),
+            builtin: std::sync::OnceLock::new(),
+            composite: std::sync::OnceLock::new(),
+            delegation: std::sync::OnceLock::new(),
+            pool: RwLock::new(None),
+            #[cfg(feature = "isolation")]
+            isolation: std::sync::OnceLock::new(),
+            #[cfg(feature = "metrics-prometheus")]
+            metrics: std::sync::OnceLock::new(),
+        }
+    }
+
+    /// Record capability grants and capability-gated accesses to `sink`.
+    ///
+    /// The capabilities the current engine was granted are recorded when the
+    /// sink is attached. Has no effect if a sink was already attached.
+    pub fn set_audit_sink(&self, sink: Arc<dyn AuditSink>) {
+        if self.audit.set(sink).is_ok() {
+            if let Some(engine_config) = self.engine_config() {
+                self.audit_grants(&engine_config);
+            }
+        }
+    }
+
+    /// Route calls through handles via the checks of the runtime managing
+    /// the plugin.
+    pub(crate) fn set_call_gate(&self, gate: Weak<dyn CallGate>) {
+        let _ = self.gate.set(gate);
+    }
+
+    /// Check if calls run on the calling thread, so the thread's CPU time
+    /// measures them.
+    pub(crate) fn runs_on_caller_thread(&self) -> bool {
+        #[cfg(feature = "isolation")]
+        if matches!(
+            self.inner.read().engine.as_deref(),
+            Some(Backend::Process(_))
+        ) {
+            return false;
+        }
+        true
+    }
+
+    /// Get the logger capturing the plugin's output.
+    ///
+    /// Only an isolated worker's output is captured on its own; host
+    /// functions record lines for in-process plugins.
+    pub fn logger(&self) -> &Arc<PluginLogger> {
+        &self.logger
+    }
+
+    /// Let the plugin read the environment variables `policy` allows.
+    ///
+    /// Has no effect if a policy was already attached.
+    pub fn set_env_policy(&self, policy: EnvPolicy) {
+        let _ = self.env_policy.set(policy);
+    }
+
+    /// Bind the implementations in `table` of the host functions the
+    /// manifest requires into the engines created afterwards.
+    ///
+    /// Has no effect if a table was already attached.
+    pub fn set_host_functions(&self, table: Arc<HostFunctionTable>) {
+        let _ = self.host_functions.set(table);
+    }
+
+    /// Get the locale and timezone the plugin formats for.
+    ///
+    /// Defaults to the host's, see [`RegionalSettings::host`].
+    pub fn regional_settings(&self) -> RegionalSettings {
+        self.regional
+            .read()
+            .clone()
+            .unwrap_or_else(RegionalSettings::host)
+    }
+
+    /// Set the locale and timezone the plugin formats for.
+    ///
+    /// Only host functions reading [`regional_settings`](Self::regional_settings)
+    /// and the `LANG` and `TZ` of worker processes follow them; workers pick
+    /// up the change when they next start.
+    pub fn set_regional_settings(&self, settings: RegionalSettings) {
+        *self.regional.write() = Some(settings);
+    }
+
+    /// Run the plugin's functions natively instead of in an engine.
+    ///
+    /// Has no effect if an implementation was already attached.
+    pub(crate) fn set_builtin(&self, builtin: BuiltinPlugin) {
+        let _ = self.builtin.set(Arc::new(builtin));
+    }
+
+    /// Check if the plugin is implemented natively.
+    pub fn is_builtin(&self) -> bool {
+        self.builtin.get().is_some()
+    }
+
+    /// Forward the plugin's calls and lifecycle to member plugins.
+    ///
+    /// Has no effect if members were already attached.
+    #[cfg(feature = "serde")]
+    pub(crate) fn set_composite(&self, composite: Composite) {
+        let _ = self.composite.set(Arc::new(composite));
+    }
+
+    /// Record that the plugin was loaded by another plugin.
+    ///
+    /// Has no effect if a delegation was already recorded.
+    pub(crate) fn set_delegation(&self, delegation: Delegation) {
+        let _ = self.delegation.set(delegation);
+    }
+
+    /// Get the restrictions inherited from the plugin that loaded this
+    /// one, if it was loaded by a plugin.
+    pub fn delegation(&self) -> Option<&Delegation> {
+        self.delegation.get()
+    }
+
+    /// Serve calls from a pool of engines sized by `policy`.
+    ///
+    /// Only plugins whose manifest declares them stateless can be pooled.
+    /// Extra engines run `init()` when created and are dropped without
+    /// `cleanup()` when the pool shrinks.
+    pub fn set_autoscale(&self, policy: AutoscalePolicy) -> Result<()> {
+        self.set_autoscale_with(policy, None)
+    }
+
+    /// Like [`set_autoscale`](Self::set_autoscale), calling `on_scale`
+    /// whenever engines are added or removed.
+    pub(crate) fn set_autoscale_with(
+        &self,
+        policy: AutoscalePolicy,
+        on_scale: Option<ScaleListener>,
+    ) -> Result<()> {
+        policy.validate()?;
+        let inner = self.inner.read();
+        if !inner.manifest.stateless {
+            return Err(Error::invalid_state("stateless plugin", "stateful plugin"));
+        }
+        if self.builtin.get().is_some() || self.composite.get().is_some() {
+            return Err(Error::invalid_state(
+                "engine-backed plugin",
+                "native plugin",
+            ));
+        }
+        *self.pool.write() = Some(Arc::new(EnginePool::new(
+            policy,
+            inner.engine.clone(),
+            on_scale,
+        )));
+        Ok(())
+    }
+
+    /// Get the policy sizing the plugin's engine pool, if it is pooled.
+    pub fn autoscale_policy(&self) -> Option<AutoscalePolicy> {
+        self.pool.read().as_ref().map(|pool| pool.policy().clone())
+    }
+
+    /// Get the load of the plugin's engine pool, if it is pooled.
+    pub fn pool_signals(&self) -> Option<PoolSignals> {
+        self.pool.read().as_ref().map(|pool| pool.signals())
+    }
+
+    /// Remove pooled engines that have been idle too long, returning how
+    /// many were removed.
+    pub(crate) fn trim_pool(&self) -> usize {
+        let pool = self.pool.read().clone();
+        pool.map_or(0, |pool| pool.trim())
+    }
+
+    /// Start a fresh pool around the current engine after it changed.
+    fn renew_pool(&self, engine: Option<Arc<Backend>>) {
+        let mut pool = self.pool.write();
+        if let Some(current) = pool.take() {
+            *pool = Some(Arc::new(current.renew(engine)));
+        }
+    }
+
+    /// Create an extra engine for the pool, running `init()` if the plugin
+    /// is running.
+    ///
+    /// Pooled engines keep their own state and are dropped without
+    /// `cleanup()` when the pool shrinks or is renewed.
+    fn pooled_engine(&self) -> Result<Arc<Backend>> {
+        self.spare_engine(&BTreeSet::new())
+            .map(|(engine, _)| Arc::new(engine))
+    }
+
+    /// Create an engine alongside the current one without the `removed`
+    /// capabilities, running `init()` if the plugin is running.
+    ///
+    /// Also returns whether the engine needs `cleanup()` once dropped.
+    fn spare_engine(&self, removed: &BTreeSet<String>) -> Result<(Backend, bool)> {
+        let (mut manifest, mut engine_config, bytecode, running) = {
+            let inner = self.inner.read();
+            let engine_config = inner
+                .engine_config
+                .clone()
+                .ok_or_else(|| Error::invalid_state("engine initialized", "no engine"))?;
+            (
+                inner.manifest.clone(),
+                engine_config,
+                inner.bytecode.clone(),
+                inner.info.state == LifecycleState::Running,
+            )
+        };
+        if !removed.is_empty() {
+            manifest
+                .capabilities
+                .retain(|spec| !removed.contains(&spec.name));
+            let mut caps = Capabilities::none();
+            for spec in &manifest.capabilities {
+                if let Some(cap) = fusabi_host::Capability::from_name(&spec.name) {
+                    if engine_config.capabilities.has(cap) {
+                        caps.grant(cap);
+                    }
+                }
+            }
+            engine_config.capabilities = caps;
+        }
+        let engine = self
+            .backend(&manifest, &engine_config, bytecode.as_deref())
+            .map_err(Error::init_failed)?;
+        if running && manifest.has_export("init") {
+            engine.hook("init").map_err(Error::init_failed)?;
         }
+        Ok((engine, running && manifest.has_export("cleanup")))
+    }
+
+    /// Get the members of a composite plugin, in manifest order.
+    pub fn members(&self) -> Vec<PluginHandle> {
+        self.composite
+            .get()
+            .map(|composite| composite.members())
+            .unwrap_or_default()
+    }
+
+    /// Get the environment variables the plugin may read.
+    ///
+    /// These are the variables listed under `[env] allow` that the host's
+    /// policy allows, provided the manifest declares `sys:env`.
+    pub fn allowed_env(&self) -> Vec<String> {
+        let inner = self.inner.read();
+        allowed_env(&inner.manifest, self.env_policy.get())
+    }
+
+    /// Read an environment variable on behalf of the plugin.
+    ///
+    /// Returns `None` if the variable is allowed but unset.
+    pub fn env_var(&self, name: &str) -> Result<Option<String>> {
+        let result = {
+            let inner = self.inner.read();
+            if inner.manifest.capability(ENV_READ).is_none() {
+                Err(Error::UndeclaredCapability(ENV_READ.to_string()))
+            } else if allowed_env(&inner.manifest, self.env_policy.get())
+                .iter()
+                .any(|v| v == name)
+            {
+                Ok(())
+            } else {
+                Err(Error::capability_denied(ENV_READ, name))
+            }
+        };
+        self.audit_access(ENV_READ, name, &result);
+        result.map(|()| std::env::var(name).ok())
+    }
+
+    /// Get the allowed environment variables that are set.
+    pub fn env(&self) -> BTreeMap<String, String> {
+        self.allowed_env()
+            .into_iter()
+            .filter_map(|name| std::env::var(&name).ok().map(|value| (name, value)))
+            .collect()
+    }
+
+    /// Start worker processes from `config` if the manifest asks for
+    /// process isolation.
+    ///
+    /// Has no effect if a configuration was already attached.
+    #[cfg(feature = "isolation")]
+    pub fn set_isolation(&self, config: Arc<IsolationConfig>) {
+        let _ = self.isolation.set(config);
+    }
+
+    /// Record call durations and failures to `metrics`.
+    ///
+    /// Has no effect if metrics were already attached.
+    #[cfg(feature = "metrics-prometheus")]
+    pub fn set_metrics(&self, metrics: Arc<PluginMetrics>) {
+        let _ = self.metrics.set(metrics);
     }
 
     /// Get the plugin ID.
@@ -107,9 +609,76 @@ impl Plugin {
         self.inner.read().manifest.clone()
     }
 
+    /// Get the schema of the plugin's configuration.
+    pub fn config_schema(&self) -> ConfigSchema {
+        self.inner.read().manifest.config.clone()
+    }
+
+    /// Take a rate limit token for a call to `function`, with the limits
+    /// declared in the manifest.
+    pub(crate) fn admit_call(&self, limiter: &CallRateLimiter, function: &str) -> Result<()> {
+        let inner = self.inner.read();
+        limiter.admit(&inner.manifest.name, function, &inner.manifest.rate_limit)
+    }
+
     /// Get plugin information.
     pub fn info(&self) -> PluginInfo {
-        self.inner.read().info.clone()
+        let mut info = self.inner.read().info.clone();
+        info.invocation_count = self.invocations.load(Ordering::Relaxed);
+        info
+    }
+
+    /// Describe the plugin's manifest, limits, state and usage.
+    pub fn describe(&self) -> PluginDescriptor {
+        let inner = self.inner.read();
+        let manifest = &inner.manifest;
+        let cache = self.call_cache.stats();
+
+        PluginDescriptor {
+            id: inner.info.id,
+            name: manifest.name.clone(),
+            version: manifest.version.clone(),
+            description: manifest.description.clone(),
+            authors: manifest.authors.clone(),
+            license: manifest.license.clone(),
+            api_version: manifest.api_version.to_string(),
+            state: inner.info.state,
+            exports: manifest.exports.clone(),
+            hooks: LIFECYCLE_HOOKS
+                .iter()
+                .filter(|hook| manifest.has_export(hook))
+                .map(|hook| hook.to_string())
+                .collect(),
+            routes: self
+                .composite
+                .get()
+                .map(|composite| composite.routes())
+                .unwrap_or_default(),
+            config: manifest.config.clone(),
+            capabilities: manifest.capabilities.clone(),
+            dependencies: manifest.dependencies.clone(),
+            isolation: manifest.isolation,
+            tags: manifest.tags.clone(),
+            metadata: manifest.metadata.clone().into_iter().collect(),
+            manifest_path: inner.info.manifest_path.clone(),
+            entry_path: inner.info.entry_path.clone(),
+            origin: inner.info.origin,
+            limits: inner
+                .engine_config
+                .as_ref()
+                .map(|config| ResourceLimits::from(&config.limits)),
+            stats: PluginStats {
+                invocations: self.invocations.load(Ordering::Relaxed),
+                export_calls: self
+                    .export_calls
+                    .iter()
+                    .map(|e| (e.key().clone(), *e.value()))
+                    .collect(),
+                cache_hits: cache.hits,
+                reloads: inner.info.reload_count,
+                uptime_secs: inner.info.loaded_at.elapsed().as_secs(),
+            },
+        }
     }
 
     /// Get the current lifecycle state.
@@ -123,198 +692,628 @@ impl Plugin {
     }
 
     /// Initialize the plugin with an engine.
+    ///
+    /// The engine is created without holding the state lock, so calls and
+    /// status queries are not blocked by a slow worker start.
     pub fn initialize(&self, engine_config: EngineConfig) -> Result<()> {
+        let _transition = self.transition.lock();
+        let (manifest, bytecode) = {
+            let inner = self.inner.read();
+
+            // Check state
+            if inner.info.state != LifecycleState::Created
+                && inner.info.state != LifecycleState::Stopped
+            {
+                return Err(Error::invalid_state(
+                    "Created or Stopped",
+                    format!("{:?}", inner.info.state),
+                ));
+            }
+
+            // Verify capabilities
+            check_capabilities(&inner.manifest, &engine_config)?;
+
+            (inner.manifest.clone(), inner.bytecode.clone())
+        };
+
+        // Create engine
+        let engine = self
+            .backend(&manifest, &engine_config, bytecode.as_deref())
+            .map_err(Error::init_failed)?;
+
+        self.audit_grants(&engine_config);
         let mut inner = self.inner.write();
+        inner.engine = Some(Arc::new(engine));
+        inner.engine_config = Some(engine_config);
+        inner.info.state = LifecycleState::Initialized;
+        self.renew_pool(inner.engine.clone());
 
-        // Check state
-        if inner.info.state != LifecycleState::Created
-            && inner.info.state != LifecycleState::Stopped
-        {
-            return Err(Error::invalid_state(
-                "Created or Stopped",
-                format!("{:?}", inner.info.state),
-            ));
-        }
+        Ok(())
+    }
 
-        // Verify capabilities
-        let caps = &engine_config.capabilities;
-        for required_cap in &inner.manifest.capabilities {
-            let cap = fusabi_host::Capability::from_name(required_cap).ok_or_else(|| {
-                Error::invalid_manifest(format!("unknown capability: {}", required_cap))
-            })?;
+    /// Create the engine for a manifest's entry kind and isolation mode.
+    fn backend(
+        &self,
+        manifest: &Manifest,
+        engine_config: &EngineConfig,
+        bytecode: Option<&[u8]>,
+    ) -> std::result::Result<Backend, String> {
+        if let Some(builtin) = self.builtin.get() {
+            return Ok(Backend::Builtin(builtin.clone()));
+        }
+        if let Some(composite) = self.composite.get() {
+            return Ok(Backend::Composite(composite.clone()));
+        }
+        if manifest.uses_wasm() {
+            let host_functions = self
+                .host_functions
+                .get()
+                .map(|table| table.bind(&manifest.name, &manifest.host_functions))
+                .unwrap_or_default();
+            return wasm_backend(bytecode, &engine_config.limits, host_functions);
+        }
 
-            if !caps.has(cap) {
-                return Err(Error::MissingCapability(required_cap.clone()));
+        match manifest.isolation {
+            Isolation::InProcess => Engine::new(engine_config.clone())
+                .map(Backend::InProcess)
+                .map_err(|e| e.to_string()),
+            #[cfg(feature = "isolation")]
+            Isolation::Process => {
+                let config = self
+                    .isolation
+                    .get()
+                    .ok_or("process isolation is not configured")?;
+                ProcessEngine::start(
+                    (**config).clone(),
+                    manifest.name.clone(),
+                    engine_config,
+                    bytecode.map(<[u8]>::to_vec),
+                    allowed_env(manifest, self.env_policy.get()),
+                    self.regional_settings(),
+                    self.logger.clone(),
+                )
+                .map(|engine| Backend::Process(Box::new(engine)))
+                .map_err(|e| e.to_string())
+            }
+            #[cfg(not(feature = "isolation"))]
+            Isolation::Process => {
+                Err("process isolation requires the `isolation` feature".to_string())
             }
         }
+    }
 
-        // Create engine
-        let engine = Engine::new(engine_config).map_err(|e| Error::init_failed(e.to_string()))?;
-
-        inner.engine = Some(engine);
-        inner.info.state = LifecycleState::Initialized;
+    /// Get the engine configuration the plugin was initialized with.
+    pub fn engine_config(&self) -> Option<EngineConfig> {
+        self.inner.read().engine_config.clone()
+    }
 
+    /// Swap in a freshly loaded manifest, bytecode and engine.
+    ///
+    /// Blue/green: the new engine is built and, if the plugin is running,
+    /// its `init()` is run before the swap; only then is the old engine
+    /// swapped out and its `cleanup()` run. Any failure before the swap
+    /// returns [`Error::ReloadFailed`] and leaves the current version
+    /// serving calls.
+    pub fn replace(
+        &self,
+        manifest: Manifest,
+        bytecode: Vec<u8>,
+        engine_config: EngineConfig,
+    ) -> Result<()> {
+        let swap = self.prepare_swap(manifest, Some(bytecode), engine_config)?;
+        self.commit_swap(swap)?.retire();
         Ok(())
     }
 
-    /// Start the plugin (call init function if exists).
-    pub fn start(&self) -> Result<()> {
+    /// Build and, if the plugin is running, initialize a replacement engine
+    /// without touching the current one.
+    pub(crate) fn prepare_swap(
+        &self,
+        manifest: Manifest,
+        bytecode: Option<Vec<u8>>,
+        engine_config: EngineConfig,
+    ) -> Result<EngineSwap> {
+        let was_running = {
+            let inner = self.inner.read();
+            if inner.info.state == LifecycleState::Unloaded {
+                return Err(Error::PluginUnloaded);
+            }
+            if manifest.name != inner.manifest.name {
+                return Err(Error::ReloadFailed(format!(
+                    "plugin name changed from {} to {}",
+                    inner.manifest.name, manifest.name
+                )));
+            }
+            inner.info.state == LifecycleState::Running
+        };
+
+        check_capabilities(&manifest, &engine_config)
+            .map_err(|e| Error::ReloadFailed(e.to_string()))?;
+        let engine = self
+            .backend(&manifest, &engine_config, bytecode.as_deref())
+            .map_err(Error::ReloadFailed)?;
+
+        if was_running && manifest.has_export("init") {
+            engine
+                .hook("init")
+                .map_err(|e| Error::ReloadFailed(format!("init of new version failed: {}", e)))?;
+        }
+
+        Ok(EngineSwap {
+            manifest,
+            bytecode,
+            source: None,
+            engine_config,
+            engine,
+            was_running,
+        })
+    }
+
+    /// Swap in a prepared engine, returning the one it replaced.
+    ///
+    /// The swap is serialized with lifecycle transitions, and catches up with
+    /// any since the engine was prepared: it runs `init()` if the plugin was
+    /// started meanwhile and `cleanup()` if it was stopped. Fails, discarding
+    /// the engine, if the plugin was unloaded or the late `init()` failed.
+    pub(crate) fn commit_swap(&self, swap: EngineSwap) -> Result<RetiredEngine> {
+        let _transition = self.transition.lock();
+        let state = self.inner.read().info.state;
+        if state == LifecycleState::Unloaded {
+            swap.discard();
+            return Err(Error::PluginUnloaded);
+        }
+        let running = state == LifecycleState::Running;
+        if running && !swap.was_running && swap.manifest.has_export("init") {
+            swap.engine
+                .hook("init")
+                .map_err(|e| Error::ReloadFailed(format!("init of new version failed: {}", e)))?;
+        }
+        let stopped = swap.was_running && !running && swap.manifest.has_export("cleanup");
+        self.audit_grants(&swap.engine_config);
+
         let mut inner = self.inner.write();
+        let manifest = std::mem::replace(&mut inner.manifest, swap.manifest);
+        inner.info.version = inner.manifest.version.clone();
+        let engine = inner.engine.replace(Arc::new(swap.engine));
+        inner.engine_config = Some(swap.engine_config);
+        if let Some(bytecode) = swap.bytecode {
+            inner.bytecode = Some(bytecode);
+            inner.source = swap.source.map(Arc::from);
+        }
+        inner.info.last_reload = Some(Instant::now());
+        inner.info.reload_count += 1;
+        self.call_cache.clear();
+        self.renew_pool(inner.engine.clone());
+        let current = inner.engine.clone();
+        drop(inner);
 
-        if inner.info.state != LifecycleState::Initialized {
-            return Err(Error::invalid_state(
-                "Initialized",
-                format!("{:?}", inner.info.state),
-            ));
+        if let (true, Some(current)) = (stopped, current) {
+            let _ = current.hook("cleanup");
         }
+        Ok(RetiredEngine {
+            engine,
+            manifest,
+            was_running: running,
+        })
+    }
 
-        // Call init function if declared
-        if inner.manifest.exports.contains(&"init".to_string()) {
-            if let Some(ref engine) = inner.engine {
-                engine
-                    .execute("init()")
-                    .map_err(|e| Error::init_failed(e.to_string()))?;
+    /// Start the plugin (call init function if exists).
+    ///
+    /// `init()` runs without holding the state lock; calls are accepted
+    /// once it returned.
+    pub fn start(&self) -> Result<()> {
+        let _transition = self.transition.lock();
+        let engine = {
+            let inner = self.inner.read();
+            if inner.info.state != LifecycleState::Initialized {
+                return Err(Error::invalid_state(
+                    "Initialized",
+                    format!("{:?}", inner.info.state),
+                ));
             }
+            inner
+                .engine
+                .clone()
+                .filter(|_| inner.manifest.has_export("init"))
+        };
+
+        // Call init function if declared
+        if let Some(engine) = engine {
+            engine.hook("init").map_err(Error::init_failed)?;
+        }
+        if let Some(composite) = self.composite.get() {
+            composite.start()?;
         }
 
-        inner.info.state = LifecycleState::Running;
+        self.inner.write().info.state = LifecycleState::Running;
         Ok(())
     }
 
     /// Stop the plugin (call cleanup function if exists).
+    ///
+    /// The plugin stops taking calls first; `cleanup()` runs without
+    /// holding the state lock, so a hung cleanup cannot block unloading.
+    /// A failing cleanup is logged, see [`stop_reporting`](Self::stop_reporting).
     pub fn stop(&self) -> Result<()> {
-        let mut inner = self.inner.write();
-
-        if inner.info.state != LifecycleState::Running {
-            return Err(Error::invalid_state(
-                "Running",
-                format!("{:?}", inner.info.state),
-            ));
+        match self.stop_reporting() {
+            Err(Error::ExecutionFailed(message)) => {
+                tracing::warn!("Plugin {} failed to clean up: {}", self.name(), message);
+                Ok(())
+            }
+            result => result,
         }
+    }
 
-        // Call cleanup function if declared
-        if inner.manifest.exports.contains(&"cleanup".to_string()) {
-            if let Some(ref engine) = inner.engine {
-                let _ = engine.execute("cleanup()");
+    /// Stop the plugin like [`stop`](Self::stop), but fail with
+    /// [`Error::ExecutionFailed`] if `cleanup()` fails.
+    pub(crate) fn stop_reporting(&self) -> Result<()> {
+        let _transition = self.transition.lock();
+        let engine = {
+            let mut inner = self.inner.write();
+            if inner.info.state != LifecycleState::Running {
+                return Err(Error::invalid_state(
+                    "Running",
+                    format!("{:?}", inner.info.state),
+                ));
             }
+            inner.info.state = LifecycleState::Stopped;
+            self.renew_pool(inner.engine.clone());
+            inner
+                .engine
+                .clone()
+                .filter(|_| inner.manifest.has_export("cleanup"))
+        };
+        if let Some(composite) = self.composite.get() {
+            composite.stop();
         }
 
-        inner.info.state = LifecycleState::Stopped;
-        Ok(())
+        match engine {
+            Some(engine) => engine
+                .hook("cleanup")
+                .map(|_| ())
+                .map_err(Error::ExecutionFailed),
+            None => Ok(()),
+        }
+    }
+
+    /// Interrupt a hung call, such as a `cleanup()` that missed the
+    /// shutdown deadline, returning whether the backend allowed it.
+    ///
+    /// Process-isolated plugins have their worker killed; in-process
+    /// engines cannot be interrupted.
+    pub(crate) fn kill(&self) -> bool {
+        let engine = self.inner.read().engine.clone();
+        engine.is_some_and(|engine| engine.kill())
     }
 
     /// Unload the plugin.
+    ///
+    /// `cleanup()` of a running plugin runs after the state lock is
+    /// released.
     pub fn unload(&self) -> Result<()> {
-        let mut inner = self.inner.write();
+        let _transition = self.transition.lock();
+        let engine = {
+            let mut inner = self.inner.write();
+            self.call_cache.clear();
+            let running =
+                inner.info.state == LifecycleState::Running && inner.manifest.has_export("cleanup");
+            let engine = inner.engine.take().filter(|_| running);
+            inner.engine_config = None;
+            inner.bytecode = None;
+            inner.info.state = LifecycleState::Unloaded;
+            self.renew_pool(None);
+            engine
+        };
 
         // Try to stop if running
-        if inner.info.state == LifecycleState::Running
-            && inner.manifest.exports.contains(&"cleanup".to_string())
-        {
-            if let Some(ref engine) = inner.engine {
-                let _ = engine.execute("cleanup()");
-            }
+        if let Some(engine) = engine {
+            let _ = engine.hook("cleanup");
         }
 
-        inner.engine = None;
-        inner.bytecode = None;
-        inner.info.state = LifecycleState::Unloaded;
+        if let Some(composite) = self.composite.get() {
+            composite.unload();
+        }
 
         Ok(())
     }
 
     /// Call a function exported by the plugin.
+    ///
+    /// Results of exports declared in the manifest's `[cache]` table are
+    /// memoized until their TTL expires or the plugin is reloaded.
     pub fn call(&self, function: &str, args: &[Value]) -> Result<Value> {
-        let mut inner = self.inner.write();
+        self.call_with(function, args, false, None)
+    }
 
-        // Check state
-        if inner.info.state != LifecycleState::Running {
-            return Err(Error::invalid_state(
-                "Running",
-                format!("{:?}", inner.info.state),
-            ));
-        }
+    /// Call an exported function with sensitive arguments.
+    ///
+    /// Skips the call result cache, zeroes the copies made for the engine
+    /// once the call returns, and redacts the sensitive values from errors.
+    pub fn call_sensitive(&self, function: &str, args: &[CallArg]) -> Result<Value> {
+        self.call_sensitive_with(function, args, None)
+    }
 
-        // Check function is exported
-        if !inner.manifest.exports.contains(&function.to_string()) && function != "main" {
-            return Err(Error::FunctionNotFound(function.to_string()));
-        }
+    fn call_sensitive_with(
+        &self,
+        function: &str,
+        args: &[CallArg],
+        restriction: Option<&Restriction>,
+    ) -> Result<Value> {
+        let exposed = ExposedArgs::new(args)?;
+        self.call_with(
+            function,
+            exposed.values(),
+            exposed.any_sensitive(),
+            restriction,
+        )
+        .map_err(|e| exposed.redact_error(e))
+    }
 
-        // Build call expression
-        let call_expr = if args.is_empty() {
-            format!("{}()", function)
-        } else {
-            // Format args - simplified for simulation
-            let args_str: Vec<String> = args.iter().map(|a| a.to_string()).collect();
-            format!("{}({})", function, args_str.join(", "))
+    /// Call an exported function, on an engine without the restricted
+    /// capabilities if `restriction` is set.
+    ///
+    /// Restricted calls bypass the call result cache and the engine pool,
+    /// whose engines hold the full capability set.
+    fn call_with(
+        &self,
+        function: &str,
+        args: &[Value],
+        sensitive: bool,
+        restriction: Option<&Restriction>,
+    ) -> Result<Value> {
+        let (engine, cacheable, generation) = {
+            let inner = self.inner.read();
+
+            // Check state
+            if inner.info.state != LifecycleState::Running {
+                return Err(Error::invalid_state(
+                    "Running",
+                    format!("{:?}", inner.info.state),
+                ));
+            }
+
+            if let Some(missing) = inner.missing_host_functions.first() {
+                return Err(Error::missing_host_function(&inner.info.name, missing));
+            }
+
+            // Check function is exported
+            if !inner.manifest.has_export(function) && function != "main" {
+                return Err(Error::FunctionNotFound(function.to_string()));
+            }
+            if let Some(export) = inner.manifest.export(function) {
+                export.check_args(args)?;
+            }
+
+            let engine = inner
+                .engine
+                .clone()
+                .ok_or_else(|| Error::invalid_state("engine initialized", "no engine"))?;
+            let cacheable = if sensitive || restriction.is_some() {
+                None
+            } else {
+                inner.manifest.cache.export(function).cloned()
+            };
+            (engine, cacheable, inner.info.reload_count)
         };
 
-        // Increment invocation count before borrowing engine
-        inner.info.invocation_count += 1;
+        *self.export_calls.entry(function.to_string()).or_default() += 1;
 
-        // Execute
-        inner.info.invocation_count += 1;
+        let cache_key = cacheable.as_ref().map(|c| c.key(args));
+        if let Some(ref key) = cache_key {
+            if let Some(value) = self.call_cache.get(function, key) {
+                return Ok(value);
+            }
+        }
 
-        let engine = inner
-            .engine
-            .as_ref()
-            .ok_or_else(|| Error::invalid_state("engine initialized", "no engine"))?;
+        // Execute without holding the state lock
+        self.invocations.fetch_add(1, Ordering::Relaxed);
+        #[cfg(feature = "metrics-prometheus")]
+        let started = Instant::now();
+        let pool = self.pool.read().clone();
+        let result = match (restriction, pool) {
+            (Some(restriction), _) => self
+                .restricted_engine(restriction, &engine)
+                .and_then(|spare| spare.engine.call_with(function, args, sensitive)),
+            (None, Some(pool)) => pool
+                .acquire(|| self.pooled_engine())
+                .and_then(|engine| engine.call_with(function, args, sensitive)),
+            (None, None) => engine.call_with(function, args, sensitive),
+        }
+        .map_err(|e| self.locate_error(e));
+
+        #[cfg(feature = "metrics-prometheus")]
+        if let Some(metrics) = self.metrics.get() {
+            let name = self.name();
+            metrics.record_call(&name, function, started.elapsed().as_secs_f64());
+            if result.is_err() {
+                metrics.record_error(&name);
+            }
+        }
+
+        if let (Some(key), Some(cacheable), Ok(value)) = (cache_key, cacheable, &result) {
+            // Skip results computed by an engine that was reloaded meanwhile
+            let inner = self.inner.read();
+            if inner.info.reload_count == generation {
+                self.call_cache
+                    .insert(function, key, value.clone(), cacheable.ttl());
+            }
+        }
 
-        engine
-            .execute(&call_expr)
-            .map_err(|e| Error::execution_failed(e.to_string()))
+        result
     }
 
-    /// Reload the plugin from source.
-    pub fn reload(&self) -> Result<()> {
-        let mut inner = self.inner.write();
+    /// Attach the position an execution error names in the plugin's
+    /// source, if any, quoting the source the running code was compiled
+    /// from.
+    fn locate_error(&self, error: Error) -> Error {
+        let Error::ExecutionFailed(ref message) = error else {
+            return error;
+        };
+        let Some(mut span) = SourceSpan::locate(message) else {
+            return error;
+        };
+        {
+            let inner = self.inner.read();
+            if let Some(ref source) = inner.source {
+                span = span.with_source(source);
+                if let Some(ref path) = inner.info.entry_path {
+                    span = span.with_file(path);
+                }
+            }
+        }
+        error.at(span)
+    }
 
-        // Must be in a reloadable state
-        if inner.info.state == LifecycleState::Unloaded {
-            return Err(Error::PluginUnloaded);
+    /// Get the engine serving calls under `restriction`, building it on
+    /// first use and again once `current` has been swapped out.
+    fn restricted_engine(
+        &self,
+        restriction: &Restriction,
+        current: &Arc<Backend>,
+    ) -> Result<Arc<SpareEngine>> {
+        let mut cached = restriction.engine.lock();
+        if let Some((ref built_for, ref spare)) = *cached {
+            if Weak::ptr_eq(built_for, &Arc::downgrade(current)) {
+                return Ok(spare.clone());
+            }
         }
+        let (engine, cleanup) = self.spare_engine(&restriction.removed)?;
+        let spare = Arc::new(SpareEngine { engine, cleanup });
+        // The replaced engine runs its cleanup() once calls using it finish
+        *cached = Some((Arc::downgrade(current), spare.clone()));
+        Ok(spare)
+    }
+
+    /// Get statistics of the call result cache.
+    pub fn call_cache_stats(&self) -> CallCacheStats {
+        self.call_cache.stats()
+    }
 
-        let was_running = inner.info.state == LifecycleState::Running;
+    /// Get the number of calls per export, including cached ones.
+    pub fn export_calls(&self) -> HashMap<String, u64> {
+        self.export_calls
+            .iter()
+            .map(|e| (e.key().clone(), *e.value()))
+            .collect()
+    }
 
-        // Stop if running
-        if was_running && inner.manifest.exports.contains(&"cleanup".to_string()) {
-            if let Some(ref engine) = inner.engine {
-                let _ = engine.execute("cleanup()");
+    /// Prepare a running plugin for traffic.
+    ///
+    /// Runs the plugin's `warmup` export if it declares one; otherwise only
+    /// checks that the engine is ready.
+    pub fn warm(&self) -> Result<()> {
+        let (engine, has_warmup) = {
+            let inner = self.inner.read();
+            if inner.info.state != LifecycleState::Running {
+                return Err(Error::invalid_state(
+                    "Running",
+                    format!("{:?}", inner.info.state),
+                ));
             }
-        }
+            let engine = inner
+                .engine
+                .clone()
+                .ok_or_else(|| Error::invalid_state("engine initialized", "no engine"))?;
+            (engine, inner.manifest.has_export("warmup"))
+        };
 
-        // Reset state
-        inner.info.state = LifecycleState::Initialized;
-        inner.info.last_reload = Some(Instant::now());
-        inner.info.reload_count += 1;
+        if has_warmup {
+            engine.hook("warmup").map_err(Error::execution_failed)?;
+        }
+        Ok(())
+    }
 
-        // Restart if was running
-        if was_running {
-            inner.info.state = LifecycleState::Running;
-            if inner.manifest.exports.contains(&"init".to_string()) {
-                if let Some(ref engine) = inner.engine {
-                    engine
-                        .execute("init()")
-                        .map_err(|e| Error::ReloadFailed(e.to_string()))?;
-                }
+    /// Check the plugin's health.
+    ///
+    /// Calls the plugin's `health` export if it declares one; composite
+    /// plugins are unhealthy if any member is. Plugins that are not running
+    /// are unhealthy.
+    pub fn health_check(&self) -> PluginHealth {
+        let started = Instant::now();
+        let (name, engine) = {
+            let inner = self.inner.read();
+            let name = inner.info.name.clone();
+            if inner.info.state != LifecycleState::Running {
+                let status = HealthStatus::Unhealthy(format!("plugin is {}", inner.info.state));
+                return PluginHealth {
+                    name,
+                    status,
+                    duration: Duration::ZERO,
+                };
             }
+            let engine = inner
+                .manifest
+                .has_export(HEALTH_EXPORT)
+                .then(|| inner.engine.clone())
+                .flatten();
+            (name, engine)
+        };
+
+        let status = match (engine, self.composite.get()) {
+            (_, Some(composite)) => composite.health(),
+            (Some(engine), None) => HealthStatus::from_result(engine.call(HEALTH_EXPORT, &[])),
+            (None, None) => HealthStatus::Unchecked,
+        };
+        PluginHealth {
+            name,
+            status,
+            duration: started.elapsed(),
         }
+    }
+
+    /// Drop all cached call results.
+    pub fn clear_call_cache(&self) {
+        self.call_cache.clear();
+    }
+
+    /// Reload the plugin on a fresh engine from its current manifest and
+    /// bytecode.
+    ///
+    /// Blue/green like [`replace`](Self::replace): the old engine keeps
+    /// serving calls until the new one is built and initialized.
+    pub fn reload(&self) -> Result<()> {
+        let (manifest, bytecode, engine_config) = {
+            let inner = self.inner.read();
+            if inner.info.state == LifecycleState::Unloaded {
+                return Err(Error::PluginUnloaded);
+            }
+            let Some(engine_config) = inner.engine_config.clone() else {
+                return Err(Error::invalid_state(
+                    "Initialized",
+                    format!("{:?}", inner.info.state),
+                ));
+            };
+            (
+                inner.manifest.clone(),
+                inner.bytecode.clone(),
+                engine_config,
+            )
+        };
 
+        let swap = self.prepare_swap(manifest, bytecode, engine_config)?;
+        self.commit_swap(swap)?.retire();
         Ok(())
     }
 
     /// Check if the plugin exports a function.
     pub fn has_export(&self, name: &str) -> bool {
-        self.inner
-            .read()
-            .manifest
-            .exports
-            .contains(&name.to_string())
+        self.inner.read().manifest.has_export(name)
+    }
+
+    /// Get the plugin's start priority.
+    pub fn priority(&self) -> i32 {
+        self.inner.read().manifest.priority
     }
 
     /// Get all exported function names.
     pub fn exports(&self) -> Vec<String> {
-        self.inner.read().manifest.exports.clone()
+        self.inner
+            .read()
+            .manifest
+            .exports
+            .iter()
+            .map(|e| e.name.clone())
+            .collect()
     }
 
     /// Check if the plugin requires a capability.
@@ -322,17 +1321,236 @@ impl Plugin {
         self.inner.read().manifest.requires_capability(cap)
     }
 
+    /// Get the required host functions that are not registered.
+    pub fn missing_host_functions(&self) -> Vec<String> {
+        self.inner.read().missing_host_functions.clone()
+    }
+
+    /// Record which required host functions are not registered, returning
+    /// whether the set changed.
+    ///
+    /// Calls fail with [`Error::MissingHostFunction`] while any are missing.
+    pub fn set_missing_host_functions(&self, missing: Vec<String>) -> bool {
+        let mut inner = self.inner.write();
+        if inner.missing_host_functions == missing {
+            return false;
+        }
+        inner.missing_host_functions = missing;
+        true
+    }
+
+    /// Get the host functions the plugin requires.
+    pub fn required_host_functions(&self) -> Vec<String> {
+        self.inner.read().manifest.host_functions.clone()
+    }
+
+    /// Record how long loading the plugin took.
+    pub fn set_load_duration(&self, duration: Duration) {
+        self.inner.write().info.load_duration = Some(duration);
+    }
+
+    /// Declare another export, for builtins whose functions are added after
+    /// they were loaded.
+    #[cfg(any(test, feature = "testing"))]
+    pub(crate) fn add_export(&self, name: &str) {
+        let mut inner = self.inner.write();
+        if !inner.manifest.has_export(name) {
+            inner
+                .manifest
+                .exports
+                .push(crate::signature::ExportSpec::new(name));
+        }
+    }
+
+    /// Record which origin the plugin was installed from.
+    pub fn set_origin(&self, origin: PluginOrigin) {
+        self.inner.write().info.origin = origin;
+    }
+
+    /// Record where the plugin was loaded from.
+    pub fn set_paths(&self, manifest_path: Option<PathBuf>, entry_path: Option<PathBuf>) {
+        let mut inner = self.inner.write();
+        inner.info.manifest_path = manifest_path;
+        inner.info.entry_path = entry_path;
+    }
+
+    /// Rewrite recorded paths after `from` was renamed to `to`.
+    ///
+    /// Paths equal to `from` or inside it are updated. Returns whether any
+    /// path changed.
+    pub fn relocate(&self, from: &Path, to: &Path) -> bool {
+        let mut inner = self.inner.write();
+        let info = &mut inner.info;
+        let mut changed = false;
+
+        for path in [&mut info.manifest_path, &mut info.entry_path]
+            .into_iter()
+            .flatten()
+        {
+            if let Ok(rest) = path.strip_prefix(from) {
+                *path = if rest.as_os_str().is_empty() {
+                    to.to_path_buf()
+                } else {
+                    to.join(rest)
+                };
+                changed = true;
+            }
+        }
+
+        changed
+    }
+
+    /// Check that a filesystem access is inside the plugin's declared scope.
+    ///
+    /// Plugins declaring scopes fail to initialize, as the engine cannot
+    /// enforce them, so this mostly checks that the capability is declared.
+    pub fn check_path_access(&self, cap: &str, path: &Path) -> Result<()> {
+        let result = {
+            let inner = self.inner.read();
+            match inner.manifest.capability(cap) {
+                None => Err(Error::UndeclaredCapability(cap.to_string())),
+                Some(spec) if spec.allows_path(path) => Ok(()),
+                Some(_) => Err(Error::capability_denied(cap, path.display().to_string())),
+            }
+        };
+        self.audit_access(cap, &path.display().to_string(), &result);
+        result
+    }
+
+    /// Check that a network access is inside the plugin's declared scope.
+    pub fn check_host_access(&self, cap: &str, host: &str) -> Result<()> {
+        let result = {
+            let inner = self.inner.read();
+            match inner.manifest.capability(cap) {
+                None => Err(Error::UndeclaredCapability(cap.to_string())),
+                Some(spec) if spec.allows_host(host) => Ok(()),
+                Some(_) => Err(Error::capability_denied(cap, host)),
+            }
+        };
+        self.audit_access(cap, host, &result);
+        result
+    }
+
+    /// Record each capability `engine_config` grants, without a target.
+    fn audit_grants(&self, engine_config: &EngineConfig) {
+        let Some(sink) = self.audit.get() else {
+            return;
+        };
+        for name in CAPABILITY_NAMES {
+            let granted = fusabi_host::Capability::from_name(name)
+                .is_some_and(|cap| engine_config.capabilities.has(cap));
+            if granted {
+                sink.record(&AuditRecord::new(
+                    self.name(),
+                    *name,
+                    None,
+                    AuditOutcome::Succeeded,
+                ));
+            }
+        }
+    }
+
+    fn audit_access(&self, cap: &str, target: &str, result: &Result<()>) {
+        if let Some(sink) = self.audit.get() {
+            let outcome = match result {
+                Ok(()) => AuditOutcome::Succeeded,
+                Err(e) => AuditOutcome::Denied(e.to_string()),
+            };
+            sink.record(&AuditRecord::new(
+                self.name(),
+                cap,
+                Some(target.to_string()),
+                outcome,
+            ));
+        }
+    }
+
     /// Set the compiled bytecode.
     pub fn set_bytecode(&self, bytecode: Vec<u8>) {
         self.inner.write().bytecode = Some(bytecode);
     }
 
+    /// Record the source text the bytecode was compiled from, so errors
+    /// quote the code that runs even after the file changes.
+    pub(crate) fn set_source(&self, source: Option<String>) {
+        self.inner.write().source = source.map(Arc::from);
+    }
+
     /// Get the compiled bytecode if available.
     pub fn bytecode(&self) -> Option<Vec<u8>> {
         self.inner.read().bytecode.clone()
     }
 }
 
+#[cfg(feature = "wasm")]
+fn wasm_backend(
+    module: Option<&[u8]>,
+    limits: &fusabi_host::Limits,
+    host_functions: BTreeMap<String, HostFn>,
+) -> std::result::Result<Backend, String> {
+    let module = module.ok_or("wasm module not loaded")?;
+    WasmEngine::new(module, limits, host_functions)
+        .map(Backend::Wasm)
+        .map_err(|e| match e {
+            Error::InitializationFailed(reason) => reason,
+            other => other.to_string(),
+        })
+}
+
+#[cfg(not(feature = "wasm"))]
+fn wasm_backend(
+    _module: Option<&[u8]>,
+    _limits: &fusabi_host::Limits,
+    _host_functions: BTreeMap<String, HostFn>,
+) -> std::result::Result<Backend, String> {
+    Err("wasm plugins require the `wasm` feature".to_string())
+}
+
+fn check_capabilities(manifest: &Manifest, engine_config: &EngineConfig) -> Result<()> {
+    let caps = &engine_config.capabilities;
+    for required_cap in &manifest.capabilities {
+        // Enforced by the runtime, never granted to the engine
+        if delegation::is_runtime_capability(&required_cap.name) {
+            continue;
+        }
+        let cap = fusabi_host::Capability::from_name(&required_cap.name).ok_or_else(|| {
+            Error::invalid_manifest(format!("unknown capability: {}", required_cap))
+        })?;
+        // The engine only takes whole capabilities, so a scope would not
+        // bind the engine's own filesystem and network functions
+        if required_cap.is_scoped() {
+            return Err(Error::invalid_manifest(format!(
+                "capability {} declares a scope, which the engine cannot enforce; \
+                 declare it without paths or hosts",
+                required_cap
+            )));
+        }
+
+        if !caps.has(cap) {
+            return Err(Error::MissingCapability(required_cap.name.clone()));
+        }
+    }
+
+    Ok(())
+}
+
+/// Get the variables listed in `manifest` that `policy` allows.
+fn allowed_env(manifest: &Manifest, policy: Option<&EnvPolicy>) -> Vec<String> {
+    let Some(policy) = policy else {
+        return Vec::new();
+    };
+    if manifest.capability(ENV_READ).is_none() {
+        return Vec::new();
+    }
+    manifest
+        .env
+        .allow
+        .iter()
+        .filter(|name| policy.allows(name))
+        .cloned()
+        .collect()
+}
+
 impl std::fmt::Debug for Plugin {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let inner = self.inner.read();
@@ -345,10 +1563,36 @@ impl std::fmt::Debug for Plugin {
     }
 }
 
+/// Capabilities withheld from calls made through a restricted handle.
+struct Restriction {
+    removed: BTreeSet<String>,
+    /// Engine built without the removed capabilities, with the engine it
+    /// was built alongside.
+    engine: Mutex<Option<(Weak<Backend>, Arc<SpareEngine>)>>,
+}
+
+/// An engine created alongside the plugin's main one.
+struct SpareEngine {
+    engine: Backend,
+    /// Whether `init()` ran and the plugin exports `cleanup()`.
+    cleanup: bool,
+}
+
+impl Drop for SpareEngine {
+    /// Run `cleanup()` once the last call using the engine released it.
+    fn drop(&mut self) {
+        if self.cleanup {
+            let _ = self.engine.hook("cleanup");
+        }
+    }
+}
+
 /// Handle to a loaded plugin for safe concurrent access.
 #[derive(Clone)]
 pub struct PluginHandle {
     plugin: Arc<Plugin>,
+    restriction: Option<Arc<Restriction>>,
+    principal: Principal,
 }
 
 impl PluginHandle {
@@ -356,7 +1600,100 @@ impl PluginHandle {
     pub fn new(plugin: Plugin) -> Self {
         Self {
             plugin: Arc::new(plugin),
+            restriction: None,
+            principal: Principal::Host,
+        }
+    }
+
+    /// Get a handle to the same plugin whose calls are made on behalf of
+    /// `principal`.
+    ///
+    /// The runtime's [`CallAuthorizer`](crate::CallAuthorizer) checks calls
+    /// through the returned handle, and handles cloned from it, as made by
+    /// `principal`; other handles call as [`Principal::Host`].
+    pub fn as_principal(&self, principal: impl Into<String>) -> PluginHandle {
+        self.with_principal(Principal::named(principal))
+    }
+
+    pub(crate) fn with_principal(&self, principal: Principal) -> PluginHandle {
+        Self {
+            principal,
+            ..self.clone()
+        }
+    }
+
+    /// Get the principal calls through this handle are made on behalf of.
+    pub fn principal(&self) -> &Principal {
+        &self.principal
+    }
+
+    /// Get a handle to the same plugin whose calls run without `caps`.
+    ///
+    /// Calls through the returned handle, and handles cloned from it, are
+    /// served by a separate engine created with the reduced capability
+    /// set, so the removed capabilities are enforced by the engine rather
+    /// than checked by the caller. Restricting an already restricted handle
+    /// removes the union of both sets. Calls through [`inner`](Self::inner)
+    /// are not restricted.
+    ///
+    /// **The restricted engine does not share state with the plugin's main
+    /// engine.** It is created on the first call and again after each
+    /// reload, runs its own `init()` if the plugin is running, and runs
+    /// `cleanup()` when it is replaced or the last handle sharing the
+    /// restriction is dropped. Globals set through one handle are not seen
+    /// through the other, so restrict plugins whose exports are stateless.
+    ///
+    /// Fails with [`Error::InvalidArguments`] if a name in `caps` is not an
+    /// engine capability, so a misspelled name cannot leave a capability
+    /// granted. Builtin and composite plugins do not run in an engine and
+    /// cannot be restricted.
+    pub fn with_capabilities_removed<I, S>(&self, caps: I) -> Result<PluginHandle>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        if self.plugin.builtin.get().is_some() || self.plugin.composite.get().is_some() {
+            return Err(Error::invalid_state(
+                "engine-backed plugin",
+                "native plugin",
+            ));
         }
+        let mut removed = self
+            .restriction
+            .as_ref()
+            .map(|restriction| restriction.removed.clone())
+            .unwrap_or_default();
+        for name in caps {
+            let name = name.into();
+            if fusabi_host::Capability::from_name(&name).is_none() {
+                return Err(Error::invalid_arguments(format!(
+                    "unknown capability: {}",
+                    name
+                )));
+            }
+            removed.insert(name);
+        }
+        Ok(Self {
+            plugin: self.plugin.clone(),
+            restriction: Some(Arc::new(Restriction {
+                removed,
+                engine: Mutex::new(None),
+            })),
+            principal: self.principal.clone(),
+        })
+    }
+
+    /// Get the capabilities removed from calls through this handle, sorted.
+    pub fn removed_capabilities(&self) -> Vec<String> {
+        self.restriction
+            .as_ref()
+            .map(|restriction| restriction.removed.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Check if calls through this handle run with reduced capabilities.
+    pub fn is_restricted(&self) -> bool {
+        self.restriction.is_some()
     }
 
     /// Get the plugin ID.
@@ -369,14 +1706,64 @@ impl PluginHandle {
         self.plugin.name()
     }
 
+    /// Get the plugin version.
+    pub fn version(&self) -> String {
+        self.plugin.version()
+    }
+
     /// Get the plugin state.
     pub fn state(&self) -> LifecycleState {
         self.plugin.state()
     }
 
     /// Call a function on the plugin.
+    ///
+    /// Once the plugin is loaded into a [`PluginRuntime`](crate::PluginRuntime),
+    /// the call goes through the runtime's call authorizer, rate limits,
+    /// execution budgets and supervision as if made through
+    /// [`PluginRuntime::call_as`](crate::PluginRuntime::call_as) with the
+    /// handle's [`principal`](Self::principal).
     pub fn call(&self, function: &str, args: &[Value]) -> Result<Value> {
-        self.plugin.call(function, args)
+        self.guarded(function, GateArgs::Plain(args), &mut || {
+            self.call_unguarded(function, args)
+        })
+    }
+
+    /// Call an exported function with sensitive arguments.
+    pub fn call_sensitive(&self, function: &str, args: &[CallArg]) -> Result<Value> {
+        self.guarded(function, GateArgs::Sensitive(args), &mut || {
+            self.call_sensitive_unguarded(function, args)
+        })
+    }
+
+    /// Call a function without the checks of the managing runtime, for the
+    /// runtime's own call paths.
+    pub(crate) fn call_unguarded(&self, function: &str, args: &[Value]) -> Result<Value> {
+        self.plugin
+            .call_with(function, args, false, self.restriction.as_deref())
+    }
+
+    /// Call a function with sensitive arguments without the checks of the
+    /// managing runtime.
+    pub(crate) fn call_sensitive_unguarded(
+        &self,
+        function: &str,
+        args: &[CallArg],
+    ) -> Result<Value> {
+        self.plugin
+            .call_sensitive_with(function, args, self.restriction.as_deref())
+    }
+
+    fn guarded(
+        &self,
+        function: &str,
+        args: GateArgs<'_>,
+        call: &mut dyn FnMut() -> Result<Value>,
+    ) -> Result<Value> {
+        match self.plugin.gate.get().and_then(Weak::upgrade) {
+            Some(gate) => gate.guard(self, function, args, call),
+            None => call(),
+        }
     }
 
     /// Get plugin info.
@@ -384,11 +1771,43 @@ impl PluginHandle {
         self.plugin.info()
     }
 
+    /// Check the plugin's health.
+    pub fn health_check(&self) -> PluginHealth {
+        self.plugin.health_check()
+    }
+
     /// Check if the plugin exports a function.
     pub fn has_export(&self, name: &str) -> bool {
         self.plugin.has_export(name)
     }
 
+    /// Get the plugin's start priority.
+    pub fn priority(&self) -> i32 {
+        self.plugin.priority()
+    }
+
+    /// Get the schema of the plugin's configuration.
+    pub fn config_schema(&self) -> ConfigSchema {
+        self.plugin.config_schema()
+    }
+
+    /// Get statistics of the call result cache.
+    pub fn call_cache_stats(&self) -> CallCacheStats {
+        self.plugin.call_cache_stats()
+    }
+
+    /// Describe the plugin for admin tools.
+    pub fn describe(&self) -> PluginDescriptor {
+        self.plugin.describe()
+    }
+
+    /// Get the most recent captured output of the plugin, oldest first.
+    ///
+    /// Empty for in-process plugins unless host functions logged lines.
+    pub fn recent_logs(&self) -> Vec<LogLine> {
+        self.plugin.logger().recent()
+    }
+
     /// Get the underlying plugin.
     pub fn inner(&self) -> &Plugin {
         &self.plugin
@@ -401,6 +1820,8 @@ impl std::fmt::Debug for PluginHandle {
             .field("id", &self.id())
             .field("name", &self.name())
             .field("state", &self.state())
+            .field("removed_capabilities", &self.removed_capabilities())
+            .field("principal", &self.principal)
             .finish()
     }
 }
@@ -469,6 +1890,99 @@ mod tests {
         assert!(plugin.stop().is_err());
     }
 
+    #[test]
+    fn test_wasm_backend() {
+        let manifest = ManifestBuilder::new("wasm", "1.0.0")
+            .bytecode("plugin.wasm")
+            .build_unchecked();
+        let plugin = Plugin::new(manifest);
+
+        // Empty module: magic number and version only
+        plugin.set_bytecode(b"\0asm\x01\0\0\0".to_vec());
+        let result = plugin.initialize(EngineConfig::default());
+        if cfg!(feature = "wasm") {
+            result.unwrap();
+        } else {
+            assert!(matches!(result, Err(Error::InitializationFailed(_))));
+        }
+    }
+
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn test_wasm_host_functions() {
+        let manifest = ManifestBuilder::new("scaler", "1.0.0")
+            .bytecode("plugin.wasm")
+            .export("scaled")
+            .host_function("math.scale")
+            .build_unchecked();
+        let plugin = Plugin::new(manifest);
+        plugin.set_bytecode(
+            br#"(module
+                (import "host" "math.scale" (func $scale (param i64) (result i64)))
+                (func (export "scaled") (param i64) (result i64)
+                    (call $scale (local.get 0))))"#
+                .to_vec(),
+        );
+        let table = Arc::new(HostFunctionTable::new());
+        table.register_fn("math.scale", |args| match args {
+            [Value::Int(n)] => Ok(Value::Int(n * 2)),
+            _ => Err(Error::invalid_arguments("expected an int")),
+        });
+        plugin.set_host_functions(table.clone());
+        plugin.initialize(EngineConfig::default()).unwrap();
+        plugin.start().unwrap();
+        assert!(matches!(
+            plugin.call("scaled", &[Value::Int(4)]),
+            Ok(Value::Int(8))
+        ));
+
+        // A new implementation is bound once the engine is recreated
+        table.register_fn("math.scale", |args| match args {
+            [Value::Int(n)] => Ok(Value::Int(n * 10)),
+            _ => Err(Error::invalid_arguments("expected an int")),
+        });
+        assert!(matches!(
+            plugin.call("scaled", &[Value::Int(4)]),
+            Ok(Value::Int(8))
+        ));
+        plugin.reload().unwrap();
+        assert!(matches!(
+            plugin.call("scaled", &[Value::Int(4)]),
+            Ok(Value::Int(40))
+        ));
+    }
+
+    #[test]
+    fn test_process_isolation() {
+        let manifest = ManifestBuilder::new("isolated", "1.0.0")
+            .source("test.fsx")
+            .export("main")
+            .isolation(Isolation::Process)
+            .build_unchecked();
+
+        let plugin = Plugin::new(manifest.clone());
+        assert!(matches!(
+            plugin.initialize(EngineConfig::default()),
+            Err(Error::InitializationFailed(_))
+        ));
+
+        #[cfg(all(unix, feature = "isolation"))]
+        {
+            let plugin = Plugin::new(manifest);
+            plugin.set_isolation(Arc::new(
+                IsolationConfig::new("/bin/sh")
+                    .with_args(["-c", r#"while read line; do echo '{"ok":[1,"a"]}'; done"#]),
+            ));
+            plugin.initialize(EngineConfig::default()).unwrap();
+            plugin.start().unwrap();
+            assert!(matches!(
+                plugin.call("main", &[]).unwrap(),
+                Value::List(ref items) if items.len() == 2
+            ));
+            plugin.unload().unwrap();
+        }
+    }
+
     #[test]
     fn test_plugin_capabilities() {
         let manifest = ManifestBuilder::new("test", "1.0.0")
@@ -491,6 +2005,77 @@ mod tests {
         assert!(plugin.initialize(config).is_ok());
     }
 
+    #[test]
+    fn test_runtime_capabilities_not_granted_to_engine() {
+        let manifest = ManifestBuilder::new("framework", "1.0.0")
+            .source("framework.fsx")
+            .capability(delegation::PLUGINS_LOAD)
+            .capability("fs:read")
+            .build_unchecked();
+        let plugin = Plugin::new(manifest);
+
+        let config = EngineConfig::default().with_capabilities(
+            fusabi_host::Capabilities::none().with(fusabi_host::Capability::FsRead),
+        );
+        plugin.initialize(config).unwrap();
+        assert!(plugin.requires_capability(delegation::PLUGINS_LOAD));
+    }
+
+    #[test]
+    fn test_scoped_capability_access() {
+        use crate::manifest::CapabilitySpec;
+
+        let manifest = ManifestBuilder::new("test", "1.0.0")
+            .source("test.fsx")
+            .capability(CapabilitySpec::new("fs:read").with_paths(["/data"]))
+            .build_unchecked();
+        let plugin = Plugin::new(manifest);
+
+        assert!(plugin
+            .check_path_access("fs:read", Path::new("/data/file.txt"))
+            .is_ok());
+        assert!(matches!(
+            plugin.check_path_access("fs:read", Path::new("/etc/passwd")),
+            Err(Error::CapabilityDenied { .. })
+        ));
+        assert!(matches!(
+            plugin.check_host_access("net:request", "example.com"),
+            Err(Error::UndeclaredCapability(_))
+        ));
+
+        // The engine would get the whole capability, so the scope is refused
+        let config = EngineConfig::default()
+            .with_capabilities(Capabilities::none().with(fusabi_host::Capability::FsRead));
+        let err = plugin.initialize(config).unwrap_err();
+        assert!(matches!(err, Error::InvalidManifest(_)));
+        assert!(err.to_string().contains("cannot enforce"));
+        assert_ne!(plugin.state(), LifecycleState::Initialized);
+    }
+
+    #[test]
+    fn test_env_access() {
+        let manifest = ManifestBuilder::new("test", "1.0.0")
+            .source("test.fsx")
+            .capability(ENV_READ)
+            .env_var("PATH")
+            .env_var("HOME")
+            .build_unchecked();
+        let plugin = Plugin::new(manifest);
+
+        // Nothing is readable until the host allows it
+        assert!(plugin.allowed_env().is_empty());
+        assert!(matches!(
+            plugin.env_var("PATH"),
+            Err(Error::CapabilityDenied { .. })
+        ));
+
+        plugin.set_env_policy(EnvPolicy::new().allow("PATH").allow("USER"));
+        assert_eq!(plugin.allowed_env(), vec!["PATH"]);
+        assert_eq!(plugin.env_var("PATH").unwrap(), std::env::var("PATH").ok());
+        assert!(plugin.env_var("HOME").is_err());
+        assert!(plugin.env_var("USER").is_err());
+    }
+
     #[test]
     fn test_plugin_handle() {
         let manifest = create_test_manifest();
@@ -505,4 +2090,248 @@ mod tests {
         let handle2 = handle.clone();
         assert_eq!(handle.id(), handle2.id());
     }
+
+    #[test]
+    fn test_concurrent_calls() {
+        let handle = PluginHandle::new(Plugin::new(create_test_manifest()));
+        handle.inner().initialize(EngineConfig::default()).unwrap();
+        handle.inner().start().unwrap();
+
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let handle = handle.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..25 {
+                        handle.call("main", &[]).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        // Each call is counted exactly once
+        assert_eq!(handle.info().invocation_count, 100);
+    }
+
+    #[test]
+    fn test_pooled_calls() {
+        let plugin = Plugin::new(create_test_manifest());
+        assert!(plugin.set_autoscale(AutoscalePolicy::default()).is_err());
+
+        let manifest = ManifestBuilder::new("pooled", "1.0.0")
+            .source("pooled.fsx")
+            .export("main")
+            .stateless(true)
+            .build_unchecked();
+        let handle = PluginHandle::new(Plugin::new(manifest));
+        handle
+            .inner()
+            .set_autoscale(AutoscalePolicy::new(2, 3))
+            .unwrap();
+        handle.inner().initialize(EngineConfig::default()).unwrap();
+        handle.inner().start().unwrap();
+        assert_eq!(handle.inner().pool_signals().unwrap().engines, 1);
+
+        // The pool grows to its minimum on demand
+        handle.call("main", &[]).unwrap();
+        handle.call("main", &[]).unwrap();
+        let signals = handle.inner().pool_signals().unwrap();
+        assert_eq!((signals.engines, signals.busy, signals.queued), (2, 0, 0));
+
+        // Stopping drops the extra engines
+        handle.inner().stop().unwrap();
+        assert_eq!(handle.inner().pool_signals().unwrap().engines, 1);
+    }
+
+    #[test]
+    fn test_reload_swaps_engine() {
+        let plugin = Plugin::new(create_test_manifest());
+        assert!(matches!(plugin.reload(), Err(Error::InvalidState { .. })));
+        plugin.initialize(EngineConfig::default()).unwrap();
+        plugin.start().unwrap();
+
+        let engine = |plugin: &Plugin| plugin.inner.read().engine.clone().unwrap();
+        let old = engine(&plugin);
+        plugin.reload().unwrap();
+        assert!(!Arc::ptr_eq(&old, &engine(&plugin)));
+        assert_eq!(plugin.info().reload_count, 1);
+        assert_eq!(plugin.state(), LifecycleState::Running);
+
+        // A plugin unloaded while its replacement was built stays unloaded
+        let swap = plugin
+            .prepare_swap(create_test_manifest(), None, EngineConfig::default())
+            .unwrap();
+        plugin.unload().unwrap();
+        assert!(matches!(
+            plugin.commit_swap(swap),
+            Err(Error::PluginUnloaded)
+        ));
+        assert_eq!(plugin.info().reload_count, 1);
+    }
+
+    #[test]
+    fn test_call_cache() {
+        use crate::call_cache::CacheableExport;
+
+        let manifest = ManifestBuilder::new("cached", "1.0.0")
+            .source("cached.fsx")
+            .export("main")
+            .cacheable(CacheableExport::new("main"))
+            .build_unchecked();
+        let handle = PluginHandle::new(Plugin::new(manifest));
+        handle.inner().initialize(EngineConfig::default()).unwrap();
+        handle.inner().start().unwrap();
+
+        handle.call("main", &[]).unwrap();
+        handle.call("main", &[]).unwrap();
+        assert_eq!(handle.info().invocation_count, 1);
+        let stats = handle.call_cache_stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 1, 1));
+
+        // Reloading invalidates cached results
+        handle.inner().reload().unwrap();
+        assert_eq!(handle.call_cache_stats().entries, 0);
+        handle.call("main", &[]).unwrap();
+        assert_eq!(handle.info().invocation_count, 2);
+    }
+
+    #[test]
+    fn test_restricted_handle() {
+        use crate::call_cache::CacheableExport;
+        use fusabi_host::Capability;
+
+        let manifest = ManifestBuilder::new("restricted", "1.0.0")
+            .source("restricted.fsx")
+            .export("main")
+            .capability("fs:read")
+            .capability("net:request")
+            .cacheable(CacheableExport::new("main"))
+            .build_unchecked();
+        let handle = PluginHandle::new(Plugin::new(manifest));
+        let config = EngineConfig::default().with_capabilities(
+            Capabilities::none()
+                .with(Capability::FsRead)
+                .with(Capability::NetRequest),
+        );
+        handle.inner().initialize(config).unwrap();
+        handle.inner().start().unwrap();
+
+        assert!(matches!(
+            handle.with_capabilities_removed(["net:requests"]),
+            Err(Error::InvalidArguments(_))
+        ));
+        let reader = handle.with_capabilities_removed(["net:request"]).unwrap();
+        let nothing = reader.with_capabilities_removed(["fs:read"]).unwrap();
+        assert!(!handle.is_restricted());
+        assert_eq!(reader.removed_capabilities(), vec!["net:request"]);
+        assert_eq!(
+            nothing.removed_capabilities(),
+            vec!["fs:read", "net:request"]
+        );
+
+        // Restricted calls run on their own engine and skip the cache
+        reader.call("main", &[]).unwrap();
+        reader.call("main", &[]).unwrap();
+        assert_eq!(handle.info().invocation_count, 2);
+        let restriction = reader.restriction.as_ref().unwrap();
+        let engine = restriction.engine.lock().as_ref().unwrap().1.clone();
+        reader.clone().call("main", &[]).unwrap();
+        assert!(Arc::ptr_eq(
+            &engine,
+            &restriction.engine.lock().as_ref().unwrap().1
+        ));
+
+        // A reload rebuilds the restricted engine
+        handle.inner().reload().unwrap();
+        reader.call("main", &[]).unwrap();
+        assert!(!Arc::ptr_eq(
+            &engine,
+            &restriction.engine.lock().as_ref().unwrap().1
+        ));
+
+        let builtin = Plugin::new(create_test_manifest());
+        builtin.set_builtin(BuiltinPlugin::new("1.0.0"));
+        assert!(PluginHandle::new(builtin)
+            .with_capabilities_removed(["fs:read"])
+            .is_err());
+    }
+
+    #[test]
+    fn test_spare_engine_cleanup_waits_for_calls() {
+        use std::sync::atomic::AtomicUsize;
+
+        let cleanups = Arc::new(AtomicUsize::new(0));
+        let counter = cleanups.clone();
+        let builtin = BuiltinPlugin::new("1.0.0").with_function("cleanup", move |_| {
+            counter.fetch_add(1, Ordering::Relaxed);
+            Ok(Value::Null)
+        });
+        let spare = Arc::new(SpareEngine {
+            engine: Backend::Builtin(Arc::new(builtin)),
+            cleanup: true,
+        });
+
+        // Replacing the cached engine while a call holds it defers cleanup()
+        let in_call = spare.clone();
+        drop(spare);
+        assert_eq!(cleanups.load(Ordering::Relaxed), 0);
+        drop(in_call);
+        assert_eq!(cleanups.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_error_snippet_from_compiled_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("main.fsx");
+        std::fs::write(&path, "let a = 1\nlet b = edited\n").unwrap();
+
+        let manifest = ManifestBuilder::new("snippet", "1.0.0")
+            .export("run")
+            .build_unchecked();
+        let plugin = Plugin::new(manifest);
+        plugin.set_builtin(BuiltinPlugin::new("1.0.0").with_function("run", |_| {
+            Err(Error::execution_failed("unbound name at line 2, column 9"))
+        }));
+        plugin.set_paths(None, Some(path.clone()));
+        plugin.set_source(Some("let a = 1\nlet b = a +\n".to_string()));
+        plugin.initialize(EngineConfig::default()).unwrap();
+        plugin.start().unwrap();
+
+        // The snippet comes from the source that was compiled, not the file
+        let error = plugin.call("run", &[]).unwrap_err();
+        let span = error.span().unwrap();
+        assert_eq!(span.snippet.as_deref(), Some("let b = a +"));
+        assert_eq!(span.file.as_deref(), Some(path.as_path()));
+    }
+
+    #[test]
+    fn test_describe() {
+        let manifest = ManifestBuilder::new("typed", "1.2.0")
+            .source("typed.fsx")
+            .export(
+                crate::signature::ExportSpec::parse("process(input: string) -> record").unwrap(),
+            )
+            .export("init")
+            .capability("fs:read")
+            .build_unchecked();
+        let handle = PluginHandle::new(Plugin::new(manifest));
+
+        let descriptor = handle.describe();
+        assert_eq!(descriptor.hooks, vec!["init"]);
+        assert!(descriptor.routes.is_empty());
+        assert_eq!(descriptor.name, "typed");
+        assert_eq!(descriptor.state, LifecycleState::Created);
+        assert!(descriptor.limits.is_none());
+        assert!(descriptor.exports[0].signature.is_some());
+
+        #[cfg(feature = "serde")]
+        {
+            let json = serde_json::to_value(&descriptor).unwrap();
+            assert_eq!(json["exports"][0], "process(input: string) -> record");
+            assert_eq!(json["capabilities"][0], "fs:read");
+            assert_eq!(json["state"], "created");
+        }
+    }
 }
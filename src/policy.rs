@@ -0,0 +1,534 @@
+//! Host policy files restricting which plugins may load.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use fusabi_host::Limits;
+
+use crate::config_error::ConfigError;
+use crate::crypto::crypto_provider;
+use crate::error::{Error, Result};
+use crate::glob::wildcard_match;
+use crate::manifest::Manifest;
+use crate::namespace;
+
+/// Upper bounds on the engine limits of every plugin.
+///
+/// Limits above a bound are lowered to it; unset bounds leave the engine
+/// configuration alone.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case", default))]
+pub struct LimitCaps {
+    /// Maximum memory in bytes.
+    pub max_memory: Option<u64>,
+    /// Maximum call depth.
+    pub max_call_depth: Option<u64>,
+    /// Maximum instructions per call.
+    pub max_instructions: Option<u64>,
+}
+
+impl LimitCaps {
+    /// Lower `limits` to the bounds.
+    pub(crate) fn apply(&self, limits: &mut Limits) {
+        cap(&mut limits.max_memory, self.max_memory);
+        cap(&mut limits.max_call_depth, self.max_call_depth);
+        cap(&mut limits.max_instructions, self.max_instructions);
+    }
+}
+
+fn cap<T: Copy + TryInto<u64> + TryFrom<u64>>(value: &mut T, max: Option<u64>) {
+    let Some(max) = max else {
+        return;
+    };
+    let over = match (*value).try_into() {
+        Ok(current) => current > max,
+        Err(_) => true,
+    };
+    if over {
+        if let Ok(max) = T::try_from(max) {
+            *value = max;
+        }
+    }
+}
+
+/// Tag starting every signed message, naming the format.
+const SIGNATURE_DOMAIN: &[u8] = b"fusabi-plugin-signature-v1\0";
+
+/// Plugins tagged with one of `tags` must be signed by one of `keys`.
+///
+/// The signature covers [`SignatureRule::message`] of the manifest file and
+/// the plugin's source or bytecode file, and is read hex-encoded from the
+/// manifest path with `.sig` appended, e.g. `plugin.toml.sig`. Signatures
+/// are checked by the installed [`CryptoProvider`](crate::CryptoProvider).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct SignatureRule {
+    /// Tags requiring a signature.
+    pub tags: Vec<String>,
+    /// Signature scheme, e.g. `ed25519`.
+    pub algorithm: String,
+    /// Trusted public keys, hex-encoded.
+    pub keys: Vec<String>,
+}
+
+impl SignatureRule {
+    /// Require plugins tagged with any of `tags` to be signed with
+    /// `algorithm` by one of `keys`.
+    pub fn new<T, K>(tags: T, algorithm: impl Into<String>, keys: K) -> Self
+    where
+        T: IntoIterator,
+        T::Item: Into<String>,
+        K: IntoIterator,
+        K::Item: Into<String>,
+    {
+        Self {
+            tags: tags.into_iter().map(Into::into).collect(),
+            algorithm: algorithm.into(),
+            keys: keys.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Build the message a plugin's signature covers.
+    ///
+    /// The message starts with a fixed tag, and each file is prefixed with
+    /// its length as a big-endian `u64`, so bytes cannot be moved from one
+    /// file to the other without invalidating the signature. A plugin
+    /// without an entry file is signed with an empty one.
+    pub fn message(manifest: &[u8], entry: Option<&[u8]>) -> Vec<u8> {
+        let entry = entry.unwrap_or_default();
+        let mut message =
+            Vec::with_capacity(SIGNATURE_DOMAIN.len() + 16 + manifest.len() + entry.len());
+        message.extend_from_slice(SIGNATURE_DOMAIN);
+        for part in [manifest, entry] {
+            message.extend_from_slice(&(part.len() as u64).to_be_bytes());
+            message.extend_from_slice(part);
+        }
+        message
+    }
+
+    fn applies_to(&self, manifest: &Manifest) -> bool {
+        manifest.tags.iter().any(|tag| self.tags.contains(tag))
+    }
+}
+
+/// Rules every plugin must satisfy to load, usually read from a TOML file
+/// the host's operators maintain.
+///
+/// ```toml
+/// forbidden-capabilities = ["process:exec"]
+/// banned-plugins = ["legacy-*"]
+/// banned-namespaces = ["untrusted"]
+///
+/// [limits]
+/// max-memory = 67108864
+///
+/// [[signatures]]
+/// tags = ["payments"]
+/// algorithm = "ed25519"
+/// keys = ["3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c"]
+/// ```
+///
+/// The policy applies on top of the capability policies: a forbidden
+/// capability fails loading even if the engine configuration grants it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case", default))]
+pub struct PolicyFile {
+    /// Capabilities no plugin may declare.
+    pub forbidden_capabilities: Vec<String>,
+    /// Plugin names that may not load; `*` and `?` are wildcards.
+    pub banned_plugins: Vec<String>,
+    /// Namespaces whose plugins may not load.
+    pub banned_namespaces: Vec<String>,
+    /// Upper bounds on engine limits.
+    pub limits: LimitCaps,
+    /// Tags requiring signed plugins.
+    pub signatures: Vec<SignatureRule>,
+}
+
+impl PolicyFile {
+    /// Create a policy allowing everything.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a policy from TOML.
+    ///
+    /// Fails with [`Error::Config`] if the policy is malformed.
+    #[cfg(feature = "serde")]
+    pub fn from_toml(content: &str) -> Result<Self> {
+        toml::from_str(content).map_err(|e| {
+            let mut errors = ConfigError::new();
+            errors.push("policy", e.to_string());
+            Error::Config(errors)
+        })
+    }
+
+    /// Read a policy file.
+    #[cfg(feature = "serde")]
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::from_toml(&std::fs::read_to_string(path)?)
+    }
+
+    /// Forbid a capability for every plugin.
+    pub fn forbid_capability(mut self, capability: impl Into<String>) -> Self {
+        self.forbidden_capabilities.push(capability.into());
+        self
+    }
+
+    /// Ban plugins whose name matches `pattern`.
+    pub fn ban_plugin(mut self, pattern: impl Into<String>) -> Self {
+        self.banned_plugins.push(pattern.into());
+        self
+    }
+
+    /// Ban every plugin in a namespace.
+    pub fn ban_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.banned_namespaces.push(namespace.into());
+        self
+    }
+
+    /// Set the upper bounds on engine limits.
+    pub fn with_limits(mut self, limits: LimitCaps) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Require signatures for some tags.
+    pub fn require_signature(mut self, rule: SignatureRule) -> Self {
+        self.signatures.push(rule);
+        self
+    }
+
+    /// Check the policy's entries.
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = ConfigError::new();
+        let lists = [
+            ("forbidden_capabilities", &self.forbidden_capabilities),
+            ("banned_plugins", &self.banned_plugins),
+            ("banned_namespaces", &self.banned_namespaces),
+        ];
+        for (field, entries) in lists {
+            for (i, entry) in entries.iter().enumerate() {
+                errors.check(
+                    !entry.is_empty(),
+                    &format!("{}[{}]", field, i),
+                    "must not be empty",
+                );
+            }
+        }
+        for (i, rule) in self.signatures.iter().enumerate() {
+            let path = |field: &str| format!("signatures[{}].{}", i, field);
+            errors.check(!rule.tags.is_empty(), &path("tags"), "must not be empty");
+            errors.check(
+                !rule.algorithm.is_empty(),
+                &path("algorithm"),
+                "must not be empty",
+            );
+            errors.check(!rule.keys.is_empty(), &path("keys"), "must not be empty");
+            for (j, key) in rule.keys.iter().enumerate() {
+                errors.check(
+                    decode_hex(key).is_some(),
+                    &format!("signatures[{}].keys[{}]", i, j),
+                    "must be hex-encoded",
+                );
+            }
+        }
+        errors.into_result()
+    }
+
+    /// Lower engine limits to the policy's bounds.
+    pub(crate) fn cap_limits(&self, limits: &mut Limits) {
+        self.limits.apply(limits);
+    }
+
+    /// Find the manifest's violations of the name, namespace and
+    /// capability rules.
+    pub(crate) fn check_manifest(&self, manifest: &Manifest) -> Vec<PolicyViolation> {
+        let mut violations = Vec::new();
+        if let Some(pattern) = self
+            .banned_plugins
+            .iter()
+            .find(|pattern| wildcard_match(pattern, &manifest.name))
+        {
+            violations.push(PolicyViolation::new(
+                "banned-plugins",
+                format!("name {} matches banned pattern {}", manifest.name, pattern),
+            ));
+        }
+        if let (Some(namespace), _) = namespace::split(&manifest.name) {
+            if self.banned_namespaces.iter().any(|n| n == namespace) {
+                violations.push(PolicyViolation::new(
+                    "banned-namespaces",
+                    format!("namespace {} is banned", namespace),
+                ));
+            }
+        }
+        for spec in &manifest.capabilities {
+            if self.forbidden_capabilities.contains(&spec.name) {
+                violations.push(PolicyViolation::new(
+                    "forbidden-capabilities",
+                    format!("capability {} is forbidden", spec.name),
+                ));
+            }
+        }
+        violations
+    }
+
+    /// Find the signature rules the plugin's files do not satisfy.
+    ///
+    /// Plugins without a manifest file cannot carry a signature, so they
+    /// violate every rule that applies to them.
+    pub(crate) fn check_signature(
+        &self,
+        manifest: &Manifest,
+        manifest_path: Option<&Path>,
+        entry_path: Option<&Path>,
+    ) -> Vec<PolicyViolation> {
+        self.signatures
+            .iter()
+            .filter(|rule| rule.applies_to(manifest))
+            .filter_map(|rule| {
+                verify(rule, manifest_path, entry_path)
+                    .err()
+                    .map(|reason| PolicyViolation::new("signatures", reason))
+            })
+            .collect()
+    }
+}
+
+/// Check the plugin's files against one of the rule's keys.
+fn verify(
+    rule: &SignatureRule,
+    manifest_path: Option<&Path>,
+    entry_path: Option<&Path>,
+) -> std::result::Result<(), String> {
+    let manifest_path = manifest_path.ok_or("plugin has no manifest file to sign")?;
+    let sig_path = signature_path(manifest_path);
+    let signature = std::fs::read_to_string(&sig_path)
+        .map_err(|e| format!("cannot read signature {}: {}", sig_path.display(), e))?;
+    let signature = decode_hex(signature.trim())
+        .ok_or_else(|| format!("signature {} is not hex-encoded", sig_path.display()))?;
+
+    let manifest = std::fs::read(manifest_path).map_err(|e| e.to_string())?;
+    let entry = match entry_path {
+        Some(entry_path) => Some(std::fs::read(entry_path).map_err(|e| e.to_string())?),
+        None => None,
+    };
+    let message = SignatureRule::message(&manifest, entry.as_deref());
+
+    let provider = crypto_provider();
+    let mut last_error = None;
+    for key in rule.keys.iter().filter_map(|key| decode_hex(key)) {
+        match provider.verify_signature(&rule.algorithm, &key, &message, &signature) {
+            Ok(()) => return Ok(()),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(match last_error {
+        Some(Error::SignatureInvalid(reason)) => reason,
+        Some(e) => e.to_string(),
+        None => "no trusted key is configured".to_string(),
+    })
+}
+
+/// Get the path of the signature of a manifest file.
+pub(crate) fn signature_path(manifest_path: &Path) -> PathBuf {
+    let mut path = manifest_path.as_os_str().to_owned();
+    path.push(".sig");
+    PathBuf::from(path)
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.is_empty() || hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// A rule of the [`PolicyFile`] a plugin breaks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyViolation {
+    /// Policy entry broken, e.g. `forbidden-capabilities`.
+    pub rule: String,
+    /// What the plugin does that the rule forbids.
+    pub reason: String,
+}
+
+impl PolicyViolation {
+    fn new(rule: &str, reason: impl Into<String>) -> Self {
+        Self {
+            rule: rule.to_string(),
+            reason: reason.into(),
+        }
+    }
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.rule, self.reason)
+    }
+}
+
+/// Fail with [`Error::PolicyViolation`] if any violation was found.
+pub(crate) fn enforce(plugin: &str, violations: Vec<PolicyViolation>) -> Result<()> {
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::policy_violation(plugin, violations))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::ManifestBuilder;
+
+    #[test]
+    fn test_manifest_violations() {
+        let policy = PolicyFile::new()
+            .forbid_capability("process:exec")
+            .ban_plugin("legacy-*")
+            .ban_namespace("untrusted");
+
+        let manifest = ManifestBuilder::new("sync", "1.0.0")
+            .capability("fs:read")
+            .build_unchecked();
+        assert!(policy.check_manifest(&manifest).is_empty());
+
+        let manifest = ManifestBuilder::new("legacy-sync", "1.0.0")
+            .capability("process:exec")
+            .build_unchecked();
+        let rules: Vec<_> = policy
+            .check_manifest(&manifest)
+            .into_iter()
+            .map(|v| v.rule)
+            .collect();
+        assert_eq!(rules, vec!["banned-plugins", "forbidden-capabilities"]);
+
+        let manifest = ManifestBuilder::new("untrusted/sync", "1.0.0").build_unchecked();
+        let err = enforce("untrusted/sync", policy.check_manifest(&manifest)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "plugin untrusted/sync violates the load policy: \
+             banned-namespaces: namespace untrusted is banned"
+        );
+    }
+
+    #[test]
+    fn test_limit_caps() {
+        let mut limits = Limits::default();
+        limits.max_call_depth = 500;
+        LimitCaps {
+            max_call_depth: Some(64),
+            ..LimitCaps::default()
+        }
+        .apply(&mut limits);
+        assert_eq!(limits.max_call_depth, 64);
+
+        LimitCaps {
+            max_call_depth: Some(128),
+            ..LimitCaps::default()
+        }
+        .apply(&mut limits);
+        assert_eq!(limits.max_call_depth, 64);
+    }
+
+    #[test]
+    fn test_required_signature() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest_path = dir.path().join("plugin.toml");
+        std::fs::write(&manifest_path, "name = \"pay\"").unwrap();
+
+        let policy = PolicyFile::new().require_signature(SignatureRule::new(
+            ["payments"],
+            "ed25519",
+            ["00"],
+        ));
+        let untagged = ManifestBuilder::new("pay", "1.0.0").build_unchecked();
+        assert!(policy
+            .check_signature(&untagged, Some(&manifest_path), None)
+            .is_empty());
+
+        let tagged = ManifestBuilder::new("pay", "1.0.0")
+            .tag("payments")
+            .build_unchecked();
+        let violations = policy.check_signature(&tagged, Some(&manifest_path), None);
+        assert!(violations[0].reason.contains("cannot read signature"));
+
+        // The default crypto provider verifies no signatures
+        std::fs::write(signature_path(&manifest_path), "abcd\n").unwrap();
+        let violations = policy.check_signature(&tagged, Some(&manifest_path), None);
+        assert!(violations[0].reason.contains("does not support ed25519"));
+        assert_eq!(
+            policy.check_signature(&tagged, None, None)[0].reason,
+            "plugin has no manifest file to sign"
+        );
+    }
+
+    #[test]
+    fn test_signature_message() {
+        let message = SignatureRule::message(b"name = \"pay\"", Some(b"let x = 1"));
+        assert!(message.starts_with(SIGNATURE_DOMAIN));
+
+        // Moving bytes between the manifest and the entry changes the message
+        assert_ne!(
+            message,
+            SignatureRule::message(b"name = \"pay\"let", Some(b" x = 1"))
+        );
+        assert_eq!(
+            SignatureRule::message(b"a", None),
+            SignatureRule::message(b"a", Some(b""))
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let policy = PolicyFile::new()
+            .ban_plugin("")
+            .require_signature(SignatureRule::new(["payments"], "ed25519", ["xyz"]));
+        match policy.validate().unwrap_err() {
+            Error::Config(errors) => {
+                assert_eq!(
+                    errors.paths(),
+                    vec!["banned_plugins[0]", "signatures[0].keys[0]"]
+                )
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_from_toml() {
+        let policy = PolicyFile::from_toml(
+            r#"
+            forbidden-capabilities = ["process:exec"]
+            banned-namespaces = ["untrusted"]
+
+            [limits]
+            max-memory = 1024
+
+            [[signatures]]
+            tags = ["payments"]
+            algorithm = "ed25519"
+            keys = ["00ff"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(policy.forbidden_capabilities, vec!["process:exec"]);
+        assert_eq!(policy.limits.max_memory, Some(1024));
+        assert_eq!(policy.signatures[0].keys, vec!["00ff"]);
+        assert!(policy.validate().is_ok());
+
+        match PolicyFile::from_toml("banned-plugins = 3").unwrap_err() {
+            Error::Config(errors) => assert_eq!(errors.paths(), vec!["policy"]),
+            other => panic!("unexpected error: {}", other),
+        }
+    }
+}
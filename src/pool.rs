@@ -0,0 +1,383 @@
+//! Autoscaling engine pools for stateless plugins.
+
+use std::ops::Deref;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use parking_lot::{Condvar, Mutex};
+
+use crate::config_error::ConfigError;
+use crate::error::Result;
+
+/// How the engine pool of a stateless plugin grows and shrinks.
+///
+/// Utilization is the share of engines busy with a call. Calls get a new
+/// engine while the pool is below `min_engines`, or below `max_engines`
+/// when utilization has reached `scale_up_at`; at `max_engines` they wait
+/// for an engine to be released. Engines idle for `idle_timeout` are
+/// removed while utilization is below `scale_down_at`, down to
+/// `min_engines`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AutoscalePolicy {
+    /// Engines kept even when idle.
+    pub min_engines: usize,
+    /// Engines the pool never exceeds.
+    pub max_engines: usize,
+    /// Utilization at or above which the pool grows.
+    pub scale_up_at: f64,
+    /// Utilization below which idle engines are removed.
+    pub scale_down_at: f64,
+    /// How long an engine stays idle before it may be removed.
+    pub idle_timeout: Duration,
+}
+
+impl AutoscalePolicy {
+    /// Create a policy keeping between `min_engines` and `max_engines`.
+    pub fn new(min_engines: usize, max_engines: usize) -> Self {
+        Self {
+            min_engines,
+            max_engines,
+            scale_up_at: 0.8,
+            scale_down_at: 0.25,
+            idle_timeout: Duration::from_secs(60),
+        }
+    }
+
+    /// Set the utilization thresholds for growing and shrinking.
+    pub fn with_thresholds(mut self, scale_up_at: f64, scale_down_at: f64) -> Self {
+        self.scale_up_at = scale_up_at;
+        self.scale_down_at = scale_down_at;
+        self
+    }
+
+    /// Set how long an engine stays idle before it may be removed.
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    /// Check the bounds and thresholds.
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = ConfigError::new();
+        errors.check(
+            self.max_engines > 0,
+            "max_engines",
+            "must be greater than zero",
+        );
+        errors.check(
+            self.min_engines <= self.max_engines,
+            "min_engines",
+            format!("must not exceed max_engines ({})", self.max_engines),
+        );
+        errors.check(
+            (0.0..=1.0).contains(&self.scale_up_at),
+            "scale_up_at",
+            "must be between 0 and 1",
+        );
+        errors.check(
+            (0.0..=1.0).contains(&self.scale_down_at),
+            "scale_down_at",
+            "must be between 0 and 1",
+        );
+        errors.check(
+            self.scale_down_at < self.scale_up_at,
+            "scale_down_at",
+            "must be below scale_up_at",
+        );
+        errors.into_result()
+    }
+}
+
+impl Default for AutoscalePolicy {
+    fn default() -> Self {
+        Self::new(1, 4)
+    }
+}
+
+/// Load of a plugin's engine pool.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolSignals {
+    /// Engines in the pool, including those being created.
+    pub engines: usize,
+    /// Engines running a call.
+    pub busy: usize,
+    /// Calls waiting for an engine.
+    pub queued: usize,
+}
+
+impl PoolSignals {
+    /// Get the share of engines running a call.
+    pub fn utilization(&self) -> f64 {
+        if self.engines == 0 {
+            0.0
+        } else {
+            self.busy as f64 / self.engines as f64
+        }
+    }
+}
+
+/// Called with the pool's load whenever engines are added or removed.
+pub(crate) type ScaleListener = Arc<dyn Fn(PoolSignals) + Send + Sync>;
+
+struct PoolState<E> {
+    /// Idle engines, least recently used first.
+    idle: Vec<(E, Instant)>,
+    busy: usize,
+    queued: usize,
+}
+
+impl<E> PoolState<E> {
+    fn signals(&self) -> PoolSignals {
+        PoolSignals {
+            engines: self.idle.len() + self.busy,
+            busy: self.busy,
+            queued: self.queued,
+        }
+    }
+}
+
+/// Engines of one plugin version, created on demand.
+pub(crate) struct EnginePool<E> {
+    policy: AutoscalePolicy,
+    state: Mutex<PoolState<E>>,
+    released: Condvar,
+    on_scale: Option<ScaleListener>,
+}
+
+impl<E> EnginePool<E> {
+    /// Create a pool seeded with the plugin's current engine, if any.
+    pub(crate) fn new(
+        policy: AutoscalePolicy,
+        engine: Option<E>,
+        on_scale: Option<ScaleListener>,
+    ) -> Self {
+        Self {
+            policy,
+            state: Mutex::new(PoolState {
+                idle: engine.map(|e| (e, Instant::now())).into_iter().collect(),
+                busy: 0,
+                queued: 0,
+            }),
+            released: Condvar::new(),
+            on_scale,
+        }
+    }
+
+    /// Create a pool with the same policy for a new plugin version.
+    pub(crate) fn renew(&self, engine: Option<E>) -> Self {
+        Self::new(self.policy.clone(), engine, self.on_scale.clone())
+    }
+
+    /// Get the pool's policy.
+    pub(crate) fn policy(&self) -> &AutoscalePolicy {
+        &self.policy
+    }
+
+    /// Get the pool's current load.
+    pub(crate) fn signals(&self) -> PoolSignals {
+        self.state.lock().signals()
+    }
+
+    /// Take an engine for a call, creating one with `create` if the pool
+    /// should grow, or waiting for one to be released at `max_engines`.
+    pub(crate) fn acquire(&self, create: impl FnOnce() -> Result<E>) -> Result<Lease<'_, E>> {
+        let mut state = self.state.lock();
+        loop {
+            let signals = state.signals();
+            let grow = signals.engines < self.policy.min_engines
+                || (signals.engines < self.policy.max_engines
+                    && (state.idle.is_empty() || signals.utilization() >= self.policy.scale_up_at));
+            if grow {
+                state.busy += 1;
+                drop(state);
+                return match create() {
+                    Ok(engine) => {
+                        self.scaled();
+                        Ok(Lease {
+                            pool: self,
+                            engine: Some(engine),
+                        })
+                    }
+                    Err(e) => {
+                        self.state.lock().busy -= 1;
+                        self.released.notify_one();
+                        Err(e)
+                    }
+                };
+            }
+            if let Some((engine, _)) = state.idle.pop() {
+                state.busy += 1;
+                return Ok(Lease {
+                    pool: self,
+                    engine: Some(engine),
+                });
+            }
+            state.queued += 1;
+            self.released.wait(&mut state);
+            state.queued -= 1;
+        }
+    }
+
+    /// Remove engines idle for longer than the policy allows, returning how
+    /// many were removed.
+    pub(crate) fn trim(&self) -> usize {
+        let removed = {
+            let mut state = self.state.lock();
+            self.trim_locked(&mut state)
+        };
+        if removed > 0 {
+            self.scaled();
+        }
+        removed
+    }
+
+    fn trim_locked(&self, state: &mut PoolState<E>) -> usize {
+        if state.signals().utilization() >= self.policy.scale_down_at {
+            return 0;
+        }
+        let floor = self.policy.min_engines.max(1);
+        let mut removed = 0;
+        while state.signals().engines > floor
+            && state
+                .idle
+                .first()
+                .is_some_and(|(_, since)| since.elapsed() >= self.policy.idle_timeout)
+        {
+            state.idle.remove(0);
+            removed += 1;
+        }
+        removed
+    }
+
+    fn release(&self, engine: E) {
+        let removed = {
+            let mut state = self.state.lock();
+            state.busy -= 1;
+            state.idle.push((engine, Instant::now()));
+            self.released.notify_one();
+            self.trim_locked(&mut state)
+        };
+        if removed > 0 {
+            self.scaled();
+        }
+    }
+
+    fn scaled(&self) {
+        if let Some(ref on_scale) = self.on_scale {
+            on_scale(self.signals());
+        }
+    }
+}
+
+/// An engine taken from a pool, returned to it when dropped.
+pub(crate) struct Lease<'a, E> {
+    pool: &'a EnginePool<E>,
+    engine: Option<E>,
+}
+
+impl<E> Deref for Lease<'_, E> {
+    type Target = E;
+
+    fn deref(&self) -> &E {
+        self.engine
+            .as_ref()
+            .expect("lease holds an engine until dropped")
+    }
+}
+
+impl<E> Drop for Lease<'_, E> {
+    fn drop(&mut self) {
+        if let Some(engine) = self.engine.take() {
+            self.pool.release(engine);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_policy_validation() {
+        assert!(AutoscalePolicy::default().validate().is_ok());
+
+        let err = AutoscalePolicy::new(5, 2)
+            .with_thresholds(0.2, 0.5)
+            .validate()
+            .unwrap_err();
+        match err {
+            crate::Error::Config(errors) => {
+                assert_eq!(errors.paths(), vec!["min_engines", "scale_down_at"])
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+    }
+
+    #[test]
+    fn test_pool_grows_under_load() {
+        let pool = EnginePool::new(AutoscalePolicy::new(1, 2), Some(0), None);
+        let created = AtomicUsize::new(0);
+        let create = || Ok(created.fetch_add(1, Ordering::SeqCst) + 1);
+
+        let first = pool.acquire(create).unwrap();
+        assert_eq!(*first, 0);
+        let second = pool.acquire(create).unwrap();
+        assert_eq!(*second, 1);
+        assert_eq!(
+            pool.signals(),
+            PoolSignals {
+                engines: 2,
+                busy: 2,
+                queued: 0
+            }
+        );
+
+        drop(first);
+        assert_eq!(*pool.acquire(create).unwrap(), 0);
+        assert_eq!(created.load(Ordering::SeqCst), 1);
+        assert_eq!(pool.signals().busy, 1);
+    }
+
+    #[test]
+    fn test_pool_queues_at_max() {
+        let pool = Arc::new(EnginePool::new(AutoscalePolicy::new(1, 1), Some(0), None));
+        let lease = pool.acquire(|| Ok(1)).unwrap();
+
+        let waiter = {
+            let pool = pool.clone();
+            std::thread::spawn(move || *pool.acquire(|| Ok(2)).unwrap())
+        };
+        while pool.signals().queued == 0 {
+            std::thread::yield_now();
+        }
+        assert_eq!(pool.signals().utilization(), 1.0);
+
+        drop(lease);
+        assert_eq!(waiter.join().unwrap(), 0);
+        assert_eq!(pool.signals().engines, 1);
+    }
+
+    #[test]
+    fn test_pool_shrinks_when_idle() {
+        let scaled = Arc::new(Mutex::new(Vec::new()));
+        let listener: ScaleListener = {
+            let scaled = scaled.clone();
+            Arc::new(move |signals| scaled.lock().push(signals.engines))
+        };
+        let pool = EnginePool::new(
+            AutoscalePolicy::new(1, 3).with_idle_timeout(Duration::ZERO),
+            Some(0),
+            Some(listener),
+        );
+
+        {
+            let _a = pool.acquire(|| Ok(1)).unwrap();
+            let _b = pool.acquire(|| Ok(2)).unwrap();
+            let _c = pool.acquire(|| Ok(3)).unwrap();
+        }
+        assert_eq!(pool.signals().engines, 1);
+        assert_eq!(pool.trim(), 0);
+        assert_eq!(*scaled.lock(), vec![2, 3, 1]);
+    }
+}
@@ -0,0 +1,94 @@
+//! Call-frequency profiles used to pre-warm hot plugins.
+
+use std::collections::HashMap;
+
+/// Number of calls per plugin and export.
+///
+/// Taken from a running runtime with `PluginRuntime::call_profile()`, a
+/// profile can be saved and fed back after the next deployment so the
+/// plugins users hit most are warmed before their first call.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CallProfile {
+    /// Call counts keyed by plugin, then export.
+    pub plugins: HashMap<String, HashMap<String, u64>>,
+}
+
+impl CallProfile {
+    /// Create an empty profile.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add calls of an export.
+    pub fn record(&mut self, plugin: &str, export: &str, calls: u64) {
+        *self
+            .plugins
+            .entry(plugin.to_string())
+            .or_default()
+            .entry(export.to_string())
+            .or_default() += calls;
+    }
+
+    /// Add all counts of another profile.
+    pub fn merge(&mut self, other: &CallProfile) {
+        for (plugin, exports) in &other.plugins {
+            for (export, calls) in exports {
+                self.record(plugin, export, *calls);
+            }
+        }
+    }
+
+    /// Get the total number of calls to a plugin.
+    pub fn total(&self, plugin: &str) -> u64 {
+        self.plugins
+            .get(plugin)
+            .map_or(0, |exports| exports.values().sum())
+    }
+
+    /// Get the `n` most-called plugins, most calls first.
+    ///
+    /// Plugins that were never called are left out; ties are ordered by name.
+    pub fn hottest(&self, n: usize) -> Vec<String> {
+        let mut totals: Vec<(&String, u64)> = self
+            .plugins
+            .keys()
+            .map(|plugin| (plugin, self.total(plugin)))
+            .filter(|(_, calls)| *calls > 0)
+            .collect();
+        totals.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        totals
+            .into_iter()
+            .take(n)
+            .map(|(plugin, _)| plugin.clone())
+            .collect()
+    }
+
+    /// Check if no calls were recorded.
+    pub fn is_empty(&self) -> bool {
+        self.plugins.values().all(|exports| exports.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hottest() {
+        let mut profile = CallProfile::new();
+        profile.record("format", "date", 40);
+        profile.record("format", "number", 20);
+        profile.record("parse", "csv", 50);
+        profile.record("lint", "check", 5);
+        profile.record("idle", "never", 0);
+
+        let mut previous = CallProfile::new();
+        previous.record("lint", "check", 100);
+        profile.merge(&previous);
+
+        assert_eq!(profile.total("format"), 60);
+        assert_eq!(profile.hottest(2), vec!["lint", "format"]);
+        assert_eq!(profile.hottest(10).len(), 3);
+    }
+}
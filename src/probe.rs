@@ -0,0 +1,190 @@
+//! Pre-flight probing of what the engine build supports.
+
+use std::collections::BTreeSet;
+
+use fusabi_host::{Capabilities, Capability, Engine, EngineConfig, Limits};
+
+use crate::delegation;
+use crate::environment::ENV_READ;
+use crate::error::{Error, Result};
+use crate::features::Feature;
+use crate::host_info;
+use crate::manifest::{Isolation, Manifest};
+
+/// Capability names plugins may request, probed against the engine.
+pub(crate) const CAPABILITY_NAMES: &[&str] = &[
+    "fs:read",
+    "fs:write",
+    "fs:delete",
+    "fs:metadata",
+    "net:request",
+    "net:listen",
+    "net:dns",
+    "sys:env",
+    "sys:exec",
+    "sys:time",
+    "plugin:call",
+    "plugin:message",
+];
+
+/// Backend running plugins inside the host process.
+pub(crate) const BACKEND_IN_PROCESS: &str = "in-process";
+/// Backend running plugins in worker processes.
+pub(crate) const BACKEND_PROCESS: &str = "process";
+/// Backend running WebAssembly plugins.
+pub(crate) const BACKEND_WASM: &str = "wasm";
+
+/// What the engine build supports, from
+/// [`PluginLoader::probe_engine`](crate::PluginLoader::probe_engine).
+#[derive(Debug, Clone)]
+pub struct EngineProbe {
+    /// Compiler version reported by the engine, if it could be determined.
+    pub engine_version: Option<String>,
+    /// Well-known capability names the engine can grant, sorted.
+    ///
+    /// Capabilities the runtime enforces itself, such as `sys:env` and
+    /// `plugins:load`, are not listed; see
+    /// [`supports_capability`](Self::supports_capability).
+    pub capabilities: BTreeSet<String>,
+    /// Limits of the base engine configuration.
+    pub limits: Limits,
+    /// Backends plugins can run on: `in-process`, `process` and `wasm`.
+    pub backends: Vec<&'static str>,
+    /// Why an engine could not be created with the base configuration.
+    pub engine_error: Option<String>,
+}
+
+impl EngineProbe {
+    /// Probe the engine with a base configuration.
+    pub(crate) fn run(engine_config: &EngineConfig) -> Self {
+        let engine_error = Engine::new(engine_config.clone())
+            .err()
+            .map(|e| e.to_string());
+        let capabilities = if engine_error.is_none() {
+            engine_capabilities(engine_config)
+        } else {
+            BTreeSet::new()
+        };
+
+        let mut backends = Vec::new();
+        if engine_error.is_none() {
+            backends.push(BACKEND_IN_PROCESS);
+        }
+        if Feature::Isolation.is_enabled() {
+            backends.push(BACKEND_PROCESS);
+        }
+        if Feature::Wasm.is_enabled() {
+            backends.push(BACKEND_WASM);
+        }
+
+        Self {
+            engine_version: host_info::engine_version(),
+            capabilities,
+            limits: engine_config.limits.clone(),
+            backends,
+            engine_error,
+        }
+    }
+
+    /// Check if a plugin may be granted a capability, either by the engine
+    /// or by the runtime enforcing it.
+    pub fn supports_capability(&self, name: &str) -> bool {
+        self.capabilities.contains(name) || is_runtime_enforced(name)
+    }
+
+    /// Check if plugins can run on a backend.
+    pub fn supports_backend(&self, backend: &str) -> bool {
+        self.backends.contains(&backend)
+    }
+
+    /// Check the engine build can run a plugin, before creating its engine.
+    pub fn check_manifest(&self, manifest: &Manifest) -> Result<()> {
+        if let Some(spec) = manifest
+            .capabilities
+            .iter()
+            .find(|spec| !self.supports_capability(&spec.name))
+        {
+            return Err(Error::invalid_manifest(format!(
+                "capability {} is not supported by the engine",
+                spec.name
+            )));
+        }
+
+        let backend = if manifest.is_composite() {
+            return Feature::Serde.require("composite plugins");
+        } else if manifest.uses_wasm() {
+            Feature::Wasm.require("wasm plugins")?;
+            BACKEND_WASM
+        } else if manifest.isolation == Isolation::Process {
+            Feature::Isolation.require("process isolation")?;
+            BACKEND_PROCESS
+        } else {
+            BACKEND_IN_PROCESS
+        };
+        if !self.supports_backend(backend) {
+            return Err(Error::invalid_manifest(format!(
+                "the {} backend is not available in this build",
+                backend
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Check if a capability is enforced by the runtime instead of the engine.
+fn is_runtime_enforced(name: &str) -> bool {
+    name == ENV_READ || delegation::is_runtime_capability(name)
+}
+
+/// Find the well-known capabilities the engine accepts, by creating an
+/// engine granted each one on top of the base configuration.
+fn engine_capabilities(engine_config: &EngineConfig) -> BTreeSet<String> {
+    CAPABILITY_NAMES
+        .iter()
+        .filter(|name| !is_runtime_enforced(name))
+        .filter(|name| {
+            Capability::from_name(name).is_some_and(|cap| {
+                let config = engine_config
+                    .clone()
+                    .with_capabilities(Capabilities::none().with(cap));
+                Engine::new(config).is_ok()
+            })
+        })
+        .map(|name| name.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_probe_engine() {
+        let probe = EngineProbe::run(&EngineConfig::default());
+        assert!(probe.supports_capability("fs:read"));
+        assert!(probe.capabilities.contains("fs:read"));
+        assert!(!probe.supports_capability("gpu:compute"));
+
+        // Enforced by the runtime, whatever the engine grants
+        for name in [ENV_READ, delegation::PLUGINS_LOAD] {
+            assert!(probe.supports_capability(name));
+            assert!(!probe.capabilities.contains(name));
+        }
+        assert!(probe.supports_backend(BACKEND_IN_PROCESS));
+        assert_eq!(probe.supports_backend(BACKEND_WASM), cfg!(feature = "wasm"));
+
+        let mut manifest = Manifest::new("demo", "1.0.0");
+        manifest.source = Some("main.fsx".into());
+        assert!(probe.check_manifest(&manifest).is_ok());
+
+        manifest.isolation = Isolation::Process;
+        let result = probe.check_manifest(&manifest);
+        #[cfg(feature = "isolation")]
+        assert!(result.is_ok());
+        #[cfg(not(feature = "isolation"))]
+        assert!(matches!(
+            result,
+            Err(Error::FeatureDisabled { ref feature, .. }) if feature == "isolation"
+        ));
+    }
+}
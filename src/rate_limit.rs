@@ -0,0 +1,418 @@
+//! Keyed token-bucket rate limiting.
+
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+
+use crate::config_error::ConfigError;
+use crate::error::{Error, Result};
+use crate::signature::ExportSpec;
+
+/// Allowed rate of operations per key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    /// Operations allowed in a burst.
+    pub burst: u32,
+    /// Time to regain the full burst.
+    pub period: Duration,
+}
+
+impl RateLimit {
+    /// Allow `burst` operations per `period`, refilled continuously.
+    ///
+    /// Fails with [`Error::Config`] if `burst` or `period` is zero.
+    pub fn new(burst: u32, period: Duration) -> Result<Self> {
+        let limit = Self { burst, period };
+        limit.validate()?;
+        Ok(limit)
+    }
+
+    /// Allow `n` operations per second.
+    pub fn per_second(n: u32) -> Result<Self> {
+        Self::new(n, Duration::from_secs(1))
+    }
+
+    /// Allow `n` operations per minute.
+    pub fn per_minute(n: u32) -> Result<Self> {
+        Self::new(n, Duration::from_secs(60))
+    }
+
+    /// Check the limit allows at least one operation per non-zero period.
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = ConfigError::new();
+        errors.check(self.burst > 0, "burst", "must be greater than zero");
+        errors.check(!self.period.is_zero(), "period", "must not be zero");
+        errors.into_result()
+    }
+
+    fn refill_per_sec(&self) -> f64 {
+        self.burst as f64 / self.period.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Token-bucket rate limiter with one bucket per key.
+#[derive(Debug)]
+pub struct RateLimiter {
+    limit: RateLimit,
+    buckets: DashMap<String, Bucket>,
+}
+
+impl RateLimiter {
+    /// Create a rate limiter.
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            buckets: DashMap::new(),
+        }
+    }
+
+    /// Get the configured limit.
+    pub fn limit(&self) -> RateLimit {
+        self.limit
+    }
+
+    /// Take a token for a key.
+    ///
+    /// Returns how long to wait before retrying if the bucket is empty.
+    pub fn try_acquire(&self, key: &str) -> std::result::Result<(), Duration> {
+        self.try_acquire_at(key, Instant::now())
+    }
+
+    /// Take a token for a key at a given time.
+    pub fn try_acquire_at(&self, key: &str, now: Instant) -> std::result::Result<(), Duration> {
+        self.take_at(key, now, true)
+    }
+
+    /// Check a token is available for a key without taking it.
+    ///
+    /// Returns how long to wait until one is if the bucket is empty.
+    pub fn check(&self, key: &str) -> std::result::Result<(), Duration> {
+        self.take_at(key, Instant::now(), false)
+    }
+
+    fn take_at(&self, key: &str, now: Instant, take: bool) -> std::result::Result<(), Duration> {
+        let burst = self.limit.burst as f64;
+        let rate = self.limit.refill_per_sec();
+
+        let mut bucket = self.buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+        });
+
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(burst);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            if take {
+                bucket.tokens -= 1.0;
+            }
+            Ok(())
+        } else {
+            // A limit built with zero burst never refills
+            Err(Duration::try_from_secs_f64((1.0 - bucket.tokens) / rate).unwrap_or(Duration::MAX))
+        }
+    }
+
+    /// Return a token taken for a key, up to the burst.
+    pub(crate) fn refund(&self, key: &str) {
+        if let Some(mut bucket) = self.buckets.get_mut(key) {
+            bucket.tokens = (bucket.tokens + 1.0).min(self.limit.burst as f64);
+        }
+    }
+
+    /// Forget the bucket of a key.
+    pub fn reset(&self, key: &str) {
+        self.buckets.remove(key);
+    }
+}
+
+/// The `[rate-limit]` table of a manifest.
+///
+/// ```toml
+/// [rate-limit]
+/// max-calls-per-second = 50
+/// functions = { index = 5 }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CallRateLimits {
+    /// Calls per second allowed into the plugin as a whole.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub max_calls_per_second: Option<u32>,
+    /// Calls per second allowed into single exported functions.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub functions: BTreeMap<String, u32>,
+}
+
+impl CallRateLimits {
+    /// Check if no limits are declared.
+    pub fn is_empty(&self) -> bool {
+        self.max_calls_per_second.is_none() && self.functions.is_empty()
+    }
+
+    /// Get the limit on calls into the plugin.
+    pub fn plugin_limit(&self) -> Option<RateLimit> {
+        self.max_calls_per_second
+            .and_then(|n| RateLimit::per_second(n).ok())
+    }
+
+    /// Get the limit on calls into a function.
+    pub fn function_limit(&self, function: &str) -> Option<RateLimit> {
+        self.functions
+            .get(function)
+            .and_then(|&n| RateLimit::per_second(n).ok())
+    }
+
+    /// Check the limits against the plugin's exports.
+    pub fn validate(&self, exports: &[ExportSpec]) -> Result<()> {
+        if self.max_calls_per_second == Some(0) {
+            return Err(Error::invalid_manifest(
+                "max-calls-per-second must be greater than zero",
+            ));
+        }
+        for (function, &rate) in &self.functions {
+            if !exports.is_empty() && !exports.iter().any(|e| e == function) {
+                return Err(Error::invalid_manifest(format!(
+                    "rate-limited function {} is not exported",
+                    function
+                )));
+            }
+            if rate == 0 {
+                return Err(Error::invalid_manifest(format!(
+                    "rate-limited function {} allows zero calls per second",
+                    function
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A plugin's bucket, or one of its functions' buckets.
+type LimiterKey = (String, Option<String>);
+
+/// Rate limits on calls into plugins, per plugin and per function.
+///
+/// Limits set by the host take precedence over those in manifests.
+#[derive(Debug, Default)]
+pub(crate) struct CallRateLimiter {
+    plugins: HashMap<String, RateLimit>,
+    functions: HashMap<(String, String), RateLimit>,
+    limiters: DashMap<LimiterKey, RateLimiter>,
+}
+
+impl CallRateLimiter {
+    /// Create a limiter with the host's limits.
+    pub(crate) fn new(
+        plugins: HashMap<String, RateLimit>,
+        functions: HashMap<(String, String), RateLimit>,
+    ) -> Self {
+        Self {
+            plugins,
+            functions,
+            limiters: DashMap::new(),
+        }
+    }
+
+    /// Take a token for a call, failing with [`Error::RateLimited`] if the
+    /// function's or the plugin's bucket is empty.
+    ///
+    /// Both buckets are checked before either is taken from, so a call
+    /// rejected by one limit does not spend the other's quota.
+    pub(crate) fn admit(
+        &self,
+        plugin: &str,
+        function: &str,
+        declared: &CallRateLimits,
+    ) -> Result<()> {
+        let function_limit = self
+            .functions
+            .get(&(plugin.to_string(), function.to_string()))
+            .copied()
+            .or_else(|| declared.function_limit(function));
+        let plugin_limit = self
+            .plugins
+            .get(plugin)
+            .copied()
+            .or_else(|| declared.plugin_limit());
+
+        let buckets: Vec<(LimiterKey, String, RateLimit)> = [
+            function_limit.map(|limit| {
+                let id = (plugin.to_string(), Some(function.to_string()));
+                (id, format!("{}.{}", plugin, function), limit)
+            }),
+            plugin_limit.map(|limit| ((plugin.to_string(), None), plugin.to_string(), limit)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        for (id, key, limit) in &buckets {
+            self.with_limiter(id, *limit, |limiter| limiter.check(key))
+                .map_err(|retry_after| Error::rate_limited(key, retry_after))?;
+        }
+        for (i, (id, key, limit)) in buckets.iter().enumerate() {
+            if let Err(retry_after) =
+                self.with_limiter(id, *limit, |limiter| limiter.try_acquire(key))
+            {
+                // A concurrent call took the last token since the check
+                for (id, key, _) in &buckets[..i] {
+                    if let Some(limiter) = self.limiters.get(id) {
+                        limiter.refund(key);
+                    }
+                }
+                return Err(Error::rate_limited(key, retry_after));
+            }
+        }
+        Ok(())
+    }
+
+    fn with_limiter<T>(
+        &self,
+        id: &LimiterKey,
+        limit: RateLimit,
+        f: impl FnOnce(&RateLimiter) -> T,
+    ) -> T {
+        let mut limiter = self
+            .limiters
+            .entry(id.clone())
+            .or_insert_with(|| RateLimiter::new(limit));
+        // A reload may have changed the manifest's limit
+        if limiter.limit() != limit {
+            *limiter = RateLimiter::new(limit);
+        }
+        f(&limiter)
+    }
+
+    /// Forget the buckets of an unloaded plugin.
+    pub(crate) fn forget(&self, plugin: &str) {
+        self.limiters.retain(|(owner, _), _| owner != plugin);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket() {
+        let limiter = RateLimiter::new(RateLimit::new(2, Duration::from_secs(2)).unwrap());
+        let now = Instant::now();
+
+        assert!(limiter.try_acquire_at("alice", now).is_ok());
+        assert!(limiter.try_acquire_at("alice", now).is_ok());
+        let retry = limiter.try_acquire_at("alice", now).unwrap_err();
+        assert_eq!(retry, Duration::from_secs(1));
+
+        // Buckets are independent per key and refill over time
+        assert!(limiter.try_acquire_at("bob", now).is_ok());
+        assert!(limiter
+            .try_acquire_at("alice", now + Duration::from_secs(1))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_zero_limits_rejected() {
+        for limit in [RateLimit::per_minute(0), RateLimit::new(1, Duration::ZERO)] {
+            assert!(matches!(limit, Err(Error::Config(_))));
+        }
+
+        // Limits built by hand never admit anything, but do not panic
+        let limiter = RateLimiter::new(RateLimit {
+            burst: 0,
+            period: Duration::from_secs(1),
+        });
+        assert_eq!(limiter.try_acquire("alice"), Err(Duration::MAX));
+    }
+
+    #[test]
+    fn test_check_does_not_take() {
+        let limiter = RateLimiter::new(RateLimit::per_minute(1).unwrap());
+        assert!(limiter.check("alice").is_ok());
+        assert!(limiter.check("alice").is_ok());
+        assert!(limiter.try_acquire("alice").is_ok());
+        assert!(limiter.check("alice").is_err());
+    }
+
+    #[test]
+    fn test_call_rate_limits() {
+        let declared = CallRateLimits {
+            max_calls_per_second: Some(2),
+            functions: BTreeMap::from([("index".to_string(), 1)]),
+        };
+        assert!(declared.validate(&[]).is_ok());
+        assert!(declared.validate(&[ExportSpec::new("search")]).is_err());
+
+        let host = HashMap::from([(
+            ("search".to_string(), "query".to_string()),
+            RateLimit::per_second(1).unwrap(),
+        )]);
+        let limiter = CallRateLimiter::new(HashMap::new(), host);
+
+        limiter.admit("search", "index", &declared).unwrap();
+        assert!(matches!(
+            limiter.admit("search", "index", &declared),
+            Err(Error::RateLimited { ref key, .. }) if key == "search.index"
+        ));
+
+        // Host limits replace the manifest's
+        limiter.admit("search", "query", &declared).unwrap();
+        assert!(limiter.admit("search", "query", &declared).is_err());
+        assert!(matches!(
+            limiter.admit("search", "stats", &declared),
+            Err(Error::RateLimited { ref key, .. }) if key == "search"
+        ));
+
+        limiter.forget("search");
+        limiter.admit("search", "index", &declared).unwrap();
+    }
+
+    #[test]
+    fn test_rejected_call_keeps_function_quota() {
+        let declared = CallRateLimits {
+            max_calls_per_second: Some(1),
+            functions: BTreeMap::from([("index".to_string(), 1)]),
+        };
+        let limiter = CallRateLimiter::new(HashMap::new(), HashMap::new());
+
+        // The plugin bucket is spent by another function
+        limiter.admit("search", "query", &declared).unwrap();
+        assert!(matches!(
+            limiter.admit("search", "index", &declared),
+            Err(Error::RateLimited { ref key, .. }) if key == "search"
+        ));
+        let index = ("search".to_string(), Some("index".to_string()));
+        assert!(limiter
+            .limiters
+            .get(&index)
+            .unwrap()
+            .check("search.index")
+            .is_ok());
+    }
+
+    #[test]
+    fn test_forget_only_own_buckets() {
+        let declared = CallRateLimits {
+            max_calls_per_second: Some(1),
+            functions: BTreeMap::from([("index".to_string(), 1)]),
+        };
+        let limiter = CallRateLimiter::new(HashMap::new(), HashMap::new());
+        limiter.admit("search", "index", &declared).unwrap();
+        limiter.admit("search.v2", "index", &declared).unwrap();
+
+        // A plugin whose name extends this one with a dot keeps its buckets
+        limiter.forget("search");
+        limiter.admit("search", "index", &declared).unwrap();
+        assert!(limiter.admit("search.v2", "index", &declared).is_err());
+    }
+}
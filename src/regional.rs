@@ -0,0 +1,115 @@
+//! Locale and timezone plugins format dates, times and numbers for.
+
+use std::sync::OnceLock;
+
+/// Locale used when the host's cannot be determined.
+pub(crate) const DEFAULT_LOCALE: &str = "en-US";
+/// Timezone used when the host's cannot be determined.
+pub(crate) const DEFAULT_TIMEZONE: &str = "UTC";
+
+/// Locale and timezone of a plugin.
+///
+/// The runtime provides no time or formatting host functions of its own and
+/// in-process engines never see these settings. They are data for the
+/// application's host functions, which read them from
+/// [`Plugin::regional_settings`](crate::Plugin::regional_settings) or
+/// [`TenantActivation::regional_settings`](crate::TenantActivation::regional_settings).
+/// Process-isolated workers additionally start with `LANG` and `TZ` set
+/// from them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegionalSettings {
+    /// BCP 47 language tag, e.g. `de-CH`.
+    pub locale: String,
+    /// IANA timezone name, e.g. `Europe/Zurich`.
+    pub timezone: String,
+}
+
+impl RegionalSettings {
+    /// Create settings for a locale and timezone.
+    pub fn new(locale: impl Into<String>, timezone: impl Into<String>) -> Self {
+        Self {
+            locale: locale.into(),
+            timezone: timezone.into(),
+        }
+    }
+
+    /// Get the host's settings, from `LC_ALL`, `LANG` and `TZ`.
+    ///
+    /// They are read once; unset or unusable values fall back to `en-US`
+    /// and `UTC`.
+    pub fn host() -> Self {
+        static HOST: OnceLock<RegionalSettings> = OnceLock::new();
+        HOST.get_or_init(|| {
+            let locale = ["LC_ALL", "LANG"]
+                .iter()
+                .filter_map(|var| std::env::var(var).ok())
+                .find_map(|value| posix_to_bcp47(&value))
+                .unwrap_or_else(|| DEFAULT_LOCALE.to_string());
+            let timezone = std::env::var("TZ")
+                .ok()
+                .map(|tz| tz.trim_start_matches(':').to_string())
+                .filter(|tz| !tz.is_empty())
+                .unwrap_or_else(|| DEFAULT_TIMEZONE.to_string());
+            Self { locale, timezone }
+        })
+        .clone()
+    }
+
+    /// Set the locale.
+    pub fn with_locale(mut self, locale: impl Into<String>) -> Self {
+        self.locale = locale.into();
+        self
+    }
+
+    /// Set the timezone.
+    pub fn with_timezone(mut self, timezone: impl Into<String>) -> Self {
+        self.timezone = timezone.into();
+        self
+    }
+
+    /// Get the `LANG` and `TZ` variables describing the settings to a
+    /// worker process.
+    #[cfg(feature = "isolation")]
+    pub(crate) fn env_vars(&self) -> [(&'static str, String); 2] {
+        let lang = format!("{}.UTF-8", self.locale.replace('-', "_"));
+        [("LANG", lang), ("TZ", self.timezone.clone())]
+    }
+}
+
+impl Default for RegionalSettings {
+    fn default() -> Self {
+        Self::host()
+    }
+}
+
+/// Convert a POSIX locale such as `de_CH.UTF-8@euro` to `de-CH`.
+fn posix_to_bcp47(value: &str) -> Option<String> {
+    let name = value.split(['.', '@']).next().unwrap_or_default();
+    match name {
+        "" | "C" | "POSIX" => None,
+        name => Some(name.replace('_', "-")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_posix_to_bcp47() {
+        assert_eq!(posix_to_bcp47("de_CH.UTF-8@euro").as_deref(), Some("de-CH"));
+        assert_eq!(posix_to_bcp47("fr").as_deref(), Some("fr"));
+        assert_eq!(posix_to_bcp47("C.UTF-8"), None);
+        assert_eq!(posix_to_bcp47(""), None);
+    }
+
+    #[test]
+    fn test_regional_settings() {
+        let settings = RegionalSettings::host()
+            .with_locale("ja-JP")
+            .with_timezone("Asia/Tokyo");
+        assert_eq!(settings, RegionalSettings::new("ja-JP", "Asia/Tokyo"));
+        assert!(!RegionalSettings::host().locale.is_empty());
+    }
+}
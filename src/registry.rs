@@ -1,11 +1,15 @@
 //! Plugin registry for managing loaded plugins.
 
+use std::path::Path;
 use std::sync::Arc;
 
 use dashmap::DashMap;
 
+use crate::compat::{check_compiler_version, AtRiskPlugin, CompatReport};
+use crate::dependency::DependencyGraph;
 use crate::error::{Error, Result};
-use crate::lifecycle::{LifecycleHooks, LifecycleState};
+use crate::lifecycle::{LifecycleEvent, LifecycleHooks, LifecycleState};
+use crate::namespace;
 use crate::plugin::{PluginHandle, PluginInfo};
 
 /// Configuration for the plugin registry.
@@ -69,6 +73,31 @@ pub struct RegistryStats {
     pub unloaded: usize,
 }
 
+/// A registered plugin borrowed from the registry by
+/// [`PluginRegistry::iter`].
+pub struct PluginRef<'a>(dashmap::mapref::multiple::RefMulti<'a, String, PluginHandle>);
+
+impl PluginRef<'_> {
+    /// Get the plugin name without cloning it.
+    pub fn name(&self) -> &str {
+        self.0.key()
+    }
+}
+
+impl std::ops::Deref for PluginRef<'_> {
+    type Target = PluginHandle;
+
+    fn deref(&self) -> &PluginHandle {
+        self.0.value()
+    }
+}
+
+impl std::fmt::Debug for PluginRef<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("PluginRef").field(&self.name()).finish()
+    }
+}
+
 /// Plugin registry for managing loaded plugins.
 pub struct PluginRegistry {
     config: RegistryConfig,
@@ -96,9 +125,26 @@ impl PluginRegistry {
         &self.config
     }
 
+    /// Receive the created, reloaded and unloaded events of registered
+    /// plugins on a channel.
+    ///
+    /// Events are queued rather than handled inside the registry, so the
+    /// receiver can process them on its own thread or task.
+    pub fn subscribe(&self) -> std::sync::mpsc::Receiver<LifecycleEvent> {
+        self.hooks.subscribe()
+    }
+
     /// Register a plugin.
+    ///
+    /// Dependencies may be registered in any order, but every version
+    /// requirement between registered plugins must hold:
+    /// [`Error::DependencyNotSatisfied`] is returned if a dependency of the
+    /// plugin is registered at a version its requirement rejects, or if the
+    /// plugin's version does not satisfy a registered dependent. Missing
+    /// dependencies are reported when the plugin is started.
     pub fn register(&self, plugin: PluginHandle) -> Result<()> {
         let name = plugin.name();
+        namespace::validate(&name)?;
 
         // Check capacity
         if self.plugins.len() >= self.config.max_plugins {
@@ -108,6 +154,8 @@ impl PluginRegistry {
             )));
         }
 
+        self.check_dependency_versions(&plugin)?;
+
         // Check for existing
         if self.plugins.contains_key(&name) {
             if !self.config.allow_overwrite {
@@ -140,11 +188,49 @@ impl PluginRegistry {
         Ok(plugin)
     }
 
+    /// Remove a plugin without unloading it, leaving that to the caller.
+    pub(crate) fn detach(&self, name: &str) -> Option<PluginHandle> {
+        self.plugins.remove(name).map(|(_, plugin)| plugin)
+    }
+
     /// Get a plugin by name.
     pub fn get(&self, name: &str) -> Option<PluginHandle> {
         self.plugins.get(name).map(|r| r.clone())
     }
 
+    /// Resolve a name to a plugin.
+    ///
+    /// An exact match wins. Otherwise a name without a namespace, such as
+    /// `http-logger`, matches the one namespaced plugin with that local name,
+    /// such as `acme/http-logger`, and is [`Error::AmbiguousPluginName`] if
+    /// several namespaces have one.
+    pub fn resolve(&self, name: &str) -> Result<PluginHandle> {
+        if let Some(plugin) = self.get(name) {
+            return Ok(plugin);
+        }
+        if namespace::split(name).0.is_some() {
+            return Err(Error::plugin_not_found(name));
+        }
+        let mut matches: Vec<PluginHandle> = self
+            .plugins
+            .iter()
+            .filter(|r| {
+                let (namespace, local) = namespace::split(r.key());
+                namespace.is_some() && local == name
+            })
+            .map(|r| r.value().clone())
+            .collect();
+        match matches.len() {
+            0 => Err(Error::plugin_not_found(name)),
+            1 => Ok(matches.remove(0)),
+            _ => {
+                let mut candidates: Vec<String> = matches.iter().map(|p| p.name()).collect();
+                candidates.sort();
+                Err(Error::ambiguous_plugin_name(name, candidates))
+            }
+        }
+    }
+
     /// Check if a plugin exists.
     pub fn contains(&self, name: &str) -> bool {
         self.plugins.contains_key(name)
@@ -160,6 +246,24 @@ impl PluginRegistry {
         self.plugins.iter().map(|r| r.value().clone()).collect()
     }
 
+    /// Iterate over the registered plugins without collecting them.
+    ///
+    /// Each item holds a read lock on part of the registry until dropped, so
+    /// do not register or unregister plugins while iterating.
+    pub fn iter(&self) -> impl Iterator<Item = PluginRef<'_>> {
+        self.plugins.iter().map(PluginRef)
+    }
+
+    /// Visit every registered plugin without collecting them.
+    ///
+    /// Like [`iter`](Self::iter), `f` must not register or unregister
+    /// plugins.
+    pub fn for_each(&self, mut f: impl FnMut(&PluginHandle)) {
+        for plugin in self.plugins.iter() {
+            f(plugin.value());
+        }
+    }
+
     /// Get plugins by state.
     pub fn by_state(&self, state: LifecycleState) -> Vec<PluginHandle> {
         self.plugins
@@ -209,30 +313,119 @@ impl PluginRegistry {
         self.plugins.iter().map(|r| r.info()).collect()
     }
 
-    /// Start all stopped plugins.
+    /// Build the dependency graph of all registered plugins.
+    pub fn dependency_graph(&self) -> DependencyGraph {
+        let manifests: Vec<_> = self
+            .plugins
+            .iter()
+            .map(|r| r.value().inner().manifest())
+            .collect();
+        DependencyGraph::from_manifests(&manifests)
+    }
+
+    /// Get plugin names in dependency order (dependencies first), then by
+    /// descending priority.
+    pub fn resolve_order(&self) -> Result<Vec<String>> {
+        self.dependency_graph().resolve()
+    }
+
+    /// Start all initialized plugins in dependency order, higher priorities
+    /// first.
+    ///
+    /// A plugin whose required dependencies are missing or not running is
+    /// not started and reports [`Error::DependencyNotSatisfied`].
     pub fn start_all(&self) -> Vec<Result<()>> {
-        self.plugins
+        self.start_all_with(|_| Ok(()))
+    }
+
+    /// Start all initialized plugins in dependency and priority order,
+    /// checking each with `guard` first.
+    pub fn start_all_with<F>(&self, guard: F) -> Vec<Result<()>>
+    where
+        F: Fn(&str) -> Result<()>,
+    {
+        let graph = self.dependency_graph();
+        let order = match graph.resolve() {
+            Ok(order) => order,
+            Err(e) => return vec![Err(e)],
+        };
+
+        order
             .iter()
-            .filter(|r| r.state() == LifecycleState::Initialized)
-            .map(|r| {
-                let plugin = r.value();
+            .filter_map(|name| self.get(name))
+            .filter(|plugin| plugin.state() == LifecycleState::Initialized)
+            .map(|plugin| {
+                graph.check(&plugin.name())?;
+                self.check_dependencies_running(&plugin)?;
+                guard(&plugin.name())?;
                 plugin.inner().start()
             })
             .collect()
     }
 
-    /// Stop all running plugins.
+    /// Stop all running plugins in the reverse of the start order.
     pub fn stop_all(&self) -> Vec<Result<()>> {
-        self.plugins
+        self.stop_all_with(|_| Ok(()))
+    }
+
+    /// Stop all running plugins in the reverse of the start order, checking
+    /// each with `guard` first.
+    pub fn stop_all_with<F>(&self, guard: F) -> Vec<Result<()>>
+    where
+        F: Fn(&str) -> Result<()>,
+    {
+        let mut order = self.resolve_order().unwrap_or_else(|_| {
+            let mut plugins = self.all();
+            plugins.sort_by_key(|p| (std::cmp::Reverse(p.priority()), p.name()));
+            plugins.iter().map(|p| p.name()).collect()
+        });
+        order.reverse();
+
+        order
             .iter()
-            .filter(|r| r.state() == LifecycleState::Running)
-            .map(|r| {
-                let plugin = r.value();
+            .filter_map(|name| self.get(name))
+            .filter(|plugin| plugin.state() == LifecycleState::Running)
+            .map(|plugin| {
+                guard(&plugin.name())?;
                 plugin.inner().stop()
             })
             .collect()
     }
 
+    fn check_dependency_versions(&self, plugin: &PluginHandle) -> Result<()> {
+        for dep in plugin.inner().manifest().dependencies {
+            match self.get(&dep.name) {
+                Some(target) if !dep.is_satisfied_by(&target.version()) => {
+                    return Err(Error::dependency_not_satisfied(dep.name, dep.version));
+                }
+                _ => {}
+            }
+        }
+
+        let (name, version) = (plugin.name(), plugin.version());
+        let dependents: Vec<PluginHandle> =
+            self.plugins.iter().map(|e| e.value().clone()).collect();
+        for dependent in dependents {
+            for dep in dependent.inner().manifest().dependencies {
+                if dep.name == name && !dep.is_satisfied_by(&version) {
+                    return Err(Error::dependency_not_satisfied(dep.name, dep.version));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn check_dependencies_running(&self, plugin: &PluginHandle) -> Result<()> {
+        for dep in plugin.inner().manifest().dependencies {
+            match self.get(&dep.name) {
+                Some(target) if target.state() == LifecycleState::Running => {}
+                None if dep.optional => {}
+                _ => return Err(Error::dependency_not_satisfied(dep.name, dep.version)),
+            }
+        }
+        Ok(())
+    }
+
     /// Unload all plugins.
     pub fn unload_all(&self) {
         for entry in self.plugins.iter() {
@@ -266,6 +459,84 @@ impl PluginRegistry {
             .collect()
     }
 
+    /// Update plugin paths after a file or directory was renamed.
+    ///
+    /// Returns the names of the plugins whose paths changed.
+    pub fn relocate(&self, from: &Path, to: &Path) -> Vec<String> {
+        self.plugins
+            .iter()
+            .filter(|r| r.value().inner().relocate(from, to))
+            .map(|r| r.key().clone())
+            .collect()
+    }
+
+    /// Find plugins loaded from a manifest or entry file.
+    pub fn find_by_path(&self, path: &Path) -> Vec<PluginHandle> {
+        let canonical = path.canonicalize().ok();
+        let matches = |candidate: &Option<std::path::PathBuf>| {
+            candidate.as_deref().is_some_and(|c| {
+                c == path || (canonical.is_some() && c.canonicalize().ok() == canonical)
+            })
+        };
+
+        self.plugins
+            .iter()
+            .filter(|r| {
+                let info = r.value().info();
+                matches(&info.manifest_path) || matches(&info.entry_path)
+            })
+            .map(|r| r.value().clone())
+            .collect()
+    }
+
+    /// List bytecode-only plugins that would not load on `engine_version`.
+    ///
+    /// Plugins loaded from source, or whose `.fzb` has an `.fsx` next to it,
+    /// are recompiled on load and never at risk.
+    pub fn compat_report(&self, engine_version: &str) -> CompatReport {
+        let mut at_risk = Vec::new();
+        for entry in self.plugins.iter() {
+            let plugin = entry.value().inner();
+            let manifest = plugin.manifest();
+            if manifest.uses_source() || manifest.uses_wasm() {
+                continue;
+            }
+            let info = plugin.info();
+            if info
+                .entry_path
+                .as_ref()
+                .is_some_and(|path| path.with_extension("fsx").is_file())
+            {
+                continue;
+            }
+            let Some(bytecode) = plugin.bytecode() else {
+                continue;
+            };
+
+            let compiler_version = fusabi_host::validate_bytecode(&bytecode)
+                .ok()
+                .map(|metadata| metadata.compiler_version);
+            let verdict = match compiler_version {
+                Some(ref version) => check_compiler_version(version, engine_version),
+                None => Err("bytecode is unreadable".to_string()),
+            };
+            if let Err(reason) = verdict {
+                at_risk.push(AtRiskPlugin {
+                    name: info.name,
+                    compiler_version,
+                    entry_path: info.entry_path,
+                    reason,
+                });
+            }
+        }
+        at_risk.sort_by(|a, b| a.name.cmp(&b.name));
+
+        CompatReport {
+            engine_version: engine_version.to_string(),
+            at_risk,
+        }
+    }
+
     /// Find plugins by tag.
     pub fn find_by_tag(&self, tag: &str) -> Vec<PluginHandle> {
         self.plugins
@@ -275,6 +546,18 @@ impl PluginRegistry {
             .collect()
     }
 
+    /// Find the plugins in a namespace, sorted by name.
+    pub fn find_by_namespace(&self, namespace: &str) -> Vec<PluginHandle> {
+        let mut plugins: Vec<PluginHandle> = self
+            .plugins
+            .iter()
+            .filter(|r| namespace::split(r.key()).0 == Some(namespace))
+            .map(|r| r.value().clone())
+            .collect();
+        plugins.sort_by_key(|p| p.name());
+        plugins
+    }
+
     /// Find plugins by capability.
     pub fn find_by_capability(&self, cap: &str) -> Vec<PluginHandle> {
         self.plugins
@@ -327,8 +610,9 @@ impl Drop for PluginRegistry {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::manifest::ManifestBuilder;
+    use crate::manifest::{Dependency, ManifestBuilder};
     use crate::plugin::Plugin;
+    use parking_lot::Mutex;
 
     fn create_test_plugin(name: &str) -> PluginHandle {
         let manifest = ManifestBuilder::new(name, "1.0.0")
@@ -337,6 +621,80 @@ mod tests {
         PluginHandle::new(Plugin::new(manifest))
     }
 
+    fn create_initialized_plugin(name: &str, deps: &[&str]) -> PluginHandle {
+        let mut builder = ManifestBuilder::new(name, "1.0.0").source("test.fsx");
+        for dep in deps {
+            builder = builder.dependency(Dependency::required(*dep, "1.0"));
+        }
+        let plugin = Plugin::new(builder.build_unchecked());
+        plugin
+            .initialize(fusabi_host::EngineConfig::default())
+            .unwrap();
+        PluginHandle::new(plugin)
+    }
+
+    #[test]
+    fn test_compat_report() {
+        let registry = PluginRegistry::default_config();
+        registry.register(create_test_plugin("scripted")).unwrap();
+
+        let manifest = ManifestBuilder::new("packaged", "1.0.0")
+            .bytecode("packaged.fzb")
+            .build_unchecked();
+        let plugin = Plugin::new(manifest);
+        let bytecode = fusabi_host::compile_source("()", &Default::default())
+            .unwrap()
+            .bytecode;
+        let compiler = fusabi_host::validate_bytecode(&bytecode)
+            .unwrap()
+            .compiler_version;
+        plugin.set_bytecode(bytecode);
+        registry.register(PluginHandle::new(plugin)).unwrap();
+
+        assert!(registry.compat_report(&compiler).is_clean());
+
+        let report = registry.compat_report("999.0.0");
+        assert_eq!(report.at_risk.len(), 1);
+        assert_eq!(report.at_risk[0].name, "packaged");
+        assert_eq!(report.at_risk[0].compiler_version, Some(compiler));
+    }
+
+    #[test]
+    fn test_namespaced_names() {
+        let registry = PluginRegistry::default_config();
+        registry
+            .register(create_test_plugin("acme/http-logger"))
+            .unwrap();
+        registry
+            .register(create_test_plugin("acme/metrics"))
+            .unwrap();
+        registry
+            .register(create_test_plugin("globex/metrics"))
+            .unwrap();
+        registry.register(create_test_plugin("tracing")).unwrap();
+
+        assert!(registry.get("acme/http-logger").is_some());
+        assert_eq!(
+            registry.resolve("http-logger").unwrap().name(),
+            "acme/http-logger"
+        );
+        assert!(matches!(
+            registry.resolve("metrics"),
+            Err(Error::AmbiguousPluginName { candidates, .. })
+                if candidates == ["acme/metrics", "globex/metrics"]
+        ));
+        assert!(registry.resolve("acme/tracing").is_err());
+
+        let names: Vec<String> = registry
+            .find_by_namespace("acme")
+            .iter()
+            .map(|p| p.name())
+            .collect();
+        assert_eq!(names, ["acme/http-logger", "acme/metrics"]);
+
+        assert!(registry.register(create_test_plugin("a/b/c")).is_err());
+    }
+
     #[test]
     fn test_registry_creation() {
         let registry = PluginRegistry::default_config();
@@ -399,6 +757,26 @@ mod tests {
         assert!(!registry.contains("test-plugin"));
     }
 
+    #[test]
+    fn test_subscribe() {
+        let registry = PluginRegistry::default_config();
+        let events = registry.subscribe();
+
+        registry.register(create_test_plugin("a")).unwrap();
+        registry.unregister("a").unwrap();
+
+        let received: Vec<_> = events.try_iter().collect();
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[0].event_name(), "created");
+        assert_eq!(received[1].event_name(), "unloaded");
+        assert_eq!(received[1].plugin_name(), "a");
+
+        // Dropped receivers are unsubscribed on the next event
+        drop(events);
+        registry.register(create_test_plugin("b")).unwrap();
+        assert!(format!("{:?}", registry.hooks).contains("subscriber_count: 0"));
+    }
+
     #[test]
     fn test_unregister_nonexistent() {
         let registry = PluginRegistry::default_config();
@@ -423,6 +801,25 @@ mod tests {
         assert!(names.contains(&"plugin-3".to_string()));
     }
 
+    #[test]
+    fn test_iter_and_for_each() {
+        let registry = PluginRegistry::default_config();
+        registry.register(create_test_plugin("plugin-1")).unwrap();
+        registry.register(create_test_plugin("plugin-2")).unwrap();
+
+        let mut names: Vec<String> = registry
+            .iter()
+            .inspect(|p| assert_eq!(p.name(), p.info().name))
+            .map(|p| p.name().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["plugin-1", "plugin-2"]);
+
+        let mut visited = 0;
+        registry.for_each(|_| visited += 1);
+        assert_eq!(visited, 2);
+    }
+
     #[test]
     fn test_registry_stats() {
         let registry = PluginRegistry::default_config();
@@ -445,4 +842,133 @@ mod tests {
         let result = registry.register(create_test_plugin("plugin-3"));
         assert!(matches!(result, Err(Error::Registry(_))));
     }
+
+    #[test]
+    fn test_relocate_paths() {
+        use std::path::PathBuf;
+
+        let registry = PluginRegistry::default_config();
+        let plugin = create_test_plugin("moved");
+        plugin.inner().set_paths(
+            Some(PathBuf::from("/plugins/old/plugin.toml")),
+            Some(PathBuf::from("/plugins/old/main.fsx")),
+        );
+        registry.register(plugin.clone()).unwrap();
+        registry.register(create_test_plugin("other")).unwrap();
+
+        let moved = registry.relocate(Path::new("/plugins/old"), Path::new("/plugins/new"));
+        assert_eq!(moved, vec!["moved"]);
+
+        let info = plugin.info();
+        assert_eq!(
+            info.manifest_path,
+            Some(PathBuf::from("/plugins/new/plugin.toml"))
+        );
+        assert_eq!(
+            info.entry_path,
+            Some(PathBuf::from("/plugins/new/main.fsx"))
+        );
+    }
+
+    #[test]
+    fn test_start_all_in_dependency_order() {
+        let registry = PluginRegistry::default_config();
+        registry
+            .register(create_initialized_plugin("app", &["db"]))
+            .unwrap();
+        registry
+            .register(create_initialized_plugin("db", &["log"]))
+            .unwrap();
+        registry
+            .register(create_initialized_plugin("log", &[]))
+            .unwrap();
+
+        assert_eq!(registry.resolve_order().unwrap(), vec!["log", "db", "app"]);
+
+        let results = registry.start_all();
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|r| r.is_ok()));
+        assert_eq!(registry.running().len(), 3);
+
+        assert!(registry.stop_all().iter().all(|r| r.is_ok()));
+        assert!(registry.running().is_empty());
+    }
+
+    #[test]
+    fn test_start_and_stop_by_priority() {
+        let registry = PluginRegistry::default_config();
+        for (name, priority) in [("metrics", 0), ("ui", -5), ("core", 10)] {
+            let plugin = Plugin::new(
+                ManifestBuilder::new(name, "1.0.0")
+                    .source("test.fsx")
+                    .priority(priority)
+                    .build_unchecked(),
+            );
+            plugin
+                .initialize(fusabi_host::EngineConfig::default())
+                .unwrap();
+            registry.register(PluginHandle::new(plugin)).unwrap();
+        }
+
+        let started = Mutex::new(Vec::new());
+        registry.start_all_with(|name| {
+            started.lock().push(name.to_string());
+            Ok(())
+        });
+        assert_eq!(*started.lock(), vec!["core", "metrics", "ui"]);
+
+        let stopped = Mutex::new(Vec::new());
+        registry.stop_all_with(|name| {
+            stopped.lock().push(name.to_string());
+            Ok(())
+        });
+        assert_eq!(*stopped.lock(), vec!["ui", "metrics", "core"]);
+    }
+
+    #[test]
+    fn test_start_all_missing_dependency() {
+        let registry = PluginRegistry::default_config();
+        registry
+            .register(create_initialized_plugin("app", &["missing"]))
+            .unwrap();
+
+        let results = registry.start_all();
+        assert!(matches!(
+            results[0],
+            Err(Error::DependencyNotSatisfied { .. })
+        ));
+        assert_eq!(
+            registry.get("app").unwrap().state(),
+            LifecycleState::Initialized
+        );
+    }
+
+    #[test]
+    fn test_register_checks_dependency_versions() {
+        let registry = PluginRegistry::default_config();
+        let dependent = |req: &str| {
+            let manifest = ManifestBuilder::new("app", "1.0.0")
+                .source("test.fsx")
+                .dependency(Dependency::required("db", req))
+                .dependency(Dependency::optional("cache", "^2"))
+                .build_unchecked();
+            PluginHandle::new(Plugin::new(manifest))
+        };
+
+        registry.register(create_test_plugin("db")).unwrap();
+        assert!(matches!(
+            registry.register(dependent(">=0.3, <0.5")),
+            Err(Error::DependencyNotSatisfied { .. })
+        ));
+        registry.register(dependent("^1.0")).unwrap();
+
+        // A dependency registered after its dependent must match too
+        let registry = PluginRegistry::default_config();
+        registry.register(dependent(">=0.3, <0.5")).unwrap();
+        assert!(matches!(
+            registry.register(create_test_plugin("db")),
+            Err(Error::DependencyNotSatisfied { .. })
+        ));
+        assert!(registry.get("db").is_none());
+    }
 }
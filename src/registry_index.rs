@@ -0,0 +1,523 @@
+//! Plugin registry indexes listing published plugins and their downloads.
+
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use parking_lot::RwLock;
+
+use crate::crypto::sha256_hex;
+use crate::error::{Error, Result};
+use crate::manifest::{ApiVersion, Manifest};
+use crate::namespace;
+use crate::network::NetworkConfig;
+use crate::version::VersionReq;
+
+/// Manifest file name of a release that does not name one.
+const DEFAULT_MANIFEST: &str = "plugin.toml";
+
+/// A file of a published release.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct IndexArtifact {
+    /// Path of the file within the installed plugin directory.
+    pub path: String,
+    /// Download URL, absolute or relative to the index.
+    pub url: String,
+    /// SHA-256 of the file, hex-encoded.
+    pub sha256: String,
+}
+
+/// A published version of a plugin.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct IndexRelease {
+    /// Release version.
+    pub version: String,
+    /// Path of the manifest among the files.
+    #[serde(default = "default_manifest")]
+    pub manifest: String,
+    /// Whether the release was withdrawn; yanked releases are never
+    /// resolved.
+    #[serde(default)]
+    pub yanked: bool,
+    /// Files to download.
+    #[serde(rename = "file", default)]
+    pub files: Vec<IndexArtifact>,
+}
+
+/// A plugin listed in an index.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct IndexEntry {
+    /// Plugin name.
+    pub name: String,
+    /// Short description.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Published versions.
+    #[serde(rename = "version", default)]
+    pub versions: Vec<IndexRelease>,
+}
+
+/// Contents of an index file.
+///
+/// ```toml
+/// [[plugin]]
+/// name = "http-logger"
+/// description = "Logs HTTP requests"
+///
+/// [[plugin.version]]
+/// version = "1.2.0"
+///
+/// [[plugin.version.file]]
+/// path = "plugin.toml"
+/// url = "http-logger/1.2.0/plugin.toml"
+/// sha256 = "9f86d0..."
+///
+/// [[plugin.version.file]]
+/// path = "main.fsx"
+/// url = "http-logger/1.2.0/main.fsx"
+/// sha256 = "60303a..."
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct IndexDocument {
+    /// Listed plugins.
+    #[serde(rename = "plugin", default)]
+    pub plugins: Vec<IndexEntry>,
+}
+
+impl IndexDocument {
+    /// Parse an index from TOML.
+    pub fn from_toml(content: &str) -> Result<Self> {
+        toml::from_str(content).map_err(|e| Error::Registry(format!("invalid index: {}", e)))
+    }
+
+    /// Serialize the index to TOML.
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string_pretty(self).map_err(|e| Error::Registry(e.to_string()))
+    }
+
+    /// Get a listed plugin.
+    pub fn get(&self, name: &str) -> Option<&IndexEntry> {
+        self.plugins.iter().find(|p| p.name == name)
+    }
+
+    /// Find the newest release of a plugin matching a version requirement,
+    /// skipping yanked releases.
+    pub fn resolve(&self, name: &str, req: &str) -> Result<IndexRelease> {
+        let entry = self
+            .get(name)
+            .ok_or_else(|| Error::Registry(format!("plugin {} is not in the index", name)))?;
+        let req = VersionReq::parse(req)?;
+        entry
+            .versions
+            .iter()
+            .filter(|release| !release.yanked)
+            .filter_map(|release| {
+                let version = ApiVersion::parse(&release.version).ok()?;
+                req.matches(&version).then_some((version, release))
+            })
+            .max_by(|(a, _), (b, _)| a.cmp(b))
+            .map(|(_, release)| release.clone())
+            .ok_or_else(|| Error::Registry(format!("no release of {} matches {}", name, req)))
+    }
+}
+
+/// Downloads index files and releases.
+///
+/// The runtime does no networking itself; hosts implement this trait with
+/// their HTTP client of choice, applying the proxy, CA bundle and timeouts of
+/// the [`NetworkConfig`] passed in. Failures reported as
+/// [`Error::Network`] are retried according to the same configuration.
+/// [`LocalFetcher`] serves local mirrors.
+pub trait IndexFetcher: Send + Sync {
+    /// Get the content at a URL.
+    fn fetch(&self, url: &str, network: &NetworkConfig) -> Result<Vec<u8>>;
+}
+
+impl<F> IndexFetcher for F
+where
+    F: Fn(&str, &NetworkConfig) -> Result<Vec<u8>> + Send + Sync,
+{
+    fn fetch(&self, url: &str, network: &NetworkConfig) -> Result<Vec<u8>> {
+        self(url, network)
+    }
+}
+
+/// Fetches paths and `file://` URLs from the local filesystem.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LocalFetcher;
+
+impl IndexFetcher for LocalFetcher {
+    fn fetch(&self, url: &str, _network: &NetworkConfig) -> Result<Vec<u8>> {
+        let path = url.strip_prefix("file://").unwrap_or(url);
+        std::fs::read(path).map_err(|e| Error::Registry(format!("cannot read {}: {}", url, e)))
+    }
+}
+
+/// Client of a plugin registry index, used by
+/// [`PluginRuntime::install_from_index`](crate::PluginRuntime::install_from_index).
+///
+/// The index is fetched on first use and cached until
+/// [`refresh`](Self::refresh). Releases are installed into
+/// `<install_dir>/<name>/<version>`.
+///
+/// A runtime created with the index replaces its network configuration with
+/// the runtime's [`RuntimeConfig::network`](crate::RuntimeConfig::network).
+pub struct RegistryIndex {
+    url: String,
+    install_dir: PathBuf,
+    fetcher: Arc<dyn IndexFetcher>,
+    network: RwLock<NetworkConfig>,
+    document: RwLock<Option<Arc<IndexDocument>>>,
+}
+
+impl RegistryIndex {
+    /// Create a client for the index at `url`.
+    pub fn new(
+        url: impl Into<String>,
+        install_dir: impl Into<PathBuf>,
+        fetcher: impl IndexFetcher + 'static,
+    ) -> Self {
+        Self {
+            url: url.into(),
+            install_dir: install_dir.into(),
+            fetcher: Arc::new(fetcher),
+            network: RwLock::new(NetworkConfig::default()),
+            document: RwLock::new(None),
+        }
+    }
+
+    /// Set the network configuration passed to the fetcher.
+    pub fn with_network(self, network: NetworkConfig) -> Self {
+        self.set_network(network);
+        self
+    }
+
+    /// Get the network configuration passed to the fetcher.
+    pub fn network(&self) -> NetworkConfig {
+        self.network.read().clone()
+    }
+
+    pub(crate) fn set_network(&self, network: NetworkConfig) {
+        *self.network.write() = network;
+    }
+
+    /// Fetch a URL, retrying network failures.
+    fn fetch(&self, url: &str) -> Result<Vec<u8>> {
+        let network = self.network();
+        network.retry(|| self.fetcher.fetch(url, &network))
+    }
+
+    /// Create a client for an index file on the local filesystem.
+    pub fn local(path: impl AsRef<Path>, install_dir: impl Into<PathBuf>) -> Self {
+        Self::new(
+            path.as_ref().to_string_lossy().into_owned(),
+            install_dir,
+            LocalFetcher,
+        )
+    }
+
+    /// Get the index URL.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Get the directory releases are installed into.
+    pub fn install_dir(&self) -> &Path {
+        &self.install_dir
+    }
+
+    /// Fetch the index again.
+    pub fn refresh(&self) -> Result<Arc<IndexDocument>> {
+        let content = self.fetch(&self.url)?;
+        let content = String::from_utf8(content)
+            .map_err(|_| Error::Registry(format!("index {} is not UTF-8", self.url)))?;
+        let document = Arc::new(IndexDocument::from_toml(&content)?);
+        *self.document.write() = Some(document.clone());
+        Ok(document)
+    }
+
+    /// Get the index, fetching it if it was not fetched yet.
+    pub fn document(&self) -> Result<Arc<IndexDocument>> {
+        if let Some(ref document) = *self.document.read() {
+            return Ok(document.clone());
+        }
+        self.refresh()
+    }
+
+    /// Find the newest release of a plugin matching a version requirement.
+    pub fn resolve(&self, name: &str, req: &str) -> Result<IndexRelease> {
+        self.document()?.resolve(name, req)
+    }
+
+    /// Download a release, verifying each file's checksum, and return the
+    /// path of its manifest.
+    ///
+    /// Fails without touching the install directory if a download fails or
+    /// the manifest does not describe the release. Files are written to a
+    /// staging directory renamed into place once complete, so the version
+    /// directory never holds a partial release. A version already installed
+    /// is never overwritten: if its files match the release it is used as
+    /// is, otherwise the download fails.
+    pub fn download(&self, name: &str, release: &IndexRelease) -> Result<PathBuf> {
+        namespace::validate(name)?;
+        let dir = safe_join(&safe_join(&self.install_dir, name)?, &release.version)?;
+        if dir.exists() {
+            return self.installed(name, release, &dir);
+        }
+
+        let mut files = Vec::with_capacity(release.files.len());
+        for artifact in &release.files {
+            let path = safe_join(&dir, &artifact.path)?;
+            let content = self.fetch(&self.artifact_url(&artifact.url))?;
+            let digest = sha256_hex(&content);
+            if !digest.eq_ignore_ascii_case(&artifact.sha256) {
+                return Err(Error::Registry(format!(
+                    "checksum mismatch for {} {} {}: expected {}, got {}",
+                    name, release.version, artifact.path, artifact.sha256, digest
+                )));
+            }
+            files.push((path, content));
+        }
+
+        let manifest_path = safe_join(&dir, &release.manifest)?;
+        let manifest = files
+            .iter()
+            .find(|(path, _)| *path == manifest_path)
+            .ok_or_else(|| {
+                Error::Registry(format!(
+                    "release {} {} has no file {}",
+                    name, release.version, release.manifest
+                ))
+            })?;
+        let manifest = Manifest::from_toml(&String::from_utf8_lossy(&manifest.1))?;
+        if manifest.name != name || manifest.version != release.version {
+            return Err(Error::Registry(format!(
+                "release {} {} contains the manifest of {} {}",
+                name, release.version, manifest.name, manifest.version
+            )));
+        }
+
+        let parent = dir.parent().unwrap_or(&self.install_dir);
+        std::fs::create_dir_all(parent)?;
+        let staging = parent.join(format!(
+            ".{}.partial-{}",
+            release.version,
+            std::process::id()
+        ));
+        let staged = stage(&staging, &dir, &files).and_then(|()| {
+            std::fs::rename(&staging, &dir).map_err(|e| {
+                Error::Registry(format!(
+                    "cannot install {} {} into {}: {}",
+                    name,
+                    release.version,
+                    dir.display(),
+                    e
+                ))
+            })
+        });
+        if let Err(e) = staged {
+            let _ = std::fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+        tracing::info!(
+            "Installed plugin {} {} into {}",
+            name,
+            release.version,
+            dir.display()
+        );
+        Ok(manifest_path)
+    }
+
+    /// Check an installed release still matches the index, returning the
+    /// path of its manifest.
+    fn installed(&self, name: &str, release: &IndexRelease, dir: &Path) -> Result<PathBuf> {
+        for artifact in &release.files {
+            let path = safe_join(dir, &artifact.path)?;
+            let matches = std::fs::read(&path)
+                .is_ok_and(|content| sha256_hex(&content).eq_ignore_ascii_case(&artifact.sha256));
+            if !matches {
+                return Err(Error::Registry(format!(
+                    "{} {} is already installed in {} with different files; remove it first",
+                    name,
+                    release.version,
+                    dir.display()
+                )));
+            }
+        }
+        safe_join(dir, &release.manifest)
+    }
+
+    /// Resolve a download URL relative to the index URL.
+    fn artifact_url(&self, url: &str) -> String {
+        if url.contains("://") || url.starts_with('/') {
+            return url.to_string();
+        }
+        match self.url.rfind('/') {
+            Some(i) => format!("{}{}", &self.url[..=i], url),
+            None => url.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Debug for RegistryIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RegistryIndex")
+            .field("url", &self.url)
+            .field("install_dir", &self.install_dir)
+            .finish()
+    }
+}
+
+fn default_manifest() -> String {
+    DEFAULT_MANIFEST.to_string()
+}
+
+/// Write `files`, whose paths are inside `dir`, below `staging` instead.
+fn stage(staging: &Path, dir: &Path, files: &[(PathBuf, Vec<u8>)]) -> Result<()> {
+    let _ = std::fs::remove_dir_all(staging);
+    std::fs::create_dir_all(staging)?;
+    for (path, content) in files {
+        let relative = path.strip_prefix(dir).unwrap_or(path);
+        let path = staging.join(relative);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, content)?;
+    }
+    Ok(())
+}
+
+/// Join a relative path from the index to `base`, refusing paths that
+/// could leave it.
+fn safe_join(base: &Path, relative: &str) -> Result<PathBuf> {
+    let path = Path::new(relative);
+    if relative.is_empty()
+        || !path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err(Error::Registry(format!(
+            "invalid path in index: {:?}",
+            relative
+        )));
+    }
+    Ok(base.join(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INDEX: &str = r#"
+[[plugin]]
+name = "greeter"
+
+[[plugin.version]]
+version = "1.2.0"
+
+[[plugin.version]]
+version = "1.3.0"
+yanked = true
+
+[[plugin.version]]
+version = "2.0.0"
+"#;
+
+    #[test]
+    fn test_resolve_release() {
+        let index = IndexDocument::from_toml(INDEX).unwrap();
+        assert_eq!(index.resolve("greeter", "^1.2").unwrap().version, "1.2.0");
+        assert_eq!(index.resolve("greeter", "*").unwrap().version, "2.0.0");
+        assert!(index.resolve("greeter", "^3").is_err());
+        assert!(index.resolve("missing", "*").is_err());
+        assert_eq!(
+            IndexDocument::from_toml(&index.to_toml().unwrap()).unwrap(),
+            index
+        );
+    }
+
+    #[test]
+    fn test_download_checks_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = concat!(
+            "name = \"greeter\"\nversion = \"1.2.0\"\n",
+            "api-version = { major = 0, minor = 21, patch = 0 }\n",
+        );
+        let fetcher = move |url: &str, _: &NetworkConfig| -> Result<Vec<u8>> {
+            assert_eq!(url, "https://plugins.example.com/greeter/plugin.toml");
+            Ok(manifest.as_bytes().to_vec())
+        };
+        let index = RegistryIndex::new(
+            "https://plugins.example.com/index.toml",
+            dir.path(),
+            fetcher,
+        );
+        let mut release = IndexRelease {
+            version: "1.2.0".into(),
+            manifest: DEFAULT_MANIFEST.into(),
+            yanked: false,
+            files: vec![IndexArtifact {
+                path: "plugin.toml".into(),
+                url: "greeter/plugin.toml".into(),
+                sha256: sha256_hex(manifest.as_bytes()),
+            }],
+        };
+
+        let path = index.download("greeter", &release).unwrap();
+        assert_eq!(path, dir.path().join("greeter/1.2.0/plugin.toml"));
+        assert!(path.is_file());
+        // Installing the same release again reuses the files
+        assert_eq!(index.download("greeter", &release).unwrap(), path);
+
+        release.files[0].path = "../escape.toml".into();
+        assert!(index.download("greeter", &release).is_err());
+        release.files[0].path = "plugin.toml".into();
+        release.files[0].sha256 = "0".repeat(64);
+        assert!(matches!(
+            index.download("greeter", &release),
+            Err(Error::Registry(_))
+        ));
+        // The installed version is left alone
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            manifest.to_string()
+        );
+
+        // A failed download of a new version leaves nothing behind
+        release.version = "1.3.0".into();
+        assert!(index.download("greeter", &release).is_err());
+        let mut entries: Vec<_> = std::fs::read_dir(dir.path().join("greeter"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        entries.sort();
+        assert_eq!(entries, vec!["1.2.0"]);
+    }
+
+    #[test]
+    fn test_fetch_uses_network_config() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+
+        let attempts = Arc::new(AtomicU32::new(0));
+        let counter = attempts.clone();
+        let fetcher = move |_: &str, network: &NetworkConfig| -> Result<Vec<u8>> {
+            assert_eq!(network.proxy.as_deref(), Some("http://proxy.corp:3128"));
+            match counter.fetch_add(1, Ordering::SeqCst) {
+                0 => Err(Error::network("connection reset")),
+                _ => Ok(b"[[plugin]]\nname = \"greeter\"\n".to_vec()),
+            }
+        };
+        let index = RegistryIndex::new(
+            "https://plugins.example.com/index.toml",
+            "installed",
+            fetcher,
+        )
+        .with_network(
+            NetworkConfig::new()
+                .with_proxy("http://proxy.corp:3128")
+                .with_retries(1, std::time::Duration::ZERO),
+        );
+
+        assert!(index.refresh().unwrap().get("greeter").is_some());
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+}
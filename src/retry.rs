@@ -0,0 +1,248 @@
+//! Per-call options and retrying failed calls.
+
+use std::time::Duration;
+
+use crate::config_error::ConfigError;
+use crate::error::{Error, Result};
+
+/// How failed plugin calls are retried.
+///
+/// A call is retried while fewer than `max_attempts` attempts were made and
+/// `retry_on` accepts the error, sleeping `backoff` before the first retry
+/// and twice as long before each further one, up to `max_backoff`. Errors
+/// carrying a retry delay, such as [`Error::RateLimited`], wait at least that
+/// long; if it exceeds `max_backoff` the error is returned instead.
+///
+/// Calls made on a [`CallDispatcher`](crate::CallDispatcher) worker are not
+/// retried, so backoff never holds up the worker pool.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Attempts made in total, including the first.
+    pub max_attempts: u32,
+    /// Delay before the first retry.
+    pub backoff: Duration,
+    /// Upper bound for the delay.
+    pub max_backoff: Duration,
+    /// Which errors are retried; [`Error::is_transient`] by default.
+    pub retry_on: fn(&Error) -> bool,
+}
+
+impl RetryPolicy {
+    /// Make up to `max_attempts` attempts, waiting `backoff` before the
+    /// first retry.
+    pub fn new(max_attempts: u32, backoff: Duration) -> Self {
+        Self {
+            max_attempts,
+            backoff,
+            max_backoff: Duration::from_secs(2),
+            retry_on: Error::is_transient,
+        }
+    }
+
+    /// Set the upper bound for the delay between attempts.
+    pub fn with_max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Set which errors are retried.
+    ///
+    /// A host whose exports are idempotent can retry engine failures too
+    /// with `|e| e.is_transient() || matches!(e.root(), Error::ExecutionFailed(_))`.
+    pub fn with_retry_on(mut self, retry_on: fn(&Error) -> bool) -> Self {
+        self.retry_on = retry_on;
+        self
+    }
+
+    /// Check the attempt count and delays.
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = ConfigError::new();
+        errors.check(
+            self.max_attempts > 0,
+            "max_attempts",
+            "must be greater than zero",
+        );
+        errors.check(
+            self.backoff <= self.max_backoff,
+            "backoff",
+            format!("must not exceed max_backoff ({:?})", self.max_backoff),
+        );
+        errors.into_result()
+    }
+
+    /// Run `op`, retrying the errors the policy accepts.
+    pub(crate) fn run<T>(&self, mut op: impl FnMut() -> Result<T>) -> Result<T> {
+        let mut delay = self.backoff;
+        let mut attempt = 1;
+
+        loop {
+            match op() {
+                Err(e) if attempt < self.max_attempts && (self.retry_on)(&e) => {
+                    let wait = e.retry_after().map_or(delay, |after| after.max(delay));
+                    if wait > self.max_backoff || crate::dispatcher::on_worker() {
+                        return Err(e);
+                    }
+                    tracing::debug!(
+                        "Call failed ({}), retry {}/{} in {:?}",
+                        e,
+                        attempt,
+                        self.max_attempts - 1,
+                        wait
+                    );
+                    attempt += 1;
+                    std::thread::sleep(wait);
+                    delay = delay.saturating_mul(2).min(self.max_backoff);
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Options applied to plugin calls made through
+/// [`PluginRuntime::call`](crate::PluginRuntime::call).
+///
+/// Defaults for every call are set with
+/// [`RuntimeConfig::with_call_options`](crate::RuntimeConfig::with_call_options);
+/// [`PluginRuntime::call_with_options`](crate::PluginRuntime::call_with_options)
+/// overrides them for a single call.
+#[derive(Debug, Clone, Default)]
+pub struct CallOptions {
+    retry: Option<RetryPolicy>,
+}
+
+impl CallOptions {
+    /// Create options that make a single attempt.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Retry failed calls with `policy`.
+    pub fn with_retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Get the retry policy, if calls are retried.
+    pub fn retry(&self) -> Option<&RetryPolicy> {
+        self.retry.as_ref()
+    }
+
+    /// Check the retry policy.
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = ConfigError::new();
+        if let Some(ref policy) = self.retry {
+            errors.nest("retry", policy.validate());
+        }
+        errors.into_result()
+    }
+
+    /// Run a call with the options applied.
+    pub(crate) fn run<T>(&self, mut op: impl FnMut() -> Result<T>) -> Result<T> {
+        match self.retry {
+            Some(ref policy) => policy.run(op),
+            None => op(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_policy() {
+        let policy = RetryPolicy::new(3, Duration::from_millis(1));
+        let mut attempts = 0;
+        let result: Result<()> = policy.run(|| {
+            attempts += 1;
+            Err(Error::queue_full("p", 1))
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 3);
+
+        // Deterministic errors and engine failures fail on the first attempt
+        let deterministic: [fn() -> Error; 4] = [
+            || Error::execution_failed("boom"),
+            || Error::plugin_not_found("p"),
+            || Error::FunctionNotFound("f".into()),
+            || Error::cancelled("call"),
+        ];
+        for error in deterministic {
+            let mut attempts = 0;
+            let result: Result<()> = policy.run(|| {
+                attempts += 1;
+                Err(error())
+            });
+            assert!(result.is_err());
+            assert_eq!(attempts, 1);
+        }
+
+        let policy = policy.with_retry_on(|e| matches!(e.root(), Error::InvalidState { .. }));
+        let mut attempts = 0;
+        let result = policy.run(|| {
+            attempts += 1;
+            match attempts {
+                1 => Err(Error::invalid_state("ready", "busy")),
+                n => Ok(n),
+            }
+        });
+        assert_eq!(result.unwrap(), 2);
+    }
+
+    #[test]
+    fn test_retry_honors_retry_after() {
+        let policy = RetryPolicy::new(2, Duration::from_millis(1))
+            .with_max_backoff(Duration::from_millis(100));
+
+        let started = std::time::Instant::now();
+        let mut attempts = 0;
+        let result: Result<()> = policy.run(|| {
+            attempts += 1;
+            Err(Error::rate_limited("p", Duration::from_millis(20)))
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 2);
+        assert!(started.elapsed() >= Duration::from_millis(20));
+
+        // Waiting longer than the backoff allows is pointless
+        let mut attempts = 0;
+        let result: Result<()> = policy.run(|| {
+            attempts += 1;
+            Err(Error::rate_limited("p", Duration::from_secs(60)))
+        });
+        assert!(matches!(result, Err(Error::RateLimited { .. })));
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn test_no_retry_on_dispatcher_workers() {
+        let dispatcher = crate::CallDispatcher::new(Default::default()).unwrap();
+        let receipt = dispatcher
+            .submit("p", || {
+                let mut attempts = 0;
+                let _: Result<()> = RetryPolicy::new(3, Duration::from_millis(1)).run(|| {
+                    attempts += 1;
+                    Err(Error::queue_full("p", 1))
+                });
+                Ok(fusabi_host::Value::Int(attempts))
+            })
+            .unwrap();
+        assert!(matches!(receipt.wait(), Ok(fusabi_host::Value::Int(1))));
+        dispatcher.shutdown();
+    }
+
+    #[test]
+    fn test_call_options_validate() {
+        assert!(CallOptions::new().validate().is_ok());
+        let options = CallOptions::new().with_retry(
+            RetryPolicy::new(0, Duration::from_secs(5)).with_max_backoff(Duration::from_secs(1)),
+        );
+        match options.validate() {
+            Err(Error::Config(e)) => {
+                assert_eq!(e.paths(), vec!["retry.max_attempts", "retry.backoff"])
+            }
+            other => panic!("expected a config error, got {:?}", other),
+        }
+    }
+}
@@ -1,15 +1,112 @@
 //! Plugin runtime for managing the plugin lifecycle.
 
-use std::path::PathBuf;
-use std::sync::Arc;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
 
+use dashmap::DashMap;
 use parking_lot::RwLock;
 
-use crate::error::{Error, Result};
-use crate::lifecycle::LifecycleHooks;
+use crate::api::RuntimeApi;
+use crate::audit::{
+    AuditFanout, AuditOutcome, AuditRecord, AuditSink, CallDetails, CallSampling, MemoryAuditLog,
+};
+use crate::authorizer::{CallAuthorizer, Principal};
+use crate::budget::{Admission, BudgetTracker, BudgetUsage, ExecutionBudget, ThrottleAction};
+use crate::builtin::BuiltinPlugin;
+use crate::bulk::{BulkOp, BulkPolicy, BulkProgress, BulkReport};
+use crate::cancel::CancellationToken;
+#[cfg(feature = "serde")]
+use crate::cancel::Partial;
+use crate::cli::CliCommand;
+use crate::cluster::ClusterBackend;
+#[cfg(feature = "serde")]
+use crate::cluster::{ConvergeReport, DesiredPlugin};
+use crate::config_error::{check_dir, ConfigError};
+#[cfg(feature = "serde")]
+use crate::delegation::PLUGINS_LOAD;
+#[cfg(feature = "serde")]
+use crate::dependency::DependencyGraph;
+use crate::discovery::CollisionStrategy;
+#[cfg(feature = "serde")]
+use crate::discovery::{self, Candidate, DiscoveryReport};
+use crate::dispatcher::{CallDispatcher, CallReceipt, DispatcherConfig};
+use crate::error::{Error, PluginOperation, Result};
+#[cfg(feature = "serde")]
+use crate::explain::LoadExplanation;
+use crate::features::{enabled_features, Feature};
+use crate::health::{HealthStatus, PluginHealth};
+use crate::host_functions::HostFunctionTable;
+use crate::host_info::HostInfo;
+#[cfg(feature = "watch")]
+use crate::hot_reload::HotReloadManager;
+use crate::lifecycle::{LifecycleHooks, LifecycleState, Transition};
 use crate::loader::{LoaderConfig, PluginLoader};
-use crate::plugin::PluginHandle;
+#[cfg(feature = "serde")]
+use crate::lockfile::{Drift, Lockfile, VerifyReport};
+#[cfg(feature = "serde")]
+use crate::manifest::Manifest;
+#[cfg(feature = "metrics-prometheus")]
+use crate::metrics::PluginMetrics;
+use crate::network::{NetworkConfig, OfflineSwitch};
+use crate::origin::PluginOrigin;
+use crate::plugin::{CallGate, GateArgs, PluginHandle};
+use crate::pool::{AutoscalePolicy, PoolSignals};
+use crate::prewarm::CallProfile;
+use crate::rate_limit::{CallRateLimiter, RateLimit};
 use crate::registry::{PluginRegistry, RegistryConfig, RegistryStats};
+#[cfg(feature = "serde")]
+use crate::registry_index::RegistryIndex;
+use crate::retry::CallOptions;
+use crate::schedule::ScheduledJob;
+use crate::sensitive::CallArg;
+use crate::startup::StartupReport;
+use crate::storage::{PluginStorage, StorageQuota, StorageScope, StorageStats};
+use crate::supervisor::{RestartPolicy, Supervisor};
+use crate::tasks::{
+    PluginShutdownOutcome, ShutdownPolicy, ShutdownReport, TaskHealth, TaskSupervisor, TaskWaker,
+};
+use crate::tenant::{TenantActivation, TenantPolicy};
+use crate::update::{UpdateChecker, UpdateConfig, UpdateSource};
+#[cfg(feature = "watch")]
+use crate::watcher::{WatchConfig, WatcherStats};
+
+const SUPERVISION_TASK: &str = "supervision";
+const RESTART_TASK: &str = "restarts";
+const UPDATE_TASK: &str = "update-checks";
+const HEALTH_TASK: &str = "health-checks";
+const AUTOSCALE_TASK: &str = "autoscaling";
+#[cfg(feature = "serde")]
+const CONVERGE_TASK: &str = "cluster-convergence";
+// How long `restart` waits for calls in progress before giving up
+const SWAP_GATE_TIMEOUT: Duration = Duration::from_secs(5);
+
+thread_local! {
+    // Plugin calls in progress on this thread, through the runtime's gate
+    static CALL_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Tracks a call on the current thread for as long as it is alive.
+struct CallDepth(usize);
+
+impl CallDepth {
+    fn enter() -> Self {
+        Self(CALL_DEPTH.with(|depth| depth.replace(depth.get() + 1)))
+    }
+
+    /// Check if this is the outermost call on the thread.
+    fn is_outermost(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl Drop for CallDepth {
+    fn drop(&mut self) {
+        CALL_DEPTH.with(|depth| depth.set(self.0));
+    }
+}
 
 /// Configuration for the plugin runtime.
 #[derive(Debug, Clone)]
@@ -20,10 +117,68 @@ pub struct RuntimeConfig {
     pub registry: RegistryConfig,
     /// Plugin directories to scan.
     pub plugin_dirs: Vec<PathBuf>,
+    /// Plugin directories tagged with the origin of their plugins, added to
+    /// the loader's when the runtime is created.
+    pub origin_dirs: Vec<(PathBuf, PluginOrigin)>,
     /// Whether to auto-discover plugins.
     pub auto_discover: bool,
     /// File patterns to match for plugins.
     pub plugin_patterns: Vec<String>,
+    /// Default storage quota applied to every plugin.
+    pub storage_quota: StorageQuota,
+    /// Whether network-touching subsystems start disabled.
+    pub offline: bool,
+    /// HTTP(S) settings for network-touching subsystems.
+    pub network: NetworkConfig,
+    /// Restart policy for plugins without an explicit policy.
+    pub restart_policy: Option<RestartPolicy>,
+    /// Execution budget for plugins without an explicit budget.
+    pub default_budget: Option<ExecutionBudget>,
+    /// Engine pool policy for stateless plugins.
+    pub autoscale: Option<AutoscalePolicy>,
+    /// Consecutive restarts after which a failing plugin is rolled back.
+    pub rollback_after: Option<u32>,
+    /// What to do with background jobs still running at shutdown.
+    pub shutdown_policy: ShutdownPolicy,
+    /// Number of capability audit records kept in memory.
+    pub audit_capacity: usize,
+    /// Which plugin calls are recorded in the audit log.
+    pub call_sampling: CallSampling,
+    /// Options such as retries applied to every call.
+    pub call_options: CallOptions,
+    /// Call rate limits per plugin, replacing the manifests' limits.
+    pub plugin_rate_limits: HashMap<String, RateLimit>,
+    /// Call rate limits per plugin and function, replacing the manifests'
+    /// limits.
+    pub function_rate_limits: HashMap<(String, String), RateLimit>,
+    /// Worker pool and queue sizes for queued calls.
+    pub dispatcher: DispatcherConfig,
+    /// Number of most-called plugins warmed as they load and after a
+    /// reload.
+    pub prewarm_top: usize,
+    /// Calls counted in a previous run, ranked together with the calls
+    /// since startup to pick the plugins to warm.
+    pub prewarm_profile: CallProfile,
+    /// Backend sharing the desired plugin set with other hosts.
+    pub cluster: Option<Arc<dyn ClusterBackend>>,
+    /// Identity of this host within a cluster, used as lease holder.
+    pub instance_id: String,
+    /// Authorizer restricting which principals may call which exports.
+    pub call_authorizer: Option<Arc<dyn CallAuthorizer>>,
+    /// Capability restrictions per tenant.
+    pub tenant_policies: HashMap<String, TenantPolicy>,
+    /// Lockfile pinning the installed plugins, checked against the
+    /// installed files and artifact store when the runtime is created.
+    pub lockfile: Option<PathBuf>,
+    /// Index plugins are installed from by
+    /// [`PluginRuntime::install_from_index`].
+    #[cfg(feature = "serde")]
+    pub registry_index: Option<Arc<RegistryIndex>>,
+    /// How discovery handles manifests declaring the same plugin name.
+    pub collision_strategy: CollisionStrategy,
+    /// Metrics recording loads, unloads, compiles, calls and errors.
+    #[cfg(feature = "metrics-prometheus")]
+    pub metrics: Option<Arc<PluginMetrics>>,
 }
 
 impl Default for RuntimeConfig {
@@ -32,12 +187,39 @@ impl Default for RuntimeConfig {
             loader: LoaderConfig::default(),
             registry: RegistryConfig::default(),
             plugin_dirs: Vec::new(),
+            origin_dirs: Vec::new(),
             auto_discover: false,
             plugin_patterns: vec![
                 "*.toml".to_string(),
                 "plugin.toml".to_string(),
                 "fusabi.toml".to_string(),
             ],
+            storage_quota: StorageQuota::default(),
+            offline: false,
+            network: NetworkConfig::default(),
+            restart_policy: None,
+            default_budget: None,
+            autoscale: None,
+            rollback_after: None,
+            shutdown_policy: ShutdownPolicy::default(),
+            audit_capacity: 1000,
+            call_sampling: CallSampling::default(),
+            call_options: CallOptions::default(),
+            plugin_rate_limits: HashMap::new(),
+            function_rate_limits: HashMap::new(),
+            dispatcher: DispatcherConfig::default(),
+            prewarm_top: 0,
+            prewarm_profile: CallProfile::new(),
+            cluster: None,
+            instance_id: default_instance_id(),
+            call_authorizer: None,
+            tenant_policies: HashMap::new(),
+            lockfile: None,
+            #[cfg(feature = "serde")]
+            registry_index: None,
+            collision_strategy: CollisionStrategy::default(),
+            #[cfg(feature = "metrics-prometheus")]
+            metrics: None,
         }
     }
 }
@@ -66,6 +248,17 @@ impl RuntimeConfig {
         self
     }
 
+    /// Add a plugin directory whose plugins come from `origin`.
+    ///
+    /// The tag is applied to the loader when the runtime is created, so it
+    /// survives a later [`with_loader`](Self::with_loader).
+    pub fn with_origin_dir(mut self, dir: impl Into<PathBuf>, origin: PluginOrigin) -> Self {
+        let dir = dir.into();
+        self.origin_dirs.push((dir.clone(), origin));
+        self.plugin_dirs.push(dir);
+        self
+    }
+
     /// Enable auto-discovery.
     pub fn with_auto_discover(mut self, auto: bool) -> Self {
         self.auto_discover = auto;
@@ -77,27 +270,365 @@ impl RuntimeConfig {
         self.plugin_patterns = patterns;
         self
     }
+
+    /// Set how discovery handles manifests declaring the same plugin name.
+    pub fn with_collision_strategy(mut self, strategy: CollisionStrategy) -> Self {
+        self.collision_strategy = strategy;
+        self
+    }
+
+    /// Enable offline mode for air-gapped deployments.
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Set the network configuration.
+    pub fn with_network(mut self, network: NetworkConfig) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// Supervise every plugin with a default restart policy.
+    pub fn with_restart_policy(mut self, policy: RestartPolicy) -> Self {
+        self.restart_policy = Some(policy);
+        self
+    }
+
+    /// Throttle every plugin with a default execution budget.
+    pub fn with_default_budget(mut self, budget: ExecutionBudget) -> Self {
+        self.default_budget = Some(budget);
+        self
+    }
+
+    /// Serve every stateless plugin from an engine pool sized by `policy`.
+    pub fn with_autoscale(mut self, policy: AutoscalePolicy) -> Self {
+        self.autoscale = Some(policy);
+        self
+    }
+
+    /// Roll supervised plugins back to their last known-good version when
+    /// they fail again after `restarts` consecutive restarts.
+    ///
+    /// Requires an artifact store in the loader configuration.
+    pub fn with_rollback_after(mut self, restarts: u32) -> Self {
+        self.rollback_after = Some(restarts);
+        self
+    }
+
+    /// Set the worker pool and queue sizes for queued calls.
+    pub fn with_dispatcher(mut self, config: DispatcherConfig) -> Self {
+        self.dispatcher = config;
+        self
+    }
+
+    /// Set the policy for background jobs still running at shutdown.
+    pub fn with_shutdown_policy(mut self, policy: ShutdownPolicy) -> Self {
+        self.shutdown_policy = policy;
+        self
+    }
+
+    /// Set the number of capability audit records kept in memory.
+    pub fn with_audit_capacity(mut self, capacity: usize) -> Self {
+        self.audit_capacity = capacity;
+        self
+    }
+
+    /// Record a sample of plugin calls in the audit log.
+    pub fn with_call_sampling(mut self, sampling: CallSampling) -> Self {
+        self.call_sampling = sampling;
+        self
+    }
+
+    /// Set the options, such as a retry policy, applied to every call.
+    pub fn with_call_options(mut self, options: CallOptions) -> Self {
+        self.call_options = options;
+        self
+    }
+
+    /// Limit the rate of calls into a plugin, in place of the
+    /// `max-calls-per-second` its manifest declares.
+    pub fn with_plugin_rate_limit(mut self, plugin: impl Into<String>, limit: RateLimit) -> Self {
+        self.plugin_rate_limits.insert(plugin.into(), limit);
+        self
+    }
+
+    /// Limit the rate of calls into one function of a plugin, in place of
+    /// the limit its manifest declares.
+    pub fn with_function_rate_limit(
+        mut self,
+        plugin: impl Into<String>,
+        function: impl Into<String>,
+        limit: RateLimit,
+    ) -> Self {
+        self.function_rate_limits
+            .insert((plugin.into(), function.into()), limit);
+        self
+    }
+
+    /// Join a cluster sharing its desired plugin set through `backend`.
+    pub fn with_cluster(mut self, backend: impl ClusterBackend + 'static) -> Self {
+        self.cluster = Some(Arc::new(backend));
+        self
+    }
+
+    /// Set the identity of this host within a cluster.
+    pub fn with_instance_id(mut self, id: impl Into<String>) -> Self {
+        self.instance_id = id.into();
+        self
+    }
+
+    /// Restrict which principals may call which plugin exports.
+    pub fn with_call_authorizer(mut self, authorizer: impl CallAuthorizer + 'static) -> Self {
+        self.call_authorizer = Some(Arc::new(authorizer));
+        self
+    }
+
+    /// Restrict the capabilities plugins get when acting for `tenant`.
+    pub fn with_tenant_policy(mut self, tenant: impl Into<String>, policy: TenantPolicy) -> Self {
+        self.tenant_policies.insert(tenant.into(), policy);
+        self
+    }
+
+    /// Set the lockfile written by [`PluginRuntime::lock`].
+    ///
+    /// If it exists, [`PluginRuntime::new`] fails with [`Error::Registry`]
+    /// when a pinned file was modified or removed, or the loader's artifact
+    /// store lacks a pinned version.
+    pub fn with_lockfile(mut self, path: impl Into<PathBuf>) -> Self {
+        self.lockfile = Some(path.into());
+        self
+    }
+
+    /// Install plugins from `index`.
+    ///
+    /// Its install directory becomes a directory of
+    /// [`PluginOrigin::Remote`] plugins.
+    #[cfg(feature = "serde")]
+    pub fn with_registry_index(mut self, index: RegistryIndex) -> Self {
+        self.registry_index = Some(Arc::new(index));
+        self
+    }
+
+    /// Warm plugins that are among the `top` most called as they load and
+    /// after a reload.
+    pub fn with_prewarm(mut self, top: usize) -> Self {
+        self.prewarm_top = top;
+        self
+    }
+
+    /// Rank plugins for warming by the calls of `profile` too, e.g. one
+    /// saved from the previous run with
+    /// [`PluginRuntime::call_profile`], so the hottest plugins are warmed
+    /// as they load at startup.
+    pub fn with_prewarm_profile(mut self, profile: CallProfile) -> Self {
+        self.prewarm_profile = profile;
+        self
+    }
+
+    /// Set the default storage quota.
+    pub fn with_storage_quota(mut self, quota: StorageQuota) -> Self {
+        self.storage_quota = quota;
+        self
+    }
+
+    /// Record runtime events to `metrics`.
+    #[cfg(feature = "metrics-prometheus")]
+    pub fn with_metrics(mut self, metrics: impl Into<Arc<PluginMetrics>>) -> Self {
+        self.metrics = Some(metrics.into());
+        self
+    }
+
+    /// Validate the configuration, failing with [`Error::Config`] listing
+    /// every invalid field by its path, e.g. `loader.base_path` or
+    /// `plugin_dirs[0]`.
+    ///
+    /// Called by [`PluginRuntime::new`].
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = ConfigError::new();
+        errors.nest("loader", self.loader.validate());
+        errors.nest("network", self.network.validate());
+        if let Some(ref policy) = self.autoscale {
+            errors.nest("autoscale", policy.validate());
+        }
+        errors.nest("call_options", self.call_options.validate());
+        let rate_limits = self
+            .plugin_rate_limits
+            .iter()
+            .map(|(plugin, limit)| (format!("plugin_rate_limits.{}", plugin), limit))
+            .chain(
+                self.function_rate_limits
+                    .iter()
+                    .map(|((plugin, function), limit)| {
+                        (
+                            format!("function_rate_limits.{}.{}", plugin, function),
+                            limit,
+                        )
+                    }),
+            );
+        for (path, limit) in rate_limits {
+            errors.nest(&path, limit.validate());
+        }
+        errors.check(
+            self.registry.max_plugins > 0,
+            "registry.max_plugins",
+            "must be greater than zero",
+        );
+
+        for (i, dir) in self.plugin_dirs.iter().enumerate() {
+            check_dir(&mut errors, &format!("plugin_dirs[{}]", i), dir);
+        }
+        errors.check(
+            !self.auto_discover || !self.plugin_dirs.is_empty(),
+            "auto_discover",
+            "requires at least one plugin directory",
+        );
+        errors.check(
+            self.plugin_dirs.is_empty() || !self.plugin_patterns.is_empty(),
+            "plugin_patterns",
+            "no patterns to match in the plugin directories",
+        );
+        for (i, pattern) in self.plugin_patterns.iter().enumerate() {
+            let relative = Path::new(pattern)
+                .components()
+                .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+            errors.check(
+                !pattern.is_empty() && relative,
+                &format!("plugin_patterns[{}]", i),
+                format!("{:?} must be relative to the plugin directories", pattern),
+            );
+        }
+
+        errors.nest("dispatcher", self.dispatcher.validate());
+        errors.check(
+            self.rollback_after.is_none() || self.loader.artifact_store.is_some(),
+            "rollback_after",
+            "requires loader.artifact_store",
+        );
+        errors.check(
+            self.cluster.is_none() || !self.instance_id.is_empty(),
+            "instance_id",
+            "must not be empty in cluster mode",
+        );
+
+        // Fields only read by functionality compiled in with `serde`
+        Feature::Serde.check(&mut errors, self.auto_discover, "auto_discover");
+        Feature::Serde.check(&mut errors, self.rollback_after.is_some(), "rollback_after");
+        Feature::Serde.check(&mut errors, self.cluster.is_some(), "cluster");
+        Feature::Serde.check(&mut errors, self.lockfile.is_some(), "lockfile");
+        errors.into_result()
+    }
 }
 
 /// Plugin runtime for managing plugins.
 pub struct PluginRuntime {
     config: RuntimeConfig,
-    loader: PluginLoader,
-    registry: PluginRegistry,
+    loader: Arc<PluginLoader>,
+    registry: Arc<PluginRegistry>,
+    storage: PluginStorage,
     hooks: Arc<RwLock<LifecycleHooks>>,
+    offline: OfflineSwitch,
+    capability_audit: Arc<AuditFanout>,
+    dispatcher: std::sync::OnceLock<CallDispatcher>,
+    host_functions: Arc<HostFunctionTable>,
+    recovery: Arc<Recovery>,
+    tasks: Arc<TaskSupervisor>,
+    // Set once `spawn_scheduled_jobs` ran, so plugins loaded later get theirs
+    jobs_scheduled: std::sync::atomic::AtomicBool,
+    cluster_managed: parking_lot::Mutex<std::collections::HashSet<String>>,
+    discovery_warnings: parking_lot::Mutex<Vec<String>>,
+    #[cfg(feature = "watch")]
+    hot_reload: parking_lot::Mutex<Option<HotReloadManager>>,
 }
 
 impl PluginRuntime {
     /// Create a new plugin runtime.
     pub fn new(config: RuntimeConfig) -> Result<Self> {
-        let loader = PluginLoader::new(config.loader.clone())?;
-        let registry = PluginRegistry::new(config.registry.clone());
+        config.validate()?;
+        let mut loader_config = config.loader.clone();
+        #[cfg(feature = "metrics-prometheus")]
+        if let Some(ref metrics) = config.metrics {
+            loader_config.metrics = Some(metrics.clone());
+        }
+        for (dir, origin) in &config.origin_dirs {
+            loader_config = loader_config.with_origin_dir(dir.clone(), *origin);
+        }
+        #[cfg(feature = "serde")]
+        if let Some(ref index) = config.registry_index {
+            index.set_network(config.network.clone());
+            loader_config =
+                loader_config.with_origin_dir(index.install_dir(), PluginOrigin::Remote);
+        }
+        let host_functions = loader_config
+            .host_functions
+            .get_or_insert_with(Default::default)
+            .clone();
+        let loader = Arc::new(PluginLoader::new(loader_config)?);
+        #[cfg(feature = "serde")]
+        if let Some(ref path) = config.lockfile {
+            if path.exists() {
+                Lockfile::load(path)?
+                    .verify_installed(loader.config().artifact_store.as_deref())
+                    .into_result()?;
+            }
+        }
+        let registry = Arc::new(PluginRegistry::new(config.registry.clone()));
+        #[cfg(feature = "metrics-prometheus")]
+        if let Some(ref metrics) = config.metrics {
+            metrics.track_registry(&registry);
+        }
+        let storage = PluginStorage::with_default_quota(config.storage_quota.clone());
+        storage.register_host_functions(&host_functions);
+        crate::host_info::register_host_functions(
+            &host_functions,
+            &loader.config().host_api_version,
+        );
+        let offline = OfflineSwitch::new(config.offline);
+        let hooks = Arc::new(RwLock::new(LifecycleHooks::new()));
+        let capability_audit = Arc::new(AuditFanout::new(
+            config.audit_capacity,
+            config.call_sampling.capacity(),
+        ));
+        let tasks = Arc::new(TaskSupervisor::new());
+        let recovery = Arc::new(Recovery {
+            loader: loader.clone(),
+            registry: registry.clone(),
+            hooks: hooks.clone(),
+            supervisor: Arc::new(supervisor(&config)),
+            budgets: Arc::new(BudgetTracker::new(config.default_budget.clone())),
+            prewarm_top: config.prewarm_top,
+            prewarm_profile: config.prewarm_profile.clone(),
+            known_good: Arc::new(DashMap::new()),
+            swap_gate: Arc::new(RwLock::new(())),
+            rate_limits: Arc::new(CallRateLimiter::new(
+                config.plugin_rate_limits.clone(),
+                config.function_rate_limits.clone(),
+            )),
+            authorizer: config.call_authorizer.clone(),
+            call_sampling: config.call_sampling.clone(),
+            sampled_calls: Arc::new(std::sync::atomic::AtomicU64::new(0)),
+            audit: capability_audit.clone(),
+            tasks: tasks.clone(),
+            restarts: Arc::new(parking_lot::Mutex::new(None)),
+        });
 
         Ok(Self {
+            capability_audit,
+            dispatcher: std::sync::OnceLock::new(),
+            host_functions,
             config,
             loader,
             registry,
-            hooks: Arc::new(RwLock::new(LifecycleHooks::new())),
+            storage,
+            hooks,
+            offline,
+            recovery,
+            tasks,
+            jobs_scheduled: std::sync::atomic::AtomicBool::new(false),
+            cluster_managed: parking_lot::Mutex::new(std::collections::HashSet::new()),
+            discovery_warnings: parking_lot::Mutex::new(Vec::new()),
+            #[cfg(feature = "watch")]
+            hot_reload: parking_lot::Mutex::new(None),
         })
     }
 
@@ -111,6 +642,20 @@ impl PluginRuntime {
         &self.config
     }
 
+    /// Get the host API version and build information.
+    ///
+    /// Plugins read the same through the
+    /// [`HOST_INFO_FUNCTIONS`](crate::HOST_INFO_FUNCTIONS), and admin clients
+    /// through [`AdminApi::host_info`](crate::AdminApi::host_info).
+    pub fn host_info(&self) -> HostInfo {
+        HostInfo::collect(&self.loader.config().host_api_version)
+    }
+
+    /// Get the optional features the runtime was built with.
+    pub fn enabled_features(&self) -> Vec<Feature> {
+        enabled_features()
+    }
+
     /// Get the plugin loader.
     pub fn loader(&self) -> &PluginLoader {
         &self.loader
@@ -121,6 +666,91 @@ impl PluginRuntime {
         &self.registry
     }
 
+    /// Get the shared plugin storage.
+    pub fn storage(&self) -> &PluginStorage {
+        &self.storage
+    }
+
+    /// Get the storage scope of a loaded plugin.
+    pub fn plugin_storage(&self, name: &str) -> Result<StorageScope> {
+        if !self.registry.contains(name) {
+            return Err(Error::plugin_not_found(name));
+        }
+        Ok(self.storage.scoped(name))
+    }
+
+    /// Get storage usage statistics of a plugin.
+    pub fn storage_stats(&self, name: &str) -> StorageStats {
+        self.storage.stats(name)
+    }
+
+    /// Get the record of capability-gated accesses by loaded plugins.
+    pub fn audit_log(&self) -> &MemoryAuditLog {
+        self.capability_audit.log()
+    }
+
+    /// Get the calls recorded by the configured
+    /// [`CallSampling`](crate::CallSampling), kept apart from the
+    /// [`audit_log`](Self::audit_log) so they never evict its records.
+    pub fn call_log(&self) -> &MemoryAuditLog {
+        self.capability_audit.calls()
+    }
+
+    /// Forward capability audit records to another sink, e.g. a
+    /// [`JsonlAuditSink`](crate::JsonlAuditSink).
+    pub fn add_audit_sink(&self, sink: impl AuditSink + 'static) {
+        self.capability_audit.add_sink(Arc::new(sink));
+    }
+
+    /// Get the network configuration for host-built HTTP clients.
+    pub fn network_config(&self) -> &NetworkConfig {
+        &self.config.network
+    }
+
+    /// Get the offline switch shared with network-touching subsystems.
+    pub fn offline_switch(&self) -> &OfflineSwitch {
+        &self.offline
+    }
+
+    /// Check if offline mode is enabled.
+    pub fn is_offline(&self) -> bool {
+        self.offline.is_offline()
+    }
+
+    /// Enable or disable offline mode.
+    pub fn set_offline(&self, offline: bool) {
+        if offline != self.offline.is_offline() {
+            tracing::info!(
+                "Offline mode {}",
+                if offline { "enabled" } else { "disabled" }
+            );
+        }
+        self.offline.set_offline(offline);
+    }
+
+    /// Create an update checker for plugins of this runtime.
+    ///
+    /// The checker follows the runtime's offline switch and network
+    /// configuration, and polls from the runtime's `update-checks`
+    /// background task.
+    pub fn update_checker(
+        &self,
+        config: UpdateConfig,
+        source: Arc<dyn UpdateSource>,
+    ) -> UpdateChecker {
+        let config = config
+            .with_offline_switch(self.offline.clone())
+            .with_network(self.config.network.clone());
+        UpdateChecker::new(config, source, self.loader.clone(), self.registry.clone())
+            .with_tasks(self.tasks.clone())
+    }
+
+    /// Receive the lifecycle events emitted by the runtime on a channel,
+    /// e.g. to process them on another thread or async task.
+    pub fn subscribe(&self) -> std::sync::mpsc::Receiver<crate::lifecycle::LifecycleEvent> {
+        self.hooks.read().subscribe()
+    }
+
     /// Add a lifecycle event handler.
     pub fn on_event<F>(&self, handler: F)
     where
@@ -129,34 +759,271 @@ impl PluginRuntime {
         self.hooks.write().on_event(handler);
     }
 
+    /// Add a hook that can veto plugin starts by returning an error.
+    pub fn before_start<F>(&self, guard: F)
+    where
+        F: Fn(&str) -> Result<()> + Send + Sync + 'static,
+    {
+        self.hooks.write().before_start(guard);
+    }
+
+    /// Add a hook that can veto plugin stops by returning an error.
+    ///
+    /// Runtime shutdown stops plugins regardless of these hooks.
+    pub fn before_stop<F>(&self, guard: F)
+    where
+        F: Fn(&str) -> Result<()> + Send + Sync + 'static,
+    {
+        self.hooks.write().before_stop(guard);
+    }
+
+    /// Add a hook that can veto plugin reloads, including hot reloads and
+    /// supervised restarts, by returning an error.
+    pub fn before_reload<F>(&self, guard: F)
+    where
+        F: Fn(&str) -> Result<()> + Send + Sync + 'static,
+    {
+        self.hooks.write().before_reload(guard);
+    }
+
+    /// Start reloading plugins automatically when their files change.
+    ///
+    /// Every configured plugin directory that exists is watched, unless
+    /// the policy of its origin disables watching. Calling
+    /// this again replaces the previous watcher.
+    #[cfg(feature = "watch")]
+    pub fn enable_hot_reload(&self, config: WatchConfig) -> Result<()> {
+        let manager = HotReloadManager::with_hooks(
+            config,
+            self.loader.clone(),
+            self.registry.clone(),
+            self.hooks.clone(),
+            self.tasks.clone(),
+        )?;
+
+        let loader = self.loader.config();
+        for dir in &self.config.plugin_dirs {
+            if dir.exists() && loader.origin_policy(loader.origin_of(dir)).watch {
+                manager.watch(dir)?;
+            }
+        }
+        manager.start()?;
+
+        *self.hot_reload.lock() = Some(manager);
+        Ok(())
+    }
+
+    /// Stop automatic reloading.
+    #[cfg(feature = "watch")]
+    pub fn disable_hot_reload(&self) {
+        if let Some(manager) = self.hot_reload.lock().take() {
+            manager.stop();
+        }
+    }
+
+    /// Check if automatic reloading is enabled.
+    #[cfg(feature = "watch")]
+    pub fn is_hot_reload_enabled(&self) -> bool {
+        self.hot_reload.lock().is_some()
+    }
+
+    /// Get hot reload watcher statistics.
+    #[cfg(feature = "watch")]
+    pub fn hot_reload_stats(&self) -> Option<WatcherStats> {
+        self.hot_reload.lock().as_ref().map(|m| m.stats())
+    }
+
     /// Load a plugin from a manifest file.
     #[cfg(feature = "serde")]
     pub fn load_manifest(&self, path: impl Into<PathBuf>) -> Result<PluginHandle> {
-        let plugin = self.loader.load_from_manifest(path.into())?;
-        self.registry.register(plugin.clone())?;
+        self.load_path(path.into(), |path| self.loader.load_from_manifest(path))
+    }
+
+    /// Install the newest release of a plugin matching `req`, e.g. `^1.2`,
+    /// from the registry index, and load it.
+    ///
+    /// Fails with [`Error::OfflineMode`] while offline.
+    #[cfg(feature = "serde")]
+    pub fn install_from_index(&self, name: &str, req: &str) -> Result<PluginHandle> {
+        let index = self
+            .config
+            .registry_index
+            .as_ref()
+            .ok_or_else(|| Error::Registry("no registry index is configured".to_string()))?;
+        self.offline.ensure_online("plugin install")?;
+        let release = index.resolve(name, req)?;
+        let manifest_path = index.download(name, &release)?;
+        self.load_manifest(manifest_path)
+    }
+
+    /// Load a plugin on behalf of the loaded plugin `parent`, which must
+    /// declare the `plugins:load` capability.
+    ///
+    /// The child gets the intersection of the capabilities its manifest
+    /// declares and those `parent` holds; capabilities withheld are recorded
+    /// in the [`audit_log`](Self::audit_log).
+    #[cfg(feature = "serde")]
+    pub fn load_delegated(&self, parent: &str, path: impl Into<PathBuf>) -> Result<PluginHandle> {
+        let parent = self
+            .registry
+            .get(parent)
+            .ok_or_else(|| Error::plugin_not_found(parent))?;
+        let loading = |outcome: AuditOutcome, target: Option<String>| {
+            self.capability_audit.record(&AuditRecord::new(
+                parent.name(),
+                PLUGINS_LOAD,
+                target,
+                outcome,
+            ));
+        };
+        let path = path.into();
+        if !parent.inner().requires_capability(PLUGINS_LOAD) {
+            let error = Error::capability_not_granted(
+                PLUGINS_LOAD,
+                format!("plugin {} does not declare it", parent.name()),
+            );
+            loading(
+                AuditOutcome::Denied(error.to_string()),
+                Some(path.display().to_string()),
+            );
+            return Err(error);
+        }
+
+        let started = Instant::now();
+        let (plugin, withheld) =
+            self.loader
+                .load_delegated(path.clone(), &parent)
+                .map_err(|e| {
+                    e.in_plugin(
+                        name_for_path(&path),
+                        PluginOperation::Load,
+                        Some(path.clone()),
+                    )
+                })?;
+        self.register_loaded(&plugin, started)
+            .map_err(|e| e.in_plugin(plugin.name(), PluginOperation::Load, Some(path)))?;
+        for cap in withheld {
+            self.capability_audit.record(&AuditRecord::new(
+                plugin.name(),
+                cap,
+                None,
+                AuditOutcome::Denied(format!("not held by parent {}", parent.name())),
+            ));
+        }
+        loading(AuditOutcome::Succeeded, Some(plugin.name()));
         Ok(plugin)
     }
 
+    /// Explain why a manifest would fail to load, checking its dependencies
+    /// against the loaded plugins.
+    #[cfg(feature = "serde")]
+    pub fn explain(&self, path: impl AsRef<std::path::Path>) -> LoadExplanation {
+        self.loader
+            .explain_against(path.as_ref(), Some(&self.registry.info()))
+    }
+
     /// Load a plugin from source.
     pub fn load_source(&self, path: impl Into<PathBuf>) -> Result<PluginHandle> {
-        let plugin = self.loader.load_source(path.into())?;
-        self.registry.register(plugin.clone())?;
+        self.load_path(path.into(), |path| self.loader.load_source(path))
+    }
+
+    /// Register a plugin implemented natively in Rust.
+    ///
+    /// The plugin is called, supervised and measured like a scripted one.
+    pub fn register_builtin(
+        &self,
+        name: impl Into<String>,
+        builtin: BuiltinPlugin,
+    ) -> Result<PluginHandle> {
+        let started = Instant::now();
+        let plugin = self.loader.load_builtin(name, builtin)?;
+        self.register_loaded(&plugin, started)?;
         Ok(plugin)
     }
 
     /// Load a plugin from bytecode.
     pub fn load_bytecode(&self, path: impl Into<PathBuf>) -> Result<PluginHandle> {
-        let plugin = self.loader.load_bytecode_file(path.into())?;
-        self.registry.register(plugin.clone())?;
+        self.load_path(path.into(), |path| self.loader.load_bytecode_file(path))
+    }
+
+    /// Load a plugin from a file with `load` and register it, recording the
+    /// plugin and file with any error.
+    fn load_path(
+        &self,
+        path: PathBuf,
+        load: impl FnOnce(PathBuf) -> Result<PluginHandle>,
+    ) -> Result<PluginHandle> {
+        let started = Instant::now();
+        let plugin = load(path.clone()).map_err(|e| {
+            e.in_plugin(
+                name_for_path(&path),
+                PluginOperation::Load,
+                Some(path.clone()),
+            )
+        })?;
+        self.register_loaded(&plugin, started)
+            .map_err(|e| e.in_plugin(plugin.name(), PluginOperation::Load, Some(path)))?;
         Ok(plugin)
     }
 
     /// Unload a plugin by name.
     pub fn unload(&self, name: &str) -> Result<()> {
+        let jobs = self
+            .registry
+            .get(name)
+            .map(|plugin| plugin.inner().manifest().schedule)
+            .unwrap_or_default();
         self.registry.unregister(name)?;
+        for job in jobs {
+            self.tasks.stop(&job.lease_key(name));
+        }
+        self.recovery.supervisor.forget(name);
+        self.recovery.known_good.remove(name);
+        self.recovery.rate_limits.forget(name);
+        #[cfg(feature = "metrics-prometheus")]
+        if let Some(ref metrics) = self.config.metrics {
+            metrics.record_unload(name);
+        }
+        Ok(())
+    }
+
+    fn register_loaded(&self, plugin: &PluginHandle, started: Instant) -> Result<()> {
+        self.registry.register(plugin.clone())?;
+        plugin.inner().set_audit_sink(self.capability_audit.clone());
+        let gate: Weak<dyn CallGate> = Arc::downgrade(&self.recovery);
+        plugin.inner().set_call_gate(gate);
+        if let Some(ref policy) = self.config.autoscale {
+            if plugin.inner().manifest().stateless {
+                let name = plugin.name();
+                if let Err(e) = self.set_autoscale(&name, policy.clone()) {
+                    tracing::warn!("Plugin {} cannot be pooled: {}", name, e);
+                }
+            }
+        }
+        if self
+            .jobs_scheduled
+            .load(std::sync::atomic::Ordering::SeqCst)
+        {
+            if let Err(e) = self.spawn_jobs(plugin) {
+                tracing::warn!("Jobs of plugin {} were not scheduled: {}", plugin.name(), e);
+            }
+        }
+        self.recovery.prewarm_if_hot(plugin);
+        let elapsed = started.elapsed();
+        plugin.inner().set_load_duration(elapsed);
+        #[cfg(feature = "metrics-prometheus")]
+        if let Some(ref metrics) = self.config.metrics {
+            metrics.record_load(&plugin.name(), elapsed.as_secs_f64());
+        }
         Ok(())
     }
 
+    /// Get the metrics the runtime records to.
+    #[cfg(feature = "metrics-prometheus")]
+    pub fn metrics(&self) -> Option<&PluginMetrics> {
+        self.config.metrics.as_deref()
+    }
+
     /// Get a plugin by name.
     pub fn get(&self, name: &str) -> Option<PluginHandle> {
         self.registry.get(name)
@@ -172,6 +1039,104 @@ impl PluginRuntime {
         self.registry.all()
     }
 
+    /// Visit every loaded plugin without collecting them; see
+    /// [`PluginRegistry::for_each`].
+    pub fn for_each_plugin(&self, f: impl FnMut(&PluginHandle)) {
+        self.registry.for_each(f)
+    }
+
+    /// Get the host functions plugins may require.
+    ///
+    /// The table starts out with the
+    /// [`STORAGE_HOST_FUNCTIONS`](crate::STORAGE_HOST_FUNCTIONS) and the
+    /// [`HOST_INFO_FUNCTIONS`](crate::HOST_INFO_FUNCTIONS).
+    pub fn host_functions(&self) -> &HostFunctionTable {
+        &self.host_functions
+    }
+
+    /// Register a host function and re-check the plugins requiring it.
+    ///
+    /// Returns the plugins whose set of missing host functions changed.
+    pub fn register_host_function(&self, name: impl Into<String>) -> Vec<String> {
+        let name = name.into();
+        if !self.host_functions.register(name.clone()) {
+            return Vec::new();
+        }
+        self.revalidate_host_functions(&name)
+    }
+
+    /// Register a host function with its implementation and re-check the
+    /// plugins requiring it.
+    ///
+    /// Loaded plugins requiring the function are reloaded on fresh engines,
+    /// so those binding host functions at creation, such as wasm plugins,
+    /// call this implementation from then on. Returns the plugins whose set
+    /// of missing host functions changed.
+    pub fn register_host_fn<F>(&self, name: impl Into<String>, function: F) -> Vec<String>
+    where
+        F: Fn(&[fusabi_host::Value]) -> Result<fusabi_host::Value> + Send + Sync + 'static,
+    {
+        let name = name.into();
+        self.host_functions.register_fn(name.clone(), function);
+        let changed = self.revalidate_host_functions(&name);
+        for plugin in self.registry.all() {
+            if !plugin
+                .inner()
+                .required_host_functions()
+                .iter()
+                .any(|f| *f == name)
+            {
+                continue;
+            }
+            if let Err(e) = plugin.inner().reload() {
+                tracing::warn!(
+                    "Plugin {} keeps its previous host function {}: {}",
+                    plugin.name(),
+                    name,
+                    e
+                );
+            }
+        }
+        changed
+    }
+
+    /// Remove a host function and re-check the plugins requiring it.
+    ///
+    /// Calls into affected plugins fail with [`Error::MissingHostFunction`]
+    /// until the function is registered again. Returns the plugins whose set
+    /// of missing host functions changed.
+    pub fn unregister_host_function(&self, name: &str) -> Vec<String> {
+        if !self.host_functions.unregister(name) {
+            return Vec::new();
+        }
+        self.revalidate_host_functions(name)
+    }
+
+    // Engines keep the functions bound when they were created, so plugins
+    // losing one are gated rather than reloaded
+    fn revalidate_host_functions(&self, function: &str) -> Vec<String> {
+        let mut changed = Vec::new();
+        self.registry.for_each(|plugin| {
+            let required = plugin.inner().required_host_functions();
+            if !required.iter().any(|f| f == function) {
+                return;
+            }
+            let missing = self.host_functions.missing(&required);
+            if plugin.inner().set_missing_host_functions(missing.clone()) {
+                changed.push((plugin.name(), missing));
+            }
+        });
+
+        let hooks = self.hooks.read();
+        changed
+            .into_iter()
+            .map(|(name, missing)| {
+                hooks.emit_host_functions_changed(&name, missing);
+                name
+            })
+            .collect()
+    }
+
     /// Get running plugins.
     pub fn running(&self) -> Vec<PluginHandle> {
         self.registry.running()
@@ -194,7 +1159,12 @@ impl PluginRuntime {
             .get(name)
             .ok_or_else(|| Error::plugin_not_found(name))?;
 
-        plugin.inner().start()?;
+        let guards = self.hooks.read().guards(Transition::Start);
+        guards.check(name)?;
+        plugin
+            .inner()
+            .start()
+            .map_err(|e| e.in_plugin(name, PluginOperation::Start, plugin_file(&plugin)))?;
         self.hooks.read().emit_started(name);
 
         Ok(())
@@ -207,175 +1177,3450 @@ impl PluginRuntime {
             .get(name)
             .ok_or_else(|| Error::plugin_not_found(name))?;
 
-        plugin.inner().stop()?;
+        let guards = self.hooks.read().guards(Transition::Stop);
+        guards.check(name)?;
+        plugin
+            .inner()
+            .stop()
+            .map_err(|e| e.in_plugin(name, PluginOperation::Stop, plugin_file(&plugin)))?;
         self.hooks.read().emit_stopped(name);
 
         Ok(())
     }
 
     /// Reload a plugin.
+    ///
+    /// Plugins loaded from files are re-read from disk; others are restarted
+    /// in place.
     pub fn reload(&self, name: &str) -> Result<()> {
-        self.registry.reload(name)
+        self.recovery.reload(name).map_err(|e| {
+            let file = self.registry.get(name).and_then(|p| plugin_file(&p));
+            e.in_plugin(name, PluginOperation::Reload, file)
+        })
+    }
+
+    /// Swap a plugin back to the last known-good version installed before
+    /// its current one, returning that version.
+    ///
+    /// Versions are read from the loader's artifact store; a version is
+    /// known-good once it has served a call. The rollback is refused with
+    /// [`Error::RollbackFailed`] if the older version's `state-version`
+    /// metadata is below the current one's, since it may not read state
+    /// written since, or if a loaded plugin requires a newer version.
+    #[cfg(feature = "serde")]
+    pub fn rollback(&self, name: &str) -> Result<String> {
+        self.recovery.rollback(name)
     }
 
-    /// Start all plugins.
+    /// Start all plugins in dependency order, higher priorities first.
     pub fn start_all(&self) -> Vec<Result<()>> {
-        self.registry.start_all()
+        let guards = self.hooks.read().guards(Transition::Start);
+        self.registry.start_all_with(|name| guards.check(name))
     }
 
-    /// Stop all plugins.
+    /// Stop all plugins in the reverse of the start order.
     pub fn stop_all(&self) -> Vec<Result<()>> {
-        self.registry.stop_all()
+        let guards = self.hooks.read().guards(Transition::Stop);
+        self.registry.stop_all_with(|name| guards.check(name))
     }
 
     /// Reload all plugins.
     pub fn reload_all(&self) -> Vec<Result<()>> {
-        self.registry.reload_all()
+        self.reload_all_cancellable(&CancellationToken::new())
     }
 
-    /// Discover and load plugins from configured directories.
-    #[cfg(feature = "serde")]
-    pub fn discover(&self) -> Result<Vec<PluginHandle>> {
-        let mut loaded = Vec::new();
+    /// Reload all plugins until `token` is cancelled.
+    ///
+    /// Plugins not reached before cancellation get `Err(Error::Cancelled)`.
+    pub fn reload_all_cancellable(&self, token: &CancellationToken) -> Vec<Result<()>> {
+        self.registry
+            .names()
+            .iter()
+            .map(|name| {
+                token.check("reload_all")?;
+                self.reload(name)
+            })
+            .collect()
+    }
 
-        for dir in &self.config.plugin_dirs {
-            if !dir.exists() {
-                tracing::warn!("Plugin directory does not exist: {}", dir.display());
-                continue;
-            }
+    /// Apply many operations, continuing past failures.
+    ///
+    /// Operations run with the host's authority; requests from admin
+    /// clients go through [`AdminApi::bulk_with`](crate::AdminApi::bulk_with)
+    /// instead, which authorizes, rate-limits and audits each operation.
+    pub fn bulk(&self, ops: impl IntoIterator<Item = BulkOp>) -> BulkReport {
+        self.bulk_with(ops, BulkPolicy::default(), |_| {})
+    }
 
-            for pattern in &self.config.plugin_patterns {
-                let glob_pattern = dir.join(pattern);
-                let glob_str = glob_pattern.to_string_lossy();
-
-                if let Ok(entries) = glob::glob(&glob_str) {
-                    for entry in entries.flatten() {
-                        match self.load_manifest(&entry) {
-                            Ok(plugin) => {
-                                tracing::info!(
-                                    "Loaded plugin {} from {}",
-                                    plugin.name(),
-                                    entry.display()
-                                );
-                                loaded.push(plugin);
-                            }
-                            Err(e) => {
-                                tracing::error!(
-                                    "Failed to load plugin from {}: {}",
-                                    entry.display(),
-                                    e
-                                );
-                            }
-                        }
-                    }
-                }
+    /// Apply many operations in order, reporting progress after each one.
+    ///
+    /// Operations run one at a time in the order given, so dependencies
+    /// should be loaded before their dependents. The report lists every
+    /// operation, including those skipped under [`BulkPolicy::StopOnError`].
+    pub fn bulk_with<F>(
+        &self,
+        ops: impl IntoIterator<Item = BulkOp>,
+        policy: BulkPolicy,
+        on_progress: F,
+    ) -> BulkReport
+    where
+        F: FnMut(&BulkProgress<'_>),
+    {
+        self.bulk_cancellable(ops, policy, &CancellationToken::new(), on_progress)
+    }
+
+    /// Apply many operations until `token` is cancelled.
+    ///
+    /// Operations not started before cancellation are reported as
+    /// [`BulkOutcome::Cancelled`](crate::BulkOutcome::Cancelled).
+    pub fn bulk_cancellable<F>(
+        &self,
+        ops: impl IntoIterator<Item = BulkOp>,
+        policy: BulkPolicy,
+        token: &CancellationToken,
+        mut on_progress: F,
+    ) -> BulkReport
+    where
+        F: FnMut(&BulkProgress<'_>),
+    {
+        crate::bulk::run(ops, policy, token, on_progress, |op| self.apply(op))
+    }
+
+    fn apply(&self, op: &BulkOp) -> Result<()> {
+        match op {
+            BulkOp::Load(path) => RuntimeApi::load(self, path).map(|_| ()),
+            BulkOp::Unload(name) => self.unload(name),
+            BulkOp::Start(name) => self.start(name),
+            BulkOp::Stop(name) => self.stop(name),
+            BulkOp::Reload(name) => self.reload(name),
+        }
+    }
+
+    /// Discover and load plugins from configured directories.
+    #[cfg(feature = "serde")]
+    pub fn discover(&self) -> Result<Vec<PluginHandle>> {
+        self.discover_cancellable(&CancellationToken::new())
+            .map(Partial::into_inner)
+    }
+
+    /// Discover and load plugins until `token` is cancelled.
+    ///
+    /// Plugins loaded before cancellation stay loaded and are returned.
+    #[cfg(feature = "serde")]
+    pub fn discover_cancellable(
+        &self,
+        token: &CancellationToken,
+    ) -> Result<Partial<Vec<PluginHandle>>> {
+        let report = self.discover_with_report(token);
+        Ok(Partial {
+            value: report.value.loaded,
+            cancelled: report.cancelled,
+        })
+    }
+
+    /// Discover and load plugins, reporting name collisions and failures.
+    ///
+    /// Manifests declaring the same plugin name are resolved with the
+    /// configured [`CollisionStrategy`].
+    #[cfg(feature = "serde")]
+    pub fn discover_with_report(&self, token: &CancellationToken) -> Partial<DiscoveryReport> {
+        let mut report = DiscoveryReport::default();
+
+        let mut candidates = Vec::new();
+        for path in discovery::scan(&self.config.plugin_dirs, &self.config.plugin_patterns) {
+            match Manifest::from_file(&path) {
+                Ok(manifest) => candidates.push(Candidate { path, manifest }),
+                Err(e) => {
+                    tracing::error!("Failed to read manifest {}: {}", path.display(), e);
+                    report.failed.push((path, e));
+                }
+            }
+        }
+
+        let (mut chosen, collisions) =
+            discovery::resolve(self.config.collision_strategy, candidates);
+        report.collisions = collisions;
+        // Load, and with auto-start start, dependencies before their
+        // dependents and otherwise higher priorities first
+        let graph = DependencyGraph::from_manifests(chosen.iter().map(|c| &c.manifest));
+        match graph.resolve() {
+            Ok(order) => chosen.sort_by_key(|c| order.iter().position(|n| *n == c.manifest.name)),
+            Err(e) => {
+                tracing::warn!("Cannot order discovered plugins by dependencies: {}", e);
+                chosen.sort_by_key(|c| std::cmp::Reverse(c.manifest.priority));
+            }
+        }
+
+        for candidate in chosen {
+            if token.is_cancelled() {
+                tracing::info!("Plugin discovery cancelled");
+                return Partial::cancelled(report);
+            }
+            let started = Instant::now();
+            let path = candidate.path;
+            let loaded = self
+                .loader
+                .load_manifest(candidate.manifest, Some(path.clone()))
+                .and_then(|plugin| self.register_loaded(&plugin, started).map(|_| plugin));
+            match loaded {
+                Ok(plugin) => {
+                    tracing::info!("Loaded plugin {} from {}", plugin.name(), path.display());
+                    report.loaded.push(plugin);
+                }
+                Err(e) => {
+                    tracing::error!("Failed to load plugin from {}: {}", path.display(), e);
+                    report.failed.push((path, e));
+                }
             }
         }
 
-        Ok(loaded)
+        self.record_discovery_warnings(&report);
+        Partial::complete(report)
+    }
+
+    #[cfg(feature = "serde")]
+    fn record_discovery_warnings(&self, report: &DiscoveryReport) {
+        let mut warnings: Vec<String> = report
+            .collisions
+            .iter()
+            .map(|c| format!("{} manifests declare plugin {}", c.candidates.len(), c.name))
+            .collect();
+        warnings.extend(
+            report
+                .failed
+                .iter()
+                .map(|(path, e)| format!("failed to load {}: {}", path.display(), e)),
+        );
+        *self.discovery_warnings.lock() = warnings;
+    }
+
+    /// Summarize the loaded plugins, e.g. to log once at boot.
+    ///
+    /// Warnings include the failures and name collisions of the most recent
+    /// discovery.
+    pub fn startup_report(&self) -> StartupReport {
+        let mut report = StartupReport::new(self.host_info());
+        report.warnings = self.discovery_warnings.lock().clone();
+        self.registry.for_each(|plugin| {
+            let plugin = plugin.inner();
+            report.add_plugin(
+                &plugin.info(),
+                &plugin.manifest(),
+                &plugin.missing_host_functions(),
+            );
+        });
+        report.finish()
     }
 
     /// Call a function on a plugin.
+    ///
+    /// With a call authorizer configured the call is checked as made by
+    /// [`Principal::Host`]. Failed calls are retried as configured with
+    /// [`RuntimeConfig::with_call_options`].
     pub fn call(
         &self,
         plugin_name: &str,
         function: &str,
         args: &[fusabi_host::Value],
+    ) -> Result<fusabi_host::Value> {
+        self.config
+            .call_options
+            .run(|| self.call_once(&Principal::Host, plugin_name, function, args))
+    }
+
+    /// Call a function on a plugin with `options` in place of the
+    /// configured call options.
+    pub fn call_with_options(
+        &self,
+        plugin_name: &str,
+        function: &str,
+        args: &[fusabi_host::Value],
+        options: &CallOptions,
+    ) -> Result<fusabi_host::Value> {
+        options.run(|| self.call_once(&Principal::Host, plugin_name, function, args))
+    }
+
+    /// Call a function on a plugin on behalf of `principal`.
+    ///
+    /// The principal is always [`Principal::Named`], so no name makes the
+    /// call pass as the host's. Fails with [`Error::CallDenied`] if the call
+    /// authorizer refuses it.
+    pub fn call_as(
+        &self,
+        principal: &str,
+        plugin_name: &str,
+        function: &str,
+        args: &[fusabi_host::Value],
+    ) -> Result<fusabi_host::Value> {
+        let principal = Principal::named(principal);
+        self.config
+            .call_options
+            .run(|| self.call_once(&principal, plugin_name, function, args))
+    }
+
+    fn call_once(
+        &self,
+        principal: &Principal,
+        plugin_name: &str,
+        function: &str,
+        args: &[fusabi_host::Value],
+    ) -> Result<fusabi_host::Value> {
+        let plugin = self
+            .registry
+            .get(plugin_name)
+            .ok_or_else(|| Error::plugin_not_found(plugin_name))?
+            .with_principal(principal.clone());
+        self.recovery
+            .guard(&plugin, function, GateArgs::Plain(args), &mut || {
+                plugin.call_unguarded(function, args)
+            })
+    }
+
+    /// Call a plugin function with sensitive arguments, as the host.
+    ///
+    /// Sensitive arguments are redacted from audit records and error
+    /// messages, never cached, and the copies handed to the engine are
+    /// zeroed once the call returns.
+    pub fn call_sensitive(
+        &self,
+        plugin_name: &str,
+        function: &str,
+        args: &[CallArg],
     ) -> Result<fusabi_host::Value> {
         let plugin = self
             .registry
             .get(plugin_name)
             .ok_or_else(|| Error::plugin_not_found(plugin_name))?;
+        self.recovery
+            .guard(&plugin, function, GateArgs::Sensitive(args), &mut || {
+                plugin.call_sensitive_unguarded(function, args)
+            })
+    }
 
-        plugin.call(function, args)
+    /// Queue a call to run on the call dispatcher's worker pool.
+    ///
+    /// The returned receipt can be waited on or awaited. Calls beyond the
+    /// plugin's queue capacity are rejected with [`Error::QueueFull`]. With
+    /// a call authorizer configured the call is checked as made by
+    /// [`Principal::Host`].
+    pub fn call_enqueued(
+        &self,
+        plugin_name: &str,
+        function: &str,
+        args: Vec<fusabi_host::Value>,
+    ) -> Result<CallReceipt> {
+        self.enqueue(Principal::Host, plugin_name, function, args)
     }
 
-    /// Broadcast a function call to all running plugins.
-    pub fn broadcast(
+    /// Queue a call on behalf of `principal`, like
+    /// [`call_enqueued`](Self::call_enqueued).
+    ///
+    /// The principal is checked when the call is queued and again when a
+    /// worker runs it, and is recorded in its audit record.
+    pub fn call_enqueued_as(
         &self,
+        principal: &str,
+        plugin_name: &str,
         function: &str,
-        args: &[fusabi_host::Value],
-    ) -> Vec<(String, Result<fusabi_host::Value>)> {
-        self.registry
-            .running()
+        args: Vec<fusabi_host::Value>,
+    ) -> Result<CallReceipt> {
+        self.enqueue(Principal::named(principal), plugin_name, function, args)
+    }
+
+    fn enqueue(
+        &self,
+        principal: Principal,
+        plugin_name: &str,
+        function: &str,
+        args: Vec<fusabi_host::Value>,
+    ) -> Result<CallReceipt> {
+        // Refuse before queueing; the worker checks again through the guard
+        self.recovery.authorize(&principal, plugin_name, function)?;
+
+        let plugin = self
+            .registry
+            .get(plugin_name)
+            .ok_or_else(|| Error::plugin_not_found(plugin_name))?
+            .with_principal(principal);
+
+        let recovery = self.recovery.clone();
+        let function = function.to_string();
+
+        self.dispatcher()?.submit(plugin_name, move || {
+            recovery.guard(&plugin, &function, GateArgs::Plain(&args), &mut || {
+                plugin.call_unguarded(&function, &args)
+            })
+        })
+    }
+
+    /// Get the call dispatcher, starting its workers on first use.
+    pub fn dispatcher(&self) -> Result<&CallDispatcher> {
+        if let Some(dispatcher) = self.dispatcher.get() {
+            return Ok(dispatcher);
+        }
+        let dispatcher = CallDispatcher::new(self.config.dispatcher.clone())?;
+        // A racing caller may have started one first; ours then shuts down
+        let _ = self.dispatcher.set(dispatcher);
+        Ok(self.dispatcher.get().expect("dispatcher was just set"))
+    }
+
+    /// Activate a plugin for a tenant, resolving the tenant's capability
+    /// restrictions.
+    ///
+    /// Withheld capabilities are recorded in the [`audit_log`](Self::audit_log).
+    pub fn activate(&self, tenant: &str, plugin_name: &str) -> Result<TenantActivation> {
+        let plugin = self
+            .registry
+            .get(plugin_name)
+            .ok_or_else(|| Error::plugin_not_found(plugin_name))?;
+        Ok(TenantActivation::new(
+            tenant,
+            plugin,
+            self.config.tenant_policies.get(tenant),
+            Some(self.capability_audit.clone()),
+        ))
+    }
+
+    /// Get the number of calls per plugin and export since they were loaded.
+    pub fn call_profile(&self) -> CallProfile {
+        call_profile(&self.registry)
+    }
+
+    /// Warm the `top` most-called loaded plugins of `profile`.
+    ///
+    /// Pass a profile saved from a previous run after startup, or
+    /// [`call_profile`](Self::call_profile) after a deployment.
+    pub fn prewarm(&self, profile: &CallProfile, top: usize) -> Vec<(String, Result<()>)> {
+        profile
+            .hottest(top)
             .into_iter()
-            .filter(|p| p.has_export(function))
-            .map(|p| {
-                let name = p.name();
-                let result = p.call(function, args);
-                (name, result)
+            .filter_map(|name| {
+                let plugin = self.registry.get(&name)?;
+                let result = plugin.inner().warm();
+                Some((name, result))
             })
             .collect()
     }
 
-    /// Clean up unloaded plugins.
-    pub fn cleanup(&self) -> usize {
-        self.registry.cleanup()
+    /// Get the restart supervisor.
+    pub fn supervisor(&self) -> &Supervisor {
+        &self.recovery.supervisor
     }
 
-    /// Shutdown the runtime.
-    pub fn shutdown(&self) {
-        // Stop all running plugins
-        let _ = self.stop_all();
+    /// Set the restart policy of a plugin.
+    pub fn set_restart_policy(&self, name: impl Into<String>, policy: RestartPolicy) {
+        self.recovery.supervisor.set_policy(name, policy);
+    }
 
-        // Unload all
-        self.registry.unload_all();
+    /// Get the execution budget tracker.
+    pub fn budgets(&self) -> &BudgetTracker {
+        &self.recovery.budgets
     }
-}
 
-impl std::fmt::Debug for PluginRuntime {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("PluginRuntime")
-            .field("config", &self.config)
-            .field("plugin_count", &self.registry.len())
-            .finish()
+    /// Set the execution budget of a plugin.
+    pub fn set_budget(&self, name: impl Into<String>, budget: ExecutionBudget) {
+        self.recovery.budgets.set_budget(name, budget);
     }
-}
 
-impl Drop for PluginRuntime {
-    fn drop(&mut self) {
-        self.shutdown();
+    /// Get a plugin's use of its execution budget.
+    pub fn budget_usage(&self, name: &str) -> BudgetUsage {
+        self.recovery.budgets.usage(name)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Serve a stateless plugin from an engine pool sized by `policy`.
+    ///
+    /// Emits `PoolScaled` whenever engines are added or removed.
+    pub fn set_autoscale(&self, name: &str, policy: AutoscalePolicy) -> Result<()> {
+        let plugin = self
+            .registry
+            .get(name)
+            .ok_or_else(|| Error::plugin_not_found(name))?;
+        let hooks = self.hooks.clone();
+        let plugin_name = name.to_string();
+        plugin.inner().set_autoscale_with(
+            policy,
+            Some(Arc::new(move |signals: PoolSignals| {
+                hooks.read().emit_pool_scaled(&plugin_name, signals)
+            })),
+        )
+    }
 
-    #[test]
-    fn test_runtime_creation() {
-        let runtime = PluginRuntime::default_config().unwrap();
-        assert_eq!(runtime.plugin_count(), 0);
+    /// Get the load of a plugin's engine pool, if it is pooled.
+    pub fn pool_signals(&self, name: &str) -> Option<PoolSignals> {
+        self.registry.get(name)?.inner().pool_signals()
     }
 
-    #[test]
-    fn test_runtime_config_builder() {
-        let config = RuntimeConfig::new()
-            .with_plugin_dir("/plugins")
-            .with_auto_discover(true);
+    /// Remove pooled engines that have been idle too long, returning how
+    /// many were removed.
+    ///
+    /// Pools also shrink as calls finish; this catches pools that receive
+    /// no calls. See also [`spawn_autoscaling`](Self::spawn_autoscaling).
+    pub fn scale_pools(&self) -> usize {
+        let mut removed = 0;
+        self.registry
+            .for_each(|plugin| removed += plugin.inner().trim_pool());
+        removed
+    }
 
-        assert_eq!(config.plugin_dirs.len(), 1);
-        assert!(config.auto_discover);
+    /// Shrink idle engine pools from the `autoscaling` background task.
+    pub fn spawn_autoscaling(&self, interval: Duration) -> Result<()> {
+        let registry = self.registry.clone();
+        self.tasks
+            .spawn_periodic(AUTOSCALE_TASK, interval, move || {
+                registry.for_each(|plugin| {
+                    plugin.inner().trim_pool();
+                });
+                Ok(())
+            })
     }
 
-    #[test]
-    fn test_runtime_stats() {
-        let runtime = PluginRuntime::default_config().unwrap();
-        let stats = runtime.stats();
+    /// Restart every supervised plugin whose restart is due.
+    ///
+    /// Restarts run on the `restarts` background task once due, off the
+    /// thread of the failed call; this runs the due ones right away. See
+    /// also [`spawn_supervision`](Self::spawn_supervision).
+    pub fn supervise(&self) -> Vec<(String, Result<()>)> {
+        self.recovery.supervise()
+    }
 
-        assert_eq!(stats.total, 0);
-        assert_eq!(stats.running, 0);
+    /// Get the background task supervisor, shared with the runtime's update
+    /// checkers and hot reload watcher.
+    pub fn tasks(&self) -> &Arc<TaskSupervisor> {
+        &self.tasks
     }
-}
 
-// glob is an optional dependency for discovery
-#[cfg(feature = "serde")]
-mod glob {
-    pub fn glob(
-        _pattern: &str,
-    ) -> std::io::Result<impl Iterator<Item = std::io::Result<std::path::PathBuf>>> {
-        // Simplified glob implementation for testing
-        // In production, would use the actual glob crate
-        Ok(std::iter::empty())
+    /// Get the health of every background task.
+    pub fn task_health(&self) -> Vec<TaskHealth> {
+        self.tasks.health()
+    }
+
+    /// Drive delayed plugin restarts from the `supervision` background task.
+    pub fn spawn_supervision(&self, interval: Duration) -> Result<()> {
+        let recovery = self.recovery.clone();
+        self.tasks
+            .spawn_periodic(SUPERVISION_TASK, interval, move || {
+                for (name, result) in recovery.supervise() {
+                    result.map_err(|e| Error::ReloadFailed(format!("{}: {}", name, e)))?;
+                }
+                Ok(())
+            })
+    }
+
+    /// Check the health of a plugin.
+    ///
+    /// A running plugin that fails the check is moved to the error state,
+    /// with `Unhealthy` and `Error` lifecycle events, and restarted if it is
+    /// supervised.
+    pub fn health_check(&self, name: &str) -> Result<PluginHealth> {
+        let plugin = self
+            .registry
+            .get(name)
+            .ok_or_else(|| Error::plugin_not_found(name))?;
+        Ok(self.recovery.check_health(&plugin))
+    }
+
+    /// Check the health of every running plugin, sorted by name.
+    pub fn health_check_all(&self) -> Vec<PluginHealth> {
+        self.recovery.check_all_health()
+    }
+
+    /// Check the health of every running plugin from the `health-checks`
+    /// background task.
+    pub fn spawn_health_checks(&self, interval: Duration) -> Result<()> {
+        let recovery = self.recovery.clone();
+        self.tasks.spawn_periodic(HEALTH_TASK, interval, move || {
+            recovery.check_all_health();
+            Ok(())
+        })
+    }
+
+    /// Poll for plugin updates from the `update-checks` background task.
+    ///
+    /// Polls are skipped while offline mode is enabled.
+    pub fn spawn_update_checks(&self, checker: Arc<UpdateChecker>) -> Result<()> {
+        let interval = checker.config().interval;
+        self.tasks
+            .spawn_periodic(UPDATE_TASK, interval, move || match checker.check_now() {
+                Err(e) if !matches!(e.root(), Error::OfflineMode(_)) => Err(e),
+                _ => Ok(()),
+            })
+    }
+
+    #[cfg(feature = "serde")]
+    fn lockfile_path(&self) -> Result<&std::path::Path> {
+        self.config
+            .lockfile
+            .as_deref()
+            .ok_or_else(|| Error::Registry("no lockfile configured".to_string()))
+    }
+
+    /// Pin the loaded plugins and the hashes of their files to the lockfile.
+    #[cfg(feature = "serde")]
+    pub fn lock(&self) -> Result<Lockfile> {
+        let path = self.lockfile_path()?;
+        let lockfile = Lockfile::capture(&self.registry.info())?;
+        lockfile.save(path)?;
+        Ok(lockfile)
+    }
+
+    /// Check the lockfile against the files on disk, the loaded plugins
+    /// and the artifact store.
+    ///
+    /// Reports modified or missing files, locked plugins that are not
+    /// loaded, loaded plugins that are not locked, version skew and pinned
+    /// versions missing from the artifact store.
+    #[cfg(feature = "serde")]
+    pub fn verify(&self) -> Result<VerifyReport> {
+        let lockfile = Lockfile::load(self.lockfile_path()?)?;
+        let mut report = lockfile.verify(&self.registry.info());
+        if let Some(ref store) = self.loader.config().artifact_store {
+            report.drift.extend(
+                lockfile
+                    .verify_installed(Some(store))
+                    .drift
+                    .into_iter()
+                    .filter(|drift| matches!(drift, Drift::NotStored { .. })),
+            );
+        }
+        Ok(report)
+    }
+
+    /// Get the cluster backend, if cluster mode is enabled.
+    pub fn cluster(&self) -> Option<&dyn ClusterBackend> {
+        self.config.cluster.as_deref()
+    }
+
+    fn cluster_backend(&self) -> Result<&dyn ClusterBackend> {
+        self.cluster()
+            .ok_or_else(|| Error::Registry("cluster mode is not enabled".to_string()))
+    }
+
+    /// Load a plugin and publish it to the cluster so other hosts load it too.
+    ///
+    /// The manifest path must be reachable from every host.
+    #[cfg(feature = "serde")]
+    pub fn install(&self, manifest_path: impl Into<PathBuf>) -> Result<PluginHandle> {
+        let backend = self.cluster_backend()?;
+        let plugin = self.load_manifest(manifest_path)?;
+        let info = plugin.info();
+        let path = info.manifest_path.unwrap_or_default();
+        let path = std::fs::canonicalize(&path).unwrap_or(path);
+
+        if let Err(e) = backend.publish(DesiredPlugin::new(info.name, info.version, path)) {
+            let _ = self.unload(&plugin.name());
+            return Err(e);
+        }
+        self.cluster_managed.lock().insert(plugin.name());
+        Ok(plugin)
+    }
+
+    /// Remove a plugin from the cluster and unload it locally.
+    ///
+    /// Other hosts unload it on their next [`converge`](Self::converge).
+    pub fn uninstall(&self, name: &str) -> Result<()> {
+        self.cluster_backend()?.retract(name)?;
+        self.cluster_managed.lock().remove(name);
+        if self.registry.contains(name) {
+            self.unload(name)?;
+        }
+        Ok(())
+    }
+
+    /// Bring the loaded plugins in line with the cluster's desired set.
+    ///
+    /// Missing plugins are loaded, plugins at another version are upgraded
+    /// in place from the desired manifest, the new version being loaded
+    /// before it replaces the old one, and plugins this runtime loaded for
+    /// the cluster are unloaded once retracted. Plugins loaded outside the
+    /// cluster are left alone unless the cluster wants another version.
+    /// Call this periodically, or let
+    /// [`spawn_convergence`](Self::spawn_convergence) do it.
+    #[cfg(feature = "serde")]
+    pub fn converge(&self) -> Result<ConvergeReport> {
+        let desired = self.cluster_backend()?.desired()?;
+        let mut report = ConvergeReport::default();
+
+        for target in &desired {
+            let name = target.name.clone();
+            let (upgrade, result) = match self.registry.get(&name) {
+                Some(plugin) if plugin.version() == target.version => {
+                    self.cluster_managed.lock().insert(name);
+                    continue;
+                }
+                Some(plugin) => (true, self.swap_in(&plugin, target)),
+                None => (
+                    false,
+                    self.load_manifest(target.manifest_path.clone()).map(|_| ()),
+                ),
+            };
+            match result {
+                Ok(()) => {
+                    self.cluster_managed.lock().insert(name.clone());
+                    if upgrade {
+                        report.upgraded.push(name);
+                    } else {
+                        report.loaded.push(name);
+                    }
+                }
+                Err(e) => report.failed.push((name, e)),
+            }
+        }
+
+        let retracted: Vec<String> = self
+            .cluster_managed
+            .lock()
+            .iter()
+            .filter(|name| !desired.iter().any(|d| &d.name == *name))
+            .cloned()
+            .collect();
+        for name in retracted {
+            self.cluster_managed.lock().remove(&name);
+            if !self.registry.contains(&name) {
+                continue;
+            }
+            match self.unload(&name) {
+                Ok(()) => report.unloaded.push(name),
+                Err(e) => report.failed.push((name, e)),
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Load the desired version of a plugin next to the running one and swap
+    /// it in, so the plugin keeps serving calls while it is upgraded.
+    #[cfg(feature = "serde")]
+    fn swap_in(&self, plugin: &PluginHandle, target: &DesiredPlugin) -> Result<()> {
+        let name = plugin.name();
+        let guards = self.hooks.read().guards(Transition::Reload);
+        guards.check(&name)?;
+        let previous = plugin.info();
+        plugin
+            .inner()
+            .set_paths(Some(target.manifest_path.clone()), None);
+        if let Err(e) = self.loader.reload_from_disk(plugin) {
+            plugin
+                .inner()
+                .set_paths(previous.manifest_path, previous.entry_path);
+            return Err(e);
+        }
+        self.hooks
+            .read()
+            .emit_reloaded(&name, plugin.info().reload_count);
+        Ok(())
+    }
+
+    /// Converge on the cluster's desired set from the `cluster-convergence`
+    /// background task, so hosts pick up installs, upgrades and uninstalls
+    /// without a host timer.
+    ///
+    /// The task holds a weak reference and stops doing anything once the
+    /// runtime is dropped.
+    #[cfg(feature = "serde")]
+    pub fn spawn_convergence(self: &Arc<Self>, interval: Duration) -> Result<()> {
+        self.cluster_backend()?;
+        let runtime = Arc::downgrade(self);
+        self.tasks.spawn_periodic(CONVERGE_TASK, interval, move || {
+            let Some(runtime) = runtime.upgrade() else {
+                return Ok(());
+            };
+            let report = runtime.converge()?;
+            match report.failed.first() {
+                Some((name, e)) => Err(Error::Registry(format!(
+                    "{} plugins did not converge, first {}: {}",
+                    report.failed.len(),
+                    name,
+                    e
+                ))),
+                None => Ok(()),
+            }
+        })
+    }
+
+    /// Spawn a background task for every job scheduled by loaded plugins.
+    ///
+    /// Tasks are named `job:<plugin>:<export>` and replace earlier tasks of
+    /// the same name. Plugins loaded afterwards get their tasks when they
+    /// are loaded, and a plugin's tasks stop when it is unloaded. Singleton
+    /// jobs only run
+    /// on the instance holding their lease in the cluster backend, and run
+    /// everywhere when cluster mode is disabled.
+    pub fn spawn_scheduled_jobs(&self) -> Result<()> {
+        self.jobs_scheduled
+            .store(true, std::sync::atomic::Ordering::SeqCst);
+        for plugin in self.registry.all() {
+            self.spawn_jobs(&plugin)?;
+        }
+        Ok(())
+    }
+
+    fn spawn_jobs(&self, plugin: &PluginHandle) -> Result<()> {
+        let name = plugin.name();
+        for job in plugin.inner().manifest().schedule {
+            let registry = self.registry.clone();
+            let cluster = self.config.cluster.clone();
+            let instance = self.config.instance_id.clone();
+            let plugin_name = name.clone();
+            self.tasks
+                .spawn_periodic(job.lease_key(&name), job.interval(), move || {
+                    run_scheduled_job(&registry, cluster.as_deref(), &instance, &plugin_name, &job)
+                        .map(|_| ())
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Get the command-line subcommands declared by loaded plugins.
+    ///
+    /// Returns `(plugin, command)` pairs sorted by plugin and command name.
+    pub fn commands(&self) -> Vec<(String, CliCommand)> {
+        let mut commands: Vec<(String, CliCommand)> = self
+            .registry
+            .all()
+            .into_iter()
+            .flat_map(|p| {
+                let name = p.name();
+                p.inner()
+                    .manifest()
+                    .cli
+                    .commands
+                    .into_iter()
+                    .map(move |c| (name.clone(), c))
+            })
+            .collect();
+        commands.sort_by(|a, b| (&a.0, &a.1.name).cmp(&(&b.0, &b.1.name)));
+        commands
+    }
+
+    /// Run a plugin-provided subcommand with raw command-line arguments.
+    ///
+    /// The command may be qualified as `plugin:command` when several
+    /// plugins declare the same name. Arguments are parsed according to the
+    /// command's declaration and passed to its export.
+    pub fn run_command(&self, command: &str, args: &[String]) -> Result<fusabi_host::Value> {
+        let (plugin_filter, name) = match command.split_once(':') {
+            Some((plugin, name)) => (Some(plugin), name),
+            None => (None, command),
+        };
+
+        let mut matches = self
+            .commands()
+            .into_iter()
+            .filter(|(plugin, c)| c.name == name && plugin_filter.map_or(true, |p| p == plugin));
+
+        let (plugin, cmd) = matches
+            .next()
+            .ok_or_else(|| Error::CommandNotFound(command.to_string()))?;
+        if let Some((other, _)) = matches.next() {
+            return Err(Error::invalid_arguments(format!(
+                "command {} is ambiguous, use {}:{} or {}:{}",
+                name, plugin, name, other, name
+            )));
+        }
+
+        let values = cmd.parse_args(args)?;
+        self.call(&plugin, &cmd.export, &values)
+    }
+
+    /// Broadcast a function call to all running plugins, higher priorities
+    /// first.
+    pub fn broadcast(
+        &self,
+        function: &str,
+        args: &[fusabi_host::Value],
+    ) -> Vec<(String, Result<fusabi_host::Value>)> {
+        let mut plugins = self.registry.running();
+        plugins.sort_by_key(|p| (std::cmp::Reverse(p.priority()), p.name()));
+        plugins
+            .into_iter()
+            .filter(|p| p.has_export(function))
+            .map(|p| {
+                let name = p.name();
+                let result = p.call(function, args);
+                (name, result)
+            })
+            .collect()
+    }
+
+    /// Replace the base engine configuration for new engines.
+    ///
+    /// Call [`restart`](Self::restart) to apply it to loaded plugins.
+    pub fn set_engine_config(&self, config: fusabi_host::EngineConfig) {
+        self.loader.set_engine_config(config);
+    }
+
+    /// Rebuild the engine of every initialized plugin, e.g. after
+    /// [`set_engine_config`](Self::set_engine_config).
+    ///
+    /// New engines are built, and initialized for running plugins, next to
+    /// the old ones, then swapped in together once calls in progress have
+    /// finished. Calls through the runtime or a plugin handle that start
+    /// during the swap are held until it completes. Plugin handles stay
+    /// valid.
+    ///
+    /// If any engine fails to build, none is swapped and the error is
+    /// returned; a plugin started while its engine was being built is
+    /// initialized during the swap, and if that fails the remaining plugins
+    /// are not swapped. If calls in progress do not finish within 5 seconds
+    /// nothing is swapped and [`Error::ReloadFailed`] is returned. Restarting
+    /// from within a plugin call fails with [`Error::InvalidState`], as the
+    /// swap would wait for that call.
+    pub fn restart(&self) -> Result<()> {
+        if CALL_DEPTH.with(Cell::get) > 0 {
+            return Err(Error::invalid_state(
+                "outside a plugin call",
+                "called from within a plugin call",
+            ));
+        }
+
+        let mut plugins = self.registry.all();
+        // Members of composite plugins are not registered themselves
+        let mut i = 0;
+        while i < plugins.len() {
+            let members = plugins[i].inner().members();
+            plugins.extend(members);
+            i += 1;
+        }
+
+        let mut swaps = Vec::new();
+        for plugin in plugins {
+            if plugin.state() == LifecycleState::Unloaded
+                || plugin.inner().engine_config().is_none()
+            {
+                continue;
+            }
+            let manifest = plugin.inner().manifest();
+            let prepared = self
+                .loader
+                .build_engine_config(&manifest, plugin.info().origin)
+                .and_then(|engine_config| {
+                    plugin
+                        .inner()
+                        .prepare_swap(manifest, plugin.inner().bytecode(), engine_config)
+                });
+            match prepared {
+                Ok(swap) => swaps.push((plugin, swap)),
+                Err(e) => {
+                    for (_, swap) in swaps {
+                        swap.discard();
+                    }
+                    let reason = match e.root() {
+                        Error::ReloadFailed(reason) => reason.clone(),
+                        _ => e.to_string(),
+                    };
+                    return Err(Error::ReloadFailed(format!(
+                        "{}: {}",
+                        plugin.name(),
+                        reason
+                    )));
+                }
+            }
+        }
+
+        let mut retired = Vec::with_capacity(swaps.len());
+        let mut failed = None;
+        {
+            let Some(_swap) = self.recovery.swap_gate.try_write_for(SWAP_GATE_TIMEOUT) else {
+                for (_, swap) in swaps {
+                    swap.discard();
+                }
+                return Err(Error::ReloadFailed(format!(
+                    "calls in progress did not finish within {:?}",
+                    SWAP_GATE_TIMEOUT
+                )));
+            };
+            for (plugin, swap) in swaps {
+                if failed.is_some() {
+                    swap.discard();
+                    continue;
+                }
+                match plugin.inner().commit_swap(swap) {
+                    Ok(old) => retired.push((plugin, old)),
+                    // An unloaded plugin no longer needs restarting
+                    Err(Error::PluginUnloaded) => {}
+                    Err(e) => failed = Some((plugin.name(), e)),
+                }
+            }
+        }
+
+        tracing::info!("Restarted {} plugin engines", retired.len());
+        let hooks = self.hooks.read();
+        for (plugin, old) in retired {
+            old.retire();
+            hooks.emit_reloaded(&plugin.name(), plugin.info().reload_count);
+        }
+        match failed {
+            Some((name, e)) => {
+                let reason = match e.root() {
+                    Error::ReloadFailed(reason) => reason.clone(),
+                    _ => e.to_string(),
+                };
+                Err(Error::ReloadFailed(format!("{}: {}", name, reason)))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Clean up unloaded plugins.
+    pub fn cleanup(&self) -> usize {
+        self.registry.cleanup()
+    }
+
+    /// Shutdown the runtime.
+    ///
+    /// Background tasks are stopped first, according to the configured
+    /// [`ShutdownPolicy`], so nothing restarts or reloads plugins while they
+    /// are being stopped. Returns which background jobs were interrupted.
+    pub fn shutdown(&self) -> ShutdownReport {
+        let report = self.tasks.shutdown_with(self.config.shutdown_policy);
+        if let Some(dispatcher) = self.dispatcher.get() {
+            dispatcher.shutdown();
+        }
+
+        #[cfg(feature = "watch")]
+        self.disable_hot_reload();
+
+        // Stop all running plugins; shutdown bypasses `before_stop` hooks
+        let running = self.registry.running();
+        let _ = self.registry.stop_all();
+        {
+            let hooks = self.hooks.read();
+            for plugin in running {
+                if plugin.state() == LifecycleState::Stopped {
+                    hooks.emit_stopped(&plugin.name());
+                }
+            }
+        }
+
+        self.unload_all();
+        report
+    }
+
+    /// Unload every plugin left in the registry, emitting their events.
+    fn unload_all(&self) {
+        let names = self.registry.names();
+        self.registry.unload_all();
+        let hooks = self.hooks.read();
+        for name in names {
+            hooks.emit_unloaded(&name);
+        }
+    }
+
+    /// Shut down the runtime within `timeout`.
+    ///
+    /// Unlike [`shutdown`](Self::shutdown), the cleanup hooks of running
+    /// plugins run concurrently and their failures are reported. Plugins
+    /// still cleaning up at the deadline are unloaded without waiting for
+    /// them; background tasks and queued calls share the same deadline.
+    pub fn shutdown_with_timeout(&self, timeout: Duration) -> ShutdownReport {
+        let deadline = Instant::now() + timeout;
+        let policy = match self.config.shutdown_policy {
+            ShutdownPolicy::Cancel => ShutdownPolicy::Cancel,
+            ShutdownPolicy::Finish => ShutdownPolicy::FinishWithin(timeout),
+            ShutdownPolicy::FinishWithin(limit) => ShutdownPolicy::FinishWithin(limit.min(timeout)),
+        };
+        let mut report = self.tasks.shutdown_with(policy);
+        if let Some(dispatcher) = self.dispatcher.get() {
+            dispatcher.shutdown_until(Some(deadline));
+        }
+
+        #[cfg(feature = "watch")]
+        self.disable_hot_reload();
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut pending = HashMap::new();
+        for plugin in self.registry.running() {
+            let name = plugin.name();
+            let tx = tx.clone();
+            let stopping = plugin.clone();
+            let spawned = std::thread::Builder::new()
+                .name(format!("fusabi-stop-{}", name))
+                .spawn(move || {
+                    let _ = tx.send((stopping.name(), stopping.inner().stop_reporting()));
+                });
+            match spawned {
+                Ok(_) => {
+                    pending.insert(name, plugin);
+                }
+                Err(e) => report
+                    .plugins
+                    .push((name, PluginShutdownOutcome::Failed(e.to_string()))),
+            }
+        }
+        drop(tx);
+
+        while !pending.is_empty() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let Ok((name, result)) = rx.recv_timeout(remaining) else {
+                break;
+            };
+            pending.remove(&name);
+            let outcome = match result {
+                Ok(()) => PluginShutdownOutcome::Stopped,
+                Err(e) => {
+                    tracing::warn!("Plugin {} failed to clean up: {}", name, e);
+                    PluginShutdownOutcome::Failed(e.to_string())
+                }
+            };
+            self.hooks.read().emit_stopped(&name);
+            report.plugins.push((name, outcome));
+        }
+
+        // Stragglers leave the registry now and are unloaded once their
+        // cleanup returns, which killing the worker forces where possible
+        for (name, plugin) in pending {
+            self.registry.detach(&name);
+            let outcome = if plugin.inner().kill() {
+                tracing::warn!("Plugin {} did not clean up in time, killed it", name);
+                PluginShutdownOutcome::Killed
+            } else {
+                tracing::warn!("Plugin {} did not clean up in time, detaching it", name);
+                PluginShutdownOutcome::TimedOut
+            };
+            self.hooks.read().emit_stopped(&name);
+            let hooks = self.hooks.clone();
+            let unloading = name.clone();
+            let spawned = std::thread::Builder::new()
+                .name(format!("fusabi-unload-{}", name))
+                .spawn(move || {
+                    let _ = plugin.inner().unload();
+                    hooks.read().emit_unloaded(&unloading);
+                });
+            if let Err(e) = spawned {
+                tracing::warn!("Plugin {} cannot be unloaded: {}", name, e);
+            }
+            report.plugins.push((name, outcome));
+        }
+        report.plugins.sort_by(|a, b| a.0.cmp(&b.0));
+
+        self.unload_all();
+        report
+    }
+}
+
+/// Get the name of the plugin a file is loaded as, for errors raised before
+/// the plugin exists: the manifest's name, or the file stem.
+fn name_for_path(path: &Path) -> String {
+    #[cfg(feature = "serde")]
+    if path.extension().is_some_and(|e| e == "toml") {
+        if let Ok(manifest) = Manifest::from_file(path) {
+            return manifest.name;
+        }
+    }
+    path.file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("unnamed")
+        .to_string()
+}
+
+/// Get the file errors about a loaded plugin relate to: its entry file, or
+/// its manifest.
+fn plugin_file(plugin: &PluginHandle) -> Option<PathBuf> {
+    let info = plugin.info();
+    info.entry_path.or(info.manifest_path)
+}
+
+/// Audit record of a sampled plugin call.
+fn call_record(
+    principal: &Principal,
+    plugin: &str,
+    function: &str,
+    args: String,
+    started: Instant,
+    result: &Result<fusabi_host::Value>,
+) -> AuditRecord {
+    let outcome = match result {
+        Ok(_) => AuditOutcome::Succeeded,
+        Err(e) => AuditOutcome::Failed(e.to_string()),
+    };
+    AuditRecord::new(
+        principal.to_string(),
+        "call",
+        Some(format!("{}.{}", plugin, function)),
+        outcome,
+    )
+    .with_call(CallDetails {
+        plugin: plugin.to_string(),
+        export: function.to_string(),
+        args,
+        duration: started.elapsed(),
+    })
+}
+
+/// Run a scheduled job once, returning whether it ran.
+fn run_scheduled_job(
+    registry: &PluginRegistry,
+    cluster: Option<&dyn ClusterBackend>,
+    instance: &str,
+    plugin: &str,
+    job: &ScheduledJob,
+) -> Result<bool> {
+    let Some(handle) = registry.get(plugin) else {
+        return Ok(false);
+    };
+    if job.singleton {
+        if let Some(cluster) = cluster {
+            // Leases outlive two intervals so a slow run does not hand over
+            let ttl = job.interval() * 2;
+            if !cluster.acquire_lease(&job.lease_key(plugin), instance, ttl)? {
+                return Ok(false);
+            }
+        }
+    }
+    handle.call(&job.export, &[])?;
+    Ok(true)
+}
+
+/// Default cluster identity: the host name and process id.
+fn default_instance_id() -> String {
+    let host = std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .unwrap_or_else(|_| "localhost".to_string());
+    format!("{}-{}", host, std::process::id())
+}
+
+fn supervisor(config: &RuntimeConfig) -> Supervisor {
+    let supervisor = Supervisor::new(config.restart_policy.clone());
+    match config.rollback_after {
+        Some(restarts) => supervisor.with_rollback_after(restarts),
+        None => supervisor,
+    }
+}
+
+fn call_profile(registry: &PluginRegistry) -> CallProfile {
+    let mut profile = CallProfile::new();
+    for plugin in registry.iter() {
+        for (export, calls) in plugin.inner().export_calls() {
+            profile.record(plugin.name(), &export, calls);
+        }
+    }
+    profile
+}
+
+/// Shared state needed to reload and restart plugins off the caller's thread.
+#[derive(Clone)]
+struct Recovery {
+    loader: Arc<PluginLoader>,
+    registry: Arc<PluginRegistry>,
+    hooks: Arc<RwLock<LifecycleHooks>>,
+    supervisor: Arc<Supervisor>,
+    budgets: Arc<BudgetTracker>,
+    prewarm_top: usize,
+    prewarm_profile: CallProfile,
+    // Version last marked known-good per plugin, sparing a store write per call
+    known_good: Arc<DashMap<String, String>>,
+    // Held for writing while `restart` swaps engines, so calls wait; nested
+    // calls read recursively so they never block behind a waiting writer
+    swap_gate: Arc<RwLock<()>>,
+    rate_limits: Arc<CallRateLimiter>,
+    authorizer: Option<Arc<dyn CallAuthorizer>>,
+    call_sampling: CallSampling,
+    sampled_calls: Arc<std::sync::atomic::AtomicU64>,
+    audit: Arc<AuditFanout>,
+    tasks: Arc<TaskSupervisor>,
+    // Wakes the `restarts` task, spawned when the first restart is scheduled
+    restarts: Arc<parking_lot::Mutex<Option<TaskWaker>>>,
+}
+
+impl CallGate for Recovery {
+    fn guard(
+        &self,
+        plugin: &PluginHandle,
+        function: &str,
+        args: GateArgs<'_>,
+        call: &mut dyn FnMut() -> Result<fusabi_host::Value>,
+    ) -> Result<fusabi_host::Value> {
+        let name = plugin.name();
+        self.authorize(plugin.principal(), &name, function)?;
+        plugin.inner().admit_call(&self.rate_limits, function)?;
+        let admitted_at = self.admit(&name)?;
+        let _charge =
+            self.budgets
+                .start_call(&name, admitted_at, plugin.inner().runs_on_caller_thread());
+        let depth = CallDepth::enter();
+        let _swap = if depth.is_outermost() {
+            self.swap_gate.read()
+        } else {
+            self.swap_gate.read_recursive()
+        };
+        let sampled = self.sample_call(&name);
+        let started = Instant::now();
+        let result = call();
+        if sampled {
+            let summary = match args {
+                GateArgs::Plain(args) => self.call_sampling.summarize(args),
+                GateArgs::Sensitive(args) => self.call_sampling.summarize_redacted(args),
+            };
+            self.audit.record_call(&call_record(
+                plugin.principal(),
+                &name,
+                function,
+                summary,
+                started,
+                &result,
+            ));
+        }
+        self.finish_call(plugin, result)
+    }
+}
+
+impl Recovery {
+    fn sample_call(&self, plugin_name: &str) -> bool {
+        self.call_sampling.is_enabled()
+            && self.call_sampling.samples(
+                plugin_name,
+                self.sampled_calls
+                    .fetch_add(1, std::sync::atomic::Ordering::Relaxed),
+            )
+    }
+
+    /// Fail with [`Error::CallDenied`] if the call authorizer refuses the call.
+    fn authorize(&self, principal: &Principal, plugin: &str, function: &str) -> Result<()> {
+        match self.authorizer {
+            Some(ref authorizer) if !authorizer.allow(principal, plugin, function) => {
+                Err(Error::call_denied(principal.to_string(), plugin, function))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn reload(&self, name: &str) -> Result<()> {
+        let plugin = self
+            .registry
+            .get(name)
+            .ok_or_else(|| Error::plugin_not_found(name))?;
+
+        let guards = self.hooks.read().guards(Transition::Reload);
+        guards.check(name)?;
+
+        let info = plugin.info();
+        if info.manifest_path.is_none() && info.entry_path.is_none() {
+            return self.registry.reload(name);
+        }
+
+        self.loader.reload_from_disk(&plugin)?;
+        self.hooks
+            .read()
+            .emit_reloaded(name, plugin.info().reload_count);
+
+        self.prewarm_if_hot(&plugin);
+
+        Ok(())
+    }
+
+    /// Warm a running plugin if it is among the `prewarm_top` most called,
+    /// counting the saved profile and the calls since startup.
+    fn prewarm_if_hot(&self, plugin: &PluginHandle) {
+        if self.prewarm_top == 0 || plugin.state() != LifecycleState::Running {
+            return;
+        }
+        let name = plugin.name();
+        let mut profile = call_profile(&self.registry);
+        profile.merge(&self.prewarm_profile);
+        if !profile.hottest(self.prewarm_top).contains(&name) {
+            return;
+        }
+        if let Err(e) = plugin.inner().warm() {
+            tracing::warn!("Failed to warm plugin {}: {}", name, e);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    fn rollback(&self, name: &str) -> Result<String> {
+        let plugin = self
+            .registry
+            .get(name)
+            .ok_or_else(|| Error::plugin_not_found(name))?;
+
+        let guards = self.hooks.read().guards(Transition::Reload);
+        guards.check(name)?;
+
+        let failed = |reason: String| Error::RollbackFailed(format!("{}: {}", name, reason));
+        let store = self
+            .loader
+            .config()
+            .artifact_store
+            .as_ref()
+            .ok_or_else(|| failed("no artifact store configured".to_string()))?;
+
+        let current = plugin.inner().manifest();
+        let installed = store.installed(name)?;
+        let newer = installed
+            .iter()
+            .position(|v| v.version == current.version)
+            .unwrap_or(installed.len());
+        let target = installed[..newer]
+            .iter()
+            .rev()
+            .find(|v| v.known_good)
+            .ok_or_else(|| {
+                failed(format!(
+                    "no known-good version installed before {}",
+                    current.version
+                ))
+            })?;
+
+        let (mut manifest, bytecode) = self
+            .loader
+            .stored_version(name, &target.version)
+            .map_err(|e| failed(e.to_string()))?;
+        if let Some(delegation) = plugin.inner().delegation() {
+            delegation.restrict(&mut manifest);
+        }
+        check_state_compatible(&current, &manifest).map_err(failed)?;
+        for dependent in self.registry.all() {
+            let required = dependent
+                .inner()
+                .manifest()
+                .dependencies
+                .into_iter()
+                .find(|d| d.name == name && !d.optional && !d.is_satisfied_by(&manifest.version));
+            if let Some(dep) = required {
+                return Err(failed(format!(
+                    "{} requires {} {}",
+                    dependent.name(),
+                    name,
+                    dep.version
+                )));
+            }
+        }
+        let engine_config = self
+            .loader
+            .prepare_manifest(&manifest, plugin.info().origin)
+            .map_err(|e| failed(e.to_string()))?;
+
+        plugin
+            .inner()
+            .replace(manifest, bytecode, engine_config)
+            .map_err(|e| failed(e.to_string()))?;
+        tracing::info!(
+            "Rolled plugin {} back from {} to {}",
+            name,
+            current.version,
+            target.version
+        );
+        self.hooks
+            .read()
+            .emit_reloaded(name, plugin.info().reload_count);
+        Ok(target.version.clone())
+    }
+
+    /// Wait for or reject a call to a plugin that is over its budget,
+    /// returning when the call was admitted.
+    ///
+    /// Emits `BudgetExceeded` when throttling starts and `BudgetRestored`
+    /// once a call is admitted again.
+    fn admit(&self, name: &str) -> Result<Instant> {
+        let deadline = match self.budgets.budget(name).map(|b| b.action) {
+            Some(ThrottleAction::Queue { max_wait }) => Some(Instant::now() + max_wait),
+            Some(ThrottleAction::Reject) => None,
+            None => return Ok(Instant::now()),
+        };
+        loop {
+            let now = Instant::now();
+            match self.budgets.admit_at(name, now) {
+                Admission::Admitted => return Ok(now),
+                Admission::Restored => {
+                    self.hooks.read().emit_budget_restored(name);
+                    return Ok(now);
+                }
+                Admission::Exceeded {
+                    budget,
+                    retry_after,
+                    newly,
+                } => {
+                    if newly {
+                        tracing::warn!(
+                            "Plugin {} exceeded its {} budget, throttling calls",
+                            name,
+                            budget
+                        );
+                        self.hooks
+                            .read()
+                            .emit_budget_exceeded(name, budget, retry_after);
+                    }
+                    match deadline {
+                        Some(deadline) if Instant::now() + retry_after <= deadline => {
+                            std::thread::sleep(retry_after.max(Duration::from_millis(1)));
+                        }
+                        _ => return Err(Error::budget_exceeded(name, budget, retry_after)),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Record the outcome of a call with the supervisor.
+    fn finish_call(
+        &self,
+        plugin: &PluginHandle,
+        result: Result<fusabi_host::Value>,
+    ) -> Result<fusabi_host::Value> {
+        match result {
+            Ok(value) => {
+                self.supervisor.record_success(&plugin.name());
+                self.mark_known_good(plugin);
+                Ok(value)
+            }
+            Err(e) => {
+                if matches!(e.root(), Error::ExecutionFailed(_)) {
+                    self.handle_call_failure(plugin, &e);
+                }
+                Err(e.in_plugin(plugin.name(), PluginOperation::Call, plugin_file(plugin)))
+            }
+        }
+    }
+
+    /// Record that the plugin's current version served a call.
+    fn mark_known_good(&self, plugin: &PluginHandle) {
+        let Some(ref store) = self.loader.config().artifact_store else {
+            return;
+        };
+        let name = plugin.name();
+        let version = plugin.version();
+        if self.known_good.get(&name).is_some_and(|v| *v == version) {
+            return;
+        }
+        match store.mark_known_good(&name, &version) {
+            Ok(()) => {
+                self.known_good.insert(name, version);
+            }
+            Err(e) => tracing::warn!(
+                "Plugin {}: failed to mark {} known-good: {}",
+                name,
+                version,
+                e
+            ),
+        }
+    }
+
+    fn supervise(&self) -> Vec<(String, Result<()>)> {
+        self.supervisor
+            .due(Instant::now())
+            .into_iter()
+            .filter_map(|name| {
+                let Some(plugin) = self.registry.get(&name) else {
+                    self.supervisor.forget(&name);
+                    return None;
+                };
+                Some((name, self.restart(&plugin)))
+            })
+            .collect()
+    }
+
+    /// Wake the `restarts` task to run due restarts, spawning it on first use.
+    fn wake_restarts(&self) {
+        let mut waker = self.restarts.lock();
+        if let Some(ref waker) = *waker {
+            if self.tasks.is_running(RESTART_TASK) {
+                waker.wake();
+                return;
+            }
+        }
+
+        // The task starts with a run, so it needs no wake
+        let recovery = self.clone();
+        let spawned = self.tasks.spawn_scheduled(RESTART_TASK, move || {
+            recovery.supervise();
+            Ok(recovery.supervisor.next_restart())
+        });
+        match spawned {
+            Ok(spawned) => *waker = Some(spawned),
+            Err(e) => tracing::warn!("Failed to start the plugin restart task: {}", e),
+        }
+    }
+
+    fn check_health(&self, plugin: &PluginHandle) -> PluginHealth {
+        let health = plugin.health_check();
+        if let HealthStatus::Unhealthy(ref reason) = health.status {
+            if plugin.state() == LifecycleState::Running {
+                let name = plugin.name();
+                tracing::warn!("Plugin {} failed its health check: {}", name, reason);
+                plugin.inner().set_state(LifecycleState::Error);
+                {
+                    let hooks = self.hooks.read();
+                    hooks.emit_unhealthy(&name, reason);
+                    hooks.emit_error(&name, &format!("health check failed: {}", reason));
+                }
+                if self.supervisor.is_supervised(&name) {
+                    self.schedule_restart(plugin);
+                }
+            }
+        }
+        health
+    }
+
+    fn check_all_health(&self) -> Vec<PluginHealth> {
+        let mut plugins = self.registry.running();
+        plugins.sort_by_key(|p| p.name());
+        plugins.iter().map(|p| self.check_health(p)).collect()
+    }
+
+    fn handle_call_failure(&self, plugin: &PluginHandle, error: &Error) {
+        let name = plugin.name();
+        if !self.supervisor.is_supervised(&name) {
+            return;
+        }
+
+        plugin.inner().set_state(LifecycleState::Error);
+        self.hooks.read().emit_error(&name, &error.to_string());
+        self.schedule_restart(plugin);
+    }
+
+    fn schedule_restart(&self, plugin: &PluginHandle) {
+        let name = plugin.name();
+        let now = Instant::now();
+
+        #[cfg(feature = "serde")]
+        if self.supervisor.should_rollback(&name) {
+            let result = self.rollback(&name).and_then(|version| {
+                plugin.inner().set_state(LifecycleState::Initialized);
+                plugin.inner().start()?;
+                Ok(version)
+            });
+            match result {
+                Ok(version) => {
+                    self.supervisor.record_success(&name);
+                    self.hooks.read().emit_started(&name);
+                    tracing::warn!("Plugin {} kept failing, rolled back to {}", name, version);
+                    return;
+                }
+                Err(e) => tracing::warn!("Failed to roll back plugin {}: {}", name, e),
+            }
+        }
+
+        match self.supervisor.record_failure(&name, now) {
+            Some(at) => {
+                tracing::debug!(
+                    "Restarting plugin {} in {:?}",
+                    name,
+                    at.saturating_duration_since(now)
+                );
+                self.wake_restarts();
+            }
+            None => {
+                tracing::warn!(
+                    "Plugin {} failed after {} restarts, leaving it in error state",
+                    name,
+                    self.supervisor.restart_count(&name)
+                );
+            }
+        }
+    }
+
+    fn restart(&self, plugin: &PluginHandle) -> Result<()> {
+        let name = plugin.name();
+        self.supervisor.record_restart(&name);
+
+        let guards = self.hooks.read().guards(Transition::Start);
+        let result = guards
+            .check(&name)
+            .and_then(|()| self.reload(&name))
+            .and_then(|()| {
+                plugin.inner().set_state(LifecycleState::Initialized);
+                plugin.inner().start()
+            });
+
+        match result {
+            Ok(()) => {
+                tracing::info!("Restarted plugin {}", name);
+                self.hooks.read().emit_started(&name);
+                Ok(())
+            }
+            Err(e) => {
+                tracing::warn!("Failed to restart plugin {}: {}", name, e);
+                plugin.inner().set_state(LifecycleState::Error);
+                self.hooks.read().emit_error(&name, &e.to_string());
+                // Retry when the next delay passes rather than looping here
+                self.supervisor.record_failure(&name, Instant::now());
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Check an older version can take over the state of the current one.
+///
+/// Plugins declare the format of the state they persist with a
+/// `state-version` metadata entry; a version reading an older format may
+/// not understand state written in a newer one.
+#[cfg(feature = "serde")]
+fn check_state_compatible(
+    current: &Manifest,
+    target: &Manifest,
+) -> std::result::Result<(), String> {
+    let state_version = |manifest: &Manifest| -> std::result::Result<u32, String> {
+        manifest.metadata.get("state-version").map_or(Ok(0), |v| {
+            v.trim()
+                .parse()
+                .map_err(|_| format!("invalid state-version {:?} in {}", v, manifest.version))
+        })
+    };
+    let (from, to) = (state_version(current)?, state_version(target)?);
+    if to < from {
+        return Err(format!(
+            "{} uses state version {}, {} only reads up to {}",
+            current.version, from, target.version, to
+        ));
+    }
+    Ok(())
+}
+
+impl std::fmt::Debug for PluginRuntime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PluginRuntime")
+            .field("config", &self.config)
+            .field("plugin_count", &self.registry.len())
+            .finish()
+    }
+}
+
+impl Drop for PluginRuntime {
+    fn drop(&mut self) {
+        let _ = self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runtime_creation() {
+        let runtime = PluginRuntime::default_config().unwrap();
+        assert_eq!(runtime.plugin_count(), 0);
+    }
+
+    #[test]
+    fn test_runtime_config_builder() {
+        let config = RuntimeConfig::new()
+            .with_plugin_dir("/plugins")
+            .with_auto_discover(true);
+
+        assert_eq!(config.plugin_dirs.len(), 1);
+        assert!(config.auto_discover);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_runtime_config_validation() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(RuntimeConfig::new()
+            .with_plugin_dir(dir.path())
+            .with_auto_discover(true)
+            .validate()
+            .is_ok());
+
+        let mut config = RuntimeConfig::new()
+            .with_loader(LoaderConfig::new().with_base_path(dir.path().join("missing")))
+            .with_network(NetworkConfig::new().with_proxy("proxy:3128"))
+            .with_plugin_dir(dir.path().join("plugins"))
+            .with_plugin_patterns(vec!["../*.toml".to_string()])
+            .with_rollback_after(2);
+        config.dispatcher.workers = 0;
+
+        let Err(Error::Config(errors)) = PluginRuntime::new(config) else {
+            panic!("expected a configuration error");
+        };
+        assert_eq!(
+            errors.paths(),
+            vec![
+                "loader.base_path",
+                "network.proxy",
+                "plugin_dirs[0]",
+                "plugin_patterns[0]",
+                "dispatcher.workers",
+                "rollback_after",
+            ]
+        );
+        assert!(errors.issues[2].reason.ends_with("does not exist"));
+    }
+
+    #[test]
+    fn test_runtime_host_info() {
+        let runtime = PluginRuntime::default_config().unwrap();
+        let info = runtime.host_info();
+
+        assert_eq!(
+            info.api_version,
+            crate::manifest::ApiVersion::default().to_string()
+        );
+        assert_eq!(info.runtime_version, crate::VERSION);
+        assert_eq!(
+            info.features,
+            runtime
+                .enabled_features()
+                .iter()
+                .map(|f| f.name())
+                .collect::<Vec<_>>()
+        );
+
+        let describe = runtime.host_functions().get("host.info").unwrap();
+        assert!(matches!(
+            describe(&[]),
+            Ok(fusabi_host::Value::String(ref s)) if *s == info.to_string()
+        ));
+    }
+
+    #[test]
+    fn test_runtime_bulk() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut ops = Vec::new();
+        for name in ["alpha", "beta", "gamma"] {
+            let path = dir.path().join(format!("{}.fsx", name));
+            std::fs::write(&path, "let x = 1").unwrap();
+            ops.push(BulkOp::load(path));
+        }
+        ops.push(BulkOp::load(dir.path().join("missing.fsx")));
+        ops.push(BulkOp::reload("alpha"));
+
+        let runtime = PluginRuntime::default_config().unwrap();
+        let mut progress = Vec::new();
+        let report = runtime.bulk_with(ops.clone(), BulkPolicy::ContinueOnError, |p| {
+            progress.push((p.completed, p.total, p.outcome.is_success()))
+        });
+
+        assert_eq!(report.succeeded(), 4);
+        assert_eq!(report.failed(), 1);
+        assert_eq!(report.failures().next().unwrap().0, &ops[3]);
+        assert_eq!(progress.len(), 5);
+        assert_eq!(progress[3], (4, 5, false));
+        assert_eq!(runtime.plugin_count(), 3);
+
+        // Loading again fails on the first duplicate and skips the rest
+        let report = runtime.bulk_with(ops, BulkPolicy::StopOnError, |_| {});
+        assert_eq!(report.failed(), 1);
+        assert_eq!(report.skipped(), 4);
+        assert!(!report.is_success());
+    }
+
+    #[test]
+    fn test_runtime_capability_audit() {
+        use crate::audit::AuditOutcome;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("reader.fsx");
+        std::fs::write(&path, "let x = 1").unwrap();
+
+        let runtime = PluginRuntime::default_config().unwrap();
+        runtime.set_engine_config(fusabi_host::EngineConfig::default().with_capabilities(
+            fusabi_host::Capabilities::none().with(fusabi_host::Capability::FsRead),
+        ));
+        let extra = Arc::new(MemoryAuditLog::new(10));
+        runtime.add_audit_sink(extra.clone());
+        let plugin = runtime.load_source(&path).unwrap();
+
+        assert!(plugin
+            .inner()
+            .check_path_access("fs:read", std::path::Path::new("/etc"))
+            .is_err());
+
+        // The grant made at engine setup comes first, then the access check
+        let records = runtime.audit_log().records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].principal, "reader");
+        assert_eq!(records[0].operation, "fs:read");
+        assert_eq!(records[0].target, None);
+        assert_eq!(records[0].outcome, AuditOutcome::Succeeded);
+        assert_eq!(records[1].operation, "fs:read");
+        assert_eq!(records[1].target.as_deref(), Some("/etc"));
+        assert!(matches!(records[1].outcome, AuditOutcome::Denied(_)));
+        assert_eq!(extra.len(), 2);
+
+        // Reloading sets up a new engine and records its grants again
+        runtime.reload("reader").unwrap();
+        let records = runtime.audit_log().records();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[2].target, None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_runtime_load_delegated() {
+        use crate::audit::AuditOutcome;
+        use crate::manifest::CapabilitySpec;
+
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, caps: &str| {
+            std::fs::write(dir.path().join(format!("{}.fsx", name)), "let x = 1").unwrap();
+            std::fs::write(
+                dir.path().join(format!("{}.toml", name)),
+                format!(
+                    "name = \"{0}\"\nversion = \"1.0.0\"\nsource = \"{0}.fsx\"\n\
+                     capabilities = [{1}]\n\
+                     api-version = {{ major = 0, minor = 21, patch = 0 }}\n",
+                    name, caps
+                ),
+            )
+            .unwrap();
+        };
+        write("framework", r#""plugins:load", "fs:read""#);
+        write("widget", r#""fs:read", "net:request""#);
+        write("plain", "");
+        write("scoped", r#"{ cap = "fs:read", paths = ["/data"] }"#);
+
+        let runtime = PluginRuntime::default_config().unwrap();
+        runtime
+            .load_manifest(dir.path().join("framework.toml"))
+            .unwrap();
+        runtime
+            .load_manifest(dir.path().join("plain.toml"))
+            .unwrap();
+
+        assert!(matches!(
+            runtime.load_delegated("plain", dir.path().join("widget.toml")),
+            Err(Error::CapabilityNotGranted { .. })
+        ));
+        assert!(!runtime.has_plugin("widget"));
+
+        let widget = runtime
+            .load_delegated("framework", dir.path().join("widget.toml"))
+            .unwrap();
+        assert_eq!(widget.inner().delegation().unwrap().parent(), "framework");
+        assert_eq!(
+            widget.inner().manifest().capabilities,
+            vec![CapabilitySpec::new("fs:read")]
+        );
+
+        // Scopes the engine cannot enforce are refused rather than ignored
+        let err = runtime
+            .load_delegated("framework", dir.path().join("scoped.toml"))
+            .unwrap_err();
+        assert!(matches!(err.root(), Error::InvalidManifest(_)));
+        assert!(!runtime.has_plugin("scoped"));
+
+        // Reloading reads the manifest again but keeps the restrictions
+        runtime.reload("widget").unwrap();
+        assert!(!widget.inner().requires_capability("net:request"));
+
+        let records = runtime.audit_log().records();
+        assert!(records.iter().any(|r| r.principal == "widget"
+            && r.operation == "net:request"
+            && matches!(r.outcome, AuditOutcome::Denied(_))));
+        assert!(records.iter().any(|r| r.principal == "framework"
+            && r.operation == "plugins:load"
+            && r.outcome == AuditOutcome::Succeeded));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_lockfile_checked_at_startup() {
+        let dir = tempfile::tempdir().unwrap();
+        let entry = dir.path().join("search.fsx");
+        std::fs::write(&entry, "let x = 1").unwrap();
+        let config = RuntimeConfig::new().with_lockfile(dir.path().join("plugins.lock"));
+
+        // No lockfile written yet
+        let runtime = PluginRuntime::new(config.clone()).unwrap();
+        runtime.load_source(&entry).unwrap();
+        runtime.lock().unwrap();
+        assert!(runtime.verify().unwrap().is_consistent());
+        assert!(PluginRuntime::new(config.clone()).is_ok());
+
+        std::fs::write(&entry, "let x = 2").unwrap();
+        match PluginRuntime::new(config) {
+            Err(Error::Registry(reason)) => assert!(reason.contains("search.fsx was modified")),
+            other => panic!("expected a registry error, got {:?}", other.err()),
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_discover_collisions() {
+        let root = tempfile::tempdir().unwrap();
+        for (dir, version) in [("first", "1.0.0"), ("second", "2.0.0")] {
+            let dir = root.path().join(dir);
+            std::fs::create_dir(&dir).unwrap();
+            std::fs::write(dir.join("main.fsx"), "let x = 1").unwrap();
+            std::fs::write(
+                dir.join("plugin.toml"),
+                format!(
+                    "name = \"dup\"\nversion = \"{}\"\n\
+                     api-version = {{ major = 0, minor = 21, patch = 0 }}\n\
+                     source = \"main.fsx\"\n",
+                    version
+                ),
+            )
+            .unwrap();
+        }
+        let config = |strategy| {
+            RuntimeConfig::new()
+                .with_plugin_dir(root.path().join("first"))
+                .with_plugin_dir(root.path().join("second"))
+                .with_collision_strategy(strategy)
+        };
+        let token = CancellationToken::new();
+
+        let runtime = PluginRuntime::new(config(CollisionStrategy::Error)).unwrap();
+        let report = runtime.discover_with_report(&token).into_inner();
+        assert!(report.loaded.is_empty());
+        assert_eq!(report.collisions[0].candidates.len(), 2);
+
+        let runtime = PluginRuntime::new(config(CollisionStrategy::PreferHigherVersion)).unwrap();
+        runtime.discover().unwrap();
+        assert_eq!(runtime.get("dup").unwrap().version(), "2.0.0");
+
+        let runtime = PluginRuntime::new(config(CollisionStrategy::SuffixWithDir)).unwrap();
+        let report = runtime.discover_with_report(&token).into_inner();
+        assert_eq!(report.loaded.len(), 2);
+        assert!(runtime.has_plugin("dup-second"));
+        runtime.reload("dup-second").unwrap();
+        assert!(runtime.has_plugin("dup-second"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_discover_loads_dependencies_first() {
+        let root = tempfile::tempdir().unwrap();
+        for (name, priority, deps) in [
+            (
+                "app",
+                10,
+                "[[dependencies]]\nname = \"db\"\nversion = \"^1\"\n",
+            ),
+            ("db", 0, ""),
+            ("ui", 5, ""),
+        ] {
+            let dir = root.path().join(name);
+            std::fs::create_dir(&dir).unwrap();
+            std::fs::write(dir.join("main.fsx"), "let x = 1").unwrap();
+            std::fs::write(
+                dir.join("plugin.toml"),
+                format!(
+                    "name = \"{}\"\nversion = \"1.0.0\"\npriority = {}\n\
+                     api-version = {{ major = 0, minor = 21, patch = 0 }}\n\
+                     source = \"main.fsx\"\n{}",
+                    name, priority, deps
+                ),
+            )
+            .unwrap();
+        }
+
+        let runtime =
+            PluginRuntime::new(RuntimeConfig::new().with_plugin_dir(root.path())).unwrap();
+        let report = runtime
+            .discover_with_report(&CancellationToken::new())
+            .into_inner();
+        let names: Vec<String> = report.loaded.iter().map(|p| p.name()).collect();
+        assert_eq!(names, ["db", "app", "ui"]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_discover_origins() {
+        let root = tempfile::tempdir().unwrap();
+        for (dir, name) in [("builtin", "core"), ("user", "extra")] {
+            let dir = root.path().join(dir);
+            std::fs::create_dir(&dir).unwrap();
+            std::fs::write(dir.join("main.fsx"), "let x = 1").unwrap();
+            std::fs::write(
+                dir.join("plugin.toml"),
+                format!(
+                    "name = \"{}\"\nversion = \"1.0.0\"\n\
+                     api-version = {{ major = 0, minor = 21, patch = 0 }}\n\
+                     source = \"main.fsx\"\ncapabilities = [\"net:request\"]\n",
+                    name
+                ),
+            )
+            .unwrap();
+        }
+
+        let runtime = PluginRuntime::new(
+            RuntimeConfig::new()
+                .with_origin_dir(root.path().join("builtin"), PluginOrigin::Builtin)
+                .with_origin_dir(root.path().join("user"), PluginOrigin::User),
+        )
+        .unwrap();
+        let report = runtime
+            .discover_with_report(&CancellationToken::new())
+            .into_inner();
+
+        assert_eq!(report.loaded.len(), 1);
+        assert_eq!(
+            runtime.get("core").unwrap().info().origin,
+            PluginOrigin::Builtin
+        );
+        // User plugins may not take capabilities the host did not grant
+        assert!(matches!(
+            report.failed[0].1,
+            Error::CapabilityNotGranted { .. }
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_runtime_cluster_converge() {
+        use crate::cluster::MemoryClusterBackend;
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("main.fsx"), "let x = 1").unwrap();
+        let manifest = dir.path().join("plugin.toml");
+        std::fs::write(
+            &manifest,
+            "name = \"shared\"\nversion = \"1.0.0\"\n\
+             api-version = { major = 0, minor = 21, patch = 0 }\nsource = \"main.fsx\"\n",
+        )
+        .unwrap();
+
+        let backend = MemoryClusterBackend::new();
+        let a = PluginRuntime::new(RuntimeConfig::new().with_cluster(backend.clone())).unwrap();
+        let b = PluginRuntime::new(RuntimeConfig::new().with_cluster(backend.clone())).unwrap();
+
+        a.install(&manifest).unwrap();
+        let report = b.converge().unwrap();
+        assert_eq!(report.loaded, vec!["shared"]);
+        assert!(b.has_plugin("shared"));
+        assert!(b.converge().unwrap().is_unchanged());
+
+        // Upgrades swap the new version in, keeping handles valid
+        let handle = b.get("shared").unwrap();
+        let next = dir.path().join("next.toml");
+        std::fs::write(
+            &next,
+            "name = \"shared\"\nversion = \"1.1.0\"\n\
+             api-version = { major = 0, minor = 21, patch = 0 }\nsource = \"main.fsx\"\n",
+        )
+        .unwrap();
+        backend
+            .publish(DesiredPlugin::new("shared", "1.1.0", &next))
+            .unwrap();
+        assert_eq!(b.converge().unwrap().upgraded, vec!["shared"]);
+        assert_eq!(handle.version(), "1.1.0");
+        assert_eq!(handle.state(), LifecycleState::Running);
+        backend
+            .publish(DesiredPlugin::new("shared", "1.0.0", &manifest))
+            .unwrap();
+        assert_eq!(b.converge().unwrap().upgraded, vec!["shared"]);
+
+        a.uninstall("shared").unwrap();
+        assert!(!a.has_plugin("shared"));
+        assert_eq!(b.converge().unwrap().unloaded, vec!["shared"]);
+        assert!(!b.has_plugin("shared"));
+
+        let standalone = PluginRuntime::default_config().unwrap();
+        assert!(standalone.converge().is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_runtime_spawn_convergence() {
+        use crate::cluster::MemoryClusterBackend;
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("main.fsx"), "let x = 1").unwrap();
+        let manifest = dir.path().join("plugin.toml");
+        std::fs::write(
+            &manifest,
+            "name = \"shared\"\nversion = \"1.0.0\"\n\
+             api-version = { major = 0, minor = 21, patch = 0 }\nsource = \"main.fsx\"\n",
+        )
+        .unwrap();
+
+        let backend = MemoryClusterBackend::new();
+        let runtime = Arc::new(
+            PluginRuntime::new(RuntimeConfig::new().with_cluster(backend.clone())).unwrap(),
+        );
+        runtime
+            .spawn_convergence(Duration::from_millis(10))
+            .unwrap();
+        backend
+            .publish(DesiredPlugin::new("shared", "1.0.0", &manifest))
+            .unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !runtime.has_plugin("shared") && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(runtime.has_plugin("shared"));
+
+        let standalone = Arc::new(PluginRuntime::default_config().unwrap());
+        assert!(standalone
+            .spawn_convergence(Duration::from_secs(1))
+            .is_err());
+    }
+
+    #[test]
+    fn test_singleton_jobs_run_on_leader() {
+        use crate::cluster::MemoryClusterBackend;
+        use crate::manifest::ManifestBuilder;
+
+        let backend = MemoryClusterBackend::new();
+        let job = ScheduledJob::new("main", Duration::from_secs(60)).singleton();
+        let runtimes: Vec<_> = ["host-a", "host-b"]
+            .into_iter()
+            .map(|id| {
+                let runtime = PluginRuntime::new(
+                    RuntimeConfig::new()
+                        .with_cluster(backend.clone())
+                        .with_instance_id(id),
+                )
+                .unwrap();
+                let manifest = ManifestBuilder::new("indexer", "1.0.0")
+                    .scheduled(job.clone())
+                    .build_unchecked();
+                let plugin = PluginHandle::new(crate::plugin::Plugin::new(manifest));
+                plugin.inner().initialize(Default::default()).unwrap();
+                plugin.inner().start().unwrap();
+                runtime.registry.register(plugin).unwrap();
+                runtime
+            })
+            .collect();
+
+        let ran: Vec<bool> = runtimes
+            .iter()
+            .map(|r| {
+                run_scheduled_job(
+                    &r.registry,
+                    r.cluster(),
+                    &r.config.instance_id,
+                    "indexer",
+                    &job,
+                )
+                .unwrap()
+            })
+            .collect();
+        assert_eq!(ran, vec![true, false]);
+
+        let standalone = PluginRuntime::default_config().unwrap();
+        assert!(!run_scheduled_job(&standalone.registry, None, "x", "indexer", &job).unwrap());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_scheduled_jobs_follow_loads() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("main.fsx"), "let x = 1").unwrap();
+        let manifest = dir.path().join("plugin.toml");
+        std::fs::write(
+            &manifest,
+            "name = \"indexer\"\nversion = \"1.0.0\"\n\
+             api-version = { major = 0, minor = 21, patch = 0 }\nsource = \"main.fsx\"\n\
+             [[schedule]]\nexport = \"main\"\nevery-secs = 60\n",
+        )
+        .unwrap();
+
+        let runtime = PluginRuntime::default_config().unwrap();
+        runtime.spawn_scheduled_jobs().unwrap();
+        assert!(!runtime.tasks.is_running("job:indexer:main"));
+
+        runtime.load_manifest(&manifest).unwrap();
+        assert!(runtime.tasks.is_running("job:indexer:main"));
+        runtime.unload("indexer").unwrap();
+        assert!(!runtime.tasks.is_running("job:indexer:main"));
+    }
+
+    #[test]
+    fn test_runtime_call_authorizer() {
+        use crate::authorizer::ExportAcl;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.fsx");
+        std::fs::write(&path, "let x = 1").unwrap();
+
+        let acl = ExportAcl::new().grant("alice", "notes", "main");
+        let runtime = PluginRuntime::new(RuntimeConfig::new().with_call_authorizer(acl)).unwrap();
+        runtime.load_source(&path).unwrap();
+
+        assert!(runtime.call_as("alice", "notes", "main", &[]).is_ok());
+        assert!(matches!(
+            runtime.call_as("bob", "notes", "main", &[]),
+            Err(Error::CallDenied { principal, .. }) if principal == "bob"
+        ));
+        assert!(matches!(
+            runtime.call("notes", "main", &[]),
+            Err(Error::CallDenied { .. })
+        ));
+
+        // Handles are checked too, as the host unless given a principal
+        let handle = runtime.get("notes").unwrap();
+        assert!(matches!(
+            handle.call("main", &[]),
+            Err(Error::CallDenied { principal, .. }) if principal == "<host>"
+        ));
+        assert!(handle.as_principal("alice").call("main", &[]).is_ok());
+        assert!(matches!(
+            handle.as_principal("bob").clone().call("main", &[]),
+            Err(Error::CallDenied { .. })
+        ));
+
+        // Queued calls carry the caller's principal to the worker
+        let receipt = runtime
+            .call_enqueued_as("alice", "notes", "main", Vec::new())
+            .unwrap();
+        assert!(receipt.wait().is_ok());
+        assert!(matches!(
+            runtime.call_enqueued_as("bob", "notes", "main", Vec::new()),
+            Err(Error::CallDenied { principal, .. }) if principal == "bob"
+        ));
+        assert!(matches!(
+            runtime.call_enqueued("notes", "main", Vec::new()),
+            Err(Error::CallDenied { .. })
+        ));
+    }
+
+    #[test]
+    fn test_runtime_call_enqueued() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.fsx");
+        std::fs::write(&path, "let x = 1").unwrap();
+
+        let runtime = PluginRuntime::new(
+            RuntimeConfig::new().with_dispatcher(DispatcherConfig::new().with_workers(2)),
+        )
+        .unwrap();
+        runtime.load_source(&path).unwrap();
+
+        let receipts: Vec<_> = (0..4)
+            .map(|_| runtime.call_enqueued("notes", "main", Vec::new()).unwrap())
+            .collect();
+        for receipt in receipts {
+            assert!(receipt.wait().is_ok());
+        }
+        assert!(matches!(
+            runtime.call_enqueued("missing", "main", Vec::new()),
+            Err(Error::PluginNotFound(_))
+        ));
+
+        runtime.shutdown();
+        assert!(matches!(
+            runtime.call_enqueued("notes", "main", Vec::new()),
+            Err(Error::PluginNotFound(_))
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_host_function_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("main.fsx"), "let x = 1").unwrap();
+        let manifest = dir.path().join("plugin.toml");
+        std::fs::write(
+            &manifest,
+            "name = \"notify\"\nversion = \"1.0.0\"\n\
+             api-version = { major = 0, minor = 21, patch = 0 }\n\
+             source = \"main.fsx\"\nhost-functions = [\"ui.notify\"]\n",
+        )
+        .unwrap();
+
+        let runtime = PluginRuntime::new(RuntimeConfig::new()).unwrap();
+        assert!(matches!(
+            runtime.load_manifest(&manifest),
+            Err(Error::MissingHostFunction { .. })
+        ));
+
+        runtime.register_host_function("ui.notify");
+        runtime.load_manifest(&manifest).unwrap();
+        let events = runtime.subscribe();
+
+        assert_eq!(
+            runtime.unregister_host_function("ui.notify"),
+            vec!["notify"]
+        );
+        assert!(matches!(
+            runtime.call("notify", "main", &[]),
+            Err(Error::MissingHostFunction { .. })
+        ));
+        assert!(matches!(
+            events.try_recv(),
+            Ok(crate::lifecycle::LifecycleEvent::HostFunctionsChanged { ref missing, .. }) if missing == &["ui.notify"]
+        ));
+
+        assert_eq!(runtime.register_host_function("ui.notify"), vec!["notify"]);
+        assert!(runtime.call("notify", "main", &[]).is_ok());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_startup_report() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("main.fsx"), "let x = 1").unwrap();
+        std::fs::write(
+            dir.path().join("plugin.toml"),
+            "name = \"search\"\nversion = \"1.0.0\"\n\
+             api-version = { major = 0, minor = 21, patch = 0 }\n\
+             source = \"main.fsx\"\ncapabilities = [\"net:request\"]\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("broken.toml"), "name = ").unwrap();
+
+        let runtime = PluginRuntime::new(
+            RuntimeConfig::new()
+                .with_plugin_dir(dir.path())
+                .with_plugin_patterns(vec!["*.toml".to_string()]),
+        )
+        .unwrap();
+        runtime.discover().unwrap();
+
+        let report = runtime.startup_report();
+        assert_eq!(report.plugins, 1);
+        assert_eq!(report.count(LifecycleState::Running), 1);
+        assert!(report.slowest[0].duration > Duration::ZERO);
+        assert_eq!(report.capabilities["net:request"], vec!["search"]);
+        assert_eq!(report.warnings.len(), 1);
+    }
+
+    #[cfg(all(unix, feature = "isolation"))]
+    #[test]
+    fn test_health_checks() {
+        use crate::isolation::IsolationConfig;
+        use crate::lifecycle::LifecycleEvent;
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("main.fsx"), "let health () = true").unwrap();
+        for (name, isolation) in [("db", "process"), ("web", "in-process")] {
+            std::fs::write(
+                dir.path().join(format!("{}.toml", name)),
+                format!(
+                    "name = \"{}\"\nversion = \"1.0.0\"\n\
+                     api-version = {{ major = 0, minor = 21, patch = 0 }}\n\
+                     source = \"main.fsx\"\nexports = [\"health\"]\n\
+                     isolation = \"{}\"\n",
+                    name, isolation
+                ),
+            )
+            .unwrap();
+        }
+
+        // Answers init, then fails every call
+        let worker = IsolationConfig::new("/bin/sh").with_args([
+            "-c",
+            r#"read line; echo '{"ok":null}'; while read line; do echo '{"error":"db down"}'; done"#,
+        ]);
+        let runtime = PluginRuntime::new(
+            RuntimeConfig::new().with_loader(LoaderConfig::new().with_isolation(worker)),
+        )
+        .unwrap();
+        runtime.load_manifest(dir.path().join("db.toml")).unwrap();
+        runtime.load_manifest(dir.path().join("web.toml")).unwrap();
+        let events = runtime.subscribe();
+
+        let report = runtime.health_check_all();
+        assert_eq!(report.len(), 2);
+        assert!(!report[0].status.is_healthy());
+        assert_eq!(report[1].status, HealthStatus::Healthy);
+
+        assert_eq!(runtime.get("db").unwrap().state(), LifecycleState::Error);
+        assert!(matches!(
+            events.try_recv(),
+            Ok(LifecycleEvent::Unhealthy { ref name, .. }) if name == "db"
+        ));
+        assert!(matches!(
+            events.try_recv(),
+            Ok(LifecycleEvent::Error { .. })
+        ));
+
+        // Plugins in the error state are left to the supervisor
+        assert_eq!(runtime.health_check_all().len(), 1);
+        assert!(matches!(
+            runtime.health_check("missing"),
+            Err(Error::PluginNotFound(_))
+        ));
+    }
+
+    #[cfg(all(unix, feature = "isolation"))]
+    #[test]
+    fn test_shutdown_with_timeout() {
+        use crate::isolation::IsolationConfig;
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("main.fsx"), "let cleanup () = ()").unwrap();
+        for (name, isolation) in [("quick", "in-process"), ("stuck", "process")] {
+            std::fs::write(
+                dir.path().join(format!("{}.toml", name)),
+                format!(
+                    "name = \"{}\"\nversion = \"1.0.0\"\n\
+                     api-version = {{ major = 0, minor = 21, patch = 0 }}\n\
+                     source = \"main.fsx\"\nexports = [\"cleanup\"]\n\
+                     isolation = \"{}\"\n",
+                    name, isolation
+                ),
+            )
+            .unwrap();
+        }
+
+        // Answers init, then never answers the cleanup call
+        let worker = IsolationConfig::new("/bin/sh")
+            .with_args(["-c", r#"read line; echo '{"ok":null}'; sleep 5"#]);
+        let runtime = PluginRuntime::new(
+            RuntimeConfig::new().with_loader(LoaderConfig::new().with_isolation(worker)),
+        )
+        .unwrap();
+        runtime
+            .load_manifest(dir.path().join("quick.toml"))
+            .unwrap();
+        runtime
+            .load_manifest(dir.path().join("stuck.toml"))
+            .unwrap();
+
+        let events = runtime.subscribe();
+        let started = Instant::now();
+        let report = runtime.shutdown_with_timeout(Duration::from_millis(200));
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert_eq!(
+            report.plugins,
+            vec![
+                ("quick".to_string(), PluginShutdownOutcome::Stopped),
+                ("stuck".to_string(), PluginShutdownOutcome::Killed),
+            ]
+        );
+        assert!(!report.is_clean());
+        assert_eq!(runtime.plugin_count(), 0);
+
+        // The killed worker lets the straggler unload well before its sleep
+        let mut seen = Vec::new();
+        while let Ok(event) = events.recv_timeout(Duration::from_secs(2)) {
+            seen.push((event.event_name(), event.plugin_name().to_string()));
+            if seen.len() == 4 {
+                break;
+            }
+        }
+        seen.sort();
+        assert_eq!(
+            seen,
+            vec![
+                ("stopped", "quick".to_string()),
+                ("stopped", "stuck".to_string()),
+                ("unloaded", "quick".to_string()),
+                ("unloaded", "stuck".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shutdown_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let runtime = PluginRuntime::new(RuntimeConfig::new()).unwrap();
+        for name in ["a", "b"] {
+            let path = dir.path().join(format!("{}.fsx", name));
+            std::fs::write(&path, "let x = 1").unwrap();
+            runtime.load_source(&path).unwrap();
+        }
+        runtime.stop("b").unwrap();
+
+        let events = runtime.subscribe();
+        runtime.shutdown();
+        let mut seen: Vec<_> = events
+            .try_iter()
+            .map(|event| (event.event_name(), event.plugin_name().to_string()))
+            .collect();
+        seen.sort();
+        assert_eq!(
+            seen,
+            vec![
+                ("stopped", "a".to_string()),
+                ("unloaded", "a".to_string()),
+                ("unloaded", "b".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_runtime_tenant_activation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("shared.fsx");
+        std::fs::write(&path, "let x = 1").unwrap();
+
+        let runtime = PluginRuntime::new(
+            RuntimeConfig::new().with_tenant_policy("a", TenantPolicy::new().deny_all("fs:read")),
+        )
+        .unwrap();
+        runtime.load_source(&path).unwrap();
+
+        let activation = runtime.activate("a", "shared").unwrap();
+        assert_eq!(activation.tenant(), "a");
+        assert!(runtime
+            .activate("b", "shared")
+            .unwrap()
+            .restricted()
+            .is_empty());
+        assert!(runtime.activate("a", "missing").is_err());
+        assert!(runtime
+            .audit_log()
+            .records()
+            .iter()
+            .any(|r| r.principal == "shared@a" && r.operation == "activate"));
+    }
+
+    #[test]
+    fn test_runtime_prewarm() {
+        let dir = tempfile::tempdir().unwrap();
+        let runtime = PluginRuntime::new(RuntimeConfig::new().with_prewarm(1)).unwrap();
+        for name in ["hot", "cold"] {
+            let path = dir.path().join(format!("{}.fsx", name));
+            std::fs::write(&path, "let x = 1").unwrap();
+            runtime.load_source(&path).unwrap();
+        }
+        for _ in 0..3 {
+            runtime.call("hot", "main", &[]).unwrap();
+        }
+        runtime.call("cold", "main", &[]).unwrap();
+
+        let profile = runtime.call_profile();
+        assert_eq!(profile.total("hot"), 3);
+        assert_eq!(profile.hottest(1), vec!["hot"]);
+
+        let mut saved = CallProfile::new();
+        saved.record("cold", "main", 10);
+        saved.record("gone", "main", 99);
+        let warmed = runtime.prewarm(&saved, 2);
+        assert_eq!(warmed.len(), 1);
+        assert_eq!(warmed[0].0, "cold");
+        assert!(warmed[0].1.is_ok());
+
+        runtime.reload("hot").unwrap();
+    }
+
+    #[test]
+    fn test_runtime_prewarm_at_startup() {
+        let mut saved = CallProfile::new();
+        saved.record("hot", "main", 10);
+        saved.record("warm", "main", 5);
+        saved.record("cold", "main", 1);
+        let runtime = PluginRuntime::new(
+            RuntimeConfig::new()
+                .with_prewarm(2)
+                .with_prewarm_profile(saved),
+        )
+        .unwrap();
+
+        let warmups: Vec<Arc<std::sync::atomic::AtomicU64>> = ["hot", "warm", "cold"]
+            .iter()
+            .map(|name| {
+                let count = Arc::new(std::sync::atomic::AtomicU64::new(0));
+                let counter = count.clone();
+                let builtin = BuiltinPlugin::new("1.0.0")
+                    .with_function("main", |_| Ok(fusabi_host::Value::Null))
+                    .with_function("warmup", move |_| {
+                        counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                        Ok(fusabi_host::Value::Null)
+                    });
+                runtime.register_builtin(*name, builtin).unwrap();
+                count
+            })
+            .collect();
+
+        // The two hottest plugins of the saved profile are warmed as they load
+        let counts: Vec<u64> = warmups
+            .iter()
+            .map(|c| c.load(std::sync::atomic::Ordering::SeqCst))
+            .collect();
+        assert_eq!(counts, [1, 1, 0]);
+        assert_eq!(runtime.call_profile().total("hot"), 0);
+    }
+
+    #[test]
+    fn test_runtime_cancellation() {
+        let dir = tempfile::tempdir().unwrap();
+        let ops: Vec<_> = ["one", "two", "three"]
+            .iter()
+            .map(|name| {
+                let path = dir.path().join(format!("{}.fsx", name));
+                std::fs::write(&path, "let x = 1").unwrap();
+                BulkOp::load(path)
+            })
+            .collect();
+
+        let runtime = PluginRuntime::default_config().unwrap();
+        let token = CancellationToken::new();
+        let report = runtime.bulk_cancellable(ops, BulkPolicy::default(), &token, |p| {
+            if p.completed == 1 {
+                token.cancel();
+            }
+        });
+
+        assert_eq!(report.succeeded(), 1);
+        assert_eq!(report.cancelled(), 2);
+        assert!(report.is_cancelled());
+        assert_eq!(runtime.plugin_count(), 1);
+
+        let results = runtime.reload_all_cancellable(&token);
+        assert!(matches!(results[..], [Err(Error::Cancelled(_))]));
+    }
+
+    #[cfg(feature = "metrics-prometheus")]
+    #[test]
+    fn test_runtime_records_metrics() {
+        use crate::metrics::MetricsConfig;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("counted.fsx");
+        std::fs::write(&path, "let x = 1").unwrap();
+
+        let config =
+            RuntimeConfig::new().with_metrics(PluginMetrics::new(MetricsConfig::default()));
+        let runtime = PluginRuntime::new(config).unwrap();
+        runtime.load_source(&path).unwrap();
+        runtime.call("counted", "main", &[]).unwrap();
+        runtime.unload("counted").unwrap();
+
+        let metrics = runtime.metrics().unwrap();
+        assert_eq!(metrics.plugins_loaded_total(), 1);
+        assert_eq!(metrics.plugins_unloaded_total(), 1);
+        assert_eq!(metrics.plugin_errors_total(), 0);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_runtime_commands() {
+        use crate::cli::{CliArg, CliArgKind};
+        use crate::manifest::ManifestBuilder;
+        use crate::plugin::Plugin;
+
+        let runtime = PluginRuntime::default_config().unwrap();
+        for name in ["reports", "exports"] {
+            let manifest = ManifestBuilder::new(name, "1.0.0")
+                .source("test.fsx")
+                .export("run")
+                .command(
+                    CliCommand::new("report", "run")
+                        .with_arg(CliArg::new("limit", CliArgKind::Int).required()),
+                )
+                .build()
+                .unwrap();
+            let plugin = Plugin::new(manifest);
+            plugin
+                .initialize(fusabi_host::EngineConfig::default())
+                .unwrap();
+            plugin.start().unwrap();
+            runtime
+                .registry
+                .register(PluginHandle::new(plugin))
+                .unwrap();
+        }
+
+        let commands = runtime.commands();
+        assert_eq!(commands.len(), 2);
+        assert_eq!(commands[0].0, "exports");
+
+        let args = vec!["--limit".to_string(), "5".to_string()];
+        assert!(matches!(
+            runtime.run_command("report", &args),
+            Err(Error::InvalidArguments(_))
+        ));
+        runtime.run_command("reports:report", &args).unwrap();
+        assert!(runtime.run_command("reports:report", &[]).is_err());
+        assert!(matches!(
+            runtime.run_command("missing", &[]),
+            Err(Error::CommandNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_runtime_stats() {
+        let runtime = PluginRuntime::default_config().unwrap();
+        let stats = runtime.stats();
+
+        assert_eq!(stats.total, 0);
+        assert_eq!(stats.running, 0);
+    }
+
+    #[cfg(feature = "watch")]
+    #[test]
+    fn test_runtime_hot_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let runtime = PluginRuntime::new(RuntimeConfig::new().with_plugin_dir(dir.path())).unwrap();
+
+        runtime.enable_hot_reload(WatchConfig::new()).unwrap();
+        assert!(runtime.is_hot_reload_enabled());
+        assert_eq!(runtime.hot_reload_stats().unwrap().events_received, 0);
+        // The watcher's flush task is supervised by the runtime
+        let flusher = |name: &String| name.starts_with("plugin-watcher-");
+        assert!(runtime.tasks().names().iter().any(flusher));
+
+        runtime.disable_hot_reload();
+        assert!(!runtime.is_hot_reload_enabled());
+        assert!(!runtime.tasks().names().iter().any(flusher));
+    }
+
+    #[test]
+    fn test_runtime_update_checker_shares_tasks() {
+        struct NoReleases;
+
+        impl UpdateSource for NoReleases {
+            fn latest(
+                &self,
+                _plugin: &str,
+                _network: &NetworkConfig,
+            ) -> Result<Option<crate::update::Release>> {
+                Ok(None)
+            }
+
+            fn fetch(
+                &self,
+                plugin: &str,
+                _release: &crate::update::Release,
+                _network: &NetworkConfig,
+            ) -> Result<PathBuf> {
+                Err(Error::plugin_not_found(plugin))
+            }
+        }
+
+        let runtime = PluginRuntime::default_config().unwrap();
+        runtime
+            .spawn_health_checks(Duration::from_secs(60))
+            .unwrap();
+        let checker = runtime.update_checker(UpdateConfig::default(), Arc::new(NoReleases));
+        checker.start().unwrap();
+        assert!(runtime.tasks().is_running("update-checks"));
+
+        // Stopping the checker leaves the runtime's other tasks running
+        checker.stop();
+        assert!(!runtime.tasks().is_running("update-checks"));
+        assert!(runtime.tasks().is_running("health-checks"));
+
+        checker.start().unwrap();
+        drop(checker);
+        assert!(!runtime.tasks().is_running("update-checks"));
+    }
+
+    #[test]
+    fn test_runtime_offline_mode() {
+        let runtime = PluginRuntime::new(RuntimeConfig::new().with_offline(true)).unwrap();
+        assert!(runtime.is_offline());
+
+        let switch = runtime.offline_switch().clone();
+        assert!(matches!(
+            switch.ensure_online("index client"),
+            Err(Error::OfflineMode(_))
+        ));
+
+        runtime.set_offline(false);
+        assert!(switch.ensure_online("index client").is_ok());
+    }
+
+    #[test]
+    fn test_runtime_transition_veto() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ledger.fsx");
+        std::fs::write(&path, "let x = 1").unwrap();
+
+        let runtime = PluginRuntime::default_config().unwrap();
+        runtime.load_source(&path).unwrap();
+
+        let busy = Arc::new(std::sync::atomic::AtomicBool::new(true));
+        let guard = busy.clone();
+        runtime.before_stop(move |_| {
+            if guard.load(std::sync::atomic::Ordering::SeqCst) {
+                Err(Error::invalid_state("idle", "open transaction"))
+            } else {
+                Ok(())
+            }
+        });
+        runtime.before_reload(|name| Err(Error::invalid_state("unpinned", name)));
+
+        assert!(runtime.stop("ledger").is_err());
+        assert!(runtime.stop_all()[0].is_err());
+        assert!(runtime.reload("ledger").is_err());
+        assert_eq!(
+            runtime.get("ledger").unwrap().state(),
+            LifecycleState::Running
+        );
+
+        busy.store(false, std::sync::atomic::Ordering::SeqCst);
+        runtime.stop("ledger").unwrap();
+    }
+
+    #[test]
+    fn test_transition_guard_registers_hooks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ledger.fsx");
+        std::fs::write(&path, "let x = 1").unwrap();
+
+        let runtime = Arc::new(PluginRuntime::default_config().unwrap());
+        runtime.load_source(&path).unwrap();
+
+        // Guards run without the hooks locked, so they may add hooks
+        let weak = Arc::downgrade(&runtime);
+        runtime.before_start(move |_| {
+            if let Some(runtime) = weak.upgrade() {
+                runtime.before_stop(|name| Err(Error::invalid_state("idle", name)));
+            }
+            Ok(())
+        });
+
+        runtime.stop("ledger").unwrap();
+        runtime.start("ledger").unwrap();
+        assert!(runtime.stop("ledger").is_err());
+    }
+
+    #[test]
+    fn test_runtime_restart_policy() {
+        use fusabi_host::Value;
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let runtime = PluginRuntime::default_config().unwrap();
+        let failing = Arc::new(AtomicBool::new(true));
+        let fail = failing.clone();
+        let builtin = BuiltinPlugin::new("1.0.0").with_function("run", move |_| {
+            if fail.load(Ordering::SeqCst) {
+                Err(Error::execution_failed("boom"))
+            } else {
+                Ok(Value::Int(1))
+            }
+        });
+        let plugin = runtime.register_builtin("flaky", builtin).unwrap();
+        runtime.set_restart_policy(
+            "flaky",
+            RestartPolicy::always(Duration::from_millis(20), Duration::from_millis(20)),
+        );
+
+        let events = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let recorded = events.clone();
+        let (started, restarts) = std::sync::mpsc::channel();
+        runtime.on_event(move |e| {
+            recorded.lock().push(e.event_name());
+            if e.event_name() == "started" {
+                let _ = started.send(());
+            }
+        });
+        let wait_restart = || restarts.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        // The failed call returns its error; the restart follows its delay
+        let failed_at = Instant::now();
+        let err = runtime.call("flaky", "run", &[]).unwrap_err();
+        assert!(matches!(err.root(), Error::ExecutionFailed(_)));
+        wait_restart();
+        assert!(failed_at.elapsed() >= Duration::from_millis(20));
+        assert_eq!(plugin.state(), LifecycleState::Running);
+        assert_eq!(runtime.supervisor().restart_count("flaky"), 1);
+        assert_eq!(events.lock().as_slice(), ["error", "started"]);
+        assert!(runtime.tasks().is_running(RESTART_TASK));
+
+        // Restarts without backoff also run on the task
+        runtime.set_restart_policy("flaky", RestartPolicy::on_failure(2));
+        let err = runtime.call("flaky", "run", &[]).unwrap_err();
+        assert!(matches!(err.root(), Error::ExecutionFailed(_)));
+        wait_restart();
+        assert_eq!(plugin.state(), LifecycleState::Running);
+        assert_eq!(runtime.supervisor().restart_count("flaky"), 2);
+
+        // Retries are exhausted, so the plugin stays failed
+        runtime.call("flaky", "run", &[]).unwrap_err();
+        assert_eq!(plugin.state(), LifecycleState::Error);
+        assert_eq!(runtime.supervisor().restart_at("flaky"), None);
+        failing.store(false, Ordering::SeqCst);
+        let err = runtime.call("flaky", "run", &[]).unwrap_err();
+        assert!(matches!(err.root(), Error::InvalidState { .. }));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_runtime_rollback() {
+        use crate::artifact_store::ArtifactStore;
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("main.fsx"), "let x = 1").unwrap();
+        let manifest = dir.path().join("plugin.toml");
+        let install = |version: &str, extra: &str| {
+            std::fs::write(
+                &manifest,
+                format!(
+                    "name = \"search\"\nversion = \"{}\"\n\
+                     api-version = {{ major = 0, minor = 21, patch = 0 }}\n\
+                     source = \"main.fsx\"\n{}",
+                    version, extra
+                ),
+            )
+            .unwrap();
+        };
+
+        let store = ArtifactStore::open(dir.path().join("store")).unwrap();
+        let runtime = PluginRuntime::new(
+            RuntimeConfig::new()
+                .with_loader(LoaderConfig::new().with_artifact_store(store))
+                .with_restart_policy(RestartPolicy::on_failure(5))
+                .with_rollback_after(1),
+        )
+        .unwrap();
+        install("1.0.0", "");
+        let plugin = runtime.load_manifest(&manifest).unwrap();
+        if plugin.state() != LifecycleState::Running {
+            plugin.inner().start().unwrap();
+        }
+        assert!(matches!(
+            runtime.rollback("search"),
+            Err(Error::RollbackFailed(_))
+        ));
+        runtime.call("search", "main", &[]).unwrap();
+
+        install("1.1.0", "");
+        runtime.reload("search").unwrap();
+        assert_eq!(runtime.rollback("search").unwrap(), "1.0.0");
+        assert_eq!(plugin.version(), "1.0.0");
+
+        // A version that keeps failing after a restart is rolled back
+        runtime.reload("search").unwrap();
+        let (started, restarts) = std::sync::mpsc::channel();
+        runtime.on_event(move |e| {
+            if e.event_name() == "started" {
+                let _ = started.send(());
+            }
+        });
+        runtime
+            .recovery
+            .handle_call_failure(&plugin, &Error::execution_failed("boom"));
+        restarts.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(plugin.version(), "1.1.0");
+        runtime
+            .recovery
+            .handle_call_failure(&plugin, &Error::execution_failed("boom"));
+        assert_eq!(plugin.version(), "1.0.0");
+        assert_eq!(plugin.state(), LifecycleState::Running);
+
+        // State written in a newer format blocks the rollback
+        install("1.2.0", "[metadata]\nstate-version = \"2\"\n");
+        runtime.reload("search").unwrap();
+        let err = runtime.rollback("search").unwrap_err();
+        assert!(err.to_string().contains("state version 2"));
+        assert_eq!(plugin.version(), "1.2.0");
+    }
+
+    #[test]
+    fn test_runtime_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("search.fsx"), "let x = 1").unwrap();
+        let runtime = PluginRuntime::default_config().unwrap();
+        let plugin = runtime.load_source(dir.path().join("search.fsx")).unwrap();
+        let reloads = plugin.info().reload_count;
+
+        let mut config = fusabi_host::EngineConfig::default();
+        config.limits.max_call_depth = 7;
+        runtime.set_engine_config(config);
+        runtime.restart().unwrap();
+
+        let engine_config = plugin.inner().engine_config().unwrap();
+        assert_eq!(engine_config.limits.max_call_depth, 7);
+        assert_eq!(plugin.info().reload_count, reloads + 1);
+        assert_eq!(plugin.state(), LifecycleState::Running);
+        runtime.call("search", "main", &[]).unwrap();
+    }
+
+    #[test]
+    fn test_runtime_restart_within_call() {
+        use fusabi_host::Value;
+        use std::sync::OnceLock;
+
+        let runtime = Arc::new(PluginRuntime::default_config().unwrap());
+        let shared: Arc<OnceLock<Weak<PluginRuntime>>> = Arc::new(OnceLock::new());
+        let inner = Arc::clone(&shared);
+        let builtin = BuiltinPlugin::new("1.0.0").with_function("restart", move |_| {
+            let runtime = inner.get().and_then(Weak::upgrade).unwrap();
+            let refused = matches!(runtime.restart(), Err(Error::InvalidState { .. }));
+            Ok(Value::Bool(refused))
+        });
+        shared.set(Arc::downgrade(&runtime)).unwrap();
+        let plugin = runtime.register_builtin("admin", builtin).unwrap();
+
+        // Restarting would wait for the call making it
+        let refused = runtime.call("admin", "restart", &[]).unwrap();
+        assert!(matches!(refused, Value::Bool(true)));
+        let refused = plugin.call("restart", &[]).unwrap();
+        assert!(matches!(refused, Value::Bool(true)));
+        runtime.restart().unwrap();
+    }
+
+    #[test]
+    fn test_runtime_register_builtin() {
+        use fusabi_host::Value;
+
+        let runtime = PluginRuntime::default_config().unwrap();
+        let builtin = BuiltinPlugin::new("1.0.0").with_function("double", |args| match args {
+            [Value::Int(n)] => Ok(Value::Int(n * 2)),
+            _ => Err(Error::invalid_arguments("expected an int")),
+        });
+        let plugin = runtime.register_builtin("math", builtin).unwrap();
+
+        assert!(plugin.inner().is_builtin());
+        assert_eq!(plugin.state(), LifecycleState::Running);
+        assert_eq!(plugin.info().origin, PluginOrigin::Builtin);
+        assert!(runtime.get("math").is_some());
+        let doubled = runtime.call("math", "double", &[Value::Int(21)]).unwrap();
+        assert!(matches!(doubled, Value::Int(42)));
+        assert!(runtime.call("math", "double", &[]).is_err());
+
+        runtime.restart().unwrap();
+        assert!(runtime.call("math", "double", &[Value::Int(1)]).is_ok());
+    }
+
+    #[test]
+    fn test_runtime_errors_in_plugin() {
+        use fusabi_host::Value;
+
+        let runtime = PluginRuntime::default_config().unwrap();
+        let builtin = BuiltinPlugin::new("1.0.0")
+            .with_function("fail", |_| Err(Error::execution_failed("boom")));
+        runtime.register_builtin("flaky", builtin).unwrap();
+
+        let err = runtime.call("flaky", "fail", &[Value::Null]).unwrap_err();
+        assert_eq!(err.plugin(), Some("flaky"));
+        assert_eq!(err.operation(), Some(PluginOperation::Call));
+        assert!(matches!(err.root(), Error::ExecutionFailed(_)));
+        assert_eq!(
+            err.to_string(),
+            "call failed for plugin flaky: plugin execution failed: boom"
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("absent.fsx");
+        let err = runtime.load_source(&path).unwrap_err();
+        assert_eq!(err.plugin(), Some("absent"));
+        assert_eq!(err.operation(), Some(PluginOperation::Load));
+        assert_eq!(err.file(), Some(path.as_path()));
+
+        // Errors that already name the plugin are left as they are
+        assert!(matches!(
+            runtime.call("absent", "main", &[]),
+            Err(Error::PluginNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_runtime_rate_limits() {
+        use fusabi_host::Value;
+
+        let runtime = PluginRuntime::new(
+            RuntimeConfig::new()
+                .with_plugin_rate_limit("clock", RateLimit::per_minute(3).unwrap())
+                .with_function_rate_limit("clock", "tick", RateLimit::per_minute(1).unwrap()),
+        )
+        .unwrap();
+        let builtin = BuiltinPlugin::new("1.0.0")
+            .with_function("tick", |_| Ok(Value::Null))
+            .with_function("now", |_| Ok(Value::Null));
+        runtime.register_builtin("clock", builtin).unwrap();
+
+        runtime.call("clock", "tick", &[]).unwrap();
+        assert!(matches!(
+            runtime.call("clock", "tick", &[]),
+            Err(Error::RateLimited { ref key, .. }) if key == "clock.tick"
+        ));
+        runtime.call("clock", "now", &[]).unwrap();
+        runtime.call("clock", "now", &[]).unwrap();
+        assert!(matches!(
+            runtime.call("clock", "now", &[]),
+            Err(Error::RateLimited { ref key, .. }) if key == "clock"
+        ));
+
+        let invalid = RuntimeConfig::new().with_plugin_rate_limit(
+            "clock",
+            RateLimit {
+                burst: 0,
+                period: Duration::from_secs(1),
+            },
+        );
+        let Err(Error::Config(errors)) = invalid.validate() else {
+            panic!("expected a config error");
+        };
+        assert_eq!(errors.paths(), vec!["plugin_rate_limits.clock.burst"]);
+    }
+
+    #[test]
+    fn test_runtime_call_retry() {
+        use crate::retry::RetryPolicy;
+        use fusabi_host::Value;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let policy = RetryPolicy::new(3, Duration::from_millis(1))
+            .with_retry_on(|e| matches!(e.root(), Error::ExecutionFailed(_)));
+        let runtime = PluginRuntime::new(
+            RuntimeConfig::new().with_call_options(CallOptions::new().with_retry(policy)),
+        )
+        .unwrap();
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let counter = attempts.clone();
+        let builtin = BuiltinPlugin::new("1.0.0").with_function("flaky", move |_| {
+            match counter.fetch_add(1, Ordering::SeqCst) {
+                0 => Err(Error::execution_failed("transient")),
+                _ => Ok(Value::Int(1)),
+            }
+        });
+        runtime.register_builtin("flaky", builtin).unwrap();
+
+        assert!(matches!(
+            runtime.call("flaky", "flaky", &[]).unwrap(),
+            Value::Int(1)
+        ));
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+
+        // Options passed to the call replace the configured ones
+        attempts.store(0, Ordering::SeqCst);
+        assert!(runtime
+            .call_with_options("flaky", "flaky", &[], &CallOptions::new())
+            .is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_runtime_install_from_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = concat!(
+            "name = \"greeter\"\nversion = \"1.2.0\"\nsource = \"main.fsx\"\n",
+            "api-version = { major = 0, minor = 21, patch = 0 }\n",
+        );
+        let source = "let main = 1";
+        std::fs::create_dir(dir.path().join("files")).unwrap();
+        std::fs::write(dir.path().join("files/plugin.toml"), manifest).unwrap();
+        std::fs::write(dir.path().join("files/main.fsx"), source).unwrap();
+        let digest = crate::crypto::sha256_hex;
+        std::fs::write(
+            dir.path().join("index.toml"),
+            format!(
+                "[[plugin]]\nname = \"greeter\"\n\n[[plugin.version]]\nversion = \"1.2.0\"\n\n\
+                 [[plugin.version.file]]\npath = \"plugin.toml\"\nurl = \"files/plugin.toml\"\nsha256 = \"{}\"\n\n\
+                 [[plugin.version.file]]\npath = \"main.fsx\"\nurl = \"files/main.fsx\"\nsha256 = \"{}\"\n",
+                digest(manifest.as_bytes()),
+                digest(source.as_bytes())
+            ),
+        )
+        .unwrap();
+
+        let index =
+            RegistryIndex::local(dir.path().join("index.toml"), dir.path().join("installed"));
+        // Setting the loader afterwards keeps the install directory's origin
+        let runtime = PluginRuntime::new(
+            RuntimeConfig::new()
+                .with_registry_index(index)
+                .with_loader(LoaderConfig::new())
+                .with_network(NetworkConfig::new().with_retries(0, Duration::ZERO)),
+        )
+        .unwrap();
+        let index = runtime.config().registry_index.as_ref().unwrap();
+        assert_eq!(index.network().max_retries, 0);
+        assert!(matches!(
+            runtime.install_from_index("greeter", "^2"),
+            Err(Error::Registry(_))
+        ));
+
+        let plugin = runtime.install_from_index("greeter", "^1.2").unwrap();
+        assert_eq!(plugin.version(), "1.2.0");
+        assert_eq!(plugin.info().origin, PluginOrigin::Remote);
+        assert!(dir
+            .path()
+            .join("installed/greeter/1.2.0/main.fsx")
+            .is_file());
+
+        runtime.offline_switch().set_offline(true);
+        assert!(matches!(
+            runtime.install_from_index("greeter", "*"),
+            Err(Error::OfflineMode(_))
+        ));
+    }
+
+    #[test]
+    fn test_runtime_execution_budget() {
+        use crate::lifecycle::LifecycleEvent;
+        use fusabi_host::Value;
+
+        let runtime = PluginRuntime::default_config().unwrap();
+        let builtin = BuiltinPlugin::new("1.0.0").with_function("tick", |_| Ok(Value::Null));
+        runtime.register_builtin("clock", builtin).unwrap();
+        runtime.set_budget(
+            "clock",
+            ExecutionBudget::new().with_calls(2, Duration::from_secs(60)),
+        );
+        let events = runtime.subscribe();
+
+        runtime.call("clock", "tick", &[]).unwrap();
+        runtime.call("clock", "tick", &[]).unwrap();
+        assert!(matches!(
+            runtime.call("clock", "tick", &[]),
+            Err(Error::BudgetExceeded { ref budget, .. }) if budget == "calls"
+        ));
+        assert!(runtime.budget_usage("clock").throttled);
+        assert!(events
+            .try_iter()
+            .any(|e| matches!(e, LifecycleEvent::BudgetExceeded { .. })));
+
+        // Calls through a handle are held to the same budget
+        let handle = runtime.get("clock").unwrap();
+        assert!(matches!(
+            handle.call("tick", &[]),
+            Err(Error::BudgetExceeded { .. })
+        ));
+
+        // Queued calls wait for the window instead of failing
+        runtime.set_budget(
+            "clock",
+            ExecutionBudget::new()
+                .with_calls(1, Duration::from_millis(50))
+                .with_queueing(Duration::from_secs(5)),
+        );
+        runtime.call("clock", "tick", &[]).unwrap();
+        runtime.call("clock", "tick", &[]).unwrap();
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_runtime_autoscale() {
+        use crate::lifecycle::LifecycleEvent;
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("resize.fsx"), "let x = 1").unwrap();
+        std::fs::write(
+            dir.path().join("resize.toml"),
+            "name = \"resize\"\nversion = \"1.0.0\"\nsource = \"resize.fsx\"\n\
+             stateless = true\n\
+             api-version = { major = 0, minor = 21, patch = 0 }\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("counter.fsx"), "let x = 1").unwrap();
+
+        let runtime =
+            PluginRuntime::new(RuntimeConfig::new().with_autoscale(AutoscalePolicy::new(2, 4)))
+                .unwrap();
+        let events = runtime.subscribe();
+        runtime
+            .load_manifest(dir.path().join("resize.toml"))
+            .unwrap();
+        assert_eq!(runtime.pool_signals("resize").unwrap().engines, 1);
+
+        runtime.call("resize", "main", &[]).unwrap();
+        assert_eq!(runtime.pool_signals("resize").unwrap().engines, 2);
+        assert!(events.try_iter().any(|e| matches!(
+            e,
+            LifecycleEvent::PoolScaled { ref name, signals, .. }
+                if name == "resize" && signals.engines == 2
+        )));
+        assert_eq!(runtime.scale_pools(), 0);
+
+        // Stateful plugins are not pooled
+        runtime.load_source(dir.path().join("counter.fsx")).unwrap();
+        assert!(runtime.pool_signals("counter").is_none());
+        assert!(runtime
+            .set_autoscale("counter", AutoscalePolicy::default())
+            .is_err());
+    }
+
+    #[test]
+    fn test_runtime_call_sampling() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["search", "billing"] {
+            std::fs::write(dir.path().join(format!("{}.fsx", name)), "let x = 1").unwrap();
+        }
+        let runtime = PluginRuntime::new(
+            RuntimeConfig::new()
+                .with_call_sampling(CallSampling::new().with_rate(0.5).with_plugin("billing")),
+        )
+        .unwrap();
+        runtime.load_source(dir.path().join("search.fsx")).unwrap();
+        runtime.load_source(dir.path().join("billing.fsx")).unwrap();
+
+        for _ in 0..4 {
+            runtime.call("search", "main", &[]).unwrap();
+        }
+        runtime.call("billing", "main", &[]).unwrap();
+
+        let records = runtime.call_log().records();
+        assert_eq!(records.len(), 3);
+        let call = records[2].call.as_ref().unwrap();
+        assert_eq!(call.plugin, "billing");
+        assert_eq!(call.export, "main");
+        assert_eq!(records[2].target.as_deref(), Some("billing.main"));
+        assert_eq!(records[2].outcome, AuditOutcome::Succeeded);
+
+        // Calls through handles are sampled too
+        let handle = runtime.get("billing").unwrap().as_principal("ops");
+        handle.call("main", &[]).unwrap();
+        let records = runtime.call_log().records();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[3].principal, "ops");
+
+        // Sampled calls are kept apart from capability records
+        assert!(runtime
+            .audit_log()
+            .records()
+            .iter()
+            .all(|r| r.call.is_none()));
+        let runtime = PluginRuntime::new(
+            RuntimeConfig::new()
+                .with_audit_capacity(1)
+                .with_call_sampling(CallSampling::new().with_rate(1.0).with_capacity(2)),
+        )
+        .unwrap();
+        runtime.load_source(dir.path().join("search.fsx")).unwrap();
+        let audited = runtime.audit_log().records();
+        for _ in 0..3 {
+            runtime.call("search", "main", &[]).unwrap();
+        }
+        assert_eq!(runtime.audit_log().records(), audited);
+        assert_eq!(runtime.call_log().len(), 2);
+    }
+
+    #[test]
+    fn test_runtime_call_sensitive() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("billing.fsx"), "let x = 1").unwrap();
+        let runtime = PluginRuntime::new(
+            RuntimeConfig::new().with_call_sampling(CallSampling::new().with_plugin("billing")),
+        )
+        .unwrap();
+        runtime.load_source(dir.path().join("billing.fsx")).unwrap();
+
+        let args = [
+            CallArg::from(fusabi_host::Value::Int(7)),
+            CallArg::from(crate::Sensitive::new(fusabi_host::Value::String(
+                "hunter2".into(),
+            ))),
+        ];
+        runtime.call_sensitive("billing", "main", &args).unwrap();
+
+        let records = runtime.call_log().records();
+        let record = records.last().unwrap();
+        assert_eq!(
+            record.call.as_ref().unwrap().args,
+            format!("{}, <redacted>", fusabi_host::Value::Int(7))
+        );
+        assert!(!format!("{:?}", record).contains("hunter2"));
+
+        // Errors echoing the secret, even through `{:?}`, are scrubbed
+        let builtin = BuiltinPlugin::new("1.0.0").with_function("charge", |args| {
+            Err(Error::execution_failed(format!("bad token {:?}", args[1])))
+        });
+        runtime.register_builtin("payments", builtin).unwrap();
+        let err = runtime
+            .call_sensitive("payments", "charge", &args)
+            .unwrap_err();
+        assert_eq!(
+            err.root().to_string(),
+            format!(
+                "plugin execution failed: bad token {:?}",
+                fusabi_host::Value::String("<redacted>".into())
+            )
+        );
+    }
+
+    #[test]
+    fn test_runtime_background_tasks() {
+        let runtime = PluginRuntime::default_config().unwrap();
+        runtime
+            .spawn_supervision(Duration::from_millis(10))
+            .unwrap();
+        assert!(runtime.tasks().is_running("supervision"));
+        assert_eq!(runtime.task_health()[0].name, "supervision");
+
+        let report = runtime.shutdown();
+        assert!(report.is_clean());
+        assert_eq!(report.stopped, vec!["supervision"]);
+        assert!(runtime.task_health().is_empty());
+    }
+
+    #[test]
+    fn test_runtime_plugin_storage() {
+        let runtime = PluginRuntime::default_config().unwrap();
+        assert!(matches!(
+            runtime.plugin_storage("missing"),
+            Err(Error::PluginNotFound(_))
+        ));
+
+        runtime.storage().set("shared", "key", "value").unwrap();
+        assert_eq!(runtime.storage().get("shared", "key").unwrap(), b"value");
+
+        // Plugins reach their namespace through host functions
+        let required: Vec<String> = crate::STORAGE_HOST_FUNCTIONS
+            .iter()
+            .map(|f| f.to_string())
+            .collect();
+        assert!(runtime.host_functions().missing(&required).is_empty());
     }
 }
@@ -0,0 +1,102 @@
+//! Plugin exports run periodically by the runtime.
+
+use std::time::Duration;
+
+use crate::error::{Error, Result};
+use crate::signature::ExportSpec;
+
+/// Export the runtime calls on a fixed interval.
+///
+/// ```toml
+/// [[schedule]]
+/// export = "refresh_index"
+/// every-secs = 300
+/// singleton = true
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ScheduledJob {
+    /// Exported function to call, without arguments.
+    pub export: String,
+    /// Seconds between runs.
+    #[cfg_attr(feature = "serde", serde(rename = "every-secs"))]
+    pub every_secs: u64,
+    /// Whether only the cluster leader runs the job.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub singleton: bool,
+}
+
+impl ScheduledJob {
+    /// Run an export every `interval`, rounded up to whole seconds.
+    pub fn new(export: impl Into<String>, interval: Duration) -> Self {
+        let every_secs = interval.as_secs() + u64::from(interval.subsec_nanos() > 0);
+        Self {
+            export: export.into(),
+            every_secs,
+            singleton: false,
+        }
+    }
+
+    /// Run the job on a single instance of a cluster.
+    pub fn singleton(mut self) -> Self {
+        self.singleton = true;
+        self
+    }
+
+    /// Get the interval between runs, at least one second.
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.every_secs.max(1))
+    }
+
+    /// Name of the lease held by the instance running a singleton job.
+    pub fn lease_key(&self, plugin: &str) -> String {
+        format!("job:{}:{}", plugin, self.export)
+    }
+
+    /// Check the job against the plugin's exports.
+    pub fn validate(&self, exports: &[ExportSpec]) -> Result<()> {
+        if !exports.is_empty() && !exports.iter().any(|e| *e == self.export) {
+            return Err(Error::invalid_manifest(format!(
+                "scheduled function {} is not exported",
+                self.export
+            )));
+        }
+        if self.every_secs == 0 {
+            return Err(Error::invalid_manifest(format!(
+                "scheduled function {} has a zero interval",
+                self.export
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scheduled_job_validation() {
+        let exports = vec![ExportSpec::new("refresh")];
+        let job = ScheduledJob::new("refresh", Duration::from_secs(60)).singleton();
+        assert!(job.validate(&exports).is_ok());
+        assert_eq!(job.lease_key("search"), "job:search:refresh");
+
+        assert!(ScheduledJob::new("missing", Duration::from_secs(60))
+            .validate(&exports)
+            .is_err());
+        assert!(ScheduledJob::new("refresh", Duration::ZERO)
+            .validate(&exports)
+            .is_err());
+
+        // Sub-second intervals never make the scheduler spin
+        let job = ScheduledJob::new("refresh", Duration::from_millis(200));
+        assert_eq!(job.interval(), Duration::from_secs(1));
+        let job = ScheduledJob {
+            every_secs: 0,
+            ..job
+        };
+        assert_eq!(job.interval(), Duration::from_secs(1));
+    }
+}
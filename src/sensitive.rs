@@ -0,0 +1,354 @@
+//! Call arguments that must not leak into logs, audit records or caches.
+
+use std::fmt;
+use std::sync::atomic::{compiler_fence, Ordering};
+
+use fusabi_host::Value;
+
+use crate::error::{Error, Result};
+
+/// Text shown in place of a sensitive value.
+pub(crate) const REDACTED: &str = "<redacted>";
+
+/// Shortest sensitive string redacted from error messages; replacing
+/// shorter ones would mangle unrelated text.
+const MIN_REDACTED_LEN: usize = 4;
+
+/// A value, such as a token or credential, that is redacted wherever the
+/// runtime renders it and whose buffers are zeroed when dropped.
+///
+/// Only strings, numbers, booleans and lists of them can be zeroed, so calls
+/// with other sensitive values are refused. Error messages are scrubbed of
+/// sensitive strings of at least four characters, as written or escaped;
+/// numbers, booleans and shorter strings are not, since they would match
+/// unrelated text.
+///
+/// Pass it to a plugin with
+/// [`PluginRuntime::call_sensitive`](crate::PluginRuntime::call_sensitive).
+#[derive(Clone)]
+pub struct Sensitive(Value);
+
+impl Sensitive {
+    /// Wrap a sensitive value.
+    pub fn new(value: Value) -> Self {
+        Self(value)
+    }
+
+    /// Get the wrapped value.
+    pub fn expose(&self) -> &Value {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Sensitive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Sensitive({})", REDACTED)
+    }
+}
+
+impl fmt::Display for Sensitive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl Drop for Sensitive {
+    fn drop(&mut self) {
+        zeroize_value(&mut self.0);
+    }
+}
+
+/// An argument of a call made with
+/// [`PluginRuntime::call_sensitive`](crate::PluginRuntime::call_sensitive).
+#[derive(Debug, Clone)]
+pub enum CallArg {
+    /// An ordinary value.
+    Plain(Value),
+    /// A value redacted from every rendering of the call.
+    Sensitive(Sensitive),
+}
+
+impl CallArg {
+    /// Check if the argument is sensitive.
+    pub fn is_sensitive(&self) -> bool {
+        matches!(self, Self::Sensitive(_))
+    }
+
+    /// Render the argument for logs and audit records.
+    pub(crate) fn render(&self) -> String {
+        match self {
+            Self::Plain(value) => value.to_string(),
+            Self::Sensitive(_) => REDACTED.to_string(),
+        }
+    }
+}
+
+impl From<Value> for CallArg {
+    fn from(value: Value) -> Self {
+        Self::Plain(value)
+    }
+}
+
+impl From<Sensitive> for CallArg {
+    fn from(value: Sensitive) -> Self {
+        Self::Sensitive(value)
+    }
+}
+
+/// Arguments of a single call, with the sensitive ones exposed.
+///
+/// Dropping it zeroes the exposed copies and everything collected to redact
+/// them.
+pub(crate) struct ExposedArgs {
+    values: Vec<Value>,
+    sensitive: Vec<bool>,
+    secrets: Vec<String>,
+}
+
+impl ExposedArgs {
+    /// Expose `args` for a call.
+    ///
+    /// Fails with [`Error::InvalidArguments`] if a sensitive value cannot be
+    /// zeroed.
+    pub(crate) fn new(args: &[CallArg]) -> Result<Self> {
+        if args.iter().any(|arg| match arg {
+            CallArg::Plain(_) => false,
+            CallArg::Sensitive(value) => !is_zeroizable(value.expose()),
+        }) {
+            return Err(Error::invalid_arguments(
+                "sensitive arguments must be strings, numbers, booleans or lists of them",
+            ));
+        }
+
+        let mut values = Vec::with_capacity(args.len());
+        let mut sensitive = Vec::with_capacity(args.len());
+        let mut secrets = Vec::new();
+        for arg in args {
+            match arg {
+                CallArg::Plain(value) => {
+                    values.push(value.clone());
+                    sensitive.push(false);
+                }
+                CallArg::Sensitive(value) => {
+                    collect_secrets(value.expose(), &mut secrets);
+                    values.push(value.expose().clone());
+                    sensitive.push(true);
+                }
+            }
+        }
+        // Redact longer secrets first so none is left half-replaced
+        secrets.sort_by_key(|s| std::cmp::Reverse(s.len()));
+        Ok(Self {
+            values,
+            sensitive,
+            secrets,
+        })
+    }
+
+    /// Get the argument values.
+    pub(crate) fn values(&self) -> &[Value] {
+        &self.values
+    }
+
+    /// Check if any argument is sensitive.
+    pub(crate) fn any_sensitive(&self) -> bool {
+        self.sensitive.contains(&true)
+    }
+
+    /// Replace every sensitive value in `text`.
+    pub(crate) fn redact(&self, text: &str) -> String {
+        let mut out = text.to_string();
+        for secret in &self.secrets {
+            if out.contains(secret.as_str()) {
+                let redacted = out.replace(secret.as_str(), REDACTED);
+                zeroize_string(&mut out);
+                out = redacted;
+            }
+        }
+        out
+    }
+
+    /// Redact sensitive values from the message of `error`.
+    pub(crate) fn redact_error(&self, error: Error) -> Error {
+        match error {
+            Error::ExecutionFailed(message) => Error::ExecutionFailed(self.redact(&message)),
+            Error::InvalidArguments(message) => Error::InvalidArguments(self.redact(&message)),
+            #[cfg(feature = "isolation")]
+            Error::Isolation(message) => Error::Isolation(self.redact(&message)),
+            Error::Located { span, source } => self.redact_error(*source).at(span),
+            Error::InPlugin {
+                name,
+                op,
+                file,
+                source,
+            } => Error::InPlugin {
+                name,
+                op,
+                file,
+                source: Box::new(self.redact_error(*source)),
+            },
+            other => other,
+        }
+    }
+}
+
+impl Drop for ExposedArgs {
+    fn drop(&mut self) {
+        for (value, sensitive) in self.values.iter_mut().zip(&self.sensitive) {
+            if *sensitive {
+                zeroize_value(value);
+            }
+        }
+        for secret in &mut self.secrets {
+            zeroize_string(secret);
+        }
+    }
+}
+
+/// Check if every buffer of `value` can be zeroed.
+fn is_zeroizable(value: &Value) -> bool {
+    match value {
+        Value::Null | Value::Bool(_) | Value::Int(_) | Value::Float(_) | Value::String(_) => true,
+        Value::List(items) => items.iter().all(is_zeroizable),
+        _ => false,
+    }
+}
+
+/// Collect the renderings of the strings in `value` that may show up in
+/// messages: as written, escaped by `{:?}` and escaped as a Fusabi literal.
+fn collect_secrets(value: &Value, out: &mut Vec<String>) {
+    match value {
+        Value::String(s) if s.chars().count() >= MIN_REDACTED_LEN => {
+            let mut renderings = vec![s.clone(), s.escape_debug().to_string()];
+            #[cfg(feature = "serde")]
+            {
+                let literal = crate::marshal::string_literal(s);
+                renderings.push(literal[1..literal.len() - 1].to_string());
+                let mut literal = literal;
+                zeroize_string(&mut literal);
+            }
+            for mut rendering in renderings {
+                if out.contains(&rendering) {
+                    zeroize_string(&mut rendering);
+                } else {
+                    out.push(rendering);
+                }
+            }
+        }
+        Value::List(items) => items.iter().for_each(|item| collect_secrets(item, out)),
+        _ => {}
+    }
+}
+
+/// Overwrite the contents of `value` with zeroes.
+pub(crate) fn zeroize_value(value: &mut Value) {
+    match value {
+        Value::String(s) => zeroize_string(s),
+        Value::List(items) => items.iter_mut().for_each(zeroize_value),
+        Value::Int(n) => {
+            // SAFETY: `n` is a valid, aligned reference
+            unsafe { std::ptr::write_volatile(n, 0) };
+        }
+        Value::Float(x) => {
+            // SAFETY: `x` is a valid, aligned reference
+            unsafe { std::ptr::write_volatile(x, 0.0) };
+        }
+        Value::Bool(b) => {
+            // SAFETY: `b` is a valid, aligned reference
+            unsafe { std::ptr::write_volatile(b, false) };
+        }
+        _ => {}
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// Overwrite the strings of `value` with zeroes.
+///
+/// Numbers are immutable in `serde_json` and left alone.
+#[cfg(feature = "serde")]
+pub(crate) fn zeroize_json(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::String(s) => zeroize_string(s),
+        serde_json::Value::Array(items) => items.iter_mut().for_each(zeroize_json),
+        serde_json::Value::Object(fields) => fields.values_mut().for_each(zeroize_json),
+        _ => {}
+    }
+}
+
+/// Overwrite the bytes of `s` with zeroes and clear it.
+pub(crate) fn zeroize_string(s: &mut String) {
+    let mut bytes = std::mem::take(s).into_bytes();
+    for byte in bytes.iter_mut() {
+        // SAFETY: `byte` is a valid reference into the vector
+        unsafe { std::ptr::write_volatile(byte, 0) };
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sensitive_is_redacted() {
+        let token = Sensitive::new(Value::String("hunter2".into()));
+        assert_eq!(token.to_string(), REDACTED);
+        assert_eq!(format!("{:?}", token), "Sensitive(<redacted>)");
+
+        let args = [CallArg::from(Value::Int(7)), CallArg::from(token.clone())];
+        assert_eq!(args[1].render(), REDACTED);
+
+        let exposed = ExposedArgs::new(&args).unwrap();
+        assert!(exposed.any_sensitive());
+        assert!(matches!(&exposed.values()[1], Value::String(s) if s == "hunter2"));
+        let error = exposed.redact_error(Error::execution_failed("bad token \"hunter2\""));
+        assert_eq!(
+            error.to_string(),
+            "plugin execution failed: bad token \"<redacted>\""
+        );
+    }
+
+    #[test]
+    fn test_redacts_escaped_strings_only() {
+        let args = [
+            CallArg::from(Sensitive::new(Value::String("pa\"ss\nword".into()))),
+            CallArg::from(Sensitive::new(Value::Int(1))),
+            CallArg::from(Sensitive::new(Value::String("abc".into()))),
+        ];
+        let exposed = ExposedArgs::new(&args).unwrap();
+
+        // `{:?}` escapes the quote and the newline
+        let debug = format!("{:?} at line 1", "pa\"ss\nword");
+        assert_eq!(exposed.redact(&debug), "\"<redacted>\" at line 1");
+        // Short strings and numbers would match unrelated text
+        assert_eq!(exposed.redact("abc failed 1 time"), "abc failed 1 time");
+    }
+
+    #[test]
+    fn test_refuses_values_that_cannot_be_zeroed() {
+        let list = Sensitive::new(Value::List(vec![Value::String("secret".into())]));
+        assert!(ExposedArgs::new(&[CallArg::from(list)]).is_ok());
+        assert!(ExposedArgs::new(&[CallArg::from(Sensitive::new(Value::Null))]).is_ok());
+
+        #[cfg(feature = "serde")]
+        {
+            let record =
+                crate::marshal::from_json(serde_json::json!({ "token": "secret" })).unwrap();
+            assert!(matches!(
+                ExposedArgs::new(&[CallArg::from(Sensitive::new(record))]),
+                Err(Error::InvalidArguments(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_zeroize_value() {
+        let mut value = Value::List(vec![Value::String("secret".into()), Value::Int(42)]);
+        zeroize_value(&mut value);
+        let Value::List(items) = value else {
+            panic!("not a list");
+        };
+        assert!(matches!(&items[0], Value::String(s) if s.is_empty()));
+        assert!(matches!(items[1], Value::Int(0)));
+    }
+}
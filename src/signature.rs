@@ -0,0 +1,384 @@
+//! Typed signatures of plugin exports.
+
+use std::fmt;
+
+use fusabi_host::Value;
+
+use crate::error::{Error, Result};
+
+/// Type of an export parameter or result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValueType {
+    /// Any value.
+    Any,
+    /// The unit value `()`.
+    Unit,
+    /// `true` or `false`.
+    Bool,
+    /// Integer.
+    Int,
+    /// Floating-point number; integers are accepted too.
+    Float,
+    /// String.
+    String,
+    /// List of values.
+    List,
+    /// Record of named fields.
+    Record,
+}
+
+impl ValueType {
+    /// Parse a type name as written in a signature.
+    pub fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "any" => Self::Any,
+            "unit" => Self::Unit,
+            "bool" => Self::Bool,
+            "int" => Self::Int,
+            "float" => Self::Float,
+            "string" => Self::String,
+            "list" => Self::List,
+            "record" => Self::Record,
+            _ => return None,
+        })
+    }
+
+    /// Get the type name.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Any => "any",
+            Self::Unit => "unit",
+            Self::Bool => "bool",
+            Self::Int => "int",
+            Self::Float => "float",
+            Self::String => "string",
+            Self::List => "list",
+            Self::Record => "record",
+        }
+    }
+
+    /// Get the type of a value, if it can be determined.
+    ///
+    /// Without the `serde` feature only a value's literal form is available,
+    /// so only booleans and numbers are recognized.
+    #[cfg(feature = "serde")]
+    fn of(value: &Value) -> Option<Self> {
+        use serde_json::Value as Json;
+
+        Some(match serde_json::to_value(value).ok()? {
+            Json::Null => Self::Unit,
+            Json::Bool(_) => Self::Bool,
+            Json::Number(n) if n.is_i64() || n.is_u64() => Self::Int,
+            Json::Number(_) => Self::Float,
+            Json::String(_) => Self::String,
+            Json::Array(_) => Self::List,
+            Json::Object(_) => Self::Record,
+        })
+    }
+
+    #[cfg(not(feature = "serde"))]
+    fn of(value: &Value) -> Option<Self> {
+        let text = value.to_string();
+        if text == "true" || text == "false" {
+            Some(Self::Bool)
+        } else if text.parse::<i64>().is_ok() {
+            Some(Self::Int)
+        } else if text.parse::<f64>().is_ok() {
+            Some(Self::Float)
+        } else {
+            None
+        }
+    }
+
+    fn accepts(&self, actual: ValueType) -> bool {
+        *self == Self::Any || *self == actual || (*self == Self::Float && actual == Self::Int)
+    }
+}
+
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A named parameter of an export.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Param {
+    /// Parameter name.
+    pub name: String,
+    /// Parameter type.
+    pub ty: ValueType,
+}
+
+/// Parameters and result of an export.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportSignature {
+    /// Parameters in order.
+    pub params: Vec<Param>,
+    /// Result type, `any` when not declared.
+    pub returns: ValueType,
+}
+
+/// Export declaration: a bare name or a typed signature.
+///
+/// In a manifest this is either `"process"` or
+/// `"process(input: string, limit: int) -> record"`. Calls to exports with a
+/// signature are checked before they reach the engine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportSpec {
+    /// Function name.
+    pub name: String,
+    /// Declared signature, if any.
+    pub signature: Option<ExportSignature>,
+}
+
+impl ExportSpec {
+    /// Create an export declaration without a signature.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            signature: None,
+        }
+    }
+
+    /// Parse a bare name or a signature.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let invalid = |reason: &str| {
+            Error::invalid_manifest(format!("invalid export signature {:?}: {}", spec, reason))
+        };
+
+        let spec = spec.trim();
+        let Some(open) = spec.find('(') else {
+            if !is_identifier(spec) {
+                return Err(invalid("expected a function name"));
+            }
+            return Ok(Self::new(spec));
+        };
+
+        let name = spec[..open].trim();
+        if !is_identifier(name) {
+            return Err(invalid("expected a function name"));
+        }
+        let close = spec.rfind(')').ok_or_else(|| invalid("missing ')'"))?;
+        if close < open {
+            return Err(invalid("missing ')'"));
+        }
+
+        let mut params: Vec<Param> = Vec::new();
+        let list = spec[open + 1..close].trim();
+        if !list.is_empty() {
+            for param in list.split(',') {
+                let (param, ty) = param
+                    .split_once(':')
+                    .ok_or_else(|| invalid("parameters are written `name: type`"))?;
+                let param = param.trim();
+                if !is_identifier(param) {
+                    return Err(invalid("expected a parameter name"));
+                }
+                if params.iter().any(|p| p.name == param) {
+                    return Err(invalid(&format!("duplicate parameter {}", param)));
+                }
+                params.push(Param {
+                    name: param.to_string(),
+                    ty: parse_type(ty.trim()).map_err(|e| invalid(&e))?,
+                });
+            }
+        }
+
+        let rest = spec[close + 1..].trim();
+        let returns = if rest.is_empty() {
+            ValueType::Any
+        } else {
+            let ty = rest
+                .strip_prefix("->")
+                .ok_or_else(|| invalid("expected `-> type` after the parameters"))?;
+            parse_type(ty.trim()).map_err(|e| invalid(&e))?
+        };
+
+        Ok(Self {
+            name: name.to_string(),
+            signature: Some(ExportSignature { params, returns }),
+        })
+    }
+
+    /// Check call arguments against the signature.
+    ///
+    /// Exports without a signature accept any arguments.
+    pub fn check_args(&self, args: &[Value]) -> Result<()> {
+        let Some(ref signature) = self.signature else {
+            return Ok(());
+        };
+
+        if args.len() != signature.params.len() {
+            return Err(Error::invalid_arguments(format!(
+                "{} takes {} argument{} ({}), got {}",
+                self.name,
+                signature.params.len(),
+                if signature.params.len() == 1 { "" } else { "s" },
+                self.params_display(),
+                args.len()
+            )));
+        }
+
+        for (param, arg) in signature.params.iter().zip(args) {
+            if param.ty == ValueType::Any {
+                continue;
+            }
+            match ValueType::of(arg) {
+                Some(actual) if param.ty.accepts(actual) => {}
+                Some(actual) => {
+                    return Err(Error::invalid_arguments(format!(
+                        "{}: parameter {} expects {}, got {}",
+                        self.name, param.name, param.ty, actual
+                    )))
+                }
+                // Not classifiable without `serde`; let the engine decide
+                None => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn params_display(&self) -> String {
+        self.signature
+            .iter()
+            .flat_map(|s| &s.params)
+            .map(|p| format!("{}: {}", p.name, p.ty))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl From<&str> for ExportSpec {
+    fn from(name: &str) -> Self {
+        Self::new(name)
+    }
+}
+
+impl From<String> for ExportSpec {
+    fn from(name: String) -> Self {
+        Self::new(name)
+    }
+}
+
+impl PartialEq<str> for ExportSpec {
+    fn eq(&self, other: &str) -> bool {
+        self.name == other
+    }
+}
+
+impl PartialEq<&str> for ExportSpec {
+    fn eq(&self, other: &&str) -> bool {
+        self.name == *other
+    }
+}
+
+impl PartialEq<String> for ExportSpec {
+    fn eq(&self, other: &String) -> bool {
+        self.name == *other
+    }
+}
+
+impl fmt::Display for ExportSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)?;
+        if let Some(ref signature) = self.signature {
+            write!(f, "({})", self.params_display())?;
+            if signature.returns != ValueType::Any {
+                write!(f, " -> {}", signature.returns)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for ExportSpec {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ExportSpec {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        let spec = String::deserialize(deserializer)?;
+        Self::parse(&spec).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for ExportSpec {
+    fn schema_name() -> String {
+        "ExportSpec".to_string()
+    }
+
+    fn json_schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        String::json_schema(gen)
+    }
+}
+
+fn parse_type(name: &str) -> std::result::Result<ValueType, String> {
+    ValueType::parse(name).ok_or_else(|| format!("unknown type {:?}", name))
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_signature() {
+        let spec = ExportSpec::parse("process(input: string, limit: int) -> record").unwrap();
+        assert_eq!(spec.name, "process");
+        let signature = spec.signature.as_ref().unwrap();
+        assert_eq!(signature.params.len(), 2);
+        assert_eq!(signature.params[1].ty, ValueType::Int);
+        assert_eq!(signature.returns, ValueType::Record);
+        assert_eq!(
+            spec.to_string(),
+            "process(input: string, limit: int) -> record"
+        );
+
+        assert_eq!(ExportSpec::parse("run").unwrap(), ExportSpec::new("run"));
+        assert!(ExportSpec::parse("tick()").unwrap().signature.is_some());
+        assert!(ExportSpec::parse("bad(x: blob)").is_err());
+        assert!(ExportSpec::parse("bad(x)").is_err());
+        assert!(ExportSpec::parse("bad(x: int) => int").is_err());
+        assert!(ExportSpec::parse("two words").is_err());
+    }
+
+    #[test]
+    fn test_check_args() {
+        let spec = ExportSpec::parse("scale(factor: float, times: int)").unwrap();
+        assert!(spec.check_args(&[Value::Int(2), Value::Int(3)]).is_ok());
+        assert!(spec.check_args(&[Value::Float(0.5), Value::Int(3)]).is_ok());
+
+        let err = spec.check_args(&[Value::Int(2)]).unwrap_err();
+        assert!(err.to_string().contains("takes 2 arguments"));
+        assert!(ExportSpec::new("free").check_args(&[Value::Null]).is_ok());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_check_arg_types() {
+        let spec = ExportSpec::parse("scale(factor: float, times: int)").unwrap();
+        let err = spec
+            .check_args(&[Value::Int(2), Value::Bool(true)])
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidArguments(_)));
+        assert!(err.to_string().contains("times expects int, got bool"));
+    }
+}
@@ -0,0 +1,184 @@
+//! One-shot summary of the plugin environment, for logging at boot.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::time::Duration;
+
+use crate::host_info::HostInfo;
+use crate::lifecycle::LifecycleState;
+use crate::manifest::Manifest;
+use crate::plugin::PluginInfo;
+
+/// Number of slowest loads listed in a startup report.
+pub(crate) const SLOWEST_LOADS: usize = 5;
+
+/// How long a plugin took to load.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PluginLoadTime {
+    /// Plugin name.
+    pub name: String,
+    /// Time from reading its manifest to registering it.
+    pub duration: Duration,
+}
+
+/// Summary of the loaded plugins, returned by
+/// [`PluginRuntime::startup_report`](crate::PluginRuntime::startup_report).
+#[derive(Debug, Clone)]
+pub struct StartupReport {
+    /// Host the plugins run on.
+    pub host: HostInfo,
+    /// Number of loaded plugins.
+    pub plugins: usize,
+    /// Number of plugins in each lifecycle state.
+    pub by_state: HashMap<LifecycleState, usize>,
+    /// Sum of the load times of every plugin.
+    pub total_load_time: Duration,
+    /// Slowest loads, slowest first.
+    pub slowest: Vec<PluginLoadTime>,
+    /// Plugins requesting each capability.
+    pub capabilities: BTreeMap<String, Vec<String>>,
+    /// Problems worth a look, such as failed loads and name collisions.
+    pub warnings: Vec<String>,
+}
+
+impl StartupReport {
+    pub(crate) fn new(host: HostInfo) -> Self {
+        Self {
+            host,
+            plugins: 0,
+            by_state: HashMap::new(),
+            total_load_time: Duration::ZERO,
+            slowest: Vec::new(),
+            capabilities: BTreeMap::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    /// Count a loaded plugin.
+    pub(crate) fn add_plugin(
+        &mut self,
+        info: &PluginInfo,
+        manifest: &Manifest,
+        missing: &[String],
+    ) {
+        self.plugins += 1;
+        *self.by_state.entry(info.state).or_default() += 1;
+
+        if let Some(duration) = info.load_duration {
+            self.total_load_time += duration;
+            self.slowest.push(PluginLoadTime {
+                name: info.name.clone(),
+                duration,
+            });
+        }
+
+        for capability in &manifest.capabilities {
+            self.capabilities
+                .entry(capability.name.clone())
+                .or_default()
+                .push(info.name.clone());
+        }
+
+        if info.state == LifecycleState::Error {
+            self.warnings
+                .push(format!("plugin {} is in the error state", info.name));
+        }
+        if !missing.is_empty() {
+            self.warnings.push(format!(
+                "plugin {} is missing host functions: {}",
+                info.name,
+                missing.join(", ")
+            ));
+        }
+    }
+
+    /// Sort the collected entries so the report reads the same every time.
+    pub(crate) fn finish(mut self) -> Self {
+        self.slowest
+            .sort_by(|a, b| b.duration.cmp(&a.duration).then(a.name.cmp(&b.name)));
+        self.slowest.truncate(SLOWEST_LOADS);
+        for plugins in self.capabilities.values_mut() {
+            plugins.sort();
+        }
+        self
+    }
+
+    /// Get the number of plugins in `state`.
+    pub fn count(&self, state: LifecycleState) -> usize {
+        self.by_state.get(&state).copied().unwrap_or(0)
+    }
+}
+
+impl fmt::Display for StartupReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.host)?;
+
+        let states = [
+            LifecycleState::Running,
+            LifecycleState::Initialized,
+            LifecycleState::Created,
+            LifecycleState::Stopped,
+            LifecycleState::Error,
+            LifecycleState::Unloaded,
+        ];
+        let counts: Vec<String> = states
+            .iter()
+            .filter(|s| self.count(**s) > 0)
+            .map(|s| format!("{} {}", self.count(*s), s))
+            .collect();
+        write!(
+            f,
+            "{} plugins loaded in {:.1?}",
+            self.plugins, self.total_load_time
+        )?;
+        if !counts.is_empty() {
+            write!(f, " ({})", counts.join(", "))?;
+        }
+        writeln!(f)?;
+
+        if !self.slowest.is_empty() {
+            let slowest: Vec<String> = self
+                .slowest
+                .iter()
+                .map(|load| format!("{} {:.1?}", load.name, load.duration))
+                .collect();
+            writeln!(f, "  slowest: {}", slowest.join(", "))?;
+        }
+        for (capability, plugins) in &self.capabilities {
+            writeln!(f, "  {}: {}", capability, plugins.join(", "))?;
+        }
+        for warning in &self.warnings {
+            writeln!(f, "  warning: {}", warning)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::ApiVersion;
+    use crate::plugin::Plugin;
+
+    #[test]
+    fn test_startup_report() {
+        let mut report = StartupReport::new(HostInfo::collect(&ApiVersion::default()));
+        for (name, millis) in [("fast", 2), ("slow", 40)] {
+            let manifest = Manifest::new(name, "1.0.0");
+            let mut info = Plugin::new(manifest.clone()).info();
+            info.load_duration = Some(Duration::from_millis(millis));
+            report.add_plugin(&info, &manifest, &[]);
+        }
+        let manifest = Manifest::new("broken", "1.0.0");
+        let info = Plugin::new(manifest.clone()).info();
+        report.add_plugin(&info, &manifest, &["ui.notify".to_string()]);
+        let report = report.finish();
+
+        assert_eq!(report.plugins, 3);
+        assert_eq!(report.count(LifecycleState::Created), 3);
+        assert_eq!(report.total_load_time, Duration::from_millis(42));
+        assert_eq!(report.slowest[0].name, "slow");
+        assert_eq!(report.warnings.len(), 1);
+        assert!(report.to_string().contains("3 plugins loaded"));
+    }
+}
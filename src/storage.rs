@@ -0,0 +1,829 @@
+//! Per-plugin key-value storage with transactional operations.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use dashmap::DashMap;
+use fusabi_host::Value;
+use parking_lot::{Mutex, RwLock};
+
+use crate::error::{Error, Result};
+use crate::host_functions::HostFunctionTable;
+
+/// Host functions giving plugins access to their own namespace, registered
+/// by [`PluginStorage::register_host_functions`].
+pub const STORAGE_HOST_FUNCTIONS: [&str; 5] = [
+    "storage.get",
+    "storage.set",
+    "storage.delete",
+    "storage.compare_and_swap",
+    "storage.transaction",
+];
+
+/// Leading byte of string values stored through the host functions.
+///
+/// Type bytes never start valid UTF-8, so untagged text the host stores
+/// cannot be mistaken for a tagged value.
+const STRING_TAG: u8 = 0xFE;
+
+/// Leading byte of integer values stored through the host functions,
+/// followed by the decimal text.
+const INT_TAG: u8 = 0xFF;
+
+/// Storage limits applied to a single plugin.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StorageQuota {
+    /// Maximum total size of keys and values in bytes.
+    pub max_bytes: Option<usize>,
+    /// Maximum number of keys.
+    pub max_keys: Option<usize>,
+}
+
+impl StorageQuota {
+    /// Create an unlimited quota.
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum total size in bytes.
+    pub fn with_max_bytes(mut self, bytes: usize) -> Self {
+        self.max_bytes = Some(bytes);
+        self
+    }
+
+    /// Set the maximum number of keys.
+    pub fn with_max_keys(mut self, keys: usize) -> Self {
+        self.max_keys = Some(keys);
+        self
+    }
+}
+
+/// Storage usage statistics for a single plugin.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StorageStats {
+    /// Number of stored keys.
+    pub keys: usize,
+    /// Total size of keys and values in bytes.
+    pub bytes: usize,
+    /// Number of writes rejected by the quota.
+    pub quota_rejections: u64,
+}
+
+/// Key-value entries owned by a single plugin.
+#[derive(Debug, Default)]
+struct Namespace {
+    entries: HashMap<String, Vec<u8>>,
+    bytes: usize,
+    quota: Option<StorageQuota>,
+    quota_rejections: u64,
+}
+
+impl Namespace {
+    fn insert(&mut self, key: String, value: Vec<u8>) {
+        if let Some(old) = self.entries.get(&key) {
+            self.bytes -= key.len() + old.len();
+        }
+        self.bytes += key.len() + value.len();
+        self.entries.insert(key, value);
+    }
+
+    /// Compute key count and byte size after applying pending writes.
+    fn projected(&self, writes: &HashMap<String, Option<Vec<u8>>>) -> (usize, usize) {
+        let mut keys = self.entries.len();
+        let mut bytes = self.bytes;
+
+        for (key, value) in writes {
+            if let Some(old) = self.entries.get(key) {
+                keys -= 1;
+                bytes -= key.len() + old.len();
+            }
+            if let Some(value) = value {
+                keys += 1;
+                bytes += key.len() + value.len();
+            }
+        }
+
+        (keys, bytes)
+    }
+
+    fn remove(&mut self, key: &str) -> Option<Vec<u8>> {
+        let old = self.entries.remove(key)?;
+        self.bytes -= key.len() + old.len();
+        Some(old)
+    }
+}
+
+/// Shared key-value storage backing plugin state.
+///
+/// Every plugin gets its own namespace, keyed by plugin name. Single-key
+/// operations and transactions on a namespace are serialized, so concurrent
+/// scheduled tasks of the same plugin never observe a partially applied update.
+///
+/// Writes are checked against the plugin's [`StorageQuota`] when they are
+/// committed; a transaction that would exceed it fails with
+/// [`Error::QuotaExceeded`] and leaves the stored data untouched.
+#[derive(Debug, Clone, Default)]
+pub struct PluginStorage {
+    namespaces: Arc<DashMap<String, Arc<Mutex<Namespace>>>>,
+    default_quota: Arc<RwLock<StorageQuota>>,
+}
+
+impl PluginStorage {
+    /// Create an empty storage.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an empty storage with a default quota for every plugin.
+    pub fn with_default_quota(quota: StorageQuota) -> Self {
+        Self {
+            namespaces: Arc::default(),
+            default_quota: Arc::new(RwLock::new(quota)),
+        }
+    }
+
+    /// Get the default quota.
+    pub fn default_quota(&self) -> StorageQuota {
+        self.default_quota.read().clone()
+    }
+
+    /// Set the default quota for plugins without an explicit one.
+    pub fn set_default_quota(&self, quota: StorageQuota) {
+        *self.default_quota.write() = quota;
+    }
+
+    /// Set the quota of a single plugin, overriding the default.
+    pub fn set_quota(&self, plugin: &str, quota: StorageQuota) {
+        self.namespace(plugin).lock().quota = Some(quota);
+    }
+
+    /// Get the effective quota of a plugin.
+    pub fn quota(&self, plugin: &str) -> StorageQuota {
+        self.namespaces
+            .get(plugin)
+            .and_then(|ns| ns.lock().quota.clone())
+            .unwrap_or_else(|| self.default_quota())
+    }
+
+    /// Get usage statistics of a plugin.
+    pub fn stats(&self, plugin: &str) -> StorageStats {
+        self.namespaces
+            .get(plugin)
+            .map(|ns| {
+                let ns = ns.lock();
+                StorageStats {
+                    keys: ns.entries.len(),
+                    bytes: ns.bytes,
+                    quota_rejections: ns.quota_rejections,
+                }
+            })
+            .unwrap_or_default()
+    }
+
+    /// Get a storage handle scoped to a single plugin.
+    pub fn scoped(&self, plugin: impl Into<String>) -> StorageScope {
+        StorageScope {
+            storage: self.clone(),
+            plugin: plugin.into(),
+        }
+    }
+
+    /// Get a value.
+    pub fn get(&self, plugin: &str, key: &str) -> Option<Vec<u8>> {
+        self.namespaces
+            .get(plugin)
+            .and_then(|ns| ns.lock().entries.get(key).cloned())
+    }
+
+    /// Set a value.
+    pub fn set(
+        &self,
+        plugin: &str,
+        key: impl Into<String>,
+        value: impl Into<Vec<u8>>,
+    ) -> Result<()> {
+        let key = key.into();
+        let value = value.into();
+        self.transaction(plugin, |txn| {
+            txn.set(key, value);
+            Ok(())
+        })
+    }
+
+    /// Delete a value, returning the previous one.
+    pub fn delete(&self, plugin: &str, key: &str) -> Option<Vec<u8>> {
+        self.namespaces
+            .get(plugin)
+            .and_then(|ns| ns.lock().remove(key))
+    }
+
+    /// Get all keys of a plugin.
+    pub fn keys(&self, plugin: &str) -> Vec<String> {
+        self.namespaces
+            .get(plugin)
+            .map(|ns| ns.lock().entries.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Atomically replace a value if it currently equals `expected`.
+    ///
+    /// `None` as `expected` means the key must be absent; `None` as `new`
+    /// deletes the key. Returns whether the swap happened.
+    pub fn compare_and_swap(
+        &self,
+        plugin: &str,
+        key: &str,
+        expected: Option<&[u8]>,
+        new: Option<Vec<u8>>,
+    ) -> Result<bool> {
+        self.transaction(plugin, |txn| Ok(txn.compare_and_swap(key, expected, new)))
+    }
+
+    /// Run a transaction against a plugin's namespace.
+    ///
+    /// Reads inside the transaction see its own pending writes. Writes are
+    /// applied atomically when the closure returns `Ok`, and discarded when it
+    /// returns `Err` or the result would exceed the plugin's quota.
+    pub fn transaction<F, T>(&self, plugin: &str, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut Transaction<'_>) -> Result<T>,
+    {
+        let namespace = self.namespace(plugin);
+        let mut namespace = namespace.lock();
+
+        let mut txn = Transaction {
+            committed: &namespace.entries,
+            writes: HashMap::new(),
+        };
+        let result = f(&mut txn)?;
+        let writes = txn.writes;
+
+        let quota = namespace
+            .quota
+            .clone()
+            .unwrap_or_else(|| self.default_quota());
+        let (keys, bytes) = namespace.projected(&writes);
+        let exceeded = match (quota.max_keys, quota.max_bytes) {
+            (Some(max), _) if keys > max && keys > namespace.entries.len() => Some(("keys", max)),
+            (_, Some(max)) if bytes > max && bytes > namespace.bytes => Some(("bytes", max)),
+            _ => None,
+        };
+        if let Some((resource, limit)) = exceeded {
+            namespace.quota_rejections += 1;
+            return Err(Error::quota_exceeded(plugin, resource, limit));
+        }
+
+        for (key, value) in writes {
+            match value {
+                Some(value) => namespace.insert(key, value),
+                None => {
+                    namespace.remove(&key);
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Remove all data of a plugin, keeping its quota.
+    ///
+    /// Waits for a transaction in progress on the namespace, so it is
+    /// either cleared along with the rest or applied afterwards.
+    pub fn clear(&self, plugin: &str) {
+        let Some(namespace) = self.namespaces.get(plugin).map(|ns| ns.value().clone()) else {
+            return;
+        };
+        let mut namespace = namespace.lock();
+        namespace.entries.clear();
+        namespace.bytes = 0;
+    }
+
+    /// Register the [`STORAGE_HOST_FUNCTIONS`] in `table`, each acting on
+    /// the namespace of the plugin calling it.
+    ///
+    /// Keys are strings or integers. Values are strings or integers, stored
+    /// with a leading type byte (`0xFE` for strings, `0xFF` for integers
+    /// followed by their decimal text) so they read back as the same type;
+    /// `Null` stands for an absent value. Values the host stores without a
+    /// type byte read back as strings.
+    ///
+    /// - `storage.get(key[, default])` returns the value, or `default`
+    ///   (`Null` if not given) when the key is absent.
+    /// - `storage.set(key, value)` stores a value; `Null` deletes the key.
+    /// - `storage.delete(key)` deletes a key, returning whether it existed.
+    /// - `storage.compare_and_swap(key, expected, new)` stores `new` if the
+    ///   value equals `expected`, returning whether it did.
+    /// - `storage.transaction(ops)` applies a list of operations atomically:
+    ///   `["check", key, expected]`, `["set", key, value]` and
+    ///   `["delete", key]`. Checks see the writes before them; if one fails
+    ///   nothing is written. Returns whether the transaction was applied.
+    pub fn register_host_functions(&self, table: &HostFunctionTable) {
+        let storage = self.clone();
+        table.register_scoped_fn("storage.get", move |plugin, args| {
+            let (key, default) = match args {
+                [key] => (key, &Value::Null),
+                [key, default] => (key, default),
+                _ => {
+                    return Err(Error::invalid_arguments(
+                        "expected a key and an optional default",
+                    ))
+                }
+            };
+            Ok(storage
+                .get(plugin, &host_key(key)?)
+                .map_or_else(|| default.clone(), |value| to_host_value(&value)))
+        });
+
+        let storage = self.clone();
+        table.register_scoped_fn("storage.set", move |plugin, args| {
+            let [key, value] = args else {
+                return Err(Error::invalid_arguments("expected a key and a value"));
+            };
+            let (key, value) = (host_key(key)?, from_host_value(value)?);
+            storage.transaction(plugin, |txn| {
+                txn.writes.insert(key, value);
+                Ok(Value::Null)
+            })
+        });
+
+        let storage = self.clone();
+        table.register_scoped_fn("storage.delete", move |plugin, args| {
+            let [key] = args else {
+                return Err(Error::invalid_arguments("expected a key"));
+            };
+            Ok(Value::Bool(
+                storage.delete(plugin, &host_key(key)?).is_some(),
+            ))
+        });
+
+        let storage = self.clone();
+        table.register_scoped_fn("storage.compare_and_swap", move |plugin, args| {
+            let [key, expected, new] = args else {
+                return Err(Error::invalid_arguments(
+                    "expected a key, the expected value and the new value",
+                ));
+            };
+            let expected = from_host_value(expected)?;
+            storage
+                .compare_and_swap(
+                    plugin,
+                    &host_key(key)?,
+                    expected.as_deref(),
+                    from_host_value(new)?,
+                )
+                .map(Value::Bool)
+        });
+
+        let storage = self.clone();
+        table.register_scoped_fn("storage.transaction", move |plugin, args| {
+            let [Value::List(ops)] = args else {
+                return Err(Error::invalid_arguments("expected a list of operations"));
+            };
+            storage
+                .transaction(plugin, |txn| host_transaction(txn, ops))
+                .map(Value::Bool)
+        });
+    }
+
+    fn namespace(&self, plugin: &str) -> Arc<Mutex<Namespace>> {
+        self.namespaces
+            .entry(plugin.to_string())
+            .or_default()
+            .value()
+            .clone()
+    }
+}
+
+/// Pending operations of a storage transaction.
+pub struct Transaction<'a> {
+    committed: &'a HashMap<String, Vec<u8>>,
+    writes: HashMap<String, Option<Vec<u8>>>,
+}
+
+impl Transaction<'_> {
+    /// Get a value, including writes made earlier in this transaction.
+    pub fn get(&self, key: &str) -> Option<&[u8]> {
+        match self.writes.get(key) {
+            Some(pending) => pending.as_deref(),
+            None => self.committed.get(key).map(Vec::as_slice),
+        }
+    }
+
+    /// Check if a key exists.
+    pub fn contains(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Set a value.
+    pub fn set(&mut self, key: impl Into<String>, value: impl Into<Vec<u8>>) {
+        self.writes.insert(key.into(), Some(value.into()));
+    }
+
+    /// Delete a value.
+    pub fn delete(&mut self, key: impl Into<String>) {
+        self.writes.insert(key.into(), None);
+    }
+
+    /// Replace a value if it currently equals `expected`.
+    pub fn compare_and_swap(
+        &mut self,
+        key: &str,
+        expected: Option<&[u8]>,
+        new: Option<Vec<u8>>,
+    ) -> bool {
+        if self.get(key) != expected {
+            return false;
+        }
+        self.writes.insert(key.to_string(), new);
+        true
+    }
+}
+
+/// Apply the operations of a `storage.transaction` call, discarding its
+/// writes if a check fails.
+fn host_transaction(txn: &mut Transaction<'_>, ops: &[Value]) -> Result<bool> {
+    for op in ops {
+        let Value::List(op) = op else {
+            return Err(Error::invalid_arguments(format!(
+                "transaction operation must be a list, got {:?}",
+                op
+            )));
+        };
+        match op.as_slice() {
+            [Value::String(kind), key, expected] if kind == "check" => {
+                let expected = from_host_value(expected)?;
+                if txn.get(&host_key(key)?) != expected.as_deref() {
+                    txn.writes.clear();
+                    return Ok(false);
+                }
+            }
+            [Value::String(kind), key, value] if kind == "set" => {
+                txn.writes.insert(host_key(key)?, from_host_value(value)?);
+            }
+            [Value::String(kind), key] if kind == "delete" => txn.delete(host_key(key)?),
+            _ => {
+                return Err(Error::invalid_arguments(format!(
+                    "expected [\"check\", key, expected], [\"set\", key, value] or \
+                     [\"delete\", key], got {:?}",
+                    op
+                )))
+            }
+        }
+    }
+    Ok(true)
+}
+
+fn host_key(key: &Value) -> Result<String> {
+    match key {
+        Value::String(key) => Ok(key.clone()),
+        Value::Int(key) => Ok(key.to_string()),
+        other => Err(Error::invalid_arguments(format!(
+            "storage key must be a string or an int, got {:?}",
+            other
+        ))),
+    }
+}
+
+fn from_host_value(value: &Value) -> Result<Option<Vec<u8>>> {
+    let tagged = |tag: u8, text: &str| {
+        let mut bytes = Vec::with_capacity(text.len() + 1);
+        bytes.push(tag);
+        bytes.extend_from_slice(text.as_bytes());
+        Some(bytes)
+    };
+    match value {
+        Value::Null => Ok(None),
+        Value::String(value) => Ok(tagged(STRING_TAG, value)),
+        Value::Int(value) => Ok(tagged(INT_TAG, &value.to_string())),
+        other => Err(Error::invalid_arguments(format!(
+            "storage value must be a string, an int or null, got {:?}",
+            other
+        ))),
+    }
+}
+
+fn to_host_value(value: &[u8]) -> Value {
+    let text = |bytes: &[u8]| String::from_utf8_lossy(bytes).into_owned();
+    match value {
+        [STRING_TAG, rest @ ..] => Value::String(text(rest)),
+        [INT_TAG, rest @ ..] => match text(rest).parse() {
+            Ok(n) => Value::Int(n),
+            Err(_) => Value::String(text(value)),
+        },
+        _ => Value::String(text(value)),
+    }
+}
+
+impl std::fmt::Debug for Transaction<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Transaction")
+            .field("pending_writes", &self.writes.len())
+            .finish()
+    }
+}
+
+/// Storage handle bound to a single plugin.
+#[derive(Debug, Clone)]
+pub struct StorageScope {
+    storage: PluginStorage,
+    plugin: String,
+}
+
+impl StorageScope {
+    /// Get the plugin name this scope is bound to.
+    pub fn plugin(&self) -> &str {
+        &self.plugin
+    }
+
+    /// Get a value.
+    pub fn get(&self, key: &str) -> Option<Vec<u8>> {
+        self.storage.get(&self.plugin, key)
+    }
+
+    /// Set a value.
+    pub fn set(&self, key: impl Into<String>, value: impl Into<Vec<u8>>) -> Result<()> {
+        self.storage.set(&self.plugin, key, value)
+    }
+
+    /// Delete a value, returning the previous one.
+    pub fn delete(&self, key: &str) -> Option<Vec<u8>> {
+        self.storage.delete(&self.plugin, key)
+    }
+
+    /// Atomically replace a value if it currently equals `expected`.
+    pub fn compare_and_swap(
+        &self,
+        key: &str,
+        expected: Option<&[u8]>,
+        new: Option<Vec<u8>>,
+    ) -> Result<bool> {
+        self.storage
+            .compare_and_swap(&self.plugin, key, expected, new)
+    }
+
+    /// Run a transaction against this plugin's namespace.
+    pub fn transaction<F, T>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&mut Transaction<'_>) -> Result<T>,
+    {
+        self.storage.transaction(&self.plugin, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+
+    #[test]
+    fn test_storage_namespaces() {
+        let storage = PluginStorage::new();
+        storage.set("a", "key", "one").unwrap();
+        storage.set("b", "key", "two").unwrap();
+
+        assert_eq!(storage.get("a", "key").unwrap(), b"one");
+        assert_eq!(storage.get("b", "key").unwrap(), b"two");
+        assert!(storage.get("c", "key").is_none());
+
+        assert_eq!(storage.delete("a", "key").unwrap(), b"one");
+        assert!(storage.keys("a").is_empty());
+    }
+
+    #[test]
+    fn test_transaction_read_your_writes() {
+        let storage = PluginStorage::new();
+        storage.set("p", "counter", "1").unwrap();
+
+        storage
+            .transaction("p", |txn| {
+                txn.set("counter", "2");
+                assert_eq!(txn.get("counter").unwrap(), b"2");
+                txn.delete("counter");
+                assert!(!txn.contains("counter"));
+                txn.set("other", "x");
+                Ok(())
+            })
+            .unwrap();
+
+        assert!(storage.get("p", "counter").is_none());
+        assert_eq!(storage.get("p", "other").unwrap(), b"x");
+    }
+
+    #[test]
+    fn test_transaction_rollback() {
+        let storage = PluginStorage::new();
+        storage.set("p", "key", "before").unwrap();
+
+        let result: Result<()> = storage.transaction("p", |txn| {
+            txn.set("key", "after");
+            Err(Error::execution_failed("abort"))
+        });
+
+        assert!(result.is_err());
+        assert_eq!(storage.get("p", "key").unwrap(), b"before");
+    }
+
+    #[test]
+    fn test_quota_enforced() {
+        let storage = PluginStorage::with_default_quota(StorageQuota::unlimited().with_max_keys(2));
+        storage.set("p", "a", "1").unwrap();
+        storage.set("p", "b", "2").unwrap();
+
+        let result = storage.set("p", "c", "3");
+        assert!(matches!(result, Err(Error::QuotaExceeded { .. })));
+
+        // Overwrites and deletes are still allowed at the limit
+        storage.set("p", "a", "10").unwrap();
+        storage.delete("p", "b");
+        storage.set("p", "c", "3").unwrap();
+
+        let stats = storage.stats("p");
+        assert_eq!(stats.keys, 2);
+        assert_eq!(stats.bytes, 5);
+        assert_eq!(stats.quota_rejections, 1);
+    }
+
+    #[test]
+    fn test_per_plugin_quota() {
+        let storage = PluginStorage::new();
+        storage.set_quota("small", StorageQuota::unlimited().with_max_bytes(8));
+
+        assert!(storage.set("small", "key", "12345").is_ok());
+        assert!(storage.set("small", "other", "x").is_err());
+        assert_eq!(storage.stats("small").bytes, 8);
+
+        assert!(storage.set("big", "other", "x".repeat(64)).is_ok());
+    }
+
+    #[test]
+    fn test_compare_and_swap() {
+        let scope = PluginStorage::new().scoped("p");
+
+        assert!(scope
+            .compare_and_swap("lock", None, Some(b"1".to_vec()))
+            .unwrap());
+        assert!(!scope
+            .compare_and_swap("lock", None, Some(b"2".to_vec()))
+            .unwrap());
+        assert!(scope.compare_and_swap("lock", Some(b"1"), None).unwrap());
+        assert!(scope.get("lock").is_none());
+    }
+
+    #[test]
+    fn test_concurrent_increments() {
+        let scope = PluginStorage::new().scoped("p");
+
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let scope = scope.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..100 {
+                        scope
+                            .transaction(|txn| {
+                                let current: u64 = txn
+                                    .get("n")
+                                    .map(|v| String::from_utf8_lossy(v).parse().unwrap())
+                                    .unwrap_or(0);
+                                txn.set("n", (current + 1).to_string());
+                                Ok(())
+                            })
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        assert_eq!(scope.get("n").unwrap(), b"800");
+    }
+
+    #[test]
+    fn test_clear_waits_for_transactions() {
+        let storage = PluginStorage::new();
+        storage.set_quota("p", StorageQuota::unlimited().with_max_keys(4));
+        storage.set("p", "old", "1").unwrap();
+
+        let (entered, inside) = std::sync::mpsc::channel();
+        let (release, resume) = std::sync::mpsc::channel::<()>();
+        let writer = {
+            let storage = storage.clone();
+            std::thread::spawn(move || {
+                storage.transaction("p", |txn| {
+                    txn.set("new", "2");
+                    entered.send(()).unwrap();
+                    let _ = resume.recv();
+                    Ok(())
+                })
+            })
+        };
+        inside.recv().unwrap();
+        let clearing = {
+            let storage = storage.clone();
+            std::thread::spawn(move || storage.clear("p"))
+        };
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        assert!(!clearing.is_finished());
+        release.send(()).unwrap();
+        writer.join().unwrap().unwrap();
+        clearing.join().unwrap();
+
+        // The commit was serialized before the clear
+        assert!(storage.keys("p").is_empty());
+        assert_eq!(storage.stats("p").bytes, 0);
+        assert_eq!(storage.quota("p").max_keys, Some(4));
+    }
+
+    #[test]
+    fn test_host_functions() {
+        let storage = PluginStorage::new();
+        let table = HostFunctionTable::new();
+        storage.register_host_functions(&table);
+        let required: Vec<String> = STORAGE_HOST_FUNCTIONS
+            .iter()
+            .map(|f| f.to_string())
+            .collect();
+        assert!(table.missing(&required).is_empty());
+        let host = table.bind("p", &required);
+        let call = |name: &str, args: &[Value]| host[name](args);
+        let s = |text: &str| Value::String(text.to_string());
+
+        assert!(matches!(call("storage.get", &[s("n")]), Ok(Value::Null)));
+        assert!(matches!(
+            call("storage.get", &[s("n"), Value::Int(0)]),
+            Ok(Value::Int(0))
+        ));
+        call("storage.set", &[s("n"), Value::Int(1)]).unwrap();
+        assert_eq!(storage.get("p", "n").unwrap(), b"\xff1");
+        assert!(storage.get("other", "n").is_none());
+
+        assert!(matches!(
+            call(
+                "storage.compare_and_swap",
+                &[s("n"), Value::Int(2), Value::Int(3)]
+            ),
+            Ok(Value::Bool(false))
+        ));
+        assert!(matches!(
+            call(
+                "storage.compare_and_swap",
+                &[s("n"), Value::Int(1), Value::Int(2)]
+            ),
+            Ok(Value::Bool(true))
+        ));
+        assert!(matches!(
+            call(
+                "storage.compare_and_swap",
+                &[s("lock"), Value::Null, s("me")]
+            ),
+            Ok(Value::Bool(true))
+        ));
+
+        // A failed check discards the writes before it
+        let ops = |expected: i64| {
+            Value::List(vec![
+                Value::List(vec![s("set"), s("log"), s("moved")]),
+                Value::List(vec![s("check"), s("n"), Value::Int(expected)]),
+                Value::List(vec![s("set"), s("n"), Value::Int(expected + 1)]),
+                Value::List(vec![s("delete"), s("lock")]),
+            ])
+        };
+        assert!(matches!(
+            call("storage.transaction", &[ops(5)]),
+            Ok(Value::Bool(false))
+        ));
+        assert!(storage.get("p", "log").is_none());
+        assert!(matches!(
+            call("storage.transaction", &[ops(2)]),
+            Ok(Value::Bool(true))
+        ));
+        assert!(matches!(call("storage.get", &[s("n")]), Ok(Value::Int(3))));
+        assert!(
+            matches!(call("storage.get", &[s("log")]), Ok(Value::String(ref v)) if v == "moved")
+        );
+        assert!(matches!(
+            call("storage.delete", &[s("lock")]),
+            Ok(Value::Bool(false))
+        ));
+
+        // Values read back as the type they were stored with
+        call("storage.set", &[s("code"), s("007")]).unwrap();
+        assert!(
+            matches!(call("storage.get", &[s("code")]), Ok(Value::String(ref v)) if v == "007")
+        );
+        assert!(matches!(
+            call(
+                "storage.compare_and_swap",
+                &[s("code"), Value::Int(7), s("8")]
+            ),
+            Ok(Value::Bool(false))
+        ));
+        storage.set("p", "raw", "42").unwrap();
+        assert!(matches!(call("storage.get", &[s("raw")]), Ok(Value::String(ref v)) if v == "42"));
+
+        assert!(call("storage.transaction", &[Value::List(vec![s("set")])]).is_err());
+        assert!(call("storage.set", &[Value::Bool(true), s("x")]).is_err());
+    }
+}
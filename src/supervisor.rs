@@ -0,0 +1,248 @@
+//! Restart supervision for failed plugins.
+
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+
+/// When a failed plugin should be restarted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RestartPolicy {
+    /// Leave the plugin in the `Error` state.
+    Never,
+    /// Restart immediately, giving up after consecutive failures.
+    OnFailure {
+        /// Maximum consecutive restarts.
+        max_retries: u32,
+    },
+    /// Always restart, waiting longer after each consecutive failure.
+    Always {
+        /// Delay before the first restart.
+        initial_backoff: Duration,
+        /// Upper bound for the delay.
+        max_backoff: Duration,
+    },
+}
+
+impl RestartPolicy {
+    /// Restart on failure up to `max_retries` times.
+    pub fn on_failure(max_retries: u32) -> Self {
+        Self::OnFailure { max_retries }
+    }
+
+    /// Always restart with exponential backoff.
+    pub fn always(initial_backoff: Duration, max_backoff: Duration) -> Self {
+        Self::Always {
+            initial_backoff,
+            max_backoff,
+        }
+    }
+
+    /// Get the delay before the next restart, or `None` to give up.
+    ///
+    /// `attempts` is the number of consecutive restarts already made.
+    pub fn next_delay(&self, attempts: u32) -> Option<Duration> {
+        match self {
+            Self::Never => None,
+            Self::OnFailure { max_retries } => (attempts < *max_retries).then_some(Duration::ZERO),
+            Self::Always {
+                initial_backoff,
+                max_backoff,
+            } => {
+                let factor = 1u32.checked_shl(attempts.min(31)).unwrap_or(u32::MAX);
+                Some(initial_backoff.saturating_mul(factor).min(*max_backoff))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct RestartState {
+    attempts: u32,
+    restart_at: Option<Instant>,
+}
+
+/// Tracks restart policies and pending restarts of supervised plugins.
+///
+/// Only plugins with a policy, either set explicitly or through the default,
+/// are supervised. The runtime moves a supervised plugin to the `Error` state
+/// when a call fails and restarts it once [`restart_at`] has passed.
+///
+/// [`restart_at`]: Supervisor::restart_at
+///
+/// With [`with_rollback_after`](Supervisor::with_rollback_after), a plugin
+/// that keeps failing after restarts is rolled back to its last known-good
+/// version instead of being restarted again.
+#[derive(Debug, Default)]
+pub struct Supervisor {
+    default_policy: Option<RestartPolicy>,
+    rollback_after: Option<u32>,
+    policies: DashMap<String, RestartPolicy>,
+    states: DashMap<String, RestartState>,
+}
+
+impl Supervisor {
+    /// Create a supervisor with an optional default policy.
+    pub fn new(default_policy: Option<RestartPolicy>) -> Self {
+        Self {
+            default_policy,
+            ..Self::default()
+        }
+    }
+
+    /// Roll a supervised plugin back once it fails again after `restarts`
+    /// consecutive restarts.
+    pub fn with_rollback_after(mut self, restarts: u32) -> Self {
+        self.rollback_after = Some(restarts);
+        self
+    }
+
+    /// Get the number of consecutive restarts after which failing plugins
+    /// are rolled back.
+    pub fn rollback_after(&self) -> Option<u32> {
+        self.rollback_after
+    }
+
+    /// Check if a failing plugin should be rolled back rather than restarted.
+    pub fn should_rollback(&self, plugin: &str) -> bool {
+        self.rollback_after
+            .is_some_and(|n| self.is_supervised(plugin) && self.restart_count(plugin) >= n)
+    }
+
+    /// Set the restart policy of a plugin.
+    pub fn set_policy(&self, plugin: impl Into<String>, policy: RestartPolicy) {
+        self.policies.insert(plugin.into(), policy);
+    }
+
+    /// Remove the explicit policy of a plugin, falling back to the default.
+    pub fn clear_policy(&self, plugin: &str) {
+        self.policies.remove(plugin);
+        self.states.remove(plugin);
+    }
+
+    /// Get the effective restart policy of a plugin.
+    pub fn policy(&self, plugin: &str) -> Option<RestartPolicy> {
+        self.policies
+            .get(plugin)
+            .map(|p| p.clone())
+            .or_else(|| self.default_policy.clone())
+    }
+
+    /// Check if a plugin is supervised.
+    pub fn is_supervised(&self, plugin: &str) -> bool {
+        self.policy(plugin).is_some()
+    }
+
+    /// Get the number of consecutive restarts of a plugin.
+    pub fn restart_count(&self, plugin: &str) -> u32 {
+        self.states.get(plugin).map(|s| s.attempts).unwrap_or(0)
+    }
+
+    /// Get when a pending restart is due.
+    pub fn restart_at(&self, plugin: &str) -> Option<Instant> {
+        self.states.get(plugin).and_then(|s| s.restart_at)
+    }
+
+    /// Get when the earliest pending restart is due.
+    pub fn next_restart(&self) -> Option<Instant> {
+        self.states.iter().filter_map(|s| s.restart_at).min()
+    }
+
+    /// Get plugins whose restart is due.
+    pub fn due(&self, now: Instant) -> Vec<String> {
+        self.states
+            .iter()
+            .filter(|s| s.restart_at.is_some_and(|at| at <= now))
+            .map(|s| s.key().clone())
+            .collect()
+    }
+
+    /// Record a failure and schedule a restart according to the policy.
+    ///
+    /// Returns when the restart is due, or `None` if the policy gives up.
+    pub fn record_failure(&self, plugin: &str, now: Instant) -> Option<Instant> {
+        let policy = self.policy(plugin)?;
+        let mut state = self.states.entry(plugin.to_string()).or_default();
+
+        state.restart_at = policy.next_delay(state.attempts).map(|delay| now + delay);
+        state.restart_at
+    }
+
+    /// Record a restart attempt.
+    pub fn record_restart(&self, plugin: &str) {
+        let mut state = self.states.entry(plugin.to_string()).or_default();
+        state.attempts += 1;
+        state.restart_at = None;
+    }
+
+    /// Reset the failure history of a plugin after a successful call.
+    pub fn record_success(&self, plugin: &str) {
+        self.states.remove(plugin);
+    }
+
+    /// Forget a plugin, e.g. after it was unloaded.
+    pub fn forget(&self, plugin: &str) {
+        self.states.remove(plugin);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_delays() {
+        assert_eq!(RestartPolicy::Never.next_delay(0), None);
+
+        let on_failure = RestartPolicy::on_failure(2);
+        assert_eq!(on_failure.next_delay(1), Some(Duration::ZERO));
+        assert_eq!(on_failure.next_delay(2), None);
+
+        let always = RestartPolicy::always(Duration::from_millis(100), Duration::from_secs(1));
+        assert_eq!(always.next_delay(0), Some(Duration::from_millis(100)));
+        assert_eq!(always.next_delay(2), Some(Duration::from_millis(400)));
+        assert_eq!(always.next_delay(40), Some(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_supervisor_scheduling() {
+        let supervisor = Supervisor::new(None);
+        let now = Instant::now();
+        assert!(supervisor.record_failure("unsupervised", now).is_none());
+
+        supervisor.set_policy(
+            "flaky",
+            RestartPolicy::always(Duration::from_secs(1), Duration::from_secs(10)),
+        );
+        let at = supervisor.record_failure("flaky", now).unwrap();
+        assert_eq!(at, now + Duration::from_secs(1));
+        assert!(supervisor.due(now).is_empty());
+        assert_eq!(supervisor.due(at), vec!["flaky".to_string()]);
+        assert_eq!(supervisor.next_restart(), Some(at));
+
+        supervisor.record_restart("flaky");
+        assert_eq!(supervisor.next_restart(), None);
+        assert_eq!(supervisor.restart_count("flaky"), 1);
+        assert_eq!(
+            supervisor.record_failure("flaky", now),
+            Some(now + Duration::from_secs(2))
+        );
+
+        supervisor.record_success("flaky");
+        assert_eq!(supervisor.restart_count("flaky"), 0);
+    }
+
+    #[test]
+    fn test_rollback_after() {
+        let supervisor = Supervisor::new(Some(RestartPolicy::on_failure(5))).with_rollback_after(2);
+        supervisor.record_restart("flaky");
+        assert!(!supervisor.should_rollback("flaky"));
+        supervisor.record_restart("flaky");
+        assert!(supervisor.should_rollback("flaky"));
+
+        supervisor.record_success("flaky");
+        assert!(!supervisor.should_rollback("flaky"));
+        assert!(!Supervisor::new(None)
+            .with_rollback_after(0)
+            .should_rollback("x"));
+    }
+}
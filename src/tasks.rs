@@ -0,0 +1,641 @@
+//! Named background tasks with health reporting and ordered shutdown.
+
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use parking_lot::{Condvar, Mutex};
+
+use crate::cancel::CancellationToken;
+use crate::error::Result;
+
+/// How long a cancelled job gets to return before its thread is detached.
+const CANCEL_GRACE: Duration = Duration::from_millis(250);
+
+/// Health of a background task.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaskHealth {
+    /// Task name.
+    pub name: String,
+    /// Whether the task is still scheduled.
+    pub running: bool,
+    /// Number of completed runs.
+    pub runs: u64,
+    /// Number of runs that returned an error or panicked.
+    pub failures: u64,
+    /// Error of the most recent failed run.
+    pub last_error: Option<String>,
+    /// When the task last ran.
+    pub last_run: Option<Instant>,
+}
+
+impl TaskHealth {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            running: true,
+            runs: 0,
+            failures: 0,
+            last_error: None,
+            last_run: None,
+        }
+    }
+
+    /// Check if the most recent run succeeded.
+    pub fn is_healthy(&self) -> bool {
+        self.running && self.last_error.is_none()
+    }
+}
+
+/// What to do with jobs that are still running when tasks are shut down.
+///
+/// Cancelled jobs see their [`CancellationToken`] cancelled, see
+/// [`TaskSupervisor::spawn_cancellable`]; a job that does not return shortly
+/// after is detached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ShutdownPolicy {
+    /// Wait for in-flight jobs to finish.
+    #[default]
+    Finish,
+    /// Wait up to a deadline, then cancel jobs that are still running.
+    FinishWithin(Duration),
+    /// Cancel in-flight jobs immediately.
+    Cancel,
+}
+
+/// How a plugin's shutdown went.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PluginShutdownOutcome {
+    /// The cleanup hook, if any, finished successfully.
+    Stopped,
+    /// The cleanup hook failed with this message.
+    Failed(String),
+    /// The cleanup hook did not finish before the deadline and the plugin's
+    /// worker process was killed.
+    Killed,
+    /// The cleanup hook did not finish before the deadline and could not be
+    /// interrupted; the plugin is unloaded once it returns.
+    TimedOut,
+}
+
+/// Outcome of shutting down background tasks and plugins.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShutdownReport {
+    /// Tasks that stopped between jobs or whose job finished.
+    pub stopped: Vec<String>,
+    /// Tasks whose running job outlived the deadline and was cancelled, in
+    /// shutdown order.
+    ///
+    /// The job may have stopped part way, or been detached because it
+    /// ignored the cancellation, so hosts may want to re-run them on the
+    /// next start.
+    pub interrupted: Vec<String>,
+    /// Plugins that were running, sorted by name, and how they stopped.
+    ///
+    /// Only filled in by
+    /// [`PluginRuntime::shutdown_with_timeout`](crate::PluginRuntime::shutdown_with_timeout).
+    pub plugins: Vec<(String, PluginShutdownOutcome)>,
+}
+
+impl ShutdownReport {
+    /// Check if every task stopped without interrupting a job and every
+    /// plugin stopped cleanly.
+    pub fn is_clean(&self) -> bool {
+        self.interrupted.is_empty()
+            && self
+                .plugins
+                .iter()
+                .all(|(_, outcome)| *outcome == PluginShutdownOutcome::Stopped)
+    }
+}
+
+struct TaskShared {
+    stopped: Mutex<bool>,
+    wake: Condvar,
+    /// Set by [`TaskWaker::wake`] and consumed before the task sleeps, so
+    /// a wake during a run is not lost.
+    woken: AtomicBool,
+    in_flight: AtomicBool,
+    cancel: CancellationToken,
+    exited: Mutex<bool>,
+    exit: Condvar,
+    health: Mutex<TaskHealth>,
+}
+
+impl TaskShared {
+    /// Wait until the task's thread exits or the deadline passes.
+    fn wait_exit(&self, deadline: Instant) -> bool {
+        let mut exited = self.exited.lock();
+        while !*exited {
+            if self.exit.wait_until(&mut exited, deadline).timed_out() {
+                break;
+            }
+        }
+        *exited
+    }
+}
+
+/// Marks the task's thread as exited however it returns.
+struct ExitGuard(Arc<TaskShared>);
+
+impl Drop for ExitGuard {
+    fn drop(&mut self) {
+        *self.0.exited.lock() = true;
+        self.0.exit.notify_all();
+    }
+}
+
+/// Wakes a task spawned with [`TaskSupervisor::spawn_scheduled`] to run its
+/// job before its deadline.
+#[derive(Clone)]
+pub struct TaskWaker(Arc<TaskShared>);
+
+impl TaskWaker {
+    /// Run the job again as soon as its current run, if any, finishes.
+    pub fn wake(&self) {
+        self.0.woken.store(true, Ordering::SeqCst);
+        // Notify under the lock so a task about to sleep sees the flag
+        let _stopped = self.0.stopped.lock();
+        self.0.wake.notify_all();
+    }
+}
+
+impl std::fmt::Debug for TaskWaker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("TaskWaker")
+            .field(&self.0.health.lock().name)
+            .finish()
+    }
+}
+
+struct Task {
+    shared: Arc<TaskShared>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Task {
+    /// Stop the task, waiting for an in-flight job until the deadline and
+    /// cancelling it after that.
+    ///
+    /// Returns false if the job was cancelled. A task stopped from its own
+    /// job is not waited for: it exits once the job returns.
+    fn stop(&mut self, deadline: Option<Instant>) -> bool {
+        *self.shared.stopped.lock() = true;
+        self.shared.wake.notify_all();
+
+        let mut completed = true;
+        let own_thread = self
+            .handle
+            .as_ref()
+            .is_some_and(|handle| handle.thread().id() == std::thread::current().id());
+        if own_thread {
+            // Joining would wait for the job calling us
+            self.handle = None;
+        } else if let Some(handle) = self.handle.take() {
+            // An idle task sees the stop flag before starting another job
+            if let (Some(deadline), true) = (deadline, self.shared.in_flight.load(Ordering::SeqCst))
+            {
+                if !self.shared.wait_exit(deadline) {
+                    completed = false;
+                    self.shared.cancel.cancel();
+                }
+            }
+
+            if completed || self.shared.wait_exit(Instant::now() + CANCEL_GRACE) {
+                let _ = handle.join();
+            } else {
+                tracing::warn!(
+                    "Background task {} ignored cancellation, detaching it",
+                    self.shared.health.lock().name
+                );
+            }
+        }
+
+        self.shared.health.lock().running = false;
+        completed
+    }
+}
+
+/// Owns the runtime's background threads.
+///
+/// Every periodic job runs as a named task so its health can be inspected,
+/// and [`shutdown`](TaskSupervisor::shutdown) stops tasks in the reverse
+/// order they were spawned, so later tasks may rely on earlier ones.
+#[derive(Default)]
+pub struct TaskSupervisor {
+    tasks: Mutex<Vec<(String, Task)>>,
+}
+
+impl TaskSupervisor {
+    /// Create an empty task supervisor.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run a job every `interval` on a dedicated thread, starting immediately.
+    ///
+    /// Errors and panics are recorded in the task's health; a panicking task
+    /// is not run again. Spawning a task with the name of an existing one
+    /// replaces it.
+    pub fn spawn_periodic<F>(
+        &self,
+        name: impl Into<String>,
+        interval: Duration,
+        mut job: F,
+    ) -> Result<()>
+    where
+        F: FnMut() -> Result<()> + Send + 'static,
+    {
+        self.spawn_cancellable(name, interval, move |_| job())
+    }
+
+    /// Like [`spawn_periodic`](Self::spawn_periodic), passing the job a token
+    /// that is cancelled when shutdown stops waiting for it.
+    ///
+    /// Long jobs should check the token between steps and return early; a
+    /// job still running shortly after cancellation is detached.
+    pub fn spawn_cancellable<F>(
+        &self,
+        name: impl Into<String>,
+        interval: Duration,
+        mut job: F,
+    ) -> Result<()>
+    where
+        F: FnMut(&CancellationToken) -> Result<()> + Send + 'static,
+    {
+        self.spawn_task(name.into(), Some(interval), move |token| {
+            job(token).map(|()| Some(Instant::now() + interval))
+        })
+        .map(|_| ())
+    }
+
+    /// Run a job on a dedicated thread, starting immediately, and again at
+    /// the deadline each run returns.
+    ///
+    /// A job returning `None`, or failing, sleeps until woken through the
+    /// returned [`TaskWaker`], which also runs the job before a pending
+    /// deadline. Suits work driven by events, which should not wake the
+    /// thread while there is nothing to do.
+    pub fn spawn_scheduled<F>(&self, name: impl Into<String>, mut job: F) -> Result<TaskWaker>
+    where
+        F: FnMut() -> Result<Option<Instant>> + Send + 'static,
+    {
+        self.spawn_task(name.into(), None, move |_| job())
+    }
+
+    /// Spawn a task whose job returns when to run next; a failed run is
+    /// retried after `retry`, or when woken if that is `None`.
+    fn spawn_task<F>(&self, name: String, retry: Option<Duration>, mut job: F) -> Result<TaskWaker>
+    where
+        F: FnMut(&CancellationToken) -> Result<Option<Instant>> + Send + 'static,
+    {
+        self.stop(&name);
+
+        let shared = Arc::new(TaskShared {
+            stopped: Mutex::new(false),
+            wake: Condvar::new(),
+            woken: AtomicBool::new(false),
+            in_flight: AtomicBool::new(false),
+            cancel: CancellationToken::new(),
+            exited: Mutex::new(false),
+            exit: Condvar::new(),
+            health: Mutex::new(TaskHealth::new(&name)),
+        });
+
+        let task_shared = shared.clone();
+        let task_name = name.clone();
+        let exit_guard = ExitGuard(shared.clone());
+        let handle = std::thread::Builder::new()
+            .name(format!("fusabi-{}", name))
+            .spawn(move || {
+                let _exit_guard = exit_guard;
+                loop {
+                    {
+                        // Mark the job in flight under the lock so `stop` never
+                        // misses a job that starts concurrently
+                        let stopped = task_shared.stopped.lock();
+                        if *stopped {
+                            return;
+                        }
+                        task_shared.in_flight.store(true, Ordering::SeqCst);
+                    }
+                    let result = catch_unwind(AssertUnwindSafe(|| job(&task_shared.cancel)));
+                    task_shared.in_flight.store(false, Ordering::SeqCst);
+
+                    let next = {
+                        let mut health = task_shared.health.lock();
+                        health.runs += 1;
+                        health.last_run = Some(Instant::now());
+                        match result {
+                            Ok(Ok(next)) => {
+                                health.last_error = None;
+                                next
+                            }
+                            Ok(Err(e)) => {
+                                tracing::warn!("Background task {} failed: {}", task_name, e);
+                                health.failures += 1;
+                                health.last_error = Some(e.to_string());
+                                retry.map(|retry| Instant::now() + retry)
+                            }
+                            Err(_) => {
+                                tracing::error!("Background task {} panicked", task_name);
+                                health.failures += 1;
+                                health.last_error = Some("task panicked".to_string());
+                                health.running = false;
+                                return;
+                            }
+                        }
+                    };
+
+                    let mut stopped = task_shared.stopped.lock();
+                    while !*stopped && !task_shared.woken.swap(false, Ordering::SeqCst) {
+                        match next {
+                            Some(deadline) => {
+                                if task_shared
+                                    .wake
+                                    .wait_until(&mut stopped, deadline)
+                                    .timed_out()
+                                {
+                                    break;
+                                }
+                            }
+                            None => task_shared.wake.wait(&mut stopped),
+                        }
+                    }
+                    if *stopped {
+                        return;
+                    }
+                }
+            })?;
+
+        self.tasks.lock().push((
+            name,
+            Task {
+                shared: shared.clone(),
+                handle: Some(handle),
+            },
+        ));
+        Ok(TaskWaker(shared))
+    }
+
+    /// Stop a task and wait for it to finish. Returns false if it was not found.
+    ///
+    /// A job may stop its own task, or shut the supervisor down; its task
+    /// then exits once the job returns instead of being waited for.
+    pub fn stop(&self, name: &str) -> bool {
+        let task = {
+            let mut tasks = self.tasks.lock();
+            let Some(index) = tasks.iter().position(|(n, _)| n == name) else {
+                return false;
+            };
+            tasks.remove(index)
+        };
+
+        // Join outside the lock so the task may inspect the supervisor
+        let (_, mut task) = task;
+        task.stop(None);
+        true
+    }
+
+    /// Check if a task exists and is still scheduled.
+    pub fn is_running(&self, name: &str) -> bool {
+        self.tasks
+            .lock()
+            .iter()
+            .any(|(n, t)| n == name && t.shared.health.lock().running)
+    }
+
+    /// Get task names in spawn order.
+    pub fn names(&self) -> Vec<String> {
+        self.tasks.lock().iter().map(|(n, _)| n.clone()).collect()
+    }
+
+    /// Get the health of every task in spawn order.
+    pub fn health(&self) -> Vec<TaskHealth> {
+        self.tasks
+            .lock()
+            .iter()
+            .map(|(_, t)| t.shared.health.lock().clone())
+            .collect()
+    }
+
+    /// Stop every task, most recently spawned first, waiting for in-flight jobs.
+    pub fn shutdown(&self) -> ShutdownReport {
+        self.shutdown_with(ShutdownPolicy::Finish)
+    }
+
+    /// Stop every task, most recently spawned first.
+    ///
+    /// A `FinishWithin` deadline applies to the whole shutdown, not to each task.
+    pub fn shutdown_with(&self, policy: ShutdownPolicy) -> ShutdownReport {
+        let deadline = match policy {
+            ShutdownPolicy::Finish => None,
+            ShutdownPolicy::FinishWithin(timeout) => Some(Instant::now() + timeout),
+            ShutdownPolicy::Cancel => Some(Instant::now()),
+        };
+
+        let tasks = std::mem::take(&mut *self.tasks.lock());
+        let mut report = ShutdownReport::default();
+        for (name, mut task) in tasks.into_iter().rev() {
+            tracing::debug!("Stopping background task {}", name);
+            if task.stop(deadline) {
+                report.stopped.push(name);
+            } else {
+                tracing::warn!("Background task {} interrupted during shutdown", name);
+                report.interrupted.push(name);
+            }
+        }
+        report
+    }
+}
+
+impl Drop for TaskSupervisor {
+    fn drop(&mut self) {
+        let _ = self.shutdown();
+    }
+}
+
+impl std::fmt::Debug for TaskSupervisor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TaskSupervisor")
+            .field("tasks", &self.names())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn wait_for(condition: impl Fn() -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !condition() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn test_task_health() {
+        let tasks = TaskSupervisor::new();
+        let runs = Arc::new(AtomicU32::new(0));
+        let counter = runs.clone();
+
+        tasks
+            .spawn_periodic("flaky", Duration::from_millis(1), move || {
+                if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                    Err(crate::Error::execution_failed("first run"))
+                } else {
+                    Ok(())
+                }
+            })
+            .unwrap();
+        tasks
+            .spawn_periodic("broken", Duration::from_millis(1), || panic!("boom"))
+            .unwrap();
+
+        wait_for(|| runs.load(Ordering::SeqCst) >= 2 && !tasks.is_running("broken"));
+
+        let health = tasks.health();
+        assert_eq!(health[0].name, "flaky");
+        assert_eq!(health[0].failures, 1);
+        assert!(health[0].is_healthy());
+        assert_eq!(health[1].last_error.as_deref(), Some("task panicked"));
+        assert!(!health[1].is_healthy());
+    }
+
+    #[test]
+    fn test_stop_and_shutdown() {
+        let tasks = TaskSupervisor::new();
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        for name in ["first", "second", "third"] {
+            let order = order.clone();
+            tasks
+                .spawn_periodic(name, Duration::from_secs(60), move || {
+                    order.lock().push(name);
+                    Ok(())
+                })
+                .unwrap();
+        }
+        wait_for(|| order.lock().len() == 3);
+
+        assert!(tasks.stop("second"));
+        assert!(!tasks.stop("second"));
+        assert_eq!(tasks.names(), vec!["first", "third"]);
+
+        // Long intervals do not delay shutdown
+        let started = Instant::now();
+        tasks.shutdown();
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(tasks.names().is_empty());
+    }
+
+    #[test]
+    fn test_task_stops_itself() {
+        let tasks = Arc::new(TaskSupervisor::new());
+        let runs = Arc::new(AtomicU32::new(0));
+
+        let (supervisor, counter) = (Arc::downgrade(&tasks), runs.clone());
+        tasks
+            .spawn_periodic("once", Duration::from_millis(1), move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+                if let Some(tasks) = supervisor.upgrade() {
+                    assert!(tasks.stop("once"));
+                }
+                Ok(())
+            })
+            .unwrap();
+
+        wait_for(|| tasks.names().is_empty());
+        assert!(tasks.names().is_empty());
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_shutdown_policy() {
+        let tasks = TaskSupervisor::new();
+        let (started_tx, started_rx) = std::sync::mpsc::channel();
+        let release = Arc::new(AtomicBool::new(false));
+
+        let gate = release.clone();
+        tasks
+            .spawn_periodic("slow", Duration::from_secs(60), move || {
+                let _ = started_tx.send(());
+                while !gate.load(Ordering::SeqCst) {
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+                Ok(())
+            })
+            .unwrap();
+        tasks
+            .spawn_periodic("idle", Duration::from_secs(60), || Ok(()))
+            .unwrap();
+        started_rx.recv().unwrap();
+        wait_for(|| tasks.health()[1].runs == 1);
+
+        let report = tasks.shutdown_with(ShutdownPolicy::FinishWithin(Duration::from_millis(20)));
+        assert_eq!(report.stopped, vec!["idle"]);
+        assert_eq!(report.interrupted, vec!["slow"]);
+        assert!(!report.is_clean());
+        release.store(true, Ordering::SeqCst);
+    }
+
+    #[test]
+    fn test_cancel_running_job() {
+        let tasks = TaskSupervisor::new();
+        let (started_tx, started_rx) = std::sync::mpsc::channel();
+        let (cancelled_tx, cancelled_rx) = std::sync::mpsc::channel();
+
+        tasks
+            .spawn_cancellable("sweep", Duration::from_secs(60), move |token| {
+                let _ = started_tx.send(());
+                while !token.is_cancelled() {
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+                let _ = cancelled_tx.send(());
+                token.check("sweep")
+            })
+            .unwrap();
+        started_rx.recv().unwrap();
+
+        let started = Instant::now();
+        let report = tasks.shutdown_with(ShutdownPolicy::Cancel);
+        assert!(started.elapsed() < CANCEL_GRACE);
+        assert_eq!(report.interrupted, vec!["sweep"]);
+        // The job saw the cancellation and its thread was joined
+        assert!(cancelled_rx.try_recv().is_ok());
+    }
+
+    #[test]
+    fn test_scheduled_task() {
+        let tasks = TaskSupervisor::new();
+        let runs = Arc::new(AtomicU32::new(0));
+        let deadline = Arc::new(Mutex::new(None));
+
+        let (counter, next) = (runs.clone(), deadline.clone());
+        let waker = tasks
+            .spawn_scheduled("flush", move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Ok(next.lock().take())
+            })
+            .unwrap();
+        wait_for(|| runs.load(Ordering::SeqCst) == 1);
+
+        // Without a deadline the task sleeps until woken
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+        *deadline.lock() = Some(Instant::now() + Duration::from_millis(10));
+        waker.wake();
+        wait_for(|| runs.load(Ordering::SeqCst) == 3);
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+
+        let started = Instant::now();
+        tasks.shutdown();
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,317 @@
+//! Tenant-level capability restrictions and regional settings.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+
+use fusabi_host::{Capabilities, Value};
+
+use crate::audit::{AuditOutcome, AuditRecord, AuditSink};
+use crate::error::{Error, Result};
+use crate::plugin::PluginHandle;
+use crate::regional::RegionalSettings;
+
+/// Capabilities withheld from plugins when acting for one tenant.
+///
+/// Policies only remove capabilities; a plugin never gets more for a
+/// tenant than its manifest declares.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TenantPolicy {
+    denied: HashMap<String, HashSet<String>>,
+    regional: Option<RegionalSettings>,
+}
+
+impl TenantPolicy {
+    /// Create a policy without restrictions.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Withhold a capability from one plugin.
+    pub fn deny(mut self, plugin: impl Into<String>, capability: impl Into<String>) -> Self {
+        self.denied
+            .entry(plugin.into())
+            .or_default()
+            .insert(capability.into());
+        self
+    }
+
+    /// Withhold a capability from every plugin.
+    pub fn deny_all(self, capability: impl Into<String>) -> Self {
+        self.deny("*", capability)
+    }
+
+    /// Format for the tenant's locale and timezone instead of the plugin's.
+    pub fn with_regional_settings(mut self, settings: RegionalSettings) -> Self {
+        self.regional = Some(settings);
+        self
+    }
+
+    /// Get the tenant's locale and timezone, if set.
+    pub fn regional_settings(&self) -> Option<&RegionalSettings> {
+        self.regional.as_ref()
+    }
+
+    /// Get the capabilities withheld from a plugin.
+    pub fn denied_for(&self, plugin: &str) -> HashSet<String> {
+        [plugin, "*"]
+            .iter()
+            .filter_map(|key| self.denied.get(*key))
+            .flatten()
+            .cloned()
+            .collect()
+    }
+}
+
+/// A plugin activated for a tenant, with the tenant's restrictions resolved.
+///
+/// Calls through [`call`](Self::call) or [`plugin`](Self::plugin) run on an
+/// engine created without the withheld capabilities, so the engine enforces
+/// them. Host functions serving the plugin's requests check them with
+/// [`check_capability`](Self::check_capability) and the scoped access checks.
+#[derive(Clone)]
+pub struct TenantActivation {
+    tenant: String,
+    plugin: PluginHandle,
+    restricted: BTreeSet<String>,
+    regional: RegionalSettings,
+    audit: Option<Arc<dyn AuditSink>>,
+}
+
+impl TenantActivation {
+    /// Resolve a tenant policy for a plugin, auditing withheld capabilities.
+    pub(crate) fn new(
+        tenant: &str,
+        plugin: PluginHandle,
+        policy: Option<&TenantPolicy>,
+        audit: Option<Arc<dyn AuditSink>>,
+    ) -> Self {
+        let name = plugin.name();
+        let denied = policy.map(|p| p.denied_for(&name)).unwrap_or_default();
+        let restricted: BTreeSet<String> = plugin
+            .inner()
+            .manifest()
+            .capabilities
+            .iter()
+            .map(|spec| spec.name.clone())
+            .filter(|cap| denied.contains(cap))
+            .collect();
+        let plugin = restricted_handle(plugin, &restricted);
+        let regional = policy
+            .and_then(TenantPolicy::regional_settings)
+            .cloned()
+            .unwrap_or_else(|| plugin.inner().regional_settings());
+
+        let activation = Self {
+            tenant: tenant.to_string(),
+            plugin,
+            restricted,
+            regional,
+            audit,
+        };
+        for cap in &activation.restricted {
+            activation.record(
+                "activate",
+                cap,
+                AuditOutcome::Denied("restricted by tenant policy".to_string()),
+            );
+        }
+        activation.record("activate", &name, AuditOutcome::Succeeded);
+        activation
+    }
+
+    /// Get the tenant.
+    pub fn tenant(&self) -> &str {
+        &self.tenant
+    }
+
+    /// Get the activated plugin, whose calls run without the withheld
+    /// capabilities.
+    pub fn plugin(&self) -> &PluginHandle {
+        &self.plugin
+    }
+
+    /// Call a function exported by the plugin on behalf of the tenant.
+    pub fn call(&self, function: &str, args: &[Value]) -> Result<Value> {
+        self.plugin.call(function, args)
+    }
+
+    /// Get the declared capabilities withheld for this tenant.
+    pub fn restricted(&self) -> Vec<String> {
+        self.restricted.iter().cloned().collect()
+    }
+
+    /// Get the locale and timezone the plugin formats for on behalf of this
+    /// tenant: the tenant's if set, otherwise the plugin's.
+    pub fn regional_settings(&self) -> &RegionalSettings {
+        &self.regional
+    }
+
+    /// Get the capabilities the plugin has for this tenant.
+    pub fn capabilities(&self) -> Capabilities {
+        let mut caps = Capabilities::none();
+        for spec in &self.plugin.inner().manifest().capabilities {
+            if self.restricted.contains(&spec.name) {
+                continue;
+            }
+            if let Some(cap) = fusabi_host::Capability::from_name(&spec.name) {
+                caps.grant(cap);
+            }
+        }
+        caps
+    }
+
+    /// Check that the plugin holds a capability for this tenant.
+    pub fn check_capability(&self, cap: &str) -> Result<()> {
+        let result = if self.restricted.contains(cap) {
+            Err(self.restriction(cap))
+        } else if self.plugin.inner().requires_capability(cap) {
+            Ok(())
+        } else {
+            Err(Error::UndeclaredCapability(cap.to_string()))
+        };
+        self.record_result(cap, cap, &result);
+        result
+    }
+
+    /// Check a filesystem access against the tenant's restrictions and the
+    /// plugin's declared scope.
+    pub fn check_path_access(&self, cap: &str, path: &Path) -> Result<()> {
+        if self.restricted.contains(cap) {
+            let result = Err(self.restriction(cap));
+            self.record_result(cap, &path.display().to_string(), &result);
+            return result;
+        }
+        self.plugin.inner().check_path_access(cap, path)
+    }
+
+    /// Check a network access against the tenant's restrictions and the
+    /// plugin's declared scope.
+    pub fn check_host_access(&self, cap: &str, host: &str) -> Result<()> {
+        if self.restricted.contains(cap) {
+            let result = Err(self.restriction(cap));
+            self.record_result(cap, host, &result);
+            return result;
+        }
+        self.plugin.inner().check_host_access(cap, host)
+    }
+
+    fn restriction(&self, cap: &str) -> Error {
+        Error::capability_not_granted(cap, format!("restricted for tenant {}", self.tenant))
+    }
+
+    fn record_result(&self, operation: &str, target: &str, result: &Result<()>) {
+        let outcome = match result {
+            Ok(()) => AuditOutcome::Succeeded,
+            Err(e) => AuditOutcome::Denied(e.to_string()),
+        };
+        self.record(operation, target, outcome);
+    }
+
+    fn record(&self, operation: &str, target: &str, outcome: AuditOutcome) {
+        if let Some(ref sink) = self.audit {
+            sink.record(&AuditRecord::new(
+                format!("{}@{}", self.plugin.name(), self.tenant),
+                operation,
+                Some(target.to_string()),
+                outcome,
+            ));
+        }
+    }
+}
+
+/// Get a handle whose calls run on an engine without the `restricted`
+/// capabilities.
+///
+/// Builtin and composite plugins run no engine of their own and keep the
+/// handle unchanged; names that are not engine capabilities are left to the
+/// activation's checks.
+fn restricted_handle(plugin: PluginHandle, restricted: &BTreeSet<String>) -> PluginHandle {
+    let engine_caps: Vec<&String> = restricted
+        .iter()
+        .filter(|cap| fusabi_host::Capability::from_name(cap).is_some())
+        .collect();
+    if engine_caps.is_empty() {
+        return plugin;
+    }
+    // Only fails for native plugins
+    plugin
+        .with_capabilities_removed(engine_caps.into_iter().cloned())
+        .unwrap_or(plugin)
+}
+
+impl std::fmt::Debug for TenantActivation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TenantActivation")
+            .field("tenant", &self.tenant)
+            .field("plugin", &self.plugin.name())
+            .field("restricted", &self.restricted)
+            .field("regional", &self.regional)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::MemoryAuditLog;
+    use crate::manifest::{CapabilitySpec, Manifest, ManifestBuilder};
+    use crate::plugin::Plugin;
+
+    #[test]
+    fn test_tenant_restrictions() {
+        let policy = TenantPolicy::new()
+            .deny_all("net:request")
+            .deny("other", "fs:read");
+        assert_eq!(policy.denied_for("weather").len(), 1);
+        assert_eq!(policy.denied_for("other").len(), 2);
+
+        let manifest = ManifestBuilder::new("weather", "1.0.0")
+            .capability("fs:read")
+            .capability(CapabilitySpec::new("net:request").with_hosts(["api.example.com"]))
+            .build_unchecked();
+        let plugin = PluginHandle::new(Plugin::new(manifest));
+        let log = Arc::new(MemoryAuditLog::new(10));
+
+        let activation =
+            TenantActivation::new("tenant-a", plugin, Some(&policy), Some(log.clone()));
+        assert_eq!(activation.restricted(), vec!["net:request"]);
+        assert_eq!(
+            activation.plugin().removed_capabilities(),
+            vec!["net:request"]
+        );
+        assert!(activation.check_capability("fs:read").is_ok());
+        assert!(matches!(
+            activation.check_host_access("net:request", "api.example.com"),
+            Err(Error::CapabilityNotGranted { .. })
+        ));
+        assert!(!activation
+            .capabilities()
+            .has(fusabi_host::Capability::NetRequest));
+
+        let records = log.records();
+        assert_eq!(records[0].principal, "weather@tenant-a");
+        assert_eq!(records[0].target.as_deref(), Some("net:request"));
+        assert!(matches!(records[0].outcome, AuditOutcome::Denied(_)));
+        assert!(matches!(
+            records.last().unwrap().outcome,
+            AuditOutcome::Denied(_)
+        ));
+    }
+
+    #[test]
+    fn test_tenant_regional_settings() {
+        let plugin = PluginHandle::new(Plugin::new(Manifest::new("clock", "1.0.0")));
+        plugin
+            .inner()
+            .set_regional_settings(RegionalSettings::new("en-GB", "Europe/London"));
+        let policy = TenantPolicy::new()
+            .with_regional_settings(RegionalSettings::new("ja-JP", "Asia/Tokyo"));
+
+        let activation = TenantActivation::new("tokyo", plugin.clone(), Some(&policy), None);
+        assert_eq!(activation.regional_settings().timezone, "Asia/Tokyo");
+        let activation = TenantActivation::new("london", plugin, None, None);
+        assert_eq!(activation.regional_settings().locale, "en-GB");
+        assert!(!activation.plugin().is_restricted());
+    }
+}
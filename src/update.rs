@@ -0,0 +1,558 @@
+//! Update checks for plugins installed from remote sources.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use dashmap::DashMap;
+use parking_lot::RwLock;
+
+use crate::cancel::CancellationToken;
+use crate::error::Result;
+use crate::loader::PluginLoader;
+use crate::manifest::{ApiVersion, Dependency};
+use crate::network::{NetworkConfig, OfflineSwitch};
+use crate::origin::PluginOrigin;
+use crate::registry::PluginRegistry;
+use crate::tasks::TaskSupervisor;
+
+/// A published plugin release.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Release {
+    /// Release version.
+    pub version: String,
+    /// Where the release can be fetched from.
+    pub location: Option<String>,
+}
+
+impl Release {
+    /// Create a release.
+    pub fn new(version: impl Into<String>) -> Self {
+        Self {
+            version: version.into(),
+            location: None,
+        }
+    }
+
+    /// Set the release location.
+    pub fn with_location(mut self, location: impl Into<String>) -> Self {
+        self.location = Some(location.into());
+        self
+    }
+}
+
+/// Source of plugin releases, such as an index or URL.
+///
+/// The runtime does no networking itself; hosts implement this trait with
+/// their HTTP client of choice, applying the proxy, CA bundle and timeouts of
+/// the [`NetworkConfig`] passed in. Failures reported as
+/// [`Error::Network`](crate::Error::Network) are retried according to the
+/// same configuration.
+pub trait UpdateSource: Send + Sync {
+    /// Get the latest published release of a plugin.
+    fn latest(&self, plugin: &str, network: &NetworkConfig) -> Result<Option<Release>>;
+
+    /// Download a release and return the path of its manifest.
+    fn fetch(&self, plugin: &str, release: &Release, network: &NetworkConfig) -> Result<PathBuf>;
+}
+
+/// What to do when a newer release is found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdatePolicy {
+    /// Only emit [`UpdateEvent::UpdateAvailable`].
+    Notify,
+    /// Upgrade automatically if the release satisfies a version requirement.
+    AutoUpgrade {
+        /// Version requirement, e.g. `1.2` or `=1.4.0`.
+        constraint: String,
+    },
+}
+
+/// Event emitted by the update checker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdateEvent {
+    /// A newer release exists but was not installed.
+    UpdateAvailable {
+        /// Plugin name.
+        plugin: String,
+        /// Installed version.
+        current: String,
+        /// Latest published version.
+        latest: String,
+    },
+    /// A newer release was installed.
+    Upgraded {
+        /// Plugin name.
+        plugin: String,
+        /// Previously installed version.
+        from: String,
+        /// Installed version.
+        to: String,
+    },
+    /// Checking or upgrading failed.
+    Failed {
+        /// Plugin name.
+        plugin: String,
+        /// Error message.
+        error: String,
+    },
+}
+
+/// Configuration for the update checker.
+#[derive(Debug, Clone)]
+pub struct UpdateConfig {
+    /// Interval between checks.
+    pub interval: Duration,
+    /// Policy for plugins tracked without an explicit policy.
+    pub default_policy: UpdatePolicy,
+    /// Switch that suspends checks while offline.
+    pub offline: OfflineSwitch,
+    /// Network settings passed to the source.
+    pub network: NetworkConfig,
+}
+
+impl Default for UpdateConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(3600),
+            default_policy: UpdatePolicy::Notify,
+            offline: OfflineSwitch::default(),
+            network: NetworkConfig::default(),
+        }
+    }
+}
+
+impl UpdateConfig {
+    /// Create a new update configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the polling interval.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Set the default update policy.
+    pub fn with_default_policy(mut self, policy: UpdatePolicy) -> Self {
+        self.default_policy = policy;
+        self
+    }
+
+    /// Set the offline switch.
+    pub fn with_offline_switch(mut self, offline: OfflineSwitch) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    /// Set the network settings passed to the source.
+    pub fn with_network(mut self, network: NetworkConfig) -> Self {
+        self.network = network;
+        self
+    }
+}
+
+type UpdateHandler = Box<dyn Fn(&UpdateEvent) + Send + Sync>;
+
+struct CheckerInner {
+    config: UpdateConfig,
+    source: Arc<dyn UpdateSource>,
+    loader: Arc<PluginLoader>,
+    registry: Arc<PluginRegistry>,
+    tracked: DashMap<String, UpdatePolicy>,
+    handlers: RwLock<Vec<UpdateHandler>>,
+}
+
+/// Name of the polling task.
+const UPDATE_TASK: &str = "update-checks";
+
+/// Polls an [`UpdateSource`] for new releases of installed plugins.
+pub struct UpdateChecker {
+    inner: Arc<CheckerInner>,
+    tasks: Arc<TaskSupervisor>,
+    // Whether `start` spawned the polling task, which dropping stops
+    started: AtomicBool,
+}
+
+impl UpdateChecker {
+    /// Create an update checker.
+    pub fn new(
+        config: UpdateConfig,
+        source: Arc<dyn UpdateSource>,
+        loader: Arc<PluginLoader>,
+        registry: Arc<PluginRegistry>,
+    ) -> Self {
+        Self {
+            inner: Arc::new(CheckerInner {
+                config,
+                source,
+                loader,
+                registry,
+                tracked: DashMap::new(),
+                handlers: RwLock::new(Vec::new()),
+            }),
+            tasks: Arc::new(TaskSupervisor::new()),
+            started: AtomicBool::new(false),
+        }
+    }
+
+    /// Poll from the `update-checks` task of `tasks`, e.g. the runtime's
+    /// supervisor, instead of a supervisor of its own.
+    pub fn with_tasks(mut self, tasks: Arc<TaskSupervisor>) -> Self {
+        self.tasks = tasks;
+        self
+    }
+
+    /// Get the configuration.
+    pub fn config(&self) -> &UpdateConfig {
+        &self.inner.config
+    }
+
+    /// Track a plugin using the default policy.
+    ///
+    /// If the plugin is loaded, the policy of its origin applies instead:
+    /// plugins of origins without updates are not tracked, and an origin's
+    /// update policy replaces the default.
+    pub fn track(&self, plugin: impl Into<String>) {
+        let plugin = plugin.into();
+        let mut policy = self.inner.config.default_policy.clone();
+        if let Some(handle) = self.inner.registry.get(&plugin) {
+            let origin = self
+                .inner
+                .loader
+                .config()
+                .origin_policy(handle.info().origin);
+            if !origin.updates {
+                tracing::debug!("Not tracking {}: its origin disables updates", plugin);
+                return;
+            }
+            if let Some(origin_policy) = origin.update_policy {
+                policy = origin_policy;
+            }
+        }
+        self.inner.tracked.insert(plugin, policy);
+    }
+
+    /// Track a plugin with a specific policy.
+    pub fn track_with_policy(&self, plugin: impl Into<String>, policy: UpdatePolicy) {
+        self.inner.tracked.insert(plugin.into(), policy);
+    }
+
+    /// Stop tracking a plugin.
+    pub fn untrack(&self, plugin: &str) {
+        self.inner.tracked.remove(plugin);
+    }
+
+    /// Get the names of tracked plugins.
+    pub fn tracked(&self) -> Vec<String> {
+        self.inner.tracked.iter().map(|e| e.key().clone()).collect()
+    }
+
+    /// Add an update event handler.
+    pub fn on_event<F>(&self, handler: F)
+    where
+        F: Fn(&UpdateEvent) + Send + Sync + 'static,
+    {
+        self.inner.handlers.write().push(Box::new(handler));
+    }
+
+    /// Check every tracked plugin once.
+    ///
+    /// Fails with [`Error::OfflineMode`](crate::Error::OfflineMode) while
+    /// offline mode is enabled.
+    pub fn check_now(&self) -> Result<Vec<UpdateEvent>> {
+        self.inner.config.offline.ensure_online("update checks")?;
+        Ok(self.inner.check_all(&CancellationToken::new()))
+    }
+
+    /// Start polling on a background task.
+    ///
+    /// Checks are skipped while offline mode is enabled.
+    pub fn start(&self) -> Result<()> {
+        if self.is_running() {
+            return Ok(());
+        }
+
+        let inner = self.inner.clone();
+        self.tasks
+            .spawn_cancellable(UPDATE_TASK, self.inner.config.interval, move |token| {
+                if inner.config.offline.is_offline() {
+                    tracing::debug!("Skipping update checks in offline mode");
+                } else {
+                    inner.check_all(token);
+                }
+                Ok(())
+            })?;
+        self.started.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Stop polling.
+    ///
+    /// Other tasks of a shared supervisor keep running.
+    pub fn stop(&self) {
+        self.started.store(false, Ordering::SeqCst);
+        self.tasks.stop(UPDATE_TASK);
+    }
+
+    /// Check if the checker is polling.
+    pub fn is_running(&self) -> bool {
+        self.tasks.is_running(UPDATE_TASK)
+    }
+}
+
+impl Drop for UpdateChecker {
+    fn drop(&mut self) {
+        if self.started.load(Ordering::SeqCst) {
+            self.stop();
+        }
+    }
+}
+
+impl CheckerInner {
+    /// Check tracked plugins until done or cancelled.
+    fn check_all(&self, token: &CancellationToken) -> Vec<UpdateEvent> {
+        let tracked: Vec<(String, UpdatePolicy)> = self
+            .tracked
+            .iter()
+            .map(|e| (e.key().clone(), e.value().clone()))
+            .collect();
+
+        let events: Vec<UpdateEvent> = tracked
+            .into_iter()
+            .take_while(|_| !token.is_cancelled())
+            .filter_map(|(name, policy)| {
+                self.check(&name, &policy).unwrap_or_else(|e| {
+                    Some(UpdateEvent::Failed {
+                        plugin: name.clone(),
+                        error: e.to_string(),
+                    })
+                })
+            })
+            .collect();
+
+        let handlers = self.handlers.read();
+        for event in &events {
+            for handler in handlers.iter() {
+                handler(event);
+            }
+        }
+
+        events
+    }
+
+    fn check(&self, name: &str, policy: &UpdatePolicy) -> Result<Option<UpdateEvent>> {
+        // Plugins that are not loaded have nothing to update
+        let Some(plugin) = self.registry.get(name) else {
+            return Ok(None);
+        };
+        let network = &self.config.network;
+        let Some(release) = network.retry(|| self.source.latest(name, network))? else {
+            return Ok(None);
+        };
+
+        let current = plugin.inner().version();
+        if !is_newer(&release.version, &current) {
+            return Ok(None);
+        }
+
+        let upgrade = match policy {
+            UpdatePolicy::Notify => false,
+            UpdatePolicy::AutoUpgrade { constraint } => {
+                Dependency::required(name, constraint.as_str()).is_satisfied_by(&release.version)
+            }
+        };
+
+        if !upgrade {
+            return Ok(Some(UpdateEvent::UpdateAvailable {
+                plugin: name.to_string(),
+                current,
+                latest: release.version,
+            }));
+        }
+
+        let manifest_path = network.retry(|| self.source.fetch(name, &release, network))?;
+        let previous = plugin.info();
+        plugin.inner().set_paths(Some(manifest_path), None);
+        plugin.inner().set_origin(PluginOrigin::Remote);
+
+        if let Err(e) = self.loader.reload_from_disk(&plugin) {
+            plugin
+                .inner()
+                .set_paths(previous.manifest_path, previous.entry_path);
+            plugin.inner().set_origin(previous.origin);
+            return Err(e);
+        }
+
+        tracing::info!(
+            "Upgraded plugin {} from {} to {}",
+            name,
+            current,
+            release.version
+        );
+        Ok(Some(UpdateEvent::Upgraded {
+            plugin: name.to_string(),
+            from: current,
+            to: release.version,
+        }))
+    }
+}
+
+fn is_newer(candidate: &str, current: &str) -> bool {
+    match (ApiVersion::parse(candidate), ApiVersion::parse(current)) {
+        (Ok(candidate), Ok(current)) => candidate > current,
+        _ => false,
+    }
+}
+
+impl std::fmt::Debug for UpdateChecker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UpdateChecker")
+            .field("config", &self.inner.config)
+            .field("tracked", &self.inner.tracked.len())
+            .field("running", &self.is_running())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::loader::LoaderConfig;
+    use crate::manifest::Manifest;
+    use crate::plugin::{Plugin, PluginHandle};
+    use crate::registry::RegistryConfig;
+
+    struct FixedSource {
+        version: String,
+        manifest: Option<PathBuf>,
+    }
+
+    impl UpdateSource for FixedSource {
+        fn latest(&self, _plugin: &str, _network: &NetworkConfig) -> Result<Option<Release>> {
+            Ok(Some(Release::new(self.version.clone())))
+        }
+
+        fn fetch(
+            &self,
+            plugin: &str,
+            _release: &Release,
+            _network: &NetworkConfig,
+        ) -> Result<PathBuf> {
+            self.manifest
+                .clone()
+                .ok_or_else(|| crate::Error::plugin_not_found(plugin))
+        }
+    }
+
+    fn checker(version: &str, manifest: Option<PathBuf>) -> (UpdateChecker, Arc<PluginRegistry>) {
+        let loader = Arc::new(PluginLoader::new(LoaderConfig::new()).unwrap());
+        let registry = Arc::new(PluginRegistry::new(RegistryConfig::new()));
+        let source = Arc::new(FixedSource {
+            version: version.to_string(),
+            manifest,
+        });
+        let checker = UpdateChecker::new(UpdateConfig::new(), source, loader, registry.clone());
+        (checker, registry)
+    }
+
+    #[test]
+    fn test_update_available() {
+        let (checker, registry) = checker("1.3.0", None);
+        let plugin = PluginHandle::new(Plugin::new(Manifest::new("remote", "1.2.0")));
+        registry.register(plugin).unwrap();
+
+        checker.track("remote");
+        checker.track("not-loaded");
+        assert_eq!(
+            checker.check_now().unwrap(),
+            vec![UpdateEvent::UpdateAvailable {
+                plugin: "remote".to_string(),
+                current: "1.2.0".to_string(),
+                latest: "1.3.0".to_string(),
+            }]
+        );
+
+        // Releases outside the constraint are only reported
+        checker.track_with_policy(
+            "remote",
+            UpdatePolicy::AutoUpgrade {
+                constraint: "=1.2.5".to_string(),
+            },
+        );
+        assert!(matches!(
+            checker.check_now().unwrap()[0],
+            UpdateEvent::UpdateAvailable { .. }
+        ));
+    }
+
+    #[test]
+    fn test_offline_mode() {
+        let (checker, registry) = checker("1.3.0", None);
+        let plugin = PluginHandle::new(Plugin::new(Manifest::new("remote", "1.2.0")));
+        registry.register(plugin).unwrap();
+        checker.track("remote");
+
+        checker.config().offline.set_offline(true);
+        assert!(matches!(
+            checker.check_now(),
+            Err(crate::Error::OfflineMode(_))
+        ));
+
+        checker.config().offline.set_offline(false);
+        assert_eq!(checker.check_now().unwrap().len(), 1);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_auto_upgrade() {
+        use crate::manifest::ManifestBuilder;
+
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = ManifestBuilder::new("remote", "1.4.0")
+            .source("main.fsx")
+            .build_unchecked();
+        let manifest_path = dir.path().join("plugin.toml");
+        std::fs::write(&manifest_path, manifest.to_toml().unwrap()).unwrap();
+        std::fs::write(dir.path().join("main.fsx"), "let x = 1").unwrap();
+
+        let (checker, registry) = checker("1.4.0", Some(manifest_path.clone()));
+        let plugin = PluginHandle::new(Plugin::new(Manifest::new("remote", "1.2.0")));
+        plugin
+            .inner()
+            .initialize(fusabi_host::EngineConfig::default())
+            .unwrap();
+        registry.register(plugin.clone()).unwrap();
+
+        checker.track_with_policy(
+            "remote",
+            UpdatePolicy::AutoUpgrade {
+                constraint: "1.2".to_string(),
+            },
+        );
+        assert_eq!(
+            checker.check_now().unwrap(),
+            vec![UpdateEvent::Upgraded {
+                plugin: "remote".to_string(),
+                from: "1.2.0".to_string(),
+                to: "1.4.0".to_string(),
+            }]
+        );
+        assert_eq!(plugin.inner().version(), "1.4.0");
+        assert_eq!(plugin.info().manifest_path, Some(manifest_path));
+        assert_eq!(plugin.info().origin, PluginOrigin::Remote);
+    }
+
+    #[test]
+    fn test_track_follows_origin() {
+        let (checker, registry) = checker("1.3.0", None);
+        let builtin = PluginHandle::new(Plugin::new(Manifest::new("bundled", "1.0.0")));
+        builtin.inner().set_origin(PluginOrigin::Builtin);
+        registry.register(builtin).unwrap();
+
+        checker.track("bundled");
+        assert!(checker.tracked().is_empty());
+    }
+}
@@ -0,0 +1,155 @@
+//! Dry-run validation of a plugin, reporting every problem found.
+
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::error::{Error, Result};
+
+/// A check run by [`PluginLoader::validate`](crate::PluginLoader::validate).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValidationCheck {
+    /// Parsing the manifest and validating its fields.
+    Schema,
+    /// Checking the requested capabilities are known to the engine.
+    Capabilities,
+    /// Checking the plugin's API version against the host's.
+    ApiVersion,
+    /// Checking the entry file exists.
+    EntryFile,
+    /// Compiling the source or reading the bytecode.
+    Compile,
+    /// Checking the declared exports are defined by the compiled code.
+    Exports,
+}
+
+impl ValidationCheck {
+    /// Get the check's name.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Schema => "schema",
+            Self::Capabilities => "capabilities",
+            Self::ApiVersion => "api-version",
+            Self::EntryFile => "entry-file",
+            Self::Compile => "compile",
+            Self::Exports => "exports",
+        }
+    }
+}
+
+impl fmt::Display for ValidationCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A problem found by a check.
+#[derive(Debug)]
+pub struct ValidationProblem {
+    /// Check that found the problem.
+    pub check: ValidationCheck,
+    /// The problem.
+    pub error: Error,
+}
+
+/// Outcome of validating a plugin without loading it.
+///
+/// Checks whose inputs are unavailable, such as compiling a plugin whose
+/// entry file is missing, are skipped and appear in neither list.
+#[derive(Debug)]
+pub struct ValidationReport {
+    /// Manifest that was validated.
+    pub manifest_path: PathBuf,
+    /// Plugin name, if the manifest could be parsed.
+    pub plugin: Option<String>,
+    /// Checks that passed, in order.
+    pub passed: Vec<ValidationCheck>,
+    /// Problems found, in order.
+    pub problems: Vec<ValidationProblem>,
+}
+
+impl ValidationReport {
+    pub(crate) fn new(manifest_path: PathBuf) -> Self {
+        Self {
+            manifest_path,
+            plugin: None,
+            passed: Vec::new(),
+            problems: Vec::new(),
+        }
+    }
+
+    /// Record the outcome of a check, returning its value if it passed.
+    pub(crate) fn check<T>(&mut self, check: ValidationCheck, result: Result<T>) -> Option<T> {
+        match result {
+            Ok(value) => {
+                self.passed.push(check);
+                Some(value)
+            }
+            Err(error) => {
+                self.problems.push(ValidationProblem { check, error });
+                None
+            }
+        }
+    }
+
+    /// Record a check that found every error in `errors`, or passed if
+    /// there are none.
+    pub(crate) fn check_all(&mut self, check: ValidationCheck, errors: Vec<Error>) {
+        if errors.is_empty() {
+            self.passed.push(check);
+        }
+        self.problems.extend(
+            errors
+                .into_iter()
+                .map(|error| ValidationProblem { check, error }),
+        );
+    }
+
+    /// Check if no problems were found.
+    pub fn is_valid(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.plugin.as_deref().unwrap_or("plugin");
+        if self.is_valid() {
+            return write!(f, "{} is valid", name);
+        }
+        write!(
+            f,
+            "{} has {} problem(s) in {}",
+            name,
+            self.problems.len(),
+            self.manifest_path.display()
+        )?;
+        for problem in &self.problems {
+            write!(f, "\n  [{}] {}", problem.check, problem.error)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validation_report() {
+        let mut report = ValidationReport::new(PathBuf::from("plugin.toml"));
+        report.plugin = Some("demo".to_string());
+        assert_eq!(report.check(ValidationCheck::Schema, Ok(1)), Some(1));
+        report.check_all(
+            ValidationCheck::Exports,
+            vec![
+                Error::missing_export("demo", "render"),
+                Error::missing_export("demo", "cleanup"),
+            ],
+        );
+
+        assert!(!report.is_valid());
+        assert_eq!(report.passed, vec![ValidationCheck::Schema]);
+        assert_eq!(report.problems.len(), 2);
+        assert!(report.to_string().contains("[exports]"));
+    }
+}
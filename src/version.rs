@@ -0,0 +1,273 @@
+//! Semver version requirements.
+
+use crate::error::{Error, Result};
+use crate::manifest::ApiVersion;
+
+/// Comparison operator of a requirement comparator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Exact,
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+    Tilde,
+    Caret,
+}
+
+/// A single comparator such as `>=1.2` or `~0.3.1`.
+///
+/// Missing minor or patch components act as wildcards.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Comparator {
+    op: Op,
+    major: u32,
+    minor: Option<u32>,
+    patch: Option<u32>,
+}
+
+impl Comparator {
+    fn parse(s: &str) -> Result<Self> {
+        let invalid = || Error::invalid_manifest(format!("invalid version requirement: {}", s));
+
+        let (op, rest) = if let Some(rest) = s.strip_prefix(">=") {
+            (Op::GreaterEq, rest)
+        } else if let Some(rest) = s.strip_prefix("<=") {
+            (Op::LessEq, rest)
+        } else if let Some(rest) = s.strip_prefix('>') {
+            (Op::Greater, rest)
+        } else if let Some(rest) = s.strip_prefix('<') {
+            (Op::Less, rest)
+        } else if let Some(rest) = s.strip_prefix('=') {
+            (Op::Exact, rest)
+        } else if let Some(rest) = s.strip_prefix('~') {
+            (Op::Tilde, rest)
+        } else if let Some(rest) = s.strip_prefix('^') {
+            (Op::Caret, rest)
+        } else {
+            (Op::Caret, s)
+        };
+
+        let mut parts = rest.trim().split('.');
+        let mut wildcard = false;
+        let mut component = || -> Result<Option<u32>> {
+            match parts.next() {
+                None => Ok(None),
+                Some("*" | "x" | "X") => {
+                    wildcard = true;
+                    Ok(None)
+                }
+                Some(p) => p.parse().map(Some).map_err(|_| invalid()),
+            }
+        };
+
+        let major = component()?.ok_or_else(invalid)?;
+        let minor = component()?;
+        let patch = component()?;
+        if parts.next().is_some() || (minor.is_none() && patch.is_some()) {
+            return Err(invalid());
+        }
+
+        // `1.*` and `1.2.x` mean "any version with this prefix"
+        let op = if wildcard && op == Op::Caret {
+            Op::Exact
+        } else {
+            op
+        };
+
+        Ok(Self {
+            op,
+            major,
+            minor,
+            patch,
+        })
+    }
+
+    fn matches(&self, v: &ApiVersion) -> bool {
+        match self.op {
+            Op::Exact => self.matches_exact(v),
+            Op::Greater => self.matches_greater(v),
+            Op::GreaterEq => self.matches_exact(v) || self.matches_greater(v),
+            Op::Less => self.matches_less(v),
+            Op::LessEq => self.matches_exact(v) || self.matches_less(v),
+            Op::Tilde => {
+                v.major == self.major
+                    && self.minor.map_or(true, |minor| {
+                        v.minor == minor && self.patch.map_or(true, |patch| v.patch >= patch)
+                    })
+            }
+            Op::Caret => self.matches_caret(v),
+        }
+    }
+
+    fn matches_exact(&self, v: &ApiVersion) -> bool {
+        v.major == self.major
+            && self.minor.map_or(true, |minor| v.minor == minor)
+            && self.patch.map_or(true, |patch| v.patch == patch)
+    }
+
+    fn matches_greater(&self, v: &ApiVersion) -> bool {
+        if v.major != self.major {
+            return v.major > self.major;
+        }
+        let Some(minor) = self.minor else {
+            return false;
+        };
+        if v.minor != minor {
+            return v.minor > minor;
+        }
+        self.patch.is_some_and(|patch| v.patch > patch)
+    }
+
+    fn matches_less(&self, v: &ApiVersion) -> bool {
+        if v.major != self.major {
+            return v.major < self.major;
+        }
+        let Some(minor) = self.minor else {
+            return false;
+        };
+        if v.minor != minor {
+            return v.minor < minor;
+        }
+        self.patch.is_some_and(|patch| v.patch < patch)
+    }
+
+    fn matches_caret(&self, v: &ApiVersion) -> bool {
+        if v.major != self.major {
+            return false;
+        }
+        let Some(minor) = self.minor else {
+            return true;
+        };
+
+        // The leftmost non-zero component must match exactly
+        match (self.major, minor, self.patch) {
+            (0, 0, Some(patch)) => v.minor == 0 && v.patch == patch,
+            (0, _, patch) => v.minor == minor && patch.map_or(true, |p| v.patch >= p),
+            (_, _, patch) => {
+                v.minor > minor || (v.minor == minor && patch.map_or(true, |p| v.patch >= p))
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Comparator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let op = match self.op {
+            Op::Exact => "=",
+            Op::Greater => ">",
+            Op::GreaterEq => ">=",
+            Op::Less => "<",
+            Op::LessEq => "<=",
+            Op::Tilde => "~",
+            Op::Caret => "^",
+        };
+        write!(f, "{}{}", op, self.major)?;
+        match (self.minor, self.patch) {
+            (Some(minor), Some(patch)) => write!(f, ".{}.{}", minor, patch),
+            (Some(minor), None) => write!(f, ".{}", minor),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Semver version requirement, e.g. `^1.2` or `>=0.3, <0.5`.
+///
+/// Comma-separated comparators must all match. Supported operators are
+/// `=`, `>`, `>=`, `<`, `<=`, `~` and `^`; a bare version is a caret
+/// requirement and `*`, `1.*` or `1.2.x` are wildcards.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct VersionReq {
+    comparators: Vec<Comparator>,
+}
+
+impl VersionReq {
+    /// Parse a requirement.
+    pub fn parse(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if s.is_empty() || s == "*" {
+            return Ok(Self::default());
+        }
+
+        let comparators = s
+            .split(',')
+            .map(|c| Comparator::parse(c.trim()))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { comparators })
+    }
+
+    /// Check if the requirement matches every version.
+    pub fn is_any(&self) -> bool {
+        self.comparators.is_empty()
+    }
+
+    /// Check if a version satisfies the requirement.
+    pub fn matches(&self, version: &ApiVersion) -> bool {
+        self.comparators.iter().all(|c| c.matches(version))
+    }
+}
+
+impl std::str::FromStr for VersionReq {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+impl std::fmt::Display for VersionReq {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_any() {
+            return write!(f, "*");
+        }
+        for (i, c) in self.comparators.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", c)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(req: &str, version: &str) -> bool {
+        VersionReq::parse(req)
+            .unwrap()
+            .matches(&ApiVersion::parse(version).unwrap())
+    }
+
+    #[test]
+    fn test_operators() {
+        assert!(matches("^1.2", "1.9.0"));
+        assert!(!matches("^1.2", "2.0.0"));
+        assert!(matches("^0.0.3", "0.0.3"));
+        assert!(!matches("^0.0.3", "0.0.4"));
+        assert!(matches("~1.2", "1.2.9"));
+        assert!(!matches("~1.2", "1.3.0"));
+        assert!(matches(">1.2", "1.3.0"));
+        assert!(!matches(">1.2", "1.2.5"));
+        assert!(matches("<=1.2", "1.2.7"));
+        assert!(matches("1.*", "1.7.2"));
+        assert!(!matches("1.2.x", "1.3.0"));
+        assert!(matches("*", "42.0.0"));
+
+        assert!(matches(">=0.3, <0.5", "0.4.2"));
+        assert!(!matches(">=0.3, <0.5", "0.5.0"));
+        assert!(!matches(">=0.3, <0.5", "0.2.9"));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for req in [">=", "^a.b", "1.2.3.4", ">=1.x.3", "1.2,"] {
+            assert!(VersionReq::parse(req).is_err(), "{} should not parse", req);
+        }
+        assert_eq!(
+            VersionReq::parse(">=0.3,<0.5").unwrap().to_string(),
+            ">=0.3, <0.5"
+        );
+    }
+}
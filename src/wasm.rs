@@ -0,0 +1,296 @@
+//! Running plugins compiled to WebAssembly.
+//!
+//! Modules may import the host functions their manifest requires from the
+//! `host` module, bound from the [`HostFunctionTable`](crate::HostFunctionTable)
+//! when the engine is created; a module importing anything else, or a
+//! function registered without an implementation, is rejected at load.
+//! Values cross the boundary the same way in both directions. Exports are
+//! called with numeric arguments only: `Int` maps to `i32`/`i64` and `Float` to `f32`/`f64` according to
+//! the export's signature, and `Bool` to `i32`. Results are returned as
+//! `Int` or `Float`, as a `List` for multiple results and `Null` for none.
+//!
+//! The engine limits apply: `max_instructions` is the fuel each call may
+//! burn, so a looping export fails instead of hanging its caller, and
+//! `max_memory` bounds the module's linear memory.
+
+use std::collections::BTreeMap;
+
+use fusabi_host::{Limits, Value};
+use parking_lot::Mutex;
+use wasmtime::{
+    Config, Engine, FuncType, Instance, Linker, Module, Store, StoreLimits, StoreLimitsBuilder,
+    Trap, Val, ValType,
+};
+
+use crate::descriptor::ResourceLimits;
+use crate::error::{Error, Result};
+use crate::host_functions::HostFn;
+
+/// Module wasm plugins import host functions from.
+const HOST_MODULE: &str = "host";
+
+/// Fuel per call when the engine limits set no instruction limit.
+const DEFAULT_FUEL: u64 = 1_000_000_000;
+
+/// A plugin module instantiated in its own store.
+pub(crate) struct WasmEngine {
+    store: Mutex<Store<StoreLimits>>,
+    instance: Instance,
+    fuel: u64,
+}
+
+impl WasmEngine {
+    /// Compile and instantiate a module under `limits`, binding its imports
+    /// to `host_functions`.
+    pub(crate) fn new(
+        bytes: &[u8],
+        limits: &Limits,
+        host_functions: BTreeMap<String, HostFn>,
+    ) -> Result<Self> {
+        let limits = ResourceLimits::from(limits);
+        let fuel = match limits.max_instructions {
+            0 => DEFAULT_FUEL,
+            n => n,
+        };
+
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config).map_err(|e| Error::init_failed(e.to_string()))?;
+        let module = Module::new(&engine, bytes)
+            .map_err(|e| Error::init_failed(format!("invalid wasm module: {}", e)))?;
+
+        let mut linker = Linker::new(&engine);
+        for import in module.imports() {
+            let bound = (import.module() == HOST_MODULE)
+                .then(|| host_functions.get(import.name()))
+                .flatten();
+            let (Some(function), Some(ty)) = (bound, import.ty().func()) else {
+                return Err(Error::init_failed(format!(
+                    "wasm module imports {}::{}, but no such host function is provided",
+                    import.module(),
+                    import.name()
+                )));
+            };
+            bind(&mut linker, import.name(), ty.clone(), function.clone())?;
+        }
+
+        let mut store_limits = StoreLimitsBuilder::new();
+        if limits.max_memory > 0 {
+            store_limits =
+                store_limits.memory_size(usize::try_from(limits.max_memory).unwrap_or(usize::MAX));
+        }
+        let mut store = Store::new(&engine, store_limits.build());
+        store.limiter(|state| state);
+        // Start functions run on the first call's budget
+        store
+            .set_fuel(fuel)
+            .map_err(|e| Error::init_failed(e.to_string()))?;
+        let instance = linker
+            .instantiate(&mut store, &module)
+            .map_err(|e| Error::init_failed(out_of_fuel(e, "start function", fuel)))?;
+
+        Ok(Self {
+            store: Mutex::new(store),
+            instance,
+            fuel,
+        })
+    }
+
+    /// Call an exported function.
+    ///
+    /// Calls are serialized per plugin, and each starts with a full fuel
+    /// budget.
+    pub(crate) fn call(&self, function: &str, args: &[Value]) -> Result<Value> {
+        let mut store = self.store.lock();
+        store
+            .set_fuel(self.fuel)
+            .map_err(|e| Error::execution_failed(e.to_string()))?;
+        let func = self
+            .instance
+            .get_func(&mut *store, function)
+            .ok_or_else(|| Error::FunctionNotFound(function.to_string()))?;
+
+        let ty = func.ty(&*store);
+        if ty.params().len() != args.len() {
+            return Err(Error::invalid_arguments(format!(
+                "{} takes {} arguments, got {}",
+                function,
+                ty.params().len(),
+                args.len()
+            )));
+        }
+        let params = ty
+            .params()
+            .zip(args)
+            .map(|(ty, arg)| to_wasm(&ty, arg))
+            .collect::<Result<Vec<_>>>()?;
+        let mut results = vec![Val::I32(0); ty.results().len()];
+
+        func.call(&mut *store, &params, &mut results)
+            .map_err(|e| Error::execution_failed(out_of_fuel(e, function, self.fuel)))?;
+
+        let mut values: Vec<Value> = results.iter().map(from_wasm).collect::<Result<_>>()?;
+        Ok(match values.len() {
+            0 => Value::Null,
+            1 => values.remove(0),
+            _ => Value::List(values),
+        })
+    }
+}
+
+impl std::fmt::Debug for WasmEngine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WasmEngine").finish_non_exhaustive()
+    }
+}
+
+/// Define the host function `name` in `linker`, converting its arguments and
+/// results like those of exports.
+fn bind(
+    linker: &mut Linker<StoreLimits>,
+    name: &str,
+    ty: FuncType,
+    function: HostFn,
+) -> Result<()> {
+    let results: Vec<ValType> = ty.results().collect();
+    let host = name.to_string();
+    linker
+        .func_new(HOST_MODULE, name, ty, move |_, params, out| {
+            let args = params.iter().map(from_wasm).collect::<Result<Vec<_>>>()?;
+            let value = function(&args)
+                .map_err(|e| wasmtime::Error::msg(format!("host function {}: {}", host, e)))?;
+            let values = match (results.len(), value) {
+                (0, _) => Vec::new(),
+                (1, value) => vec![value],
+                (_, Value::List(values)) => values,
+                (n, other) => {
+                    return Err(wasmtime::Error::msg(format!(
+                        "host function {} returned {:?}, expected {} results",
+                        host, other, n
+                    )))
+                }
+            };
+            if values.len() != results.len() {
+                return Err(wasmtime::Error::msg(format!(
+                    "host function {} returned {} results, expected {}",
+                    host,
+                    values.len(),
+                    results.len()
+                )));
+            }
+            for (slot, (ty, value)) in out.iter_mut().zip(results.iter().zip(&values)) {
+                *slot = to_wasm(ty, value)?;
+            }
+            Ok(())
+        })
+        .map_err(|e| Error::init_failed(e.to_string()))?;
+    Ok(())
+}
+
+/// Describe a wasm error, naming the instruction limit if it was hit.
+fn out_of_fuel(error: wasmtime::Error, function: &str, fuel: u64) -> String {
+    match error.downcast_ref::<Trap>() {
+        Some(Trap::OutOfFuel) => format!(
+            "{} exceeded the instruction limit of {} and was stopped",
+            function, fuel
+        ),
+        _ => error.to_string(),
+    }
+}
+
+fn to_wasm(ty: &ValType, arg: &Value) -> Result<Val> {
+    let val = match (ty, arg) {
+        (ValType::I32, Value::Int(i)) => i32::try_from(*i).ok().map(Val::I32),
+        (ValType::I32, Value::Bool(b)) => Some(Val::I32(i32::from(*b))),
+        (ValType::I64, Value::Int(i)) => Some(Val::I64(*i)),
+        (ValType::F32, Value::Float(f)) => Some(Val::F32((*f as f32).to_bits())),
+        (ValType::F32, Value::Int(i)) => Some(Val::F32((*i as f32).to_bits())),
+        (ValType::F64, Value::Float(f)) => Some(Val::F64(f.to_bits())),
+        (ValType::F64, Value::Int(i)) => Some(Val::F64((*i as f64).to_bits())),
+        _ => None,
+    };
+    val.ok_or_else(|| Error::invalid_arguments(format!("cannot pass {:?} as a wasm {}", arg, ty)))
+}
+
+fn from_wasm(val: &Val) -> Result<Value> {
+    match val {
+        Val::I32(i) => Ok(Value::Int(i64::from(*i))),
+        Val::I64(i) => Ok(Value::Int(*i)),
+        Val::F32(bits) => Ok(Value::Float(f64::from(f32::from_bits(*bits)))),
+        Val::F64(bits) => Ok(Value::Float(f64::from_bits(*bits))),
+        _ => Err(Error::execution_failed(
+            "wasm export returned a non-numeric value",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_value_conversion() {
+        assert!(matches!(
+            to_wasm(&ValType::I32, &Value::Int(7)),
+            Ok(Val::I32(7))
+        ));
+        assert!(to_wasm(&ValType::I32, &Value::Int(i64::MAX)).is_err());
+        assert!(to_wasm(&ValType::I64, &Value::String("x".into())).is_err());
+
+        let val = to_wasm(&ValType::F64, &Value::Float(1.5)).unwrap();
+        assert!(matches!(from_wasm(&val), Ok(Value::Float(f)) if f == 1.5));
+    }
+
+    #[test]
+    fn test_rejects_invalid_module() {
+        assert!(matches!(
+            WasmEngine::new(b"not a module", &Limits::default(), BTreeMap::new()),
+            Err(Error::InitializationFailed(_))
+        ));
+    }
+
+    #[test]
+    fn test_looping_export_runs_out_of_fuel() {
+        // Modules may be given in the text format
+        let module = br#"(module (func (export "spin") (loop $l (br $l))))"#;
+        let mut limits = Limits::default();
+        limits.max_instructions = 10_000;
+        let engine = WasmEngine::new(module, &limits, BTreeMap::new()).unwrap();
+
+        match engine.call("spin", &[]) {
+            Err(Error::ExecutionFailed(reason)) => {
+                assert!(reason.contains("exceeded the instruction limit of 10000"))
+            }
+            other => panic!("expected an execution error, got {:?}", other),
+        }
+        // The next call gets a fresh budget
+        assert!(engine.call("spin", &[]).is_err());
+    }
+
+    #[test]
+    fn test_binds_host_functions() {
+        let module = br#"(module
+            (import "host" "math.double" (func $double (param i64) (result i64)))
+            (func (export "quadruple") (param i64) (result i64)
+                (call $double (call $double (local.get 0)))))"#;
+        let double: HostFn = std::sync::Arc::new(|args: &[Value]| match args {
+            [Value::Int(n)] => Ok(Value::Int(n * 2)),
+            _ => Err(Error::invalid_arguments("expected an int")),
+        });
+
+        let host = BTreeMap::from([("math.double".to_string(), double)]);
+        let engine = WasmEngine::new(module, &Limits::default(), host).unwrap();
+        assert!(matches!(
+            engine.call("quadruple", &[Value::Int(5)]),
+            Ok(Value::Int(20))
+        ));
+
+        // Unbound imports are rejected at load
+        match WasmEngine::new(module, &Limits::default(), BTreeMap::new()) {
+            Err(Error::InitializationFailed(reason)) => {
+                assert!(reason.contains("host::math.double"))
+            }
+            other => panic!("expected an init error, got {:?}", other),
+        }
+    }
+}
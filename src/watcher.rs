@@ -1,15 +1,65 @@
 //! File system watcher for plugin hot reload.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "tokio")]
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
+#[cfg(feature = "tokio")]
+use std::task::{Context, Poll};
 use std::time::{Duration, Instant};
 
-use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
-use parking_lot::RwLock;
+#[cfg(feature = "tokio")]
+use futures_core::Stream;
 
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Config, Event, EventKind, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+use parking_lot::{Mutex, RwLock};
+
+use crate::config_error::ConfigError;
 use crate::error::{Error, Result};
+use crate::glob::glob_match;
+use crate::tasks::{TaskSupervisor, TaskWaker};
+
+/// Build output, version control metadata and editor temporary files,
+/// ignored unless [`WatchConfig::with_exclude_patterns`] says otherwise.
+const DEFAULT_EXCLUDES: &[&str] = &["target/", ".git/", "*~", "*.swp", "*.swx", ".#*"];
+
+/// Numbers the flush tasks of watchers sharing a task supervisor.
+static NEXT_WATCHER: AtomicU64 = AtomicU64::new(0);
+
+/// Interval used when [`WatchBackend::Auto`] falls back to polling.
+const FALLBACK_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Filesystem types on which OS change notifications are unreliable.
+#[cfg(target_os = "linux")]
+const REMOTE_FILESYSTEMS: &[&str] = &[
+    "nfs",
+    "nfs4",
+    "cifs",
+    "smb3",
+    "smbfs",
+    "9p",
+    "virtiofs",
+    "vboxsf",
+    "fuse.sshfs",
+];
+
+/// How the watcher learns about file changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchBackend {
+    /// Use OS notifications, polling instead when a watched path is on a
+    /// network or shared filesystem or notifications cannot be set up.
+    Auto,
+    /// Only use OS notifications (inotify, FSEvents, ReadDirectoryChangesW).
+    Native,
+    /// Stat watched files every `interval`.
+    Poll {
+        /// Time between scans.
+        interval: Duration,
+    },
+}
 
 /// Configuration for the plugin watcher.
 #[derive(Debug, Clone)]
@@ -20,8 +70,21 @@ pub struct WatchConfig {
     pub recursive: bool,
     /// File extensions to watch.
     pub extensions: Vec<String>,
+    /// Globs a changed path must match, if any are set.
+    pub include: Vec<String>,
+    /// Globs of paths to ignore, checked after `include`.
+    pub exclude: Vec<String>,
     /// Whether to auto-reload on change.
     pub auto_reload: bool,
+    /// How long a rename source waits for its destination before it is
+    /// reported as a removal.
+    pub rename_timeout: Duration,
+    /// Maximum number of paths tracked for debouncing.
+    pub max_debounce_entries: usize,
+    /// How file changes are detected.
+    pub backend: WatchBackend,
+    /// Events queued per async stream before further events are dropped.
+    pub stream_capacity: usize,
 }
 
 impl Default for WatchConfig {
@@ -30,7 +93,16 @@ impl Default for WatchConfig {
             debounce: Duration::from_millis(500),
             recursive: true,
             extensions: vec!["fsx".to_string(), "fzb".to_string(), "toml".to_string()],
+            include: Vec::new(),
+            exclude: DEFAULT_EXCLUDES
+                .iter()
+                .map(|glob| glob.to_string())
+                .collect(),
             auto_reload: true,
+            rename_timeout: Duration::from_millis(100),
+            max_debounce_entries: 4096,
+            backend: WatchBackend::Auto,
+            stream_capacity: 1024,
         }
     }
 }
@@ -59,11 +131,113 @@ impl WatchConfig {
         self
     }
 
+    /// Only watch paths matching one of these globs.
+    ///
+    /// See [`WatchConfig::with_exclude_patterns`] for the glob syntax.
+    pub fn with_include_patterns(mut self, globs: &[&str]) -> Self {
+        self.include = globs.iter().map(|glob| glob.to_string()).collect();
+        self
+    }
+
+    /// Ignore paths matching any of these globs, replacing the defaults.
+    ///
+    /// `*` and `?` match within a path component and `**` matches any
+    /// number of components. A glob matching a directory ignores everything
+    /// below it, and a trailing `/` restricts it to directories, so
+    /// `target/` skips build output while `*.swp` skips editor swap files.
+    ///
+    /// Globs see the path relative to the watched directory it is under, so
+    /// a directory above the watched one named `target` excludes nothing.
+    pub fn with_exclude_patterns(mut self, globs: &[&str]) -> Self {
+        self.exclude = globs.iter().map(|glob| glob.to_string()).collect();
+        self
+    }
+
+    /// Check if a path passes the include and exclude globs.
+    ///
+    /// `path` should be relative to the watched directory; the watcher
+    /// strips the directory before checking.
+    pub fn matches_patterns(&self, path: &Path) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|glob| glob_match(glob, path)))
+            && !self.exclude.iter().any(|glob| glob_match(glob, path))
+    }
+
     /// Set auto-reload behavior.
     pub fn with_auto_reload(mut self, auto: bool) -> Self {
         self.auto_reload = auto;
         self
     }
+
+    /// Set the maximum number of paths tracked for debouncing.
+    ///
+    /// When full, the change to the least recently touched path is
+    /// delivered early.
+    pub fn with_max_debounce_entries(mut self, max: usize) -> Self {
+        self.max_debounce_entries = max;
+        self
+    }
+
+    /// Set how long to wait for the second half of a rename.
+    pub fn with_rename_timeout(mut self, timeout: Duration) -> Self {
+        self.rename_timeout = timeout;
+        self
+    }
+
+    /// Set how file changes are detected.
+    pub fn with_backend(mut self, backend: WatchBackend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Set how many events an async stream queues before it lags.
+    ///
+    /// Events arriving while a stream's queue is full are dropped for that
+    /// stream and counted in [`WatcherStats::events_dropped`].
+    pub fn with_stream_capacity(mut self, capacity: usize) -> Self {
+        self.stream_capacity = capacity;
+        self
+    }
+
+    /// Validate the configuration, failing with [`Error::Config`] listing
+    /// every invalid field.
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = ConfigError::new();
+        for (i, extension) in self.extensions.iter().enumerate() {
+            let path = format!("extensions[{}]", i);
+            if extension.is_empty() {
+                errors.push(path, "must not be empty");
+            } else if extension.starts_with('.') {
+                errors.push(path, format!("{} must not start with a dot", extension));
+            }
+        }
+        for (field, globs) in [("include", &self.include), ("exclude", &self.exclude)] {
+            for (i, glob) in globs.iter().enumerate() {
+                errors.check(
+                    glob.split('/').any(|part| !part.is_empty()),
+                    &format!("{}[{}]", field, i),
+                    "must not be empty",
+                );
+            }
+        }
+        errors.check(
+            self.max_debounce_entries > 0,
+            "max_debounce_entries",
+            "must be greater than zero",
+        );
+        errors.check(
+            self.stream_capacity > 0,
+            "stream_capacity",
+            "must be greater than zero",
+        );
+        if let WatchBackend::Poll { interval } = self.backend {
+            errors.check(
+                !interval.is_zero(),
+                "backend.interval",
+                "must be greater than zero",
+            );
+        }
+        errors.into_result()
+    }
 }
 
 /// Event emitted when a watched file changes.
@@ -93,7 +267,54 @@ pub enum WatchEvent {
     },
 }
 
+/// Kind of a [`WatchEvent`], used for filtering.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WatchEventKind {
+    /// A file was created.
+    Created,
+    /// A file was modified.
+    Modified,
+    /// A file was removed.
+    Removed,
+    /// A file was renamed.
+    Renamed,
+}
+
 impl WatchEvent {
+    /// Combine a pending change with a later one to the same path, or
+    /// `None` if they cancel out.
+    ///
+    /// A file created and then modified is still new; one removed and
+    /// recreated, as editors do on save, was modified; one created and
+    /// removed again never needs reporting. Otherwise the later change wins.
+    fn coalesce(self, next: WatchEvent) -> Option<WatchEvent> {
+        match (self, next) {
+            (created @ Self::Created { .. }, Self::Modified { .. }) => Some(created),
+            (Self::Created { .. }, Self::Removed { .. }) => None,
+            (Self::Removed { .. }, Self::Created { path }) => Some(Self::Modified { path }),
+            (renamed @ Self::Renamed { .. }, Self::Modified { .. }) => Some(renamed),
+            (_, next) => Some(next),
+        }
+    }
+
+    /// Get the kind of this event.
+    pub fn kind(&self) -> WatchEventKind {
+        match self {
+            Self::Created { .. } => WatchEventKind::Created,
+            Self::Modified { .. } => WatchEventKind::Modified,
+            Self::Removed { .. } => WatchEventKind::Removed,
+            Self::Renamed { .. } => WatchEventKind::Renamed,
+        }
+    }
+
+    /// Get every path affected by this event.
+    pub fn paths(&self) -> Vec<&Path> {
+        match self {
+            Self::Renamed { from, to } => vec![from.as_path(), to.as_path()],
+            _ => vec![self.path()],
+        }
+    }
+
     /// Get the primary path for this event.
     pub fn path(&self) -> &Path {
         match self {
@@ -105,52 +326,556 @@ impl WatchEvent {
     }
 
     /// Check if this event affects a file with the given extensions.
+    ///
+    /// Renames match if either the old or the new path matches.
     pub fn matches_extension(&self, extensions: &[String]) -> bool {
-        let path = self.path();
-        if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-            extensions.iter().any(|e| e == ext)
-        } else {
-            false
+        self.paths().iter().any(|path| {
+            path.extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|ext| extensions.iter().any(|e| e == ext))
+        })
+    }
+}
+
+/// Filter deciding which events reach a subscription.
+///
+/// Empty criteria match everything.
+#[derive(Debug, Clone, Default)]
+pub struct EventFilter {
+    extensions: Vec<String>,
+    roots: Vec<PathBuf>,
+    kinds: Vec<WatchEventKind>,
+    auto_reload_only: bool,
+}
+
+impl EventFilter {
+    /// Create a filter that matches every event.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check if an event from a path with the given `auto_reload` setting
+    /// should be delivered.
+    fn accepts(&self, event: &WatchEvent, auto_reload: bool) -> bool {
+        (auto_reload || !self.auto_reload_only) && self.matches(event)
+    }
+
+    /// Check if an event passes this filter.
+    pub fn matches(&self, event: &WatchEvent) -> bool {
+        if !self.kinds.is_empty() && !self.kinds.contains(&event.kind()) {
+            return false;
+        }
+
+        if !self.extensions.is_empty() && !event.matches_extension(&self.extensions) {
+            return false;
         }
+
+        self.roots.is_empty()
+            || event
+                .paths()
+                .iter()
+                .any(|path| self.roots.iter().any(|root| path.starts_with(root)))
     }
 }
 
-type EventHandler = Box<dyn Fn(WatchEvent) + Send + Sync>;
+/// Builder for a filtered watch event subscription.
+///
+/// Created by [`PluginWatcher::subscribe`].
+pub struct SubscriptionBuilder<'a> {
+    state: &'a Arc<RwLock<WatchState>>,
+    filter: EventFilter,
+    #[cfg_attr(not(feature = "tokio"), allow(dead_code))]
+    capacity: usize,
+}
+
+impl SubscriptionBuilder<'_> {
+    /// Only deliver events for files with one of these extensions.
+    pub fn extensions<I, S>(mut self, extensions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.filter
+            .extensions
+            .extend(extensions.into_iter().map(Into::into));
+        self
+    }
+
+    /// Only deliver events for paths under this directory.
+    pub fn under(mut self, path: impl Into<PathBuf>) -> Self {
+        self.filter.roots.push(path.into());
+        self
+    }
+
+    /// Only deliver events of these kinds.
+    pub fn events<I>(mut self, kinds: I) -> Self
+    where
+        I: IntoIterator<Item = WatchEventKind>,
+    {
+        self.filter.kinds.extend(kinds);
+        self
+    }
+
+    /// Only deliver events for paths whose configuration enables `auto_reload`.
+    pub fn when_auto_reload(mut self) -> Self {
+        self.filter.auto_reload_only = true;
+        self
+    }
+
+    /// Register the handler for matching events.
+    pub fn on_change<F>(self, handler: F)
+    where
+        F: Fn(WatchEvent) + Send + Sync + 'static,
+    {
+        self.state
+            .write()
+            .handlers
+            .push((self.filter, Arc::new(handler)));
+    }
+
+    /// Receive matching events as an async stream.
+    ///
+    /// Up to [`WatchConfig::stream_capacity`] events are queued until
+    /// polled, without blocking the watcher; while the queue is full, new
+    /// events are dropped for this stream and counted in
+    /// [`WatcherStats::events_dropped`]. The subscription ends when the
+    /// stream is dropped.
+    #[cfg(feature = "tokio")]
+    pub fn stream(self) -> impl Stream<Item = WatchEvent> + Send + Unpin {
+        let (tx, rx) = tokio::sync::mpsc::channel(self.capacity.max(1));
+        self.state.write().streams.push((self.filter, tx));
+        EventStream { rx }
+    }
+}
+
+type EventHandler = Arc<dyn Fn(WatchEvent) + Send + Sync>;
+
+/// Events of a subscription created by [`SubscriptionBuilder::stream`].
+#[cfg(feature = "tokio")]
+struct EventStream {
+    rx: tokio::sync::mpsc::Receiver<WatchEvent>,
+}
+
+#[cfg(feature = "tokio")]
+impl Stream for EventStream {
+    type Item = WatchEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<WatchEvent>> {
+        self.rx.poll_recv(cx)
+    }
+}
+
+/// Watcher counters for diagnosing missed reloads.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WatcherStats {
+    /// Raw notify events received.
+    pub events_received: u64,
+    /// Events dropped by the extension filter or matching no subscription.
+    pub events_filtered: u64,
+    /// Events merged into a pending change to the same path.
+    pub events_debounced: u64,
+    /// Events delivered to at least one handler.
+    pub events_delivered: u64,
+    /// Delivered events that should trigger a reload under `auto_reload`.
+    pub reloads_triggered: u64,
+    /// Events dropped for async streams whose queue was full.
+    pub events_dropped: u64,
+    /// Current number of paths with a change waiting to be delivered.
+    pub debounce_entries: usize,
+}
 
 /// Internal state for tracking file changes.
 struct WatchState {
-    last_events: HashMap<PathBuf, Instant>,
-    handlers: Vec<EventHandler>,
+    pending: HashMap<PathBuf, PendingChange>,
+    handlers: Vec<(EventFilter, EventHandler)>,
+    #[cfg(feature = "tokio")]
+    streams: Vec<(EventFilter, tokio::sync::mpsc::Sender<WatchEvent>)>,
+    pending_renames: VecDeque<PendingRename>,
+    groups: Vec<WatchGroup>,
+    /// Watched directories as given and canonicalized, which globs are
+    /// matched relative to.
+    roots: Vec<(PathBuf, PathBuf)>,
+    /// Settled changes waiting to be handed to handlers once the state
+    /// lock is released, with their `auto_reload` setting.
+    ready: Vec<(WatchEvent, bool)>,
+    stats: WatcherStats,
+    /// Wakes the flush task while the watcher runs.
+    flusher: Option<TaskWaker>,
+    /// When the flush task next runs, if it is waiting for a deadline.
+    next_flush: Option<Instant>,
+}
+
+/// Directories sharing a watch configuration.
+struct WatchGroup {
+    name: String,
+    /// Directories as given and canonicalized, like [`WatchState::roots`].
+    roots: Vec<(PathBuf, PathBuf)>,
+    config: Arc<WatchConfig>,
+}
+
+/// Change waiting for its path to go quiet.
+struct PendingChange {
+    event: WatchEvent,
+    auto_reload: bool,
+    debounce: Duration,
+    last: Instant,
+}
+
+/// Rename source waiting for its destination.
+struct PendingRename {
+    path: PathBuf,
+    tracker: Option<usize>,
+    since: Instant,
+}
+
+impl WatchState {
+    /// Find the group owning a path, preferring the most specific root.
+    fn group_for(&self, path: &Path) -> Option<&WatchGroup> {
+        self.groups
+            .iter()
+            .flat_map(|group| {
+                group
+                    .roots
+                    .iter()
+                    .flat_map(|(given, canonical)| [given, canonical])
+                    .map(move |root| (root, group))
+            })
+            .filter(|(root, _)| path.starts_with(root))
+            .max_by_key(|(root, _)| root.components().count())
+            .map(|(_, group)| group)
+    }
+
+    /// Strip the most specific watched directory from a path.
+    fn relative<'a>(&self, path: &'a Path) -> &'a Path {
+        self.roots
+            .iter()
+            .flat_map(|(given, canonical)| [given, canonical])
+            .filter_map(|root| path.strip_prefix(root).ok())
+            .min_by_key(|relative| relative.components().count())
+            .unwrap_or(path)
+    }
+
+    /// Dispatch events using the configuration of the group owning each path.
+    fn dispatch_all(&mut self, config: &WatchConfig, events: Vec<WatchEvent>, now: Instant) {
+        for watch_event in events {
+            let group_config = self
+                .group_for(watch_event.path())
+                .map(|group| group.config.clone());
+            self.dispatch(group_config.as_deref().unwrap_or(config), watch_event, now);
+        }
+    }
+
+    /// Translate a notify event, pairing rename halves.
+    fn translate(&mut self, event: Event, now: Instant) -> Option<WatchEvent> {
+        let tracker = event.tracker();
+        let mut paths = event.paths.into_iter();
+
+        match event.kind {
+            EventKind::Create(_) => paths.next().map(|path| WatchEvent::Created { path }),
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                if let Some(path) = paths.next() {
+                    self.pending_renames.push_back(PendingRename {
+                        path,
+                        tracker,
+                        since: now,
+                    });
+                }
+                None
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+                let to = paths.next()?;
+                // A destination without a source was moved in from outside
+                match self.take_pending_rename(tracker) {
+                    Some(from) => Some(WatchEvent::Renamed { from, to }),
+                    None => Some(WatchEvent::Created { path: to }),
+                }
+            }
+            EventKind::Modify(ModifyKind::Name(_)) => match (paths.next(), paths.next()) {
+                (Some(from), Some(to)) => Some(WatchEvent::Renamed { from, to }),
+                (Some(path), None) => Some(WatchEvent::Modified { path }),
+                _ => None,
+            },
+            EventKind::Modify(_) => paths.next().map(|path| WatchEvent::Modified { path }),
+            EventKind::Remove(_) => paths.next().map(|path| WatchEvent::Removed { path }),
+            _ => None,
+        }
+    }
+
+    fn take_pending_rename(&mut self, tracker: Option<usize>) -> Option<PathBuf> {
+        let pos = match tracker {
+            Some(tracker) => self
+                .pending_renames
+                .iter()
+                .position(|p| p.tracker == Some(tracker))?,
+            None => 0,
+        };
+        self.pending_renames.remove(pos).map(|p| p.path)
+    }
+
+    /// Turn rename sources that were never paired into removals.
+    fn expire_renames(&mut self, timeout: Duration, now: Instant) -> Vec<WatchEvent> {
+        let mut expired = Vec::new();
+        while let Some(pending) = self.pending_renames.front() {
+            if now.duration_since(pending.since) < timeout {
+                break;
+            }
+            if let Some(pending) = self.pending_renames.pop_front() {
+                expired.push(WatchEvent::Removed { path: pending.path });
+            }
+        }
+        expired
+    }
+
+    /// Hold a change until its path has been quiet for the debounce
+    /// window, merging it with any change already pending for the path.
+    fn debounce(&mut self, config: &WatchConfig, watch_event: WatchEvent, now: Instant) {
+        let path = watch_event.path().to_path_buf();
+        if let Some(mut pending) = self.pending.remove(&path) {
+            self.stats.events_debounced += 1;
+            if let Some(event) = pending.event.coalesce(watch_event) {
+                pending.event = event;
+                pending.auto_reload = config.auto_reload;
+                pending.debounce = config.debounce;
+                pending.last = now;
+                self.pending.insert(path, pending);
+            }
+            self.stats.debounce_entries = self.pending.len();
+            return;
+        }
+
+        // Deliver the least recently touched changes early rather than drop them
+        while self.pending.len() >= config.max_debounce_entries.max(1) {
+            let oldest = self
+                .pending
+                .iter()
+                .min_by_key(|(_, pending)| pending.last)
+                .map(|(path, _)| path.clone());
+            match oldest.and_then(|oldest| self.pending.remove(&oldest)) {
+                Some(pending) => self.deliver(pending.event, pending.auto_reload),
+                None => break,
+            }
+        }
+
+        self.pending.insert(
+            path,
+            PendingChange {
+                event: watch_event,
+                auto_reload: config.auto_reload,
+                debounce: config.debounce,
+                last: now,
+            },
+        );
+        self.stats.debounce_entries = self.pending.len();
+    }
+
+    /// When the next pending change settles or rename source expires.
+    fn next_deadline(&self, rename_timeout: Duration) -> Option<Instant> {
+        let settles = self
+            .pending
+            .values()
+            .map(|pending| pending.last + pending.debounce);
+        let expires = self
+            .pending_renames
+            .front()
+            .map(|pending| pending.since + rename_timeout);
+        settles.chain(expires).min()
+    }
+
+    /// Wake the flush task if something is due before it would next run.
+    fn schedule_flush(&mut self, rename_timeout: Duration) {
+        let Some(deadline) = self.next_deadline(rename_timeout) else {
+            return;
+        };
+        if self.next_flush.map_or(true, |next| deadline < next) {
+            if let Some(ref flusher) = self.flusher {
+                self.next_flush = Some(deadline);
+                flusher.wake();
+            }
+        }
+    }
+
+    /// Deliver every pending change whose path has been quiet for its
+    /// debounce window, oldest first.
+    fn flush_settled(&mut self, now: Instant) {
+        let mut settled: Vec<_> = self
+            .pending
+            .iter()
+            .filter(|(_, pending)| now.duration_since(pending.last) >= pending.debounce)
+            .map(|(path, pending)| (pending.last, path.clone()))
+            .collect();
+        if settled.is_empty() {
+            return;
+        }
+        settled.sort();
+
+        for (_, path) in settled {
+            if let Some(pending) = self.pending.remove(&path) {
+                self.deliver(pending.event, pending.auto_reload);
+            }
+        }
+        self.stats.debounce_entries = self.pending.len();
+    }
+
+    fn dispatch(&mut self, config: &WatchConfig, watch_event: WatchEvent, now: Instant) {
+        // Check extension and glob filters; a renamed directory carries the
+        // plugin files below it
+        let directory_renamed =
+            matches!(&watch_event, WatchEvent::Renamed { to, .. } if to.is_dir());
+        if !(directory_renamed || watch_event.matches_extension(&config.extensions))
+            || !watch_event
+                .paths()
+                .iter()
+                .any(|path| config.matches_patterns(self.relative(path)))
+        {
+            self.stats.events_filtered += 1;
+            return;
+        }
+
+        if config.debounce.is_zero() {
+            self.deliver(watch_event, config.auto_reload);
+        } else {
+            self.debounce(config, watch_event, now);
+        }
+    }
+
+    /// Queue a settled change for delivery after the state lock is
+    /// released.
+    fn deliver(&mut self, watch_event: WatchEvent, auto_reload: bool) {
+        self.ready.push((watch_event, auto_reload));
+    }
+
+    /// Send a change to matching streams and count it; `delivered` tells
+    /// whether a handler took it.
+    fn record_delivery(&mut self, watch_event: &WatchEvent, auto_reload: bool, delivered: bool) {
+        let mut delivered = delivered;
+
+        // Dropped streams are unsubscribed; full ones lag and lose the event
+        #[cfg(feature = "tokio")]
+        {
+            use tokio::sync::mpsc::error::TrySendError;
+
+            let mut dropped = 0;
+            self.streams.retain(|(filter, tx)| {
+                if !filter.accepts(watch_event, auto_reload) {
+                    return !tx.is_closed();
+                }
+                match tx.try_send(watch_event.clone()) {
+                    Ok(()) => {
+                        delivered = true;
+                        true
+                    }
+                    Err(TrySendError::Full(_)) => {
+                        dropped += 1;
+                        true
+                    }
+                    Err(TrySendError::Closed(_)) => false,
+                }
+            });
+            self.stats.events_dropped += dropped;
+        }
+
+        if !delivered {
+            self.stats.events_filtered += 1;
+            return;
+        }
+
+        self.stats.events_delivered += 1;
+        if auto_reload && watch_event.kind() != WatchEventKind::Removed {
+            self.stats.reloads_triggered += 1;
+        }
+    }
+}
+
+/// Hand the queued changes to the matching handlers and streams.
+///
+/// Handlers run without the state lock held, so they may call back into the
+/// watcher, e.g. to watch another path or stop it.
+fn deliver_ready(state: &RwLock<WatchState>) {
+    let (ready, handlers) = {
+        let mut state = state.write();
+        if state.ready.is_empty() {
+            return;
+        }
+        (std::mem::take(&mut state.ready), state.handlers.clone())
+    };
+
+    for (watch_event, auto_reload) in ready {
+        let mut delivered = false;
+        for (filter, handler) in &handlers {
+            if filter.accepts(&watch_event, auto_reload) {
+                handler(watch_event.clone());
+                delivered = true;
+            }
+        }
+        state
+            .write()
+            .record_delivery(&watch_event, auto_reload, delivered);
+    }
+}
+
+/// The running OS or polling watcher.
+struct ActiveWatcher {
+    watcher: Box<dyn Watcher + Send>,
+    backend: WatchBackend,
 }
 
 /// Plugin file watcher for hot reload support.
+///
+/// The OS-level watcher lives behind a mutex, so paths can be watched and
+/// unwatched through a shared reference while the watcher is running.
+/// Settled changes are delivered from a background task named
+/// `plugin-watcher-<n>`.
 pub struct PluginWatcher {
     config: WatchConfig,
-    watcher: Option<RecommendedWatcher>,
+    watcher: Mutex<Option<ActiveWatcher>>,
     watched_paths: RwLock<Vec<PathBuf>>,
     state: Arc<RwLock<WatchState>>,
     running: Arc<AtomicBool>,
+    tasks: Arc<TaskSupervisor>,
+    flush_task: String,
 }
 
 impl PluginWatcher {
     /// Create a new plugin watcher.
     pub fn new(config: WatchConfig) -> Result<Self> {
+        config.validate()?;
         let state = Arc::new(RwLock::new(WatchState {
-            last_events: HashMap::new(),
+            pending: HashMap::new(),
             handlers: Vec::new(),
+            #[cfg(feature = "tokio")]
+            streams: Vec::new(),
+            pending_renames: VecDeque::new(),
+            groups: Vec::new(),
+            roots: Vec::new(),
+            ready: Vec::new(),
+            flusher: None,
+            next_flush: None,
+            stats: WatcherStats::default(),
         }));
 
         let running = Arc::new(AtomicBool::new(false));
 
         Ok(Self {
             config,
-            watcher: None,
+            watcher: Mutex::new(None),
             watched_paths: RwLock::new(Vec::new()),
             state,
             running,
+            tasks: Arc::new(TaskSupervisor::new()),
+            flush_task: format!(
+                "plugin-watcher-{}",
+                NEXT_WATCHER.fetch_add(1, Ordering::Relaxed)
+            ),
         })
     }
 
+    /// Run the flush task on `tasks`, e.g. the runtime's supervisor, so its
+    /// health is reported and shutdown stops it.
+    pub fn with_tasks(mut self, tasks: Arc<TaskSupervisor>) -> Self {
+        self.tasks = tasks;
+        self
+    }
+
     /// Create with default configuration.
     pub fn default_config() -> Result<Self> {
         Self::new(WatchConfig::default())
@@ -171,87 +896,256 @@ impl PluginWatcher {
     where
         F: Fn(WatchEvent) + Send + Sync + 'static,
     {
-        self.state.write().handlers.push(Box::new(handler));
+        self.state
+            .write()
+            .handlers
+            .push((EventFilter::new(), Arc::new(handler)));
+    }
+
+    /// Start building a filtered subscription.
+    ///
+    /// ```rust,ignore
+    /// watcher
+    ///     .subscribe()
+    ///     .extensions(["fsx"])
+    ///     .under("plugins/")
+    ///     .events([WatchEventKind::Modified])
+    ///     .on_change(|event| println!("{:?}", event));
+    /// ```
+    pub fn subscribe(&self) -> SubscriptionBuilder<'_> {
+        SubscriptionBuilder {
+            state: &self.state,
+            filter: EventFilter::new(),
+            capacity: self.config.stream_capacity,
+        }
+    }
+
+    /// Receive every event as an async stream, for hosts that `select!` on
+    /// file changes alongside other work.
+    ///
+    /// ```rust,ignore
+    /// let mut events = watcher.events();
+    /// loop {
+    ///     tokio::select! {
+    ///         Some(event) = events.next() => println!("{:?}", event),
+    ///         _ = shutdown.recv() => break,
+    ///     }
+    /// }
+    /// ```
+    #[cfg(feature = "tokio")]
+    pub fn events(&self) -> impl Stream<Item = WatchEvent> + Send + Unpin {
+        self.subscribe().stream()
     }
 
     /// Start watching.
-    pub fn start(&mut self) -> Result<()> {
-        if self.running.load(Ordering::Relaxed) {
+    ///
+    /// Every registered path is handed to the OS watcher; a path that cannot
+    /// be watched fails the start and leaves the watcher stopped. With
+    /// [`WatchBackend::Auto`], paths on network filesystems and failures to
+    /// set up OS notifications switch the watcher to polling instead.
+    /// Paths added later with [`watch`](Self::watch) are checked the same
+    /// way.
+    pub fn start(&self) -> Result<()> {
+        let mut slot = self.watcher.lock();
+        if slot.is_some() {
             return Ok(());
         }
 
+        let watcher = match self.config.backend {
+            WatchBackend::Auto => {
+                let remote = self
+                    .watched_paths
+                    .read()
+                    .iter()
+                    .find(|path| on_remote_filesystem(path))
+                    .cloned();
+                let native = match remote {
+                    Some(path) => Err(Error::Watch(format!(
+                        "{} is on a network filesystem",
+                        path.display()
+                    ))),
+                    None => self.start_backend(WatchBackend::Native),
+                };
+                native.or_else(|e| {
+                    tracing::warn!("Falling back to polling for file changes: {}", e);
+                    self.start_backend(WatchBackend::Poll {
+                        interval: FALLBACK_POLL_INTERVAL,
+                    })
+                })?
+            }
+            backend => self.start_backend(backend)?,
+        };
+
+        self.spawn_flusher()?;
+        tracing::info!("Plugin watcher started ({:?})", watcher.backend);
+        *slot = Some(watcher);
+        self.running.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Get the backend detecting changes, while the watcher is running.
+    ///
+    /// Never [`WatchBackend::Auto`]: reports what `Auto` resolved to.
+    pub fn active_backend(&self) -> Option<WatchBackend> {
+        self.watcher.lock().as_ref().map(|active| active.backend)
+    }
+
+    /// Create a watcher on a concrete backend and register every path.
+    fn start_backend(&self, backend: WatchBackend) -> Result<ActiveWatcher> {
         let state = self.state.clone();
         let config = self.config.clone();
         let running = self.running.clone();
+        let handler = move |res: std::result::Result<Event, notify::Error>| {
+            if !running.load(Ordering::Relaxed) {
+                return;
+            }
 
-        let watcher = RecommendedWatcher::new(
-            move |res: std::result::Result<Event, notify::Error>| {
-                if !running.load(Ordering::Relaxed) {
-                    return;
-                }
-
-                if let Ok(event) = res {
-                    Self::handle_event(&state, &config, event);
-                }
-            },
-            Config::default(),
-        )
-        .map_err(|e| Error::Watch(e.to_string()))?;
+            match res {
+                Ok(event) => Self::handle_event(&state, &config, event),
+                Err(e) => tracing::warn!("File watcher error: {}", e),
+            }
+        };
 
-        self.watcher = Some(watcher);
-        self.running.store(true, Ordering::Relaxed);
+        let watcher: std::result::Result<Box<dyn Watcher + Send>, notify::Error> = match backend {
+            WatchBackend::Poll { interval } => {
+                PollWatcher::new(handler, Config::default().with_poll_interval(interval))
+                    .map(|w| Box::new(w) as Box<dyn Watcher + Send>)
+            }
+            _ => RecommendedWatcher::new(handler, Config::default())
+                .map(|w| Box::new(w) as Box<dyn Watcher + Send>),
+        };
+        let mut watcher = watcher.map_err(|e| Error::Watch(e.to_string()))?;
 
-        // Re-watch all registered paths
+        // Register all paths before publishing the watcher
         for path in self.watched_paths.read().iter() {
-            self.watch_path_internal(path)?;
+            self.watch_path_internal(watcher.as_mut(), path)?;
         }
 
-        tracing::info!("Plugin watcher started");
+        Ok(ActiveWatcher { watcher, backend })
+    }
+
+    /// Deliver settled changes and expired renames until the watcher stops,
+    /// so the last change in a burst arrives without waiting for another
+    /// event.
+    ///
+    /// The task sleeps until the earliest pending deadline, and while
+    /// nothing is pending until an event wakes it.
+    fn spawn_flusher(&self) -> Result<()> {
+        let state = self.state.clone();
+        let config = self.config.clone();
+        let flusher = self
+            .tasks
+            .spawn_scheduled(self.flush_task.clone(), move || {
+                Self::flush(&state, &config, Instant::now());
+                let mut state = state.write();
+                state.next_flush = state.next_deadline(config.rename_timeout);
+                Ok(state.next_flush)
+            })?;
+        // Events that arrived before the waker was published are pending
+        flusher.wake();
+        self.state.write().flusher = Some(flusher);
         Ok(())
     }
 
     /// Stop watching.
-    pub fn stop(&mut self) {
+    ///
+    /// Dropping the OS watcher releases every registration; watched paths
+    /// are kept and re-registered by the next [`start`](Self::start). A
+    /// change handler may stop the watcher that is delivering to it.
+    pub fn stop(&self) {
         self.running.store(false, Ordering::Relaxed);
-        self.watcher = None;
-        tracing::info!("Plugin watcher stopped");
+        if self.watcher.lock().take().is_some() {
+            self.tasks.stop(&self.flush_task);
+            {
+                let mut state = self.state.write();
+                state.flusher = None;
+                state.next_flush = None;
+            }
+            tracing::info!("Plugin watcher stopped");
+        }
     }
 
     /// Watch a path.
-    pub fn watch(&mut self, path: impl AsRef<Path>) -> Result<()> {
+    ///
+    /// While running, the path is registered with the OS watcher
+    /// immediately and must exist. With [`WatchBackend::Auto`], a path on a
+    /// network filesystem switches a watcher using OS notifications to
+    /// polling, as [`start`](Self::start) would have.
+    pub fn watch(&self, path: impl AsRef<Path>) -> Result<()> {
         let path = path.as_ref().to_path_buf();
 
-        // Add to watched paths
-        {
+        let mut slot = self.watcher.lock();
+        let fall_back = match slot.as_mut() {
+            Some(active)
+                if self.config.backend == WatchBackend::Auto
+                    && active.backend == WatchBackend::Native
+                    && on_remote_filesystem(&path) =>
+            {
+                true
+            }
+            Some(active) => {
+                self.watch_path_internal(active.watcher.as_mut(), &path)?;
+                false
+            }
+            None => false,
+        };
+
+        let added = {
             let mut paths = self.watched_paths.write();
-            if !paths.contains(&path) {
+            let added = !paths.contains(&path);
+            if added {
+                let canonical = std::fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+                self.state.write().roots.push((path.clone(), canonical));
                 paths.push(path.clone());
             }
-        }
+            added
+        };
 
-        // If running, start watching
-        if self.running.load(Ordering::Relaxed) {
-            self.watch_path_internal(&path)?;
+        if fall_back {
+            tracing::warn!(
+                "Falling back to polling for file changes: {} is on a network filesystem",
+                path.display()
+            );
+            // Registers every watched path, including the new one
+            match self.start_backend(WatchBackend::Poll {
+                interval: FALLBACK_POLL_INTERVAL,
+            }) {
+                Ok(watcher) => *slot = Some(watcher),
+                Err(e) => {
+                    if added {
+                        self.watched_paths.write().retain(|p| p != &path);
+                        self.state.write().roots.retain(|(given, _)| given != &path);
+                    }
+                    return Err(e);
+                }
+            }
         }
 
         Ok(())
     }
 
     /// Unwatch a path.
-    pub fn unwatch(&mut self, path: impl AsRef<Path>) -> Result<()> {
+    pub fn unwatch(&self, path: impl AsRef<Path>) -> Result<()> {
         let path = path.as_ref();
 
-        // Remove from watched paths
-        {
+        let was_watched = {
             let mut paths = self.watched_paths.write();
+            let before = paths.len();
             paths.retain(|p| p != path);
+            paths.len() != before
+        };
+        if was_watched {
+            self.state.write().roots.retain(|(given, _)| given != path);
         }
 
-        // If running, stop watching
-        if let Some(ref mut watcher) = self.watcher {
-            watcher
-                .unwatch(path)
-                .map_err(|e| Error::Watch(e.to_string()))?;
+        if was_watched {
+            if let Some(active) = self.watcher.lock().as_mut() {
+                active
+                    .watcher
+                    .unwatch(path)
+                    .map_err(|e| Error::Watch(e.to_string()))?;
+                tracing::debug!("Stopped watching path: {}", path.display());
+            }
         }
 
         Ok(())
@@ -262,68 +1156,192 @@ impl PluginWatcher {
         self.watched_paths.read().clone()
     }
 
-    // Internal methods
-
-    fn watch_path_internal(&self, path: &Path) -> Result<()> {
-        if let Some(ref _watcher) = self.watcher {
-            let _mode = if self.config.recursive {
-                RecursiveMode::Recursive
-            } else {
-                RecursiveMode::NonRecursive
-            };
-
-            // Note: watcher is not mutable here, so this is a simplified version
-            // In real implementation, would need interior mutability or different design
-            tracing::debug!("Watching path: {}", path.display());
+    /// Add a named group of directories with its own configuration.
+    ///
+    /// Debounce, extensions, recursion and auto-reload come from the group
+    /// for every path under one of its directories.
+    pub fn add_group(&self, name: impl Into<String>, config: WatchConfig) -> Result<()> {
+        let name = name.into();
+        let mut errors = ConfigError::new();
+        errors.nest(&format!("groups.{}", name), config.validate());
+        errors.into_result()?;
+        let mut state = self.state.write();
+        if state.groups.iter().any(|group| group.name == name) {
+            return Err(Error::Watch(format!(
+                "watch group already exists: {}",
+                name
+            )));
         }
 
+        state.groups.push(WatchGroup {
+            name,
+            roots: Vec::new(),
+            config: Arc::new(config),
+        });
         Ok(())
     }
 
-    fn handle_event(state: &Arc<RwLock<WatchState>>, config: &WatchConfig, event: Event) {
-        let watch_event = match event.kind {
-            EventKind::Create(_) => event
-                .paths
-                .first()
-                .map(|p| WatchEvent::Created { path: p.clone() }),
-            EventKind::Modify(_) => event
-                .paths
-                .first()
-                .map(|p| WatchEvent::Modified { path: p.clone() }),
-            EventKind::Remove(_) => event
-                .paths
-                .first()
-                .map(|p| WatchEvent::Removed { path: p.clone() }),
-            _ => None,
+    /// Watch a directory as part of a group.
+    pub fn watch_group(&self, group: &str, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref().to_path_buf();
+        // Added before watching so the first events already use the group
+        let added = {
+            let mut state = self.state.write();
+            let group = state
+                .groups
+                .iter_mut()
+                .find(|g| g.name == group)
+                .ok_or_else(|| Error::Watch(format!("unknown watch group: {}", group)))?;
+            let added = !group.roots.iter().any(|(given, _)| given == &path);
+            if added {
+                let canonical = std::fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+                group.roots.push((path.clone(), canonical));
+            }
+            added
         };
 
-        if let Some(watch_event) = watch_event {
-            // Check extension filter
-            if !watch_event.matches_extension(&config.extensions) {
-                return;
+        let result = self.watch(&path);
+        if result.is_err() && added {
+            if let Some(owner) = self
+                .state
+                .write()
+                .groups
+                .iter_mut()
+                .find(|g| g.name == group)
+            {
+                owner.roots.retain(|(given, _)| given != &path);
             }
+        }
+        result
+    }
 
-            // Debounce
-            let path = watch_event.path().to_path_buf();
-            {
-                let mut state = state.write();
-                let now = Instant::now();
+    /// Remove a group and stop watching its directories.
+    pub fn remove_group(&self, name: &str) -> Result<()> {
+        let roots = {
+            let mut state = self.state.write();
+            let pos = state
+                .groups
+                .iter()
+                .position(|g| g.name == name)
+                .ok_or_else(|| Error::Watch(format!("unknown watch group: {}", name)))?;
+            state.groups.remove(pos).roots
+        };
 
-                if let Some(last) = state.last_events.get(&path) {
-                    if now.duration_since(*last) < config.debounce {
-                        return;
-                    }
-                }
+        for (root, _) in roots {
+            self.unwatch(root)?;
+        }
+        Ok(())
+    }
 
-                state.last_events.insert(path, now);
+    /// Get the names of all groups.
+    pub fn groups(&self) -> Vec<String> {
+        self.state
+            .read()
+            .groups
+            .iter()
+            .map(|g| g.name.clone())
+            .collect()
+    }
 
-                // Notify handlers
-                for handler in &state.handlers {
-                    handler(watch_event.clone());
-                }
-            }
+    /// Get the name of the group owning a path.
+    pub fn group_of(&self, path: impl AsRef<Path>) -> Option<String> {
+        self.state
+            .read()
+            .group_for(path.as_ref())
+            .map(|g| g.name.clone())
+    }
+
+    /// Get event counters.
+    pub fn stats(&self) -> WatcherStats {
+        self.state.read().stats.clone()
+    }
+
+    /// Report rename sources that timed out without a destination as removals.
+    ///
+    /// A running watcher does this in the background; while stopped, expiry
+    /// otherwise only happens when the next event arrives.
+    pub fn flush_renames(&self) {
+        {
+            let mut state = self.state.write();
+            let now = Instant::now();
+
+            let events = state.expire_renames(self.config.rename_timeout, now);
+            state.dispatch_all(&self.config, events, now);
+        }
+        deliver_ready(&self.state);
+    }
+
+    // Internal methods
+
+    fn watch_path_internal(&self, watcher: &mut dyn Watcher, path: &Path) -> Result<()> {
+        let recursive = self
+            .state
+            .read()
+            .group_for(path)
+            .map_or(self.config.recursive, |group| group.config.recursive);
+        let mode = if recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+
+        watcher
+            .watch(path, mode)
+            .map_err(|e| Error::Watch(format!("{}: {}", path.display(), e)))?;
+        tracing::debug!("Watching path: {}", path.display());
+        Ok(())
+    }
+
+    fn handle_event(state: &Arc<RwLock<WatchState>>, config: &WatchConfig, event: Event) {
+        {
+            let mut state = state.write();
+            let now = Instant::now();
+            state.stats.events_received += 1;
+            state.flush_settled(now);
+
+            let mut events = state.expire_renames(config.rename_timeout, now);
+            events.extend(state.translate(event, now));
+            state.dispatch_all(config, events, now);
+            state.schedule_flush(config.rename_timeout);
         }
+        deliver_ready(state);
     }
+
+    fn flush(state: &Arc<RwLock<WatchState>>, config: &WatchConfig, now: Instant) {
+        {
+            let mut state = state.write();
+            let events = state.expire_renames(config.rename_timeout, now);
+            state.dispatch_all(config, events, now);
+            state.flush_settled(now);
+        }
+        deliver_ready(state);
+    }
+}
+
+/// Check if a path is on a filesystem where OS change notifications may
+/// not arrive, by finding its mount point in `/proc/self/mounts`.
+#[cfg(target_os = "linux")]
+fn on_remote_filesystem(path: &Path) -> bool {
+    let Ok(mounts) = std::fs::read_to_string("/proc/self/mounts") else {
+        return false;
+    };
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let mount_point = fields.nth(1)?;
+            let fs_type = fields.next()?;
+            Some((Path::new(mount_point), fs_type))
+        })
+        .filter(|(mount_point, _)| path.starts_with(mount_point))
+        .max_by_key(|(mount_point, _)| mount_point.components().count())
+        .is_some_and(|(_, fs_type)| REMOTE_FILESYSTEMS.contains(&fs_type))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn on_remote_filesystem(_path: &Path) -> bool {
+    false
 }
 
 impl std::fmt::Debug for PluginWatcher {
@@ -368,6 +1386,500 @@ mod tests {
         assert!(!event.matches_extension(&["rs".to_string()]));
     }
 
+    #[test]
+    fn test_event_filter() {
+        let modified = WatchEvent::Modified {
+            path: PathBuf::from("/plugins/dev/a.fsx"),
+        };
+        let removed = WatchEvent::Removed {
+            path: PathBuf::from("/other/b.toml"),
+        };
+
+        assert!(EventFilter::new().matches(&modified));
+
+        let filter = EventFilter {
+            extensions: vec!["fsx".to_string()],
+            roots: vec![PathBuf::from("/plugins")],
+            kinds: vec![WatchEventKind::Modified],
+            auto_reload_only: false,
+        };
+        assert!(filter.matches(&modified));
+        assert!(!filter.matches(&removed));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_event_stream() {
+        async fn next<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+            std::future::poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+        }
+
+        let watcher = PluginWatcher::new(WatchConfig::new().with_debounce(Duration::ZERO)).unwrap();
+        let mut all = watcher.events();
+        let mut created = watcher
+            .subscribe()
+            .events([WatchEventKind::Created])
+            .stream();
+
+        let config = watcher.config().clone();
+        for kind in [
+            EventKind::Modify(ModifyKind::Any),
+            EventKind::Create(notify::event::CreateKind::File),
+        ] {
+            let event = Event::new(kind).add_path(PathBuf::from("a.fsx"));
+            PluginWatcher::handle_event(&watcher.state, &config, event);
+        }
+
+        assert_eq!(
+            next(&mut all).await.unwrap().kind(),
+            WatchEventKind::Modified
+        );
+        assert_eq!(
+            next(&mut all).await.unwrap().kind(),
+            WatchEventKind::Created
+        );
+        assert_eq!(
+            next(&mut created).await.unwrap().kind(),
+            WatchEventKind::Created
+        );
+        assert_eq!(watcher.stats().events_delivered, 2);
+
+        drop(all);
+        let event = Event::new(EventKind::Modify(ModifyKind::Any)).add_path(PathBuf::from("b.fsx"));
+        PluginWatcher::handle_event(&watcher.state, &config, event);
+        assert_eq!(watcher.state.read().streams.len(), 1);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_event_stream_lags() {
+        async fn next<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+            std::future::poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+        }
+
+        let config = WatchConfig::new()
+            .with_debounce(Duration::ZERO)
+            .with_stream_capacity(2);
+        let watcher = PluginWatcher::new(config).unwrap();
+        let mut events = watcher.events();
+
+        let config = watcher.config().clone();
+        for name in ["a.fsx", "b.fsx", "c.fsx"] {
+            let event =
+                Event::new(EventKind::Modify(ModifyKind::Any)).add_path(PathBuf::from(name));
+            PluginWatcher::handle_event(&watcher.state, &config, event);
+        }
+
+        // The unpolled stream keeps the oldest events and drops the rest
+        let stats = watcher.stats();
+        assert_eq!(stats.events_dropped, 1);
+        assert_eq!(stats.events_delivered, 2);
+        assert_eq!(next(&mut events).await.unwrap().path(), Path::new("a.fsx"));
+        assert_eq!(next(&mut events).await.unwrap().path(), Path::new("b.fsx"));
+        assert_eq!(watcher.state.read().streams.len(), 1);
+    }
+
+    #[test]
+    fn test_subscription_delivery() {
+        use std::sync::atomic::AtomicUsize;
+
+        let watcher = PluginWatcher::default_config().unwrap();
+        let all = Arc::new(AtomicUsize::new(0));
+        let filtered = Arc::new(AtomicUsize::new(0));
+
+        let counter = all.clone();
+        watcher.on_change(move |_| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        });
+        let counter = filtered.clone();
+        watcher
+            .subscribe()
+            .extensions(["fsx"])
+            .events([WatchEventKind::Created])
+            .on_change(move |_| {
+                counter.fetch_add(1, Ordering::Relaxed);
+            });
+
+        let config = WatchConfig::new().with_debounce(Duration::ZERO);
+        for (kind, path) in [
+            (EventKind::Create(notify::event::CreateKind::File), "a.fsx"),
+            (EventKind::Create(notify::event::CreateKind::File), "b.toml"),
+            (EventKind::Remove(notify::event::RemoveKind::File), "c.fsx"),
+        ] {
+            let event = Event::new(kind).add_path(PathBuf::from(path));
+            PluginWatcher::handle_event(&watcher.state, &config, event);
+        }
+
+        assert_eq!(all.load(Ordering::Relaxed), 3);
+        assert_eq!(filtered.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_handlers_may_call_the_watcher() {
+        let watcher = Arc::new(PluginWatcher::default_config().unwrap());
+        let (tx, rx) = std::sync::mpsc::channel();
+        let weak = Arc::downgrade(&watcher);
+        watcher.on_change(move |_| {
+            let Some(watcher) = weak.upgrade() else {
+                return;
+            };
+            watcher.on_change(|_| {});
+            let _ = tx.send(watcher.stats().events_received);
+        });
+
+        let config = WatchConfig::new().with_debounce(Duration::ZERO);
+        let event = Event::new(EventKind::Modify(ModifyKind::Any)).add_path(PathBuf::from("a.fsx"));
+        PluginWatcher::handle_event(&watcher.state, &config, event);
+        assert_eq!(rx.try_recv().unwrap(), 1);
+        assert_eq!(watcher.state.read().handlers.len(), 2);
+        assert_eq!(watcher.stats().events_delivered, 1);
+    }
+
+    #[test]
+    fn test_watcher_stats() {
+        let watcher = PluginWatcher::default_config().unwrap();
+        watcher.subscribe().under("/plugins").on_change(|_| {});
+
+        let config = WatchConfig::new();
+        for path in [
+            "/plugins/a.fsx",
+            "/plugins/a.fsx",
+            "/plugins/notes.txt",
+            "/other/b.fsx",
+        ] {
+            let event =
+                Event::new(EventKind::Modify(ModifyKind::Any)).add_path(PathBuf::from(path));
+            PluginWatcher::handle_event(&watcher.state, &config, event);
+        }
+
+        let stats = watcher.stats();
+        assert_eq!(stats.events_received, 4);
+        assert_eq!(stats.events_delivered, 0);
+        assert_eq!(stats.events_debounced, 1);
+        assert_eq!(stats.events_filtered, 1);
+        assert_eq!(stats.debounce_entries, 2);
+
+        PluginWatcher::flush(
+            &watcher.state,
+            &config,
+            Instant::now() + Duration::from_secs(1),
+        );
+        let stats = watcher.stats();
+        assert_eq!(stats.events_delivered, 1);
+        assert_eq!(stats.events_filtered, 2);
+        assert_eq!(stats.reloads_triggered, 1);
+        assert_eq!(stats.debounce_entries, 0);
+    }
+
+    #[test]
+    fn test_config_validation() {
+        assert!(WatchConfig::new().validate().is_ok());
+        let config = WatchConfig::new()
+            .with_extensions(vec![".fsx".to_string(), String::new()])
+            .with_max_debounce_entries(0);
+        assert!(matches!(
+            PluginWatcher::new(config.clone()),
+            Err(Error::Config(e))
+                if e.paths() == ["extensions[0]", "extensions[1]", "max_debounce_entries"]
+        ));
+
+        let watcher = PluginWatcher::default_config().unwrap();
+        assert!(matches!(
+            watcher.add_group("ui", config),
+            Err(Error::Config(e)) if e.issues[0].path == "groups.ui.extensions[0]"
+        ));
+
+        let config = WatchConfig::new().with_backend(WatchBackend::Poll {
+            interval: Duration::ZERO,
+        });
+        assert!(matches!(
+            config.validate(),
+            Err(Error::Config(e)) if e.paths() == ["backend.interval"]
+        ));
+
+        let config = WatchConfig::new()
+            .with_include_patterns(&["src/**"])
+            .with_exclude_patterns(&["*.swp", "/"]);
+        assert!(matches!(
+            config.validate(),
+            Err(Error::Config(e)) if e.paths() == ["exclude[1]"]
+        ));
+    }
+
+    #[test]
+    fn test_glob_filters() {
+        let watcher = PluginWatcher::default_config().unwrap();
+        watcher.on_change(|_| {});
+
+        let config = WatchConfig::new().with_debounce(Duration::ZERO);
+        for path in [
+            "/work/plugins/a.fsx",
+            "/work/target/debug/a.fsx",
+            "/work/.git/plugin.toml",
+            "/work/plugins/.#a.fsx",
+        ] {
+            let event =
+                Event::new(EventKind::Modify(ModifyKind::Any)).add_path(PathBuf::from(path));
+            PluginWatcher::handle_event(&watcher.state, &config, event);
+        }
+        let stats = watcher.stats();
+        assert_eq!(stats.events_delivered, 1);
+        assert_eq!(stats.events_filtered, 3);
+
+        // Only the part below the watched directory is matched
+        let watcher = PluginWatcher::default_config().unwrap();
+        watcher.on_change(|_| {});
+        watcher.watch("/srv/target/plugins").unwrap();
+        for path in [
+            "/srv/target/plugins/a.fsx",
+            "/srv/target/plugins/target/b.fsx",
+        ] {
+            let event =
+                Event::new(EventKind::Modify(ModifyKind::Any)).add_path(PathBuf::from(path));
+            PluginWatcher::handle_event(&watcher.state, &config, event);
+        }
+        let stats = watcher.stats();
+        assert_eq!(stats.events_delivered, 1);
+        assert_eq!(stats.events_filtered, 1);
+
+        let config = WatchConfig::new()
+            .with_include_patterns(&["plugins/"])
+            .with_exclude_patterns(&["*.test.fsx"]);
+        assert!(config.matches_patterns(Path::new("/work/plugins/a.fsx")));
+        assert!(!config.matches_patterns(Path::new("/work/plugins/a.test.fsx")));
+        assert!(!config.matches_patterns(Path::new("/work/scripts/a.fsx")));
+        assert!(config.matches_patterns(Path::new("/work/plugins/target/a.fsx")));
+    }
+
+    #[test]
+    fn test_debounce_map_bounded() {
+        let (watcher, rx) = recording_watcher();
+        let config = WatchConfig::new()
+            .with_debounce(Duration::from_secs(60))
+            .with_max_debounce_entries(2);
+        let now = Instant::now();
+        for path in ["a.fsx", "b.fsx", "c.fsx"] {
+            let event = WatchEvent::Modified {
+                path: PathBuf::from(path),
+            };
+            watcher.state.write().dispatch(&config, event, now);
+            deliver_ready(&watcher.state);
+        }
+
+        // The oldest change is delivered early instead of being dropped
+        assert_eq!(rx.try_recv().unwrap().path(), Path::new("a.fsx"));
+        assert!(rx.try_recv().is_err());
+        assert_eq!(watcher.stats().debounce_entries, 2);
+
+        watcher
+            .state
+            .write()
+            .flush_settled(now + Duration::from_secs(60));
+        deliver_ready(&watcher.state);
+        let rest: Vec<_> = rx.try_iter().map(|e| e.path().to_path_buf()).collect();
+        assert_eq!(rest.len(), 2);
+        assert_eq!(watcher.stats().debounce_entries, 0);
+    }
+
+    #[test]
+    fn test_trailing_delivery() {
+        let (watcher, rx) = recording_watcher();
+        let config = WatchConfig::new().with_debounce(Duration::from_millis(100));
+        let t0 = Instant::now();
+        let at = |ms| t0 + Duration::from_millis(ms);
+        let path = |name: &str| PathBuf::from(name);
+
+        let saves = [
+            // Editors that save by replacing the file
+            (
+                0,
+                WatchEvent::Removed {
+                    path: path("a.fsx"),
+                },
+            ),
+            (
+                10,
+                WatchEvent::Created {
+                    path: path("a.fsx"),
+                },
+            ),
+            (
+                20,
+                WatchEvent::Modified {
+                    path: path("a.fsx"),
+                },
+            ),
+            // A new file written in several chunks
+            (
+                0,
+                WatchEvent::Created {
+                    path: path("b.fsx"),
+                },
+            ),
+            (
+                30,
+                WatchEvent::Modified {
+                    path: path("b.fsx"),
+                },
+            ),
+            (
+                60,
+                WatchEvent::Modified {
+                    path: path("b.fsx"),
+                },
+            ),
+            (
+                90,
+                WatchEvent::Modified {
+                    path: path("b.fsx"),
+                },
+            ),
+            // A scratch file that came and went
+            (
+                0,
+                WatchEvent::Created {
+                    path: path("c.fsx"),
+                },
+            ),
+            (
+                5,
+                WatchEvent::Removed {
+                    path: path("c.fsx"),
+                },
+            ),
+        ];
+        for (ms, event) in saves {
+            watcher.state.write().dispatch(&config, event, at(ms));
+        }
+        let flush_at = |ms| {
+            watcher.state.write().flush_settled(at(ms));
+            deliver_ready(&watcher.state);
+        };
+
+        // Nothing is delivered while a path is still changing
+        flush_at(110);
+        assert!(rx.try_recv().is_err());
+
+        flush_at(120);
+        assert!(matches!(
+            rx.try_recv().unwrap(),
+            WatchEvent::Modified { path } if path == Path::new("a.fsx")
+        ));
+        assert!(rx.try_recv().is_err());
+
+        flush_at(190);
+        assert!(matches!(
+            rx.try_recv().unwrap(),
+            WatchEvent::Created { path } if path == Path::new("b.fsx")
+        ));
+        assert!(rx.try_recv().is_err());
+
+        let stats = watcher.stats();
+        assert_eq!(stats.events_debounced, 6);
+        assert_eq!(stats.events_delivered, 2);
+        assert_eq!(stats.debounce_entries, 0);
+    }
+
+    #[test]
+    fn test_watch_groups() {
+        use std::sync::atomic::AtomicUsize;
+
+        let watcher = PluginWatcher::default_config().unwrap();
+        watcher
+            .add_group("dev", WatchConfig::new().with_debounce(Duration::ZERO))
+            .unwrap();
+        watcher
+            .add_group(
+                "prod",
+                WatchConfig::new().with_extensions(vec!["toml".to_string()]),
+            )
+            .unwrap();
+        assert!(watcher.add_group("dev", WatchConfig::new()).is_err());
+
+        watcher.watch_group("dev", "/plugins/dev").unwrap();
+        watcher.watch_group("prod", "/plugins").unwrap();
+        assert!(watcher.watch_group("missing", "/tmp").is_err());
+        assert_eq!(
+            watcher.group_of("/plugins/dev/a.fsx").as_deref(),
+            Some("dev")
+        );
+        assert_eq!(watcher.group_of("/plugins/b.fsx").as_deref(), Some("prod"));
+        assert_eq!(watcher.group_of("/elsewhere"), None);
+
+        let delivered = Arc::new(AtomicUsize::new(0));
+        let counter = delivered.clone();
+        watcher.on_change(move |_| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        });
+
+        let config = watcher.config().clone();
+        for path in [
+            "/plugins/dev/a.fsx",
+            "/plugins/dev/a.fsx",
+            "/plugins/b.fsx",
+            "/plugins/b/plugin.toml",
+        ] {
+            let event =
+                Event::new(EventKind::Modify(ModifyKind::Any)).add_path(PathBuf::from(path));
+            PluginWatcher::handle_event(&watcher.state, &config, event);
+        }
+
+        // dev has no debounce; prod only watches manifests and debounces them
+        assert_eq!(delivered.load(Ordering::Relaxed), 2);
+        PluginWatcher::flush(
+            &watcher.state,
+            &config,
+            Instant::now() + Duration::from_secs(1),
+        );
+        assert_eq!(delivered.load(Ordering::Relaxed), 3);
+
+        watcher.remove_group("dev").unwrap();
+        assert_eq!(watcher.groups(), vec!["prod"]);
+        assert!(!watcher
+            .watched_paths()
+            .contains(&PathBuf::from("/plugins/dev")));
+    }
+
+    #[test]
+    fn test_rename_pairing() {
+        use notify::event::RenameMode;
+
+        // flush_renames uses the watcher's own config
+        let watcher = PluginWatcher::new(
+            WatchConfig::new()
+                .with_debounce(Duration::ZERO)
+                .with_rename_timeout(Duration::ZERO),
+        )
+        .unwrap();
+        let events = Arc::new(RwLock::new(Vec::new()));
+        let received = events.clone();
+        watcher.on_change(move |event| received.write().push(event));
+
+        let rename = |mode, path: &str| {
+            Event::new(EventKind::Modify(ModifyKind::Name(mode))).add_path(PathBuf::from(path))
+        };
+
+        // Paired halves become a single rename
+        let config = WatchConfig::new().with_debounce(Duration::ZERO);
+        PluginWatcher::handle_event(&watcher.state, &config, rename(RenameMode::From, "a.fsx"));
+        PluginWatcher::handle_event(&watcher.state, &config, rename(RenameMode::To, "b.fsx"));
+
+        // An orphan destination is a creation
+        PluginWatcher::handle_event(&watcher.state, &config, rename(RenameMode::To, "c.fsx"));
+
+        // An orphan source expires into a removal
+        PluginWatcher::handle_event(&watcher.state, &config, rename(RenameMode::From, "d.fsx"));
+        watcher.flush_renames();
+        let events = events.read();
+        assert_eq!(events.len(), 3);
+        assert!(matches!(
+            &events[0],
+            WatchEvent::Renamed { from, to } if from == Path::new("a.fsx") && to == Path::new("b.fsx")
+        ));
+        assert!(matches!(&events[1], WatchEvent::Created { path } if path == Path::new("c.fsx")));
+        assert!(matches!(&events[2], WatchEvent::Removed { .. }));
+    }
+
     #[test]
     fn test_watcher_creation() {
         let watcher = PluginWatcher::default_config().unwrap();
@@ -376,7 +1888,7 @@ mod tests {
 
     #[test]
     fn test_watch_path() {
-        let mut watcher = PluginWatcher::default_config().unwrap();
+        let watcher = PluginWatcher::default_config().unwrap();
         watcher.watch("/tmp/plugins").unwrap();
 
         let paths = watcher.watched_paths();
@@ -385,11 +1897,178 @@ mod tests {
 
     #[test]
     fn test_unwatch_path() {
-        let mut watcher = PluginWatcher::default_config().unwrap();
+        let watcher = PluginWatcher::default_config().unwrap();
         watcher.watch("/tmp/plugins").unwrap();
         watcher.unwatch("/tmp/plugins").unwrap();
 
         let paths = watcher.watched_paths();
         assert!(!paths.contains(&PathBuf::from("/tmp/plugins")));
     }
+
+    fn recording_watcher() -> (PluginWatcher, std::sync::mpsc::Receiver<WatchEvent>) {
+        let watcher = PluginWatcher::new(WatchConfig::new().with_debounce(Duration::ZERO)).unwrap();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let tx = parking_lot::Mutex::new(tx);
+        watcher.on_change(move |event| {
+            let _ = tx.lock().send(event);
+        });
+        (watcher, rx)
+    }
+
+    #[test]
+    fn test_watch_delivers_file_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let (watcher, rx) = recording_watcher();
+
+        watcher.start().unwrap();
+        watcher.watch(dir.path()).unwrap();
+        std::fs::write(dir.path().join("plugin.fsx"), "let x = 1").unwrap();
+
+        let event = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(event.path().file_name().unwrap(), "plugin.fsx");
+        assert!(watcher.stats().events_delivered >= 1);
+
+        // Paths must exist once they are registered with the OS
+        assert!(watcher.watch(dir.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn test_watch_group_roots() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("dev")).unwrap();
+        let watcher = PluginWatcher::default_config().unwrap();
+        watcher.add_group("dev", WatchConfig::new()).unwrap();
+        watcher.start().unwrap();
+
+        // Events carry canonical paths, which a root given with `..` only
+        // matches once canonicalized
+        watcher
+            .watch_group("dev", dir.path().join("dev/../dev"))
+            .unwrap();
+        let canonical = dir.path().join("dev").canonicalize().unwrap();
+        assert_eq!(
+            watcher.group_of(canonical.join("a.fsx")).as_deref(),
+            Some("dev")
+        );
+
+        // A root that cannot be watched is not kept
+        let missing = dir.path().join("missing");
+        assert!(watcher.watch_group("dev", &missing).is_err());
+        assert_eq!(watcher.group_of(missing.join("a.fsx")), None);
+    }
+
+    #[test]
+    fn test_burst_delivered_once() {
+        let dir = tempfile::tempdir().unwrap();
+        // A window far longer than the burst, so a slow machine cannot
+        // split it
+        let watcher =
+            PluginWatcher::new(WatchConfig::new().with_debounce(Duration::from_secs(1))).unwrap();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let tx = parking_lot::Mutex::new(tx);
+        watcher.on_change(move |event| {
+            let _ = tx.lock().send(event);
+        });
+        watcher.watch(dir.path()).unwrap();
+        watcher.start().unwrap();
+
+        let file = dir.path().join("plugin.fsx");
+        for i in 0..5 {
+            std::fs::write(&file, format!("let x = {}", i)).unwrap();
+        }
+
+        // The final save arrives without any later event to flush it
+        let event = rx.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(event.path().file_name().unwrap(), "plugin.fsx");
+        assert!(rx.recv_timeout(Duration::from_millis(1500)).is_err());
+    }
+
+    #[test]
+    fn test_flusher_sleeps_while_idle() {
+        let dir = tempfile::tempdir().unwrap();
+        let watcher = PluginWatcher::default_config().unwrap();
+        watcher.watch(dir.path()).unwrap();
+        watcher.start().unwrap();
+
+        let runs = || {
+            watcher
+                .tasks
+                .health()
+                .into_iter()
+                .find(|health| health.name == watcher.flush_task)
+                .map_or(0, |health| health.runs)
+        };
+        std::thread::sleep(Duration::from_millis(50));
+        let idle = runs();
+        std::thread::sleep(Duration::from_millis(200));
+        assert_eq!(runs(), idle);
+
+        // A pending change wakes it, and it runs again once it settles
+        let config = watcher.config().clone();
+        let event =
+            Event::new(EventKind::Modify(ModifyKind::Any)).add_path(dir.path().join("a.fsx"));
+        PluginWatcher::handle_event(&watcher.state, &config, event);
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while watcher.stats().debounce_entries > 0 && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(watcher.stats().debounce_entries, 0);
+        assert!(runs() > idle);
+        watcher.stop();
+    }
+
+    #[test]
+    fn test_unwatch_and_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let (watcher, rx) = recording_watcher();
+
+        // Paths registered before start are watched once it runs
+        watcher.watch(dir.path()).unwrap();
+        watcher.start().unwrap();
+        std::fs::write(dir.path().join("a.fsx"), "let a = 1").unwrap();
+        assert!(rx.recv_timeout(Duration::from_secs(5)).is_ok());
+
+        watcher.unwatch(dir.path()).unwrap();
+        while rx.recv_timeout(Duration::from_millis(200)).is_ok() {}
+        std::fs::write(dir.path().join("b.fsx"), "let b = 1").unwrap();
+        assert!(rx.recv_timeout(Duration::from_millis(500)).is_err());
+
+        watcher.watch(dir.path()).unwrap();
+        watcher.stop();
+        watcher.start().unwrap();
+        std::fs::write(dir.path().join("c.fsx"), "let c = 1").unwrap();
+        assert!(rx.recv_timeout(Duration::from_secs(5)).is_ok());
+    }
+
+    #[test]
+    fn test_poll_backend() {
+        let dir = tempfile::tempdir().unwrap();
+        let watcher = PluginWatcher::new(
+            WatchConfig::new()
+                .with_debounce(Duration::ZERO)
+                .with_backend(WatchBackend::Poll {
+                    interval: Duration::from_millis(50),
+                }),
+        )
+        .unwrap();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let tx = parking_lot::Mutex::new(tx);
+        watcher.on_change(move |event| {
+            let _ = tx.lock().send(event);
+        });
+        assert_eq!(watcher.active_backend(), None);
+
+        watcher.watch(dir.path()).unwrap();
+        watcher.start().unwrap();
+        assert_eq!(
+            watcher.active_backend(),
+            Some(WatchBackend::Poll {
+                interval: Duration::from_millis(50)
+            })
+        );
+        std::fs::write(dir.path().join("plugin.fsx"), "let x = 1").unwrap();
+
+        let event = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(event.path().file_name().unwrap(), "plugin.fsx");
+    }
 }
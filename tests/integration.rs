@@ -39,7 +39,7 @@ fn test_manifest_creation() {
     assert_eq!(manifest.version, "1.0.0");
     assert_eq!(manifest.capabilities.len(), 2);
     assert!(manifest.requires_capability("fs:read"));
-    assert!(manifest.exports.contains(&"init".to_string()));
+    assert!(manifest.has_export("init"));
 }
 
 #[test]
@@ -284,7 +284,7 @@ mod watch_tests {
 
     #[test]
     fn test_watch_paths() {
-        let mut watcher = PluginWatcher::default_config().unwrap();
+        let watcher = PluginWatcher::default_config().unwrap();
 
         watcher.watch("/tmp/test1").unwrap();
         watcher.watch("/tmp/test2").unwrap();